@@ -0,0 +1,65 @@
+//! Compares `GeoIpReader::contains`, which only walks the tree, with `find_record`, which also
+//! reads and decodes the record.
+//!
+//! Run with `cargo bench --bench contains`.
+
+use ipcap::geo_ip_reader::GeoIpReader;
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+const LOOKUPS: u32 = 200_000;
+
+/// Spreads `LOOKUPS` addresses over the IPv4 space.
+fn addresses() -> Vec<IpAddr> {
+    let step = u32::MAX / LOOKUPS;
+    (0..LOOKUPS)
+        .map(|i| IpAddr::V4(Ipv4Addr::from(i * step)))
+        .collect()
+}
+
+fn bench(name: &str, addrs: &[IpAddr], mut lookup: impl FnMut(IpAddr)) -> Duration {
+    let start = Instant::now();
+    for addr in addrs {
+        lookup(black_box(*addr));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<24} {:>10.2?} total, {:>8.0?} per lookup",
+        name,
+        elapsed,
+        elapsed / addrs.len().max(1) as u32
+    );
+    elapsed
+}
+
+fn main() {
+    // Read the database in memory so that disk reads do not blur the comparison
+    let path = GeoIpReader::<File>::database_path("v4").expect("no v4 database");
+    let database = fs::read(path).expect("cannot read the v4 database");
+    let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+    let all = addresses();
+    // The addresses with a record, for which `find_record` does more than `contains`
+    let covered: Vec<IpAddr> = all
+        .iter()
+        .copied()
+        .filter(|addr| geo_ip.contains_addr(*addr).unwrap())
+        .collect();
+
+    for (name, addrs) in [("all addresses", &all), ("covered addresses", &covered)] {
+        println!("{} ({}):", name, addrs.len());
+        let contains = bench("  contains", addrs, |addr| {
+            black_box(geo_ip.contains_addr(addr).unwrap());
+        });
+        let find_record = bench("  find_record", addrs, |addr| {
+            black_box(geo_ip.find_record(&addr.to_string()).unwrap());
+        });
+        println!(
+            "  contains is {:.1}x faster",
+            find_record.as_secs_f64() / contains.as_secs_f64()
+        );
+    }
+}
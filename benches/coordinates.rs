@@ -0,0 +1,60 @@
+//! Compares `GeoIpReader::get_coordinates`, which only decodes the latitude and longitude, with
+//! `find_record`, which also decodes the strings and resolves the time zone.
+//!
+//! Run with `cargo bench --bench coordinates`.
+
+use ipcap::geo_ip_reader::GeoIpReader;
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+const LOOKUPS: u32 = 200_000;
+
+/// Spreads `LOOKUPS` addresses over the IPv4 space.
+fn addresses() -> Vec<String> {
+    let step = u32::MAX / LOOKUPS;
+    (0..LOOKUPS)
+        .map(|i| std::net::Ipv4Addr::from(i * step).to_string())
+        .collect()
+}
+
+fn bench(name: &str, ips: &[String], mut lookup: impl FnMut(&str)) -> Duration {
+    let start = Instant::now();
+    for ip in ips {
+        lookup(black_box(ip));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<24} {:>10.2?} total, {:>8.0?} per lookup",
+        name,
+        elapsed,
+        elapsed / ips.len().max(1) as u32
+    );
+    elapsed
+}
+
+fn main() {
+    // Read the database in memory so that disk reads do not blur the comparison
+    let path = GeoIpReader::<File>::database_path("v4").expect("no v4 database");
+    let database = fs::read(path).expect("cannot read the v4 database");
+    let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+    // The addresses with a record, for which both methods decode something
+    let ips: Vec<String> = addresses()
+        .into_iter()
+        .filter(|ip| geo_ip.contains(ip).unwrap())
+        .collect();
+
+    println!("covered addresses ({}):", ips.len());
+    let coordinates = bench("  get_coordinates", &ips, |ip| {
+        black_box(geo_ip.get_coordinates(ip).unwrap());
+    });
+    let find_record = bench("  find_record", &ips, |ip| {
+        black_box(geo_ip.find_record(ip).unwrap());
+    });
+    println!(
+        "  get_coordinates is {:.1}x faster",
+        find_record.as_secs_f64() / coordinates.as_secs_f64()
+    );
+}
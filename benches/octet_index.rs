@@ -0,0 +1,56 @@
+//! Compares the tree walks of `GeoIpReader::get_country` over a database in memory with and
+//! without the index of the first octets of IPv4 addresses.
+//!
+//! Run with `cargo bench --bench octet_index`.
+
+use ipcap::geo_ip_reader::{GeoIpReader, GeoIpReaderBuilder};
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+const LOOKUPS: u32 = 1_000_000;
+
+/// Spreads `LOOKUPS` IP numbers over the IPv4 space.
+fn ip_numbers() -> Vec<u128> {
+    let step = u32::MAX / LOOKUPS;
+    (0..LOOKUPS).map(|i| u128::from(i * step)).collect()
+}
+
+fn bench(name: &str, ip_numbers: &[u128], mut lookup: impl FnMut(u128)) -> Duration {
+    let start = Instant::now();
+    for ip_number in ip_numbers {
+        lookup(black_box(*ip_number));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<24} {:>10.2?} total, {:>8.0?} per lookup",
+        name,
+        elapsed,
+        elapsed / ip_numbers.len().max(1) as u32
+    );
+    elapsed
+}
+
+fn main() {
+    let path = GeoIpReader::<File>::database_path("v4").expect("no v4 database");
+    let database = fs::read(path).expect("cannot read the v4 database");
+    let mut indexed = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+    let mut unindexed = GeoIpReaderBuilder::new()
+        .octet_index(false)
+        .build(Cursor::new(database))
+        .unwrap();
+
+    let ip_numbers = ip_numbers();
+    println!("addresses ({}):", ip_numbers.len());
+    let walk = bench("  without index", &ip_numbers, |ip_number| {
+        black_box(unindexed.get_country(ip_number).unwrap());
+    });
+    let indexed_walk = bench("  with octet index", &ip_numbers, |ip_number| {
+        black_box(indexed.get_country(ip_number).unwrap());
+    });
+    println!(
+        "  the octet index is {:.2}x faster",
+        walk.as_secs_f64() / indexed_walk.as_secs_f64()
+    );
+}
@@ -0,0 +1,148 @@
+//! Measures the lookups whose speed the crate defends: a single v4 lookup, a single v6 lookup,
+//! a batch of 10k lookups, with and without interned strings, and a `contains` check, over the
+//! databases in memory.
+//!
+//! Run with `cargo bench --bench regression`. Save the results as a named baseline with
+//! `cargo bench --bench regression -- --save-baseline <NAME>`, then compare a later run to it
+//! with `cargo bench --bench regression -- --baseline <NAME>`. The baselines are kept under
+//! `target/bench-baselines`.
+
+use ipcap::geo_ip_reader::GeoIpReader;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200_000;
+const BATCH: u32 = 10_000;
+const BATCHES: u32 = 20;
+
+/// Spreads `count` addresses over the IPv4 space.
+fn addresses(count: u32) -> Vec<IpAddr> {
+    let step = u32::MAX / count;
+    (0..count)
+        .map(|i| IpAddr::V4(Ipv4Addr::from(i * step)))
+        .collect()
+}
+
+/// Returns the mean duration of `iterations` runs of `f`.
+fn bench(iterations: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+fn reader(type_: &str) -> GeoIpReader<Cursor<Vec<u8>>> {
+    let path = GeoIpReader::<File>::database_path(type_).expect("no database");
+    let database = fs::read(path).expect("cannot read the database");
+    GeoIpReader::from_reader(Cursor::new(database)).unwrap()
+}
+
+fn baseline_path(name: &str) -> PathBuf {
+    [
+        env!("CARGO_MANIFEST_DIR"),
+        "target",
+        "bench-baselines",
+        name,
+    ]
+    .iter()
+    .collect()
+}
+
+/// Reads a baseline saved by `--save-baseline`, one `name<TAB>nanoseconds` line per case.
+fn read_baseline(name: &str) -> HashMap<String, u128> {
+    let content = fs::read_to_string(baseline_path(name))
+        .unwrap_or_else(|_| panic!("no baseline named {}", name));
+    content
+        .lines()
+        .filter_map(|line| {
+            let (case, nanos) = line.split_once('\t')?;
+            Some((case.to_string(), nanos.parse().ok()?))
+        })
+        .collect()
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to the binary, which is ignored
+    let args: Vec<String> = std::env::args().collect();
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+            .cloned()
+    };
+    let save = option("--save-baseline");
+    let baseline = option("--baseline").map(|name| read_baseline(&name));
+
+    let mut v4 = reader("v4");
+    let mut v6 = reader("v6");
+    // Build the index of the first octets before measuring
+    v4.find_record("8.8.8.8").unwrap();
+    let batch = addresses(BATCH);
+
+    let results = [
+        (
+            "single v4 lookup",
+            bench(ITERATIONS, || {
+                black_box(v4.find_record(black_box("108.95.4.105")).unwrap());
+            }),
+        ),
+        (
+            "single v6 lookup",
+            bench(ITERATIONS, || {
+                black_box(
+                    v6.find_record(black_box("2a08:1450:300f:900::1003"))
+                        .unwrap(),
+                );
+            }),
+        ),
+        (
+            "10k batch",
+            bench(BATCHES, || {
+                black_box(v4.get_records(batch.iter().copied()).partition());
+            }),
+        ),
+        (
+            "10k batch interned",
+            bench(BATCHES, || {
+                black_box(
+                    v4.get_records_interned(batch.iter().copied())
+                        .filter_map(|(_, record)| record.ok())
+                        .count(),
+                );
+            }),
+        ),
+        (
+            "contains",
+            bench(ITERATIONS, || {
+                black_box(v4.contains(black_box("108.95.4.105")).unwrap());
+            }),
+        ),
+    ];
+
+    for (case, duration) in &results {
+        match baseline.as_ref().and_then(|baseline| baseline.get(*case)) {
+            Some(&before) => {
+                let change = (duration.as_nanos() as f64 / before as f64 - 1.0) * 100.0;
+                println!("{:<24} {:>10.2?} ({:+.1}%)", case, duration, change);
+            }
+            None => println!("{:<24} {:>10.2?}", case, duration),
+        }
+    }
+
+    if let Some(name) = save {
+        let path = baseline_path(&name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let content: String = results
+            .iter()
+            .map(|(case, duration)| format!("{}\t{}\n", case, duration.as_nanos()))
+            .collect();
+        fs::write(&path, content).unwrap();
+        println!("saved baseline {} to {}", name, path.display());
+    }
+}
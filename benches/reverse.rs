@@ -0,0 +1,64 @@
+//! Measures the one-time build of a `CityIndex` over the v4 database and the latency of its
+//! queries.
+//!
+//! Run with `cargo bench --bench reverse`.
+
+use ipcap::geo_ip_reader::GeoIpReader;
+use ipcap::reverse::CityIndex;
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+const QUERIES: u32 = 100_000;
+
+/// Spreads `QUERIES` coordinates over the globe.
+fn coordinates() -> Vec<(f64, f64)> {
+    (0..QUERIES)
+        .map(|i| {
+            let i = f64::from(i);
+            (
+                (i * 0.618_034 % 1.0) * 180.0 - 90.0,
+                (i * 0.414_214 % 1.0) * 360.0 - 180.0,
+            )
+        })
+        .collect()
+}
+
+fn bench(name: &str, coordinates: &[(f64, f64)], mut query: impl FnMut(f64, f64)) -> Duration {
+    let start = Instant::now();
+    for (lat, lon) in coordinates {
+        query(black_box(*lat), black_box(*lon));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<24} {:>10.2?} total, {:>8.0?} per query",
+        name,
+        elapsed,
+        elapsed / coordinates.len().max(1) as u32
+    );
+    elapsed
+}
+
+fn main() {
+    // Read the database in memory so that disk reads do not blur the build time
+    let path = GeoIpReader::<File>::database_path("v4").expect("no v4 database");
+    let database = fs::read(path).expect("cannot read the v4 database");
+    let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+    let start = Instant::now();
+    let index = CityIndex::build(&mut geo_ip).unwrap();
+    println!(
+        "build ({} cities)        {:>10.2?}",
+        index.len(),
+        start.elapsed()
+    );
+
+    let coordinates = coordinates();
+    bench("nearest", &coordinates, |lat, lon| {
+        black_box(index.nearest(lat, lon));
+    });
+    bench("within_radius 50 km", &coordinates, |lat, lon| {
+        black_box(index.within_radius(lat, lon, 50.0));
+    });
+}
@@ -7,7 +7,13 @@ const NAMES_DATA: &str = include_str!("../countries-names.txt");
 const CODES_2_DATA: &str = include_str!("../countries-two.txt");
 const CODES_3_DATA: &str = include_str!("../countries-three.txt");
 const COUNTRIES_TO_CONTINENTS: &str = include_str!("../countries-to-continents.txt");
+const COORDINATES: &str = include_str!("../countries-coordinates.txt");
+const METADATA: &str = include_str!("../countries-metadata.txt");
 const DMA: &str = include_str!("../dma.txt");
+const REGIONS_FIPS_TO_ISO: &str = include_str!("../regions-fips-to-iso.txt");
+const NAMES_LOCALIZED: &str = include_str!("../countries-names-localized.txt");
+const COUNTRY_PREFIXES: &str = include_str!("../country-prefixes.csv");
+const STATUSES: &str = include_str!("../countries-status.txt");
 
 trait PushMut<T> {
     fn add(self, item: T) -> Self;
@@ -56,19 +62,6 @@ macro_rules! _save_content {
 }
 
 macro_rules! save_content {
-    (result $data: expr, $value: expr, $file_name: expr) => {
-        _save_content!(
-            format!(
-                r#"Ok(match {} {{
-                    {}
-                }}?)"#,
-                $value,
-                $data
-            ),
-            $file_name
-        )
-    };
-
     ($data: expr, $value: expr, $file_name: expr) => {
         _save_content!(
             format!(
@@ -108,7 +101,7 @@ pub fn run() {
         format!("{out_dir}/countries-enum-values"),
         format!(
             r#"
-            #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
             pub enum Country {{
                 {enum_content}
             }}"#
@@ -124,10 +117,10 @@ pub fn run() {
     let match_pattern = names_by_line
         .clone()
         .enumerate()
-        .map(|(i, name)| format!("Country::{} => f.write_str(\"{name}\")", enum_names[i]))
+        .map(|(i, name)| format!("Country::{} => \"{name}\"", enum_names[i]))
         .collect::<Vec<String>>()
         .join(",\n");
-    save_content!(result match_pattern, "self", "countries-to-names");
+    save_content!(match_pattern, "self", "countries-names");
 
     let match_pattern = enum_names
         .iter()
@@ -152,6 +145,128 @@ pub fn run() {
         .join(",\n");
     save_content!(match_pattern, "value", "country-to-continent");
 
+    // Each line holds the centroid of the country, then its bounding boxes, if it has a territory
+    let coordinates = COORDINATES
+        .split('\n')
+        .map(|line| {
+            line.split(';')
+                .map(|values| {
+                    values
+                        .split_whitespace()
+                        .map(|value| format!("{:?}", value.parse::<f64>().unwrap()))
+                        .collect::<Vec<String>>()
+                })
+                .filter(|values| !values.is_empty())
+                .collect::<Vec<Vec<String>>>()
+        })
+        .collect::<Vec<Vec<Vec<String>>>>();
+
+    let match_pattern = coordinates
+        .iter()
+        .enumerate()
+        .map(|(i, values)| match values.first() {
+            Some(centroid) => format!(
+                "Country::{} => Some(({}))",
+                enum_names[i],
+                centroid.join(", ")
+            ),
+            None => format!("Country::{} => None", enum_names[i]),
+        })
+        .collect::<Vec<String>>()
+        .join(",\n");
+    save_content!(match_pattern, "self", "countries-centroids");
+
+    let match_pattern = coordinates
+        .iter()
+        .enumerate()
+        .map(|(i, values)| {
+            let boxes = values
+                .iter()
+                .skip(1)
+                .map(|bounds| format!("[{}]", bounds.join(", ")))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("Country::{} => &[{boxes}]", enum_names[i])
+        })
+        .collect::<Vec<String>>()
+        .join(",\n");
+    save_content!(match_pattern, "self", "countries-bounds");
+
+    // Each line holds the capital, the area in km² and the population of the country, if it has a
+    // territory. The capital is empty for the territories without one
+    let match_pattern = METADATA
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            match fields[..] {
+                [capital, area_km2, population] => {
+                    let capital = match capital {
+                        "" => "None".to_string(),
+                        capital => format!("Some({capital:?})"),
+                    };
+                    format!(
+                        "Country::{} => Some(CountryMetadata {{ capital: {capital}, area_km2: {:?}, population: {} }})",
+                        enum_names[i],
+                        area_km2.parse::<f64>().unwrap(),
+                        population.parse::<u64>().unwrap()
+                    )
+                }
+                _ => format!("Country::{} => None", enum_names[i]),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",\n");
+    save_content!(match_pattern, "self", "countries-metadata");
+
+    // Each line holds the country, the region code of the database and the ISO 3166-2 code of
+    // the region
+    let match_pattern = REGIONS_FIPS_TO_ISO
+        .split('\n')
+        .map(|line| {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            let index = CODES_2_DATA
+                .split('\n')
+                .position(|code| code == fields[0])
+                .unwrap();
+            format!(
+                "(Country::{}, {:?}) => Some({:?})",
+                enum_names[index], fields[1], fields[2]
+            )
+        })
+        .collect::<Vec<String>>()
+        .add("_ => None".to_string())
+        .join(",\n");
+    save_content!(match_pattern, "(self, fips)", "regions-fips-to-iso");
+
+    // Each line holds the country, then its name in French, German and Spanish, the countries
+    // without a line keeping their English name
+    let match_pattern = NAMES_LOCALIZED
+        .split('\n')
+        .flat_map(|line| {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            let index = CODES_2_DATA
+                .split('\n')
+                .position(|code| code == fields[0])
+                .unwrap();
+            ["Fr", "De", "Es"]
+                .iter()
+                .zip(&fields[1..])
+                .map(|(locale, name)| {
+                    format!(
+                        "(Country::{}, Locale::{locale}) => Some({name:?})",
+                        enum_names[index]
+                    )
+                })
+                .collect::<Vec<String>>()
+        })
+        .collect::<Vec<String>>()
+        .add("_ => None".to_string())
+        .join(",\n");
+    save_content!(match_pattern, "(self, locale)", "countries-names-localized");
+
+    country_statuses(&enum_names);
+
     let match_pattern = DMA.split('\n')
         .map(|dma| {
             let data: Vec<&str> = dma.split("; ").collect();
@@ -161,4 +276,194 @@ pub fn run() {
         .add("_ => f.write_str(\"Unknown DMA\")".to_string())
         .join(",\n");
     save_content!(match_pattern, "value", "dma-code-to-name");
+
+    let codes = DMA
+        .split('\n')
+        .map(|dma| dma.split("; ").next().unwrap().to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    _save_content!(format!("[{codes}]"), "dma-codes");
+
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_COUNTRY_DATA").is_some() {
+        country_prefixes(&enum_names);
+    }
+}
+
+/// Writes the prefixes of the embedded country dataset as `(first /16 of the prefix, prefix
+/// length, country)`, checking that they are aligned, between /8 and /16, sorted and disjoint.
+fn country_prefixes(enum_names: &[String]) {
+    let mut next = 0u64;
+    let prefixes = COUNTRY_PREFIXES
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let (prefix, code) = line.split_once(',').unwrap();
+            let (network, len) = prefix.split_once('/').unwrap();
+            let network: u32 = network.parse::<std::net::Ipv4Addr>().unwrap().into();
+            let len: u32 = len.parse().unwrap();
+            assert!((8..=16).contains(&len), "{line}: not between /8 and /16");
+            assert_eq!(network % (1 << (32 - len)), 0, "{line}: not aligned");
+            assert!(u64::from(network) >= next, "{line}: not sorted or overlapping");
+            next = u64::from(network) + (1 << (32 - len));
+
+            let index = CODES_2_DATA.split('\n').position(|c| c == code).unwrap();
+            format!("({}, {len}, Country::{})", network >> 16, enum_names[index])
+        })
+        .collect::<Vec<String>>()
+        .join(",\n");
+    _save_content!(format!("[{prefixes}]"), "country-prefixes");
+}
+
+/// The status of a country of `countries-status.txt`, the countries without a line being
+/// current.
+#[derive(Debug, PartialEq)]
+enum Status {
+    /// Replaced by the countries of the given indexes, the first being the most populous.
+    Deprecated(Vec<usize>),
+    Reserved,
+}
+
+/// Parses the statuses, each line holding the country, `reserved` or `deprecated`, and for the
+/// latter the space-separated countries replacing it. The successors must be known current
+/// countries, and are sorted by decreasing population, the first one being what `modernize`
+/// returns.
+fn parse_statuses(
+    data: &str,
+    codes: &[&str],
+    populations: &[u64],
+) -> Result<Vec<(usize, Status)>, String> {
+    let index = |code: &str| {
+        codes
+            .iter()
+            .position(|c| *c == code)
+            .ok_or_else(|| format!("unknown country {code:?}"))
+    };
+
+    let mut statuses: Vec<(usize, Status)> = Vec::new();
+    for line in data.lines() {
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        let country = index(fields[0]).map_err(|err| format!("{line}: {err}"))?;
+        if statuses.iter().any(|(other, _)| *other == country) {
+            return Err(format!("{line}: duplicate country"));
+        }
+        let status = match fields[1..] {
+            ["reserved"] => Status::Reserved,
+            ["deprecated", successors] => {
+                let mut successors = successors
+                    .split_whitespace()
+                    .map(index)
+                    .collect::<Result<Vec<usize>, String>>()
+                    .map_err(|err| format!("{line}: {err}"))?;
+                if successors.is_empty() {
+                    return Err(format!("{line}: no successor"));
+                }
+                successors.sort_by_key(|successor| std::cmp::Reverse(populations[*successor]));
+                Status::Deprecated(successors)
+            }
+            _ => {
+                return Err(format!(
+                    "{line}: expected reserved or deprecated; <successors>"
+                ))
+            }
+        };
+        statuses.push((country, status));
+    }
+
+    for (_, status) in &statuses {
+        if let Status::Deprecated(successors) = status {
+            for successor in successors {
+                if statuses.iter().any(|(country, _)| country == successor) {
+                    return Err(format!("successor {} is not current", codes[*successor]));
+                }
+            }
+        }
+    }
+    Ok(statuses)
+}
+
+/// Writes the status of each country and its modern successor.
+fn country_statuses(enum_names: &[String]) {
+    let codes: Vec<&str> = CODES_2_DATA.split('\n').collect();
+    let populations: Vec<u64> = METADATA
+        .split('\n')
+        .map(|line| {
+            line.rsplit(';')
+                .next()
+                .and_then(|population| population.trim().parse().ok())
+                .unwrap_or(0)
+        })
+        .collect();
+    let statuses = parse_statuses(STATUSES, &codes, &populations)
+        .unwrap_or_else(|err| panic!("countries-status.txt: {err}"));
+
+    let match_pattern = statuses
+        .iter()
+        .map(|(country, status)| match status {
+            Status::Deprecated(successors) => {
+                let successors = successors
+                    .iter()
+                    .map(|successor| format!("Country::{}", enum_names[*successor]))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!(
+                    "Country::{} => CountryStatus::Deprecated {{ successors: &[{successors}] }}",
+                    enum_names[*country]
+                )
+            }
+            Status::Reserved => format!(
+                "Country::{} => CountryStatus::Reserved",
+                enum_names[*country]
+            ),
+        })
+        .collect::<Vec<String>>()
+        .add("_ => CountryStatus::Current".to_string())
+        .join(",\n");
+    save_content!(match_pattern, "self", "countries-status");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODES: [&str; 4] = ["AN", "BQ", "CW", "FX"];
+    const POPULATIONS: [u64; 4] = [0, 25_000, 150_000, 0];
+
+    #[test]
+    fn test_parse_statuses() {
+        let statuses =
+            parse_statuses("AN; deprecated; BQ CW\nFX; reserved", &CODES, &POPULATIONS).unwrap();
+        assert_eq!(
+            statuses,
+            [(0, Status::Deprecated(vec![2, 1])), (3, Status::Reserved)]
+        );
+    }
+
+    #[test]
+    fn test_parse_statuses_rejects() {
+        for (data, error) in [
+            (
+                "AN; deprecated; BQ XX",
+                "AN; deprecated; BQ XX: unknown country \"XX\"",
+            ),
+            ("XX; reserved", "XX; reserved: unknown country \"XX\""),
+            ("AN; deprecated; ", "AN; deprecated; : no successor"),
+            (
+                "AN; current",
+                "AN; current: expected reserved or deprecated; <successors>",
+            ),
+            (
+                "AN; reserved\nAN; reserved",
+                "AN; reserved: duplicate country",
+            ),
+            (
+                "AN; deprecated; FX\nFX; reserved",
+                "successor FX is not current",
+            ),
+        ] {
+            assert_eq!(
+                parse_statuses(data, &CODES, &POPULATIONS),
+                Err(error.to_string())
+            );
+        }
+    }
 }
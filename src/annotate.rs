@@ -0,0 +1,731 @@
+#[cfg(feature = "serde")]
+use crate::binary::BinaryRow;
+use crate::errors::GeoIpReaderError;
+use crate::format::{SchemaMode, SchemaVersion};
+use crate::geo_ip_reader::{edition_name, GeoIpReader, Provenance, Record, SourceKind};
+use crate::ip_hash::IpHashKey;
+use crate::locale::Locale;
+use crate::post_process::{FieldList, RecordFields};
+use crate::time_zones::unix_now;
+use crate::utils::{
+    annotate_line, find_first_ip_matching, is_reserved, json_string, IpPattern, RECORD_FIELD_NAMES,
+};
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::net::IpAddr;
+
+/// `Annotator` appends the geographical fields of the first IP address found in a line of
+/// text, e.g. of an access log, to that line.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::annotate::Annotator;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut annotator = Annotator::new(&["country_code", "city"])
+///     .with_v4(GeoIpReader::<File>::new("v4").unwrap())
+///     .delimiter(",");
+///
+/// let line = annotator.annotate("108.95.4.105 GET /").unwrap();
+/// assert_eq!(line, "108.95.4.105 GET /,US,San Diego");
+/// ```
+#[derive(Debug)]
+pub struct Annotator<R = File>
+where
+    R: Read + Seek,
+{
+    v4: Option<GeoIpReader<R>>,
+    v6: Option<GeoIpReader<R>>,
+    fields: Vec<String>,
+    pattern: IpPattern,
+    delimiter: String,
+    at: Option<i64>,
+    provenance: bool,
+    schema: SchemaVersion,
+    schema_mode: SchemaMode,
+    locale: Locale,
+    hash_key: Option<IpHashKey>,
+}
+
+impl<R> Annotator<R>
+where
+    R: Read + Seek,
+{
+    /// Constructs an `Annotator` appending the given fields, named as in
+    /// `utils::RECORD_FIELD_NAMES` or added by the post-processors of the readers, separated by
+    /// tabs.
+    pub fn new(fields: &[&str]) -> Self {
+        Annotator {
+            v4: None,
+            v6: None,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            pattern: IpPattern::default(),
+            delimiter: "\t".to_string(),
+            at: None,
+            provenance: false,
+            schema: SchemaVersion::default(),
+            schema_mode: SchemaMode::default(),
+            locale: Locale::default(),
+            hash_key: None,
+        }
+    }
+
+    /// Sets the database used for IPv4 addresses.
+    pub fn with_v4(mut self, reader: GeoIpReader<R>) -> Self {
+        self.v4 = Some(reader);
+        self
+    }
+
+    /// Sets the database used for IPv6 addresses.
+    pub fn with_v6(mut self, reader: GeoIpReader<R>) -> Self {
+        self.v6 = Some(reader);
+        self
+    }
+
+    /// Sets the address families to look for in each line.
+    pub fn pattern(mut self, pattern: IpPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Sets the separator written before each appended field.
+    pub fn delimiter(mut self, delimiter: &str) -> Self {
+        self.delimiter = delimiter.to_string();
+        self
+    }
+
+    /// Computes the time zone abbreviation and UTC offset at the given instant, in seconds since
+    /// the Unix epoch, instead of the current time.
+    pub fn at(mut self, unix_ts: i64) -> Self {
+        self.at = Some(unix_ts);
+        self
+    }
+
+    /// Sets whether `to_json` includes the provenance of the records, the database file,
+    /// edition and build date they were read from, from schema version 2. Disabled by default.
+    pub fn provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Sets the schema version of the objects of `to_json`, `SchemaVersion::V1` by default.
+    pub fn schema(mut self, version: SchemaVersion) -> Self {
+        self.schema = version;
+        self
+    }
+
+    /// Sets whether `to_json` emits the custom fields added by the post-processors of the
+    /// readers, `SchemaMode::Strict` by default, see `SchemaMode`.
+    pub fn schema_mode(mut self, mode: SchemaMode) -> Self {
+        self.schema_mode = mode;
+        self
+    }
+
+    /// Sets the language of the `country_name` and `continent` fields, in the annotated lines
+    /// and in `to_json` alike. English by default.
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Replaces the looked up address with its keyed hash, `IpHashKey::hash`, in the annotated
+    /// lines and in the `ip` key and error messages of `to_json`, so that the output holds no raw
+    /// address. The fields of the record are unchanged. Disabled by default.
+    ///
+    /// Only the first address of a line, the one looked up, is replaced.
+    pub fn hash_ip(mut self, key: IpHashKey) -> Self {
+        self.hash_key = Some(key);
+        self
+    }
+
+    /// Annotates a single line of text.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line, without its line terminator.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<String, GeoIpReaderError>`): The line followed by the requested fields, or the
+    /// untouched line if it contains no IP address. The fields of an address that is not in the
+    /// database, or whose family has no database, are empty.
+    pub fn annotate(&mut self, line: &str) -> Result<String, GeoIpReaderError> {
+        let (range, ip) = match find_first_ip_matching(line, self.pattern) {
+            Some(found) => found,
+            None => return Ok(line.to_string()),
+        };
+        let mut line = line.to_string();
+        if self.hash_key.is_some() {
+            line.replace_range(range, &self.shown(ip));
+        }
+
+        let values = match self.lookup(ip) {
+            Ok(record) => self.values(ip, &record),
+            Err(GeoIpReaderError::AddressNotFound(_) | GeoIpReaderError::ReservedAddress(_)) => {
+                vec![None; self.fields.len()]
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(annotate_line(&line, &values, &self.delimiter))
+    }
+
+    /// Formats the lookup of an address as a single-line JSON object, e.g. for a stream of
+    /// newline-delimited JSON.
+    ///
+    /// Every object starts with its `schema_version`, and has the keys of that version in
+    /// `format::schema`. A successful lookup gives the address, then the requested fields, `null`
+    /// when empty, then `count` if given, then `provenance` if enabled with
+    /// `Annotator::provenance` and the version has it. A failed lookup gives the address, the
+    /// `code` of the error and its message, as in `{"schema_version": "1", "ip": "10.0.0.1",
+    /// "error": "reserved_address", "message": "..."}`. With `Annotator::hash_ip`, the address is
+    /// replaced with its hash in the `ip` key and in the message alike.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The address to look up.
+    /// * `count` - The number of occurrences of the address, if counted.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<String, GeoIpReaderError>`): The object, an error object if the address is not
+    /// in the database, reserved or has no database for its family.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    pub fn to_json(&mut self, ip: IpAddr, count: Option<u64>) -> Result<String, GeoIpReaderError> {
+        let record = match self.lookup(ip) {
+            Ok(record) => record,
+            Err(
+                err @ (GeoIpReaderError::AddressNotFound(_) | GeoIpReaderError::ReservedAddress(_)),
+            ) => return Ok(json_error(&self.shown(ip), &err, self.schema)),
+            Err(err) => return Err(err),
+        };
+
+        let mut members = vec![
+            format!("\"schema_version\": {}", json_string(self.schema.as_str())),
+            format!("\"ip\": {}", json_string(&self.shown(ip))),
+        ];
+        let fields = self.processed(ip, &record);
+        let permissive = self.schema_mode == SchemaMode::Permissive;
+        let is_custom = |name: &str| !RECORD_FIELD_NAMES.contains(&name);
+        let mut member = |name: &str, value: Option<&String>| {
+            let value = value.map_or("null".to_string(), |value| json_string(value));
+            members.push(format!("{}: {}", json_string(name), value));
+        };
+        for name in &self.fields {
+            let field = fields.iter().find(|(key, _)| key == name);
+            // A custom field is not part of any schema, and a removed field keeps its key only
+            // in the strict mode
+            if (!permissive && is_custom(name)) || (permissive && field.is_none()) {
+                continue;
+            }
+            member(name, field.and_then(|(_, value)| value.as_ref()));
+        }
+        if let Some(count) = count {
+            members.push(format!("\"count\": {}", count));
+        }
+        if let (true, Some(provenance)) = (self.provenance, &record.provenance) {
+            if self.schema.has_field("provenance") {
+                members.push(format!("\"provenance\": {}", provenance_json(provenance)));
+            }
+        }
+        if permissive {
+            for (name, value) in &fields {
+                if is_custom(name) && !self.fields.iter().any(|field| field == name) {
+                    let value = value
+                        .as_ref()
+                        .map_or("null".to_string(), |value| json_string(value));
+                    members.push(format!("{}: {}", json_string(name), value));
+                }
+            }
+        }
+        Ok(format!("{{{}}}", members.join(", ")))
+    }
+
+    /// Looks up an address into a row of the binary format of `binary`, whose header lists the
+    /// requested fields.
+    ///
+    /// A field removed by the post-processors of the readers is empty, and the address is
+    /// hashed with `Annotator::hash_ip`, as in `Annotator::annotate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The address to look up.
+    /// * `count` - The number of occurrences of the address, if counted.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<BinaryRow, GeoIpReaderError>`): The row, an error row if the address is not in
+    /// the database, reserved or has no database for its family.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    #[cfg(feature = "serde")]
+    pub fn to_binary(
+        &mut self,
+        ip: IpAddr,
+        count: Option<u64>,
+    ) -> Result<BinaryRow, GeoIpReaderError> {
+        match self.lookup(ip) {
+            Ok(record) => Ok(BinaryRow::Record {
+                ip: self.shown(ip),
+                values: self.values(ip, &record),
+                count,
+            }),
+            Err(
+                err @ (GeoIpReaderError::AddressNotFound(_) | GeoIpReaderError::ReservedAddress(_)),
+            ) => Ok(BinaryRow::error(&self.shown(ip), &err)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the address as written in the output, its hash with `Annotator::hash_ip`.
+    fn shown(&self, ip: IpAddr) -> String {
+        match &self.hash_key {
+            Some(key) => key.hash(ip),
+            None => ip.to_string(),
+        }
+    }
+
+    /// Looks up the record of an address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::ReservedAddress` or `GeoIpReaderError::AddressNotFound`, of the
+    /// address as shown in the output, if the address is not in the database of its family, or
+    /// if its family has no database.
+    fn lookup(&mut self, ip: IpAddr) -> Result<Record<'static>, GeoIpReaderError> {
+        let reader = match ip {
+            IpAddr::V4(_) => self.v4.as_mut(),
+            IpAddr::V6(_) => self.v6.as_mut(),
+        };
+        let record = match reader {
+            Some(reader) => reader.find_record(&ip.to_string())?,
+            None => None,
+        };
+        record.ok_or_else(|| {
+            if is_reserved(&ip) {
+                GeoIpReaderError::ReservedAddress(self.shown(ip))
+            } else {
+                GeoIpReaderError::AddressNotFound(self.shown(ip))
+            }
+        })
+    }
+
+    /// Returns the fields of a record, processed by the post-processors of the reader of the
+    /// family of the address.
+    fn processed(&self, ip: IpAddr, record: &Record) -> FieldList {
+        let unix_ts = self.at.unwrap_or_else(unix_now);
+        let reader = match ip {
+            IpAddr::V4(_) => self.v4.as_ref(),
+            IpAddr::V6(_) => self.v6.as_ref(),
+        };
+        match reader {
+            Some(reader) => reader.record_fields(record, Some(ip), unix_ts, self.locale),
+            None => RecordFields::new(record, Some(ip), unix_ts, self.locale),
+        }
+        .into_vec()
+    }
+
+    /// Returns the requested fields of a record, empty when the post-processors removed them.
+    fn values(&self, ip: IpAddr, record: &Record) -> Vec<Option<String>> {
+        let fields = self.processed(ip, record);
+        self.fields
+            .iter()
+            .map(|name| {
+                fields
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .and_then(|(_, value)| value.clone())
+            })
+            .collect()
+    }
+}
+
+/// Formats the provenance of a record as a JSON object, e.g.
+/// `{"source": "file", "path": "...", "edition": "City Edition, Rev 1", "build_date": "2016-04-05"}`.
+fn provenance_json(provenance: &Provenance) -> String {
+    let mut members = Vec::new();
+    match &provenance.source {
+        SourceKind::File { path } => {
+            members.push("\"source\": \"file\"".to_string());
+            members.push(format!(
+                "\"path\": {}",
+                json_string(&path.display().to_string())
+            ));
+        }
+        SourceKind::Memory => members.push("\"source\": \"memory\"".to_string()),
+        SourceKind::Override => members.push("\"source\": \"override\"".to_string()),
+        #[cfg(feature = "embedded-country-data")]
+        SourceKind::Embedded => members.push("\"source\": \"embedded\"".to_string()),
+    }
+    let edition = provenance.edition.map_or("null".to_string(), |edition| {
+        json_string(edition_name(edition))
+    });
+    members.push(format!("\"edition\": {}", edition));
+    let build_date = provenance
+        .db_build_date
+        .map_or("null".to_string(), |(year, month, day)| {
+            format!("\"{:04}-{:02}-{:02}\"", year, month, day)
+        });
+    members.push(format!("\"build_date\": {}", build_date));
+    format!("{{{}}}", members.join(", "))
+}
+
+/// Formats an error of the lookup of an input as a single-line JSON object of the given schema
+/// version: `{"schema_version": version, "ip": input, "error": code, "message": message}`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::annotate::json_error;
+/// use ipcap::errors::GeoIpReaderError;
+/// use ipcap::format::SchemaVersion;
+///
+/// let err = GeoIpReaderError::AddressNotFound("10.0.0.1".to_string());
+/// assert_eq!(
+///     json_error("10.0.0.1", &err, SchemaVersion::V1),
+///     r#"{"schema_version": "1", "ip": "10.0.0.1", "error": "address_not_found", "message": "Address not found in the database: 10.0.0.1"}"#
+/// );
+/// ```
+pub fn json_error(input: &str, err: &GeoIpReaderError, version: SchemaVersion) -> String {
+    format!(
+        "{{\"schema_version\": {}, \"ip\": {}, \"error\": {}, \"message\": {}}}",
+        json_string(version.as_str()),
+        json_string(input),
+        json_string(err.code()),
+        json_string(&err.to_string())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use crate::designated_market_area::DesignatedMarketArea;
+    use crate::geo_ip_reader::{CoordinatePolicy, GeoIpReaderBuilder};
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::io::Cursor;
+
+    const APACHE_LOG: &str = r#"108.95.4.105 - - [10/Oct/2023:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/7.68.0"
+[Tue Oct 10 13:55:37.123456 2023] [mpm_event:notice] [pid 1:tid 2] AH00489: Apache/2.4.41 (Ubuntu) configured
+10.0.0.1 - - [10/Oct/2023:13:55:38 +0000] "GET /health HTTP/1.1" 200 2 "-" "kube-probe/1.27"
+2a08:1450:300f:900::1003 - - [10/Oct/2023:13:55:39 +0000] "GET / HTTP/1.1" 301 0 "-" "Mozilla/5.0""#;
+
+    fn annotator() -> Annotator {
+        Annotator::new(&["country_code", "city"])
+            .with_v4(GeoIpReader::<File>::new("v4").unwrap())
+            .with_v6(GeoIpReader::<File>::new("v6").unwrap())
+            .delimiter("|")
+    }
+
+    #[test]
+    fn test_annotate_apache_log() {
+        let mut annotator = annotator();
+
+        let lines: Vec<String> = APACHE_LOG
+            .lines()
+            .map(|line| annotator.annotate(line).unwrap())
+            .collect();
+        let input: Vec<&str> = APACHE_LOG.lines().collect();
+
+        assert_eq!(lines[0], format!("{}|US|San Diego", input[0]));
+        assert_eq!(lines[1], input[1]);
+        assert_eq!(lines[2], format!("{}||", input[2]));
+        assert_eq!(lines[3], format!("{}|GB|", input[3]));
+    }
+
+    #[test]
+    fn test_annotate_with_pattern() {
+        let mut annotator = annotator().pattern(IpPattern::Ipv4);
+        let line = "2a08:1450:300f:900::1003 proxied for 108.95.4.105";
+
+        assert_eq!(
+            annotator.annotate(line).unwrap(),
+            format!("{}|US|San Diego", line)
+        );
+    }
+
+    #[test]
+    fn test_annotate_at_instant() {
+        let mut annotator = Annotator::new(&["tz_abbreviation", "utc_offset"])
+            .with_v4(GeoIpReader::<File>::new("v4").unwrap())
+            .delimiter(",")
+            .at(1721001600);
+
+        assert_eq!(
+            annotator.annotate("108.95.4.105").unwrap(),
+            "108.95.4.105,PDT,-07:00"
+        );
+    }
+
+    #[test]
+    fn test_annotate_without_database_for_family() {
+        let mut annotator =
+            Annotator::new(&["country_code"]).with_v4(GeoIpReader::<File>::new("v4").unwrap());
+
+        assert_eq!(annotator.annotate("::1").unwrap(), "::1\t");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut annotator = annotator();
+
+        assert_eq!(
+            annotator
+                .to_json("108.95.4.105".parse().unwrap(), Some(2))
+                .unwrap(),
+            r#"{"schema_version": "1", "ip": "108.95.4.105", "country_code": "US", "city": "San Diego", "count": 2}"#
+        );
+        assert_eq!(
+            annotator
+                .to_json("2a08:1450:300f:900::1003".parse().unwrap(), None)
+                .unwrap(),
+            r#"{"schema_version": "1", "ip": "2a08:1450:300f:900::1003", "country_code": "GB", "city": null}"#
+        );
+        assert_eq!(
+            annotator
+                .to_json("10.0.0.1".parse().unwrap(), None)
+                .unwrap(),
+            r#"{"schema_version": "1", "ip": "10.0.0.1", "error": "reserved_address", "message": "Address of a reserved range: 10.0.0.1"}"#
+        );
+    }
+
+    #[test]
+    fn test_hash_ip() {
+        let key: IpHashKey = "000102030405060708090a0b0c0d0e0f".parse().unwrap();
+        let token = key.hash("108.95.4.105".parse().unwrap());
+        let reserved = key.hash("10.0.0.1".parse().unwrap());
+        let mut annotator = annotator().hash_ip(key);
+
+        assert_eq!(
+            annotator.annotate("108.95.4.105 GET /").unwrap(),
+            format!("{} GET /|US|San Diego", token)
+        );
+        assert_eq!(
+            annotator
+                .to_json("108.95.4.105".parse().unwrap(), Some(2))
+                .unwrap(),
+            format!(
+                r#"{{"schema_version": "1", "ip": "{}", "country_code": "US", "city": "San Diego", "count": 2}}"#,
+                token
+            )
+        );
+        assert_eq!(
+            annotator
+                .to_json("10.0.0.1".parse().unwrap(), None)
+                .unwrap(),
+            format!(
+                r#"{{"schema_version": "1", "ip": "{0}", "error": "reserved_address", "message": "Address of a reserved range: {0}"}}"#,
+                reserved
+            )
+        );
+    }
+
+    #[test]
+    fn test_dma_fields_in_every_format() {
+        let mut builder = DatabaseBuilder::new();
+        let us = || RecordSpec::new(Country::UnitedStates).region_code("CA");
+        builder
+            .insert("1.0.0.0/8", us())
+            .unwrap()
+            .insert("2.0.0.0/8", us().dma(DesignatedMarketArea(999_650)))
+            .unwrap()
+            .insert("3.0.0.0/8", us().dma(DesignatedMarketArea(807_650)))
+            .unwrap();
+        let reader = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let mut annotator = Annotator::new(&["dma_code", "area_code", "metro_code"])
+            .with_v4(reader)
+            .delimiter(",");
+
+        // An empty CSV column, never "Unknown DMA", for a missing metro
+        let lines: Vec<String> = ["1.2.3.4", "2.2.3.4", "3.2.3.4"]
+            .iter()
+            .map(|ip| annotator.annotate(ip).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "1.2.3.4,,,",
+                "2.2.3.4,999,650,",
+                "3.2.3.4,807,650,San Francisco, CA"
+            ]
+        );
+
+        // Null in JSON
+        let mut json = |ip: &str| {
+            let object = annotator.to_json(ip.parse().unwrap(), None).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&object).unwrap();
+            ["dma_code", "area_code", "metro_code"].map(|key| value[key].as_str().map(String::from))
+        };
+        let some = |value: &str| Some(value.to_string());
+        assert_eq!(json("1.2.3.4"), [None, None, None]);
+        assert_eq!(json("2.2.3.4"), [some("999"), some("650"), None]);
+        assert_eq!(
+            json("3.2.3.4"),
+            [some("807"), some("650"), some("San Francisco, CA")]
+        );
+    }
+
+    #[test]
+    fn test_locale_in_every_format() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let reader = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let mut annotator = Annotator::new(&["country_code", "country_name", "continent"])
+            .with_v4(reader)
+            .delimiter(",")
+            .locale(Locale::Fr);
+
+        assert_eq!(
+            annotator.annotate("1.2.3.4").unwrap(),
+            "1.2.3.4,DE,Allemagne,Europe"
+        );
+        let object = annotator.to_json("1.2.3.4".parse().unwrap(), None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&object).unwrap();
+        assert_eq!(value["country_code"], "DE");
+        assert_eq!(value["country_name"], "Allemagne");
+        assert_eq!(value["continent"], "Europe");
+    }
+
+    #[test]
+    fn test_post_processed_fields_in_every_format() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let reader = || {
+            GeoIpReaderBuilder::new()
+                .post_processor(crate::post_process::add_is_eu_flag())
+                .post_processor(|fields: &mut RecordFields| {
+                    fields.remove("country_name");
+                })
+                .build(Cursor::new(builder.build()))
+                .unwrap()
+        };
+        let fields = ["country_code", "country_name", "is_eu"];
+        let ip = "1.2.3.4".parse().unwrap();
+
+        // The custom field is appended like the others, the removed one is empty
+        let mut annotator = Annotator::new(&fields).with_v4(reader()).delimiter(",");
+        assert_eq!(annotator.annotate("1.2.3.4").unwrap(), "1.2.3.4,DE,,true");
+
+        // Strict keeps to the schema
+        assert_eq!(
+            annotator.to_json(ip, None).unwrap(),
+            r#"{"schema_version": "1", "ip": "1.2.3.4", "country_code": "DE", "country_name": null}"#
+        );
+
+        // Permissive drops the removed field and adds the custom ones, requested or not
+        let mut annotator = Annotator::new(&fields)
+            .with_v4(reader())
+            .schema_mode(SchemaMode::Permissive);
+        assert_eq!(
+            annotator.to_json(ip, Some(3)).unwrap(),
+            r#"{"schema_version": "1", "ip": "1.2.3.4", "country_code": "DE", "is_eu": "true", "count": 3}"#
+        );
+        let mut annotator = Annotator::new(&["country_code"])
+            .with_v4(reader())
+            .schema_mode(SchemaMode::Permissive);
+        assert_eq!(
+            annotator.to_json(ip, None).unwrap(),
+            r#"{"schema_version": "1", "ip": "1.2.3.4", "country_code": "DE", "is_eu": "true"}"#
+        );
+    }
+
+    #[test]
+    fn test_coordinate_policy_in_every_format() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::France))
+            .unwrap();
+        builder
+            .insert(
+                "2.0.0.0/8",
+                RecordSpec::new(Country::France).coordinates(48.5, 2.25),
+            )
+            .unwrap();
+        let database = builder.build();
+        let fields = ["country_code", "latitude", "longitude"];
+        let annotator = |policy| {
+            let reader = GeoIpReaderBuilder::new()
+                .coordinate_policy(policy)
+                .build(Cursor::new(database.clone()))
+                .unwrap();
+            Annotator::new(&fields).with_v4(reader).delimiter(",")
+        };
+        let json = |latitude: &str, longitude: &str| {
+            format!(
+                r#"{{"schema_version": "1", "ip": "1.2.3.4", "country_code": "FR", "latitude": {}, "longitude": {}}}"#,
+                latitude, longitude
+            )
+        };
+
+        let cases = [
+            (CoordinatePolicy::None, "1.2.3.4,FR,,", json("null", "null")),
+            (
+                CoordinatePolicy::CountryCentroid,
+                "1.2.3.4,FR,46,2",
+                json(r#""46""#, r#""2""#),
+            ),
+            (
+                CoordinatePolicy::LegacySentinel,
+                "1.2.3.4,FR,-180,-180",
+                json(r#""-180""#, r#""-180""#),
+            ),
+        ];
+        for (policy, text, expected) in cases {
+            let mut annotator = annotator(policy);
+            assert_eq!(annotator.annotate("1.2.3.4").unwrap(), text, "{}", policy);
+            assert_eq!(
+                annotator.to_json("1.2.3.4".parse().unwrap(), None).unwrap(),
+                expected,
+                "{}",
+                policy
+            );
+            // Real coordinates are kept under every policy
+            assert_eq!(
+                annotator.annotate("2.3.4.5").unwrap(),
+                "2.3.4.5,FR,48.5,2.25",
+                "{}",
+                policy
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_binary() {
+        let mut annotator =
+            annotator().hash_ip("000102030405060708090a0b0c0d0e0f".parse().unwrap());
+        let token = |ip: &str| annotator.shown(ip.parse().unwrap());
+        let (us, reserved) = (token("108.95.4.105"), token("10.0.0.1"));
+
+        assert_eq!(
+            annotator
+                .to_binary("108.95.4.105".parse().unwrap(), Some(2))
+                .unwrap(),
+            BinaryRow::Record {
+                ip: us,
+                values: vec![Some("US".to_string()), Some("San Diego".to_string())],
+                count: Some(2),
+            }
+        );
+        assert_eq!(
+            annotator
+                .to_binary("10.0.0.1".parse().unwrap(), None)
+                .unwrap(),
+            BinaryRow::error(
+                &reserved,
+                &GeoIpReaderError::ReservedAddress(reserved.clone())
+            )
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt::{Display, Formatter};
+
+/// An Autonomous System as stored in the ASN database editions, e.g. `AS15169 Google LLC`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asn {
+    /// The Autonomous System Number.
+    pub number: u32,
+    /// The name of the organization operating the Autonomous System, if any.
+    pub name: Option<Box<str>>,
+}
+
+impl Asn {
+    /// Parses an ASN database record of the form `AS<number> <name>`.
+    ///
+    /// ```rust
+    /// use ipcap::asn::Asn;
+    ///
+    /// let asn = Asn::parse("AS15169 Google LLC").unwrap();
+    /// assert_eq!(asn.number, 15169);
+    /// assert_eq!(asn.name, Some("Google LLC".into()));
+    ///
+    /// assert_eq!(Asn::parse("Google LLC"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.strip_prefix("AS")?;
+        let (number, name) = match value.split_once(' ') {
+            Some((number, name)) => (number, Some(name.trim())),
+            None => (value, None),
+        };
+
+        Some(Asn {
+            number: number.parse().ok()?,
+            name: name.filter(|name| !name.is_empty()).map(Into::into),
+        })
+    }
+}
+
+impl Display for Asn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "AS{} {}", self.number, name),
+            None => write!(f, "AS{}", self.number),
+        }
+    }
+}
@@ -0,0 +1,919 @@
+//! Consistency audits between databases, e.g. of the locations the v4 and v6 databases give to
+//! the two addresses of a dual-stack service, and profiles of a single database, e.g. to check
+//! that a downloaded database is neither truncated nor regionally skewed.
+
+use crate::continents::Continent;
+use crate::countries::Country;
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::{GeoIpReader, Record};
+use crate::utils::json_string;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{Read, Seek};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// How the records of the two addresses of a dual-stack pair compare.
+///
+/// The locations are compared from the country down to the city, a level only being compared
+/// when the levels above it agree. A region or city missing on both sides agrees, one missing on
+/// a single side is unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "snake_case"))]
+pub enum Classification {
+    /// The same country, region and city.
+    SameCity,
+    /// The same country and region, in different cities.
+    DifferentCity,
+    /// The same country and region, one of the records or both without a city.
+    CityUnknown,
+    /// The same country, in different regions.
+    DifferentRegion,
+    /// The same country, a single record with a region.
+    RegionUnknown,
+    /// Different countries.
+    DifferentCountry,
+    /// One of the records or both in a pseudo-country, e.g. `AnonymousProxy` or `Europe`, whose
+    /// location cannot be compared.
+    PseudoCountry,
+    /// The v4 address is not in the v4 database, the v6 address is in the v6 database.
+    V4NotFound,
+    /// The v6 address is not in the v6 database, the v4 address is in the v4 database.
+    V6NotFound,
+    /// Neither address is in its database.
+    NeitherFound,
+    /// A lookup failed, e.g. on a corrupt database.
+    Error,
+}
+
+impl Classification {
+    /// Every classification, from the closest agreement.
+    pub const ALL: [Classification; 11] = [
+        Classification::SameCity,
+        Classification::DifferentCity,
+        Classification::CityUnknown,
+        Classification::DifferentRegion,
+        Classification::RegionUnknown,
+        Classification::DifferentCountry,
+        Classification::PseudoCountry,
+        Classification::V4NotFound,
+        Classification::V6NotFound,
+        Classification::NeitherFound,
+        Classification::Error,
+    ];
+
+    /// The name of the classification, e.g. `same_city`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Classification::SameCity => "same_city",
+            Classification::DifferentCity => "different_city",
+            Classification::CityUnknown => "city_unknown",
+            Classification::DifferentRegion => "different_region",
+            Classification::RegionUnknown => "region_unknown",
+            Classification::DifferentCountry => "different_country",
+            Classification::PseudoCountry => "pseudo_country",
+            Classification::V4NotFound => "v4_not_found",
+            Classification::V6NotFound => "v6_not_found",
+            Classification::NeitherFound => "neither_found",
+            Classification::Error => "error",
+        }
+    }
+
+    /// Whether both records were found in actual countries, so that their locations compare.
+    pub fn is_comparable(&self) -> bool {
+        *self <= Classification::DifferentCountry
+    }
+
+    /// Whether the records are in the same country.
+    pub fn same_country(&self) -> bool {
+        *self <= Classification::RegionUnknown
+    }
+
+    /// Whether the records are in the same country and region.
+    pub fn same_region(&self) -> bool {
+        *self <= Classification::CityUnknown
+    }
+
+    /// Classifies the outcomes of the lookups of a pair.
+    fn of(
+        v4: &Result<Option<Record>, impl Sized>,
+        v6: &Result<Option<Record>, impl Sized>,
+    ) -> Self {
+        let (v4, v6) = match (v4, v6) {
+            (Err(_), _) | (_, Err(_)) => return Classification::Error,
+            (Ok(None), Ok(None)) => return Classification::NeitherFound,
+            (Ok(None), Ok(Some(_))) => return Classification::V4NotFound,
+            (Ok(Some(_)), Ok(None)) => return Classification::V6NotFound,
+            (Ok(Some(v4)), Ok(Some(v6))) => (v4, v6),
+        };
+
+        if is_pseudo_country(v4.country) || is_pseudo_country(v6.country) {
+            return Classification::PseudoCountry;
+        }
+        if v4.country != v6.country {
+            return Classification::DifferentCountry;
+        }
+        match (present(v4.region_code()), present(v6.region_code())) {
+            (Some(v4), Some(v6)) if v4 != v6 => return Classification::DifferentRegion,
+            (Some(_), None) | (None, Some(_)) => return Classification::RegionUnknown,
+            _ => {}
+        }
+        match (present(v4.city()), present(v6.city())) {
+            (Some(v4), Some(v6)) if v4 == v6 => Classification::SameCity,
+            (Some(_), Some(_)) => Classification::DifferentCity,
+            _ => Classification::CityUnknown,
+        }
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Whether a country of the databases stands for something else than a territory.
+fn is_pseudo_country(country: Country) -> bool {
+    matches!(
+        country,
+        Country::AsiaPacificRegion
+            | Country::Europe
+            | Country::AnonymousProxy
+            | Country::SatelliteProvider
+            | Country::Other
+    )
+}
+
+/// A field of a record, `None` when it is missing or empty.
+fn present(field: Option<&str>) -> Option<&str> {
+    field.filter(|value| !value.is_empty())
+}
+
+/// The classification of a dual-stack pair, as listed in `DualStackReport::pairs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PairClassification {
+    pub v4: Ipv4Addr,
+    pub v6: Ipv6Addr,
+    pub classification: Classification,
+}
+
+/// The comparison of the locations of dual-stack pairs, as returned by `compare_dual_stack`.
+///
+/// The agreement percentages are over the comparable pairs, those found on both sides in actual
+/// countries, and are `None` without any.
+///
+/// The `Display` form lists each pair with its classification, then the counts and the
+/// percentages.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DualStackReport {
+    /// The classification of each pair, in input order.
+    pub pairs: Vec<PairClassification>,
+}
+
+impl DualStackReport {
+    /// Returns the number of pairs with the given classification.
+    pub fn count(&self, classification: Classification) -> usize {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.classification == classification)
+            .count()
+    }
+
+    /// Returns the number of pairs whose locations compare.
+    pub fn comparable(&self) -> usize {
+        self.matching(Classification::is_comparable)
+    }
+
+    /// Returns the percentage of the comparable pairs in the same country.
+    pub fn country_agreement(&self) -> Option<f64> {
+        self.percentage(Classification::same_country)
+    }
+
+    /// Returns the percentage of the comparable pairs in the same country and region.
+    pub fn region_agreement(&self) -> Option<f64> {
+        self.percentage(Classification::same_region)
+    }
+
+    /// Returns the percentage of the comparable pairs in the same city.
+    pub fn city_agreement(&self) -> Option<f64> {
+        self.percentage(|classification| *classification == Classification::SameCity)
+    }
+
+    /// Formats the report as a JSON object of the pairs, the counts of each classification and
+    /// the agreement percentages, `null` without comparable pairs.
+    pub fn to_json(&self) -> String {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|pair| {
+                format!(
+                    "{{\"v4\": {}, \"v6\": {}, \"classification\": {}}}",
+                    json_string(&pair.v4.to_string()),
+                    json_string(&pair.v6.to_string()),
+                    json_string(pair.classification.as_str())
+                )
+            })
+            .collect();
+        let counts: Vec<String> = Classification::ALL
+            .iter()
+            .map(|classification| {
+                format!(
+                    "{}: {}",
+                    json_string(classification.as_str()),
+                    self.count(*classification)
+                )
+            })
+            .collect();
+        let percentage = |value: Option<f64>| value.map_or("null".to_string(), |v| v.to_string());
+        format!(
+            "{{\"total\": {}, \"comparable\": {}, \"country_agreement\": {}, \
+             \"region_agreement\": {}, \"city_agreement\": {}, \"counts\": {{{}}}, \
+             \"pairs\": [{}]}}",
+            self.pairs.len(),
+            self.comparable(),
+            percentage(self.country_agreement()),
+            percentage(self.region_agreement()),
+            percentage(self.city_agreement()),
+            counts.join(", "),
+            pairs.join(", ")
+        )
+    }
+
+    fn matching(&self, predicate: impl Fn(&Classification) -> bool) -> usize {
+        self.pairs
+            .iter()
+            .filter(|pair| predicate(&pair.classification))
+            .count()
+    }
+
+    fn percentage(&self, predicate: impl Fn(&Classification) -> bool) -> Option<f64> {
+        let comparable = self.comparable();
+        let matching = self
+            .matching(|classification| classification.is_comparable() && predicate(classification));
+        (comparable > 0).then(|| matching as f64 * 100.0 / comparable as f64)
+    }
+}
+
+impl fmt::Display for DualStackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pair in &self.pairs {
+            writeln!(f, "{} {} {}", pair.v4, pair.v6, pair.classification)?;
+        }
+        writeln!(f, "pairs: {}", self.pairs.len())?;
+        for classification in Classification::ALL {
+            writeln!(f, "  {}: {}", classification, self.count(classification))?;
+        }
+        writeln!(f, "comparable: {}", self.comparable())?;
+        for (level, agreement) in [
+            ("country", self.country_agreement()),
+            ("region", self.region_agreement()),
+            ("city", self.city_agreement()),
+        ] {
+            match agreement {
+                Some(agreement) => writeln!(f, "same {}: {:.1}%", level, agreement)?,
+                None => writeln!(f, "same {}: n/a", level)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the two addresses of each dual-stack pair, the IPv4 one in `v4` and the IPv6 one in
+/// `v6`, and classifies how their locations agree.
+///
+/// # Arguments
+///
+/// * `v4` - A reader of an IPv4 city database.
+/// * `v6` - A reader of an IPv6 city database.
+/// * `pairs` - The IPv4 and IPv6 addresses of each service.
+///
+/// # Returns
+///
+/// (`DualStackReport`): The classification of each pair, a failed lookup being classified as
+/// `Classification::Error` rather than failing the audit.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::audit::{compare_dual_stack, Classification};
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut v4 = GeoIpReader::<File>::new("v4").unwrap();
+/// let mut v6 = GeoIpReader::<File>::new("v6").unwrap();
+/// let pairs = [
+///     ("8.8.8.8".parse().unwrap(), "2001:4860:4860::8888".parse().unwrap()),
+///     ("10.0.0.1".parse().unwrap(), "2001:4860:4860::8888".parse().unwrap()),
+/// ];
+///
+/// let report = compare_dual_stack(&mut v4, &mut v6, &pairs);
+/// assert!(report.pairs[0].classification.same_country());
+/// assert_eq!(report.pairs[1].classification, Classification::V4NotFound);
+/// assert_eq!(report.country_agreement(), Some(100.0));
+/// ```
+pub fn compare_dual_stack<R1, R2>(
+    v4: &mut GeoIpReader<R1>,
+    v6: &mut GeoIpReader<R2>,
+    pairs: &[(Ipv4Addr, Ipv6Addr)],
+) -> DualStackReport
+where
+    R1: Read + Seek,
+    R2: Read + Seek,
+{
+    let pairs = pairs
+        .iter()
+        .map(|&(v4_addr, v6_addr)| {
+            let v4_record = v4.find_record(&v4_addr.to_string());
+            let v6_record = v6.find_record(&v6_addr.to_string());
+            PairClassification {
+                v4: v4_addr,
+                v6: v6_addr,
+                classification: Classification::of(&v4_record, &v6_record),
+            }
+        })
+        .collect();
+    DualStackReport { pairs }
+}
+
+/// How many records of a database `database_stats` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleSpec {
+    /// Every record, for exact counts.
+    Full,
+    /// A random sample of `size` distinct records, for quick estimates. The sample is drawn by
+    /// a generator seeded with `seed`, so the same seed draws the same sample.
+    Sample { size: usize, seed: u64 },
+}
+
+/// The profile of a city database, as returned by `database_stats`.
+///
+/// The networks, records and offsets are exact. The breakdowns by country, continent and
+/// populated field are over the records read: every record, or a sample of them when `sampled`
+/// is set, `estimate` scaling them to the whole database. Every counter has a fixed size,
+/// whatever the size of the database.
+///
+/// The `Display` form is a table of the counts with their percentages of the records read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbStats {
+    /// The networks of the database, the pointers of its tree to a record.
+    pub networks: u64,
+    /// The distinct records of the database.
+    pub records: u64,
+    /// The number of records read when they were sampled, `None` when every record was read.
+    pub sampled: Option<u64>,
+    /// The lowest absolute position of a record, `None` for a database without records.
+    pub min_offset: Option<u64>,
+    /// The highest absolute position of a record, `None` for a database without records.
+    pub max_offset: Option<u64>,
+    /// The records read with a city.
+    pub with_city: u64,
+    /// The records read with a postal code.
+    pub with_postal_code: u64,
+    /// The records read with real coordinates, see `Record::has_real_coordinates`.
+    pub with_coordinates: u64,
+    /// The records read by country, indexed by `Country::to_buffer`.
+    countries: [u64; 256],
+    /// The records read by continent, in the order of `Continent::ALL`.
+    continents: [u64; 7],
+}
+
+impl DbStats {
+    fn new() -> Self {
+        DbStats {
+            networks: 0,
+            records: 0,
+            sampled: None,
+            min_offset: None,
+            max_offset: None,
+            with_city: 0,
+            with_postal_code: 0,
+            with_coordinates: 0,
+            countries: [0; 256],
+            continents: [0; 7],
+        }
+    }
+
+    /// Counts a record read.
+    fn add(&mut self, record: &Record) {
+        self.with_city += u64::from(record.city().is_some());
+        self.with_postal_code += u64::from(record.postal_code().is_some());
+        self.with_coordinates += u64::from(record.has_real_coordinates());
+        if let Some(count) = self
+            .countries
+            .get_mut(usize::from(record.country.to_buffer()))
+        {
+            *count += 1;
+        }
+        let continent = record
+            .country
+            .continent()
+            .and_then(|continent| Continent::ALL.iter().position(|c| *c == continent));
+        if let Some(count) = continent.and_then(|index| self.continents.get_mut(index)) {
+            *count += 1;
+        }
+    }
+
+    /// Returns the number of records read, every record unless they were sampled.
+    pub fn read(&self) -> u64 {
+        self.sampled.unwrap_or(self.records)
+    }
+
+    /// Returns the number of records read of a country.
+    pub fn country(&self, country: Country) -> u64 {
+        self.countries
+            .get(usize::from(country.to_buffer()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the countries with records read and their counts, the most frequent first.
+    pub fn countries(&self) -> Vec<(Country, u64)> {
+        let mut countries: Vec<(Country, u64)> = (0..=u8::MAX)
+            .filter_map(Country::from_buffer)
+            .map(|country| (country, self.country(country)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        countries.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.0.alphabetic_code_2().cmp(b.0.alphabetic_code_2()))
+        });
+        countries
+    }
+
+    /// Returns the number of records read of a continent.
+    pub fn continent(&self, continent: Continent) -> u64 {
+        Continent::ALL
+            .iter()
+            .position(|c| *c == continent)
+            .and_then(|index| self.continents.get(index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the percentage of the records read a count stands for, `None` without records
+    /// read.
+    pub fn percentage(&self, count: u64) -> Option<f64> {
+        let read = self.read();
+        (read > 0).then(|| count as f64 * 100.0 / read as f64)
+    }
+
+    /// Scales a count of the records read to the whole database, the count itself when every
+    /// record was read.
+    pub fn estimate(&self, count: u64) -> u64 {
+        match self.read() {
+            0 => 0,
+            read => (count as f64 * self.records as f64 / read as f64).round() as u64,
+        }
+    }
+
+    /// Formats the profile as a JSON object, `sampled` being `null` when every record was read
+    /// and the offsets `null` without records.
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<u64>| value.map_or("null".to_string(), |v| v.to_string());
+        let continents: Vec<String> = Continent::ALL
+            .iter()
+            .map(|continent| {
+                format!(
+                    "{}: {}",
+                    json_string(continent.alphabetic_code_2()),
+                    self.continent(*continent)
+                )
+            })
+            .collect();
+        let countries: Vec<String> = self
+            .countries()
+            .iter()
+            .map(|(country, count)| {
+                format!("{}: {}", json_string(country.alphabetic_code_2()), count)
+            })
+            .collect();
+        format!(
+            "{{\"networks\": {}, \"records\": {}, \"sampled\": {}, \"min_offset\": {}, \
+             \"max_offset\": {}, \"with_city\": {}, \"with_postal_code\": {}, \
+             \"with_coordinates\": {}, \"continents\": {{{}}}, \"countries\": {{{}}}}}",
+            self.networks,
+            self.records,
+            optional(self.sampled),
+            optional(self.min_offset),
+            optional(self.max_offset),
+            self.with_city,
+            self.with_postal_code,
+            self.with_coordinates,
+            continents.join(", "),
+            countries.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for DbStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |f: &mut fmt::Formatter<'_>, name: &str, count: u64| match self.percentage(count)
+        {
+            Some(percentage) => writeln!(f, "  {:<16} {:>10} {:>6.1}%", name, count, percentage),
+            None => writeln!(f, "  {:<16} {:>10}", name, count),
+        };
+
+        writeln!(f, "networks: {}", self.networks)?;
+        writeln!(f, "records: {}", self.records)?;
+        if let Some(sampled) = self.sampled {
+            writeln!(
+                f,
+                "sampled: {} of {} records, the counts below are of the sample",
+                sampled, self.records
+            )?;
+        }
+        if let (Some(min), Some(max)) = (self.min_offset, self.max_offset) {
+            writeln!(f, "offsets: {}-{}", min, max)?;
+        }
+        writeln!(f, "populated:")?;
+        row(f, "city", self.with_city)?;
+        row(f, "postal_code", self.with_postal_code)?;
+        row(f, "coordinates", self.with_coordinates)?;
+        writeln!(f, "continents:")?;
+        for continent in Continent::ALL {
+            row(f, continent.alphabetic_code_2(), self.continent(continent))?;
+        }
+        writeln!(f, "countries:")?;
+        for (country, count) in self.countries() {
+            row(f, country.alphabetic_code_2(), count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Profiles a city database: its networks and distinct records, the range of the positions
+/// of its records, and the breakdown of its records by country, continent and populated field.
+///
+/// The tree is read once for its distinct leaves, then the records of every leaf or of a
+/// sample of them are read. The distinct leaves are held in memory, 4 bytes each; the
+/// statistics themselves have a fixed size.
+///
+/// # Arguments
+///
+/// * `reader` - A reader of a city database.
+/// * `sample` - Whether every record is read, or a random sample of them.
+///
+/// # Returns
+///
+/// (`Result<DbStats, GeoIpReaderError>`): The profile of the database.
+///
+/// # Errors
+///
+/// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is not a city edition,
+/// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::audit::{database_stats, SampleSpec};
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let sample = SampleSpec::Sample { size: 1000, seed: 42 };
+/// let stats = database_stats(&mut geo_ip, sample).unwrap();
+///
+/// assert_eq!(stats.sampled, Some(1000));
+/// assert!(stats.networks > stats.records);
+/// assert!(stats.country(Country::UnitedStates) > 0);
+/// ```
+pub fn database_stats<R: Read + Seek>(
+    reader: &mut GeoIpReader<R>,
+    sample: SampleSpec,
+) -> Result<DbStats, GeoIpReaderError> {
+    let mut stats = DbStats::new();
+    let mut leaves = BTreeSet::new();
+    reader.visit_city_leaves(|leaf| {
+        stats.networks += 1;
+        leaves.insert(leaf);
+    })?;
+    stats.records = leaves.len() as u64;
+    stats.min_offset = leaves
+        .first()
+        .map(|leaf| reader.leaf_position(u64::from(*leaf)));
+    stats.max_offset = leaves
+        .last()
+        .map(|leaf| reader.leaf_position(u64::from(*leaf)));
+
+    let leaves: Vec<u32> = match sample {
+        SampleSpec::Sample { size, seed } if size < leaves.len() => {
+            stats.sampled = Some(size as u64);
+            reservoir_sample(leaves, size, seed)
+        }
+        _ => leaves.into_iter().collect(),
+    };
+    for leaf in leaves {
+        stats.add(&reader.leaf_record(leaf)?);
+    }
+    Ok(stats)
+}
+
+/// Draws `size` of the leaves uniformly, returned in ascending order so that the records are
+/// read forward.
+fn reservoir_sample(leaves: BTreeSet<u32>, size: usize, seed: u64) -> Vec<u32> {
+    // A xorshift generator, whose state must not be zero
+    let mut state = seed | 1;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut sample = Vec::with_capacity(size);
+    for (index, leaf) in leaves.into_iter().enumerate() {
+        if index < size {
+            sample.push(leaf);
+        } else if let Some(slot) = sample.get_mut((next() % (index as u64 + 1)) as usize) {
+            *slot = leaf;
+        }
+    }
+    sample.sort_unstable();
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::io::Cursor;
+
+    type Reader = GeoIpReader<Cursor<Vec<u8>>>;
+
+    /// The v4 and v6 databases of the fixture, the nth /24 of `10.0.0.0/16` being paired with
+    /// the nth /32 of `2a00::/16`, each pair being built to agree at a given level.
+    fn databases() -> (Reader, Reader) {
+        let us = || RecordSpec::new(Country::UnitedStates);
+        let specs: [(Option<RecordSpec>, Option<RecordSpec>); 11] = [
+            // Same city, twice
+            (
+                Some(us().region_code("CA").city("San Diego")),
+                Some(us().region_code("CA").city("San Diego")),
+            ),
+            (
+                Some(us().region_code("NY").city("New York")),
+                Some(us().region_code("NY").city("New York")),
+            ),
+            // Different cities of a region
+            (
+                Some(us().region_code("CA").city("San Diego")),
+                Some(us().region_code("CA").city("Los Angeles")),
+            ),
+            // A region without a city on one side
+            (
+                Some(us().region_code("CA").city("San Diego")),
+                Some(us().region_code("CA")),
+            ),
+            // Different regions
+            (
+                Some(us().region_code("CA").city("San Diego")),
+                Some(us().region_code("NY").city("New York")),
+            ),
+            // A single side with a region
+            (Some(us().region_code("CA")), Some(us())),
+            // Different countries
+            (Some(us()), Some(RecordSpec::new(Country::Canada))),
+            // A pseudo-country
+            (Some(RecordSpec::new(Country::AnonymousProxy)), Some(us())),
+            // Missing on one side, then the other, then both
+            (None, Some(us())),
+            (Some(us()), None),
+            (None, None),
+        ];
+
+        let mut v4 = DatabaseBuilder::new();
+        let mut v6 = DatabaseBuilder::new();
+        for (i, (v4_spec, v6_spec)) in specs.into_iter().enumerate() {
+            if let Some(spec) = v4_spec {
+                v4.insert(&format!("10.0.{}.0/24", i), spec).unwrap();
+            }
+            if let Some(spec) = v6_spec {
+                v6.insert(&format!("2a00:{:x}::/32", i), spec).unwrap();
+            }
+        }
+        (
+            GeoIpReader::from_reader(Cursor::new(v4.build())).unwrap(),
+            GeoIpReader::from_reader(Cursor::new(v6.build())).unwrap(),
+        )
+    }
+
+    fn pair(i: usize) -> (Ipv4Addr, Ipv6Addr) {
+        (
+            format!("10.0.{}.1", i).parse().unwrap(),
+            format!("2a00:{:x}::1", i).parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_classifications() {
+        let (mut v4, mut v6) = databases();
+        let pairs: Vec<_> = (0..11).map(pair).collect();
+
+        let report = compare_dual_stack(&mut v4, &mut v6, &pairs);
+        let classifications: Vec<Classification> = report
+            .pairs
+            .iter()
+            .map(|pair| pair.classification)
+            .collect();
+        assert_eq!(
+            classifications,
+            [
+                Classification::SameCity,
+                Classification::SameCity,
+                Classification::DifferentCity,
+                Classification::CityUnknown,
+                Classification::DifferentRegion,
+                Classification::RegionUnknown,
+                Classification::DifferentCountry,
+                Classification::PseudoCountry,
+                Classification::V4NotFound,
+                Classification::V6NotFound,
+                Classification::NeitherFound,
+            ]
+        );
+        assert_eq!(report.pairs[3].v4, pairs[3].0);
+        assert_eq!(report.pairs[3].v6, pairs[3].1);
+    }
+
+    #[test]
+    fn test_error() {
+        let record = Record::new(Country::UnitedStates);
+        let error: Result<Option<Record>, ()> = Err(());
+
+        // A failed lookup on either side wins over the outcome of the other one
+        for other in [Ok(None), Ok(Some(record)), Err(())] {
+            assert_eq!(Classification::of(&error, &other), Classification::Error);
+            assert_eq!(Classification::of(&other, &error), Classification::Error);
+        }
+        assert!(!Classification::Error.is_comparable());
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let (mut v4, mut v6) = databases();
+        let pairs: Vec<_> = (0..11).map(pair).collect();
+        let report = compare_dual_stack(&mut v4, &mut v6, &pairs);
+
+        assert_eq!(report.pairs.len(), 11);
+        assert_eq!(report.count(Classification::SameCity), 2);
+        assert_eq!(report.count(Classification::Error), 0);
+        assert_eq!(
+            Classification::ALL
+                .iter()
+                .map(|classification| report.count(*classification))
+                .sum::<usize>(),
+            report.pairs.len()
+        );
+
+        // 7 comparable pairs: 6 in the same country, 4 in the same region, 2 in the same city
+        assert_eq!(report.comparable(), 7);
+        assert_eq!(report.country_agreement(), Some(600.0 / 7.0));
+        assert_eq!(report.region_agreement(), Some(400.0 / 7.0));
+        assert_eq!(report.city_agreement(), Some(200.0 / 7.0));
+
+        // Without comparable pairs, there is no agreement to report
+        let report = compare_dual_stack(&mut v4, &mut v6, &pairs[7..]);
+        assert_eq!(report.comparable(), 0);
+        assert_eq!(report.country_agreement(), None);
+        assert_eq!(DualStackReport::default().city_agreement(), None);
+    }
+
+    #[test]
+    fn test_output() {
+        let (mut v4, mut v6) = databases();
+        let report = compare_dual_stack(&mut v4, &mut v6, &[pair(0), pair(2), pair(8)]);
+
+        let text = report.to_string();
+        assert!(text.starts_with("10.0.0.1 2a00::1 same_city\n"));
+        assert!(text.contains("\n  different_city: 1\n"));
+        assert!(text.contains("\ncomparable: 2\nsame country: 100.0%\n"));
+        assert!(text.ends_with("same city: 50.0%\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["total"], 3);
+        assert_eq!(json["comparable"], 2);
+        assert_eq!(json["city_agreement"], 50.0);
+        assert_eq!(json["counts"]["v4_not_found"], 1);
+        assert_eq!(json["counts"].as_object().unwrap().len(), 11);
+        assert_eq!(json["pairs"][1]["classification"], "different_city");
+        assert_eq!(json["pairs"][2]["v6"], "2a00:8::1");
+
+        let empty: serde_json::Value =
+            serde_json::from_str(&DualStackReport::default().to_json()).unwrap();
+        assert_eq!(empty["country_agreement"], serde_json::Value::Null);
+    }
+
+    /// A city database of four records over eleven networks, `1.2.0.0/16` splitting
+    /// `1.0.0.0/8` into eight networks.
+    fn stats_database() -> Reader {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert(
+                "1.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates)
+                    .city("San Diego")
+                    .postal_code("92109")
+                    .coordinates(32.75, -117.25),
+            )
+            .unwrap()
+            .insert("1.2.0.0/16", RecordSpec::new(Country::Germany))
+            .unwrap()
+            .insert("2.0.0.0/8", RecordSpec::new(Country::France).city("Paris"))
+            .unwrap()
+            .insert("3.0.0.0/8", RecordSpec::new(Country::AnonymousProxy))
+            .unwrap();
+        GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap()
+    }
+
+    #[test]
+    fn test_database_stats() {
+        let mut reader = stats_database();
+        let stats = database_stats(&mut reader, SampleSpec::Full).unwrap();
+
+        assert_eq!(stats.networks, 11);
+        assert_eq!(stats.records, 4);
+        assert_eq!(stats.sampled, None);
+        assert_eq!(stats.read(), 4);
+        assert!(stats.min_offset < stats.max_offset);
+        assert_eq!(stats.with_city, 2);
+        assert_eq!(stats.with_postal_code, 1);
+        assert_eq!(stats.with_coordinates, 1);
+        assert_eq!(stats.country(Country::Germany), 1);
+        assert_eq!(stats.country(Country::Canada), 0);
+        assert_eq!(stats.countries().len(), 4);
+        assert_eq!(stats.continent(Continent::Europe), 2);
+        assert_eq!(stats.continent(Continent::NorthAmerica), 1);
+        assert_eq!(stats.continent(Continent::Asia), 0);
+        assert_eq!(stats.percentage(stats.with_city), Some(50.0));
+        assert_eq!(stats.estimate(stats.with_city), 2);
+
+        // A sample at least as large as the database reads every record
+        let sample = SampleSpec::Sample { size: 4, seed: 1 };
+        assert_eq!(database_stats(&mut reader, sample).unwrap(), stats);
+    }
+
+    #[test]
+    fn test_sampled_database_stats() {
+        let countries = [Country::UnitedStates, Country::Germany, Country::France];
+        let mut builder = DatabaseBuilder::new();
+        for i in 0..400 {
+            let mut spec = RecordSpec::new(countries[i % 3]);
+            if i % 4 == 0 {
+                spec = spec.city("City");
+            }
+            builder
+                .insert(&format!("10.{}.{}.0/24", i / 256, i % 256), spec)
+                .unwrap();
+        }
+        let mut reader = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let full = database_stats(&mut reader, SampleSpec::Full).unwrap();
+        assert_eq!(full.records, 400);
+        assert_eq!(full.with_city, 100);
+
+        let sample = SampleSpec::Sample { size: 200, seed: 7 };
+        let sampled = database_stats(&mut reader, sample).unwrap();
+        assert_eq!(sampled.networks, full.networks);
+        assert_eq!(sampled.records, 400);
+        assert_eq!(sampled.sampled, Some(200));
+        assert_eq!(sampled.min_offset, full.min_offset);
+        assert_eq!(sampled.max_offset, full.max_offset);
+        assert_eq!(sampled.continents.iter().sum::<u64>(), 200);
+
+        // The estimates are close to the exact counts, and the same seed draws the same sample
+        let close = |sampled_count: u64, count: u64| {
+            (sampled.percentage(sampled_count).unwrap() - full.percentage(count).unwrap()).abs()
+                < 10.0
+        };
+        assert!(close(sampled.with_city, full.with_city));
+        for country in countries {
+            assert!(close(sampled.country(country), full.country(country)));
+        }
+        assert!(sampled.estimate(sampled.with_city).abs_diff(100) < 40);
+        assert_eq!(database_stats(&mut reader, sample).unwrap(), sampled);
+    }
+
+    #[test]
+    fn test_database_stats_output() {
+        let mut reader = stats_database();
+        let stats = database_stats(&mut reader, SampleSpec::Full).unwrap();
+
+        let text = stats.to_string();
+        assert!(text.starts_with("networks: 11\nrecords: 4\noffsets: "));
+        assert!(!text.contains("sampled"));
+        assert!(text.contains("\n  city                      2   50.0%\n"));
+        assert!(text.contains("\n  EU                        2   50.0%\n"));
+        assert!(text.contains("\ncountries:\n  A1 "));
+
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["networks"], 11);
+        assert_eq!(json["sampled"], serde_json::Value::Null);
+        assert_eq!(json["with_postal_code"], 1);
+        assert_eq!(json["continents"]["EU"], 2);
+        assert_eq!(json["continents"].as_object().unwrap().len(), 7);
+        assert_eq!(json["countries"]["FR"], 1);
+    }
+}
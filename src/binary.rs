@@ -0,0 +1,378 @@
+//! A compact, length-prefixed binary format of the rows of `ipcap batch --format binary`, for
+//! feeding lookups to another program without parsing JSON.
+//!
+//! All the integers are little-endian, and a string is its `u32` byte length followed by its
+//! UTF-8 bytes. A stream starts with a header:
+//!
+//! | Bytes | Content                                        |
+//! |-------|------------------------------------------------|
+//! | 4     | The magic `IPCB`                               |
+//! | 1     | The version of the format, `1`                 |
+//! | 2     | The number of fields, then the name of each    |
+//!
+//! Then comes one frame per row: its `u32` payload length, then the payload. The payload starts
+//! with a kind byte and the address, as a string, hashed with `--hash-ip`:
+//!
+//! * `0`, a record: for each field of the header, a presence byte, `0` for an empty field or
+//!   `1` followed by its value as a string, then a presence byte, followed by the `u64` count of
+//!   the address if it is `1`.
+//! * `1`, a failed lookup: the `code` of the error, then its message, as strings.
+//!
+//! The length prefix of the frames lets a reader skip the rows it does not need, and a later
+//! version append data to the payloads without breaking it.
+//!
+//! # Examples
+//!
+//! Decoding the output of `ipcap batch --format binary`, e.g. read from its stdout:
+//!
+//! ```
+//! use ipcap::binary::{write_header, BinaryReader, BinaryRow};
+//!
+//! // The output of `echo 108.95.4.105 | ipcap batch --format binary --append country_code,city`
+//! let mut output = Vec::new();
+//! write_header(&mut output, &["country_code", "city"]).unwrap();
+//! let row = BinaryRow::Record {
+//!     ip: "108.95.4.105".to_string(),
+//!     values: vec![Some("US".to_string()), Some("San Diego".to_string())],
+//!     count: None,
+//! };
+//! output.extend(row.encode());
+//!
+//! let mut reader = BinaryReader::new(&output[..]).unwrap();
+//! assert_eq!(reader.fields(), ["country_code", "city"]);
+//! for row in reader.by_ref() {
+//!     match row.unwrap() {
+//!         BinaryRow::Record { ip, values, .. } => {
+//!             assert_eq!(ip, "108.95.4.105");
+//!             assert_eq!(values[1].as_deref(), Some("San Diego"));
+//!         }
+//!         BinaryRow::Error { ip, code, .. } => panic!("{}: {}", ip, code),
+//!     }
+//! }
+//! ```
+
+use crate::errors::GeoIpReaderError;
+use std::io::{self, Read, Write};
+
+/// The magic bytes starting a stream.
+pub const MAGIC: [u8; 4] = *b"IPCB";
+
+/// The version of the format written by this crate.
+pub const VERSION: u8 = 1;
+
+/// The kind byte of a record.
+const KIND_RECORD: u8 = 0;
+
+/// The kind byte of a failed lookup.
+const KIND_ERROR: u8 = 1;
+
+/// A row of a binary stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryRow {
+    /// The fields of the record of an address, in the order of the header.
+    Record {
+        ip: String,
+        values: Vec<Option<String>>,
+        /// The number of occurrences of the address, if counted.
+        count: Option<u64>,
+    },
+    /// A failed lookup, e.g. of a reserved address.
+    Error {
+        ip: String,
+        /// The `GeoIpReaderError::code` of the error.
+        code: String,
+        message: String,
+    },
+}
+
+impl BinaryRow {
+    /// Constructs the row of a failed lookup of the given input.
+    pub fn error(input: &str, err: &GeoIpReaderError) -> Self {
+        BinaryRow::Error {
+            ip: input.to_string(),
+            code: err.code().to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    /// Encodes the row as a frame: its payload length, then its payload.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        match self {
+            BinaryRow::Record { ip, values, count } => {
+                payload.push(KIND_RECORD);
+                put_string(&mut payload, ip);
+                for value in values {
+                    match value {
+                        Some(value) => {
+                            payload.push(1);
+                            put_string(&mut payload, value);
+                        }
+                        None => payload.push(0),
+                    }
+                }
+                match count {
+                    Some(count) => {
+                        payload.push(1);
+                        payload.extend(count.to_le_bytes());
+                    }
+                    None => payload.push(0),
+                }
+            }
+            BinaryRow::Error { ip, code, message } => {
+                payload.push(KIND_ERROR);
+                put_string(&mut payload, ip);
+                put_string(&mut payload, code);
+                put_string(&mut payload, message);
+            }
+        }
+
+        let mut frame = Vec::with_capacity(payload.len() + 4);
+        frame.extend((payload.len() as u32).to_le_bytes());
+        frame.extend(payload);
+        frame
+    }
+}
+
+/// Writes the header of a stream of rows of the given fields.
+///
+/// # Errors
+///
+/// Returns the errors of the writer, or an `io::ErrorKind::InvalidInput` error if there are
+/// more than 65535 fields.
+pub fn write_header(out: &mut impl Write, fields: &[&str]) -> io::Result<()> {
+    let count = u16::try_from(fields.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many fields"))?;
+    let mut header = MAGIC.to_vec();
+    header.push(VERSION);
+    header.extend(count.to_le_bytes());
+    for field in fields {
+        put_string(&mut header, field);
+    }
+    out.write_all(&header)
+}
+
+/// A decoder of a binary stream, iterating over its rows.
+///
+/// # Errors
+///
+/// The rows are `io::ErrorKind::InvalidData` errors if the stream is truncated or malformed.
+#[derive(Debug)]
+pub struct BinaryReader<R> {
+    reader: R,
+    fields: Vec<String>,
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// Reads the header of a stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::ErrorKind::InvalidData` error if the stream does not start with the
+    /// magic bytes, or is of another version.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not an ipcap binary stream"));
+        }
+        let version = read_array::<1>(&mut reader)?[0];
+        if version != VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported version {} of the binary format",
+                version
+            )));
+        }
+        let count = u16::from_le_bytes(read_array(&mut reader)?);
+        let fields = (0..count)
+            .map(|_| read_string(&mut reader))
+            .collect::<io::Result<_>>()?;
+        Ok(BinaryReader { reader, fields })
+    }
+
+    /// The names of the fields of the records, in order.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Decodes the payload of a frame.
+    fn decode(&self, payload: &[u8]) -> io::Result<BinaryRow> {
+        let mut payload = payload;
+        let kind = read_array::<1>(&mut payload)?[0];
+        let ip = read_string(&mut payload)?;
+        match kind {
+            KIND_RECORD => {
+                let values = self
+                    .fields
+                    .iter()
+                    .map(|_| match read_array::<1>(&mut payload)?[0] {
+                        0 => Ok(None),
+                        _ => read_string(&mut payload).map(Some),
+                    })
+                    .collect::<io::Result<_>>()?;
+                let count = match read_array::<1>(&mut payload)?[0] {
+                    0 => None,
+                    _ => Some(u64::from_le_bytes(read_array(&mut payload)?)),
+                };
+                Ok(BinaryRow::Record { ip, values, count })
+            }
+            KIND_ERROR => Ok(BinaryRow::Error {
+                ip,
+                code: read_string(&mut payload)?,
+                message: read_string(&mut payload)?,
+            }),
+            kind => Err(invalid_data(&format!("unknown row kind {}", kind))),
+        }
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = io::Result<BinaryRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut length = [0u8; 4];
+        // The end of the stream, between two frames
+        match self.reader.read(&mut length[..1]) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(err) => return Some(Err(err)),
+        }
+        let frame = self
+            .reader
+            .read_exact(&mut length[1..])
+            .and_then(|()| {
+                let mut payload = vec![0u8; u32::from_le_bytes(length) as usize];
+                self.reader.read_exact(&mut payload)?;
+                Ok(payload)
+            })
+            .map_err(truncated);
+        Some(frame.and_then(|payload| self.decode(&payload)))
+    }
+}
+
+/// Appends a string, prefixed with its length.
+fn put_string(out: &mut Vec<u8>, value: &str) {
+    out.extend((value.len() as u32).to_le_bytes());
+    out.extend(value.as_bytes());
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let length = u32::from_le_bytes(read_array(reader)?) as usize;
+    let mut bytes = Vec::new();
+    reader
+        .take(length as u64)
+        .read_to_end(&mut bytes)
+        .map_err(truncated)?;
+    if bytes.len() != length {
+        return Err(truncated(io::ErrorKind::UnexpectedEof.into()));
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("a string is not valid UTF-8"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reports the end of the data in the middle of a frame as a malformed stream.
+fn truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => invalid_data("truncated binary stream"),
+        _ => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<BinaryRow> {
+        vec![
+            BinaryRow::Record {
+                ip: "108.95.4.105".to_string(),
+                values: vec![Some("US".to_string()), None],
+                count: Some(3),
+            },
+            BinaryRow::error(
+                "10.0.0.1",
+                &GeoIpReaderError::ReservedAddress("10.0.0.1".to_string()),
+            ),
+            BinaryRow::Record {
+                ip: "2a08:1450:300f:900::1003".to_string(),
+                values: vec![Some("GB".to_string()), Some("Zürich".to_string())],
+                count: None,
+            },
+        ]
+    }
+
+    fn stream() -> Vec<u8> {
+        let mut stream = Vec::new();
+        write_header(&mut stream, &["country_code", "city"]).unwrap();
+        for row in rows() {
+            stream.extend(row.encode());
+        }
+        stream
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let stream = stream();
+        let reader = BinaryReader::new(&stream[..]).unwrap();
+        assert_eq!(reader.fields(), ["country_code", "city"]);
+        let decoded: Vec<BinaryRow> = reader.collect::<io::Result<_>>().unwrap();
+        assert_eq!(decoded, rows());
+
+        match &decoded[1] {
+            BinaryRow::Error { code, message, .. } => {
+                assert_eq!(code, "reserved_address");
+                assert_eq!(message, "Address of a reserved range: 10.0.0.1");
+            }
+            row => panic!("{:?}", row),
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut header = Vec::new();
+        write_header(&mut header, &["city"]).unwrap();
+        assert_eq!(header, b"IPCB\x01\x01\x00\x04\x00\x00\x00city");
+
+        let row = BinaryRow::Record {
+            ip: "1.2.3.4".to_string(),
+            values: vec![None],
+            count: Some(2),
+        };
+        let mut expected = vec![22, 0, 0, 0, 0, 7, 0, 0, 0];
+        expected.extend(b"1.2.3.4");
+        expected.extend([0, 1, 2, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(row.encode(), expected);
+    }
+
+    #[test]
+    fn test_malformed_streams() {
+        let kind = |result: io::Result<BinaryReader<&[u8]>>| result.unwrap_err().kind();
+        assert_eq!(
+            kind(BinaryReader::new(&b"{\"ip\": 1}"[..])),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind(BinaryReader::new(&b"IPCB\x02\x00\x00"[..])),
+            io::ErrorKind::InvalidData
+        );
+
+        // A frame cut short
+        let stream = stream();
+        let mut reader = BinaryReader::new(&stream[..stream.len() - 3]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::Record;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::net::IpAddr;
+use std::vec;
+
+/// The number of distinct addresses `unique_lookup` counts exactly before switching to a
+/// probabilistic filter.
+pub const DEFAULT_EXACT_LIMIT: usize = 1 << 20;
+
+/// The number of fingerprints per bucket of `SeenFilter`.
+const WAYS: usize = 4;
+
+/// A distinct address yielded by `UniqueLookup` together with its lookup result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueRow<T> {
+    pub ip: IpAddr,
+    /// The number of occurrences of `ip` in the input, `None` once the lookup switched to the
+    /// probabilistic filter.
+    pub count: Option<u64>,
+    pub value: T,
+}
+
+/// A fixed-size table of address fingerprints.
+///
+/// A full bucket evicts its oldest fingerprint, so an address seen long ago may be reported as
+/// new again: duplicates can slip through, but a distinct address is never dropped (barring a
+/// 64 bits fingerprint collision).
+#[derive(Debug)]
+struct SeenFilter {
+    slots: Vec<u64>,
+}
+
+impl SeenFilter {
+    fn new(capacity: usize) -> Self {
+        let buckets = (capacity / WAYS).max(1).next_power_of_two();
+        SeenFilter {
+            slots: vec![0; buckets * WAYS],
+        }
+    }
+
+    /// Records the address and returns whether it was not seen before.
+    fn insert(&mut self, ip: IpAddr) -> bool {
+        let mut hasher = DefaultHasher::new();
+        ip.hash(&mut hasher);
+        // 0 marks an empty slot
+        let fingerprint = hasher.finish().max(1);
+
+        let buckets = self.slots.len() / WAYS;
+        let start = (fingerprint as usize & (buckets - 1)) * WAYS;
+        let bucket = &mut self.slots[start..start + WAYS];
+        if bucket.contains(&fingerprint) {
+            return false;
+        }
+        bucket.rotate_right(1);
+        bucket[0] = fingerprint;
+        true
+    }
+}
+
+/// `UniqueLookup` is the iterator returned by `unique_lookup`.
+///
+/// While the number of distinct addresses stays within the exact limit, the rows are counted in
+/// a `HashMap` and only yielded once the input is exhausted. Past the limit, the rows counted so
+/// far are yielded without their count, and the remaining input is deduplicated by a
+/// probabilistic filter, yielding each new address as soon as it is read.
+#[derive(Debug)]
+pub struct UniqueLookup<I, F> {
+    ips: I,
+    lookup: F,
+    limit: usize,
+    counts: HashMap<IpAddr, u64>,
+    order: Vec<IpAddr>,
+    filter: Option<SeenFilter>,
+    pending: vec::IntoIter<(IpAddr, Option<u64>)>,
+}
+
+impl<I, F> UniqueLookup<I, F> {
+    /// Sets the number of distinct addresses counted exactly, `DEFAULT_EXACT_LIMIT` by default.
+    pub fn exact_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Returns whether the addresses read so far were deduplicated exactly, i.e. whether the
+    /// rows carry their count.
+    pub fn is_exact(&self) -> bool {
+        self.filter.is_none()
+    }
+
+    /// Replaces the exact counts with a probabilistic filter holding the addresses seen so far
+    /// and the new address `ip`, and schedules their rows, without count.
+    fn switch_to_filter(&mut self, ip: IpAddr) {
+        let mut order = mem::take(&mut self.order);
+        order.push(ip);
+
+        let mut filter = SeenFilter::new(self.limit);
+        for ip in &order {
+            filter.insert(*ip);
+        }
+        self.counts = HashMap::new();
+        self.filter = Some(filter);
+        self.pending = order
+            .into_iter()
+            .map(|ip| (ip, None))
+            .collect::<Vec<_>>()
+            .into_iter();
+    }
+}
+
+impl<I, F, T, E> Iterator for UniqueLookup<I, F>
+where
+    I: Iterator<Item = IpAddr>,
+    F: FnMut(IpAddr) -> Result<T, E>,
+{
+    type Item = Result<UniqueRow<T>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((ip, count)) = self.pending.next() {
+                return Some((self.lookup)(ip).map(|value| UniqueRow { ip, count, value }));
+            }
+
+            let Some(ip) = self.ips.next() else {
+                let counts = mem::take(&mut self.counts);
+                self.pending = mem::take(&mut self.order)
+                    .into_iter()
+                    .map(|ip| (ip, counts.get(&ip).copied()))
+                    .collect::<Vec<_>>()
+                    .into_iter();
+                return self.pending.next().map(|(ip, count)| {
+                    (self.lookup)(ip).map(|value| UniqueRow { ip, count, value })
+                });
+            };
+
+            match &mut self.filter {
+                Some(filter) => {
+                    if filter.insert(ip) {
+                        return Some((self.lookup)(ip).map(|value| UniqueRow {
+                            ip,
+                            count: None,
+                            value,
+                        }));
+                    }
+                }
+                None => {
+                    if let Some(count) = self.counts.get_mut(&ip) {
+                        *count += 1;
+                    } else if self.counts.len() < self.limit {
+                        self.counts.insert(ip, 1);
+                        self.order.push(ip);
+                    } else {
+                        self.switch_to_filter(ip);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Looks up each distinct address of the input once, in order of first occurrence.
+///
+/// # Arguments
+///
+/// * `ips` - The addresses to look up, possibly repeated.
+/// * `lookup` - The lookup performed for each distinct address, e.g. `GeoIpReader::find_record`.
+///
+/// # Returns
+///
+/// (`UniqueLookup<I::IntoIter, F>`): An iterator over one `UniqueRow` per distinct address. The
+/// memory used for deduplication is bounded by `UniqueLookup::exact_limit`: past this number of
+/// distinct addresses, the counts are dropped and a duplicate may rarely be yielded twice.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::bulk::unique_lookup;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let ips = ["108.95.4.105", "10.0.0.1", "108.95.4.105"].map(|ip| ip.parse().unwrap());
+///
+/// let rows: Vec<_> = unique_lookup(ips, |ip| geo_ip.find_record(&ip.to_string()))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+///
+/// assert_eq!(rows.len(), 2);
+/// assert_eq!(rows[0].count, Some(2));
+/// assert_eq!(rows[0].value.as_ref().unwrap().city(), Some("San Diego"));
+/// assert_eq!(rows[1].value, None);
+/// ```
+pub fn unique_lookup<I, F, T, E>(ips: I, lookup: F) -> UniqueLookup<I::IntoIter, F>
+where
+    I: IntoIterator<Item = IpAddr>,
+    F: FnMut(IpAddr) -> Result<T, E>,
+{
+    UniqueLookup {
+        ips: ips.into_iter(),
+        lookup,
+        limit: DEFAULT_EXACT_LIMIT,
+        counts: HashMap::new(),
+        order: Vec::new(),
+        filter: None,
+        pending: Vec::new().into_iter(),
+    }
+}
+
+/// The result of the lookup of an address in a batch, paired with the address.
+pub type LookupItem = (IpAddr, Result<Record<'static>, GeoIpReaderError>);
+
+/// A failed lookup of a batch, paired with the address.
+pub type LookupError = (IpAddr, GeoIpReaderError);
+
+/// `LookupResults` holds the results of a batch lookup, e.g. `GeoIpReader::get_records`, in
+/// the order of the input, with adapters into the shapes callers usually want.
+///
+/// The results are produced as they are consumed: the lookups of a lazy batch are only
+/// performed when an adapter asks for them, so `first_error` stops at the first failed lookup.
+/// An address that is not in the database is a `GeoIpReaderError::AddressNotFound` error, as
+/// with `GeoIpReader::get_record`.
+///
+/// A collected batch, e.g. a `Vec<LookupItem>` or the items of `GeoIpStreamExt::geolocate`
+/// over a stream of `IpAddr`s, converts into a `LookupResults` with `collect`.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::errors::GeoIpReaderError;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+/// use std::net::IpAddr;
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let ips: Vec<IpAddr> = ["108.95.4.105", "10.0.0.1"].map(|ip| ip.parse().unwrap()).to_vec();
+///
+/// let (found, errors) = geo_ip.get_records(ips.clone()).partition();
+/// assert_eq!(found[0].1.city(), Some("San Diego"));
+/// assert!(matches!(errors[0].1, GeoIpReaderError::AddressNotFound(_)));
+///
+/// let records = geo_ip.get_records(ips).into_map();
+/// assert_eq!(records.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct LookupResults<I> {
+    results: I,
+    error: Option<GeoIpReaderError>,
+}
+
+impl<I> LookupResults<I>
+where
+    I: Iterator<Item = LookupItem>,
+{
+    /// Wraps the results of a batch, in the order of the input.
+    pub fn new<T>(results: T) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        LookupResults {
+            results: results.into_iter(),
+            error: None,
+        }
+    }
+
+    /// Collects the records of the found addresses by address, dropping the errors.
+    pub fn into_map(self) -> HashMap<IpAddr, Record<'static>> {
+        self.ok_only().collect()
+    }
+
+    /// Splits the results into the records of the found addresses and the errors, each in the
+    /// order of the input.
+    pub fn partition(self) -> (Vec<(IpAddr, Record<'static>)>, Vec<LookupError>) {
+        let mut found = Vec::new();
+        let mut errors = Vec::new();
+        for (ip, result) in self.results {
+            match result {
+                Ok(record) => found.push((ip, record)),
+                Err(err) => errors.push((ip, err)),
+            }
+        }
+        (found, errors)
+    }
+
+    /// Iterates over the records of the found addresses in the order of the input, skipping
+    /// the errors.
+    pub fn ok_only(self) -> impl Iterator<Item = (IpAddr, Record<'static>)> {
+        self.results
+            .filter_map(|(ip, result)| result.ok().map(|record| (ip, record)))
+    }
+
+    /// Returns the first error of the batch, consuming the results up to it only.
+    ///
+    /// The error is kept, so later calls return it again without consuming more results.
+    pub fn first_error(&mut self) -> Option<&GeoIpReaderError> {
+        if self.error.is_none() {
+            self.error = self.results.find_map(|(_, result)| result.err());
+        }
+        self.error.as_ref()
+    }
+}
+
+impl<I> IntoIterator for LookupResults<I>
+where
+    I: Iterator<Item = LookupItem>,
+{
+    type Item = LookupItem;
+    type IntoIter = I;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results
+    }
+}
+
+impl FromIterator<LookupItem> for LookupResults<vec::IntoIter<LookupItem>> {
+    fn from_iter<T: IntoIterator<Item = LookupItem>>(results: T) -> Self {
+        LookupResults::new(results.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl Default for LookupResults<vec::IntoIter<LookupItem>> {
+    fn default() -> Self {
+        LookupResults::new(Vec::new())
+    }
+}
+
+impl Extend<LookupItem> for LookupResults<vec::IntoIter<LookupItem>> {
+    fn extend<T: IntoIterator<Item = LookupItem>>(&mut self, results: T) {
+        let mut collected: Vec<_> = mem::take(&mut self.results).collect();
+        collected.extend(results);
+        self.results = collected.into_iter();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use std::convert::Infallible;
+    use std::net::Ipv4Addr;
+
+    fn ip(n: u32) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::from(n))
+    }
+
+    fn identity(ip: IpAddr) -> Result<IpAddr, Infallible> {
+        Ok(ip)
+    }
+
+    #[test]
+    fn test_exact_counts() {
+        let ips = [1, 2, 1, 3, 1, 2].map(ip);
+
+        let rows: Vec<_> = unique_lookup(ips, identity).map(Result::unwrap).collect();
+
+        let counts: Vec<_> = rows.iter().map(|row| (row.ip, row.count)).collect();
+        assert_eq!(
+            counts,
+            vec![(ip(1), Some(3)), (ip(2), Some(2)), (ip(3), Some(1))]
+        );
+        assert!(rows.iter().all(|row| row.value == row.ip));
+    }
+
+    #[test]
+    fn test_rows_match_distinct_inputs_in_exact_mode() {
+        let ips: Vec<IpAddr> = (0..10_000).map(|n| ip(n % 1_234)).collect();
+
+        let mut lookup = unique_lookup(ips, identity).exact_limit(1_234);
+        let rows: Vec<_> = lookup.by_ref().map(Result::unwrap).collect();
+
+        assert!(lookup.is_exact());
+        assert_eq!(rows.len(), 1_234);
+        assert_eq!(
+            rows.iter().map(|row| row.count.unwrap()).sum::<u64>(),
+            10_000
+        );
+    }
+
+    #[test]
+    fn test_switch_at_exact_limit() {
+        let ips = [1, 2, 1, 3, 2, 4, 3].map(ip);
+
+        let mut lookup = unique_lookup(ips, identity).exact_limit(2);
+        let first = lookup.next().unwrap().unwrap();
+        assert!(!lookup.is_exact());
+        let rows: Vec<_> = std::iter::once(first)
+            .chain(lookup.map(Result::unwrap))
+            .collect();
+
+        let rows: Vec<_> = rows.iter().map(|row| (row.ip, row.count)).collect();
+        assert_eq!(
+            rows,
+            vec![(ip(1), None), (ip(2), None), (ip(3), None), (ip(4), None)]
+        );
+    }
+
+    #[test]
+    fn test_stays_exact_at_exact_limit() {
+        let ips = [1, 2, 1, 2].map(ip);
+
+        let mut lookup = unique_lookup(ips, identity).exact_limit(2);
+        let rows: Vec<_> = lookup.by_ref().map(Result::unwrap).collect();
+
+        assert!(lookup.is_exact());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].count, Some(2));
+    }
+
+    #[test]
+    fn test_lookup_error() {
+        let ips = [1, 2].map(ip);
+
+        let results: Vec<_> = unique_lookup(ips, |ip| {
+            if ip == self::ip(2) {
+                Err("not found")
+            } else {
+                Ok(())
+            }
+        })
+        .collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1], Err("not found"));
+    }
+
+    #[test]
+    fn test_seen_filter_deduplicates_within_capacity() {
+        let mut filter = SeenFilter::new(64);
+
+        assert!(filter.insert(ip(1)));
+        assert!(!filter.insert(ip(1)));
+        assert!((2..10).all(|n| filter.insert(ip(n))));
+        assert!((2..10).all(|n| !filter.insert(ip(n))));
+    }
+
+    /// The results of a batch of 5 addresses, the 2nd not being found and the 4th failing.
+    fn batch() -> Vec<LookupItem> {
+        (1..=5)
+            .map(|n| {
+                let result = match n {
+                    2 => Err(GeoIpReaderError::AddressNotFound(ip(n).to_string())),
+                    4 => Err(GeoIpReaderError::CorruptDatabase),
+                    _ => Ok(Record::new(Country::from_buffer(n as u8).unwrap())),
+                };
+                (ip(n), result)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lookup_results_into_map() {
+        let records = LookupResults::new(batch()).into_map();
+
+        assert_eq!(records.len(), 3);
+        assert!(!records.contains_key(&ip(2)));
+        assert_eq!(records[&ip(3)].country, Country::from_buffer(3).unwrap());
+    }
+
+    #[test]
+    fn test_lookup_results_partition() {
+        let (found, errors) = LookupResults::new(batch()).partition();
+
+        let found: Vec<IpAddr> = found.into_iter().map(|(ip, _)| ip).collect();
+        assert_eq!(found, [ip(1), ip(3), ip(5)]);
+        // Addresses that are not found are errors, in the order of the input
+        assert!(matches!(
+            &errors[..],
+            [
+                (first, GeoIpReaderError::AddressNotFound(_)),
+                (second, GeoIpReaderError::CorruptDatabase)
+            ] if *first == ip(2) && *second == ip(4)
+        ));
+    }
+
+    #[test]
+    fn test_lookup_results_ok_only() {
+        let ips: Vec<IpAddr> = LookupResults::new(batch())
+            .ok_only()
+            .map(|(ip, _)| ip)
+            .collect();
+
+        assert_eq!(ips, [ip(1), ip(3), ip(5)]);
+    }
+
+    #[test]
+    fn test_lookup_results_first_error() {
+        let mut consumed = 0;
+        let mut results = LookupResults::new(batch().into_iter().inspect(|_| consumed += 1));
+        assert!(matches!(
+            results.first_error(),
+            Some(GeoIpReaderError::AddressNotFound(_))
+        ));
+        assert!(matches!(
+            results.first_error(),
+            Some(GeoIpReaderError::AddressNotFound(_))
+        ));
+        drop(results);
+        assert_eq!(consumed, 2);
+
+        let mut results = LookupResults::new(batch().into_iter().filter(|(_, r)| r.is_ok()));
+        assert!(results.first_error().is_none());
+    }
+
+    #[test]
+    fn test_lookup_results_into_iter_and_collect() {
+        let ips: Vec<IpAddr> = LookupResults::new(batch())
+            .into_iter()
+            .map(|(ip, _)| ip)
+            .collect();
+        assert_eq!(ips, (1..=5).map(ip).collect::<Vec<_>>());
+
+        let mut collected: LookupResults<_> = batch().into_iter().take(2).collect();
+        collected.extend(batch().into_iter().skip(2));
+        let (found, errors) = collected.partition();
+        assert_eq!((found.len(), errors.len()), (3, 2));
+        assert!(LookupResults::default().first_error().is_none());
+    }
+}
@@ -0,0 +1,529 @@
+//! A cache of looked up records for long-running processes sharing a reader, e.g. a daemon
+//! answering the lookups of many clients, where the same networks are looked up over and over.
+//!
+//! The records are cached by the network they were matched on, the address masked to the
+//! `netmask` of the record, so that one lookup answers every address of its network. The
+//! entries belong to a generation of the database, bumped on each reload, so that a reload
+//! invalidates all of them at once without walking the cache.
+
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::Record;
+use crate::global::IpCap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+
+/// A network of a generation of the database: the generation, whether the network is IPv6, the
+/// masked address and the length of the prefix.
+type Key = (u64, bool, u128, u8);
+
+/// The counters of a `TtlPrefixCache`, each lookup being exactly one of a hit, a miss or a stale
+/// entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// The lookups answered by an entry.
+    pub hits: u64,
+    /// The lookups without an entry of the current generation.
+    pub misses: u64,
+    /// The lookups whose entry had outlived the time to live, and was dropped.
+    pub stale: u64,
+    /// The number of entries held, including those of former generations not evicted yet.
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    record: Record<'static>,
+    inserted: Instant,
+    /// The order of the insertion, telling the entry apart from a later one of the same key in
+    /// the eviction queue.
+    sequence: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<Key, Entry>,
+    /// The keys in the order of their insertion, the oldest being evicted first.
+    queue: VecDeque<(Key, u64)>,
+    /// The prefix lengths of the networks inserted, by family, probed from the longest.
+    prefixes: BTreeSet<(bool, u8)>,
+    sequence: u64,
+}
+
+/// A bounded cache of records keyed by the network they were matched on and the generation of
+/// the database they were read from.
+///
+/// The cache holds at most `capacity` entries, evicting the oldest inserted first, and drops
+/// the entries older than the time to live, if any, when they are looked up or purged.
+///
+/// A lookup that raced with a reload must not cache a record of the former database under the
+/// new generation: `get_or_insert_with` reads the generation before the lookup, and the reload
+/// bumps it with `advance_generation` only once the new database is in place, so that such a
+/// record lands in the former generation, which is never looked up again.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::cache::TtlPrefixCache;
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::Record;
+/// use std::time::Duration;
+///
+/// let cache = TtlPrefixCache::new(1024).ttl(Duration::from_secs(60));
+/// let lookup = || -> Result<_, ()> { Ok(Record::new(Country::France).with_netmask(8, 32)) };
+///
+/// // The first address of the network is looked up, the others are answered by the cache
+/// cache.get_or_insert_with("2.0.0.1".parse().unwrap(), lookup).unwrap();
+/// let record = cache.get("2.255.0.1".parse().unwrap()).unwrap();
+/// assert_eq!(record.country, Country::France);
+/// assert_eq!(cache.get("3.0.0.1".parse().unwrap()), None);
+///
+/// // A reload invalidates every entry
+/// cache.advance_generation();
+/// assert_eq!(cache.get("2.0.0.1".parse().unwrap()), None);
+/// ```
+pub struct TtlPrefixCache {
+    entries: Mutex<Entries>,
+    generation: AtomicU64,
+    capacity: usize,
+    ttl: Option<Duration>,
+    now: Arc<dyn Fn() -> Instant + Send + Sync>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    stale: AtomicU64,
+}
+
+impl std::fmt::Debug for TtlPrefixCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TtlPrefixCache")
+            .field("generation", &self.generation())
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl TtlPrefixCache {
+    /// Constructs a cache of at most `capacity` entries, without a time to live. A capacity of
+    /// `0` disables the cache, every lookup being a miss.
+    pub fn new(capacity: usize) -> Self {
+        TtlPrefixCache {
+            entries: Mutex::new(Entries::default()),
+            generation: AtomicU64::new(0),
+            capacity,
+            ttl: None,
+            now: Arc::new(Instant::now),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stale: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the time to live of the entries, after which they are looked up again.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the clock the age of the entries is measured with, `Instant::now` by default, e.g.
+    /// a mocked one in tests.
+    pub fn clock(mut self, now: impl Fn() -> Instant + Send + Sync + 'static) -> Self {
+        self.now = Arc::new(now);
+        self
+    }
+
+    /// The generation of the database the entries are currently looked up in.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Starts a new generation, invalidating every entry, and returns it. This is to be called
+    /// once a reloaded database is in place, not before: a lookup of the former database
+    /// starting in between would cache its record in the new generation.
+    pub fn advance_generation(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// Returns the record cached for the network of an address in the current generation, if
+    /// any and not older than the time to live.
+    pub fn get(&self, ip: IpAddr) -> Option<Record<'static>> {
+        if self.capacity == 0 {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let generation = self.generation();
+        let (v6, bits) = address_bits(ip);
+        let mut entries = self.lock();
+        let prefixes: Vec<u8> = entries
+            .prefixes
+            .range((v6, 0)..=(v6, u8::MAX))
+            .rev()
+            .map(|(_, prefix)| *prefix)
+            .collect();
+        for prefix in prefixes {
+            let key = (generation, v6, mask(bits, v6, prefix), prefix);
+            let Some(entry) = entries.map.get(&key) else {
+                continue;
+            };
+            if self.is_expired(entry) {
+                entries.map.remove(&key);
+                self.stale.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(entry.record.clone());
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    /// Caches a record under the network it was matched on, in the given generation, the one
+    /// read before looking the record up, evicting the oldest entries beyond the capacity.
+    pub fn insert(&self, generation: u64, ip: IpAddr, record: &Record<'static>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let (v6, bits) = address_bits(ip);
+        let prefix = u8::try_from(record.netmask.min(if v6 { 128 } else { 32 })).unwrap_or(0);
+        let key = (generation, v6, mask(bits, v6, prefix), prefix);
+        let inserted = (self.now)();
+
+        let mut entries = self.lock();
+        entries.sequence += 1;
+        let sequence = entries.sequence;
+        entries.map.insert(
+            key,
+            Entry {
+                record: record.clone(),
+                inserted,
+                sequence,
+            },
+        );
+        entries.queue.push_back((key, sequence));
+        entries.prefixes.insert((v6, prefix));
+
+        while entries.map.len() > self.capacity {
+            let Some((key, sequence)) = entries.queue.pop_front() else {
+                break;
+            };
+            if entries
+                .map
+                .get(&key)
+                .is_some_and(|entry| entry.sequence == sequence)
+            {
+                entries.map.remove(&key);
+            }
+        }
+        // The keys of the entries dropped as stale stay queued until evicted
+        if entries.queue.len() > 2 * self.capacity {
+            compact(&mut entries);
+        }
+    }
+
+    /// Returns the record cached for the network of an address, or looks it up and caches it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the lookup, which is not cached.
+    pub fn get_or_insert_with<E>(
+        &self,
+        ip: IpAddr,
+        lookup: impl FnOnce() -> Result<Record<'static>, E>,
+    ) -> Result<Record<'static>, E> {
+        let generation = self.generation();
+        if let Some(record) = self.get(ip) {
+            return Ok(record);
+        }
+
+        let record = lookup()?;
+        self.insert(generation, ip, &record);
+        Ok(record)
+    }
+
+    /// Removes the entries older than the time to live and those of former generations, and
+    /// returns how many were removed. The lookups drop such entries anyway, this is to bound
+    /// the memory of a cache looked up less than it is filled.
+    pub fn purge_expired(&self) -> usize {
+        let generation = self.generation();
+        let mut entries = self.lock();
+        let before = entries.map.len();
+        entries
+            .map
+            .retain(|key, entry| key.0 == generation && !self.is_expired(entry));
+        compact(&mut entries);
+        before - entries.map.len()
+    }
+
+    /// The counters of the lookups and the number of entries.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+            entries: self.lock().map.len(),
+        }
+    }
+
+    fn is_expired(&self, entry: &Entry) -> bool {
+        self.ttl
+            .is_some_and(|ttl| (self.now)().saturating_duration_since(entry.inserted) >= ttl)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        // The entries are consistent between statements, whatever panicked while holding them
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Drops the queued keys whose entry was removed or replaced.
+fn compact(entries: &mut Entries) {
+    let Entries { map, queue, .. } = entries;
+    queue.retain(|(key, sequence)| {
+        map.get(key)
+            .is_some_and(|entry| entry.sequence == *sequence)
+    });
+}
+
+/// The family of an address, `true` for IPv6, and its bits.
+fn address_bits(ip: IpAddr) -> (bool, u128) {
+    match ip {
+        IpAddr::V4(ip) => (false, u128::from(u32::from(ip))),
+        IpAddr::V6(ip) => (true, u128::from(ip)),
+    }
+}
+
+/// The bits of an address masked to the prefix of the given length.
+fn mask(bits: u128, v6: bool, prefix: u8) -> u128 {
+    let width = if v6 { 128 } else { 32 };
+    let host_bits = width - u32::from(prefix).min(width);
+    bits.checked_shr(host_bits)
+        .and_then(|network| network.checked_shl(host_bits))
+        .unwrap_or(0)
+}
+
+/// An `IpCap` whose databases can be swapped while it is shared, with a `TtlPrefixCache` in
+/// front of its lookups.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::cache::{CachedIpCap, TtlPrefixCache};
+/// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+/// use ipcap::IpCap;
+///
+/// let ipcap = IpCap::open(GeoIpReaderBuilder::new()).unwrap();
+/// let cached = CachedIpCap::new(ipcap, TtlPrefixCache::new(1024));
+///
+/// let record = cached.lookup("108.95.4.105").unwrap();
+/// assert_eq!(cached.lookup("108.95.4.105").unwrap(), record);
+/// assert_eq!(cached.cache().stats().hits, 1);
+/// ```
+#[derive(Debug)]
+pub struct CachedIpCap {
+    ipcap: RwLock<IpCap>,
+    cache: TtlPrefixCache,
+}
+
+impl CachedIpCap {
+    /// Puts the cache in front of the lookups of the reader.
+    pub fn new(ipcap: IpCap, cache: TtlPrefixCache) -> Self {
+        CachedIpCap {
+            ipcap: RwLock::new(ipcap),
+            cache,
+        }
+    }
+
+    /// Looks up the geographical record of an IP address, v4 or v6, from the cache if its
+    /// network was looked up in the current databases.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `IpCap::lookup`, which are not cached.
+    pub fn lookup(&self, ip: &str) -> Result<Record<'static>, GeoIpReaderError> {
+        let addr = ip
+            .parse::<IpAddr>()
+            .map_err(|source| GeoIpReaderError::InvalidAddress {
+                input: ip.to_string(),
+                source,
+            })?;
+        self.cache.get_or_insert_with(addr, || {
+            self.ipcap
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .lookup(ip)
+        })
+    }
+
+    /// Swaps the databases for reloaded ones, then invalidates the cache: once this returns, no
+    /// lookup answers a record of the former databases.
+    pub fn reload(&self, ipcap: IpCap) {
+        *self
+            .ipcap
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = ipcap;
+        self.cache.advance_generation();
+    }
+
+    /// The cache of the lookups, e.g. for its counters or to purge it periodically.
+    pub fn cache(&self) -> &TtlPrefixCache {
+        &self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use crate::geo_ip_reader::GeoIpReaderBuilder;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::sync::atomic::AtomicBool;
+
+    /// A database of a single /8 network of the given country.
+    fn ipcap(country: Country) -> IpCap {
+        let mut database = DatabaseBuilder::new();
+        database
+            .insert("2.0.0.0/8", RecordSpec::new(country))
+            .unwrap();
+        IpCap::from_bytes(Some(database.build()), None, GeoIpReaderBuilder::new()).unwrap()
+    }
+
+    #[test]
+    fn test_prefix_entries() {
+        let cache = TtlPrefixCache::new(16);
+        let generation = cache.generation();
+        let wide = Record::new(Country::France).with_netmask(8, 32);
+        let narrow = Record::new(Country::Germany).with_netmask(24, 32);
+        cache.insert(generation, "2.0.0.1".parse().unwrap(), &wide);
+        cache.insert(generation, "3.1.2.200".parse().unwrap(), &narrow);
+
+        let get = |ip: &str| cache.get(ip.parse().unwrap()).map(|record| record.country);
+        assert_eq!(get("2.200.0.1"), Some(Country::France));
+        assert_eq!(get("3.1.2.1"), Some(Country::Germany));
+        assert_eq!(get("3.1.3.1"), None);
+        // The families do not share their networks
+        assert_eq!(get("200::1"), None);
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                stale: 0,
+                entries: 2,
+            }
+        );
+
+        // Disabled by a capacity of 0
+        let disabled = TtlPrefixCache::new(0);
+        disabled.insert(0, "2.0.0.1".parse().unwrap(), &wide);
+        assert_eq!(disabled.get("2.0.0.1".parse().unwrap()), None);
+        assert_eq!(disabled.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let cache = TtlPrefixCache::new(2);
+        for (i, ip) in ["1.0.0.1", "2.0.0.1", "3.0.0.1"].into_iter().enumerate() {
+            let record = Record::new(Country::France).with_netmask(8, 32);
+            cache.insert(0, ip.parse().unwrap(), &record);
+            assert_eq!(cache.stats().entries, (i + 1).min(2));
+        }
+        // The oldest was evicted
+        assert_eq!(cache.get("1.0.0.1".parse().unwrap()), None);
+        assert!(cache.get("2.0.0.1".parse().unwrap()).is_some());
+        assert!(cache.get("3.0.0.1".parse().unwrap()).is_some());
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let elapsed = Arc::new(AtomicU64::new(0));
+        let start = Instant::now();
+        let clock = elapsed.clone();
+        let cache = TtlPrefixCache::new(16)
+            .ttl(Duration::from_secs(60))
+            .clock(move || start + Duration::from_secs(clock.load(Ordering::SeqCst)));
+        let record = Record::new(Country::France).with_netmask(8, 32);
+        let ip = "2.0.0.1".parse().unwrap();
+
+        cache.insert(cache.generation(), ip, &record);
+        elapsed.store(59, Ordering::SeqCst);
+        assert!(cache.get(ip).is_some());
+        elapsed.store(60, Ordering::SeqCst);
+        assert_eq!(cache.get(ip), None);
+        assert_eq!(cache.get(ip), None);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.stale, stats.misses), (1, 1, 1));
+        assert_eq!(stats.entries, 0);
+
+        // Purged without being looked up, as are the entries of former generations
+        cache.insert(cache.generation(), ip, &record);
+        cache.insert(cache.generation(), "3.0.0.1".parse().unwrap(), &record);
+        elapsed.store(120, Ordering::SeqCst);
+        cache.insert(cache.generation(), "4.0.0.1".parse().unwrap(), &record);
+        assert_eq!(cache.purge_expired(), 2);
+        assert_eq!(cache.stats().entries, 1);
+        cache.advance_generation();
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_reload_invalidates() {
+        let cached = CachedIpCap::new(ipcap(Country::France), TtlPrefixCache::new(16));
+        assert_eq!(cached.lookup("2.0.0.1").unwrap().country, Country::France);
+        assert_eq!(cached.lookup("2.9.9.9").unwrap().country, Country::France);
+        assert_eq!(cached.cache().stats().hits, 1);
+
+        cached.reload(ipcap(Country::Germany));
+        assert_eq!(cached.lookup("2.9.9.9").unwrap().country, Country::Germany);
+        assert_eq!(cached.lookup("2.0.0.1").unwrap().country, Country::Germany);
+        let stats = cached.cache().stats();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+
+        // The errors are not cached
+        assert!(cached.lookup("3.0.0.1").is_err());
+        assert!(cached.lookup("not an ip").is_err());
+        assert_eq!(cached.cache().stats().entries, 2);
+    }
+
+    #[test]
+    fn test_concurrent_reload() {
+        let cached = CachedIpCap::new(ipcap(Country::France), TtlPrefixCache::new(64));
+        let reloaded = AtomicBool::new(false);
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            for thread in 0..8u8 {
+                let (cached, reloaded, done) = (&cached, &reloaded, &done);
+                scope.spawn(move || {
+                    let mut i = 0u8;
+                    while !done.load(Ordering::SeqCst) {
+                        let after_reload = reloaded.load(Ordering::SeqCst);
+                        let ip = format!("2.{}.{}.1", thread, i);
+                        let country = cached.lookup(&ip).unwrap().country;
+                        if after_reload {
+                            assert_eq!(country, Country::Germany, "{}", ip);
+                        }
+                        i = i.wrapping_add(1);
+                    }
+                });
+            }
+
+            std::thread::sleep(Duration::from_millis(20));
+            cached.reload(ipcap(Country::Germany));
+            reloaded.store(true, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            done.store(true, Ordering::SeqCst);
+        });
+
+        assert_eq!(cached.lookup("2.0.0.1").unwrap().country, Country::Germany);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::constants::*;
+use crate::geo_ip_reader::edition_name;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fmt;
+
+/// The editions `GeoIpReader` can open, in ascending order of their identifier.
+pub(crate) const SUPPORTED_EDITIONS: &[u8] = &[
+    COUNTRY_EDITION,
+    CITY_EDITION_REV1,
+    REGION_EDITION_REV1,
+    ISP_EDITION,
+    ORG_EDITION,
+    CITY_EDITION_REV0,
+    REGION_EDITION_REV0,
+    ASNUM_EDITION,
+    COUNTRY_EDITION_V6,
+    ASNUM_EDITION_V6,
+    CITY_EDITION_REV1_V6,
+];
+
+/// What this build of `ipcap` can do, as returned by `capabilities`.
+///
+/// The `Display` form lists the crate version, the enabled features and the supported editions,
+/// one per line, and only changes with the build.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::capabilities;
+/// use ipcap::constants::CITY_EDITION_REV1;
+///
+/// let capabilities = capabilities();
+///
+/// assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+/// assert!(capabilities.supported_editions.contains(&CITY_EDITION_REV1));
+/// println!("{}", capabilities);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Capabilities {
+    /// The version of the crate.
+    pub version: &'static str,
+    /// Whether the `cli` feature, the command-line interface, is enabled.
+    pub cli: bool,
+    /// Whether the `compat-geoip` feature, the surface of the `geoip` crate, is enabled.
+    pub compat_geoip: bool,
+    /// Whether the `embedded-country-data` feature, the fallback country dataset, is enabled.
+    pub embedded_country_data: bool,
+    /// Whether the `maxminddb-compat` feature, the conversions from and to MMDB City records,
+    /// is enabled.
+    pub maxminddb_compat: bool,
+    /// Whether the `otel` feature, the conversions of geo attributes to OpenTelemetry key-values,
+    /// is enabled.
+    pub otel: bool,
+    /// Whether the `problem` feature, RFC 7807 error responses, is enabled.
+    pub problem: bool,
+    /// Whether the `resolve` feature, the DNS resolution of host names, is enabled.
+    pub resolve: bool,
+    /// Whether the `serde` feature, serialization of the records and reports, is enabled.
+    pub serde: bool,
+    /// Whether the `snapshot` feature, the snapshots of parsed databases, is enabled.
+    pub snapshot: bool,
+    /// Whether the `tokio` feature, asynchronous lookup streams, is enabled.
+    pub tokio: bool,
+    /// Whether the `tracing` feature, the spans around lookups, is enabled.
+    pub tracing: bool,
+    /// Whether the `writer` feature, the database builder, is enabled.
+    pub writer: bool,
+    /// The editions `GeoIpReader` can open, as `*_EDITION*` constants.
+    pub supported_editions: &'static [u8],
+}
+
+impl Capabilities {
+    /// Returns the names of the enabled features, in alphabetical order.
+    pub fn features(&self) -> Vec<&'static str> {
+        [
+            ("cli", self.cli),
+            ("compat-geoip", self.compat_geoip),
+            ("embedded-country-data", self.embedded_country_data),
+            ("maxminddb-compat", self.maxminddb_compat),
+            ("otel", self.otel),
+            ("problem", self.problem),
+            ("resolve", self.resolve),
+            ("serde", self.serde),
+            ("snapshot", self.snapshot),
+            ("tokio", self.tokio),
+            ("tracing", self.tracing),
+            ("writer", self.writer),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ipcap {}", self.version)?;
+        let features = self.features();
+        if features.is_empty() {
+            writeln!(f, "features: none")?;
+        } else {
+            writeln!(f, "features: {}", features.join(", "))?;
+        }
+        write!(f, "editions:")?;
+        for edition in self.supported_editions {
+            write!(f, "\n  {:>2} {}", edition, edition_name(*edition))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the version, the enabled features and the supported database editions of this build.
+///
+/// # Returns
+///
+/// (`Capabilities`): The capabilities, identical for every call within a build.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        cli: cfg!(feature = "cli"),
+        compat_geoip: cfg!(feature = "compat-geoip"),
+        embedded_country_data: cfg!(feature = "embedded-country-data"),
+        maxminddb_compat: cfg!(feature = "maxminddb-compat"),
+        otel: cfg!(feature = "otel"),
+        problem: cfg!(feature = "problem"),
+        resolve: cfg!(feature = "resolve"),
+        serde: cfg!(feature = "serde"),
+        snapshot: cfg!(feature = "snapshot"),
+        tokio: cfg!(feature = "tokio"),
+        tracing: cfg!(feature = "tracing"),
+        writer: cfg!(feature = "writer"),
+        supported_editions: SUPPORTED_EDITIONS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_flags() {
+        let capabilities = capabilities();
+
+        #[cfg(feature = "cli")]
+        assert!(capabilities.cli);
+        #[cfg(not(feature = "cli"))]
+        assert!(!capabilities.cli);
+        #[cfg(feature = "compat-geoip")]
+        assert!(capabilities.compat_geoip);
+        #[cfg(not(feature = "compat-geoip"))]
+        assert!(!capabilities.compat_geoip);
+        #[cfg(feature = "embedded-country-data")]
+        assert!(capabilities.embedded_country_data);
+        // Without the feature, neither the dataset nor its resolver are compiled in
+        #[cfg(not(feature = "embedded-country-data"))]
+        assert!(!capabilities.embedded_country_data);
+        #[cfg(not(feature = "embedded-country-data"))]
+        assert!(!capabilities.features().contains(&"embedded-country-data"));
+        #[cfg(not(feature = "embedded-country-data"))]
+        assert!(!std::path::Path::new(concat!(env!("OUT_DIR"), "/country-prefixes")).exists());
+        #[cfg(feature = "maxminddb-compat")]
+        assert!(capabilities.maxminddb_compat);
+        #[cfg(not(feature = "maxminddb-compat"))]
+        assert!(!capabilities.maxminddb_compat);
+        #[cfg(feature = "otel")]
+        assert!(capabilities.otel);
+        #[cfg(not(feature = "otel"))]
+        assert!(!capabilities.otel);
+        #[cfg(feature = "problem")]
+        assert!(capabilities.problem);
+        #[cfg(not(feature = "problem"))]
+        assert!(!capabilities.problem);
+        #[cfg(feature = "resolve")]
+        assert!(capabilities.resolve);
+        #[cfg(not(feature = "resolve"))]
+        assert!(!capabilities.resolve);
+        #[cfg(feature = "serde")]
+        assert!(capabilities.serde);
+        #[cfg(not(feature = "serde"))]
+        assert!(!capabilities.serde);
+        #[cfg(feature = "snapshot")]
+        assert!(capabilities.snapshot);
+        #[cfg(not(feature = "snapshot"))]
+        assert!(!capabilities.snapshot);
+        #[cfg(feature = "tokio")]
+        assert!(capabilities.tokio);
+        #[cfg(not(feature = "tokio"))]
+        assert!(!capabilities.tokio);
+        #[cfg(feature = "tracing")]
+        assert!(capabilities.tracing);
+        #[cfg(not(feature = "tracing"))]
+        assert!(!capabilities.tracing);
+        #[cfg(feature = "writer")]
+        assert!(capabilities.writer);
+        #[cfg(not(feature = "writer"))]
+        assert!(!capabilities.writer);
+    }
+
+    #[test]
+    fn test_supported_editions() {
+        let editions = capabilities().supported_editions;
+
+        for edition in [CITY_EDITION_REV0, CITY_EDITION_REV1, CITY_EDITION_REV1_V6] {
+            assert!(editions.contains(&edition), "{}", edition);
+        }
+        assert!(editions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(editions
+            .iter()
+            .all(|edition| edition_name(*edition) != "Unknown Edition"));
+    }
+
+    #[test]
+    fn test_display() {
+        let text = capabilities().to_string();
+
+        assert_eq!(text, capabilities().to_string());
+        assert!(text.starts_with(&format!("ipcap {}\nfeatures: ", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("\n   2 City Edition, Rev 1\n"));
+        assert!(text.ends_with("\n  30 City Edition V6, Rev 1"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let json = serde_json::to_string(&capabilities()).unwrap();
+
+        assert_eq!(json, serde_json::to_string(&capabilities()).unwrap());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "cli": cfg!(feature = "cli"),
+                "compat_geoip": cfg!(feature = "compat-geoip"),
+                "embedded_country_data": cfg!(feature = "embedded-country-data"),
+                "maxminddb_compat": cfg!(feature = "maxminddb-compat"),
+                "otel": cfg!(feature = "otel"),
+                "problem": cfg!(feature = "problem"),
+                "resolve": cfg!(feature = "resolve"),
+                "serde": true,
+                "snapshot": cfg!(feature = "snapshot"),
+                "tokio": cfg!(feature = "tokio"),
+                "tracing": cfg!(feature = "tracing"),
+                "writer": cfg!(feature = "writer"),
+                "supported_editions": [1, 2, 3, 4, 5, 6, 7, 9, 12, 21, 30],
+            })
+        );
+    }
+}
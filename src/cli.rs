@@ -1,7 +1,35 @@
 #[cfg(feature = "cli")]
+use crate::bulk::DEFAULT_EXACT_LIMIT;
+#[cfg(feature = "cli")]
+use crate::data_export::{Format, Table};
+#[cfg(feature = "cli")]
+use crate::errors::ExpectationError;
+#[cfg(feature = "cli")]
+use crate::format::{SchemaMode, SchemaVersion};
+#[cfg(feature = "cli")]
+use crate::geo_ip_reader::CoordinatePolicy;
+#[cfg(feature = "cli")]
+use crate::ip_hash::IpHashKey;
+#[cfg(feature = "cli")]
+use crate::locale::Locale;
+#[cfg(feature = "cli")]
+use crate::post_process::BUILTIN_FIELD_NAMES;
+#[cfg(feature = "cli")]
+use crate::time_zones::parse_timestamp;
+#[cfg(feature = "cli")]
+use crate::utils::{IpPattern, RECORD_FIELD_NAMES};
+#[cfg(feature = "cli")]
+use crate::validate::Expectation;
+#[cfg(feature = "cli")]
 use clap::builder::styling::{AnsiColor, Effects, Styles};
 #[cfg(feature = "cli")]
-use clap::Parser;
+use clap::builder::PossibleValuesParser;
+#[cfg(feature = "cli")]
+use clap::{Args, Parser, Subcommand};
+#[cfg(feature = "cli")]
+use std::path::PathBuf;
+#[cfg(feature = "cli")]
+use std::time::Duration;
 
 #[cfg(feature = "cli")]
 fn styles() -> Styles {
@@ -48,7 +76,13 @@ Perform IP lookup from the command line without internet access. Retrieve inform
 about IP addresses, including details such as city, region, country, location, etc.
 
 FEATURES:
-  - Target: Set the IP address to lookup with the --target option.
+  - Target: Set the IP address to lookup with the --target option, repeatable.
+  - Expect: Assert a field of the record of each target with --expect, e.g. country_code=US.
+  - Enrichment: Combine the lookup with ASN and organization databases using --asn-db and --org-db.
+  - Annotate: Append geo columns to each line of a log read from stdin with the annotate command.
+  - Batch: Look up the first IP address of each stdin line, once per distinct address with --unique.
+  - Audit: Check that the v4 and v6 databases agree on the location of dual-stack pairs.
+  - Locale: Print the country and continent names in French, German or Spanish with --locale.
     
 USAGE:
   iplookup [OPTIONS]
@@ -57,6 +91,45 @@ EXAMPLES:
   Perform IP lookup:
     ipcap -t 8.8.8.8
 
+  Check where known addresses resolve, e.g. in CI:
+    ipcap -t 8.8.8.8 -t 2a08:1450:300f:900::1003 --expect country_code=US --expect country_code=GB
+
+  Perform IP lookup of an address with leading zeros:
+    ipcap -t 008.008.008.008 --legacy-parse
+
+  Perform IP lookup enriched with ASN data:
+    ipcap -t 8.8.8.8 --asn-db ~/ipcap/geo_ip_asnum.dat
+
+  Perform IP lookup with the time zone abbreviation at a given instant:
+    ipcap -t 8.8.8.8 --at 2024-07-01T12:00:00Z
+
+  Perform IP lookup of a host name, with the resolve feature:
+    ipcap -t example.com --resolve
+
+  Perform IP lookup with the names in German:
+    ipcap -t 8.8.8.8 --locale de
+
+  Annotate an access log:
+    cat access.log | ipcap annotate --pattern ipv4 --append country_code,city
+
+  Look up each distinct client of an access log with its number of requests:
+    cat access.log | ipcap batch --unique --count --append country_code,city
+
+  Export the locations of the clients of an access log without their addresses:
+    cat access.log | ipcap batch --format json --hash-ip-env IPCAP_HASH_KEY
+
+  Compare the v4 and v6 locations of dual-stack services:
+    ipcap audit --pairs pairs.csv --format json
+
+  Summarize the locations of a range from WHOIS output:
+    ipcap range 8.8.8.0-8.8.8.255
+
+  Check the build and the installed databases:
+    ipcap doctor
+
+  Export the built-in countries as JSON:
+    ipcap export-data countries --format json
+
 For more information, visit: https://github.com/wiseaidev/ipcap
 "#
 )]
@@ -65,7 +138,498 @@ pub struct Cli {
     #[arg(global = true, short, long)]
     pub verbose: bool,
 
-    /// IP address to lookup.
+    /// Instant of the time zone abbreviation and UTC offset, as unix seconds or RFC 3339.
+    #[arg(global = true, long = "at", value_parser = parse_at)]
+    pub at: Option<i64>,
+
+    /// Language of the country and continent names: en, fr, de or es. Defaults to the locale
+    /// of the config file, then to en. The codes are not translated.
+    #[arg(global = true, long = "locale")]
+    pub locale: Option<Locale>,
+
+    /// IP address to lookup. Repeatable, the targets being looked up in order.
     #[arg(short = 't', long = "target")]
-    pub target: String,
+    pub target: Vec<String>,
+
+    /// Assertion on a field of the record of the target at the same position: field=value, or
+    /// field~=substring for a value containing the substring. Repeatable, once per target.
+    /// Prints whether each target passed and fails if any did not.
+    #[arg(long = "expect", value_parser = parse_expectation)]
+    pub expect: Vec<Expectation>,
+
+    /// Format of the results of --expect: text or json.
+    #[arg(long = "format", default_value = "text", requires = "expect")]
+    pub format: AuditFormat,
+
+    /// Accept IPv4 octets with leading zeros in the target, read as decimal (010 is 10).
+    #[arg(long = "legacy-parse")]
+    pub legacy_parse: bool,
+
+    /// Resolve a target that is not an IP address, e.g. example.com, with the system resolver,
+    /// and look up the first of its addresses, the IPv4 ones first. Lookups are offline
+    /// without this flag.
+    #[cfg(feature = "resolve")]
+    #[arg(long = "resolve")]
+    pub resolve: bool,
+
+    /// Path of an ASN database to enrich the lookup with.
+    #[arg(long = "asn-db")]
+    pub asn_db: Option<String>,
+
+    /// Path of an organization database to enrich the lookup with.
+    #[arg(long = "org-db")]
+    pub org_db: Option<String>,
+
+    /// Path of a CSV file of ranges resolved to their own records instead of the database.
+    #[arg(long = "overrides")]
+    pub overrides: Option<String>,
+
+    /// Report a failing ASN or organization lookup without failing the whole lookup.
+    #[arg(long = "lenient")]
+    pub lenient: bool,
+
+    /// Print to stderr the fields of the record left empty, and why, e.g. a region without a
+    /// time zone mapping. Printed with --verbose as well.
+    #[arg(long = "explain-gaps")]
+    pub explain_gaps: bool,
+
+    /// Path of a snapshot written by `snapshot build`, read instead of the database by the
+    /// lookups of --target and by range.
+    #[cfg(feature = "snapshot")]
+    #[arg(global = true, long = "snapshot")]
+    pub snapshot: Option<PathBuf>,
+
+    /// Time budget of reading the databases into the page cache before annotate and batch
+    /// start, in seconds. The whole databases are read by default.
+    #[arg(global = true, long = "warm-up", value_name = "SECONDS", value_parser = parse_seconds)]
+    pub warm_up: Option<Duration>,
+
+    /// Built-in post-processors run on the fields of each record, in order: is-eu adds the
+    /// is_eu field, true for a member state of the European Union, and modern-country adds the
+    /// country_code_modern field to the records of a deprecated country, e.g. FR for FX.
+    /// Repeatable or comma-separated.
+    #[arg(global = true, long = "post-processor", value_delimiter = ',')]
+    pub post_processors: Vec<BuiltinProcessor>,
+
+    /// Coordinates of the records without real ones, e.g. those of Region databases: none
+    /// leaves them out, country-centroid gives the centroid of their country and
+    /// legacy-sentinel gives -180, -180.
+    #[arg(global = true, long = "coordinate-policy", default_value = "none")]
+    pub coordinate_policy: CoordinatePolicy,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The defaults of the command-line options, read from the config file: `~/ipcap/config`, or
+/// the file set by the `IPCAP_CONFIG` environment variable.
+///
+/// The file holds `key = value` lines, blank lines and lines starting with `#` being ignored.
+/// The only key is `locale`, the default of `--locale`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub locale: Option<Locale>,
+}
+
+#[cfg(feature = "cli")]
+impl Config {
+    /// The path of the config file.
+    pub fn path() -> PathBuf {
+        if let Some(path) = std::env::var_os("IPCAP_CONFIG") {
+            return PathBuf::from(path);
+        }
+        let mut path = dirs::home_dir().unwrap_or_default();
+        path.push("ipcap");
+        path.push("config");
+        path
+    }
+
+    /// Reads the config file, a missing file giving no defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be read or is invalid.
+    pub fn load() -> Result<Config, String> {
+        let path = Config::path();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                Config::parse(&content).map_err(|err| format!("{}: {}", path.display(), err))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(format!("cannot read {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Parses the content of a config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message naming the line of an unknown key or an invalid value.
+    pub fn parse(content: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| format!("line {}: {}", index + 1, message);
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected 'key = value', got '{}'", line)))?;
+            match key.trim() {
+                "locale" => config.locale = Some(value.trim().parse().map_err(invalid)?),
+                key => return Err(invalid(format!("unknown key '{}'", key))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Parses the `--at` argument, unix seconds or an RFC 3339 date-time.
+#[cfg(feature = "cli")]
+fn parse_at(value: &str) -> Result<i64, String> {
+    parse_timestamp(value).ok_or_else(|| {
+        "expected unix seconds or an RFC 3339 date-time like 2024-01-15T00:00:00Z".to_string()
+    })
+}
+
+/// Parses an `--expect` argument, whose field must be one of the fields of the output.
+#[cfg(feature = "cli")]
+fn parse_expectation(value: &str) -> Result<Expectation, String> {
+    let expectation: Expectation = value
+        .parse()
+        .map_err(|err: ExpectationError| err.to_string())?;
+    let field = expectation.field.as_str();
+    if !RECORD_FIELD_NAMES.contains(&field) && !BUILTIN_FIELD_NAMES.contains(&field) {
+        return Err(format!(
+            "unknown field '{}', expected one of {}",
+            field,
+            RECORD_FIELD_NAMES
+                .into_iter()
+                .chain(BUILTIN_FIELD_NAMES)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    Ok(expectation)
+}
+
+/// Parses the `--warm-up` argument, a non-negative number of seconds.
+#[cfg(feature = "cli")]
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| "expected a non-negative number of seconds like 2.5".to_string())
+}
+
+#[cfg(feature = "cli")]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Append the geo fields of the first IP address of each stdin line to that line.
+    Annotate(AnnotateArgs),
+    /// Compare the locations the v4 and v6 databases give to the addresses of dual-stack pairs.
+    Audit(AuditArgs),
+    /// Look up the first IP address of each stdin line and print it with its geo fields.
+    Batch(BatchArgs),
+    /// Print a profile of a database: its networks, records and records by country and continent.
+    DbStats(DbStatsArgs),
+    /// Print the version, features and supported editions of this build and the databases found.
+    Doctor,
+    /// Print a table of the built-in reference data as CSV or JSON.
+    ExportData(ExportDataArgs),
+    /// Print the guide to migrate from the 0.1 library API.
+    MigrateHelp,
+    /// Print the distinct records of a range of addresses with the sub-ranges they cover.
+    Range(RangeArgs),
+    /// Manage the snapshots of parsed databases, read by --snapshot.
+    #[cfg(feature = "snapshot")]
+    Snapshot(SnapshotArgs),
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, Debug)]
+pub struct AnnotateArgs {
+    /// Address families to look for: ipv4, ipv6 or auto.
+    #[arg(long = "pattern", default_value = "auto")]
+    pub pattern: IpPattern,
+
+    /// Comma-separated fields to append to each line.
+    #[arg(
+        long = "append",
+        value_delimiter = ',',
+        default_value = "country_code",
+        value_parser = field_names()
+    )]
+    pub append: Vec<String>,
+
+    /// Separator written before each appended field.
+    #[arg(long = "delimiter", default_value = "\t")]
+    pub delimiter: String,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, Debug)]
+pub struct AuditArgs {
+    /// Path of a CSV file of `v4,v6` address pairs, one per line.
+    #[arg(long = "pairs")]
+    pub pairs: String,
+
+    /// Format of the report: text or json.
+    #[arg(long = "format", default_value = "text")]
+    pub format: AuditFormat,
+}
+
+/// The format of the report printed by `audit` and `db-stats`, and of the results of `--expect`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuditFormat {
+    /// A human-readable report, e.g. the classification of each pair, then the counts and
+    /// agreement rates.
+    #[default]
+    Text,
+    /// A single JSON object, as `DualStackReport::to_json` or `DbStats::to_json`, or an array
+    /// of the objects of `ExpectationResult::to_json`.
+    Json,
+}
+
+#[cfg(feature = "cli")]
+impl std::str::FromStr for AuditFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(AuditFormat::Text),
+            "json" => Ok(AuditFormat::Json),
+            _ => Err(format!(
+                "invalid format '{}', expected one of text, json",
+                value
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Address families to look for: ipv4, ipv6 or auto.
+    #[arg(long = "pattern", default_value = "auto")]
+    pub pattern: IpPattern,
+
+    /// Comma-separated fields to print after each address.
+    #[arg(
+        long = "append",
+        value_delimiter = ',',
+        default_value = "country_code",
+        value_parser = field_names()
+    )]
+    pub append: Vec<String>,
+
+    /// Separator written before each field.
+    #[arg(long = "delimiter", default_value = "\t")]
+    pub delimiter: String,
+
+    /// Look for the address in the whole line rather than in its first whitespace-delimited
+    /// token. Blank lines and lines starting with # are skipped either way.
+    #[arg(long = "whole-line")]
+    pub whole_line: bool,
+
+    /// Print one row per distinct address, in order of first occurrence.
+    #[arg(long = "unique")]
+    pub unique: bool,
+
+    /// Append the number of occurrences of each address, while it is counted exactly.
+    #[arg(long = "count", requires = "unique")]
+    pub count: bool,
+
+    /// Number of distinct addresses counted exactly before switching to a probabilistic filter.
+    #[arg(long = "unique-limit", default_value_t = DEFAULT_EXACT_LIMIT)]
+    pub unique_limit: usize,
+
+    /// Number of addresses read between two progress lines printed with --verbose.
+    #[arg(long = "progress-every", default_value_t = 100_000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub progress_every: u64,
+
+    /// Format of the rows: text, json for one object per line or json-array for an array of
+    /// the same objects, failed lookups included. Builds with the serde feature add binary, for
+    /// the length-prefixed frames of ipcap::binary.
+    #[arg(long = "format", default_value = "text")]
+    pub format: BatchFormat,
+
+    /// Schema version of the JSON objects: 1, or 2 to add the provenance printed with
+    /// --verbose.
+    #[arg(long = "schema", default_value = "1")]
+    pub schema: SchemaVersion,
+
+    /// Whether the JSON objects keep to the schema: strict leaves out the fields added by
+    /// --post-processor, permissive adds them after the others.
+    #[arg(long = "schema-mode", default_value = "strict")]
+    pub schema_mode: SchemaMode,
+
+    /// Replace each address with its SipHash-2-4 under this 128-bit key of 32 hex digits, as 16
+    /// lowercase hex digits, in every format. The same address gives the same token with the
+    /// same key.
+    #[arg(
+        long = "hash-ip",
+        value_name = "HEX_KEY",
+        conflicts_with = "hash_ip_env"
+    )]
+    pub hash_ip: Option<IpHashKey>,
+
+    /// Name of an environment variable holding the key of --hash-ip, keeping it out of the
+    /// shell history.
+    #[arg(long = "hash-ip-env", value_name = "VAR")]
+    pub hash_ip_env: Option<String>,
+}
+
+/// A built-in post-processor of `--post-processor`, see `post_process`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinProcessor {
+    /// `post_process::add_is_eu_flag`.
+    IsEu,
+    /// `post_process::add_modern_country_code`.
+    ModernCountry,
+}
+
+#[cfg(feature = "cli")]
+impl std::str::FromStr for BuiltinProcessor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "is-eu" => Ok(BuiltinProcessor::IsEu),
+            "modern-country" => Ok(BuiltinProcessor::ModernCountry),
+            _ => Err(format!(
+                "invalid post-processor '{}', expected one of is-eu, modern-country",
+                value
+            )),
+        }
+    }
+}
+
+/// The values of `--append`: the fields of the records and those of the built-in processors.
+#[cfg(feature = "cli")]
+fn field_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(RECORD_FIELD_NAMES.into_iter().chain(BUILTIN_FIELD_NAMES))
+}
+
+/// The format of the rows printed by `batch`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchFormat {
+    /// The address and its fields, separated by the delimiter.
+    #[default]
+    Text,
+    /// One JSON object per line, an error object for the lines whose lookup failed.
+    Json,
+    /// The objects of `Json` in a JSON array.
+    JsonArray,
+    /// The length-prefixed frames of `binary`, after a header listing the fields.
+    #[cfg(feature = "serde")]
+    Binary,
+}
+
+#[cfg(feature = "cli")]
+impl std::str::FromStr for BatchFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(BatchFormat::Text),
+            "json" => Ok(BatchFormat::Json),
+            "json-array" => Ok(BatchFormat::JsonArray),
+            #[cfg(feature = "serde")]
+            "binary" => Ok(BatchFormat::Binary),
+            _ => Err(format!(
+                "invalid format '{}', expected one of text, json, json-array{}",
+                value,
+                if cfg!(feature = "serde") {
+                    ", binary"
+                } else {
+                    ""
+                }
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, Debug)]
+pub struct RangeArgs {
+    /// Range to summarize: start-end, e.g. 8.8.8.0-8.8.8.255, whose bounds need not be on
+    /// prefix boundaries, or a CIDR.
+    pub range: String,
+
+    /// Comma-separated fields to print after the sub-ranges of each record.
+    #[arg(
+        long = "append",
+        value_delimiter = ',',
+        default_value = "country_code,region_code,city",
+        value_parser = field_names()
+    )]
+    pub append: Vec<String>,
+
+    /// Separator written before each field.
+    #[arg(long = "delimiter", default_value = "\t")]
+    pub delimiter: String,
+}
+
+#[cfg(all(feature = "cli", feature = "snapshot"))]
+#[derive(Args, Debug)]
+pub struct SnapshotArgs {
+    #[command(subcommand)]
+    pub command: SnapshotCommand,
+}
+
+#[cfg(all(feature = "cli", feature = "snapshot"))]
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommand {
+    /// Write a snapshot of the database of an address family.
+    Build(SnapshotBuildArgs),
+}
+
+#[cfg(all(feature = "cli", feature = "snapshot"))]
+#[derive(Args, Debug)]
+pub struct SnapshotBuildArgs {
+    /// Path of the snapshot to write.
+    #[arg(short = 'o', long = "output")]
+    pub output: PathBuf,
+
+    /// Address family of the database: v4 or v6.
+    #[arg(long = "family", default_value = "v4", value_parser = ["v4", "v6"])]
+    pub family: String,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, Debug)]
+pub struct DbStatsArgs {
+    /// Read every record for exact counts instead of a sample of them.
+    #[arg(long = "full")]
+    pub full: bool,
+
+    /// Number of records read for the estimates, unless --full.
+    #[arg(long = "sample", default_value_t = 10_000, conflicts_with = "full")]
+    pub sample: usize,
+
+    /// Address family of the database: v4 or v6.
+    #[arg(long = "family", default_value = "v4", value_parser = ["v4", "v6"])]
+    pub family: String,
+
+    /// Format of the profile: text or json.
+    #[arg(long = "format", default_value = "text")]
+    pub format: AuditFormat,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args, Debug)]
+pub struct ExportDataArgs {
+    /// Table to print: countries, continents, dmas, time-zones or centroids.
+    pub table: Table,
+
+    /// Format of the table: csv or json.
+    #[arg(long = "format", default_value = "csv")]
+    pub format: Format,
 }
@@ -0,0 +1,182 @@
+//! The entry points of the 0.1 API whose behavior changed, kept until 0.3 as deprecated wrappers
+//! over their replacements.
+//!
+//! Each wrapper panics where the 0.1 entry point panicked, with a message naming its replacement.
+//!
+//! With the `compat-geoip` feature, `geoip` mirrors the surface of the unmaintained `geoip`
+//! crate instead.
+
+#[cfg(feature = "compat-geoip")]
+pub mod geoip;
+
+use crate::geo_ip_reader::{GeoIpReader, Record};
+use crate::time_zones::COUNTRY_DICT;
+use crate::utils::try_ip_to_number;
+use std::io::{Read, Seek};
+
+/// The migration guide from the 0.1 API, printed by `ipcap migrate-help`.
+#[doc(hidden)]
+pub const MIGRATION: &str = "\
+Migrating from the ipcap 0.1 API
+================================
+
+The 0.1 entry points below keep working until 0.3 through `ipcap::compat`, with a
+deprecation warning naming their replacement.
+
+  GeoIpReader::get_record(&str) -> Record
+      now returns Result<Record, GeoIpReaderError>: AddressNotFound for an address
+      that is not in the database, InvalidAddress for an input that is not an IP
+      address. Use `GeoIpReader::find_record` to get Ok(None) for a missing address.
+      Panicking wrapper: `ipcap::compat::get_record(&mut reader, ip)`.
+
+  GeoIpReader::get_time_zone_given_ip_addr(&str) -> &str
+      now returns Result<&str, GeoIpReaderError> with the errors of get_record.
+      Panicking wrapper: `ipcap::compat::get_time_zone_given_ip_addr(&mut reader, ip)`.
+
+  utils::ip_to_number(&str) -> u128
+      panics on an invalid address. Use `utils::try_ip_to_number`, which returns
+      Result<u128, GeoIpReaderError>, or `utils::normalize_ip_text` to parse an
+      address with leading zeros.
+
+  time_zones::time_zone_by_country(&str, &str, Option<&str>) -> Option<&str>
+      now takes the region as an Option<&str> and falls back to the time zone of
+      the country as a whole: `time_zone_by_country(\"US\", Some(\"CA\"))`, and
+      `time_zone_by_country(\"AD\", None)` for `(\"AD\", \"default\", None)`.
+      Wrapper: `ipcap::compat::time_zone_by_country(country, key1, key2)`.
+
+GeoIpReader::new(&str) is unchanged.
+";
+
+/// Looks up the record of an IP address, panicking where `GeoIpReader::get_record` fails, as
+/// the 0.1 `get_record` did.
+///
+/// # Panics
+///
+/// Panics if the address is invalid, not in the database, or the database is corrupted.
+///
+/// # Examples
+///
+/// ```
+/// # #![allow(deprecated)]
+/// use std::fs::File;
+/// use ipcap::compat;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let record = compat::get_record(&mut geo_ip, "108.95.4.105");
+///
+/// assert_eq!(record.city(), Some("San Diego"));
+/// ```
+#[deprecated(
+    since = "0.1.7",
+    note = "use `GeoIpReader::get_record`, which returns a `Result`, or `GeoIpReader::find_record`"
+)]
+pub fn get_record<R: Read + Seek>(reader: &mut GeoIpReader<R>, ip: &str) -> Record<'static> {
+    reader.get_record(ip).unwrap_or_else(|err| {
+        panic!(
+            "{}; use `GeoIpReader::get_record`, which returns a `Result`",
+            err
+        )
+    })
+}
+
+/// Returns the time zone of an IP address, panicking where
+/// `GeoIpReader::get_time_zone_given_ip_addr` fails, as the 0.1 method did.
+///
+/// # Panics
+///
+/// Panics in the cases of `get_record`.
+#[deprecated(
+    since = "0.1.7",
+    note = "use `GeoIpReader::get_time_zone_given_ip_addr`, which returns a `Result`"
+)]
+pub fn get_time_zone_given_ip_addr<'a, R: Read + Seek>(
+    reader: &'a mut GeoIpReader<R>,
+    ip: &str,
+) -> &'a str {
+    reader
+        .get_time_zone_given_ip_addr(ip)
+        .unwrap_or_else(|err| {
+            panic!(
+                "{}; use `GeoIpReader::get_time_zone_given_ip_addr`, which returns a `Result`",
+                err
+            )
+        })
+}
+
+/// Converts an IP address in string format to a 128-bit unsigned integer representation.
+///
+/// This function takes a string representing an IP address and converts it into a 128-bit
+/// unsigned integer. It supports both IPv4 and IPv6 addresses. The result is the numeric
+/// representation of the IP address.
+///
+/// # Arguments
+///
+/// * `ip` - A string slice containing the IP address.
+///
+/// # Returns
+///
+/// (`u128`): A 128-bit unsigned integer representation of the IP address.
+///
+/// # Panics
+///
+/// This function will panic if the input string does not represent a valid IPv4 or IPv6 address.
+/// Use `try_ip_to_number` to handle invalid addresses instead.
+///
+/// # Examples
+///
+/// ```
+/// # #![allow(deprecated)]
+/// use ipcap::utils::ip_to_number;
+///
+/// let ipv4_address = "1.32.0.0";
+/// let ipv6_address = "2001:0db8:85a3:0000:0000:8a2e:0370:7334";
+///
+/// let ipv4_numeric = ip_to_number(ipv4_address);
+/// let ipv6_numeric = ip_to_number(ipv6_address);
+///
+/// assert_eq!(ipv4_numeric, 18874368);
+/// assert_eq!(ipv6_numeric, 42540766452641154071740215577757643572);
+/// ```
+#[deprecated(
+    since = "0.1.7",
+    note = "use `utils::try_ip_to_number`, which returns a `Result`"
+)]
+pub fn ip_to_number(ip: &str) -> u128 {
+    try_ip_to_number(ip).unwrap_or_else(|_| {
+        panic!(
+            "Invalid IP address: {}; use `utils::try_ip_to_number`, which returns a `Result`",
+            ip
+        )
+    })
+}
+
+/// Returns the time zone of the entry `key2`, or `key1` without it, of a country of
+/// `COUNTRY_DICT`, as the 0.1 `time_zone_by_country` did, without any fallback.
+///
+/// # Examples
+///
+/// ```
+/// # #![allow(deprecated)]
+/// use ipcap::compat;
+///
+/// assert_eq!(
+///     compat::time_zone_by_country("AD", "default", None),
+///     Some("Europe/Andorra")
+/// );
+/// assert_eq!(compat::time_zone_by_country("AD", "07", None), None);
+/// ```
+#[deprecated(
+    since = "0.1.7",
+    note = "use `time_zones::time_zone_by_country`, which takes the region as an `Option`"
+)]
+pub fn time_zone_by_country<'a>(
+    country_code: &str,
+    key1: &str,
+    key2: Option<&str>,
+) -> Option<&'a str> {
+    COUNTRY_DICT
+        .get(country_code)
+        .and_then(|regions| regions.get(key2.unwrap_or(key1)))
+        .copied()
+}
@@ -0,0 +1,276 @@
+//! The core surface of the unmaintained `geoip` crate, the bindings of the legacy GeoIP C
+//! library, over `GeoIpReader`, so that its call sites keep compiling with `ipcap::compat::geoip`
+//! in place of `geoip`.
+//!
+//! The types are redefined here, the `geoip` crate and the C library are not needed. Their
+//! behavior differs from the originals in a few ways:
+//!
+//! - `GeoIp::open` fails with the message of the `GeoIpReaderError` rather than of the C
+//!   library.
+//! - The lookups return `None` for any failure, e.g. an address of another family than the
+//!   database, as well as for an address that is not in it: `GeoIpReader` tells them apart.
+//! - The coordinates of a record without real ones, e.g. of a Region database, are `0.0`, where
+//!   `Record::has_real_coordinates` is false. The DMA and area codes of a record without a
+//!   designated market area are `0`, as in the C library.
+//! - The strings are owned copies of the fields of the `Record`, in UTF-8 rather than in the
+//!   charset of the database.
+
+use crate::geo_ip_reader::{GeoIpReader, Record};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The address type of the lookups, whose `V4` and `V6` variants are those of the `IpAddr` of
+/// the `geoip` crate.
+pub use std::net::IpAddr;
+
+/// How the C library read the database file.
+///
+/// The database is always read through the file with the caching of `GeoIpReader`, except for
+/// `CheckCache`, which reloads it when the file changes, see `GeoIpReader::reload_if_changed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Options {
+    #[default]
+    Standard,
+    MemoryCache,
+    CheckCache,
+    IndexCache,
+    MmapCache,
+}
+
+/// The location of an address, as returned by `GeoIp::city_info_by_ip`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CityInfo {
+    /// The ISO 3166-1 alpha-2 code of the country, e.g. `US`.
+    pub country_code: Option<String>,
+    /// The English name of the country.
+    pub country_name: Option<String>,
+    /// The code of the region, e.g. `CA` for California.
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub postal_code: Option<String>,
+    /// The latitude, `0.0` without real coordinates.
+    pub latitude: f32,
+    /// The longitude, `0.0` without real coordinates.
+    pub longitude: f32,
+    /// The designated market area code, `0` without one.
+    pub dma_code: u32,
+    /// The telephone area code of the designated market area, `0` without one.
+    pub area_code: u32,
+}
+
+impl From<&Record<'_>> for CityInfo {
+    fn from(record: &Record<'_>) -> Self {
+        let (latitude, longitude) = if record.has_real_coordinates() {
+            (record.latitude as f32, record.longitude as f32)
+        } else {
+            (0.0, 0.0)
+        };
+        CityInfo {
+            country_code: Some(record.country.alphabetic_code_2().to_string()),
+            country_name: Some(record.country.name().to_string()),
+            region: record.region_code().map(str::to_string),
+            city: record.city().map(str::to_string),
+            postal_code: record.postal_code().map(str::to_string),
+            latitude,
+            longitude,
+            dma_code: record.dma.map_or(0, |dma| dma.dma_code()),
+            area_code: record.dma.map_or(0, |dma| dma.area_code()),
+        }
+    }
+}
+
+/// The country of an address, as returned by `GeoIp::country_info_by_ip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountryInfo {
+    /// The ISO 3166-1 alpha-2 code of the country, e.g. `US`.
+    pub country_code: String,
+    /// The English name of the country.
+    pub country_name: String,
+}
+
+/// A database opened as with the `geoip` crate.
+///
+/// The lookups take `&self` as in the `geoip` crate, the reader being behind a lock.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::compat::geoip::{GeoIp, IpAddr, Options};
+/// use std::path::Path;
+///
+/// let path = dirs::home_dir().unwrap().join("ipcap/geo_ip_city_v4.dat");
+/// let geoip = GeoIp::open(&path, Options::MemoryCache).unwrap();
+///
+/// let ip = IpAddr::V4("108.95.4.105".parse().unwrap());
+/// let info = geoip.city_info_by_ip(ip).unwrap();
+/// assert_eq!(info.city.as_deref(), Some("San Diego"));
+/// assert_eq!(info.dma_code, 825);
+/// assert_eq!(geoip.country_info_by_ip(ip).unwrap().country_code, "US");
+///
+/// assert!(GeoIp::open(Path::new("missing.dat"), Options::Standard).is_err());
+/// ```
+#[derive(Debug)]
+pub struct GeoIp {
+    reader: Mutex<GeoIpReader<File>>,
+    options: Options,
+}
+
+impl GeoIp {
+    /// Opens the database file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns the message of the error of `GeoIpReader::from_path`.
+    pub fn open<P: AsRef<Path>>(path: P, options: Options) -> Result<GeoIp, String> {
+        let reader = GeoIpReader::<File>::from_path(path).map_err(|err| err.to_string())?;
+        Ok(GeoIp {
+            reader: Mutex::new(reader),
+            options,
+        })
+    }
+
+    /// Looks up the location of an address, `None` if it is not in the database or cannot be
+    /// looked up.
+    pub fn city_info_by_ip(&self, ip: IpAddr) -> Option<CityInfo> {
+        self.find_record(ip).as_ref().map(CityInfo::from)
+    }
+
+    /// Looks up the country of an address, `None` if it is not in the database or cannot be
+    /// looked up.
+    pub fn country_info_by_ip(&self, ip: IpAddr) -> Option<CountryInfo> {
+        self.find_record(ip).map(|record| CountryInfo {
+            country_code: record.country.alphabetic_code_2().to_string(),
+            country_name: record.country.name().to_string(),
+        })
+    }
+
+    fn find_record(&self, ip: IpAddr) -> Option<Record<'static>> {
+        // A lookup that panicked left the reader as consistent as after an error
+        let mut reader = self
+            .reader
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.options == Options::CheckCache {
+            // A failed reload keeps the database that was opened
+            let _ = reader.reload_if_changed();
+        }
+        reader.find_record(&ip.to_string()).ok().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CITY_EDITION_REV1;
+    use crate::countries::Country;
+    use crate::designated_market_area::DesignatedMarketArea;
+    use crate::fixtures::city_database;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::path::PathBuf;
+
+    /// Writes a database to a file of the temporary directory.
+    fn write(name: &str, database: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ipcap_test_geoip_{}_{}", std::process::id(), name));
+        std::fs::write(&path, database).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_city_info_matches_record() {
+        let mut database = DatabaseBuilder::new();
+        database
+            .insert(
+                "1.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates)
+                    .region_code("CA")
+                    .city("San Diego")
+                    .postal_code("92109")
+                    .coordinates(32.7977, -117.2335)
+                    .dma(DesignatedMarketArea(825858)),
+            )
+            .unwrap()
+            .insert("2.0.0.0/8", RecordSpec::new(Country::France))
+            .unwrap();
+        let path = write("city.dat", &database.build());
+        let geoip = GeoIp::open(&path, Options::Standard).unwrap();
+        let mut reader = GeoIpReader::<File>::from_path(&path).unwrap();
+
+        for ip in ["1.2.3.4", "2.2.3.4"] {
+            let record = reader.get_record(ip).unwrap();
+            let info = geoip.city_info_by_ip(ip.parse().unwrap()).unwrap();
+            assert_eq!(
+                info.country_code.as_deref(),
+                Some(record.country.alphabetic_code_2())
+            );
+            assert_eq!(info.country_name.as_deref(), Some(record.country.name()));
+            assert_eq!(info.region.as_deref(), record.region_code());
+            assert_eq!(info.city.as_deref(), record.city());
+            assert_eq!(info.postal_code.as_deref(), record.postal_code());
+            if record.has_real_coordinates() {
+                assert_eq!(info.latitude, record.latitude as f32);
+                assert_eq!(info.longitude, record.longitude as f32);
+            } else {
+                assert_eq!((info.latitude, info.longitude), (0.0, 0.0));
+            }
+            assert_eq!(info.dma_code, record.dma.map_or(0, |dma| dma.dma_code()));
+            assert_eq!(info.area_code, record.dma.map_or(0, |dma| dma.area_code()));
+
+            let country = geoip.country_info_by_ip(ip.parse().unwrap()).unwrap();
+            assert_eq!(country.country_code, record.country.alphabetic_code_2());
+            assert_eq!(country.country_name, record.country.name());
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_data() {
+        let mut database = DatabaseBuilder::new();
+        database
+            .insert("2.0.0.0/8", RecordSpec::new(Country::France))
+            .unwrap();
+        let path = write("sparse.dat", &database.build());
+        let geoip = GeoIp::open(&path, Options::CheckCache).unwrap();
+
+        // A record without strings nor coordinates nor DMA
+        let info = geoip.city_info_by_ip("2.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(
+            info,
+            CityInfo {
+                country_code: Some("FR".to_string()),
+                country_name: Some("France".to_string()),
+                region: None,
+                city: None,
+                postal_code: None,
+                latitude: 0.0,
+                longitude: 0.0,
+                dma_code: 0,
+                area_code: 0,
+            }
+        );
+
+        // Neither an address that is not in the database nor one of another family
+        assert_eq!(geoip.city_info_by_ip("3.0.0.1".parse().unwrap()), None);
+        assert_eq!(geoip.country_info_by_ip("3.0.0.1".parse().unwrap()), None);
+        assert_eq!(geoip.city_info_by_ip("2a08::1".parse().unwrap()), None);
+
+        // CheckCache reads the database again when the file changes
+        std::fs::write(&path, city_database(CITY_EDITION_REV1)).unwrap();
+        let info = geoip.city_info_by_ip("200.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(info.city.as_deref(), Some("Zürich"));
+        assert!((info.latitude - 47.37).abs() < 1e-4);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_error() {
+        let path = write("empty.dat", &[]);
+        let err = GeoIp::open(&path, Options::Standard).unwrap_err();
+        assert!(err.contains(&path.display().to_string()), "{}", err);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -3,6 +3,13 @@ pub const COUNTRY_BEGIN: u32 = 16776960; // Country data begin offset
 pub const STATE_BEGIN_REV0: u32 = 16700000; // State data begin offset (Database revision 0)
 pub const STATE_BEGIN_REV1: u32 = 16000000; // State data begin offset (Database revision 1)
 pub const STRUCTURE_INFO_MAX_SIZE: u32 = 20; // Maximum size of structure information
+pub const MIN_DATABASE_SIZE: u64 = 13; // Minimum database file size: one node, then the shortest structure info
+
+// Region database offsets, relative to STATE_BEGIN_REV1
+pub const US_OFFSET: u32 = 1; // First United States region offset
+pub const CANADA_OFFSET: u32 = 677; // First Canada region offset
+pub const WORLD_OFFSET: u32 = 1353; // First offset of the other countries
+pub const FIPS_RANGE: u32 = 360; // Number of offsets per country past WORLD_OFFSET
 
 // Database editions
 pub const COUNTRY_EDITION: u8 = 1; // Country edition identifier
@@ -21,3 +28,4 @@ pub const SEGMENT_RECORD_LENGTH: usize = 3; // Length of a segment record
 pub const STANDARD_RECORD_LENGTH: usize = 3; // Standard record length
 pub const ORG_RECORD_LENGTH: usize = 4; // Organization record length
 pub const FULL_RECORD_LENGTH: usize = 50; // Full record length
+pub const MAX_ORG_RECORD_LENGTH: usize = 255; // Maximum length of an organization or ASN record
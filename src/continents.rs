@@ -1,5 +1,6 @@
 use crate::codegen;
 use crate::countries::Country;
+use crate::locale::Locale;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -14,6 +15,17 @@ pub enum Continent {
 }
 
 impl Continent {
+    /// The continents, in alphabetical order.
+    pub const ALL: [Continent; 7] = [
+        Continent::Africa,
+        Continent::Antarctica,
+        Continent::Asia,
+        Continent::Europe,
+        Continent::NorthAmerica,
+        Continent::Oceania,
+        Continent::SouthAmerica,
+    ];
+
     pub fn alphabetic_code_2(&self) -> &'static str {
         match self {
             Continent::Africa => "AF",
@@ -25,19 +37,69 @@ impl Continent {
             Continent::SouthAmerica => "SA",
         }
     }
+
+    /// The name of the continent in the given locale, as displayed in English.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::continents::Continent;
+    /// use ipcap::locale::Locale;
+    ///
+    /// assert_eq!(Continent::Europe.name_in(Locale::De), "Europa");
+    /// assert_eq!(Continent::NorthAmerica.name_in(Locale::Fr), "Amérique du Nord");
+    /// assert_eq!(Continent::NorthAmerica.name_in(Locale::En), "North America");
+    /// ```
+    pub fn name_in(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Continent::Africa, Locale::En) => "Africa",
+            (Continent::Africa, Locale::Fr) => "Afrique",
+            (Continent::Africa, Locale::De) => "Afrika",
+            (Continent::Africa, Locale::Es) => "África",
+            (Continent::Antarctica, Locale::En) => "Antarctica",
+            (Continent::Antarctica, Locale::Fr) => "Antarctique",
+            (Continent::Antarctica, Locale::De) => "Antarktis",
+            (Continent::Antarctica, Locale::Es) => "Antártida",
+            (Continent::Asia, Locale::En | Locale::Es) => "Asia",
+            (Continent::Asia, Locale::Fr) => "Asie",
+            (Continent::Asia, Locale::De) => "Asien",
+            (Continent::Europe, Locale::En | Locale::Fr) => "Europe",
+            (Continent::Europe, Locale::De | Locale::Es) => "Europa",
+            (Continent::NorthAmerica, Locale::En) => "North America",
+            (Continent::NorthAmerica, Locale::Fr) => "Amérique du Nord",
+            (Continent::NorthAmerica, Locale::De) => "Nordamerika",
+            (Continent::NorthAmerica, Locale::Es) => "América del Norte",
+            (Continent::Oceania, Locale::En) => "Oceania",
+            (Continent::Oceania, Locale::Fr) => "Océanie",
+            (Continent::Oceania, Locale::De) => "Ozeanien",
+            (Continent::Oceania, Locale::Es) => "Oceanía",
+            (Continent::SouthAmerica, Locale::En) => "South America",
+            (Continent::SouthAmerica, Locale::Fr) => "Amérique du Sud",
+            (Continent::SouthAmerica, Locale::De) => "Südamerika",
+            (Continent::SouthAmerica, Locale::Es) => "América del Sur",
+        }
+    }
+
+    /// Returns whether the country belongs to this continent, e.g. to validate a (country,
+    /// continent) pair of a third-party source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::continents::Continent;
+    /// use ipcap::countries::Country;
+    ///
+    /// assert!(Continent::NorthAmerica.contains_country(Country::UnitedStates));
+    /// assert!(!Continent::Europe.contains_country(Country::UnitedStates));
+    /// ```
+    pub fn contains_country(&self, country: Country) -> bool {
+        country.continent() == Some(*self)
+    }
 }
 
 impl Display for Continent {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Continent::Africa => f.write_str("Africa"),
-            Continent::Antarctica => f.write_str("Antarctica"),
-            Continent::Asia => f.write_str("Asia"),
-            Continent::Europe => f.write_str("Europe"),
-            Continent::NorthAmerica => f.write_str("North America"),
-            Continent::Oceania => f.write_str("Oceania"),
-            Continent::SouthAmerica => f.write_str("South America"),
-        }
+        f.write_str(self.name_in(Locale::En))
     }
 }
 
@@ -46,3 +108,79 @@ impl From<&Country> for Option<Continent> {
         codegen!("country-to-continent")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_country() {
+        assert!(Continent::Europe.contains_country(Country::Poland));
+        assert!(Continent::Asia.contains_country(Country::Japan));
+        assert!(!Continent::Europe.contains_country(Country::UnitedStates));
+        assert!(!Continent::Africa.contains_country(Country::Japan));
+    }
+
+    #[test]
+    fn test_name_in() {
+        let names = |locale| Continent::ALL.map(|continent| continent.name_in(locale));
+
+        assert_eq!(
+            names(Locale::En).map(str::to_string),
+            Continent::ALL.map(|continent| continent.to_string())
+        );
+        assert_eq!(
+            names(Locale::Fr),
+            [
+                "Afrique",
+                "Antarctique",
+                "Asie",
+                "Europe",
+                "Amérique du Nord",
+                "Océanie",
+                "Amérique du Sud"
+            ]
+        );
+        assert_eq!(
+            names(Locale::De),
+            [
+                "Afrika",
+                "Antarktis",
+                "Asien",
+                "Europa",
+                "Nordamerika",
+                "Ozeanien",
+                "Südamerika"
+            ]
+        );
+        assert_eq!(
+            names(Locale::Es),
+            [
+                "África",
+                "Antártida",
+                "Asia",
+                "Europa",
+                "América del Norte",
+                "Oceanía",
+                "América del Sur"
+            ]
+        );
+        // A language without translations is displayed in English
+        assert_eq!(names(Locale::from_tag("it")), names(Locale::En));
+    }
+
+    #[test]
+    fn test_contains_country_matches_continent() {
+        for country in Country::all() {
+            let containing: Vec<_> = Continent::ALL
+                .iter()
+                .filter(|continent| continent.contains_country(country))
+                .copied()
+                .collect();
+            assert_eq!(
+                containing,
+                country.continent().into_iter().collect::<Vec<_>>()
+            );
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::codegen;
 use crate::continents::Continent;
+use crate::locale::Locale;
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 
 codegen!(statement; "countries-enum-values");
@@ -9,6 +11,31 @@ impl Country {
         codegen!("countries-from-buffer")
     }
 
+    /// Returns every country, in the order of their database identifier.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// let countries: Vec<Country> = Country::all().collect();
+    /// assert_eq!(countries.len(), 254);
+    /// assert_eq!(countries[0].to_buffer(), 1);
+    /// ```
+    pub fn all() -> impl Iterator<Item = Country> {
+        (1..=u8::MAX).filter_map(Self::from_buffer)
+    }
+
+    /// The inverse of `from_buffer`: the byte identifying the country in a database record.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// let byte = Country::Poland.to_buffer();
+    /// assert_eq!(Country::from_buffer(byte), Some(Country::Poland))
+    /// ```
+    pub fn to_buffer(&self) -> u8 {
+        *self as u8 + 1
+    }
+
     /// ```rust
     /// use ipcap::countries::Country;
     /// let country = Country::Poland;
@@ -47,6 +74,59 @@ impl Country {
         codegen!("countries-codes-3-reverse")
     }
 
+    /// Looks up a country by either its 2-letter or 3-letter code, case-insensitively.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// assert_eq!(Country::from_code("US"), Some(Country::UnitedStates));
+    /// assert_eq!(Country::from_code("usa"), Some(Country::UnitedStates));
+    /// assert_eq!(Country::from_code("Pl"), Some(Country::Poland));
+    /// assert_eq!(Country::from_code("USAA"), None);
+    /// assert_eq!(Country::from_code(""), None);
+    /// ```
+    pub fn from_code(value: &str) -> Option<Self> {
+        let value = value.to_ascii_uppercase();
+        match value.len() {
+            2 => Self::from_alphabetic_code_2(&value),
+            3 => Self::from_alphabetic_code_3(&value),
+            _ => None,
+        }
+    }
+
+    /// The English name of the country, as displayed.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// assert_eq!(Country::Poland.name(), "Poland");
+    /// assert_eq!(Country::UnitedStates.to_string(), Country::UnitedStates.name());
+    /// ```
+    pub fn name(&self) -> &'static str {
+        codegen!("countries-names")
+    }
+
+    /// The name of the country in the given locale, falling back to the English name for the
+    /// countries without a translation. Only the most common countries are translated.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    /// use ipcap::locale::Locale;
+    ///
+    /// assert_eq!(Country::Germany.name_in(Locale::Fr), "Allemagne");
+    /// assert_eq!(Country::Germany.name_in(Locale::De), "Deutschland");
+    /// assert_eq!(Country::Germany.name_in(Locale::En), Country::Germany.name());
+    /// assert_eq!(Country::Andorra.name_in(Locale::Es), "Andorra");
+    /// ```
+    pub fn name_in(&self, locale: Locale) -> &'static str {
+        self.translated_name(locale).unwrap_or_else(|| self.name())
+    }
+
+    /// The name of the country in the given locale, `None` if it is not translated.
+    fn translated_name(&self, locale: Locale) -> Option<&'static str> {
+        codegen!("countries-names-localized")
+    }
+
     /// ```rust
     /// use ipcap::continents::Continent;
     /// use ipcap::countries::Country;
@@ -56,10 +136,305 @@ impl Country {
     pub fn continent(&self) -> Option<Continent> {
         self.into()
     }
+
+    /// The approximate center of the country as `(latitude, longitude)`, in degrees, `None` for
+    /// the entries that are not a territory such as `AnonymousProxy` or `Europe`.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// assert_eq!(Country::UnitedKingdom.centroid(), Some((54.0, -2.0)));
+    /// assert_eq!(Country::AnonymousProxy.centroid(), None);
+    /// ```
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        codegen!("countries-centroids")
+    }
+
+    /// The capital, area and population of the country, `None` for the entries that are not a
+    /// territory such as `AnonymousProxy` or `Europe`. The figures are as of
+    /// `CountryMetadata::AS_OF`.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// let metadata = Country::Poland.metadata().unwrap();
+    /// assert_eq!(metadata.capital, Some("Warsaw"));
+    /// assert_eq!(Country::AnonymousProxy.metadata(), None);
+    /// ```
+    pub fn metadata(&self) -> Option<CountryMetadata> {
+        codegen!("countries-metadata")
+    }
+
+    /// Converts a region code of the database, FIPS 10-4 for most countries and the postal
+    /// abbreviation for the United States and Canada, to its ISO 3166-2 code. The conversion is
+    /// best-effort: only the regions of Australia, Canada, Germany and the United States are
+    /// known.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// assert_eq!(Country::UnitedStates.iso_region_code("CA"), Some("US-CA"));
+    /// assert_eq!(Country::Australia.iso_region_code("02"), Some("AU-NSW"));
+    /// assert_eq!(Country::Poland.iso_region_code("72"), None);
+    /// ```
+    pub fn iso_region_code(&self, fips: &str) -> Option<&'static str> {
+        codegen!("regions-fips-to-iso")
+    }
+
+    /// Whether the code of the country is current in ISO 3166-1, deprecated with the countries
+    /// replacing it, or reserved for something else than a country, such as `AnonymousProxy`
+    /// or `Europe`. Older databases still carry deprecated codes, which current ISO data lacks.
+    ///
+    /// ```rust
+    /// use ipcap::countries::{Country, CountryStatus};
+    ///
+    /// assert_eq!(Country::Poland.status(), CountryStatus::Current);
+    /// assert_eq!(
+    ///     Country::MetropolitanFrance.status(),
+    ///     CountryStatus::Deprecated { successors: &[Country::France] }
+    /// );
+    /// assert_eq!(Country::AnonymousProxy.status(), CountryStatus::Reserved);
+    /// ```
+    pub fn status(&self) -> CountryStatus {
+        codegen!("countries-status")
+    }
+
+    /// The current country of a deprecated one, e.g. to join records of an older database
+    /// against current ISO data, and the country itself otherwise.
+    ///
+    /// A deprecated country split into several successors gives the most populous of them, as
+    /// per `CountryMetadata::population`. Of the successors of the Netherlands Antilles, only
+    /// Bonaire, Sint Eustatius and Saba is a country of the databases, Curaçao and Sint Maarten
+    /// are not, so it gives Bonaire, Sint Eustatius and Saba.
+    ///
+    /// ```rust
+    /// use ipcap::countries::Country;
+    ///
+    /// assert_eq!(Country::MetropolitanFrance.modernize(), Country::France);
+    /// assert_eq!(Country::NetherlandsAntilles.modernize(), Country::Bonaire);
+    /// assert_eq!(Country::Poland.modernize(), Country::Poland);
+    /// ```
+    pub fn modernize(&self) -> Country {
+        match self.status() {
+            CountryStatus::Deprecated { successors } => {
+                successors.first().copied().unwrap_or(*self)
+            }
+            CountryStatus::Current | CountryStatus::Reserved => *self,
+        }
+    }
+
+    /// The boxes covering the territory of the country, as `[south, west, north, east]` in
+    /// degrees. A box crossing the antimeridian has its west bound greater than its east bound.
+    pub(crate) fn bounding_boxes(&self) -> &'static [[f64; 4]] {
+        codegen!("countries-bounds")
+    }
+}
+
+/// The status of the code of a country in ISO 3166-1, see `Country::status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountryStatus {
+    /// A code of ISO 3166-1.
+    Current,
+    /// A code removed from ISO 3166-1, with the current countries replacing it, the most
+    /// populous first.
+    Deprecated { successors: &'static [Country] },
+    /// A code of the databases standing for something else than a country, e.g. a continent or
+    /// anonymous proxies.
+    Reserved,
+}
+
+/// Static facts about a country, for displaying a profile of it without a second data source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountryMetadata {
+    /// The seat of government, `None` for the territories without one such as Antarctica or
+    /// Hong Kong.
+    pub capital: Option<&'static str>,
+    /// The total area, land and inland water, in square kilometres.
+    pub area_km2: f64,
+    /// The population estimate, 0 for the territories without a permanent population.
+    pub population: u64,
+}
+
+impl CountryMetadata {
+    /// The year of the figures: the populations are the estimates of the United Nations World
+    /// Population Prospects 2022 for that year, or of the national statistics offices for the
+    /// territories it does not cover, the areas those of the CIA World Factbook.
+    pub const AS_OF: &'static str = "2023";
 }
 
 impl Display for Country {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        codegen!("countries-to-names")
+        f.write_str(self.name())
+    }
+}
+
+/// Countries are ordered alphabetically by their name, so that sorting a list of countries gives
+/// the order a reader expects rather than the order of their database identifiers. Compare
+/// `to_buffer` or `alphabetic_code_2` for those orders instead.
+///
+/// ```rust
+/// use ipcap::countries::Country;
+///
+/// let mut countries = vec![Country::Poland, Country::Austria, Country::Germany];
+/// countries.sort();
+///
+/// assert_eq!(countries, [Country::Austria, Country::Germany, Country::Poland]);
+/// ```
+impl Ord for Country {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name().cmp(other.name())
+    }
+}
+
+impl PartialOrd for Country {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_by_name() {
+        let mut countries: Vec<Country> = Country::all().collect();
+        countries.sort();
+
+        let names: Vec<&str> = countries.iter().map(Country::name).collect();
+        let mut sorted_names = names.clone();
+        sorted_names.sort();
+        sorted_names.dedup();
+        assert_eq!(names, sorted_names);
+
+        let mut countries = vec![
+            Country::UnitedStates,
+            Country::Canada,
+            Country::Zimbabwe,
+            Country::Afghanistan,
+        ];
+        countries.sort();
+        assert_eq!(
+            countries,
+            [
+                Country::Afghanistan,
+                Country::Canada,
+                Country::UnitedStates,
+                Country::Zimbabwe
+            ]
+        );
+        assert!(Country::Germany < Country::Poland);
+        assert_eq!(Country::Poland.cmp(&Country::Poland), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_name_in() {
+        for country in Country::all() {
+            assert_eq!(country.name_in(Locale::En), country.name());
+            for locale in Locale::ALL {
+                assert!(!country.name_in(locale).is_empty());
+            }
+        }
+        assert_eq!(
+            Locale::ALL.map(|locale| Country::UnitedStates.name_in(locale)),
+            [
+                "United States",
+                "États-Unis",
+                "Vereinigte Staaten",
+                "Estados Unidos"
+            ]
+        );
+        // Not translated
+        assert_eq!(Country::Zimbabwe.name_in(Locale::De), "Zimbabwe");
+    }
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(
+            Country::Germany.metadata(),
+            Some(CountryMetadata {
+                capital: Some("Berlin"),
+                area_km2: 357022.0,
+                population: 83294633,
+            })
+        );
+
+        let united_states = Country::UnitedStates.metadata().unwrap();
+        assert_eq!(united_states.capital, Some("Washington, D.C."));
+        assert!(united_states.population > 300_000_000);
+
+        let vatican = Country::HolySee.metadata().unwrap();
+        assert_eq!(vatican.area_km2, 0.44);
+
+        let antarctica = Country::Antarctica.metadata().unwrap();
+        assert_eq!((antarctica.capital, antarctica.population), (None, 0));
+
+        // Exactly the territories have metadata
+        for country in Country::all() {
+            assert_eq!(
+                country.metadata().is_some(),
+                country.centroid().is_some(),
+                "{:?}",
+                country
+            );
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(
+            Country::NetherlandsAntilles.status(),
+            CountryStatus::Deprecated {
+                successors: &[Country::Bonaire]
+            }
+        );
+        assert_eq!(Country::NetherlandsAntilles.modernize(), Country::Bonaire);
+        assert_eq!(Country::MetropolitanFrance.modernize(), Country::France);
+        assert_eq!(Country::Germany.modernize(), Country::Germany);
+        assert_eq!(Country::Europe.status(), CountryStatus::Reserved);
+        assert_eq!(Country::Europe.modernize(), Country::Europe);
+
+        for country in Country::all() {
+            match country.status() {
+                CountryStatus::Current => assert_eq!(country.modernize(), country),
+                CountryStatus::Deprecated { successors } => {
+                    assert!(!successors.is_empty(), "{:?}", country);
+                    for successor in successors {
+                        assert_eq!(successor.status(), CountryStatus::Current, "{:?}", country);
+                    }
+                    assert_eq!(country.modernize(), successors[0]);
+                }
+                // The pseudo-countries, none of which is a territory
+                CountryStatus::Reserved => assert_eq!(country.centroid(), None, "{:?}", country),
+            }
+        }
+    }
+
+    #[test]
+    fn test_iso_region_code() {
+        assert_eq!(Country::UnitedStates.iso_region_code("NY"), Some("US-NY"));
+        assert_eq!(Country::Canada.iso_region_code("QC"), Some("CA-QC"));
+        assert_eq!(Country::Germany.iso_region_code("16"), Some("DE-BE"));
+        assert_eq!(Country::Australia.iso_region_code("08"), Some("AU-WA"));
+
+        // The codes are only converted within their country
+        assert_eq!(Country::Canada.iso_region_code("NY"), None);
+        assert_eq!(Country::UnitedStates.iso_region_code("02"), None);
+        assert_eq!(Country::UnitedStates.iso_region_code(""), None);
+
+        // Every region of the time zone mappings of these countries converts
+        for (code, country) in [
+            ("AU", Country::Australia),
+            ("CA", Country::Canada),
+            ("US", Country::UnitedStates),
+        ] {
+            for (_, region, _) in crate::time_zones::time_zone_mappings()
+                .into_iter()
+                .filter(|(country, _, _)| *country == code)
+            {
+                let iso = country.iso_region_code(region).unwrap();
+                assert_eq!(iso, format!("{}-{}", code, &iso[3..]), "{}", region);
+            }
+        }
     }
 }
@@ -0,0 +1,375 @@
+//! Export of the reference data compiled into the crate, e.g. for the tools that validate
+//! configurations or fill dropdowns without linking to Rust.
+//!
+//! The tables are read from the same data as the lookups, and their rows are sorted, so that
+//! the exports of two versions of the crate differ only where their data does.
+
+use crate::continents::Continent;
+use crate::countries::Country;
+use crate::designated_market_area::DesignatedMarketArea;
+use crate::time_zones::time_zone_mappings;
+use crate::utils::json_string;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// The format of an exported table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// A header line with the column names, then one line per row.
+    #[default]
+    Csv,
+    /// An array of objects keyed by the column names, one per row.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!(
+                "invalid format '{}', expected one of csv, json",
+                value
+            )),
+        }
+    }
+}
+
+/// A table of reference data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    /// The countries by database identifier: `index`, `alpha2`, `alpha3`, `name`, `continent`.
+    Countries,
+    /// The continents by code: `code`, `name`.
+    Continents,
+    /// The designated market areas by code: `code`, `name`.
+    Dmas,
+    /// The time zones by country and region code: `country`, `region`, `zone`.
+    TimeZones,
+    /// The centroids of the countries by database identifier: `alpha2`, `latitude`, `longitude`.
+    Centroids,
+}
+
+impl Table {
+    /// The tables, in the order of their documentation.
+    pub const ALL: [Table; 5] = [
+        Table::Countries,
+        Table::Continents,
+        Table::Dmas,
+        Table::TimeZones,
+        Table::Centroids,
+    ];
+
+    /// The name of the table, as parsed by `from_str`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Countries => "countries",
+            Table::Continents => "continents",
+            Table::Dmas => "dmas",
+            Table::TimeZones => "time-zones",
+            Table::Centroids => "centroids",
+        }
+    }
+
+    /// The names of the columns of the table.
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            Table::Countries => &["index", "alpha2", "alpha3", "name", "continent"],
+            Table::Continents | Table::Dmas => &["code", "name"],
+            Table::TimeZones => &["country", "region", "zone"],
+            Table::Centroids => &["alpha2", "latitude", "longitude"],
+        }
+    }
+
+    /// The rows of the table, each holding one value per column.
+    pub fn rows(&self) -> Vec<Vec<Value>> {
+        match self {
+            Table::Countries => Country::all()
+                .map(|country| {
+                    vec![
+                        Value::Number(country.to_buffer().into()),
+                        Value::Text(country.alphabetic_code_2().into()),
+                        Value::Text(country.alphabetic_code_3().into()),
+                        Value::Text(country.name().into()),
+                        country.continent().map_or(Value::Null, |continent| {
+                            Value::Text(continent.alphabetic_code_2().into())
+                        }),
+                    ]
+                })
+                .collect(),
+            Table::Continents => {
+                let mut continents = Continent::ALL.to_vec();
+                continents.sort_by_key(Continent::alphabetic_code_2);
+                continents
+                    .into_iter()
+                    .map(|continent| {
+                        vec![
+                            Value::Text(continent.alphabetic_code_2().into()),
+                            Value::Text(continent.to_string()),
+                        ]
+                    })
+                    .collect()
+            }
+            Table::Dmas => DesignatedMarketArea::codes()
+                .iter()
+                .map(|&code| {
+                    vec![
+                        Value::Number(code.into()),
+                        Value::Text(DesignatedMarketArea(code * 1000).to_string()),
+                    ]
+                })
+                .collect(),
+            Table::TimeZones => time_zone_mappings()
+                .into_iter()
+                .map(|(country, region, zone)| {
+                    vec![
+                        Value::Text(country.into()),
+                        Value::Text(region.into()),
+                        Value::Text(zone.into()),
+                    ]
+                })
+                .collect(),
+            Table::Centroids => Country::all()
+                .filter_map(|country| {
+                    let (latitude, longitude) = country.centroid()?;
+                    Some(vec![
+                        Value::Text(country.alphabetic_code_2().into()),
+                        Value::Number(latitude),
+                        Value::Number(longitude),
+                    ])
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Table {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Table::ALL
+            .into_iter()
+            .find(|table| table.name() == value)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Table::ALL.iter().map(Table::name).collect();
+                format!(
+                    "invalid table '{}', expected one of {}",
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A value of an exported row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    /// A missing value, an empty field in CSV and `null` in JSON.
+    Null,
+}
+
+/// Writes a table in the given format.
+///
+/// # Arguments
+///
+/// * `table` - The table to write.
+/// * `format` - The format to write it in.
+/// * `writer` - The destination of the table.
+///
+/// # Errors
+///
+/// Returns the errors of `writer`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::data_export::{export, Format, Table};
+///
+/// let mut csv = Vec::new();
+/// export(Table::Continents, Format::Csv, &mut csv).unwrap();
+///
+/// let csv = String::from_utf8(csv).unwrap();
+/// assert!(csv.starts_with("code,name\nAF,Africa\n"));
+/// ```
+pub fn export<W: Write>(table: Table, format: Format, writer: &mut W) -> io::Result<()> {
+    let columns = table.columns();
+    let rows = table.rows();
+    match format {
+        Format::Csv => {
+            writeln!(writer, "{}", columns.join(","))?;
+            for row in rows {
+                let fields: Vec<String> = row.iter().map(csv_field).collect();
+                writeln!(writer, "{}", fields.join(","))?;
+            }
+        }
+        Format::Json => {
+            writeln!(writer, "[")?;
+            for (i, row) in rows.iter().enumerate() {
+                let fields: Vec<String> = columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| {
+                        format!("{}: {}", json_string(column), json_value(value))
+                    })
+                    .collect();
+                let separator = if i + 1 < rows.len() { "," } else { "" };
+                writeln!(writer, "  {{{}}}{}", fields.join(", "), separator)?;
+            }
+            writeln!(writer, "]")?;
+        }
+    }
+    Ok(())
+}
+
+/// Formats a CSV field, quoted when it holds a separator, a quote or a line break.
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Text(text) if text.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        }
+        Value::Text(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Null => String::new(),
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Text(text) => json_string(text),
+        Value::Number(number) => number.to_string(),
+        Value::Null => "null".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exported(table: Table, format: Format) -> String {
+        let mut output = Vec::new();
+        export(table, format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_row_counts() {
+        assert_eq!(Table::Countries.rows().len(), 254);
+        assert_eq!(Table::Continents.rows().len(), 7);
+        assert_eq!(
+            Table::Dmas.rows().len(),
+            DesignatedMarketArea::codes().len()
+        );
+        for table in Table::ALL {
+            for row in table.rows() {
+                assert_eq!(row.len(), table.columns().len(), "{}", table);
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_rows() {
+        let countries = exported(Table::Countries, Format::Csv);
+        assert!(countries.starts_with("index,alpha2,alpha3,name,continent\n"));
+        assert!(countries.contains("\n1,AP,AP,Asia/Pacific Region,AS\n"));
+        assert!(countries.contains("\n225,US,USA,United States,NA\n"));
+        assert!(countries.contains(",\"Micronesia, Federated States of\",OC\n"));
+
+        let dmas = exported(Table::Dmas, Format::Csv);
+        assert!(dmas.contains("\n501,\"New York, NY\"\n"));
+
+        let time_zones = exported(Table::TimeZones, Format::Csv);
+        assert!(time_zones.contains("\nAD,default,Europe/Andorra\n"));
+
+        let centroids = exported(Table::Centroids, Format::Json);
+        assert!(centroids.contains("{\"alpha2\": \"GB\", \"latitude\": 54, \"longitude\": -2}"));
+    }
+
+    #[test]
+    fn test_valid_json() {
+        for table in Table::ALL {
+            let json: serde_json::Value =
+                serde_json::from_str(&exported(table, Format::Json)).unwrap();
+            let rows = json.as_array().unwrap();
+            assert_eq!(rows.len(), table.rows().len(), "{}", table);
+            for row in rows {
+                let keys: Vec<&str> = row
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .map(String::as_str)
+                    .collect();
+                assert_eq!(keys.len(), table.columns().len());
+            }
+        }
+
+        let countries: serde_json::Value =
+            serde_json::from_str(&exported(Table::Countries, Format::Json)).unwrap();
+        let anonymous_proxy = countries
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|row| row["alpha2"] == "A1")
+            .unwrap();
+        assert_eq!(anonymous_proxy["continent"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_valid_csv() {
+        for table in Table::ALL {
+            let csv = exported(table, Format::Csv);
+            let mut lines = csv.lines();
+            assert_eq!(lines.next(), Some(table.columns().join(",").as_str()));
+            for line in lines {
+                // Split the fields outside of quotes
+                let mut fields = 1;
+                let mut quoted = false;
+                for char in line.chars() {
+                    match char {
+                        '"' => quoted = !quoted,
+                        ',' if !quoted => fields += 1,
+                        _ => {}
+                    }
+                }
+                assert!(!quoted, "{}", line);
+                assert_eq!(fields, table.columns().len(), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        for table in Table::ALL {
+            for format in [Format::Csv, Format::Json] {
+                assert_eq!(exported(table, format), exported(table, format));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sorted() {
+        let time_zones = time_zone_mappings();
+        assert!(time_zones.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let codes = DesignatedMarketArea::codes();
+        assert!(codes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("time-zones".parse::<Table>(), Ok(Table::TimeZones));
+        assert!("zones".parse::<Table>().is_err());
+        assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Installation of database files next to the readers using them.
+//!
+//! A database is never written in place: `atomic_replace` writes the new content to a temporary
+//! file of the destination directory, then renames it over the destination. A reader opening the
+//! path sees the old file or the new one, never a partial copy, and a reader that opened the old
+//! file keeps reading it until it is dropped.
+//!
+//! Writers are serialized by an exclusive advisory lock on a sidecar `<database>.lock` file,
+//! and readers built with `GeoIpReaderBuilder::lock_shared` hold a shared lock on the database
+//! file itself, for the tools that rewrite it in place under an exclusive lock. The locks are
+//! `flock` locks: on platforms or filesystems without them, e.g. Windows or some network
+//! filesystems, taking a lock does nothing and only the atomic rename protects the readers.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The kind of advisory lock taken by `lock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockKind {
+    /// Held by any number of readers at once.
+    Shared,
+    /// Held by a single writer, while no reader holds the lock.
+    Exclusive,
+}
+
+/// Takes an advisory lock on an open file, waiting until it is available. The lock is released
+/// when every handle of the file is closed.
+///
+/// On platforms or filesystems without `flock` semantics, this does nothing.
+///
+/// # Errors
+///
+/// Returns the error of `flock`, other than the ones of an unsupported lock.
+#[cfg(unix)]
+pub(crate) fn lock(file: &File, kind: LockKind) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let operation = match kind {
+        LockKind::Shared => libc::LOCK_SH,
+        LockKind::Exclusive => libc::LOCK_EX,
+    };
+    loop {
+        // SAFETY: the descriptor is owned by `file`, which is open for the duration of the call
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINTR) => continue,
+            // A filesystem without flock semantics, e.g. some network filesystems
+            Some(libc::ENOLCK | libc::EOPNOTSUPP | libc::ENOSYS) => return Ok(()),
+            _ => return Err(err),
+        }
+    }
+}
+
+/// Takes an advisory lock on an open file, which does nothing on this platform.
+#[cfg(not(unix))]
+pub(crate) fn lock(_file: &File, _kind: LockKind) -> io::Result<()> {
+    Ok(())
+}
+
+/// Returns the path of the sidecar lock file of a database, `<database>.lock`.
+pub fn lock_path(dest: &Path) -> PathBuf {
+    let mut path = OsString::from(dest.as_os_str());
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+/// Replaces the database file at `dest` with a copy of `src`, so that readers of `dest` never
+/// observe a partial copy.
+///
+/// The copy is written to a temporary file of the directory of `dest` and flushed to disk,
+/// then renamed over `dest`, while holding the exclusive lock of the sidecar lock file, see
+/// `lock_path`. Concurrent calls for the same destination are serialized by that lock.
+///
+/// # Arguments
+///
+/// * `src` - The path of the new database.
+/// * `dest` - The path of the installed database, which may not exist yet.
+///
+/// # Errors
+///
+/// Returns the error of reading `src`, or of writing, flushing or renaming the copy, in which
+/// case `dest` is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::db_install::atomic_replace;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+/// use std::fs::File;
+///
+/// let src = GeoIpReader::<File>::database_path("v4").unwrap();
+/// let dest = std::env::temp_dir().join("ipcap_doc_atomic_replace.dat");
+///
+/// atomic_replace(&src, &dest).unwrap();
+/// let mut geo_ip = GeoIpReader::<File>::from_path(&dest).unwrap();
+/// assert_eq!(geo_ip.get_record("108.95.4.105").unwrap().city(), Some("San Diego"));
+/// ```
+pub fn atomic_replace<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dest: Q) -> io::Result<()> {
+    // Distinguishes the temporary files of the threads of this process
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let dest = dest.as_ref();
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(dest))?;
+    lock(&lock_file, LockKind::Exclusive)?;
+
+    let dir = match dest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut name = OsString::from(".");
+    name.push(dest.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = dir.join(name);
+
+    let result = copy_and_rename(src.as_ref(), &temp, dest);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Copies `src` to the new file `temp`, flushes it to disk, then renames it to `dest`.
+fn copy_and_rename(src: &Path, temp: &Path, dest: &Path) -> io::Result<()> {
+    let mut source = File::open(src)?;
+    let mut copy = OpenOptions::new().write(true).create_new(true).open(temp)?;
+    io::copy(&mut source, &mut copy)?;
+    copy.sync_all()?;
+    drop(copy);
+
+    fs::rename(temp, dest)?;
+    // Persist the rename itself, the directory entry being written with the directory
+    #[cfg(unix)]
+    if let Some(dir) = dest.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use crate::errors::GeoIpReaderError;
+    use crate::geo_ip_reader::{GeoIpReader, GeoIpReaderBuilder};
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::os::unix::io::AsRawFd;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ipcap_test_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Builds a database resolving 8.0.0.0/8 to the given country, padded with `records`
+    /// networks so that the versions of a test have different lengths.
+    fn database(country: Country, records: u32) -> Vec<u8> {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.0.0.0/8", RecordSpec::new(country).city("Springfield"))
+            .unwrap();
+        for i in 0..records {
+            builder
+                .insert(
+                    &format!("9.{}.{}.0/24", i / 256, i % 256),
+                    RecordSpec::new(country),
+                )
+                .unwrap();
+        }
+        builder.build()
+    }
+
+    /// Returns whether an exclusive lock of the file can be taken without waiting.
+    fn can_lock_exclusive(path: &Path) -> bool {
+        let file = File::open(path).unwrap();
+        // SAFETY: the descriptor is owned by `file`, which is open for the duration of the call
+        unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) == 0 }
+    }
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            lock_path(Path::new("/var/lib/ipcap/geo_ip_city_v4.dat")),
+            Path::new("/var/lib/ipcap/geo_ip_city_v4.dat.lock")
+        );
+    }
+
+    #[test]
+    fn test_lock() {
+        let dir = temp_dir("lock");
+        let path = dir.join("locked");
+        fs::write(&path, b"").unwrap();
+
+        let shared = File::open(&path).unwrap();
+        lock(&shared, LockKind::Shared).unwrap();
+        // Another reader can share the lock, a writer cannot take it
+        lock(&File::open(&path).unwrap(), LockKind::Shared).unwrap();
+        assert!(!can_lock_exclusive(&path));
+        drop(shared);
+        assert!(can_lock_exclusive(&path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reader_lock_shared() {
+        let dir = temp_dir("lock_shared");
+        let path = dir.join("city.dat");
+        fs::write(&path, database(Country::Germany, 0)).unwrap();
+
+        let reader = GeoIpReaderBuilder::new().open(&path).unwrap();
+        assert!(can_lock_exclusive(&path));
+        drop(reader);
+
+        let mut reader = GeoIpReaderBuilder::new()
+            .lock_shared(true)
+            .open(&path)
+            .unwrap();
+        assert!(!can_lock_exclusive(&path));
+        assert_eq!(
+            reader.get_record("8.8.8.8").unwrap().country,
+            Country::Germany
+        );
+        drop(reader);
+        assert!(can_lock_exclusive(&path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_replace_waits_for_the_lock() {
+        let dir = temp_dir("replace_lock");
+        let src = dir.join("new.dat");
+        let dest = dir.join("city.dat");
+        fs::write(&src, database(Country::Germany, 0)).unwrap();
+
+        // Another writer holds the lock
+        let other = File::create(lock_path(&dest)).unwrap();
+        lock(&other, LockKind::Exclusive).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let writer = {
+            let (src, dest) = (src.clone(), dest.clone());
+            thread::spawn(move || sender.send(atomic_replace(&src, &dest).is_ok()).unwrap())
+        };
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(!dest.exists());
+
+        drop(other);
+        assert!(receiver.recv_timeout(Duration::from_secs(10)).unwrap());
+        writer.join().unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), fs::read(&src).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_replace_failure_leaves_dest() {
+        let dir = temp_dir("replace_failure");
+        let dest = dir.join("city.dat");
+        fs::write(&dest, b"old").unwrap();
+
+        assert!(atomic_replace(dir.join("missing.dat"), &dest).is_err());
+        assert_eq!(fs::read(&dest).unwrap(), b"old");
+        // Only the database and its lock file are left
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["city.dat", "city.dat.lock"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_replace_is_never_partial() {
+        let dir = temp_dir("replace_partial");
+        let dest = dir.join("city.dat");
+        let versions = [
+            database(Country::Germany, 0),
+            database(Country::France, 2000),
+        ];
+        let sources: Vec<PathBuf> = versions
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = dir.join(format!("version_{}.dat", i));
+                fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        atomic_replace(&sources[0], &dest).unwrap();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let (done, dest) = (Arc::clone(&done), dest.clone());
+            thread::spawn(move || {
+                let (mut reads, mut lookups, mut corrupt) = (0, 0, 0);
+                loop {
+                    // Reading the whole file gives one version or the other
+                    let content = fs::read(&dest).unwrap();
+                    assert!(versions.contains(&content), "{} bytes", content.len());
+                    reads += 1;
+
+                    // So does a reader reopening the file, as a service reloading it would
+                    match GeoIpReader::<File>::from_path(&dest)
+                        .and_then(|mut reader| reader.get_record("8.8.8.8"))
+                    {
+                        Ok(record) => {
+                            assert!([Country::Germany, Country::France].contains(&record.country));
+                            lookups += 1;
+                        }
+                        Err(GeoIpReaderError::CorruptDatabase) => corrupt += 1,
+                        Err(err) => panic!("{}", err),
+                    }
+                    if done.load(Ordering::Relaxed) {
+                        break (reads, lookups, corrupt);
+                    }
+                }
+            })
+        };
+
+        for i in 0..200 {
+            atomic_replace(&sources[i % 2], &dest).unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        let (reads, lookups, corrupt) = reader.join().unwrap();
+
+        assert!(reads > 0);
+        assert!(lookups > 0);
+        assert_eq!(corrupt, 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::codegen;
+use once_cell::sync::Lazy;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -12,6 +13,50 @@ impl DesignatedMarketArea {
     pub fn area_code(&self) -> u32 {
         self.0 % 1000
     }
+
+    /// Whether the packed value is 0, which the databases store for the records of the United
+    /// States without a DMA.
+    ///
+    /// ```rust
+    /// use ipcap::designated_market_area::DesignatedMarketArea;
+    ///
+    /// assert!(DesignatedMarketArea(0).is_empty());
+    /// assert!(!DesignatedMarketArea(807_650).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the DMA code has a name, as listed by `codes`. The others display as
+    /// "Unknown DMA".
+    ///
+    /// ```rust
+    /// use ipcap::designated_market_area::DesignatedMarketArea;
+    ///
+    /// assert!(DesignatedMarketArea(807_650).is_known());
+    /// assert!(!DesignatedMarketArea(999_650).is_known());
+    /// ```
+    pub fn is_known(&self) -> bool {
+        Self::codes().binary_search(&self.dma_code()).is_ok()
+    }
+
+    /// The DMA codes with a name, in ascending order.
+    ///
+    /// ```rust
+    /// use ipcap::designated_market_area::DesignatedMarketArea;
+    ///
+    /// let codes = DesignatedMarketArea::codes();
+    /// assert!(codes.contains(&501));
+    /// assert_eq!(DesignatedMarketArea(501 * 1000).to_string(), "New York, NY");
+    /// ```
+    pub fn codes() -> &'static [u32] {
+        static CODES: Lazy<Vec<u32>> = Lazy::new(|| {
+            let mut codes = codegen!("dma-codes").to_vec();
+            codes.sort_unstable();
+            codes
+        });
+        &CODES
+    }
 }
 
 impl Display for DesignatedMarketArea {
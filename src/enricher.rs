@@ -0,0 +1,293 @@
+//! Combines the lookups of a city database with those of the ASN and organization databases
+//! into one record, the "geo + network" enrichment of log analysis.
+
+use crate::asn::Asn;
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::{GeoIpReader, Record};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{Read, Seek};
+
+/// The database an enrichment lookup was performed against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnrichmentSource {
+    City,
+    Asn,
+    Org,
+}
+
+impl Display for EnrichmentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnrichmentSource::City => f.write_str("geo"),
+            EnrichmentSource::Asn => f.write_str("asn"),
+            EnrichmentSource::Org => f.write_str("org"),
+        }
+    }
+}
+
+/// The combined result of the city, ASN and organization lookups for a single IP address.
+///
+/// Each portion is `None` when its database is not configured, when the address is not in
+/// that database, or, in lenient mode, when the lookup failed (see `errors`).
+#[derive(Debug)]
+pub struct EnrichedRecord<'a> {
+    pub geo: Option<Record<'a>>,
+    pub asn: Option<Asn>,
+    pub org: Option<Box<str>>,
+    /// The per-database failures tolerated in lenient mode.
+    pub errors: Vec<(EnrichmentSource, GeoIpReaderError)>,
+}
+
+/// `Enricher` holds up to three readers (city, ASN and organization databases) and combines
+/// their lookups into one `EnrichedRecord`.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::enricher::Enricher;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut enricher = Enricher::new().with_city(GeoIpReader::<File>::new("v4").unwrap());
+///
+/// let record = enricher.enrich("108.95.4.105").unwrap();
+/// assert_eq!(record.geo.unwrap().city, Some("San Diego".into()));
+/// assert_eq!(record.asn, None);
+/// ```
+#[derive(Debug)]
+pub struct Enricher<R = File>
+where
+    R: Read + Seek,
+{
+    city: Option<GeoIpReader<R>>,
+    asn: Option<GeoIpReader<R>>,
+    org: Option<GeoIpReader<R>>,
+    lenient: bool,
+}
+
+impl<R> Default for Enricher<R>
+where
+    R: Read + Seek,
+{
+    fn default() -> Self {
+        Enricher {
+            city: None,
+            asn: None,
+            org: None,
+            lenient: false,
+        }
+    }
+}
+
+impl<R> Enricher<R>
+where
+    R: Read + Seek,
+{
+    /// Constructs an `Enricher` without any database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the city database used for the `geo` portion.
+    pub fn with_city(mut self, reader: GeoIpReader<R>) -> Self {
+        self.city = Some(reader);
+        self
+    }
+
+    /// Sets the ASN database used for the `asn` portion.
+    pub fn with_asn(mut self, reader: GeoIpReader<R>) -> Self {
+        self.asn = Some(reader);
+        self
+    }
+
+    /// Sets the organization database used for the `org` portion.
+    pub fn with_org(mut self, reader: GeoIpReader<R>) -> Self {
+        self.org = Some(reader);
+        self
+    }
+
+    /// In lenient mode, a failing lookup only empties its own portion and is reported in
+    /// `EnrichedRecord::errors` instead of failing the whole enrichment.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Performs the lookups of every configured database for a given IP address.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - IP address
+    ///
+    /// # Returns
+    ///
+    /// (`Result<EnrichedRecord, GeoIpReaderError>`): The combined record. Addresses missing from
+    /// one database leave only that portion empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first lookup error unless the enricher is lenient.
+    pub fn enrich(&mut self, ip: &str) -> Result<EnrichedRecord<'static>, GeoIpReaderError> {
+        let mut errors = Vec::new();
+
+        let geo = match self.city.as_mut() {
+            Some(reader) => reader.find_record(ip),
+            None => Ok(None),
+        };
+        let geo = self.tolerate(EnrichmentSource::City, geo, &mut errors)?;
+
+        let asn = match self.asn.as_mut() {
+            Some(reader) => reader.get_asn(ip),
+            None => Ok(None),
+        };
+        let asn = self.tolerate(EnrichmentSource::Asn, asn, &mut errors)?;
+
+        let org = match self.org.as_mut() {
+            Some(reader) => reader.get_org(ip),
+            None => Ok(None),
+        };
+        let org = self.tolerate(EnrichmentSource::Org, org, &mut errors)?;
+
+        Ok(EnrichedRecord {
+            geo,
+            asn,
+            org,
+            errors,
+        })
+    }
+
+    /// Propagates a lookup error, or records it when lenient.
+    fn tolerate<T>(
+        &self,
+        source: EnrichmentSource,
+        result: Result<Option<T>, GeoIpReaderError>,
+        errors: &mut Vec<(EnrichmentSource, GeoIpReaderError)>,
+    ) -> Result<Option<T>, GeoIpReaderError> {
+        match result {
+            Err(err) if self.lenient => {
+                errors.push((source, err));
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{ASNUM_EDITION, ORG_EDITION};
+    use crate::countries::Country;
+    use crate::fixtures::{corrupt_database, string_database};
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::io::Cursor;
+
+    fn reader(database: Vec<u8>) -> GeoIpReader<Cursor<Vec<u8>>> {
+        GeoIpReader::from_reader(Cursor::new(database)).unwrap()
+    }
+
+    fn city() -> GeoIpReader<Cursor<Vec<u8>>> {
+        let mut path = dirs::home_dir().unwrap();
+        path.push("ipcap/geo_ip_city_v4.dat");
+        reader(std::fs::read(path).unwrap())
+    }
+
+    #[test]
+    fn test_enrich_with_all_databases() {
+        let mut enricher = Enricher::new()
+            .with_city(city())
+            .with_asn(reader(string_database(ASNUM_EDITION, "AS7018 AT&T")))
+            .with_org(reader(string_database(ORG_EDITION, "AT&T Services")));
+
+        let record = enricher.enrich("208.95.4.105").unwrap();
+
+        assert!(record.geo.is_some());
+        assert_eq!(
+            record.asn,
+            Some(Asn {
+                number: 7018,
+                name: Some("AT&T".into())
+            })
+        );
+        assert_eq!(record.org, Some("AT&T Services".into()));
+        assert!(record.errors.is_empty());
+    }
+
+    #[test]
+    fn test_enrich_with_fixture_databases() {
+        let mut city = DatabaseBuilder::new();
+        city.insert(
+            "10.0.0.0/8",
+            RecordSpec::new(Country::UnitedStates).city("San Diego"),
+        )
+        .unwrap();
+        let mut enricher = Enricher::new()
+            .with_city(reader(city.build()))
+            .with_asn(reader(string_database(ASNUM_EDITION, "AS7018 AT&T")));
+
+        // Each lookup misses or hits regardless of the other one
+        let record = enricher.enrich("10.0.0.1").unwrap();
+        assert_eq!(record.geo.unwrap().city, Some("San Diego".into()));
+        assert_eq!(record.asn, None);
+
+        let record = enricher.enrich("200.0.0.1").unwrap();
+        assert!(record.geo.is_none());
+        assert_eq!(record.asn.unwrap().number, 7018);
+        assert!(record.errors.is_empty());
+    }
+
+    #[test]
+    fn test_enrich_with_subset_of_databases() {
+        let mut enricher =
+            Enricher::new().with_asn(reader(string_database(ASNUM_EDITION, "AS7018 AT&T")));
+
+        let record = enricher.enrich("208.95.4.105").unwrap();
+
+        assert!(record.geo.is_none());
+        assert_eq!(record.asn.unwrap().number, 7018);
+        assert_eq!(record.org, None);
+    }
+
+    #[test]
+    fn test_enrich_tolerates_not_found_independently() {
+        let mut enricher = Enricher::new()
+            .with_city(city())
+            .with_org(reader(string_database(ORG_EDITION, "AT&T Services")));
+
+        let record = enricher.enrich("108.95.4.105").unwrap();
+
+        assert_eq!(record.geo.unwrap().city, Some("San Diego".into()));
+        assert_eq!(record.org, None);
+    }
+
+    #[test]
+    fn test_enrich_strict_with_corrupt_database() {
+        let mut enricher = Enricher::new()
+            .with_city(city())
+            .with_asn(reader(corrupt_database(ASNUM_EDITION)));
+
+        let result = enricher.enrich("108.95.4.105");
+
+        assert!(matches!(result, Err(GeoIpReaderError::CorruptDatabase)));
+    }
+
+    #[test]
+    fn test_enrich_lenient_with_corrupt_database() {
+        let mut enricher = Enricher::new()
+            .with_city(city())
+            .with_asn(reader(corrupt_database(ASNUM_EDITION)))
+            .with_org(reader(string_database(ORG_EDITION, "AT&T Services")))
+            .lenient(true);
+
+        let record = enricher.enrich("208.95.4.105").unwrap();
+
+        assert!(record.geo.is_some());
+        assert_eq!(record.asn, None);
+        assert_eq!(record.org, Some("AT&T Services".into()));
+        assert!(matches!(
+            record.errors.as_slice(),
+            [(EnrichmentSource::Asn, GeoIpReaderError::CorruptDatabase)]
+        ));
+    }
+}
@@ -1,4 +1,6 @@
 use std::fmt;
+use std::net::{AddrParseError, IpAddr};
+use std::path::PathBuf;
 
 /// Enum representing various errors that may occur while using `GeoIpReader`.
 #[derive(Debug)]
@@ -10,6 +12,42 @@ pub enum GeoIpReaderError {
     /// Error indicating a failure to open a file.
     OpenFileError,
     CorruptDatabase,
+    /// Error indicating an IP address that is not in the database.
+    AddressNotFound(String),
+    /// Error indicating an IP address of a reserved range, e.g. private or loopback, which is not
+    /// in the database.
+    ReservedAddress(String),
+    /// Error indicating an input that is neither an IPv4 nor an IPv6 address.
+    InvalidAddress {
+        input: String,
+        source: AddrParseError,
+    },
+    /// Error indicating a database file refused by the `FileTrustPolicy` of the reader.
+    UntrustedDatabaseFile {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    /// Error indicating an IP address whose city record has no real coordinates, with
+    /// `GeoIpReaderBuilder::strict_coordinates`.
+    MissingCoordinates(String),
+    /// Error indicating an address of another family than the database, e.g. an IPv4 address
+    /// looked up in an IPv6 database, unless the reader was built with
+    /// `GeoIpReaderBuilder::map_v4_into_v6`.
+    AddressFamilyMismatch(String),
+    /// Error indicating a range of addresses that cannot be looked up, see `RangeError`.
+    InvalidRange(RangeError),
+    /// Error indicating a failure to seek or read the database, other than a truncated node.
+    Io(std::io::Error),
+    /// Error indicating a database file too small to hold a database, e.g. an empty file left by
+    /// an interrupted download, with its size and the minimum size in bytes.
+    DatabaseTooSmall {
+        path: PathBuf,
+        size: u64,
+        minimum: u64,
+    },
+    /// Error indicating a call to `init_global` after the global reader was already used or
+    /// configured.
+    GlobalAlreadyInitialized,
 }
 
 impl fmt::Display for GeoIpReaderError {
@@ -20,6 +58,339 @@ impl fmt::Display for GeoIpReaderError {
             GeoIpReaderError::InvalidDatabaseType => write!(f, "Invalid database type"),
             GeoIpReaderError::OpenFileError => write!(f, "Cannot open file"),
             GeoIpReaderError::CorruptDatabase => write!(f, "Corrupt database"),
+            GeoIpReaderError::AddressNotFound(ip) => {
+                write!(f, "Address not found in the database: {}", ip)
+            }
+            GeoIpReaderError::ReservedAddress(ip) => {
+                write!(f, "Address of a reserved range: {}", ip)
+            }
+            GeoIpReaderError::InvalidAddress { input, source } => {
+                write!(f, "Invalid IP address {:?}: {}", input, source)
+            }
+            GeoIpReaderError::UntrustedDatabaseFile { path, reason } => {
+                write!(f, "Untrusted database file {}: {}", path.display(), reason)
+            }
+            GeoIpReaderError::MissingCoordinates(ip) => {
+                write!(f, "No real coordinates in the record of: {}", ip)
+            }
+            GeoIpReaderError::AddressFamilyMismatch(ip) => {
+                write!(f, "Address of another family than the database: {}", ip)
+            }
+            GeoIpReaderError::InvalidRange(err) => write!(f, "{}", err),
+            GeoIpReaderError::Io(err) => write!(f, "Cannot read the database: {}", err),
+            GeoIpReaderError::DatabaseTooSmall {
+                path,
+                size,
+                minimum,
+            } => write!(
+                f,
+                "Database file {} too small: {} bytes, expected at least {}. The download or \
+                 copy of the database was likely interrupted, download it again",
+                path.display(),
+                size,
+                minimum
+            ),
+            GeoIpReaderError::GlobalAlreadyInitialized => write!(
+                f,
+                "The global reader is already initialized, init_global must come before its first \
+                 use"
+            ),
         }
     }
 }
+
+impl GeoIpReaderError {
+    /// The name of the variant in snake case, e.g. `address_not_found`, as a stable identifier
+    /// of the error in machine-readable output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::errors::GeoIpReaderError;
+    ///
+    /// let err = GeoIpReaderError::AddressNotFound("10.0.0.1".to_string());
+    /// assert_eq!(err.code(), "address_not_found");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            GeoIpReaderError::GetHostByNameError => "get_host_by_name_error",
+            GeoIpReaderError::InvalidDatabaseType => "invalid_database_type",
+            GeoIpReaderError::OpenFileError => "open_file_error",
+            GeoIpReaderError::CorruptDatabase => "corrupt_database",
+            GeoIpReaderError::AddressNotFound(_) => "address_not_found",
+            GeoIpReaderError::ReservedAddress(_) => "reserved_address",
+            GeoIpReaderError::InvalidAddress { .. } => "invalid_address",
+            GeoIpReaderError::UntrustedDatabaseFile { .. } => "untrusted_database_file",
+            GeoIpReaderError::MissingCoordinates(_) => "missing_coordinates",
+            GeoIpReaderError::AddressFamilyMismatch(_) => "address_family_mismatch",
+            GeoIpReaderError::InvalidRange(_) => "invalid_range",
+            GeoIpReaderError::Io(_) => "io",
+            GeoIpReaderError::DatabaseTooSmall { .. } => "database_too_small",
+            GeoIpReaderError::GlobalAlreadyInitialized => "global_already_initialized",
+        }
+    }
+}
+
+impl std::error::Error for GeoIpReaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GeoIpReaderError::InvalidAddress { source, .. } => Some(source),
+            GeoIpReaderError::InvalidRange(source) => Some(source),
+            GeoIpReaderError::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GeoIpReaderError {
+    fn from(err: std::io::Error) -> Self {
+        GeoIpReaderError::Io(err)
+    }
+}
+
+/// Enum representing the errors of `utils::parse_ip_range` and `utils::range_to_cidrs`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeError {
+    /// Error indicating an input that is neither a `start-end` range, a CIDR nor an address.
+    Invalid(String),
+    /// Error indicating bounds of different address families.
+    MixedFamilies { start: IpAddr, end: IpAddr },
+    /// Error indicating a start bound after the end bound.
+    Reversed { start: IpAddr, end: IpAddr },
+}
+
+impl fmt::Display for RangeError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::Invalid(input) => write!(
+                f,
+                "Invalid IP range {:?}: expected start-end, a CIDR or an address",
+                input
+            ),
+            RangeError::MixedFamilies { start, end } => write!(
+                f,
+                "Invalid IP range {}-{}: the bounds are of different address families",
+                start, end
+            ),
+            RangeError::Reversed { start, end } => write!(
+                f,
+                "Invalid IP range {}-{}: the start is after the end",
+                start, end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// Enum representing the errors of parsing an `ip_hash::IpHashKey`, which leave the key out of
+/// their message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HashKeyError {
+    /// Error indicating a key that is not 32 hex digits long, with its length.
+    Length(usize),
+    /// Error indicating a character of the key that is not a hex digit.
+    NotHex(char),
+}
+
+impl fmt::Display for HashKeyError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashKeyError::Length(length) => write!(
+                f,
+                "Invalid hash key: expected 32 hex digits (128 bits), got {}",
+                length
+            ),
+            HashKeyError::NotHex(c) => {
+                write!(f, "Invalid hash key: {:?} is not a hex digit", c)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HashKeyError {}
+
+/// Enum representing the errors of `utils::normalize_ip_text`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpParseError {
+    /// Error indicating an input that is not an IP address in any accepted form.
+    Invalid(String),
+    /// Error indicating an IPv4 octet with leading zeros, only accepted by `ParseMode::Legacy`.
+    LeadingZeros(String),
+}
+
+impl fmt::Display for IpParseError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpParseError::Invalid(input) => write!(f, "Invalid IP address {:?}", input),
+            IpParseError::LeadingZeros(input) => write!(
+                f,
+                "Invalid IP address {:?}: leading zeros in an IPv4 octet are only accepted by \
+                 legacy parsing",
+                input
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IpParseError {}
+
+/// Enum representing the errors of parsing and checking a `validate::Expectation`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpectationError {
+    /// Error indicating an expression that is neither `field=value` nor `field~=substring`.
+    Invalid(String),
+    /// Error indicating a field that the record does not have.
+    UnknownField(String),
+}
+
+impl fmt::Display for ExpectationError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpectationError::Invalid(input) => write!(
+                f,
+                "Invalid expectation {:?}: expected field=value or field~=substring",
+                input
+            ),
+            ExpectationError::UnknownField(field) => {
+                write!(f, "Unknown field {:?} in expectation", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExpectationError {}
+
+/// Enum representing various errors that may occur while using `DatabaseBuilder`.
+#[derive(Debug)]
+pub enum DatabaseWriterError {
+    /// Error indicating a range that is not a valid CIDR, e.g. `10.0.0.0/8`.
+    InvalidCidr(String),
+    /// Error indicating a range of another address family than the previous ones.
+    MixedAddressFamilies(String),
+    /// Error indicating a record whose strings do not fit in a full record.
+    RecordTooLong,
+}
+
+impl fmt::Display for DatabaseWriterError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseWriterError::InvalidCidr(cidr) => write!(f, "Invalid CIDR: {}", cidr),
+            DatabaseWriterError::MixedAddressFamilies(cidr) => {
+                write!(f, "Mixed address families: {}", cidr)
+            }
+            DatabaseWriterError::RecordTooLong => write!(f, "Record too long"),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseWriterError {}
+
+/// Enum representing the errors of building or loading an `OverrideTable`.
+#[derive(Debug)]
+pub enum OverrideError {
+    /// Error indicating a range that is not a valid CIDR, e.g. `10.0.0.0/8`.
+    InvalidCidr(String),
+    /// Error indicating a line of an override file that cannot be parsed, numbered from 1.
+    InvalidLine { line: usize, reason: String },
+    /// Error indicating a failure to read an override file.
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl fmt::Display for OverrideError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverrideError::InvalidCidr(cidr) => write!(f, "Invalid CIDR: {}", cidr),
+            OverrideError::InvalidLine { line, reason } => {
+                write!(f, "Invalid override on line {}: {}", line, reason)
+            }
+            OverrideError::Read { path, source } => {
+                write!(
+                    f,
+                    "Cannot read override file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for OverrideError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OverrideError::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Enum representing the errors of saving or loading a snapshot of a `GeoIpReader`.
+#[cfg(feature = "snapshot")]
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Error indicating that the data does not start with the magic number of a snapshot.
+    NotASnapshot,
+    /// Error indicating a snapshot of a format version this build cannot read.
+    UnsupportedVersion(u8),
+    /// Error indicating that the database bytes of a snapshot do not match the hash it embeds,
+    /// e.g. after a corruption in storage.
+    HashMismatch { expected: u64, actual: u64 },
+    /// Error indicating a layout that does not fit the database bytes of the snapshot.
+    InvalidLayout,
+    /// Error indicating a failure to read the database being saved.
+    Database(GeoIpReaderError),
+    /// Error indicating a failure to read or write the snapshot.
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "snapshot")]
+impl fmt::Display for SnapshotError {
+    /// Implements the `fmt::Display` trait to customize the error message.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotASnapshot => write!(f, "Not a snapshot"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported snapshot version {}, expected {}",
+                version,
+                crate::snapshot::SNAPSHOT_VERSION
+            ),
+            SnapshotError::HashMismatch { expected, actual } => write!(
+                f,
+                "Snapshot hash mismatch: expected {:016x}, got {:016x}",
+                expected, actual
+            ),
+            SnapshotError::InvalidLayout => write!(f, "Invalid snapshot layout"),
+            SnapshotError::Database(err) => write!(f, "Cannot snapshot the database: {}", err),
+            SnapshotError::Io(err) => write!(f, "Cannot read or write the snapshot: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SnapshotError::Database(err) => Some(err),
+            SnapshotError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
@@ -0,0 +1,200 @@
+//! A coarse country-level dataset compiled into the crate with the `embedded-country-data`
+//! feature, to resolve IPv4 addresses when no database file is installed.
+//!
+//! The dataset maps about 14 000 prefixes from /8 to /16 to countries, and takes about 57 KB in
+//! the binary. It is generated at build time from `ipcap-codegen/country-prefixes.csv`, which
+//! holds the country of each /16 of the city database, merged into the largest aligned prefixes
+//! of a single country. A /16 often spans several countries, so the records it gives are only
+//! right for most of the addresses of their prefix: they carry the `SourceKind::Embedded`
+//! provenance and their `Record::location_confidence` is `CONFIDENCE`.
+
+use crate::codegen;
+use crate::countries::Country;
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::{Provenance, Record, SourceKind};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// The prefixes of the dataset as `(first /16 of the prefix, prefix length, country)`, sorted and
+/// disjoint.
+static PREFIXES: &[(u16, u8, Country)] = &codegen!("country-prefixes");
+
+/// The `Record::location_confidence` of the records of the dataset, below the score of any
+/// database record matched on a /4 or longer prefix.
+pub const CONFIDENCE: f32 = 0.1;
+
+/// `FallbackResolver` looks up IPv4 addresses in the embedded country dataset, e.g. when the
+/// database file cannot be opened.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::fallback::{FallbackResolver, CONFIDENCE};
+/// use ipcap::geo_ip_reader::{Accuracy, SourceKind};
+///
+/// let resolver = FallbackResolver::new();
+/// let record = resolver.lookup("8.8.8.8").unwrap().unwrap();
+///
+/// assert_eq!(record.country, Country::UnitedStates);
+/// assert_eq!(record.accuracy(), Accuracy::Country);
+/// assert_eq!(record.location_confidence(), CONFIDENCE);
+/// assert_eq!(record.provenance.unwrap().source, SourceKind::Embedded);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallbackResolver;
+
+impl FallbackResolver {
+    /// Constructs a resolver over the embedded dataset, which allocates nothing.
+    pub fn new() -> Self {
+        FallbackResolver
+    }
+
+    /// Looks up the country of an IP address.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address, v4 or v6.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<Record<'static>>, GeoIpReaderError>`): The record of the prefix containing
+    /// the address, or `None` if the dataset has no such prefix, as for every IPv6 address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address.
+    pub fn lookup(&self, ip: &str) -> Result<Option<Record<'static>>, GeoIpReaderError> {
+        let addr = ip
+            .parse::<IpAddr>()
+            .map_err(|source| GeoIpReaderError::InvalidAddress {
+                input: ip.to_string(),
+                source,
+            })?;
+        Ok(self.lookup_addr(addr))
+    }
+
+    /// Looks up the country of a parsed IP address, like `lookup`.
+    ///
+    /// # Returns
+    ///
+    /// (`Option<Record<'static>>`): The record, its netmask being the prefix length, its
+    /// coordinates the centroid of the country and its provenance `SourceKind::Embedded`, or
+    /// `None` if the dataset has no prefix containing the address.
+    pub fn lookup_addr(&self, ip: IpAddr) -> Option<Record<'static>> {
+        let IpAddr::V4(ip) = ip else {
+            return None;
+        };
+        let (prefix, country) = find(ip)?;
+
+        let (latitude, longitude) = country.centroid().unwrap_or_default();
+        Some(
+            Record::new(country)
+                .with_coordinates(latitude, longitude)
+                .with_netmask(usize::from(prefix), 32)
+                .with_provenance(Provenance {
+                    source: SourceKind::Embedded,
+                    edition: None,
+                    db_build_date: None,
+                }),
+        )
+    }
+
+    /// Returns the number of prefixes of the dataset.
+    pub fn len(&self) -> usize {
+        PREFIXES.len()
+    }
+
+    /// Returns whether the dataset has no prefixes, which a build never produces.
+    pub fn is_empty(&self) -> bool {
+        PREFIXES.is_empty()
+    }
+}
+
+/// Returns the length and country of the prefix containing an address, with a binary search over
+/// the first /16 of the prefixes.
+fn find(ip: Ipv4Addr) -> Option<(u8, Country)> {
+    let block = (u32::from(ip) >> 16) as u16;
+    let index = PREFIXES
+        .partition_point(|&(first, _, _)| first <= block)
+        .checked_sub(1)?;
+    let &(first, prefix, country) = PREFIXES.get(index)?;
+
+    // The prefix spans 2^(16 - prefix) blocks of /16
+    let blocks = 1u32 << (16 - u32::from(prefix));
+    (u32::from(block) < u32::from(first) + blocks).then_some((prefix, country))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_ip_reader::Accuracy;
+
+    fn country(ip: &str) -> Option<Country> {
+        FallbackResolver::new()
+            .lookup(ip)
+            .unwrap()
+            .map(|record| record.country)
+    }
+
+    #[test]
+    fn test_well_known_prefixes() {
+        // Whole /8 allocated to a single organization
+        assert_eq!(country("8.8.8.8"), Some(Country::UnitedStates));
+        assert_eq!(country("17.253.144.10"), Some(Country::UnitedStates));
+        assert_eq!(country("25.1.2.3"), Some(Country::UnitedKingdom));
+        assert_eq!(country("53.1.2.3"), Some(Country::Germany));
+        // Shorter allocations of the regional registries
+        assert_eq!(country("58.32.0.1"), Some(Country::China));
+        assert_eq!(country("200.160.2.3"), Some(Country::Brazil));
+    }
+
+    #[test]
+    fn test_unknown_addresses() {
+        assert_eq!(country("10.0.0.1"), None);
+        assert_eq!(country("127.0.0.1"), None);
+        assert_eq!(country("255.255.255.255"), None);
+        assert_eq!(country("2001:4860:4860::8888"), None);
+        assert!(matches!(
+            FallbackResolver::new().lookup("banana"),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+    }
+
+    #[test]
+    fn test_records_are_marked() {
+        let record = FallbackResolver::new().lookup("8.8.8.8").unwrap().unwrap();
+
+        assert_eq!(record.accuracy(), Accuracy::Country);
+        assert_eq!(record.location_confidence(), CONFIDENCE);
+        assert_eq!(
+            record.provenance.as_ref().unwrap().source,
+            SourceKind::Embedded
+        );
+        assert!((8..=16).contains(&record.netmask));
+        assert_eq!(record.max_netmask, 32);
+        assert_eq!((record.city(), record.region_code()), (None, None));
+    }
+
+    #[test]
+    fn test_prefixes_are_sorted_and_disjoint() {
+        let resolver = FallbackResolver::new();
+        assert!(!resolver.is_empty());
+        // Tens of KB, without any allocation
+        assert!(std::mem::size_of_val(PREFIXES) < 100 * 1024);
+
+        for pair in PREFIXES.windows(2) {
+            let (first, prefix, _) = pair[0];
+            let blocks = 1u32 << (16 - u32::from(prefix));
+            assert!(
+                u32::from(first) + blocks <= u32::from(pair[1].0),
+                "{:?}",
+                pair
+            );
+        }
+        for &(first, prefix, country) in PREFIXES {
+            assert!((8..=16).contains(&prefix));
+            let ip = Ipv4Addr::from(u32::from(first) << 16);
+            assert_eq!(find(ip), Some((prefix, country)));
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Hand-crafted database images used by the unit tests.
+
+use crate::constants::*;
+use crate::countries::Country;
+
+/// Encodes `value` as a little-endian integer of `length` bytes.
+fn encode(value: u32, length: usize) -> Vec<u8> {
+    value.to_le_bytes()[..length].to_vec()
+}
+
+/// Builds the structure info trailer that `detect_database_type` looks for.
+fn trailer(edition: u8, segments: u32) -> Vec<u8> {
+    let mut trailer = vec![255, 255, 255, edition];
+    trailer.extend(encode(segments, SEGMENT_RECORD_LENGTH));
+    trailer
+}
+
+/// The length of a tree pointer for the given edition.
+fn record_length(edition: u8) -> usize {
+    if [ORG_EDITION, ISP_EDITION].contains(&edition) {
+        ORG_RECORD_LENGTH
+    } else {
+        STANDARD_RECORD_LENGTH
+    }
+}
+
+/// Builds a single-node Organization, ISP or ASN database.
+///
+/// Addresses with the most significant bit cleared (e.g. `10.0.0.1`) are not in the database,
+/// while the others (e.g. `200.0.0.1`) resolve to `record`.
+pub fn string_database(edition: u8, record: &str) -> Vec<u8> {
+    let mut record = record.as_bytes().to_vec();
+    record.push(0);
+    record_database(edition, &record)
+}
+
+/// Builds a single-node City database whose record is in Zürich, with a DMA code of 0 for the
+/// REV1 editions.
+///
+/// Addresses with the most significant bit cleared (e.g. `10.0.0.1`) are not in the database,
+/// while the others (e.g. `200.0.0.1`) resolve to the record.
+pub fn city_database(edition: u8) -> Vec<u8> {
+    let mut record = vec![Country::Switzerland.to_buffer()];
+    record.extend(b"ZH\0Z\xC3\xBCrich\08001\0");
+    // Latitude 47.37 and longitude 8.54, offset by 180 degrees, in ten-thousandths
+    record.extend(encode(2_273_700, 3));
+    record.extend(encode(1_885_400, 3));
+    record.extend(encode(0, 3));
+    record_database(edition, &record)
+}
+
+/// Builds a single-node database whose addresses with the most significant bit set point at
+/// `record`, the others not being in the database.
+fn record_database(edition: u8, record: &[u8]) -> Vec<u8> {
+    let record_length = record_length(edition);
+
+    // With one segment, a pointer of 2 lands right after the tree and a padding byte
+    let mut database = encode(1, record_length);
+    database.extend(encode(2, record_length));
+    database.push(0);
+    database.extend(record);
+    database.extend(trailer(edition, 1));
+    database
+}
+
+/// Builds a single-node Region database, `leaves` being the offsets of the two leaves relative
+/// to the first state offset of the edition.
+///
+/// Addresses with the most significant bit cleared (e.g. `10.0.0.1`) resolve to the first leaf,
+/// the others (e.g. `200.0.0.1`) to the second one.
+pub fn region_database(edition: u8, leaves: [u32; 2]) -> Vec<u8> {
+    let state_begin = if edition == REGION_EDITION_REV0 {
+        STATE_BEGIN_REV0
+    } else {
+        STATE_BEGIN_REV1
+    };
+
+    let mut database = Vec::new();
+    for leaf in leaves {
+        database.extend(encode(state_begin + leaf, STANDARD_RECORD_LENGTH));
+    }
+    database.extend(trailer(edition, 0));
+    database
+}
+
+/// Builds a database whose tree never reaches a leaf.
+pub fn corrupt_database(edition: u8) -> Vec<u8> {
+    let mut database = vec![0; 2 * record_length(edition)];
+    database.extend(trailer(edition, 1));
+    database
+}
+
+/// Derives corrupted copies of a database: every truncation, every byte set to 0x00 or 0xFF,
+/// random byte flips, and random bytes with or without a structure info trailer.
+///
+/// The mutations are a function of `seed` only, so that a failure can be reproduced.
+pub fn mutations(database: &[u8], seed: u64) -> Vec<Vec<u8>> {
+    // A xorshift generator, the tests do not depend on a random number crate
+    let mut state = seed | 1;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut mutations = Vec::new();
+    for length in 0..database.len() {
+        mutations.push(database[..length].to_vec());
+    }
+    for index in 0..database.len() {
+        for byte in [0x00, 0xFF] {
+            let mut mutation = database.to_vec();
+            mutation[index] = byte;
+            mutations.push(mutation);
+        }
+    }
+    for _ in 0..64 {
+        let mut mutation = database.to_vec();
+        for _ in 0..1 + next() % 4 {
+            let index = (next() % database.len().max(1) as u64) as usize;
+            if let Some(byte) = mutation.get_mut(index) {
+                *byte ^= next() as u8;
+            }
+        }
+        mutations.push(mutation);
+    }
+    for edition in [
+        0,
+        CITY_EDITION_REV1,
+        REGION_EDITION_REV0,
+        ORG_EDITION,
+        ASNUM_EDITION_V6,
+    ] {
+        for _ in 0..16 {
+            let length = (next() % 96) as usize;
+            let mut mutation: Vec<u8> = (0..length).map(|_| next() as u8).collect();
+            if edition != 0 {
+                mutation.extend(trailer(edition, (next() % 8) as u32));
+            }
+            mutations.push(mutation);
+        }
+    }
+    mutations
+}
@@ -0,0 +1,348 @@
+//! The contract of the JSON objects describing lookups, e.g. the rows of `ipcap batch --format
+//! json`.
+//!
+//! Every object carries the `schema_version` it conforms to, and a version only ever emits the
+//! keys its `schema` lists, so that a parser written against a version keeps working as fields
+//! are added: new fields only appear under a new version, which the consumers opt into.
+
+use crate::utils::RECORD_FIELD_NAMES;
+use std::fmt;
+use std::str::FromStr;
+
+/// A version of the JSON objects describing lookups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum SchemaVersion {
+    /// The address, the requested fields of its record and its count.
+    #[default]
+    V1,
+    /// Adds the `provenance` of the records.
+    V2,
+}
+
+impl SchemaVersion {
+    /// The supported versions, from the oldest.
+    pub const ALL: [SchemaVersion; 2] = [SchemaVersion::V1, SchemaVersion::V2];
+
+    /// The value of the `schema_version` key.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchemaVersion::V1 => "1",
+            SchemaVersion::V2 => "2",
+        }
+    }
+
+    /// Whether the version has the field named `name`, e.g. `provenance`.
+    pub fn has_field(&self, name: &str) -> bool {
+        schema(*self).iter().any(|field| field.name == name)
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SchemaVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SchemaVersion::ALL
+            .into_iter()
+            .find(|version| version.as_str() == value)
+            .ok_or_else(|| {
+                let supported: Vec<&str> = SchemaVersion::ALL
+                    .iter()
+                    .map(SchemaVersion::as_str)
+                    .collect();
+                format!(
+                    "unsupported schema version '{}', expected one of {}",
+                    value,
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// Whether the JSON objects keep to their schema when the post-processors of the readers edit
+/// the fields of the records, see `post_process`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaMode {
+    /// Only the keys of the schema: the custom fields added by the post-processors are left out,
+    /// and a field they removed is `null`.
+    #[default]
+    Strict,
+    /// The fields as the post-processors left them: a removed field is left out, and the custom
+    /// fields they added follow the keys of the schema, requested or not.
+    Permissive,
+}
+
+impl SchemaMode {
+    /// The supported modes.
+    pub const ALL: [SchemaMode; 2] = [SchemaMode::Strict, SchemaMode::Permissive];
+
+    /// The name of the mode, as accepted by `--schema-mode`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchemaMode::Strict => "strict",
+            SchemaMode::Permissive => "permissive",
+        }
+    }
+}
+
+impl fmt::Display for SchemaMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SchemaMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SchemaMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "unsupported schema mode '{}', expected one of strict, permissive",
+                    value
+                )
+            })
+    }
+}
+
+/// The type of the value of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// A string.
+    String,
+    /// A string, or `null` when the record has no value.
+    NullableString,
+    /// A non-negative integer.
+    Integer,
+    /// A nested object.
+    Object,
+}
+
+/// When a field is part of an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    /// In every object, successful lookups and errors alike.
+    Always,
+    /// In the objects of successful lookups, when the field is requested, e.g. with `--append`.
+    Requested,
+    /// In the objects of successful lookups, when enabled, e.g. `count` with `--count`.
+    Optional,
+    /// In the objects of failed lookups only.
+    Error,
+}
+
+/// A key of the JSON objects describing lookups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldDescriptor {
+    /// The key.
+    pub name: &'static str,
+    /// The type of its value.
+    pub kind: FieldKind,
+    /// When it is part of an object.
+    pub presence: Presence,
+}
+
+impl FieldDescriptor {
+    const fn new(name: &'static str, kind: FieldKind, presence: Presence) -> Self {
+        FieldDescriptor {
+            name,
+            kind,
+            presence,
+        }
+    }
+
+    const fn record_field(index: usize) -> Self {
+        FieldDescriptor::new(
+            RECORD_FIELD_NAMES[index],
+            FieldKind::NullableString,
+            Presence::Requested,
+        )
+    }
+}
+
+/// Expands to the fields of the first version, followed by the given ones.
+macro_rules! fields {
+    ($($field: expr),*) => {
+        [
+            FieldDescriptor::new("schema_version", FieldKind::String, Presence::Always),
+            FieldDescriptor::new("ip", FieldKind::String, Presence::Always),
+            FieldDescriptor::record_field(0),
+            FieldDescriptor::record_field(1),
+            FieldDescriptor::record_field(2),
+            FieldDescriptor::record_field(3),
+            FieldDescriptor::record_field(4),
+            FieldDescriptor::record_field(5),
+            FieldDescriptor::record_field(6),
+            FieldDescriptor::record_field(7),
+            FieldDescriptor::record_field(8),
+            FieldDescriptor::record_field(9),
+            FieldDescriptor::record_field(10),
+            FieldDescriptor::record_field(11),
+            FieldDescriptor::record_field(12),
+            FieldDescriptor::record_field(13),
+            FieldDescriptor::record_field(14),
+            FieldDescriptor::new("count", FieldKind::Integer, Presence::Optional),
+            FieldDescriptor::new("error", FieldKind::String, Presence::Error),
+            FieldDescriptor::new("message", FieldKind::String, Presence::Error),
+            $($field),*
+        ]
+    };
+}
+
+static SCHEMA_V1: [FieldDescriptor; 20] = fields!();
+
+static SCHEMA_V2: [FieldDescriptor; 21] = fields!(FieldDescriptor::new(
+    "provenance",
+    FieldKind::Object,
+    Presence::Optional
+));
+
+/// Returns the keys the objects of a schema version may have, in the order they are emitted.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::format::{schema, Presence, SchemaVersion};
+///
+/// let errors: Vec<&str> = schema(SchemaVersion::V1)
+///     .iter()
+///     .filter(|field| matches!(field.presence, Presence::Always | Presence::Error))
+///     .map(|field| field.name)
+///     .collect();
+/// assert_eq!(errors, ["schema_version", "ip", "error", "message"]);
+///
+/// assert!(!SchemaVersion::V1.has_field("provenance"));
+/// assert!(SchemaVersion::V2.has_field("provenance"));
+/// ```
+pub fn schema(version: SchemaVersion) -> &'static [FieldDescriptor] {
+    match version {
+        SchemaVersion::V1 => &SCHEMA_V1,
+        SchemaVersion::V2 => &SCHEMA_V2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotate::{json_error, Annotator};
+    use crate::constants::CITY_EDITION_REV1;
+    use crate::errors::GeoIpReaderError;
+    use crate::fixtures::city_database;
+    use crate::geo_ip_reader::GeoIpReader;
+    use std::collections::BTreeSet;
+    use std::io::Cursor;
+
+    /// The keys of a JSON object.
+    fn keys(object: &str) -> BTreeSet<String> {
+        let value: serde_json::Value = serde_json::from_str(object).unwrap();
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    /// The keys of a schema version with the given presences.
+    fn schema_keys(version: SchemaVersion, presences: &[Presence]) -> BTreeSet<String> {
+        schema(version)
+            .iter()
+            .filter(|field| presences.contains(&field.presence))
+            .map(|field| field.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_conformance() {
+        let database = city_database(CITY_EDITION_REV1);
+
+        for version in SchemaVersion::ALL {
+            let reader = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+            let mut annotator = Annotator::new(&RECORD_FIELD_NAMES)
+                .with_v4(reader)
+                .provenance(true)
+                .schema(version);
+
+            // Every field enabled gives exactly the keys of the version
+            let found = annotator
+                .to_json("200.0.0.1".parse().unwrap(), Some(1))
+                .unwrap();
+            assert_eq!(
+                keys(&found),
+                schema_keys(
+                    version,
+                    &[Presence::Always, Presence::Requested, Presence::Optional]
+                ),
+                "{}",
+                found
+            );
+            assert!(found.starts_with(&format!("{{\"schema_version\": \"{}\", ", version)));
+
+            // Then the optional fields are left out
+            let found = annotator
+                .to_json("200.0.0.1".parse().unwrap(), None)
+                .unwrap();
+            let mut expected = schema_keys(version, &[Presence::Always, Presence::Requested]);
+            if version.has_field("provenance") {
+                expected.insert("provenance".to_string());
+            }
+            assert_eq!(keys(&found), expected, "{}", found);
+
+            let errors = schema_keys(version, &[Presence::Always, Presence::Error]);
+            for ip in ["10.0.0.1", "100.0.0.1"] {
+                let error = annotator.to_json(ip.parse().unwrap(), Some(1)).unwrap();
+                assert_eq!(keys(&error), errors, "{}", error);
+            }
+            let err = GeoIpReaderError::AddressNotFound("100.0.0.1".to_string());
+            assert_eq!(keys(&json_error("100.0.0.1", &err, version)), errors);
+        }
+    }
+
+    #[test]
+    fn test_record_fields() {
+        for version in SchemaVersion::ALL {
+            let requested: Vec<&str> = schema(version)
+                .iter()
+                .filter(|field| field.presence == Presence::Requested)
+                .map(|field| field.name)
+                .collect();
+            assert_eq!(requested, RECORD_FIELD_NAMES);
+        }
+    }
+
+    #[test]
+    fn test_versions_only_add_fields() {
+        let v1 = schema(SchemaVersion::V1);
+        let v2 = schema(SchemaVersion::V2);
+
+        assert_eq!(&v2[..v1.len()], v1);
+        let names: BTreeSet<&str> = v2.iter().map(|field| field.name).collect();
+        assert_eq!(names.len(), v2.len());
+    }
+
+    #[test]
+    fn test_parse_schema_version() {
+        assert_eq!("1".parse(), Ok(SchemaVersion::V1));
+        assert_eq!("2".parse(), Ok(SchemaVersion::V2));
+        assert_eq!(SchemaVersion::default(), SchemaVersion::V1);
+        assert_eq!(
+            "3".parse::<SchemaVersion>(),
+            Err("unsupported schema version '3', expected one of 1, 2".to_string())
+        );
+        assert!("v1".parse::<SchemaVersion>().is_err());
+    }
+
+    #[test]
+    fn test_parse_schema_mode() {
+        assert_eq!("strict".parse(), Ok(SchemaMode::Strict));
+        assert_eq!("permissive".parse(), Ok(SchemaMode::Permissive));
+        assert_eq!(SchemaMode::default(), SchemaMode::Strict);
+        assert_eq!(SchemaMode::Permissive.to_string(), "permissive");
+        assert_eq!(
+            "lax".parse::<SchemaMode>(),
+            Err("unsupported schema mode 'lax', expected one of strict, permissive".to_string())
+        );
+    }
+}
@@ -1,13 +1,40 @@
+use crate::asn::Asn;
+use crate::bulk::{LookupItem, LookupResults};
+use crate::capabilities::SUPPORTED_EDITIONS;
 use crate::constants::*;
-use crate::countries::Country;
+use crate::countries::{Country, CountryStatus};
+use crate::db_install::{lock, LockKind};
 use crate::designated_market_area::DesignatedMarketArea;
-use crate::errors::GeoIpReaderError;
-use crate::time_zones::time_zone_by_country;
-use crate::utils::{ip_to_number, read_data};
+#[cfg(feature = "snapshot")]
+use crate::errors::SnapshotError;
+use crate::errors::{GeoIpReaderError, RangeError};
+use crate::interner::{InternedRecord, StringInterner};
+use crate::locale::Locale;
+use crate::post_process::{PostProcessors, RecordFields};
+use crate::progress::{Progress, Throttle};
+use crate::report::LookupReport;
+#[cfg(feature = "snapshot")]
+use crate::snapshot::{read_snapshot, write_snapshot, Layout};
+use crate::time_zones::{resolve_time_zone, OverrideSet};
+use crate::tree::{seek_depth, OctetIndex, TreeWalker};
+use crate::utils::{
+    addr_bits, addr_to_number, bits_addr, block_last, is_reserved, parse_ip_range, range_to_cidrs,
+    read_data, read_str_checked,
+};
 use dirs::home_dir;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+pub use crate::tree::ByteOrder;
 
 /// `GeoIpReader` represents a reader for GeoIP databases, allowing the retrieval
 /// of information based on IP addresses.
@@ -35,461 +62,7010 @@ where
     /// The starting point of the database segments in the GeoIP database.
     database_segments: u32,
     netmask: usize,
+    /// Which records get their designated market area decoded.
+    dma_policy: DmaPolicy,
+    /// The coordinates of the records without real ones.
+    coordinate_policy: CoordinatePolicy,
+    /// Whether lookups of city records fail on coordinates that are not real, see
+    /// `GeoIpReaderBuilder::strict_coordinates`.
+    strict_coordinates: bool,
+    /// Whether IPv4 addresses are looked up as IPv4-mapped addresses in an IPv6 database, see
+    /// `GeoIpReaderBuilder::map_v4_into_v6`.
+    map_v4_into_v6: bool,
+    /// The order of the bytes of the integers of the database.
+    byte_order: ByteOrder,
+    /// The timings of the last lookup, `None` unless enabled by `GeoIpReaderBuilder::timings`.
+    timings: Option<LookupTimings>,
+    /// Where the database content is read from.
+    source: SourceKind,
+    /// The build date of the database, from its database info.
+    build_date: Option<(u16, u8, u8)>,
+    /// Whether the first octets of IPv4 addresses get indexed, for a database in memory.
+    index_octets: bool,
+    /// Whether the database bytes are held in memory, so that `warm_up` has nothing to read.
+    in_memory: bool,
+    /// The index of the first octets of IPv4 addresses, built on the first IPv4 lookup, and
+    /// shared by the readers built with `GeoIpReaderBuilder::build_with_layout`.
+    octet_index: Option<Arc<OctetIndex>>,
+    /// The post-processors of the fields of `record_fields`.
+    post_processors: PostProcessors,
+    /// The time zones taking precedence over the built-in tables, see
+    /// `GeoIpReaderBuilder::time_zone_overrides`.
+    time_zone_overrides: Option<OverrideSet>,
+    /// How the database file was opened and its metadata then, for `reload_if_changed`.
+    opened: Option<(OpenOptions, FileStamp)>,
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Record<'a> {
-    pub dma: Option<DesignatedMarketArea>,
-    pub postal_code: Option<Box<str>>,
-    pub country: Country,
-    pub region_code: Option<Box<str>>,
-    pub city: Option<Box<str>>,
-    pub latitude: f64,
-    pub longitude: f64,
-    pub time_zone: &'a str,
-}
-
-impl<R> GeoIpReader<R>
-where
-    R: Read + Seek,
-{
-    /// Constructs a new `GeoIpReader` from the database.
-    ///
-    /// # Returns
+#[cfg(feature = "snapshot")]
+impl GeoIpReader<io::Cursor<Vec<u8>>> {
+    /// Restores an in-memory reader from a snapshot written by `GeoIpReader::save_snapshot`,
+    /// without detecting the layout of its database again.
     ///
-    /// (`Result<GeoIpReader<File>, GeoIpReaderError>`): A Result containing a `GeoIpReader` on success
-    /// or a `GeoIpReaderError` on failure.
+    /// # Arguments
     ///
-    /// # Examples
+    /// * `input` - The snapshot, e.g. a file.
     ///
-    /// ```
-    /// use std::fs::File;
-    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// # Errors
     ///
-    /// let result = GeoIpReader::<File>::new("v4");
-    /// match result {
-    ///     Ok(reader) => println!("GeoIpReader created successfully: {:?}", reader),
-    ///     Err(err) => eprintln!("Error creating GeoIpReader: {:?}", err),
-    /// }
-    /// ```
-    pub fn new(type_: &str) -> Result<GeoIpReader<File>, GeoIpReaderError> {
-        const ENV_VAR_NAME: &str = "IPCAP_FILE_PATH";
-        let file_path = match env::var(ENV_VAR_NAME) {
-            Ok(val) => val,
-            Err(_) => {
-                let default_path = match type_ {
-                    "v4" => {
-                        let mut path = home_dir().unwrap_or_default();
-                        path.push("ipcap");
-                        path.push("geo_ip_city_v4.dat");
-                        path
-                    }
-                    "v6" => {
-                        let mut path = home_dir().unwrap_or_default();
-                        path.push("ipcap");
-                        path.push("geo_ip_city_v6.dat");
-                        path
-                    }
-                    _ => {
-                        return Err(GeoIpReaderError::OpenFileError);
-                    }
-                };
-                default_path.to_string_lossy().into_owned()
-            }
-        };
+    /// Returns `SnapshotError::NotASnapshot` if the input is not a snapshot,
+    /// `SnapshotError::UnsupportedVersion` if it is of another version of the format,
+    /// `SnapshotError::HashMismatch` if its database bytes were altered,
+    /// `SnapshotError::InvalidLayout` if its layout does not fit its database, or
+    /// `SnapshotError::Io` if it cannot be read or is truncated.
+    pub fn load_snapshot(input: impl Read) -> Result<Self, SnapshotError> {
+        GeoIpReaderBuilder::new().load_snapshot(input)
+    }
+}
 
-        let fp = File::open(&file_path).map_err(|_| GeoIpReaderError::OpenFileError)?;
+/// Controls which city records get their designated market area (DMA) decoded.
+///
+/// The DMA bytes only exist in the `CITY_EDITION_REV1` editions, and regardless of the policy
+/// they are only decoded when the record actually holds them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DmaPolicy {
+    /// Decode the DMA for United States records only, as laid out by the standard databases.
+    #[default]
+    UnitedStatesOnly,
+    /// Decode the DMA for records of every country, for databases carrying non-US metro codes.
+    AllCountries,
+    /// Never decode the DMA.
+    Never,
+}
 
-        let mut geoip_reader = GeoIpReader {
-            fp,
-            netmask: 0,
-            database_type: 0,
-            record_length: 3,
-            database_segments: 0,
-        };
+/// Controls the coordinates given to the records without real coordinates, see
+/// `LatLng::is_real`: the records of Region editions, and the city records whose coordinates
+/// are zeroed.
+///
+/// The policy applies to the records returned by the lookups, to `GeoIpReader::get_coordinates`
+/// and to the fields formatted through `GeoIpReader::record_fields`. The records with real
+/// coordinates are never affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinatePolicy {
+    /// No coordinates: the `latitude` and `longitude` fields are missing, and the records keep
+    /// their decoded coordinates, which `Record::has_real_coordinates` tells apart.
+    #[default]
+    None,
+    /// The centroid of the country of the record, see `Country::centroid`, or no coordinates
+    /// for the countries without one.
+    CountryCentroid,
+    /// The (-180, -180) of zeroed coordinate fields, which older pipelines already special-case.
+    LegacySentinel,
+}
 
-        geoip_reader.detect_database_type()?;
-        Ok(geoip_reader)
+impl CoordinatePolicy {
+    /// The supported policies.
+    pub const ALL: [CoordinatePolicy; 3] = [
+        CoordinatePolicy::None,
+        CoordinatePolicy::CountryCentroid,
+        CoordinatePolicy::LegacySentinel,
+    ];
+
+    /// The name of the policy, as accepted by `--coordinate-policy`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoordinatePolicy::None => "none",
+            CoordinatePolicy::CountryCentroid => "country-centroid",
+            CoordinatePolicy::LegacySentinel => "legacy-sentinel",
+        }
     }
 
-    /// Detects the type of the GeoIP database and sets up segment sizes and start points accordingly.
-    ///
-    /// # Returns
-    ///
-    /// (`Result<(), GeoIpReaderError>`): A result indicating success or a `GeoIpReaderError` on failure.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if there is an issue reading or seeking the file.
+    /// Returns the coordinates of a record of the given country under this policy, `None`
+    /// when it has none.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::io::Cursor;
-    /// use ipcap::geo_ip_reader::GeoIpReader;
-    /// use ipcap::errors::GeoIpReaderError;
-    /// use std::fs::File;
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::{CoordinatePolicy, LatLng};
     ///
-    /// fn main() -> Result<(), GeoIpReaderError> {
-    ///     let mut reader = GeoIpReader::<File>::new("v4")?;
-    ///     reader.detect_database_type()?;
-    ///     Ok(())
-    /// }
+    /// let origin = LatLng { latitude: 0.0, longitude: 0.0 };
+    /// let paris = LatLng { latitude: 48.8566, longitude: 2.3522 };
+    ///
+    /// assert_eq!(CoordinatePolicy::None.resolve(Country::France, origin), None);
+    /// assert_eq!(
+    ///     CoordinatePolicy::CountryCentroid.resolve(Country::France, origin),
+    ///     Some(LatLng { latitude: 46.0, longitude: 2.0 })
+    /// );
+    /// assert_eq!(
+    ///     CoordinatePolicy::LegacySentinel.resolve(Country::France, origin),
+    ///     Some(LatLng { latitude: -180.0, longitude: -180.0 })
+    /// );
+    /// // Real coordinates are kept under every policy
+    /// assert_eq!(CoordinatePolicy::None.resolve(Country::France, paris), Some(paris));
     /// ```
-    pub fn detect_database_type(&mut self) -> Result<(), GeoIpReaderError> {
-        // Initialize default values
-        self.database_type = COUNTRY_EDITION;
-        self.record_length = STANDARD_RECORD_LENGTH;
-        self.database_segments = COUNTRY_BEGIN;
-
-        // Save current file position
-        let file_position = self.fp.stream_position().unwrap();
-
-        // Move to the end of the file minus 3 bytes
-        self.fp.seek(SeekFrom::End(-3)).unwrap();
-
-        // Loop to find the database type header
-        for _ in 0..STRUCTURE_INFO_MAX_SIZE {
-            // Define the expected header characters
-            let chars = [255u8, 255u8, 255u8];
-            // Read 3 bytes into delimiter
-            let mut delimiter = [0u8; 3];
-            self.fp.read_exact(&mut delimiter).unwrap();
-
-            // Check if delimiter matches the expected header
-            if delimiter == chars {
-                // Read one more byte to determine the database type
-                let mut byte = [0u8];
-                self.fp.read_exact(&mut byte).unwrap();
-                self.database_type = byte[0];
-
-                // Adjust the database type if needed
-                if self.database_type >= 106 {
-                    self.database_type -= 105;
-                }
-
-                // Match the database type to set appropriate values
-                match self.database_type {
-                    REGION_EDITION_REV0 => self.database_segments = STATE_BEGIN_REV0,
-                    REGION_EDITION_REV1 => self.database_segments = STATE_BEGIN_REV1,
-                    CITY_EDITION_REV0 | CITY_EDITION_REV1 | CITY_EDITION_REV1_V6 | ORG_EDITION
-                    | ISP_EDITION | ASNUM_EDITION | ASNUM_EDITION_V6 => {
-                        self.database_segments = 0;
-
-                        // Read SEGMENT_RECORD_LENGTH bytes into buf
-                        let mut buf = [0u8; SEGMENT_RECORD_LENGTH];
-                        self.fp.read_exact(&mut buf).unwrap();
-
-                        // Calculate database_segments from buf
-                        for (j, item) in buf.iter().enumerate().take(SEGMENT_RECORD_LENGTH) {
-                            self.database_segments += (*item as u32) << (j * 8);
-                        }
-
-                        // Adjust record_length for certain database types
-                        let long_records = [ORG_EDITION, ISP_EDITION];
-                        if long_records.contains(&self.database_type) {
-                            self.record_length = ORG_RECORD_LENGTH;
-                        }
-                    }
-                    _ => {}
-                }
-                // Break the loop once the header is found
-                break;
-            } else {
-                // Move back 4 bytes to continue searching
-                self.fp.seek(SeekFrom::Current(-4)).unwrap();
+    pub fn resolve(&self, country: Country, coordinates: LatLng) -> Option<LatLng> {
+        if coordinates.is_real() {
+            return Some(coordinates);
+        }
+        match self {
+            CoordinatePolicy::None => None,
+            CoordinatePolicy::CountryCentroid => {
+                country.centroid().map(|(latitude, longitude)| LatLng {
+                    latitude,
+                    longitude,
+                })
             }
+            CoordinatePolicy::LegacySentinel => Some(LatLng {
+                latitude: -180.0,
+                longitude: -180.0,
+            }),
         }
+    }
+}
 
-        // Restore the original file position
-        self.fp.seek(SeekFrom::Start(file_position)).unwrap();
+impl fmt::Display for CoordinatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
-        Ok(())
+impl FromStr for CoordinatePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        CoordinatePolicy::ALL
+            .into_iter()
+            .find(|policy| policy.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "unsupported coordinate policy '{}', expected one of none, \
+                     country-centroid, legacy-sentinel",
+                    value
+                )
+            })
     }
+}
 
-    /// Using the record length and appropriate start points, seek to the
-    /// country that corresponds to the converted IP address integer.
-    ///
-    /// # Arguments
-    ///
-    /// * `ip_number` - Result of ip_to_long conversion.
-    ///
-    /// # Returns
-    ///
-    /// The offset of the start of the record.
-    ///
-    /// # Errors
-    ///
-    /// Returns a `GeoIpReaderError` if the database is corrupted.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use ipcap::geo_ip_reader::GeoIpReader;
-    /// use std::fs::File;
-    ///
-    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-    ///
-    /// match geo_ip.get_country(16777216) {
-    ///     Ok(offset) => println!("Country offset: {}", offset),
-    ///     Err(err) => eprintln!("Error: {}", err),
-    /// }
-    /// ```
-    pub fn get_country(&mut self, ip_number: u128) -> Result<usize, GeoIpReaderError> {
-        // Initialize offset to 0
-        let mut offset = 0;
+/// Controls which database files `GeoIpReaderBuilder::open` accepts, and thus every constructor
+/// opening a path such as `GeoIpReader::new`.
+///
+/// The checks apply to the file itself: a symbolic link is only detected as the last component
+/// of the path. On platforms other than unix, only the symbolic link check of `Strict` applies.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FileTrustPolicy {
+    /// Refuse world-writable files, which any local user could swap.
+    #[default]
+    Standard,
+    /// Refuse symbolic links, files not owned by the current user, and group- or world-writable
+    /// files.
+    Strict,
+    /// Open any file.
+    AllowAny,
+}
 
-        // Determine seek depth based on the length of the IP address
-        let seek_depth = if ip_number.to_string().len() > 10 {
-            127
-        } else {
-            31
+impl FileTrustPolicy {
+    /// Opens the database file, refusing a symbolic link under `Strict`, and checks the metadata
+    /// of the opened file.
+    fn open(self, path: &Path) -> Result<File, GeoIpReaderError> {
+        let untrusted = |reason| GeoIpReaderError::UntrustedDatabaseFile {
+            path: path.to_path_buf(),
+            reason,
         };
+        let strict = self == FileTrustPolicy::Strict;
 
-        // Iterate through the seek depth in reverse order
-        for depth in (0..=seek_depth).rev() {
-            // Create a buffer to store read data
-            let mut buf: Vec<u8>;
+        let mut options = std::fs::OpenOptions::new();
+        options.read(true);
+        // The open itself refuses the link, so the path cannot be swapped for one after a check
+        #[cfg(unix)]
+        if strict {
+            use std::os::unix::fs::OpenOptionsExt;
 
-            // Calculate the start index and read length for the database
-            let start_index = 2 * self.record_length * offset;
-            let read_length = 2 * self.record_length;
-            // Create a new GeoIpReader instance for reading the database
-            let mut reader;
-            if seek_depth == 31 {
-                reader = GeoIpReader::<File>::new("v4").unwrap();
-            } else {
-                reader = GeoIpReader::<File>::new("v6").unwrap();
+            options.custom_flags(libc::O_NOFOLLOW);
+        }
+        #[cfg(not(unix))]
+        if strict
+            && path
+                .symlink_metadata()
+                .map_err(|_| GeoIpReaderError::OpenFileError)?
+                .file_type()
+                .is_symlink()
+        {
+            return Err(untrusted("the path is a symbolic link"));
+        }
+
+        let file = match options.open(path) {
+            Ok(file) => file,
+            #[cfg(unix)]
+            Err(err) if strict && err.raw_os_error() == Some(libc::ELOOP) => {
+                return Err(untrusted("the path is a symbolic link"));
             }
-            // Seek to the start index in the database
-            reader.fp.seek(SeekFrom::Start(start_index as u64)).unwrap();
+            Err(_) => return Err(GeoIpReaderError::OpenFileError),
+        };
+        self.check(path, &file)?;
+        Ok(file)
+    }
 
-            // Initialize the buffer with capacity and read data from the database
-            buf = Vec::with_capacity(read_length);
-            reader
-                .fp
-                .take(read_length as u64)
-                .read_to_end(&mut buf)
-                .unwrap();
+    /// Checks the metadata of the opened database file.
+    fn check(self, path: &Path, file: &File) -> Result<(), GeoIpReaderError> {
+        if self == FileTrustPolicy::AllowAny {
+            return Ok(());
+        }
 
-            // Array to store two 32-bit values
-            let mut x: [u32; 2] = [0, 0];
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
 
-            // Extract values from the buffer
-            for i in 0..2 {
-                for j in 0..self.record_length {
-                    let byte = buf[self.record_length * i + j] as u32;
-                    x[i] += byte << (j * 8);
-                }
+            let untrusted = |reason| GeoIpReaderError::UntrustedDatabaseFile {
+                path: path.to_path_buf(),
+                reason,
+            };
+            let metadata = file
+                .metadata()
+                .map_err(|_| GeoIpReaderError::OpenFileError)?;
+            if metadata.mode() & 0o002 != 0 {
+                return Err(untrusted("the file is world-writable"));
             }
-
-            // Check if the bit at the current depth is set in the IP number
-            if ip_number & (1 << depth) != 0 {
-                // If true, check if the second value is greater than or equal to database segments
-                if x[1] >= self.database_segments {
-                    // Set netmask and return the offset
-                    self.netmask = seek_depth - depth + 1;
-                    return Ok(x[1] as usize);
+            if self == FileTrustPolicy::Strict {
+                if metadata.mode() & 0o020 != 0 {
+                    return Err(untrusted("the file is group-writable"));
                 }
-                // Update offset with the second value
-                offset = x[1] as usize;
-            } else {
-                // If the bit is not set, check if the first value is greater than or equal to database segments
-                if x[0] >= self.database_segments {
-                    // Set netmask and return the offset
-                    self.netmask = seek_depth - depth + 1;
-                    return Ok(x[0] as usize);
+                // SAFETY: getuid has no preconditions and cannot fail
+                if metadata.uid() != unsafe { libc::getuid() } {
+                    return Err(untrusted("the file is not owned by the current user"));
                 }
-                // Update offset with the first value
-                offset = x[0] as usize;
             }
         }
+        #[cfg(not(unix))]
+        let _ = (path, file);
 
-        // If no valid offset is found, return an error
-        Err(GeoIpReaderError::CorruptDatabase)
+        Ok(())
     }
+}
 
-    /// Get the geographical record for a converted IP address.
-    ///
-    /// This function retrieves information such as country code, region code,
-    /// city, latitude, longitude, time zone, and more based on the provided IP address.
-    ///
-    /// # Arguments
-    ///
-    /// * `ip_number` - The converted IP address as a 32-bit unsigned integer.
-    ///
-    /// # Examples
-    /// ```
-    /// use ipcap::geo_ip_reader::GeoIpReader;
-    /// use std::fs::File;
-    ///
-    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-    ///
-    /// let record = geo_ip.get_record("185.90.90.120");
-    /// println!("Geographical Record: {:?}", record);
-    /// ```
-    pub fn get_record(&mut self, ip_number: &str) -> Record {
-        // Get the offset of the country record for the given IP address
-        let seek_country = self
-            .get_country(ip_to_number(ip_number).try_into().unwrap())
-            .unwrap();
-
-        // Check if the offset is equal to the total number of database segments
-        println!("{:?}", self.database_segments);
-        if seek_country == self.database_segments.try_into().unwrap() {
-            // todo!("Error handling")
-        }
-
-        // Calculate the read length based on the record length and database segments
-        let read_length = (2 * self.record_length - 1) * self.database_segments as usize;
-        // Create a buffer to store the read data
-        let mut buffer = vec![0; FULL_RECORD_LENGTH];
+/// `GeoIpReaderBuilder` configures how a `GeoIpReader` decodes its records.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::geo_ip_reader::{DmaPolicy, GeoIpReaderBuilder};
+///
+/// let mut path = dirs::home_dir().unwrap();
+/// path.push("ipcap/geo_ip_city_v4.dat");
+///
+/// let mut geo_ip = GeoIpReaderBuilder::new()
+///     .dma_policy(DmaPolicy::Never)
+///     .open(path)
+///     .unwrap();
+///
+/// assert_eq!(geo_ip.get_record("108.95.4.105").unwrap().dma, None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GeoIpReaderBuilder {
+    dma_policy: DmaPolicy,
+    coordinate_policy: CoordinatePolicy,
+    file_trust_policy: FileTrustPolicy,
+    timings: bool,
+    skip_octet_index: bool,
+    strict_coordinates: bool,
+    lock_shared: bool,
+    map_v4_into_v6: bool,
+    byte_order: ByteOrder,
+    post_processors: PostProcessors,
+    time_zone_overrides: Option<OverrideSet>,
+}
 
-        // Seek to the position in the file where the record is located
-        self.fp
-            .seek(SeekFrom::Start(seek_country as u64 + read_length as u64))
-            .unwrap();
-        // Read the record data into the buffer
-        self.fp.read_exact(&mut buffer).unwrap();
+/// The options of `GeoIpReaderBuilder` that apply to the opening of a database file, kept by
+/// the reader to open it again on `GeoIpReader::reload_if_changed`.
+#[derive(Debug, Clone, Copy)]
+struct OpenOptions {
+    file_trust_policy: FileTrustPolicy,
+    lock_shared: bool,
+}
 
-        let mut latitude = 0;
-        let mut longitude = 0;
+/// The offset basis and the prime of the 64-bit FNV-1a hash of `content_hash` and `fingerprint`.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
 
-        let country = Country::from_buffer(buffer[0]).unwrap();
+/// Hashes the bytes into a 64-bit FNV-1a hash, started from `FNV_OFFSET_BASIS`.
+fn fnv1a(hash: &mut u64, bytes: &[u8]) {
+    for byte in bytes {
+        *hash ^= u64::from(*byte);
+        *hash = hash.wrapping_mul(FNV_PRIME);
+    }
+}
 
-        let (offset, region_code) = read_data(&buffer, 1);
-        let (offset, city) = read_data(&buffer, offset + 1);
-        let (offset, postal_code) = read_data(&buffer, offset + 1);
-        let offset = offset + 1;
+/// Converts the error of a read of the database, a read past its end meaning a truncated
+/// database, as in the walks of the tree.
+fn read_error(err: io::Error) -> GeoIpReaderError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => GeoIpReaderError::CorruptDatabase,
+        _ => GeoIpReaderError::Io(err),
+    }
+}
 
-        for j in 0..3 {
-            latitude += (buffer[offset + j] as i32) << (j * 8);
+impl OpenOptions {
+    /// Opens a database file, checking it against the file trust policy and the minimum size,
+    /// and locks it if requested.
+    fn open(self, path: &Path) -> Result<(File, FileStamp), GeoIpReaderError> {
+        let fp = self.file_trust_policy.open(path)?;
+        // An empty or truncated file would otherwise be read as a country database without any
+        // address
+        let stamp = FileStamp::of(&fp.metadata()?);
+        if stamp.size < MIN_DATABASE_SIZE {
+            return Err(GeoIpReaderError::DatabaseTooSmall {
+                path: path.to_path_buf(),
+                size: stamp.size,
+                minimum: MIN_DATABASE_SIZE,
+            });
         }
-
-        for j in 0..3 {
-            longitude += (buffer[offset + j + 3] as i32) << (j * 8);
+        if self.lock_shared {
+            lock(&fp, LockKind::Shared)?;
         }
+        Ok((fp, stamp))
+    }
+}
 
-        let latitude = latitude as f64 / 10000.0 - 180.0;
-        let longitude = longitude as f64 / 10000.0 - 180.0;
-
-        let dma = if (self.database_type == CITY_EDITION_REV1
-            || self.database_type == CITY_EDITION_REV1_V6)
-            && country == Country::UnitedStates
-        {
-            let mut dma_area = 0;
-            for j in 0..3 {
-                dma_area += (buffer[offset + j + 6] as u32) << (j * 8);
-            }
+/// The size and modification time of a database file, which change when it is rewritten or
+/// replaced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileStamp {
+    size: u64,
+    /// `None` on platforms without modification times.
+    modified: Option<SystemTime>,
+}
 
-            Some(DesignatedMarketArea(dma_area))
-        } else {
-            None
-        };
+impl FileStamp {
+    fn of(metadata: &fs::Metadata) -> Self {
+        FileStamp {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
 
-        let time_zone = time_zone_by_country(
-            country.alphabetic_code_2(),
-            match &region_code {
-                Some(d) => d,
-                None => "default",
-            },
-            None,
-        )
-        .unwrap_or_default();
+impl GeoIpReaderBuilder {
+    /// Constructs a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        Record {
-            dma,
-            postal_code,
-            country,
-            region_code,
-            city,
-            latitude,
-            longitude,
-            time_zone,
-        }
+    /// Sets which records get their designated market area decoded.
+    pub fn dma_policy(mut self, dma_policy: DmaPolicy) -> Self {
+        self.dma_policy = dma_policy;
+        self
     }
 
-    /// Look up the time zone for a given IP address.
-    /// Use this method if you have a Region or City database.
+    /// Sets the coordinates given to the records without real ones, `CoordinatePolicy::None`
+    /// by default.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `addr` - IP address
+    /// ```
+    /// use ipcap::geo_ip_reader::{CoordinatePolicy, GeoIpReaderBuilder};
     ///
-    /// # Returns
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
     ///
-    /// Time zone as a string.
+    /// let mut geo_ip = GeoIpReaderBuilder::new()
+    ///     .coordinate_policy(CoordinatePolicy::CountryCentroid)
+    ///     .open(path)
+    ///     .unwrap();
     ///
-    pub fn get_time_zone_given_ip_addr(&mut self, addr: &str) -> &str {
-        let record = self.get_record(addr);
-        record.time_zone
+    /// // A record of the United States stored without coordinates
+    /// let record = geo_ip.get_record("12.25.116.1").unwrap();
+    /// assert_eq!((record.latitude, record.longitude), (38.0, -97.0));
+    /// ```
+    pub fn coordinate_policy(mut self, coordinate_policy: CoordinatePolicy) -> Self {
+        self.coordinate_policy = coordinate_policy;
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_new_geo_ip_reader() {
-        let result = GeoIpReader::<File>::new("v4");
-        assert!(result.is_ok());
+    /// Sets which database files `open` accepts, `FileTrustPolicy::Standard` by default.
+    pub fn file_trust_policy(mut self, file_trust_policy: FileTrustPolicy) -> Self {
+        self.file_trust_policy = file_trust_policy;
+        self
     }
 
-    #[test]
-    fn test_get_country() {
-        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-
-        match geo_ip.get_country(16777216) {
-            Ok(offset) => assert_eq!(offset, 2735459),
-            Err(err) => eprintln!("Error: {}", err),
-        }
+    /// Sets whether the reader times the phases of each lookup, see
+    /// `GeoIpReader::last_timings`. Disabled by default, so that lookups do not read the clock.
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.timings = timings;
+        self
     }
 
-    #[test]
-    fn test_get_time_zone_given_ip_addr() {
-        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-
-        let result = geo_ip.get_time_zone_given_ip_addr("185.90.90.120");
-        assert_eq!(result, "Asia/Riyadh".to_string());
-
-        let result = geo_ip.get_time_zone_given_ip_addr("108.95.4.105");
-        assert_eq!(result, "America/Los_Angeles");
+    /// Sets whether a reader built over a database in memory indexes the first octets of IPv4
+    /// addresses on its first IPv4 lookup, sparing the next walks the top 8 levels of the tree.
+    /// Enabled by default. Readers opened from a file never index them.
+    pub fn octet_index(mut self, octet_index: bool) -> Self {
+        self.skip_octet_index = !octet_index;
+        self
     }
 
-    #[test]
-    fn test_get_record_with_valid_ip() {
-        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-        let record = geo_ip.get_record("185.90.90.120");
+    /// Sets whether the lookups of city records fail with `GeoIpReaderError::MissingCoordinates`
+    /// when the coordinates of the record are not real, e.g. the (0, 0) "null island" of a
+    /// record without coordinates, see `LatLng::is_real`. Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let mut geo_ip = GeoIpReaderBuilder::new()
+    ///     .strict_coordinates(true)
+    ///     .open(path)
+    ///     .unwrap();
+    ///
+    /// assert!(geo_ip.get_record("108.95.4.105").unwrap().has_real_coordinates());
+    /// ```
+    pub fn strict_coordinates(mut self, strict_coordinates: bool) -> Self {
+        self.strict_coordinates = strict_coordinates;
+        self
+    }
 
-        assert_eq!(record.country, Country::SaudiArabia);
+    /// Sets whether `open` takes a shared advisory lock on the database file, held until the
+    /// reader is dropped, so that a tool rewriting the file in place under an exclusive lock
+    /// waits for the readers. Disabled by default.
+    ///
+    /// The lock is a `flock` lock: on platforms or filesystems without them, e.g. Windows or
+    /// some network filesystems, no lock is taken. Replacing the file with
+    /// `db_install::atomic_replace` protects the readers without any lock.
+    pub fn lock_shared(mut self, lock_shared: bool) -> Self {
+        self.lock_shared = lock_shared;
+        self
     }
 
-    #[test]
-    fn test_all_records_with_valid_ip() {
-        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-        let record = geo_ip.get_record("108.95.4.105");
+    /// Sets whether the IPv4 addresses looked up in an IPv6 database are widened to their
+    /// IPv4-mapped form, `::ffff:a.b.c.d`, so that a single IPv6 database serves both address
+    /// families. Disabled by default: the lookups of IPv4 addresses in an IPv6 database fail
+    /// with `GeoIpReaderError::AddressFamilyMismatch`.
+    ///
+    /// The records found depend on the database holding data for the IPv4-mapped range, as
+    /// most IPv6 databases do. IPv4 databases are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v6.dat");
+    ///
+    /// let mut geo_ip = GeoIpReaderBuilder::new()
+    ///     .map_v4_into_v6(true)
+    ///     .open(path)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(geo_ip.get_record("108.95.4.105").unwrap().country, Country::UnitedStates);
+    /// ```
+    pub fn map_v4_into_v6(mut self, map_v4_into_v6: bool) -> Self {
+        self.map_v4_into_v6 = map_v4_into_v6;
+        self
+    }
+
+    /// Sets the order of the bytes of the integers of the database: the pointers of its tree,
+    /// the segment count of its structure info, and the coordinates and DMA codes of its city
+    /// records. `ByteOrder::LittleEndian` by default, the order of the standard databases.
+    ///
+    /// The order cannot be detected: a database read with the wrong one fails to be detected or
+    /// yields garbage records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::{ByteOrder, GeoIpReaderBuilder};
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let mut geo_ip = GeoIpReaderBuilder::new()
+    ///     .byte_order(ByteOrder::LittleEndian)
+    ///     .open(&path)
+    ///     .unwrap();
+    /// assert_eq!(geo_ip.get_record("108.95.4.105").unwrap().country, Country::UnitedStates);
+    ///
+    /// // The standard database read in the other order
+    /// let mut geo_ip = GeoIpReaderBuilder::new()
+    ///     .byte_order(ByteOrder::BigEndian)
+    ///     .open(&path)
+    ///     .unwrap();
+    /// assert!(geo_ip.get_record("108.95.4.105").is_err());
+    /// ```
+    pub fn byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Registers a post-processor of the fields of the records formatted through
+    /// `GeoIpReader::record_fields`, after the ones registered before, see `post_process`.
+    pub fn post_processor<F>(mut self, processor: F) -> Self
+    where
+        F: Fn(&mut RecordFields<'_>) + Send + Sync + 'static,
+    {
+        self.post_processors.add(processor);
+        self
+    }
+
+    /// Sets the time zones resolved before the built-in tables, e.g. loaded with
+    /// `OverrideSet::from_toml`: a record gets the override of its region, otherwise the one of
+    /// its country as a whole, otherwise the zone of the built-in tables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+    /// use ipcap::time_zones::OverrideSet;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let overrides = OverrideSet::parse("US.CA = \"America/Phoenix\"").unwrap();
+    /// let mut geo_ip = GeoIpReaderBuilder::new()
+    ///     .time_zone_overrides(overrides)
+    ///     .open(path)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(geo_ip.get_record("108.95.4.105").unwrap().time_zone, "America/Phoenix");
+    /// ```
+    pub fn time_zone_overrides(mut self, overrides: OverrideSet) -> Self {
+        self.time_zone_overrides = Some(overrides);
+        self
+    }
+
+    /// Opens the database file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::UntrustedDatabaseFile` if the file is refused by the file
+    /// trust policy, `GeoIpReaderError::DatabaseTooSmall` if it is shorter than
+    /// `MIN_DATABASE_SIZE`, `GeoIpReaderError::OpenFileError` if it cannot be opened, or
+    /// `GeoIpReaderError::Io` if its metadata cannot be read or it cannot be locked.
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<GeoIpReader<File>, GeoIpReaderError> {
+        let path = path.as_ref();
+        let options = OpenOptions {
+            file_trust_policy: self.file_trust_policy,
+            lock_shared: self.lock_shared,
+        };
+        let (fp, stamp) = options.open(path)?;
+
+        let mut geoip_reader = self.build(fp)?;
+        geoip_reader.source = SourceKind::File {
+            path: Arc::from(path),
+        };
+        geoip_reader.opened = Some((options, stamp));
+        geoip_reader.index_octets = false;
+        Ok(geoip_reader)
+    }
+
+    /// Builds a `GeoIpReader` over any reader holding the database content.
+    pub fn build<R: Read + Seek>(self, fp: R) -> Result<GeoIpReader<R>, GeoIpReaderError> {
+        let mut geoip_reader = self.reader(fp);
+        geoip_reader.detect_database_type()?;
+        geoip_reader.build_date = geoip_reader.read_build_date();
+        Ok(geoip_reader)
+    }
+
+    /// Builds a `GeoIpReader` over the database bytes held in memory, e.g. downloaded or
+    /// embedded, which `GeoIpReader::warm_up` then knows to have nothing to read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let bytes = std::fs::read(path).unwrap();
+    /// let mut geo_ip = GeoIpReaderBuilder::new().build_in_memory(bytes).unwrap();
+    ///
+    /// assert!(geo_ip.warm_up(Duration::from_secs(10)).unwrap().already_resident);
+    /// assert_eq!(geo_ip.get_record("108.95.4.105").unwrap().city, Some("San Diego".into()));
+    /// ```
+    pub fn build_in_memory<T: AsRef<[u8]>>(
+        self,
+        bytes: T,
+    ) -> Result<GeoIpReader<io::Cursor<T>>, GeoIpReaderError> {
+        let mut geoip_reader = self.build(io::Cursor::new(bytes))?;
+        geoip_reader.in_memory = true;
+        Ok(geoip_reader)
+    }
+
+    /// Restores a reader from a snapshot written by `GeoIpReader::save_snapshot`, with the
+    /// options of this builder, see `GeoIpReader::load_snapshot`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `GeoIpReader::load_snapshot`.
+    #[cfg(feature = "snapshot")]
+    pub fn load_snapshot(
+        self,
+        mut input: impl Read,
+    ) -> Result<GeoIpReader<io::Cursor<Vec<u8>>>, SnapshotError> {
+        let (layout, database) = read_snapshot(&mut input)?;
+        let mut geoip_reader = self.reader(io::Cursor::new(database));
+        geoip_reader.database_type = layout.edition;
+        geoip_reader.record_length = layout.record_length;
+        geoip_reader.database_segments = layout.segments;
+        geoip_reader.build_date = geoip_reader.read_build_date();
+        geoip_reader.in_memory = true;
+        Ok(geoip_reader)
+    }
+
+    /// Builds a reader over database bytes whose layout was detected by another reader of the
+    /// same bytes, without detecting it again, as `load_snapshot` does.
+    pub(crate) fn build_with_layout<T: AsRef<[u8]>>(
+        self,
+        bytes: T,
+        layout: &DetectedLayout,
+    ) -> GeoIpReader<io::Cursor<T>> {
+        let mut geoip_reader = self.reader(io::Cursor::new(bytes));
+        geoip_reader.in_memory = true;
+        geoip_reader.database_type = layout.database_type;
+        geoip_reader.record_length = layout.record_length;
+        geoip_reader.database_segments = layout.database_segments;
+        geoip_reader.build_date = layout.build_date;
+        if geoip_reader.index_octets {
+            geoip_reader.octet_index = layout.octet_index.clone();
+        }
+        geoip_reader
+    }
+
+    /// Constructs a reader with the options of this builder, before the detection of the layout
+    /// of its database.
+    fn reader<R: Read + Seek>(self, fp: R) -> GeoIpReader<R> {
+        GeoIpReader {
+            fp,
+            netmask: 0,
+            database_type: 0,
+            record_length: 3,
+            database_segments: 0,
+            dma_policy: self.dma_policy,
+            coordinate_policy: self.coordinate_policy,
+            strict_coordinates: self.strict_coordinates,
+            map_v4_into_v6: self.map_v4_into_v6,
+            byte_order: self.byte_order,
+            timings: self.timings.then(LookupTimings::default),
+            source: SourceKind::Memory,
+            build_date: None,
+            index_octets: !self.skip_octet_index,
+            in_memory: false,
+            octet_index: None,
+            post_processors: self.post_processors,
+            time_zone_overrides: self.time_zone_overrides,
+            opened: None,
+        }
+    }
+}
+
+/// The layout of a database detected by a reader, with the index of its first octets, see
+/// `GeoIpReaderBuilder::build_with_layout`.
+#[derive(Debug, Clone)]
+pub(crate) struct DetectedLayout {
+    database_type: u8,
+    record_length: usize,
+    database_segments: u32,
+    build_date: Option<(u16, u8, u8)>,
+    octet_index: Option<Arc<OctetIndex>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Record<'a> {
+    pub dma: Option<DesignatedMarketArea>,
+    pub postal_code: Option<Box<str>>,
+    pub country: Country,
+    pub region_code: Option<Box<str>>,
+    pub city: Option<Box<str>>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub time_zone: &'a str,
+    /// The length of the prefix the record was matched on, the number of address bits walked
+    /// down the tree to reach it.
+    pub netmask: usize,
+    /// The longest prefix the lookup could have matched: 32 for an IPv4 address, 128 for an
+    /// IPv6 one.
+    pub max_netmask: usize,
+    /// The defects of the record that were worked around while decoding it.
+    pub warnings: Vec<RecordWarning>,
+    /// The fields the lookup that produced the record left empty or could not name, and why,
+    /// see `Record::gaps`. They are ignored by the comparison of records.
+    pub gaps: Vec<FieldGap>,
+    /// The source of the record, set by the lookup that produced it. It is ignored by the
+    /// comparison of records.
+    pub provenance: Option<Provenance>,
+    /// The byte of the database record the country was decoded from, see
+    /// `Record::country_index`. `Record::new` sets it from the country.
+    pub country_byte: u8,
+}
+
+impl PartialEq for Record<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dma == other.dma
+            && self.postal_code == other.postal_code
+            && self.country == other.country
+            && self.region_code == other.region_code
+            && self.city == other.city
+            && self.latitude == other.latitude
+            && self.longitude == other.longitude
+            && self.time_zone == other.time_zone
+            && self.netmask == other.netmask
+            && self.max_netmask == other.max_netmask
+            && self.warnings == other.warnings
+    }
+}
+
+/// A record found over a range of addresses, with the sub-ranges of the range it covers, as
+/// returned by `GeoIpReader::get_records_in_range`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeRecord {
+    /// The record, whose netmask is the prefix length of the first network it was found in.
+    pub record: Record<'static>,
+    /// The first and last addresses of the sub-ranges, in ascending order. Adjacent networks
+    /// of the record are merged into one sub-range.
+    pub ranges: Vec<(IpAddr, IpAddr)>,
+}
+
+/// The source of a record, e.g. to tell which layer of a `LayeredResolver` produced it once the
+/// record is detached from its reader.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::constants::CITY_EDITION_REV1;
+/// use ipcap::geo_ip_reader::{GeoIpReader, SourceKind};
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let record = geo_ip.get_record("108.95.4.105").unwrap();
+/// let provenance = record.provenance.unwrap();
+///
+/// assert!(matches!(provenance.source, SourceKind::File { .. }));
+/// assert_eq!(provenance.edition, Some(CITY_EDITION_REV1));
+/// assert_eq!(provenance.db_build_date, Some((2016, 4, 5)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub source: SourceKind,
+    /// The edition of the database, one of the `*_EDITION*` constants, `None` for an override.
+    pub edition: Option<u8>,
+    /// The build date of the database as `(year, month, day)`, if its database info has one.
+    pub db_build_date: Option<(u16, u8, u8)>,
+}
+
+/// Where the records of a `Provenance` are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceKind {
+    /// A database file, opened by its path.
+    File { path: Arc<Path> },
+    /// A database held by any other reader, e.g. a buffer in memory.
+    Memory,
+    /// An `OverrideTable`.
+    Override,
+    /// The dataset compiled into the crate, looked up by a `FallbackResolver`.
+    #[cfg(feature = "embedded-country-data")]
+    Embedded,
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceKind::File { path } => write!(f, "file {}", path.display()),
+            SourceKind::Memory => f.write_str("memory"),
+            SourceKind::Override => f.write_str("override"),
+            #[cfg(feature = "embedded-country-data")]
+            SourceKind::Embedded => f.write_str("embedded"),
+        }
+    }
+}
+
+/// A defect of a record that was worked around while decoding it, as listed in
+/// `Record::warnings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordWarning {
+    /// A string field held bytes that are not valid UTF-8, which were dropped from its value.
+    InvalidBytes {
+        /// The name of the field, as in `utils::RECORD_FIELD_NAMES`.
+        field: &'static str,
+        /// The number of dropped bytes.
+        count: usize,
+    },
+}
+
+impl fmt::Display for RecordWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordWarning::InvalidBytes { field, count } => {
+                write!(f, "dropped {} invalid bytes from the {}", count, field)
+            }
+        }
+    }
+}
+
+/// A field of a record that the lookup left empty or could not name, and why, as listed in
+/// `Record::gaps`, e.g. to tell the limits of the data from a bug when a field is missing.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::{FieldGap, GapReason};
+///
+/// let gap = FieldGap {
+///     field: "time_zone",
+///     reason: GapReason::UnmappedRegion {
+///         country: Country::UnitedStates,
+///         region: Some("XX".into()),
+///     },
+/// };
+/// assert_eq!(gap.to_string(), "time_zone missing: no zone mapping for US region 'XX'");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldGap {
+    /// The name of the field, as in `utils::RECORD_FIELD_NAMES`.
+    pub field: &'static str,
+    pub reason: GapReason,
+}
+
+/// Why a field of a record is empty or could not be named, as in `FieldGap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GapReason {
+    /// The record of the database does not hold the field.
+    NotInDatabase,
+    /// The time zone table has no zone for the region of the record, or no region to tell the
+    /// zone of a country spanning several ones.
+    UnmappedRegion {
+        country: Country,
+        region: Option<Box<str>>,
+    },
+    /// The country is not a territory but a pseudo-country such as `AnonymousProxy`, which has
+    /// no location.
+    PseudoCountry,
+    /// The edition of the database only locates countries and their regions, e.g. a Region
+    /// edition, whose records have no city, postal code nor coordinates.
+    CountryOnlyRecord,
+    /// The DMA code has no name, see `DesignatedMarketArea::is_known`.
+    UnknownDmaCode(u32),
+}
+
+impl fmt::Display for FieldGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            GapReason::NotInDatabase => {
+                write!(
+                    f,
+                    "{} missing: not in the record of the database",
+                    self.field
+                )
+            }
+            GapReason::UnmappedRegion {
+                country,
+                region: Some(region),
+            } => write!(
+                f,
+                "{} missing: no zone mapping for {} region '{}'",
+                self.field,
+                country.alphabetic_code_2(),
+                region
+            ),
+            GapReason::UnmappedRegion {
+                country,
+                region: None,
+            } => write!(
+                f,
+                "{} missing: {} spans several zones and the record has no region",
+                self.field,
+                country.alphabetic_code_2()
+            ),
+            GapReason::PseudoCountry => write!(
+                f,
+                "{} missing: the country is a pseudo-country, not a territory",
+                self.field
+            ),
+            GapReason::CountryOnlyRecord => write!(
+                f,
+                "{} missing: the database only locates countries and regions",
+                self.field
+            ),
+            GapReason::UnknownDmaCode(code) => {
+                write!(f, "{} unknown: no name for DMA code {}", self.field, code)
+            }
+        }
+    }
+}
+
+impl GapReason {
+    /// The reason of a missing time zone: the country is a pseudo-country, or the time zone
+    /// table does not map its region.
+    fn time_zone(country: Country, region: Option<&str>) -> Self {
+        if country.status() == CountryStatus::Reserved {
+            GapReason::PseudoCountry
+        } else {
+            GapReason::UnmappedRegion {
+                country,
+                region: region.map(Box::from),
+            }
+        }
+    }
+}
+
+/// The region code of a record in both conventions, as returned by `Record::region_codes`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RegionCodes {
+    /// The code stored in the database, as returned by `Record::region_code`: FIPS 10-4 for most
+    /// countries, e.g. `02` for New South Wales, and the postal abbreviation for the United
+    /// States and Canada.
+    pub fips: Option<Box<str>>,
+    /// The ISO 3166-2 code, e.g. `AU-NSW`, when the region code could be converted.
+    pub iso: Option<Box<str>>,
+}
+
+/// The most specific level of location a record has, as returned by `Record::accuracy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Accuracy {
+    /// The country only.
+    Country,
+    /// The region, without a city.
+    Region,
+    /// The city, with or without a region: some databases leave the region of a city empty.
+    City,
+}
+
+impl fmt::Display for Accuracy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Accuracy::Country => "country",
+            Accuracy::Region => "region",
+            Accuracy::City => "city",
+        })
+    }
+}
+
+impl Record<'static> {
+    /// Constructs a `Record` of the given country, e.g. to fabricate lookup results in tests
+    /// without a database.
+    ///
+    /// The other fields are empty, the coordinates are `0.0` and the time zone is the default
+    /// one of the country, if it has one. They can be set with the `with_*` methods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    ///
+    /// let record = Record::new(Country::UnitedStates)
+    ///     .with_region_code("CA")
+    ///     .with_city("San Diego")
+    ///     .with_coordinates(32.7977, -117.2335);
+    ///
+    /// assert_eq!(record.city(), Some("San Diego"));
+    /// assert_eq!(record.postal_code(), None);
+    /// assert_eq!(record.time_zone, "America/Los_Angeles");
+    /// ```
+    pub fn new(country: Country) -> Self {
+        Record {
+            dma: None,
+            postal_code: None,
+            country,
+            region_code: None,
+            city: None,
+            latitude: 0.0,
+            longitude: 0.0,
+            time_zone: resolve_time_zone(country.alphabetic_code_2(), None).unwrap_or_default(),
+            netmask: 0,
+            max_netmask: 0,
+            warnings: Vec::new(),
+            gaps: Vec::new(),
+            provenance: None,
+            country_byte: country.to_buffer(),
+        }
+    }
+}
+
+impl<'a> Record<'a> {
+    /// Sets the designated market area.
+    pub fn with_dma(mut self, dma: DesignatedMarketArea) -> Self {
+        self.dma = Some(dma);
+        self
+    }
+
+    /// Sets the postal code.
+    pub fn with_postal_code(mut self, postal_code: &str) -> Self {
+        self.postal_code = Some(postal_code.into());
+        self
+    }
+
+    /// Sets the region code, and the time zone of the region if it has its own.
+    pub fn with_region_code(mut self, region_code: &str) -> Self {
+        if let Some(time_zone) =
+            resolve_time_zone(self.country.alphabetic_code_2(), Some(region_code))
+        {
+            self.time_zone = time_zone;
+        }
+        self.region_code = Some(region_code.into());
+        self
+    }
+
+    /// Sets the city name.
+    pub fn with_city(mut self, city: &str) -> Self {
+        self.city = Some(city.into());
+        self
+    }
+
+    /// Sets the latitude and longitude.
+    pub fn with_coordinates(mut self, latitude: f64, longitude: f64) -> Self {
+        self.latitude = latitude;
+        self.longitude = longitude;
+        self
+    }
+
+    /// Sets the time zone.
+    pub fn with_time_zone(mut self, time_zone: &'a str) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    /// Sets the length of the matched prefix and the longest one the lookup could have matched.
+    pub fn with_netmask(mut self, netmask: usize, max_netmask: usize) -> Self {
+        self.netmask = netmask;
+        self.max_netmask = max_netmask;
+        self
+    }
+
+    /// Sets the source of the record.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+}
+
+impl Record<'_> {
+    /// Returns the postal code, if any.
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// assert_eq!(record.postal_code(), Some("92109"));
+    /// ```
+    pub fn postal_code(&self) -> Option<&str> {
+        self.postal_code.as_deref()
+    }
+
+    /// Returns the city name, if any.
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// assert_eq!(record.city(), Some("San Diego"));
+    /// ```
+    pub fn city(&self) -> Option<&str> {
+        self.city.as_deref()
+    }
+
+    /// Returns the fields the lookup left empty or could not name, and why, empty for a record
+    /// with every field, or not produced by a lookup.
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::{FieldGap, GapReason, GeoIpReader};
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// assert!(geo_ip.get_record("108.95.4.105").unwrap().gaps().is_empty());
+    ///
+    /// let record = geo_ip.get_record("185.90.90.120").unwrap();
+    /// assert_eq!(record.country, Country::SaudiArabia);
+    /// assert!(record.gaps().contains(&FieldGap {
+    ///     field: "city",
+    ///     reason: GapReason::NotInDatabase,
+    /// }));
+    /// ```
+    pub fn gaps(&self) -> &[FieldGap] {
+        &self.gaps
+    }
+
+    /// Lists the gaps of a record of a Region edition, which holds no more than a country and
+    /// a region.
+    fn with_region_gaps(mut self) -> Self {
+        let region = self.region_code.is_some();
+        for (field, missing) in [
+            ("region_code", !region),
+            ("city", true),
+            ("postal_code", true),
+            ("latitude", true),
+            ("longitude", true),
+        ] {
+            if missing {
+                self.gaps.push(FieldGap {
+                    field,
+                    reason: GapReason::CountryOnlyRecord,
+                });
+            }
+        }
+        if self.time_zone.is_empty() {
+            let reason = GapReason::time_zone(self.country, self.region_code());
+            self.gaps.push(FieldGap {
+                field: "time_zone",
+                reason,
+            });
+        }
+        self
+    }
+
+    /// Returns the region code, if any, as stored in the database. It is the `fips` form of
+    /// `region_codes`.
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// assert_eq!(record.region_code(), Some("CA"));
+    /// ```
+    pub fn region_code(&self) -> Option<&str> {
+        self.region_code.as_deref()
+    }
+
+    /// Returns the region code of the record both as stored in the database and, when it can be
+    /// converted with `Country::iso_region_code`, as an ISO 3166-2 code.
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    /// let region_codes = record.region_codes();
+    ///
+    /// assert_eq!(region_codes.fips.as_deref(), Some("CA"));
+    /// assert_eq!(region_codes.iso.as_deref(), Some("US-CA"));
+    /// ```
+    pub fn region_codes(&self) -> RegionCodes {
+        let iso = self
+            .region_code()
+            .and_then(|fips| self.country.iso_region_code(fips));
+        RegionCodes {
+            fips: self.region_code.clone(),
+            iso: iso.map(Into::into),
+        }
+    }
+
+    /// Returns the country byte of the record, the internal index of the country in the
+    /// database rather than a standard code, e.g. to join against tables keyed on it.
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// assert_eq!(record.country_index(), 225);
+    /// ```
+    pub fn country_index(&self) -> u8 {
+        self.country_byte
+    }
+
+    /// Returns whether the coordinates of the record can be a real location, see
+    /// `LatLng::is_real`. The records of Region editions and the records built without
+    /// coordinates are at the origin, so they have none.
+    pub fn has_real_coordinates(&self) -> bool {
+        LatLng {
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }
+        .is_real()
+    }
+
+    /// Returns the geohash of the coordinates of the record, the standard base-32 encoding of
+    /// the cell holding them, e.g. as a key to shard or bucket records by location. Each
+    /// character narrows the cell: 5 characters are within about 2.4 km, 12 within a few
+    /// centimeters.
+    ///
+    /// # Arguments
+    ///
+    /// * `precision` - The number of characters of the geohash.
+    ///
+    /// # Returns
+    ///
+    /// (`Option<String>`): The geohash, `None` if the record has no real coordinates, see
+    /// `has_real_coordinates`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// assert_eq!(record.geohash(5).as_deref(), Some("9muds"));
+    /// ```
+    pub fn geohash(&self, precision: usize) -> Option<String> {
+        const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+        if !self.has_real_coordinates() {
+            return None;
+        }
+        let mut latitude = (-90.0, 90.0);
+        let mut longitude = (-180.0, 180.0);
+        let mut even = true;
+        let mut geohash = String::with_capacity(precision);
+        while geohash.len() < precision {
+            let mut index = 0;
+            for _ in 0..5 {
+                // The bits alternate between the longitude and the latitude, longitude first,
+                // each halving the range of its coordinate
+                let (range, value) = if even {
+                    (&mut longitude, self.longitude)
+                } else {
+                    (&mut latitude, self.latitude)
+                };
+                let middle = (range.0 + range.1) / 2.0;
+                index <<= 1;
+                if value >= middle {
+                    index |= 1;
+                    range.0 = middle;
+                } else {
+                    range.1 = middle;
+                }
+                even = !even;
+            }
+            geohash.push(BASE32.get(index).map_or('0', |byte| char::from(*byte)));
+        }
+        Some(geohash)
+    }
+
+    /// Returns the current country of the record, the successor of a deprecated country of an
+    /// older database, see `Country::modernize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    ///
+    /// let record = Record::new(Country::MetropolitanFrance);
+    /// assert_eq!(record.modernized_country(), Country::France);
+    /// ```
+    pub fn modernized_country(&self) -> Country {
+        self.country.modernize()
+    }
+
+    /// Computes a content hash of the record, to detect whether the record of an address
+    /// materially changed, e.g. between two versions of the database.
+    ///
+    /// The country, region, city, postal code, coordinates and time zone are hashed with 64-bit
+    /// FNV-1a, so the value is stable across runs and platforms. The coordinates are rounded to
+    /// the ten-thousandths of a degree the databases store, so that float noise does not change
+    /// the hash. The DMA, netmasks, warnings and provenance are left out.
+    ///
+    /// # Returns
+    ///
+    /// (`u64`): The content hash of the record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    ///
+    /// let record = Record::new(Country::France).with_coordinates(48.8566, 2.3522);
+    /// let same_record = Record::new(Country::France).with_coordinates(48.856600000001, 2.3522);
+    /// let other_record = Record::new(Country::France).with_coordinates(48.8567, 2.3522);
+    ///
+    /// assert_eq!(record.content_hash(), same_record.content_hash());
+    /// assert_ne!(record.content_hash(), other_record.content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        // Length-prefixed, so that e.g. a city moving to the postal code changes the hash
+        fn write_str(hash: &mut u64, value: Option<&str>) {
+            match value {
+                Some(value) => {
+                    fnv1a(hash, &[1]);
+                    fnv1a(hash, &(value.len() as u64).to_le_bytes());
+                    fnv1a(hash, value.as_bytes());
+                }
+                None => fnv1a(hash, &[0]),
+            }
+        }
+
+        let mut hash = FNV_OFFSET_BASIS;
+        write_str(&mut hash, Some(self.country.alphabetic_code_2()));
+        write_str(&mut hash, self.region_code.as_deref());
+        write_str(&mut hash, self.city.as_deref());
+        write_str(&mut hash, self.postal_code.as_deref());
+        for coordinate in [self.latitude, self.longitude] {
+            fnv1a(
+                &mut hash,
+                &((coordinate * 10_000.0).round() as i64).to_le_bytes(),
+            );
+        }
+        write_str(&mut hash, Some(self.time_zone));
+        hash
+    }
+
+    /// Returns how specific the match of the record is, from `0.0` to `1.0`.
+    ///
+    /// The score is the length of the matched prefix over the longest one the lookup could have
+    /// matched, i.e. the depth of the leaf relative to the last level of the tree (31 for IPv4,
+    /// 127 for IPv6): a record matched on a `/24` of an IPv4 address scores `0.75`, one matched
+    /// on a `/8` scores `0.25`. A longer prefix means the database singles out a smaller network
+    /// for the record, which usually comes with more precise data, but the score says nothing
+    /// about the accuracy of the data itself. Scores of IPv4 and IPv6 lookups are comparable as
+    /// fractions of their address length only.
+    ///
+    /// # Returns
+    ///
+    /// (`f32`): The score, `0.0` for a record that was not looked up, e.g. built with
+    /// `Record::new`, and `fallback::CONFIDENCE` for a record of the embedded dataset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// assert_eq!(record.location_confidence(), geo_ip.netmask() as f32 / 32.0);
+    /// ```
+    pub fn location_confidence(&self) -> f32 {
+        #[cfg(feature = "embedded-country-data")]
+        if matches!(&self.provenance, Some(provenance) if provenance.source == SourceKind::Embedded)
+        {
+            return crate::fallback::CONFIDENCE;
+        }
+        if self.max_netmask == 0 {
+            return 0.0;
+        }
+        (self.netmask as f32 / self.max_netmask as f32).min(1.0)
+    }
+
+    /// Returns the most specific level of location the record has. A city without a region is
+    /// `Accuracy::City`, the region being missing from the database rather than unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::{Accuracy, Record};
+    ///
+    /// let record = Record::new(Country::Singapore).with_city("Singapore");
+    ///
+    /// assert_eq!(record.region_code(), None);
+    /// assert_eq!(record.accuracy(), Accuracy::City);
+    /// assert_eq!(Record::new(Country::Singapore).accuracy(), Accuracy::Country);
+    /// ```
+    pub fn accuracy(&self) -> Accuracy {
+        let present = |field: Option<&str>| field.is_some_and(|value| !value.is_empty());
+        if present(self.city()) {
+            Accuracy::City
+        } else if present(self.region_code()) {
+            Accuracy::Region
+        } else {
+            Accuracy::Country
+        }
+    }
+
+    /// Returns the city, the region code and the country name of the record, from the most to
+    /// the least specific, leaving out the missing and empty ones, e.g. to join them with
+    /// `", "` without dangling separators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    ///
+    /// let record = Record::new(Country::UnitedStates).with_city("San Diego");
+    /// assert_eq!(record.location_parts().join(", "), "San Diego, United States");
+    ///
+    /// let record = record.with_region_code("CA");
+    /// assert_eq!(record.location_parts(), ["San Diego", "CA", "United States"]);
+    /// ```
+    pub fn location_parts(&self) -> Vec<&str> {
+        [self.city(), self.region_code(), Some(self.country.name())]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect()
+    }
+}
+
+/// The place of an address as owned strings, as returned by `GeoIpReader::get_place`, e.g. to
+/// be sent across threads or an API boundary without the lifetime of a `Record`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Place {
+    /// The English name of the country.
+    pub country: String,
+    /// The code of the region, e.g. `CA` for California.
+    pub region: Option<String>,
+    pub city: Option<String>,
+}
+
+impl From<&Record<'_>> for Place {
+    fn from(record: &Record<'_>) -> Self {
+        Place {
+            country: record.country.name().to_string(),
+            region: record.region_code().map(str::to_string),
+            city: record.city().map(str::to_string),
+        }
+    }
+}
+
+/// The coordinates of a city record, as returned by `GeoIpReader::get_coordinates`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatLng {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl LatLng {
+    /// Returns whether the coordinates can be a real location rather than an artifact of a
+    /// record without coordinates.
+    ///
+    /// The coordinates of such records decode to the origin, the "null island" (0, 0) in the
+    /// Gulf of Guinea, or to values out of range when the fields are zeroed or filled, e.g.
+    /// (-180, -180). Only the exact origin is rejected: a location on the equator or on the
+    /// prime meridian, even a few meters away from the origin, is real.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::LatLng;
+    ///
+    /// let coordinates = |latitude, longitude| LatLng { latitude, longitude };
+    ///
+    /// assert!(!coordinates(0.0, 0.0).is_real());
+    /// assert!(!coordinates(-180.0, -180.0).is_real());
+    /// // Greenwich, on the prime meridian, and a point of the equator off Sao Tome
+    /// assert!(coordinates(51.4779, 0.0).is_real());
+    /// assert!(coordinates(0.0, 6.7273).is_real());
+    /// assert!(coordinates(0.0001, 0.0001).is_real());
+    /// ```
+    pub fn is_real(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude)
+            && (-180.0..=180.0).contains(&self.longitude)
+            && !(self.latitude == 0.0 && self.longitude == 0.0)
+    }
+
+    /// Returns whether two coordinates are within `epsilon_deg` degrees of each other in
+    /// latitude and in longitude, e.g. to group records by location despite float noise.
+    ///
+    /// The longitudes wrap around the antimeridian, so that 180 and -180 are equal. Coordinates
+    /// holding a NaN are never equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::LatLng;
+    ///
+    /// let a = LatLng { latitude: 0.1 + 0.2, longitude: 179.99999 };
+    /// let b = LatLng { latitude: 0.3, longitude: -180.0 };
+    ///
+    /// assert_ne!(a, b);
+    /// assert!(a.approx_eq(&b, 1e-4));
+    /// assert!(!a.approx_eq(&b, 1e-6));
+    /// ```
+    pub fn approx_eq(&self, other: &LatLng, epsilon_deg: f64) -> bool {
+        let latitude = (self.latitude - other.latitude).abs();
+        let longitude = (self.longitude - other.longitude).abs() % 360.0;
+        latitude <= epsilon_deg && longitude.min(360.0 - longitude) <= epsilon_deg
+    }
+
+    /// Returns the coordinates rounded to the given number of decimals, half away from zero,
+    /// e.g. 4 for the precision of the databases. The coordinates are unchanged when scaling
+    /// them overflows, e.g. with hundreds of decimals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::LatLng;
+    ///
+    /// let coordinates = LatLng { latitude: 32.79769999999999, longitude: -117.23349999999999 };
+    ///
+    /// assert_eq!(
+    ///     coordinates.round(4),
+    ///     LatLng { latitude: 32.7977, longitude: -117.2335 }
+    /// );
+    /// ```
+    pub fn round(&self, decimals: u32) -> LatLng {
+        let factor = 10f64.powi(decimals.min(i32::MAX as u32) as i32);
+        let round = |value: f64| {
+            let rounded = (value * factor).round() / factor;
+            if rounded.is_finite() {
+                rounded
+            } else {
+                value
+            }
+        };
+        LatLng {
+            latitude: round(self.latitude),
+            longitude: round(self.longitude),
+        }
+    }
+
+    /// Decodes the coordinates at the start of the bytes following the strings of a record,
+    /// missing bytes reading as zeros.
+    #[deny(clippy::arithmetic_side_effects)]
+    fn decode(tail: &[u8], byte_order: ByteOrder) -> Self {
+        // The coordinates are stored as 3-byte integers, so they are below 2^24 and their
+        // conversion to f64 is exact
+        let read = |bytes| byte_order.read(bytes);
+        LatLng {
+            latitude: tail.get(..3).map_or(0, read) as f64 / 10000.0 - 180.0,
+            longitude: tail.get(3..6).map_or(0, read) as f64 / 10000.0 - 180.0,
+        }
+    }
+}
+
+/// The fields decoded from a city record, before they are assembled into a `Record` or an
+/// `InternedRecord`.
+struct CityFields<S> {
+    dma: Option<DesignatedMarketArea>,
+    postal_code: Option<S>,
+    country: Country,
+    /// The byte the country was decoded from.
+    country_byte: u8,
+    region_code: Option<S>,
+    city: Option<S>,
+    latitude: f64,
+    longitude: f64,
+    time_zone: &'static str,
+    warnings: Vec<RecordWarning>,
+    gaps: Vec<FieldGap>,
+}
+
+/// The wall time spent in each phase of a lookup, as returned by `GeoIpReader::last_timings`
+/// and `GeoIpReader::get_record_timed`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+///
+/// let mut path = dirs::home_dir().unwrap();
+/// path.push("ipcap/geo_ip_city_v4.dat");
+///
+/// let mut geo_ip = GeoIpReaderBuilder::new().timings(true).open(path).unwrap();
+/// geo_ip.get_record("108.95.4.105").unwrap();
+///
+/// let timings = geo_ip.last_timings().unwrap();
+/// assert!(timings.total() >= timings.tree_walk);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupTimings {
+    /// Parsing the address into an IP number.
+    pub parse: Duration,
+    /// Walking the tree down to the leaf of the address.
+    pub tree_walk: Duration,
+    /// Reading and decoding the record.
+    pub record_parse: Duration,
+}
+
+impl LookupTimings {
+    /// Returns the duration of the whole lookup.
+    pub fn total(&self) -> Duration {
+        self.parse + self.tree_walk + self.record_parse
+    }
+}
+
+/// The outcome of `GeoIpReader::warm_up` and `GeoIpReader::warm_up_index_only`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmUpReport {
+    /// The number of bytes read from the start of the database.
+    pub bytes_touched: u64,
+    /// The number of bytes to warm up: the size of the database, or of its tree.
+    pub total_bytes: u64,
+    /// The time spent reading.
+    pub elapsed: Duration,
+    /// Whether the database bytes are held in memory already, see
+    /// `GeoIpReaderBuilder::build_in_memory`, nothing being read then.
+    pub already_resident: bool,
+}
+
+impl WarmUpReport {
+    /// Returns whether every byte to warm up is cached, the budget not being exhausted before.
+    pub fn is_complete(&self) -> bool {
+        self.already_resident || self.bytes_touched >= self.total_bytes
+    }
+}
+
+/// Measures the laps of a lookup, without reading the clock when disabled.
+struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    fn start(enabled: bool) -> Self {
+        Stopwatch(enabled.then(Instant::now))
+    }
+
+    /// Returns the time elapsed since the previous lap, zero when disabled.
+    fn lap(&mut self) -> Duration {
+        match &mut self.0 {
+            Some(last) => {
+                let now = Instant::now();
+                let lap = now - *last;
+                *last = now;
+                lap
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+/// A reader over the `[offset, offset + len)` range of another reader, as opened by
+/// `GeoIpReader::from_reader_at` to read a database embedded in a larger file.
+///
+/// Positions are relative to the start of the range, and the end of the range reads as the end
+/// of the file.
+#[derive(Debug)]
+pub struct DatabaseSlice<R> {
+    /// The reader over the whole file.
+    inner: R,
+    /// The offset of the range in the file.
+    offset: u64,
+    /// The length of the range.
+    len: u64,
+    /// The position within the range.
+    position: u64,
+}
+
+impl<R: Read + Seek> DatabaseSlice<R> {
+    /// Constructs a reader over the `[offset, offset + len)` range of `inner`, positioned at the
+    /// start of the range.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the range exceeds the end of `inner`, or
+    /// `GeoIpReaderError::Io` if `inner` cannot be sought.
+    pub fn new(mut inner: R, offset: u64, len: u64) -> Result<Self, GeoIpReaderError> {
+        let size = inner.seek(SeekFrom::End(0))?;
+        if offset.checked_add(len).is_none_or(|end| end > size) {
+            return Err(GeoIpReaderError::CorruptDatabase);
+        }
+        inner.seek(SeekFrom::Start(offset))?;
+
+        Ok(DatabaseSlice {
+            inner,
+            offset,
+            len,
+            position: 0,
+        })
+    }
+
+    /// Returns the reader over the whole file.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for DatabaseSlice<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.position);
+        let length = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let read = self.inner.read(buf.get_mut(..length).unwrap_or_default())?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for DatabaseSlice<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        // Past the end of the range, reads return nothing as at the end of a file
+        self.inner
+            .seek(SeekFrom::Start(self.offset.saturating_add(position)))?;
+        self.position = position;
+        Ok(position)
+    }
+}
+
+/// Decodes the structure info that follows the delimiter at the end of a database.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes following the delimiter.
+/// * `file_size` - The size of the database file.
+/// * `byte_order` - The order of the bytes of the segment count.
+///
+/// # Returns
+///
+/// (`Option<(u8, usize, u32)>`): The edition, the record length and the segment count, or `None`
+/// if the bytes are not a valid structure info: the edition is unknown, or the tree of the
+/// segment count would not fit in the file.
+fn structure_info(bytes: &[u8], file_size: u64, byte_order: ByteOrder) -> Option<(u8, usize, u32)> {
+    let mut edition = *bytes.first()?;
+    if edition >= 106 {
+        edition -= 105;
+    }
+    if !SUPPORTED_EDITIONS.contains(&edition) {
+        return None;
+    }
+
+    match edition {
+        REGION_EDITION_REV0 => Some((edition, STANDARD_RECORD_LENGTH, STATE_BEGIN_REV0)),
+        REGION_EDITION_REV1 => Some((edition, STANDARD_RECORD_LENGTH, STATE_BEGIN_REV1)),
+        CITY_EDITION_REV0 | CITY_EDITION_REV1 | CITY_EDITION_REV1_V6 | ORG_EDITION
+        | ISP_EDITION | ASNUM_EDITION | ASNUM_EDITION_V6 => {
+            let segments = byte_order.read(bytes.get(1..1 + SEGMENT_RECORD_LENGTH)?);
+            let record_length = if [ORG_EDITION, ISP_EDITION].contains(&edition) {
+                ORG_RECORD_LENGTH
+            } else {
+                STANDARD_RECORD_LENGTH
+            };
+
+            // Each segment is a node of two pointers at the start of the file
+            let tree_size = u64::from(segments) * 2 * record_length as u64;
+            (tree_size <= file_size).then_some((edition, record_length, segments))
+        }
+        _ => Some((edition, STANDARD_RECORD_LENGTH, COUNTRY_BEGIN)),
+    }
+}
+
+/// Parses the build date of a database from its database info, e.g.
+/// `GEO-533LITE 20160405 Build 1 Copyright (c) 2016 MaxMind Inc All Rights Reserved`.
+fn parse_build_date(info: &[u8]) -> Option<(u16, u8, u8)> {
+    let info = String::from_utf8_lossy(info);
+    let (before, _) = info.rsplit_once(" Build ")?;
+    let date = before.rsplit(' ').next()?;
+    if date.len() != 8 || !date.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let year = date.get(..4)?.parse().ok()?;
+    let month = date.get(4..6)?.parse().ok()?;
+    let day = date.get(6..)?.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((year, month, day))
+}
+
+/// Returns a human-readable name of a database edition.
+///
+/// # Arguments
+///
+/// * `edition` - One of the `*_EDITION*` constants.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::constants::ASNUM_EDITION;
+/// use ipcap::geo_ip_reader::edition_name;
+///
+/// assert_eq!(edition_name(ASNUM_EDITION), "AS Number Edition");
+/// assert_eq!(edition_name(0), "Unknown Edition");
+/// ```
+pub fn edition_name(edition: u8) -> &'static str {
+    match edition {
+        COUNTRY_EDITION => "Country Edition",
+        COUNTRY_EDITION_V6 => "Country Edition V6",
+        REGION_EDITION_REV0 => "Region Edition, Rev 0",
+        REGION_EDITION_REV1 => "Region Edition, Rev 1",
+        CITY_EDITION_REV0 => "City Edition, Rev 0",
+        CITY_EDITION_REV1 => "City Edition, Rev 1",
+        CITY_EDITION_REV1_V6 => "City Edition V6, Rev 1",
+        ORG_EDITION => "Organization Edition",
+        ISP_EDITION => "ISP Edition",
+        ASNUM_EDITION => "AS Number Edition",
+        ASNUM_EDITION_V6 => "AS Number Edition V6",
+        _ => "Unknown Edition",
+    }
+}
+
+impl<R> GeoIpReader<R>
+where
+    R: Read + Seek,
+{
+    /// Constructs a new `GeoIpReader` from the database.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<GeoIpReader<File>, GeoIpReaderError>`): A Result containing a `GeoIpReader` on success
+    /// or a `GeoIpReaderError` on failure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    ///
+    /// let result = GeoIpReader::<File>::new("v4");
+    /// match result {
+    ///     Ok(reader) => println!("GeoIpReader created successfully: {:?}", reader),
+    ///     Err(err) => eprintln!("Error creating GeoIpReader: {:?}", err),
+    /// }
+    /// ```
+    pub fn new(type_: &str) -> Result<GeoIpReader<File>, GeoIpReaderError> {
+        GeoIpReader::<File>::from_path(GeoIpReader::<File>::database_path(type_)?)
+    }
+
+    /// Resolves the path of the database file opened by `new`: the `IPCAP_FILE_PATH`
+    /// environment variable if set, otherwise the default location in `~/ipcap`.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_` - Type of the database ("v4" or "v6").
+    ///
+    /// # Returns
+    ///
+    /// (`Result<PathBuf, GeoIpReaderError>`): The path, or `GeoIpReaderError::OpenFileError` if
+    /// the type is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    ///
+    /// let path = GeoIpReader::<File>::database_path("v4").unwrap();
+    /// assert!(path.ends_with("geo_ip_city_v4.dat"));
+    /// ```
+    pub fn database_path(type_: &str) -> Result<PathBuf, GeoIpReaderError> {
+        const ENV_VAR_NAME: &str = "IPCAP_FILE_PATH";
+        if let Ok(val) = env::var(ENV_VAR_NAME) {
+            return Ok(PathBuf::from(val));
+        }
+
+        let file_name = match type_ {
+            "v4" => "geo_ip_city_v4.dat",
+            "v6" => "geo_ip_city_v6.dat",
+            _ => {
+                return Err(GeoIpReaderError::OpenFileError);
+            }
+        };
+        let mut path = home_dir().unwrap_or_default();
+        path.push("ipcap");
+        path.push(file_name);
+        Ok(path)
+    }
+
+    /// Constructs a new `GeoIpReader` from the database file at the given path.
+    ///
+    /// Unlike `new`, this neither consults `IPCAP_FILE_PATH` nor the default location, which
+    /// makes it suitable for opening additional databases such as the ASN or organization editions.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the database file.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<GeoIpReader<File>, GeoIpReaderError>`): A Result containing a `GeoIpReader` on success
+    /// or a `GeoIpReaderError` on failure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let reader = GeoIpReader::<File>::from_path(path);
+    /// assert!(reader.is_ok());
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<GeoIpReader<File>, GeoIpReaderError> {
+        GeoIpReaderBuilder::new().open(path)
+    }
+
+    /// Constructs a new `GeoIpReader` from any reader holding the database content.
+    ///
+    /// # Arguments
+    ///
+    /// * `fp` - The reader over the database bytes, e.g. a `File` or an in-memory `Cursor`.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<GeoIpReader<R>, GeoIpReaderError>`): A Result containing a `GeoIpReader` on success
+    /// or a `GeoIpReaderError` on failure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let bytes = std::fs::read(path).unwrap();
+    /// let reader = GeoIpReader::from_reader(Cursor::new(bytes));
+    /// assert!(reader.is_ok());
+    /// ```
+    pub fn from_reader(fp: R) -> Result<GeoIpReader<R>, GeoIpReaderError> {
+        GeoIpReaderBuilder::new().build(fp)
+    }
+
+    /// Constructs a new `GeoIpReader` from a database embedded in a larger file, e.g. appended
+    /// to an executable, without extracting it.
+    ///
+    /// # Arguments
+    ///
+    /// * `fp` - The reader over the whole file.
+    /// * `offset` - The offset of the database in the file.
+    /// * `len` - The length of the database.
+    /// * `family` - The address family of the database, `"v4"` or `"v6"` as in `new`.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<GeoIpReader<DatabaseSlice<R>>, GeoIpReaderError>`): A `GeoIpReader` over the
+    /// `[offset, offset + len)` range of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the range exceeds the end of the file, or
+    /// `GeoIpReaderError::InvalidDatabaseType` if `family` is not `"v4"` or `"v6"` or the
+    /// edition of the database is of the other family.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    ///
+    /// let mut path = dirs::home_dir().unwrap();
+    /// path.push("ipcap/geo_ip_city_v4.dat");
+    ///
+    /// let database = std::fs::read(path).unwrap();
+    /// let mut bytes = b"#!/bin/sh\nexit 0\n".to_vec();
+    /// let offset = bytes.len() as u64;
+    /// bytes.extend(&database);
+    ///
+    /// let mut reader =
+    ///     GeoIpReader::from_reader_at(Cursor::new(bytes), offset, database.len() as u64, "v4")
+    ///         .unwrap();
+    /// assert_eq!(reader.get_record("108.95.4.105").unwrap().city(), Some("San Diego"));
+    /// ```
+    pub fn from_reader_at(
+        fp: R,
+        offset: u64,
+        len: u64,
+        family: &str,
+    ) -> Result<GeoIpReader<DatabaseSlice<R>>, GeoIpReaderError> {
+        let ipv6 = match family {
+            "v4" => false,
+            "v6" => true,
+            _ => return Err(GeoIpReaderError::InvalidDatabaseType),
+        };
+
+        let reader = GeoIpReaderBuilder::new().build(DatabaseSlice::new(fp, offset, len)?)?;
+        let v6_editions = [COUNTRY_EDITION_V6, ASNUM_EDITION_V6, CITY_EDITION_REV1_V6];
+        if v6_editions.contains(&reader.database_type) != ipv6 {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+        Ok(reader)
+    }
+
+    /// Detects the type of the GeoIP database and sets up segment sizes and start points accordingly.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<(), GeoIpReaderError>`): A result indicating success or a `GeoIpReaderError` on failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::Io` if the file cannot be sought or read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use ipcap::errors::GeoIpReaderError;
+    /// use std::fs::File;
+    ///
+    /// fn main() -> Result<(), GeoIpReaderError> {
+    ///     let mut reader = GeoIpReader::<File>::new("v4")?;
+    ///     reader.detect_database_type()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn detect_database_type(&mut self) -> Result<(), GeoIpReaderError> {
+        // Save current file position, restored whether the end of the file can be read or not,
+        // so that a failed detection leaves the reader as it was
+        let file_position = self.fp.stream_position()?;
+        let end = self.read_structure_area();
+        self.fp.seek(SeekFrom::Start(file_position))?;
+        let (end, file_size) = end?;
+
+        // Default values, kept when no structure info is found
+        self.database_type = COUNTRY_EDITION;
+        self.record_length = STANDARD_RECORD_LENGTH;
+        self.database_segments = COUNTRY_BEGIN;
+
+        // Search backwards for the delimiter followed by a valid structure info, skipping the
+        // 0xFF runs that the strings of the last record may hold
+        for start in (0..end.len().saturating_sub(2)).rev() {
+            if !end
+                .get(start..)
+                .unwrap_or_default()
+                .starts_with(&[255, 255, 255])
+            {
+                continue;
+            }
+            if let Some((database_type, record_length, segments)) = structure_info(
+                end.get(start + 3..).unwrap_or_default(),
+                file_size,
+                self.byte_order,
+            ) {
+                self.database_type = database_type;
+                self.record_length = record_length;
+                self.database_segments = segments;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the end of the file, where the structure info is, returning it with the size of
+    /// the file. The position of the file is left at its end.
+    fn read_structure_area(&mut self) -> io::Result<(Vec<u8>, u64)> {
+        let file_size = self.fp.seek(SeekFrom::End(0))?;
+        let mut end = vec![0u8; file_size.min(u64::from(STRUCTURE_INFO_MAX_SIZE) + 2) as usize];
+        self.fp.seek(SeekFrom::End(-(end.len() as i64)))?;
+        self.fp.read_exact(&mut end)?;
+        Ok((end, file_size))
+    }
+
+    /// Constructs a walker over the tree of the database.
+    fn walker(&mut self) -> TreeWalker<'_, R> {
+        TreeWalker::new(&mut self.fp, self.record_length, self.database_segments)
+            .byte_order(self.byte_order)
+    }
+
+    /// Using the record length and appropriate start points, seek to the
+    /// country that corresponds to the converted IP address integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip_number` - Result of ip_to_long conversion.
+    ///
+    /// # Returns
+    ///
+    /// The offset of the start of the record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the database is corrupted, or
+    /// `GeoIpReaderError::Io` if it cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// match geo_ip.get_country(16777216) {
+    ///     Ok(offset) => println!("Country offset: {}", offset),
+    ///     Err(err) => eprintln!("Error: {}", err),
+    /// }
+    /// ```
+    pub fn get_country(&mut self, ip_number: u128) -> Result<usize, GeoIpReaderError> {
+        let mut walker = TreeWalker::new(&mut self.fp, self.record_length, self.database_segments)
+            .byte_order(self.byte_order);
+        // Reading the top of the tree once only pays off when the nodes are not read from disk
+        if self.index_octets && self.octet_index.is_none() && seek_depth(ip_number) == 31 {
+            self.octet_index = Some(Arc::new(walker.octet_index()));
+        }
+        let leaf = match &self.octet_index {
+            Some(octet_index) => walker.walk_indexed(ip_number, octet_index)?,
+            None => walker.walk(ip_number)?,
+        };
+
+        self.netmask = leaf.netmask;
+        Ok(leaf.pointer as usize)
+    }
+
+    /// Get the geographical record for an IP address.
+    ///
+    /// This function retrieves information such as country code, region code,
+    /// city, latitude, longitude, time zone, and more based on the provided IP address.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address, v4 or v6.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Record, GeoIpReaderError>`): The record of the address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::AddressNotFound` if the address is not in the database,
+    /// `GeoIpReaderError::InvalidAddress` if it is not a valid IP address, or
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    ///
+    /// # Examples
+    /// ```
+    /// use ipcap::errors::GeoIpReaderError;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// let record = geo_ip.get_record("185.90.90.120").unwrap();
+    /// println!("Geographical Record: {:?}", record);
+    ///
+    /// assert!(matches!(
+    ///     geo_ip.get_record("10.0.0.1"),
+    ///     Err(GeoIpReaderError::AddressNotFound(ip)) if ip == "10.0.0.1"
+    /// ));
+    /// ```
+    pub fn get_record(&mut self, ip: &str) -> Result<Record<'static>, GeoIpReaderError> {
+        // The leaf equal to the segment count stands for addresses that are not in the
+        // database, there is no record to read then
+        self.find_record(ip)?
+            .ok_or_else(|| GeoIpReaderError::AddressNotFound(ip.to_string()))
+    }
+
+    /// Gets the geographical record for an IP address like `get_record`, along with the time
+    /// spent in each phase of the lookup, e.g. to profile a workload.
+    ///
+    /// The clock is only read for this lookup: a reader built without
+    /// `GeoIpReaderBuilder::timings` keeps its other lookups untimed, and `last_timings` only
+    /// changes if the timings were enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `get_record`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// let (record, timings) = geo_ip.get_record_timed("108.95.4.105").unwrap();
+    /// assert_eq!(record.city(), Some("San Diego"));
+    /// assert!(timings.total() >= timings.tree_walk);
+    /// assert_eq!(geo_ip.last_timings(), None);
+    /// ```
+    pub fn get_record_timed(
+        &mut self,
+        ip: &str,
+    ) -> Result<(Record<'static>, LookupTimings), GeoIpReaderError> {
+        let enabled = self.timings;
+        self.timings = Some(enabled.unwrap_or_default());
+        let record = self.get_record(ip);
+        let timings = self.timings.unwrap_or_default();
+        if enabled.is_none() {
+            self.timings = None;
+        }
+
+        Ok((record?, timings))
+    }
+
+    /// Get the geographical record for the IP address of a socket address, e.g. the peer of an
+    /// incoming connection, the port being ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The socket address, v4 or v6.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Record, GeoIpReaderError>`): The record of the IP address.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `get_record`.
+    ///
+    /// # Examples
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    /// use std::net::SocketAddr;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let peer: SocketAddr = "185.90.90.120:443".parse().unwrap();
+    ///
+    /// let record = geo_ip.get_record_for_socket(peer).unwrap();
+    /// assert_eq!(record.country, Country::SaudiArabia);
+    /// ```
+    pub fn get_record_for_socket(
+        &mut self,
+        addr: SocketAddr,
+    ) -> Result<Record<'static>, GeoIpReaderError> {
+        self.get_record(&addr.ip().to_string())
+    }
+
+    /// Looks up the geographical record for an IP address, tolerating addresses that are not
+    /// in the database.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<Record>, GeoIpReaderError>`): The record, or `None` if the address is not
+    /// in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `addr` is not a valid IP address, or
+    /// `GeoIpReaderError::MissingCoordinates` if the coordinates of the city record are not real
+    /// and the reader was built with `GeoIpReaderBuilder::strict_coordinates`.
+    ///
+    /// With the `tracing` feature, each lookup runs in an info-level `find_record` span
+    /// recording the address as `ip`, and the `country` code and `netmask` of the record found.
+    pub fn find_record(&mut self, addr: &str) -> Result<Option<Record<'static>>, GeoIpReaderError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "find_record",
+            ip = addr,
+            country = tracing::field::Empty,
+            netmask = tracing::field::Empty,
+        )
+        .entered();
+
+        let mut stopwatch = Stopwatch::start(self.timings.is_some());
+        let ip_number = self.parse_lookup_number(addr)?;
+        let parse = stopwatch.lap();
+
+        let (tree_walk, record) = if self.is_region_edition() {
+            let seek_country = self.get_country(ip_number)?;
+            let tree_walk = stopwatch.lap();
+            let record = self.region_record(seek_country)?;
+            (
+                tree_walk,
+                record.map(|record| self.with_policy_coordinates(record)),
+            )
+        } else {
+            let position = self.record_position(ip_number)?;
+            let tree_walk = stopwatch.lap();
+            match position {
+                Some(position) => {
+                    let record = self.read_record(position)?;
+                    if self.strict_coordinates && !record.has_real_coordinates() {
+                        return Err(GeoIpReaderError::MissingCoordinates(addr.to_string()));
+                    }
+                    (tree_walk, Some(self.with_policy_coordinates(record)))
+                }
+                None => (tree_walk, None),
+            }
+        };
+
+        if self.timings.is_some() {
+            self.timings = Some(LookupTimings {
+                parse,
+                tree_walk,
+                record_parse: stopwatch.lap(),
+            });
+        }
+
+        #[cfg(feature = "tracing")]
+        if let Some(record) = &record {
+            span.record("country", record.country.alphabetic_code_2());
+            span.record("netmask", self.netmask);
+        }
+
+        let max_netmask = seek_depth(ip_number) as usize + 1;
+        Ok(record.map(|record| {
+            record
+                .with_netmask(self.netmask, max_netmask)
+                .with_provenance(self.provenance())
+        }))
+    }
+
+    /// Returns the timings of the last successful `find_record` or `get_record`.
+    ///
+    /// # Returns
+    ///
+    /// (`Option<LookupTimings>`): The timings, `None` unless the reader was built with
+    /// `GeoIpReaderBuilder::timings`. They are zero before the first lookup.
+    pub fn last_timings(&self) -> Option<LookupTimings> {
+        self.timings
+    }
+
+    /// Looks up only the coordinates of an IP address, skipping over the strings of its record
+    /// without decoding them and without resolving its time zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<LatLng>, GeoIpReaderError>`): The coordinates, resolved by the coordinate
+    /// policy for a record without real ones, or `None` if the policy gives none, if the address
+    /// is not in the database or if the database is not a City edition, whose records are the
+    /// only ones with coordinates.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address,
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted, or
+    /// `GeoIpReaderError::MissingCoordinates` if the coordinates are not real and the reader was
+    /// built with `GeoIpReaderBuilder::strict_coordinates`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// let coordinates = geo_ip.get_coordinates("108.95.4.105").unwrap().unwrap();
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    /// assert_eq!(coordinates.latitude, record.latitude);
+    /// assert_eq!(coordinates.longitude, record.longitude);
+    /// ```
+    pub fn get_coordinates(&mut self, ip: &str) -> Result<Option<LatLng>, GeoIpReaderError> {
+        let ip_number = self.parse_lookup_number(ip)?;
+        if ![CITY_EDITION_REV0, CITY_EDITION_REV1, CITY_EDITION_REV1_V6]
+            .contains(&self.database_type)
+        {
+            return Ok(None);
+        }
+        let Some(position) = self.record_position(ip_number)? else {
+            return Ok(None);
+        };
+
+        let mut buffer = [0; FULL_RECORD_LENGTH];
+        self.read_at(position, &mut buffer)?;
+
+        // Skip the country byte, then the region code, city and postal code strings, each
+        // terminated by a null byte
+        let mut tail = buffer.get(1..).unwrap_or_default();
+        for _ in 0..3 {
+            let end = tail
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or(GeoIpReaderError::CorruptDatabase)?;
+            tail = tail.get(end.saturating_add(1)..).unwrap_or_default();
+        }
+
+        let coordinates = LatLng::decode(tail, self.byte_order);
+        if self.strict_coordinates && !coordinates.is_real() {
+            return Err(GeoIpReaderError::MissingCoordinates(ip.to_string()));
+        }
+        let country = buffer
+            .first()
+            .and_then(|byte| Country::from_buffer(*byte))
+            .ok_or(GeoIpReaderError::CorruptDatabase)?;
+        Ok(self.coordinate_policy.resolve(country, coordinates))
+    }
+
+    /// Looks up the country, region and city of an IP address, as owned strings.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `get_record`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::{GeoIpReader, Place};
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// assert_eq!(
+    ///     geo_ip.get_place("108.95.4.105").unwrap(),
+    ///     Place {
+    ///         country: "United States".to_string(),
+    ///         region: Some("CA".to_string()),
+    ///         city: Some("San Diego".to_string()),
+    ///     }
+    /// );
+    /// ```
+    pub fn get_place(&mut self, ip: &str) -> Result<Place, GeoIpReaderError> {
+        Ok(Place::from(&self.get_record(ip)?))
+    }
+
+    /// Reads the bytes at the given absolute position into `buffer`, the bytes past the end of
+    /// the file reading as zeros.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::Io` if the file cannot be read.
+    fn read_at(&mut self, position: u64, buffer: &mut [u8]) -> Result<(), GeoIpReaderError> {
+        buffer.fill(0);
+        self.fp.seek(SeekFrom::Start(position))?;
+
+        // Read in place until the buffer is full or the file ends, without allocating
+        let mut filled = 0;
+        while let Some(rest) = buffer.get_mut(filled..).filter(|rest| !rest.is_empty()) {
+            match self.fp.read(rest) {
+                Ok(0) => break,
+                Ok(read) => filled = filled.saturating_add(read),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(read_error(err)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the city record located at the given absolute position.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the country byte is out of range.
+    fn read_record(&mut self, position: u64) -> Result<Record<'static>, GeoIpReaderError> {
+        let fields = self.read_city_fields(position, |data| Box::from(data))?;
+        Ok(Record {
+            dma: fields.dma,
+            postal_code: fields.postal_code,
+            country: fields.country,
+            region_code: fields.region_code,
+            city: fields.city,
+            latitude: fields.latitude,
+            longitude: fields.longitude,
+            time_zone: fields.time_zone,
+            netmask: 0,
+            max_netmask: 0,
+            warnings: fields.warnings,
+            gaps: fields.gaps,
+            provenance: None,
+            country_byte: fields.country_byte,
+        })
+    }
+
+    /// Gives a record without real coordinates those of the coordinate policy, if any.
+    fn with_policy_coordinates<'a>(&self, mut record: Record<'a>) -> Record<'a> {
+        let coordinates = LatLng {
+            latitude: record.latitude,
+            longitude: record.longitude,
+        };
+        if let Some(resolved) = self.coordinate_policy.resolve(record.country, coordinates) {
+            record.latitude = resolved.latitude;
+            record.longitude = resolved.longitude;
+            record
+                .gaps
+                .retain(|gap| !["latitude", "longitude"].contains(&gap.field));
+        }
+        record
+    }
+
+    /// Decodes the fields of the city record located at the given absolute position, its
+    /// strings being converted by `convert` straight from the record bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the country byte is out of range.
+    #[deny(clippy::arithmetic_side_effects)]
+    fn read_city_fields<S>(
+        &mut self,
+        position: u64,
+        mut convert: impl FnMut(&str) -> S,
+    ) -> Result<CityFields<S>, GeoIpReaderError> {
+        // Read the record data into a buffer on the stack
+        let mut buffer = [0; FULL_RECORD_LENGTH];
+        self.read_at(position, &mut buffer)?;
+
+        let country_byte = buffer.first().copied().unwrap_or_default();
+        let country =
+            Country::from_buffer(country_byte).ok_or(GeoIpReaderError::CorruptDatabase)?;
+
+        // Each offset points at a null byte within the buffer, or at its end for a truncated record
+        let mut warnings = Vec::new();
+        let mut read_field = |field, pos| {
+            let (offset, data, count) = read_str_checked(&buffer, pos);
+            if count > 0 {
+                warnings.push(RecordWarning::InvalidBytes { field, count });
+            }
+            (offset, data)
+        };
+        let (offset, region_code) = read_field("region_code", 1);
+        let (offset, city) = read_field("city", offset.saturating_add(1));
+        let (offset, postal_code) = read_field("postal_code", offset.saturating_add(1));
+        let tail = buffer.get(offset.saturating_add(1)..).unwrap_or_default();
+
+        let LatLng {
+            latitude,
+            longitude,
+        } = LatLng::decode(tail, self.byte_order);
+
+        let has_dma = match self.dma_policy {
+            DmaPolicy::UnitedStatesOnly => country == Country::UnitedStates,
+            DmaPolicy::AllCountries => true,
+            DmaPolicy::Never => false,
+        };
+
+        // Only the REV1 editions store the DMA, right after the coordinates
+        let dma = if (self.database_type == CITY_EDITION_REV1
+            || self.database_type == CITY_EDITION_REV1_V6)
+            && has_dma
+        {
+            tail.get(6..9)
+                .map(|bytes| DesignatedMarketArea(self.byte_order.read(bytes)))
+        } else {
+            None
+        };
+
+        let time_zone = self
+            .resolve_time_zone(country, region_code.as_deref())
+            .unwrap_or_default();
+
+        // Why the empty fields are empty, the vector only allocating for a record missing some
+        let mut gaps = Vec::new();
+        for (field, value) in [
+            ("region_code", &region_code),
+            ("city", &city),
+            ("postal_code", &postal_code),
+        ] {
+            if value.is_none() {
+                gaps.push(FieldGap {
+                    field,
+                    reason: GapReason::NotInDatabase,
+                });
+            }
+        }
+        if !(LatLng {
+            latitude,
+            longitude,
+        })
+        .is_real()
+        {
+            let reason = if country.status() == CountryStatus::Reserved {
+                GapReason::PseudoCountry
+            } else {
+                GapReason::NotInDatabase
+            };
+            for field in ["latitude", "longitude"] {
+                gaps.push(FieldGap {
+                    field,
+                    reason: reason.clone(),
+                });
+            }
+        }
+        if let Some(dma) = dma.filter(|dma| !dma.is_empty() && !dma.is_known()) {
+            gaps.push(FieldGap {
+                field: "dma_code",
+                reason: GapReason::UnknownDmaCode(dma.dma_code()),
+            });
+        }
+        if time_zone.is_empty() {
+            gaps.push(FieldGap {
+                field: "time_zone",
+                reason: GapReason::time_zone(country, region_code.as_deref()),
+            });
+        }
+
+        let mut convert = |data: Option<Cow<str>>| data.map(|data| convert(&data));
+        Ok(CityFields {
+            dma,
+            postal_code: convert(postal_code),
+            country,
+            country_byte,
+            region_code: convert(region_code),
+            city: convert(city),
+            latitude,
+            longitude,
+            time_zone,
+            warnings,
+            gaps,
+        })
+    }
+
+    /// Returns whether the database is a Region edition, whose leaves encode the country and
+    /// region instead of pointing at a record.
+    fn is_region_edition(&self) -> bool {
+        self.database_type == REGION_EDITION_REV0 || self.database_type == REGION_EDITION_REV1
+    }
+
+    /// Whether the database is one of the IPv6 editions.
+    pub fn is_v6_edition(&self) -> bool {
+        [COUNTRY_EDITION_V6, ASNUM_EDITION_V6, CITY_EDITION_REV1_V6].contains(&self.database_type)
+    }
+
+    /// Converts an address to the number the lookups walk the tree with, widening an IPv4
+    /// address looked up in an IPv6 database to its IPv4-mapped form, see
+    /// `GeoIpReaderBuilder::map_v4_into_v6`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::AddressFamilyMismatch` for an IPv4 address looked up in an
+    /// IPv6 database, unless the reader widens them.
+    fn lookup_number(&self, addr: IpAddr) -> Result<u128, GeoIpReaderError> {
+        let addr = match addr {
+            IpAddr::V4(ipv4_addr) if self.is_v6_edition() => {
+                if !self.map_v4_into_v6 {
+                    return Err(GeoIpReaderError::AddressFamilyMismatch(addr.to_string()));
+                }
+                // Widened to the same number as its `::ffff:a.b.c.d` literal
+                IpAddr::V6(ipv4_addr.to_ipv6_mapped())
+            }
+            addr => addr,
+        };
+        Ok(addr_to_number(addr))
+    }
+
+    /// Parses an address and converts it like `lookup_number`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address, or
+    /// `GeoIpReaderError::AddressFamilyMismatch` as `lookup_number` does.
+    fn parse_lookup_number(&self, ip: &str) -> Result<u128, GeoIpReaderError> {
+        self.lookup_number(parse_addr(ip)?)
+    }
+
+    /// Decodes the country and region encoded by the leaf offset of a Region database.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<Record>, GeoIpReaderError>`): The record, holding no city nor
+    /// coordinates, or `None` if the address is not in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the offset is outside of the edition's
+    /// ranges.
+    fn region_record(
+        &self,
+        seek_country: usize,
+    ) -> Result<Option<Record<'static>>, GeoIpReaderError> {
+        // United States and Canada regions are two letters, numbered from AA
+        let us_or_canada = |country: Country, offset: usize| {
+            let region_code: String = [offset / 26, offset % 26]
+                .iter()
+                .map(|letter| char::from(b'A' + *letter as u8))
+                .collect();
+            Record::new(country).with_region_code(&region_code)
+        };
+        // The other countries are numbered from 1 as in `Country::from_buffer`, 0 marking
+        // addresses that are not in the database
+        let country = |index: usize| u8::try_from(index).ok().and_then(Country::from_buffer);
+
+        let state_begin = if self.database_type == REGION_EDITION_REV0 {
+            STATE_BEGIN_REV0
+        } else {
+            STATE_BEGIN_REV1
+        };
+        let seek_region = seek_country
+            .checked_sub(state_begin as usize)
+            .ok_or(GeoIpReaderError::CorruptDatabase)?;
+
+        let record = if self.database_type == REGION_EDITION_REV0 {
+            match seek_region.checked_sub(1000) {
+                Some(offset) if offset < 26 * 26 => {
+                    Some(us_or_canada(Country::UnitedStates, offset))
+                }
+                Some(_) => return Err(GeoIpReaderError::CorruptDatabase),
+                None => country(seek_region).map(Record::new),
+            }
+        } else if seek_region < US_OFFSET as usize {
+            None
+        } else if seek_region < CANADA_OFFSET as usize {
+            Some(us_or_canada(
+                Country::UnitedStates,
+                seek_region - US_OFFSET as usize,
+            ))
+        } else if seek_region < WORLD_OFFSET as usize {
+            Some(us_or_canada(
+                Country::Canada,
+                seek_region - CANADA_OFFSET as usize,
+            ))
+        } else {
+            country((seek_region - WORLD_OFFSET as usize) / FIPS_RANGE as usize).map(Record::new)
+        };
+
+        Ok(record.map(|mut record| {
+            if let Some(time_zone) = self.time_zone_overrides.as_ref().and_then(|overrides| {
+                overrides.time_zone(record.country.alphabetic_code_2(), record.region_code())
+            }) {
+                record.time_zone = time_zone;
+            }
+            record.with_region_gaps()
+        }))
+    }
+
+    /// Resolves the time zone of a record from the time zone overrides of the reader, if any,
+    /// then from the built-in tables.
+    fn resolve_time_zone(
+        &self,
+        country: Country,
+        region_code: Option<&str>,
+    ) -> Option<&'static str> {
+        let country_code = country.alphabetic_code_2();
+        match &self.time_zone_overrides {
+            Some(overrides) => overrides.resolve(country_code, region_code),
+            None => resolve_time_zone(country_code, region_code),
+        }
+    }
+
+    /// Look up the time zone for a given IP address.
+    /// Use this method if you have a Region or City database.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - IP address
+    ///
+    /// # Returns
+    ///
+    /// Time zone as a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `get_record`.
+    pub fn get_time_zone_given_ip_addr(&mut self, addr: &str) -> Result<&str, GeoIpReaderError> {
+        Ok(self.get_record(addr)?.time_zone)
+    }
+
+    /// Returns the edition of the database, one of the `*_EDITION*` constants.
+    pub fn database_type(&self) -> u8 {
+        self.database_type
+    }
+
+    /// Returns the coordinates given to the records without real ones, see
+    /// `GeoIpReaderBuilder::coordinate_policy`.
+    pub fn coordinate_policy(&self) -> CoordinatePolicy {
+        self.coordinate_policy
+    }
+
+    /// Sets the coordinates given to the records of the next lookups without real ones, see
+    /// `GeoIpReaderBuilder::coordinate_policy`.
+    pub fn set_coordinate_policy(&mut self, coordinate_policy: CoordinatePolicy) {
+        self.coordinate_policy = coordinate_policy;
+    }
+
+    /// Registers a post-processor of the fields of `record_fields`, after the ones registered
+    /// before, see `post_process`.
+    pub fn add_post_processor<F>(&mut self, processor: F)
+    where
+        F: Fn(&mut RecordFields<'_>) + Send + Sync + 'static,
+    {
+        self.post_processors.add(processor);
+    }
+
+    /// Collects the displayed fields of a record, as `utils::record_fields_in`, then runs the
+    /// post-processors registered on the reader on them, in registration order.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record, e.g. returned by a lookup of this reader.
+    /// * `ip` - The address the record was looked up for, if known.
+    /// * `unix_ts` - The instant of the time zone abbreviation and UTC offset.
+    /// * `locale` - The language of the names.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use ipcap::locale::Locale;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// geo_ip.add_post_processor(|fields| {
+    ///     fields.remove("postal_code");
+    /// });
+    /// let record = geo_ip.get_record("108.95.4.105").unwrap();
+    ///
+    /// let fields = geo_ip.record_fields(&record, None, 0, Locale::En);
+    /// assert!(!fields.contains("postal_code"));
+    /// assert_eq!(record.postal_code(), Some("92109"));
+    /// ```
+    pub fn record_fields<'r>(
+        &self,
+        record: &'r Record<'r>,
+        ip: Option<IpAddr>,
+        unix_ts: i64,
+        locale: Locale,
+    ) -> RecordFields<'r> {
+        let mut fields = RecordFields::new(record, ip, unix_ts, locale);
+        let coordinates = LatLng {
+            latitude: record.latitude,
+            longitude: record.longitude,
+        };
+        if !coordinates.is_real() {
+            let resolved = self.coordinate_policy.resolve(record.country, coordinates);
+            fields.insert("latitude", resolved.map(|c| c.latitude.to_string()));
+            fields.insert("longitude", resolved.map(|c| c.longitude.to_string()));
+        }
+        self.post_processors.apply(&mut fields);
+        fields
+    }
+
+    /// Returns a human-readable name of the edition of the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// assert_eq!(geo_ip.edition_name(), "City Edition, Rev 1");
+    /// ```
+    pub fn edition_name(&self) -> &'static str {
+        edition_name(self.database_type)
+    }
+
+    /// Returns the source of the records of this reader, as set in their `Record::provenance`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::{GeoIpReader, SourceKind};
+    /// use std::fs::File;
+    /// use std::io::Cursor;
+    ///
+    /// let bytes = std::fs::read(GeoIpReader::<File>::database_path("v4").unwrap()).unwrap();
+    /// let geo_ip = GeoIpReader::from_reader(Cursor::new(bytes)).unwrap();
+    ///
+    /// assert_eq!(geo_ip.provenance().source, SourceKind::Memory);
+    /// ```
+    pub fn provenance(&self) -> Provenance {
+        Provenance {
+            source: self.source.clone(),
+            edition: Some(self.database_type),
+            db_build_date: self.build_date,
+        }
+    }
+
+    /// Returns the prefix length of the network matched by the last lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// geo_ip.find_record("108.95.4.105").unwrap();
+    ///
+    /// assert!(geo_ip.netmask() > 0 && geo_ip.netmask() <= 32);
+    /// ```
+    pub fn netmask(&self) -> usize {
+        self.netmask
+    }
+
+    /// Reads the whole database once so that its pages are in the OS page cache, as
+    /// `warm_up` does without a budget.
+    ///
+    /// # Returns
+    ///
+    /// (`io::Result<()>`): An error if the database could not be read. The stream position is
+    /// restored either way.
+    #[deprecated(
+        since = "0.1.7",
+        note = "use `GeoIpReader::warm_up`, which returns a `WarmUpReport`"
+    )]
+    pub fn warm(&mut self) -> io::Result<()> {
+        match self.warm_up(Duration::MAX) {
+            Ok(_) => Ok(()),
+            Err(GeoIpReaderError::Io(err)) => Err(err),
+            Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+
+    /// Reads the database from its start in large chunks, so that its pages are in the OS page
+    /// cache, until the whole database is read or `budget` is exhausted. This is meant to be
+    /// called at startup, sparing the first lookups the latency of cold disk reads while
+    /// delaying the startup by at most about `budget`, at the cost of leaving the end of the
+    /// database cold.
+    ///
+    /// Unlike loading the database in memory, the heap usage does not grow with the size of the
+    /// database, but the OS may evict the cached pages again under memory pressure.
+    ///
+    /// A reader built by `GeoIpReaderBuilder::build_in_memory` or restored from a snapshot holds
+    /// the database in memory already, and nothing is read. Any other reader is read, e.g.
+    /// `from_reader` over a `File`. Lookups are unaffected either way, and the stream position
+    /// is restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - The time after which no more chunk is read.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<WarmUpReport, GeoIpReaderError>`): The number of bytes read out of the size of
+    /// the database, and the time it took.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::Io` if the database could not be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let report = geo_ip.warm_up(Duration::from_secs(10)).unwrap();
+    ///
+    /// assert!(report.is_complete());
+    /// assert_eq!(report.bytes_touched, report.total_bytes);
+    /// ```
+    pub fn warm_up(&mut self, budget: Duration) -> Result<WarmUpReport, GeoIpReaderError> {
+        self.warm_up_range(u64::MAX, budget)
+    }
+
+    /// Reads the tree of the database like `warm_up`, the records being left cold. The tree is
+    /// read by every lookup, while each record is read only by the lookups of its networks, so
+    /// this is the cheaper part of a warm-up.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::Io` if the database could not be read.
+    pub fn warm_up_index_only(
+        &mut self,
+        budget: Duration,
+    ) -> Result<WarmUpReport, GeoIpReaderError> {
+        let tree_size = 2 * self.record_length as u64 * u64::from(self.database_segments);
+        self.warm_up_range(tree_size, budget)
+    }
+
+    /// Reads at most the first `len` bytes of the database within `budget`, restoring the
+    /// position of the file.
+    fn warm_up_range(
+        &mut self,
+        len: u64,
+        budget: Duration,
+    ) -> Result<WarmUpReport, GeoIpReaderError> {
+        const CHUNK_SIZE: usize = 1 << 20;
+
+        let start = Instant::now();
+        let file_position = self.fp.stream_position()?;
+        let total_bytes = self.fp.seek(SeekFrom::End(0))?.min(len);
+
+        let mut report = WarmUpReport {
+            bytes_touched: 0,
+            total_bytes,
+            elapsed: Duration::ZERO,
+            already_resident: self.in_memory,
+        };
+        let mut read = Ok(());
+        if !report.already_resident {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            read = self.fp.seek(SeekFrom::Start(0)).map(|_| ());
+            while read.is_ok() && report.bytes_touched < total_bytes && start.elapsed() < budget {
+                let remaining = total_bytes - report.bytes_touched;
+                let chunk = match chunk.get_mut(..remaining.min(CHUNK_SIZE as u64) as usize) {
+                    Some(chunk) => chunk,
+                    None => break,
+                };
+                read = match self.fp.read(chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        report.bytes_touched += n as u64;
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                };
+            }
+        }
+        self.fp.seek(SeekFrom::Start(file_position))?;
+        read.map_err(read_error)?;
+
+        report.elapsed = start.elapsed();
+        Ok(report)
+    }
+
+    /// Returns the detected layout of the database, with the index of its first octets, built
+    /// first if the reader indexes them, to build other readers of the same bytes.
+    pub(crate) fn detected_layout(&mut self) -> DetectedLayout {
+        if self.index_octets && self.octet_index.is_none() {
+            let mut walker =
+                TreeWalker::new(&mut self.fp, self.record_length, self.database_segments)
+                    .byte_order(self.byte_order);
+            self.octet_index = Some(Arc::new(walker.octet_index()));
+        }
+        DetectedLayout {
+            database_type: self.database_type,
+            record_length: self.record_length,
+            database_segments: self.database_segments,
+            build_date: self.build_date,
+            octet_index: self.octet_index.clone(),
+        }
+    }
+
+    /// Reads the build date from the database info near the end of the file, restoring the
+    /// position of the file.
+    fn read_build_date(&mut self) -> Option<(u16, u8, u8)> {
+        // Covers the structure info and the database info preceding it
+        const INFO_LENGTH: u64 = 128;
+
+        let file_position = self.fp.stream_position().ok()?;
+        let mut tail = Vec::with_capacity(INFO_LENGTH as usize);
+        let read = self
+            .fp
+            .seek(SeekFrom::End(0))
+            .and_then(|size| {
+                self.fp
+                    .seek(SeekFrom::Start(size.saturating_sub(INFO_LENGTH)))
+            })
+            .and_then(|_| (&mut self.fp).take(INFO_LENGTH).read_to_end(&mut tail));
+        self.fp.seek(SeekFrom::Start(file_position)).ok()?;
+        read.ok()?;
+
+        parse_build_date(&tail)
+    }
+
+    /// Computes a fingerprint of the database, to detect whether it changed, e.g. across restarts.
+    ///
+    /// This is a fast approximate fingerprint, not a cryptographic hash: only the size of the
+    /// database and its last bytes, holding the structure info and build information, are hashed
+    /// (with 64-bit FNV-1a), so the value is stable across runs and platforms.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<u64, GeoIpReaderError>`): The fingerprint of the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let mut same_geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// assert_eq!(geo_ip.fingerprint().unwrap(), same_geo_ip.fingerprint().unwrap());
+    /// ```
+    pub fn fingerprint(&mut self) -> Result<u64, GeoIpReaderError> {
+        // Covers the structure info and the build information preceding it
+        const FINGERPRINT_LENGTH: u64 = 128;
+
+        let file_position = self.fp.stream_position()?;
+        let size = self.fp.seek(SeekFrom::End(0))?;
+
+        let mut tail = Vec::with_capacity(FINGERPRINT_LENGTH as usize);
+        let read = self
+            .fp
+            .seek(SeekFrom::Start(size.saturating_sub(FINGERPRINT_LENGTH)))
+            .and_then(|_| {
+                (&mut self.fp)
+                    .take(FINGERPRINT_LENGTH)
+                    .read_to_end(&mut tail)
+            });
+        self.fp.seek(SeekFrom::Start(file_position))?;
+        read.map_err(read_error)?;
+
+        let mut hash = FNV_OFFSET_BASIS;
+        fnv1a(&mut hash, &size.to_le_bytes());
+        fnv1a(&mut hash, &tail);
+
+        Ok(hash)
+    }
+
+    /// Writes a snapshot of the database and of its detected layout, to be restored with
+    /// `GeoIpReader::load_snapshot`, see `snapshot`.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where the snapshot is written, e.g. a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SnapshotError::Database` if the database cannot be read, or `SnapshotError::Io`
+    /// if the snapshot cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let mut snapshot = Vec::new();
+    /// geo_ip.save_snapshot(&mut snapshot).unwrap();
+    ///
+    /// let mut restored = GeoIpReader::load_snapshot(&snapshot[..]).unwrap();
+    /// assert_eq!(restored.edition_name(), geo_ip.edition_name());
+    /// assert_eq!(
+    ///     restored.get_record("108.95.4.105").unwrap(),
+    ///     geo_ip.get_record("108.95.4.105").unwrap()
+    /// );
+    /// ```
+    #[cfg(feature = "snapshot")]
+    pub fn save_snapshot(&mut self, mut out: impl io::Write) -> Result<(), SnapshotError> {
+        let mut database = Vec::new();
+        let read = self.fp.stream_position().and_then(|file_position| {
+            self.fp.seek(SeekFrom::Start(0))?;
+            self.fp.read_to_end(&mut database)?;
+            self.fp.seek(SeekFrom::Start(file_position))
+        });
+        read.map_err(|err| SnapshotError::Database(err.into()))?;
+
+        let layout = Layout {
+            edition: self.database_type,
+            record_length: self.record_length,
+            segments: self.database_segments,
+        };
+        write_snapshot(&mut out, layout, &database)
+    }
+
+    /// Look up the organization (or ISP) name for a given IP address.
+    /// Use this method if you have an Organization, ISP or ASN database.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - IP address
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<Box<str>>, GeoIpReaderError>`): The organization name, or `None` if the
+    /// address is not in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is not one of the above editions,
+    /// `GeoIpReaderError::InvalidAddress` if `addr` is not a valid IP address, and
+    /// `GeoIpReaderError::Io` if the database cannot be read.
+    pub fn get_org(&mut self, addr: &str) -> Result<Option<Box<str>>, GeoIpReaderError> {
+        let editions = [ORG_EDITION, ISP_EDITION, ASNUM_EDITION, ASNUM_EDITION_V6];
+        if !editions.contains(&self.database_type) {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+
+        let position = match self.record_position(self.parse_lookup_number(addr)?)? {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        // Read up to the maximum record length, the string ends at the first null byte
+        let mut buffer = Vec::with_capacity(MAX_ORG_RECORD_LENGTH + 1);
+        self.fp.seek(SeekFrom::Start(position))?;
+        (&mut self.fp)
+            .take(MAX_ORG_RECORD_LENGTH as u64)
+            .read_to_end(&mut buffer)
+            .map_err(read_error)?;
+        buffer.push(0);
+
+        let (_, org) = read_data(&buffer, 0);
+        Ok(org)
+    }
+
+    /// Look up the Autonomous System for a given IP address.
+    /// Use this method if you have an ASN database.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - IP address
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<Asn>, GeoIpReaderError>`): The Autonomous System, or `None` if the
+    /// address is not in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is not an ASN edition, and
+    /// `GeoIpReaderError::CorruptDatabase` if the record is not of the form `AS<number> <name>`.
+    /// Invalid addresses are reported as by `get_org`.
+    pub fn get_asn(&mut self, addr: &str) -> Result<Option<Asn>, GeoIpReaderError> {
+        if ![ASNUM_EDITION, ASNUM_EDITION_V6].contains(&self.database_type) {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+
+        match self.get_org(addr)? {
+            Some(record) => Asn::parse(&record)
+                .map(Some)
+                .ok_or(GeoIpReaderError::CorruptDatabase),
+            None => Ok(None),
+        }
+    }
+
+    /// Walks the tree down to the leaf of the IP number.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<usize>, GeoIpReaderError>`): The leaf offset, or `None` if the walk ended
+    /// at the no-data leaf, i.e. the address is not in the database.
+    fn leaf(&mut self, ip_number: u128) -> Result<Option<usize>, GeoIpReaderError> {
+        let seek_country = self.get_country(ip_number)?;
+        Ok((seek_country != self.database_segments as usize).then_some(seek_country))
+    }
+
+    /// Checks whether the database holds data for an IP address, without reading its record.
+    ///
+    /// This only walks the tree as `find_record` does, so it is cheaper than a lookup. As after
+    /// a lookup, `netmask()` then returns the length of the prefix the answer holds for, e.g. to
+    /// cache negative answers per network.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<bool, GeoIpReaderError>`): `true` if `find_record` would return a record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address, or
+    /// `GeoIpReaderError::CorruptDatabase` if the walk does not end at a leaf.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// assert!(geo_ip.contains("108.95.4.105").unwrap());
+    /// assert!(!geo_ip.contains("10.0.0.1").unwrap());
+    /// assert!(geo_ip.netmask() <= 8);
+    /// ```
+    pub fn contains(&mut self, ip: &str) -> Result<bool, GeoIpReaderError> {
+        self.contains_addr(
+            ip.parse()
+                .map_err(|source| GeoIpReaderError::InvalidAddress {
+                    input: ip.to_string(),
+                    source,
+                })?,
+        )
+    }
+
+    /// Checks whether the database holds data for a parsed IP address, see `contains`.
+    pub fn contains_addr(&mut self, addr: IpAddr) -> Result<bool, GeoIpReaderError> {
+        Ok(self.leaf(self.lookup_number(addr)?)?.is_some())
+    }
+
+    /// Checks whether the database holds data for each of the IP addresses, see `contains`.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Vec<bool>, GeoIpReaderError>`): One answer per address, in the same order.
+    pub fn contains_batch(&mut self, addrs: &[IpAddr]) -> Result<Vec<bool>, GeoIpReaderError> {
+        addrs.iter().map(|addr| self.contains_addr(*addr)).collect()
+    }
+
+    /// Checks whether an IP address is geolocatable: public, in the database, and resolving to
+    /// a real country rather than to an entry such as `Country::AnonymousProxy` or
+    /// `Country::Europe`.
+    ///
+    /// Reserved addresses are answered without reading the database. The others walk the tree
+    /// as `contains` does, then read only the country of the record, so this is cheaper than
+    /// `get_record` for a yes or no answer, e.g. to filter traffic up front.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<bool, GeoIpReaderError>`): `false` for reserved addresses, the addresses that
+    /// are not in the database and those of an entry that is not a territory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address, or
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// assert!(geo_ip.is_geolocatable("108.95.4.105").unwrap());
+    /// assert!(!geo_ip.is_geolocatable("192.168.1.1").unwrap());
+    /// assert!(geo_ip.is_geolocatable("not an address").is_err());
+    /// ```
+    pub fn is_geolocatable(&mut self, ip: &str) -> Result<bool, GeoIpReaderError> {
+        let addr = ip
+            .parse::<IpAddr>()
+            .map_err(|source| GeoIpReaderError::InvalidAddress {
+                input: ip.to_string(),
+                source,
+            })?;
+        if is_reserved(&addr) {
+            return Ok(false);
+        }
+
+        let Some(seek_country) = self.leaf(self.lookup_number(addr)?)? else {
+            return Ok(false);
+        };
+        let country = self.leaf_country(seek_country)?;
+        Ok(country.is_some_and(|country| country.centroid().is_some()))
+    }
+
+    /// Checks whether an IP address is in one of the allowed countries, e.g. for the access
+    /// control of a geo-restricted service.
+    ///
+    /// Like `is_geolocatable`, this walks the tree then reads only the country of the record,
+    /// so it is cheaper than `get_record`. Reserved addresses, and every address when no
+    /// country is allowed, are answered without reading the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address.
+    /// * `allowed` - The allowed countries.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<bool, GeoIpReaderError>`): Whether the country of the address is allowed,
+    /// `false` for reserved addresses and the addresses that are not in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address, or
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::collections::HashSet;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let allowed = HashSet::from([Country::UnitedStates, Country::Canada]);
+    ///
+    /// assert!(geo_ip.country_in("108.95.4.105", &allowed).unwrap());
+    /// assert!(!geo_ip.country_in("185.90.90.120", &allowed).unwrap());
+    /// ```
+    pub fn country_in(
+        &mut self,
+        ip: &str,
+        allowed: &HashSet<Country>,
+    ) -> Result<bool, GeoIpReaderError> {
+        let addr = ip
+            .parse::<IpAddr>()
+            .map_err(|source| GeoIpReaderError::InvalidAddress {
+                input: ip.to_string(),
+                source,
+            })?;
+        if allowed.is_empty() || is_reserved(&addr) {
+            return Ok(false);
+        }
+
+        let Some(seek_country) = self.leaf(self.lookup_number(addr)?)? else {
+            return Ok(false);
+        };
+        let country = self.leaf_country(seek_country)?;
+        Ok(country.is_some_and(|country| allowed.contains(&country)))
+    }
+
+    /// Reads only the country of the record of a leaf, `None` for a region leaf outside of the
+    /// ranges of the edition.
+    fn leaf_country(&mut self, seek_country: usize) -> Result<Option<Country>, GeoIpReaderError> {
+        if self.is_region_edition() {
+            return Ok(self
+                .region_record(seek_country)?
+                .map(|record| record.country));
+        }
+
+        // The country is the first byte of a city record
+        let mut country = [0u8; 1];
+        self.read_at(self.leaf_position(seek_country as u64), &mut country)?;
+        let [byte] = country;
+        Ok(Some(
+            Country::from_buffer(byte).ok_or(GeoIpReaderError::CorruptDatabase)?,
+        ))
+    }
+
+    /// Looks up a batch of IP addresses lazily, each lookup being performed when its result is
+    /// consumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `addrs` - The IP addresses.
+    ///
+    /// # Returns
+    ///
+    /// (`LookupResults`): The results of `get_record` paired with their address, in the order
+    /// of the input, to be consumed with the adapters of `LookupResults` or as an iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    /// use std::net::IpAddr;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let ips = ["108.95.4.105", "10.0.0.1"].map(|ip| ip.parse::<IpAddr>().unwrap());
+    ///
+    /// let mut results = geo_ip.get_records(ips);
+    /// assert!(results.first_error().unwrap().to_string().contains("10.0.0.1"));
+    /// ```
+    pub fn get_records<'a, I>(
+        &'a mut self,
+        addrs: I,
+    ) -> LookupResults<impl Iterator<Item = LookupItem> + 'a>
+    where
+        I: IntoIterator<Item = IpAddr>,
+        I::IntoIter: 'a,
+    {
+        LookupResults::new(
+            addrs
+                .into_iter()
+                .map(|addr| (addr, self.get_record(&addr.to_string()))),
+        )
+    }
+
+    /// Looks up a batch of IP addresses lazily like `get_records`, the strings of the records
+    /// being shared through an interner rather than allocated for each record.
+    ///
+    /// The interner lives as long as the iterator, so a batch with geographic locality, e.g.
+    /// the client addresses of a regional service, allocates each distinct city, region code
+    /// and postal code once. The records carry no provenance and the lookups are not timed.
+    ///
+    /// # Arguments
+    ///
+    /// * `addrs` - The IP addresses.
+    ///
+    /// # Returns
+    ///
+    /// (`impl Iterator<Item = (IpAddr, Result<InternedRecord, GeoIpReaderError>)>`): The
+    /// results paired with their address, in the order of the input.
+    ///
+    /// # Errors
+    ///
+    /// Each result holds the errors of `get_record`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    /// use std::net::IpAddr;
+    /// use std::sync::Arc;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let ips = ["108.95.4.105", "108.95.4.105"].map(|ip| ip.parse::<IpAddr>().unwrap());
+    ///
+    /// let records: Vec<_> = geo_ip
+    ///     .get_records_interned(ips)
+    ///     .map(|(_, record)| record.unwrap())
+    ///     .collect();
+    /// assert_eq!(records[0].city(), Some("San Diego"));
+    /// assert!(Arc::ptr_eq(
+    ///     records[0].city.as_ref().unwrap(),
+    ///     records[1].city.as_ref().unwrap()
+    /// ));
+    /// ```
+    pub fn get_records_interned<'a, I>(
+        &'a mut self,
+        addrs: I,
+    ) -> impl Iterator<Item = (IpAddr, Result<InternedRecord<'static>, GeoIpReaderError>)> + 'a
+    where
+        I: IntoIterator<Item = IpAddr>,
+        I::IntoIter: 'a,
+    {
+        let mut interner = StringInterner::new();
+        addrs.into_iter().map(move |addr| {
+            let record = self
+                .find_record_interned(addr, &mut interner)
+                .and_then(|record| {
+                    record.ok_or_else(|| GeoIpReaderError::AddressNotFound(addr.to_string()))
+                });
+            (addr, record)
+        })
+    }
+
+    /// Looks up the record of a parsed IP address like `find_record`, drawing its strings from
+    /// `interner`.
+    fn find_record_interned(
+        &mut self,
+        addr: IpAddr,
+        interner: &mut StringInterner,
+    ) -> Result<Option<InternedRecord<'static>>, GeoIpReaderError> {
+        let ip_number = self.lookup_number(addr)?;
+
+        let record = if self.is_region_edition() {
+            let seek_country = self.get_country(ip_number)?;
+            self.region_record(seek_country)?.map(|record| {
+                InternedRecord::from_record(self.with_policy_coordinates(record), interner)
+            })
+        } else {
+            match self.record_position(ip_number)? {
+                Some(position) => {
+                    let fields = self.read_city_fields(position, |data| interner.intern(data))?;
+                    let coordinates = LatLng {
+                        latitude: fields.latitude,
+                        longitude: fields.longitude,
+                    };
+                    if self.strict_coordinates && !coordinates.is_real() {
+                        return Err(GeoIpReaderError::MissingCoordinates(addr.to_string()));
+                    }
+                    let coordinates = self
+                        .coordinate_policy
+                        .resolve(fields.country, coordinates)
+                        .unwrap_or(coordinates);
+                    Some(InternedRecord {
+                        dma: fields.dma,
+                        postal_code: fields.postal_code,
+                        country: fields.country,
+                        region_code: fields.region_code,
+                        city: fields.city,
+                        latitude: coordinates.latitude,
+                        longitude: coordinates.longitude,
+                        time_zone: fields.time_zone,
+                        netmask: 0,
+                        max_netmask: 0,
+                        warnings: fields.warnings,
+                    })
+                }
+                None => None,
+            }
+        };
+
+        let max_netmask = seek_depth(ip_number) as usize + 1;
+        Ok(record.map(|record| InternedRecord {
+            netmask: self.netmask,
+            max_netmask,
+            ..record
+        }))
+    }
+
+    /// Looks up a batch of IP addresses and summarizes the results, e.g. to be logged or
+    /// serialized for a dashboard. Addresses of reserved ranges are counted without a lookup.
+    ///
+    /// # Arguments
+    ///
+    /// * `ips` - The IP addresses.
+    ///
+    /// # Returns
+    ///
+    /// (`LookupReport`): The counts of the inputs by outcome, the countries and continents of
+    /// the found addresses, and the errors.
+    pub fn analyze<'a, I>(&mut self, ips: I) -> LookupReport
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut report = LookupReport::default();
+
+        for ip in ips {
+            report.total += 1;
+
+            let lookup = match ip.parse::<IpAddr>() {
+                Ok(addr) if is_reserved(&addr) => {
+                    report.reserved += 1;
+                    continue;
+                }
+                Ok(_) => self.find_record(ip),
+                Err(source) => Err(GeoIpReaderError::InvalidAddress {
+                    input: ip.to_string(),
+                    source,
+                }),
+            };
+
+            match lookup {
+                Ok(Some(record)) => {
+                    report.successful += 1;
+                    *report
+                        .by_country
+                        .entry(record.country.alphabetic_code_2().to_string())
+                        .or_default() += 1;
+                    if let Some(continent) = record.country.continent() {
+                        *report
+                            .by_continent
+                            .entry(continent.alphabetic_code_2().to_string())
+                            .or_default() += 1;
+                    }
+                }
+                Ok(None) => report.not_found += 1,
+                Err(err) => {
+                    report.errored += 1;
+                    report.errors.push(err.to_string());
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Resolves the absolute position of the record for a converted IP address.
+    ///
+    /// Returns `None` when the tree walk ends on the database segments, meaning the address
+    /// is not in the database.
+    fn record_position(&mut self, ip_number: u128) -> Result<Option<u64>, GeoIpReaderError> {
+        Ok(self
+            .leaf(ip_number)?
+            .map(|seek_country| self.leaf_position(seek_country as u64)))
+    }
+
+    /// Returns the absolute position of the record of a leaf.
+    pub(crate) fn leaf_position(&self, seek_country: u64) -> u64 {
+        // Records start after the tree, which is `segments` nodes of `2 * record_length` bytes,
+        // minus the `segments` offset of the leaf pointers. This fits in a u64 for any 32-bit
+        // segment count.
+        let read_length = (2 * self.record_length as u64 - 1) * u64::from(self.database_segments);
+        seek_country + read_length
+    }
+
+    /// Reads every distinct record of a city database, in the order of their position,
+    /// reporting the records read to `progress`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is not a city edition,
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    pub(crate) fn city_records(
+        &mut self,
+        progress: Option<&dyn Progress>,
+    ) -> Result<Vec<Record<'static>>, GeoIpReaderError> {
+        if ![CITY_EDITION_REV0, CITY_EDITION_REV1, CITY_EDITION_REV1_V6]
+            .contains(&self.database_type)
+        {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+
+        let leaves = self.walker().leaves()?;
+        let total = Some(leaves.len() as u64);
+        let mut throttle = Throttle::new(progress);
+        let records = leaves
+            .into_iter()
+            .map(|seek_country| {
+                throttle.tick(total);
+                let record = self.read_record(self.leaf_position(u64::from(seek_country)))?;
+                Ok(self.with_policy_coordinates(record))
+            })
+            .collect();
+        throttle.finish();
+        records
+    }
+
+    /// Calls `visit` with the leaf of each network of a city database, see
+    /// `TreeWalker::visit_leaves`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is not a city edition,
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    pub(crate) fn visit_city_leaves(
+        &mut self,
+        visit: impl FnMut(u32),
+    ) -> Result<(), GeoIpReaderError> {
+        if ![CITY_EDITION_REV0, CITY_EDITION_REV1, CITY_EDITION_REV1_V6]
+            .contains(&self.database_type)
+        {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+        self.walker().visit_leaves(visit)
+    }
+
+    /// Reads the city record of a leaf as stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the record cannot be decoded.
+    pub(crate) fn leaf_record(&mut self, leaf: u32) -> Result<Record<'static>, GeoIpReaderError> {
+        self.read_record(self.leaf_position(u64::from(leaf)))
+    }
+
+    /// Collects the countries the database has records of, e.g. to check that a regional
+    /// database covers the expected countries.
+    ///
+    /// The tree is read once for its distinct leaves, then only the country of each record is
+    /// read.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<HashSet<Country>, GeoIpReaderError>`): The countries, empty for a database
+    /// without records.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is neither a city nor a
+    /// region edition, `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let countries = geo_ip.distinct_countries().unwrap();
+    ///
+    /// assert!(countries.contains(&Country::UnitedStates));
+    /// assert!(countries.contains(&Country::SaudiArabia));
+    /// ```
+    pub fn distinct_countries(&mut self) -> Result<HashSet<Country>, GeoIpReaderError> {
+        let is_city_edition = [CITY_EDITION_REV0, CITY_EDITION_REV1, CITY_EDITION_REV1_V6]
+            .contains(&self.database_type);
+        if !is_city_edition && !self.is_region_edition() {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+
+        let mut walker = self.walker();
+        let leaves = if is_city_edition {
+            walker.leaves()?
+        } else {
+            walker.reachable_leaves()?
+        };
+        let mut countries = HashSet::new();
+        for seek_country in leaves {
+            let country = if is_city_edition {
+                // The country is the first byte of a city record
+                let mut buffer = [0; 1];
+                self.read_at(self.leaf_position(u64::from(seek_country)), &mut buffer)?;
+                let [byte] = buffer;
+                Some(Country::from_buffer(byte).ok_or(GeoIpReaderError::CorruptDatabase)?)
+            } else {
+                self.region_record(seek_country as usize)?
+                    .map(|record| record.country)
+            };
+            countries.extend(country);
+        }
+        Ok(countries)
+    }
+
+    /// Estimates the number of networks of the database, the terminal pointers of its tree,
+    /// e.g. as the total of a progress bar over a traversal of the whole database.
+    ///
+    /// This is an upper bound rather than an exact count: the ranges that are not in the
+    /// database count as networks, and the records shared by several networks count once per
+    /// network. A tree of `n` nodes has `n + 1` terminal pointers, `n` being the node count of
+    /// the structure info. The country and region editions store a fixed offset in its place,
+    /// so their node count is bounded by the size of the database instead. The estimate only
+    /// depends on the database, so it is the same across calls.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<u64, GeoIpReaderError>`): The estimated number of networks.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::Io` if the size of a country or region database cannot be
+    /// read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let total = geo_ip.total_networks().unwrap();
+    ///
+    /// assert!(total > 1_000_000);
+    /// assert_eq!(geo_ip.total_networks().unwrap(), total);
+    /// ```
+    pub fn total_networks(&mut self) -> Result<u64, GeoIpReaderError> {
+        let has_node_count = ![COUNTRY_EDITION, COUNTRY_EDITION_V6].contains(&self.database_type)
+            && !self.is_region_edition();
+        if has_node_count {
+            return Ok(u64::from(self.database_segments) + 1);
+        }
+
+        let file_position = self.fp.stream_position()?;
+        let size = self.fp.seek(SeekFrom::End(0))?;
+        self.fp.seek(SeekFrom::Start(file_position))?;
+
+        let node_length = 2 * self.record_length as u64;
+        Ok(size / node_length + 1)
+    }
+
+    /// Looks up the records of every address of a range, e.g. to summarize a range of a WHOIS
+    /// output, walking the tree over the networks of the range rather than looking up each of
+    /// its addresses.
+    ///
+    /// The bounds need not be on prefix boundaries: the range is decomposed into aligned
+    /// prefixes, see `utils::range_to_cidrs`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the range.
+    /// * `end` - The last address of the range, of the same family.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Vec<RangeRecord>, GeoIpReaderError>`): The distinct records of the range with
+    /// the sub-ranges they cover, in the order of their first sub-range. The addresses that
+    /// are not in the database are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidRange` if the bounds are of different families or in
+    /// reverse order, `GeoIpReaderError::AddressFamilyMismatch` if they are of another family
+    /// than the database, `GeoIpReaderError::InvalidDatabaseType` if the database is neither a
+    /// city nor a region edition, `GeoIpReaderError::MissingCoordinates` as `find_record` does,
+    /// or `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let records = geo_ip
+    ///     .get_records_in_range("108.95.4.100".parse().unwrap(), "108.95.4.110".parse().unwrap())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(records.len(), 1);
+    /// assert_eq!(records[0].record.city(), Some("San Diego"));
+    /// assert_eq!(records[0].ranges, [("108.95.4.100".parse().unwrap(), "108.95.4.110".parse().unwrap())]);
+    /// ```
+    pub fn get_records_in_range(
+        &mut self,
+        start: IpAddr,
+        end: IpAddr,
+    ) -> Result<Vec<RangeRecord>, GeoIpReaderError> {
+        let cidrs = range_to_cidrs(start, end).map_err(GeoIpReaderError::InvalidRange)?;
+        let is_city_edition = [CITY_EDITION_REV0, CITY_EDITION_REV1, CITY_EDITION_REV1_V6]
+            .contains(&self.database_type);
+        if !is_city_edition && !self.is_region_edition() {
+            return Err(GeoIpReaderError::InvalidDatabaseType);
+        }
+        // IPv4 ranges are walked in the IPv4-mapped range of an IPv6 database when widened
+        let widen = match start {
+            IpAddr::V4(_) if self.is_v6_edition() && self.map_v4_into_v6 => true,
+            IpAddr::V4(_) if self.is_v6_edition() => {
+                return Err(GeoIpReaderError::AddressFamilyMismatch(start.to_string()))
+            }
+            IpAddr::V6(_) if !self.is_v6_edition() => {
+                return Err(GeoIpReaderError::AddressFamilyMismatch(start.to_string()))
+            }
+            _ => false,
+        };
+        let to_addr = |number: u128, bits: u32| {
+            let addr = bits_addr(number, bits);
+            match addr {
+                IpAddr::V6(ipv6_addr) if widen => {
+                    ipv6_addr.to_ipv4_mapped().map_or(addr, IpAddr::V4)
+                }
+                _ => addr,
+            }
+        };
+
+        // The networks of the range, adjacent ones of a same leaf merged, as
+        // `(first, last, prefix length, leaf)`
+        let mut spans: Vec<(u128, u128, u32, u32)> = Vec::new();
+        let mut walker = self.walker();
+        let mut bits = 32;
+        for (network, prefix) in cidrs {
+            let ((network, network_bits), prefix) = match network {
+                IpAddr::V4(ipv4_addr) if widen => (
+                    addr_bits(IpAddr::V6(ipv4_addr.to_ipv6_mapped())),
+                    u32::from(prefix) + 96,
+                ),
+                _ => (addr_bits(network), u32::from(prefix)),
+            };
+            bits = network_bits;
+            for (first, length, leaf) in walker.prefix_leaves(network, prefix, bits)? {
+                let last = block_last(first, bits - length);
+                match spans.last_mut() {
+                    Some(span) if span.3 == leaf && span.1.checked_add(1) == Some(first) => {
+                        span.1 = last
+                    }
+                    _ => spans.push((first, last, length, leaf)),
+                }
+            }
+        }
+
+        // The records by leaf, `None` for the leaves of the addresses not in the database
+        let mut records: Vec<RangeRecord> = Vec::new();
+        let mut leaf_records: HashMap<u32, Option<usize>> = HashMap::new();
+        for (first, last, length, leaf) in spans {
+            let range = (to_addr(first, bits), to_addr(last, bits));
+            let index = match leaf_records.get(&leaf) {
+                Some(index) => *index,
+                None => {
+                    let record = if self.is_region_edition() {
+                        self.region_record(leaf as usize)?
+                    } else if leaf == self.database_segments {
+                        None
+                    } else {
+                        let record = self.read_record(self.leaf_position(u64::from(leaf)))?;
+                        if self.strict_coordinates && !record.has_real_coordinates() {
+                            return Err(GeoIpReaderError::MissingCoordinates(range.0.to_string()));
+                        }
+                        Some(record)
+                    };
+                    let record = record.map(|record| self.with_policy_coordinates(record));
+                    let index = record.map(|record| {
+                        records.push(RangeRecord {
+                            record: record
+                                .with_netmask(length as usize, bits as usize)
+                                .with_provenance(self.provenance()),
+                            ranges: Vec::new(),
+                        });
+                        records.len() - 1
+                    });
+                    leaf_records.insert(leaf, index);
+                    index
+                }
+            };
+            if let Some(record) = index.and_then(|index| records.get_mut(index)) {
+                record.ranges.push(range);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Looks up the records of every address of a CIDR, e.g. `8.8.8.0/24`, like
+    /// `get_records_in_range`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidRange` if `cidr` is not a valid CIDR, and the errors
+    /// of `get_records_in_range`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    /// let records = geo_ip.get_records_in_cidr("108.95.4.0/24").unwrap();
+    ///
+    /// assert!(records.iter().all(|found| found.record.country == Country::UnitedStates));
+    /// ```
+    pub fn get_records_in_cidr(
+        &mut self,
+        cidr: &str,
+    ) -> Result<Vec<RangeRecord>, GeoIpReaderError> {
+        if !cidr.contains('/') {
+            return Err(GeoIpReaderError::InvalidRange(RangeError::Invalid(
+                cidr.to_string(),
+            )));
+        }
+        let (start, end) = parse_ip_range(cidr).map_err(GeoIpReaderError::InvalidRange)?;
+        self.get_records_in_range(start, end)
+    }
+
+    /// Checks whether two IP addresses resolve to the same network block in the database.
+    ///
+    /// Both addresses are looked up, and they are considered part of the same block when
+    /// their netmasks match and `ip & mask` is equal for both of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The first IP address.
+    /// * `b` - The second IP address.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<bool, GeoIpReaderError>`): `true` if both addresses share the same block.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// assert!(geo_ip.same_block("108.95.4.105", "108.95.4.106").unwrap());
+    /// assert!(!geo_ip.same_block("108.95.4.105", "185.90.90.120").unwrap());
+    /// ```
+    pub fn same_block(&mut self, a: &str, b: &str) -> Result<bool, GeoIpReaderError> {
+        let (a_addr, b_addr) = (parse_addr(a)?, parse_addr(b)?);
+        let (a_number, b_number) = (self.lookup_number(a_addr)?, self.lookup_number(b_addr)?);
+
+        self.get_country(a_number)?;
+        let a_netmask = self.netmask;
+        self.get_country(b_number)?;
+        let b_netmask = self.netmask;
+
+        // The blocks of both families are apart, and so are the walks of different depths
+        let depth = seek_depth(a_number);
+        if a_addr.is_ipv4() != b_addr.is_ipv4()
+            || depth != seek_depth(b_number)
+            || a_netmask != b_netmask
+        {
+            return Ok(false);
+        }
+
+        let mask = u32::try_from(a_netmask)
+            .ok()
+            .and_then(|netmask| (depth + 1).checked_sub(netmask))
+            .and_then(|host_bits| u128::MAX.checked_shl(host_bits))
+            .unwrap_or(0);
+
+        Ok(a_number & mask == b_number & mask)
+    }
+}
+
+/// Parses an address of a lookup.
+///
+/// # Errors
+///
+/// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address.
+fn parse_addr(ip: &str) -> Result<IpAddr, GeoIpReaderError> {
+    ip.parse::<IpAddr>()
+        .map_err(|source| GeoIpReaderError::InvalidAddress {
+            input: ip.to_string(),
+            source,
+        })
+}
+
+impl GeoIpReader<File> {
+    /// Opens the database file again if its size or modification time changed since it was
+    /// opened, e.g. by a database update of a long-running service, and detects its layout
+    /// again. The options of the builder that opened it still apply.
+    ///
+    /// Updates should replace the file at once, e.g. by a rename as `db_install` does: a file
+    /// written in place may be reloaded while only partially written.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<bool, GeoIpReaderError>`): Whether the database was reloaded, never for a
+    /// reader over a file that was not opened by its path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::Io` if the metadata of the path cannot be read, e.g. the file
+    /// was removed, or the errors of `GeoIpReaderBuilder::open` if the changed file cannot be
+    /// opened or its layout cannot be detected, in which case the reader keeps reading the
+    /// database it had opened.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::geo_ip_reader::GeoIpReader;
+    /// use std::fs::File;
+    ///
+    /// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    ///
+    /// assert!(!geo_ip.reload_if_changed().unwrap());
+    /// ```
+    pub fn reload_if_changed(&mut self) -> Result<bool, GeoIpReaderError> {
+        let (SourceKind::File { path }, Some((options, stamp))) = (&self.source, self.opened)
+        else {
+            return Ok(false);
+        };
+        let path = Arc::clone(path);
+        // The metadata of the path rather than of the open file, which a rename leaves as it was
+        let metadata = fs::metadata(&path)?;
+        if FileStamp::of(&metadata) == stamp {
+            return Ok(false);
+        }
+
+        let (fp, stamp) = options.open(&path)?;
+        let previous_fp = std::mem::replace(&mut self.fp, fp);
+        let layout = (
+            self.database_type,
+            self.record_length,
+            self.database_segments,
+        );
+        if let Err(err) = self.detect_database_type() {
+            self.fp = previous_fp;
+            (
+                self.database_type,
+                self.record_length,
+                self.database_segments,
+            ) = layout;
+            return Err(err);
+        }
+
+        self.build_date = self.read_build_date();
+        self.octet_index = None;
+        self.opened = Some((options, stamp));
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{city_database, region_database, string_database};
+    use crate::utils::try_ip_to_number;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::io::Cursor;
+    use std::net::IpAddr;
+    use std::num::Wrapping;
+
+    #[test]
+    fn test_new_geo_ip_reader() {
+        let result = GeoIpReader::<File>::new("v4");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_get_country() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+        match geo_ip.get_country(16777216) {
+            Ok(offset) => assert_eq!(offset, 2735459),
+            Err(err) => eprintln!("Error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_get_time_zone_given_ip_addr() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+        let result = geo_ip.get_time_zone_given_ip_addr("185.90.90.120").unwrap();
+        assert_eq!(result, "Asia/Riyadh".to_string());
+
+        let result = geo_ip.get_time_zone_given_ip_addr("108.95.4.105").unwrap();
+        assert_eq!(result, "America/Los_Angeles");
+    }
+
+    #[test]
+    fn test_get_record_with_valid_ip() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("185.90.90.120").unwrap();
+
+        assert_eq!(record.country, Country::SaudiArabia);
+    }
+
+    #[test]
+    fn test_get_record_for_socket() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let peer = SocketAddr::from(([185, 90, 90, 120], 51234));
+        assert_eq!(
+            geo_ip.get_record_for_socket(peer).unwrap(),
+            geo_ip.get_record("185.90.90.120").unwrap()
+        );
+
+        let peer: SocketAddr = "10.0.0.1:80".parse().unwrap();
+        assert!(matches!(
+            geo_ip.get_record_for_socket(peer),
+            Err(GeoIpReaderError::AddressNotFound(ip)) if ip == "10.0.0.1"
+        ));
+
+        let mut geo_ip = GeoIpReader::<File>::new("v6").unwrap();
+        let peer: SocketAddr = "[2001:4860:4860::8888]:443".parse().unwrap();
+        let record = geo_ip.get_record_for_socket(peer).unwrap();
+        assert_eq!(record, geo_ip.get_record("2001:4860:4860::8888").unwrap());
+        assert_eq!(record.country, Country::UnitedStates);
+    }
+
+    #[test]
+    fn test_all_records_with_valid_ip() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+
+        let expected_value = Record {
+            dma: Some(DesignatedMarketArea(825858)),
+            postal_code: Some("92109".into()),
+            country: Country::UnitedStates,
+            region_code: Some("CA".into()),
+            city: Some("San Diego".into()),
+            latitude: 32.79769999999999,
+            longitude: -117.23349999999999,
+            time_zone: "America/Los_Angeles",
+            netmask: 23,
+            max_netmask: 32,
+            warnings: Vec::new(),
+            gaps: Vec::new(),
+            provenance: None,
+            country_byte: Country::UnitedStates.to_buffer(),
+        };
+
+        assert_eq!(record, expected_value);
+    }
+
+    #[test]
+    fn test_dma_with_non_us_record() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("185.90.90.120").unwrap();
+
+        assert_eq!(record.country, Country::SaudiArabia);
+        assert_eq!(record.dma, None);
+    }
+
+    #[test]
+    fn test_dma_policy_never() {
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .dma_policy(DmaPolicy::Never)
+            .build(File::open(home_dir().unwrap().join("ipcap/geo_ip_city_v4.dat")).unwrap())
+            .unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+
+        assert_eq!(record.country, Country::UnitedStates);
+        assert_eq!(record.dma, None);
+    }
+
+    #[test]
+    fn test_dma_policy_all_countries() {
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .dma_policy(DmaPolicy::AllCountries)
+            .open(home_dir().unwrap().join("ipcap/geo_ip_city_v4.dat"))
+            .unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+
+        assert_eq!(record.dma, Some(DesignatedMarketArea(825858)));
+    }
+
+    #[test]
+    fn test_timings() {
+        let path = home_dir().unwrap().join("ipcap/geo_ip_city_v4.dat");
+        let mut geo_ip = GeoIpReaderBuilder::new().timings(true).open(path).unwrap();
+        assert_eq!(geo_ip.last_timings(), Some(LookupTimings::default()));
+
+        geo_ip.get_record("108.95.4.105").unwrap();
+        let timings = geo_ip.last_timings().unwrap();
+        assert!(timings.tree_walk > Duration::ZERO);
+        assert!(timings.record_parse > Duration::ZERO);
+        assert_eq!(
+            timings.total(),
+            timings.parse + timings.tree_walk + timings.record_parse
+        );
+
+        // A failed lookup keeps the timings of the last successful one
+        assert!(geo_ip.find_record("banana").is_err());
+        assert_eq!(geo_ip.last_timings(), Some(timings));
+
+        // A missing address has no record to parse
+        assert_eq!(geo_ip.find_record("10.0.0.1").unwrap(), None);
+        assert!(geo_ip.last_timings().unwrap().tree_walk > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_timings_disabled() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        geo_ip.get_record("108.95.4.105").unwrap();
+        assert_eq!(geo_ip.last_timings(), None);
+
+        let mut stopwatch = Stopwatch::start(false);
+        assert_eq!(stopwatch.lap(), Duration::ZERO);
+        assert!(stopwatch.0.is_none());
+    }
+
+    #[test]
+    fn test_get_place() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let place = geo_ip.get_place("108.95.4.105").unwrap();
+        assert_eq!(
+            place,
+            Place {
+                country: "United States".to_string(),
+                region: Some("CA".to_string()),
+                city: Some("San Diego".to_string()),
+            }
+        );
+        // Owned, so that it outlives the reader and moves to another thread
+        drop(geo_ip);
+        let place = std::thread::spawn(move || place).join().unwrap();
+        assert_eq!(place.city.as_deref(), Some("San Diego"));
+
+        let record = Record::new(Country::France);
+        assert_eq!(
+            Place::from(&record),
+            Place {
+                country: "France".to_string(),
+                region: None,
+                city: None,
+            }
+        );
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        assert!(matches!(
+            geo_ip.get_place("10.0.0.1"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_record_timed() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let start = Instant::now();
+        let (record, timings) = geo_ip.get_record_timed("108.95.4.105").unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(record, geo_ip.get_record("108.95.4.105").unwrap());
+        assert!(timings.tree_walk > Duration::ZERO);
+        assert!(timings.record_parse > Duration::ZERO);
+        // The phases are laps of a single lookup, within the time of the whole call
+        assert_eq!(
+            timings.total(),
+            timings.parse + timings.tree_walk + timings.record_parse
+        );
+        assert!(timings.total() <= elapsed);
+        // The other lookups of the reader stay untimed
+        assert_eq!(geo_ip.last_timings(), None);
+        assert!(matches!(
+            geo_ip.get_record_timed("10.0.0.1"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+        assert_eq!(geo_ip.last_timings(), None);
+
+        // With the timings enabled, the timed lookup is the last one
+        let path = home_dir().unwrap().join("ipcap/geo_ip_city_v4.dat");
+        let mut geo_ip = GeoIpReaderBuilder::new().timings(true).open(path).unwrap();
+        let (_, timings) = geo_ip.get_record_timed("108.95.4.105").unwrap();
+        assert_eq!(geo_ip.last_timings(), Some(timings));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_trust_policy() {
+        use std::os::unix::fs::{chown, symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("ipcap_test_trust_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = string_database(ORG_EDITION, "Google LLC");
+        let write = |name: &str, mode: u32| {
+            let path = dir.join(name);
+            std::fs::write(&path, &database).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            path
+        };
+
+        let normal = write("normal.dat", 0o644);
+        let world_writable = write("world_writable.dat", 0o666);
+        let group_writable = write("group_writable.dat", 0o664);
+        let link = dir.join("link.dat");
+        let _ = std::fs::remove_file(&link);
+        symlink(&normal, &link).unwrap();
+        // Only root can give a file away, skip this case otherwise
+        let foreign = write("foreign.dat", 0o644);
+        let foreign = chown(&foreign, Some(65534), None).ok().map(|_| foreign);
+
+        let opens = |path: &Path, policy| match GeoIpReaderBuilder::new()
+            .file_trust_policy(policy)
+            .open(path)
+        {
+            Ok(_) => true,
+            Err(GeoIpReaderError::UntrustedDatabaseFile { path: refused, .. }) => {
+                assert_eq!(refused, path);
+                false
+            }
+            Err(err) => panic!("{}", err),
+        };
+
+        use FileTrustPolicy::*;
+        for (path, standard, strict) in [
+            (&normal, true, true),
+            (&link, true, false),
+            (&world_writable, false, false),
+            (&group_writable, true, false),
+        ] {
+            assert_eq!(opens(path, Standard), standard, "{}", path.display());
+            assert_eq!(opens(path, Strict), strict, "{}", path.display());
+            assert!(opens(path, AllowAny), "{}", path.display());
+        }
+        // Refused by the open itself, which does not follow the link
+        assert!(matches!(
+            GeoIpReaderBuilder::new()
+                .file_trust_policy(Strict)
+                .open(&link),
+            Err(GeoIpReaderError::UntrustedDatabaseFile {
+                reason: "the path is a symbolic link",
+                ..
+            })
+        ));
+        if let Some(foreign) = foreign {
+            assert!(opens(&foreign, Standard));
+            assert!(!opens(&foreign, Strict));
+            assert!(opens(&foreign, AllowAny));
+        }
+
+        // The constructors opening a path apply the default policy
+        assert!(matches!(
+            GeoIpReader::<File>::from_path(&world_writable),
+            Err(GeoIpReaderError::UntrustedDatabaseFile { .. })
+        ));
+        assert!(GeoIpReader::<File>::from_path(&link).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_too_small() {
+        let dir = std::env::temp_dir().join(format!("ipcap_test_small_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, bytes).unwrap();
+            path
+        };
+
+        // An empty file, a few bytes and a bare gzip header, e.g. of an interrupted download
+        let gzip_header = [0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0x03];
+        for (name, bytes) in [
+            ("empty.dat", &[][..]),
+            ("two_bytes.dat", &[0xff, 0xff][..]),
+            ("gzip_header.dat", &gzip_header[..]),
+        ] {
+            let path = write(name, bytes);
+            let err = GeoIpReader::<File>::from_path(&path).unwrap_err();
+            match &err {
+                GeoIpReaderError::DatabaseTooSmall {
+                    path: small,
+                    size,
+                    minimum,
+                } => {
+                    assert_eq!(small, &path);
+                    assert_eq!(*size, bytes.len() as u64);
+                    assert_eq!(*minimum, MIN_DATABASE_SIZE);
+                }
+                err => panic!("{}: {}", name, err),
+            }
+            let message = err.to_string();
+            assert!(message.contains(&path.display().to_string()), "{}", message);
+            assert!(
+                message.contains(&format!("{} bytes, expected at least 13", bytes.len())),
+                "{}",
+                message
+            );
+            assert!(message.contains("download it again"), "{}", message);
+        }
+
+        // Junk past the minimum size opens, and its lookups fail rather than panic
+        let junk = write("junk.dat", &[0; 100]);
+        let mut geo_ip = GeoIpReader::<File>::from_path(&junk).unwrap();
+        assert!(matches!(
+            geo_ip.get_record("8.8.8.8"),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+
+        // A valid database is unaffected
+        let valid = write("valid.dat", &city_database(CITY_EDITION_REV1));
+        let mut geo_ip = GeoIpReader::<File>::from_path(&valid).unwrap();
+        assert_eq!(
+            geo_ip.get_record("200.0.0.1").unwrap().city(),
+            Some("Zürich")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let dir = std::env::temp_dir().join(format!("ipcap_test_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("city.dat");
+        std::fs::write(&path, city_database(CITY_EDITION_REV1)).unwrap();
+        // Replaces the database as an update would, by a rename
+        let replace = |bytes: &[u8]| {
+            let staged = dir.join("city.dat.tmp");
+            std::fs::write(&staged, bytes).unwrap();
+            std::fs::rename(&staged, &path).unwrap();
+        };
+
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .dma_policy(DmaPolicy::Never)
+            .open(&path)
+            .unwrap();
+        assert!(!geo_ip.reload_if_changed().unwrap());
+
+        // Touching the file is a change, then the reloaded file is the reference
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(10))
+            .unwrap();
+        assert!(geo_ip.reload_if_changed().unwrap());
+        assert!(!geo_ip.reload_if_changed().unwrap());
+        assert_eq!(
+            geo_ip.get_record("200.0.0.1").unwrap().city(),
+            Some("Zürich")
+        );
+
+        let mut database = DatabaseBuilder::new();
+        database
+            .insert("1.0.0.0/8", RecordSpec::new(Country::France).city("Paris"))
+            .unwrap();
+        replace(&database.build());
+        assert!(geo_ip.reload_if_changed().unwrap());
+        let record = geo_ip.get_record("1.2.3.4").unwrap();
+        assert_eq!(record.city(), Some("Paris"));
+        assert_eq!(record.dma, None);
+        assert!(matches!(
+            geo_ip.get_record("200.0.0.1"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+
+        // A reader over a file it did not open by its path never reloads
+        let mut unopened = GeoIpReader::from_reader(File::open(&path).unwrap()).unwrap();
+        assert!(!unopened.reload_if_changed().unwrap());
+
+        // A broken update is refused, the opened database still being read
+        replace(&[0xff; 2]);
+        assert!(matches!(
+            geo_ip.reload_if_changed(),
+            Err(GeoIpReaderError::DatabaseTooSmall { .. })
+        ));
+        assert_eq!(geo_ip.get_record("1.2.3.4").unwrap().city(), Some("Paris"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut v4 = GeoIpReader::<File>::new("v4").unwrap();
+        let fingerprint = v4.fingerprint().unwrap();
+
+        assert_eq!(v4.fingerprint().unwrap(), fingerprint);
+        assert_eq!(v4.fp.stream_position().unwrap(), 0);
+
+        let mut v6 = GeoIpReader::<File>::new("v6").unwrap();
+        assert_ne!(v6.fingerprint().unwrap(), fingerprint);
+
+        let mut database = string_database(ORG_EDITION, "Google LLC");
+        let mut org = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        database[9] = b'g';
+        let mut changed_org = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_ne!(
+            org.fingerprint().unwrap(),
+            changed_org.fingerprint().unwrap()
+        );
+    }
+
+    /// The offset of a two-letter United States or Canada region code.
+    fn region_offset(region_code: &str) -> u32 {
+        let letters = region_code.as_bytes();
+        u32::from(letters[0] - b'A') * 26 + u32::from(letters[1] - b'A')
+    }
+
+    #[test]
+    fn test_provenance() {
+        let path = GeoIpReader::<File>::database_path("v4").unwrap();
+        let mut geo_ip = GeoIpReader::<File>::from_path(&path).unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+        assert_eq!(
+            record.provenance,
+            Some(Provenance {
+                source: SourceKind::File {
+                    path: Arc::from(path.as_path())
+                },
+                edition: Some(CITY_EDITION_REV1),
+                db_build_date: Some((2016, 4, 5)),
+            })
+        );
+
+        // The same database in memory gives an equal record, the provenance being ignored
+        let bytes = std::fs::read(&path).unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(bytes)).unwrap();
+        let in_memory = geo_ip.get_record("108.95.4.105").unwrap();
+        assert_eq!(
+            in_memory.provenance.as_ref().unwrap().source,
+            SourceKind::Memory
+        );
+        assert_ne!(in_memory.provenance, record.provenance);
+        assert_eq!(in_memory, record);
+
+        // A country level database, without database info
+        let database = region_database(REGION_EDITION_REV1, [0, US_OFFSET]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(
+            geo_ip.get_record("200.0.0.1").unwrap().provenance,
+            Some(Provenance {
+                source: SourceKind::Memory,
+                edition: Some(REGION_EDITION_REV1),
+                db_build_date: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_build_date() {
+        let info =
+            b"\0\0\0GEO-533LITE 20160405 Build 1 Copyright (c) 2016 MaxMind Inc\xFF\xFF\xFF\x02";
+        assert_eq!(parse_build_date(info), Some((2016, 4, 5)));
+        assert_eq!(parse_build_date(b"GEO-533LITE 20161305 Build 1"), None);
+        assert_eq!(parse_build_date(b"GEO-533LITE 2016045 Build 1"), None);
+        assert_eq!(parse_build_date(b"Google LLC"), None);
+    }
+
+    #[test]
+    fn test_region_edition_rev1() {
+        let us = US_OFFSET + region_offset("CA");
+        let canada = CANADA_OFFSET + region_offset("ON");
+        let germany = WORLD_OFFSET + u32::from(Country::Germany.to_buffer()) * FIPS_RANGE + 5;
+
+        let database = region_database(REGION_EDITION_REV1, [0, us]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(geo_ip.edition_name(), "Region Edition, Rev 1");
+        assert_eq!(geo_ip.find_record("10.0.0.1").unwrap(), None);
+        let record = geo_ip.find_record("200.0.0.1").unwrap().unwrap();
+        assert_eq!(
+            record,
+            Record::new(Country::UnitedStates)
+                .with_region_code("CA")
+                .with_netmask(1, 32)
+        );
+        assert_eq!(record.time_zone, "America/Los_Angeles");
+        assert_eq!(
+            geo_ip.get_record("200.0.0.1").unwrap().region_code(),
+            Some("CA")
+        );
+
+        let database = region_database(REGION_EDITION_REV1, [canada, germany]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(
+            geo_ip.find_record("10.0.0.1").unwrap(),
+            Some(
+                Record::new(Country::Canada)
+                    .with_region_code("ON")
+                    .with_netmask(1, 32)
+            )
+        );
+        assert_eq!(
+            geo_ip.find_record("200.0.0.1").unwrap(),
+            Some(Record::new(Country::Germany).with_netmask(1, 32))
+        );
+    }
+
+    #[test]
+    fn test_time_zone_overrides() {
+        let mut database = DatabaseBuilder::new();
+        database
+            .insert("1.0.0.0/8", RecordSpec::new(Country::India))
+            .unwrap()
+            .insert(
+                "2.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates).region_code("CA"),
+            )
+            .unwrap()
+            .insert(
+                "3.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates).region_code("NY"),
+            )
+            .unwrap()
+            .insert(
+                "4.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates).region_code("TX"),
+            )
+            .unwrap()
+            .insert("5.0.0.0/8", RecordSpec::new(Country::France))
+            .unwrap()
+            .insert(
+                "6.0.0.0/8",
+                RecordSpec::new(Country::Australia).region_code("02"),
+            )
+            .unwrap();
+        let database = database.build();
+        let time_zones = |overrides: Option<&str>| {
+            let mut builder = GeoIpReaderBuilder::new();
+            if let Some(overrides) = overrides {
+                builder = builder.time_zone_overrides(OverrideSet::parse(overrides).unwrap());
+            }
+            let mut geo_ip = builder.build(Cursor::new(database.clone())).unwrap();
+            [
+                "1.1.1.1", "2.2.2.2", "3.3.3.3", "4.4.4.4", "5.5.5.5", "6.6.6.6",
+            ]
+            .map(|ip| geo_ip.get_record(ip).unwrap().time_zone)
+        };
+
+        let builtin = [
+            "Asia/Calcutta",
+            "America/Los_Angeles",
+            "America/New_York",
+            "America/Chicago",
+            "Europe/Paris",
+            "Australia/NSW",
+        ];
+        assert_eq!(time_zones(None), builtin);
+        // A rename of the zone of a country as a whole
+        assert_eq!(
+            time_zones(Some("IN.default = \"Asia/Kolkata\"")),
+            [
+                "Asia/Kolkata",
+                "America/Los_Angeles",
+                "America/New_York",
+                "America/Chicago",
+                "Europe/Paris",
+                "Australia/NSW",
+            ]
+        );
+        // The override of a region, then of the country as a whole, then the built-in region
+        assert_eq!(
+            time_zones(Some(
+                "[US]\n\
+                 CA = \"America/Phoenix\"\n\
+                 default = \"America/Denver\"\n\
+                 [AU]\n\
+                 03 = \"Australia/Darwin\"\n"
+            )),
+            [
+                "Asia/Calcutta",
+                "America/Phoenix",
+                "America/Denver",
+                "America/Denver",
+                "Europe/Paris",
+                "Australia/NSW",
+            ]
+        );
+
+        // The overrides of a Region edition
+        let database = region_database(REGION_EDITION_REV1, [0, US_OFFSET + region_offset("CA")]);
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .time_zone_overrides(OverrideSet::parse("US.ca = \"America/Phoenix\"").unwrap())
+            .build(Cursor::new(database))
+            .unwrap();
+        let record = geo_ip.get_record("200.0.0.1").unwrap();
+        assert_eq!(record.time_zone, "America/Phoenix");
+        assert!(record.gaps().iter().all(|gap| gap.field != "time_zone"));
+    }
+
+    #[test]
+    fn test_record_gaps() {
+        let gap = |field, reason| FieldGap { field, reason };
+        let mut database = DatabaseBuilder::new();
+        database
+            .insert(
+                "1.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates)
+                    .region_code("CA")
+                    .city("San Diego")
+                    .postal_code("92109")
+                    .coordinates(32.7977, -117.2335)
+                    .dma(DesignatedMarketArea(825858)),
+            )
+            .unwrap()
+            .insert("2.0.0.0/8", RecordSpec::new(Country::France))
+            .unwrap()
+            .insert(
+                "3.0.0.0/8",
+                RecordSpec::new(Country::UnitedStates)
+                    .region_code("XX")
+                    .city("Nowhere")
+                    .postal_code("00000")
+                    .coordinates(40.0, -100.0)
+                    .dma(DesignatedMarketArea(999650)),
+            )
+            .unwrap()
+            .insert("4.0.0.0/8", RecordSpec::new(Country::AnonymousProxy))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database.build())).unwrap();
+
+        // A fully populated record
+        assert_eq!(geo_ip.get_record("1.2.3.4").unwrap().gaps(), []);
+
+        // A record of a country, without region, city, postal code nor coordinates
+        assert_eq!(
+            geo_ip.get_record("2.2.3.4").unwrap().gaps(),
+            [
+                gap("region_code", GapReason::NotInDatabase),
+                gap("city", GapReason::NotInDatabase),
+                gap("postal_code", GapReason::NotInDatabase),
+                gap("latitude", GapReason::NotInDatabase),
+                gap("longitude", GapReason::NotInDatabase),
+            ]
+        );
+
+        // A region the time zone table does not know, and a DMA code without a name
+        assert_eq!(
+            geo_ip.get_record("3.2.3.4").unwrap().gaps(),
+            [
+                gap("dma_code", GapReason::UnknownDmaCode(999)),
+                gap(
+                    "time_zone",
+                    GapReason::UnmappedRegion {
+                        country: Country::UnitedStates,
+                        region: Some("XX".into()),
+                    }
+                ),
+            ]
+        );
+
+        // A pseudo-country has no location
+        assert_eq!(
+            geo_ip.get_record("4.2.3.4").unwrap().gaps(),
+            [
+                gap("region_code", GapReason::NotInDatabase),
+                gap("city", GapReason::NotInDatabase),
+                gap("postal_code", GapReason::NotInDatabase),
+                gap("latitude", GapReason::PseudoCountry),
+                gap("longitude", GapReason::PseudoCountry),
+                gap("time_zone", GapReason::PseudoCountry),
+            ]
+        );
+
+        // The coordinates of the coordinate policy fill their gaps
+        geo_ip.set_coordinate_policy(CoordinatePolicy::CountryCentroid);
+        assert_eq!(
+            geo_ip.get_record("2.2.3.4").unwrap().gaps(),
+            [
+                gap("region_code", GapReason::NotInDatabase),
+                gap("city", GapReason::NotInDatabase),
+                gap("postal_code", GapReason::NotInDatabase),
+            ]
+        );
+
+        // The records of a Region edition only have a country and a region
+        let us = US_OFFSET + region_offset("CA");
+        let database = region_database(REGION_EDITION_REV1, [0, us]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        let country_only = |field| gap(field, GapReason::CountryOnlyRecord);
+        assert_eq!(
+            geo_ip.get_record("200.0.0.1").unwrap().gaps(),
+            [
+                country_only("city"),
+                country_only("postal_code"),
+                country_only("latitude"),
+                country_only("longitude"),
+            ]
+        );
+        let australia = WORLD_OFFSET + u32::from(Country::Australia.to_buffer()) * FIPS_RANGE;
+        let database = region_database(REGION_EDITION_REV1, [australia, us]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        let gaps = geo_ip.get_record("10.0.0.1").unwrap().gaps;
+        assert_eq!(gaps.first(), Some(&country_only("region_code")));
+        assert_eq!(
+            gaps.last(),
+            Some(&gap(
+                "time_zone",
+                GapReason::UnmappedRegion {
+                    country: Country::Australia,
+                    region: None,
+                }
+            ))
+        );
+        assert_eq!(
+            gaps.last().unwrap().to_string(),
+            "time_zone missing: AU spans several zones and the record has no region"
+        );
+
+        // The records that are not looked up have none
+        assert_eq!(Record::new(Country::France).gaps(), []);
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let spec = RecordSpec::new(Country::UnitedStates)
+            .region_code("CA")
+            .city("San Diego")
+            .coordinates(32.7977, -117.2335)
+            .dma(DesignatedMarketArea(825858));
+        let database = |byte_order| {
+            let mut database = DatabaseBuilder::new();
+            database
+                .byte_order(byte_order)
+                .insert("1.0.0.0/8", RecordSpec::new(Country::France))
+                .unwrap()
+                .insert("108.95.4.0/24", spec.clone())
+                .unwrap();
+            database.build()
+        };
+        let reader = |byte_order, database| {
+            GeoIpReaderBuilder::new()
+                .byte_order(byte_order)
+                .build(Cursor::new(database))
+                .unwrap()
+        };
+
+        let segments =
+            reader(ByteOrder::LittleEndian, database(ByteOrder::LittleEndian)).database_segments;
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let mut geo_ip = reader(byte_order, database(byte_order));
+            assert_eq!(geo_ip.database_segments, segments, "{:?}", byte_order);
+            let record = geo_ip.get_record("108.95.4.105").unwrap();
+            assert_eq!(record.city(), Some("San Diego"), "{:?}", byte_order);
+            assert_eq!(record.region_code(), Some("CA"));
+            assert!(
+                (record.latitude - 32.7977).abs() < 1e-6,
+                "{}",
+                record.latitude
+            );
+            assert!(
+                (record.longitude + 117.2335).abs() < 1e-6,
+                "{}",
+                record.longitude
+            );
+            assert_eq!(record.dma, Some(DesignatedMarketArea(825858)));
+            assert_eq!(
+                geo_ip.get_record("1.2.3.4").unwrap().country,
+                Country::France
+            );
+        }
+
+        // The segment count of a big-endian database read as little-endian does not fit the file
+        let mut geo_ip = reader(ByteOrder::LittleEndian, database(ByteOrder::BigEndian));
+        assert_ne!(geo_ip.database_segments, segments);
+        assert!(geo_ip.get_record("108.95.4.105").is_err());
+    }
+
+    #[test]
+    fn test_total_networks() {
+        // A city database has as many records as networks at most
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let total = geo_ip.total_networks().unwrap();
+        let records = TreeWalker::new(
+            &mut geo_ip.fp,
+            geo_ip.record_length,
+            geo_ip.database_segments,
+        )
+        .leaves()
+        .unwrap()
+        .len() as u64;
+        assert!(records <= total, "{} > {}", records, total);
+        assert!(total <= 2 * u64::from(geo_ip.database_segments));
+
+        let mut builder = DatabaseBuilder::new();
+        for network in ["8.8.8.0/24", "9.9.9.0/24", "10.0.0.0/8"] {
+            builder
+                .insert(network, RecordSpec::new(Country::UnitedStates))
+                .unwrap();
+        }
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let total = geo_ip.total_networks().unwrap();
+        assert!((3..=64).contains(&total), "{}", total);
+        assert_eq!(geo_ip.total_networks().unwrap(), total);
+
+        // Region databases are bounded by their size, without moving the position of the file
+        let database = region_database(REGION_EDITION_REV1, [CANADA_OFFSET, US_OFFSET]);
+        let size = database.len() as u64;
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        geo_ip.fp.set_position(7);
+        let total = geo_ip.total_networks().unwrap();
+        assert!((2..=size).contains(&total), "{}", total);
+        assert_eq!(geo_ip.fp.position(), 7);
+        assert_eq!(
+            geo_ip.find_record("10.0.0.1").unwrap().unwrap().country,
+            Country::Canada
+        );
+    }
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn test_get_records_in_range() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert(
+                "8.8.8.0/25",
+                RecordSpec::new(Country::UnitedStates).city("Mountain View"),
+            )
+            .unwrap()
+            .insert(
+                "8.8.8.128/25",
+                RecordSpec::new(Country::Switzerland).city("Zurich"),
+            )
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+
+        // Two differently-located blocks, with bounds off their prefix boundaries
+        let records = geo_ip
+            .get_records_in_range(ip("8.8.8.3"), ip("8.8.8.200"))
+            .unwrap();
+        let rows: Vec<_> = records
+            .iter()
+            .map(|found| (found.record.city(), found.ranges.as_slice()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (
+                    Some("Mountain View"),
+                    &[(ip("8.8.8.3"), ip("8.8.8.127"))][..]
+                ),
+                (Some("Zurich"), &[(ip("8.8.8.128"), ip("8.8.8.200"))][..]),
+            ]
+        );
+        assert_eq!(records[0].record.max_netmask, 32);
+        assert!(records[0].record.provenance.is_some());
+
+        // The addresses that are not in the database are left out
+        let records = geo_ip
+            .get_records_in_range(ip("8.8.7.250"), ip("8.8.8.10"))
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.country, Country::UnitedStates);
+        assert_eq!(records[0].ranges, [(ip("8.8.8.0"), ip("8.8.8.10"))]);
+        assert_eq!(
+            geo_ip
+                .get_records_in_range(ip("9.0.0.0"), ip("9.255.255.255"))
+                .unwrap(),
+            []
+        );
+
+        // The whole address space
+        let records = geo_ip
+            .get_records_in_range(ip("0.0.0.0"), ip("255.255.255.255"))
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].ranges, [(ip("8.8.8.128"), ip("8.8.8.255"))]);
+        assert_eq!(records[1].record.netmask, 25);
+        assert_eq!(geo_ip.get_records_in_cidr("8.8.8.0/24").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_get_records_in_range_merges_and_groups() {
+        // The record of the /24 is shared by the networks around the /26 nested in it
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("10.0.0.0/24", RecordSpec::new(Country::UnitedStates))
+            .unwrap()
+            .insert("10.0.0.128/26", RecordSpec::new(Country::Japan))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+
+        let records = geo_ip.get_records_in_cidr("10.0.0.0/24").unwrap();
+        let countries: Vec<Country> = records.iter().map(|found| found.record.country).collect();
+        assert_eq!(countries, [Country::UnitedStates, Country::Japan]);
+        assert_eq!(
+            records[0].ranges,
+            [
+                (ip("10.0.0.0"), ip("10.0.0.127")),
+                (ip("10.0.0.192"), ip("10.0.0.255"))
+            ]
+        );
+        assert_eq!(records[1].ranges, [(ip("10.0.0.128"), ip("10.0.0.191"))]);
+
+        // The adjacent prefixes of a range off prefix boundaries are merged back
+        let records = geo_ip
+            .get_records_in_range(ip("10.0.0.3"), ip("10.0.0.100"))
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].ranges, [(ip("10.0.0.3"), ip("10.0.0.100"))]);
+
+        // Region databases
+        let database = region_database(
+            REGION_EDITION_REV1,
+            [CANADA_OFFSET + region_offset("ON"), 0],
+        );
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        let records = geo_ip
+            .get_records_in_range(ip("10.0.0.0"), ip("10.0.0.255"))
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.country, Country::Canada);
+        assert_eq!(records[0].record.region_code(), Some("ON"));
+    }
+
+    #[test]
+    fn test_get_records_in_range_errors() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.8.8.0/24", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+
+        assert!(matches!(
+            geo_ip.get_records_in_range(ip("8.8.8.9"), ip("8.8.8.0")),
+            Err(GeoIpReaderError::InvalidRange(RangeError::Reversed { .. }))
+        ));
+        assert!(matches!(
+            geo_ip.get_records_in_range(ip("8.8.8.0"), ip("::1")),
+            Err(GeoIpReaderError::InvalidRange(
+                RangeError::MixedFamilies { .. }
+            ))
+        ));
+        assert!(matches!(
+            geo_ip.get_records_in_range(ip("::"), ip("::1")),
+            Err(GeoIpReaderError::AddressFamilyMismatch(start)) if start == "::"
+        ));
+        assert!(matches!(
+            geo_ip.get_records_in_cidr("8.8.8.0-8.8.8.255"),
+            Err(GeoIpReaderError::InvalidRange(RangeError::Invalid(_)))
+        ));
+
+        let database = string_database(ASNUM_EDITION, "AS15169 Google LLC");
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert!(matches!(
+            geo_ip.get_records_in_range(ip("8.8.8.0"), ip("8.8.8.255")),
+            Err(GeoIpReaderError::InvalidDatabaseType)
+        ));
+    }
+
+    #[test]
+    fn test_get_records_in_range_ipv6() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("2001:db8::/32", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let database = builder.build();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+
+        let records = geo_ip
+            .get_records_in_range(ip("2001:db7:ffff::"), ip("2001:db8::ff"))
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.country, Country::Germany);
+        assert_eq!(records[0].ranges, [(ip("2001:db8::"), ip("2001:db8::ff"))]);
+        assert!(matches!(
+            geo_ip.get_records_in_range(ip("8.8.8.0"), ip("8.8.8.255")),
+            Err(GeoIpReaderError::AddressFamilyMismatch(_))
+        ));
+
+        // Widened IPv4 ranges are reported as IPv4 ranges
+        let mut mapped = GeoIpReader::<File>::new("v6").unwrap();
+        mapped.map_v4_into_v6 = true;
+        let records = mapped
+            .get_records_in_range(ip("108.95.4.100"), ip("108.95.4.110"))
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].record.country, Country::UnitedStates);
+        assert_eq!(
+            records[0].ranges,
+            [(ip("108.95.4.100"), ip("108.95.4.110"))]
+        );
+    }
+
+    #[test]
+    fn test_distinct_countries() {
+        let mut builder = DatabaseBuilder::new();
+        for (network, country) in [
+            ("8.8.8.0/24", Country::UnitedStates),
+            ("9.9.9.0/24", Country::Switzerland),
+            ("10.0.0.0/8", Country::UnitedStates),
+            ("11.0.0.0/16", Country::Japan),
+        ] {
+            builder.insert(network, RecordSpec::new(country)).unwrap();
+        }
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        assert_eq!(
+            geo_ip.distinct_countries().unwrap(),
+            HashSet::from([Country::UnitedStates, Country::Switzerland, Country::Japan])
+        );
+
+        let canada = CANADA_OFFSET + region_offset("ON");
+        let germany = WORLD_OFFSET + u32::from(Country::Germany.to_buffer()) * FIPS_RANGE + 5;
+        let database = region_database(REGION_EDITION_REV1, [canada, germany]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(
+            geo_ip.distinct_countries().unwrap(),
+            HashSet::from([Country::Canada, Country::Germany])
+        );
+
+        // Region databases point at an offset of 0 for the addresses that are not in them
+        let database = region_database(REGION_EDITION_REV1, [0, canada]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(
+            geo_ip.distinct_countries().unwrap(),
+            HashSet::from([Country::Canada])
+        );
+
+        let database = string_database(ASNUM_EDITION, "AS15169 Google LLC");
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert!(matches!(
+            geo_ip.distinct_countries(),
+            Err(GeoIpReaderError::InvalidDatabaseType)
+        ));
+    }
+
+    #[test]
+    fn test_region_edition_rev0() {
+        let germany = u32::from(Country::Germany.to_buffer());
+        let database = region_database(REGION_EDITION_REV0, [germany, 1000 + region_offset("NY")]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+        assert_eq!(geo_ip.edition_name(), "Region Edition, Rev 0");
+        assert_eq!(
+            geo_ip.find_record("10.0.0.1").unwrap(),
+            Some(Record::new(Country::Germany).with_netmask(1, 32))
+        );
+        assert_eq!(
+            geo_ip.find_record("200.0.0.1").unwrap(),
+            Some(
+                Record::new(Country::UnitedStates)
+                    .with_region_code("NY")
+                    .with_netmask(1, 32)
+            )
+        );
+
+        let database = region_database(REGION_EDITION_REV0, [0, 1000 + 26 * 26]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(geo_ip.find_record("10.0.0.1").unwrap(), None);
+        assert!(matches!(
+            geo_ip.find_record("200.0.0.1"),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_get_org() {
+        let database = string_database(ORG_EDITION, "Google LLC");
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+        assert_eq!(
+            geo_ip.get_org("200.0.0.1").unwrap(),
+            Some("Google LLC".into())
+        );
+        assert_eq!(geo_ip.get_org("10.0.0.1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_asn() {
+        let database = string_database(ASNUM_EDITION, "AS15169 Google LLC");
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+        let asn = geo_ip.get_asn("200.0.0.1").unwrap().unwrap();
+        assert_eq!(asn.number, 15169);
+        assert_eq!(asn.name, Some("Google LLC".into()));
+        assert_eq!(geo_ip.get_asn("10.0.0.1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_asn_with_city_database() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+        assert!(matches!(
+            geo_ip.get_asn("108.95.4.105"),
+            Err(GeoIpReaderError::InvalidDatabaseType)
+        ));
+    }
+
+    #[test]
+    fn test_same_block_with_same_network() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+        assert!(geo_ip.same_block("108.95.4.105", "108.95.4.106").unwrap());
+    }
+
+    #[test]
+    fn test_same_block_with_different_networks() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+        assert!(!geo_ip.same_block("108.95.4.105", "185.90.90.120").unwrap());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_find_record_span() {
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record as Values};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// A subscriber collecting the names and the fields of the spans.
+        struct Spans(Arc<Mutex<Vec<String>>>);
+
+        struct Fields<'a>(&'a mut Vec<String>);
+
+        impl Visit for Fields<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.push(format!("{}={}", field.name(), value));
+            }
+        }
+
+        impl Subscriber for Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut spans = self.0.lock().unwrap();
+                spans.push(span.metadata().name().to_string());
+                span.record(&mut Fields(&mut spans));
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, values: &Values<'_>) {
+                values.record(&mut Fields(&mut self.0.lock().unwrap()));
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        tracing::subscriber::with_default(Spans(Arc::clone(&spans)), || {
+            geo_ip.get_record("108.95.4.105").unwrap();
+            assert_eq!(geo_ip.find_record("10.0.0.1").unwrap(), None);
+        });
+
+        assert_eq!(
+            *spans.lock().unwrap(),
+            [
+                "find_record",
+                "ip=108.95.4.105",
+                "country=US",
+                "netmask=23",
+                "find_record",
+                "ip=10.0.0.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_country_index() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+        assert_eq!(record.country_index(), 225);
+        assert_eq!(
+            Country::from_buffer(record.country_index()),
+            Some(record.country)
+        );
+
+        // A record built rather than read takes the byte of its country
+        assert_eq!(
+            Record::new(Country::Poland).country_index(),
+            Country::Poland.to_buffer()
+        );
+    }
+
+    #[test]
+    fn test_same_block_with_different_families() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("0.0.0.0/8", RecordSpec::new(Country::France))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        assert!(geo_ip.same_block("0.0.0.1", "0.200.0.1").unwrap());
+
+        // The numbers of `0.0.0.1` and `::1` are equal, their blocks are not
+        assert!(!geo_ip.same_block("0.0.0.1", "::1").unwrap());
+        assert!(!geo_ip.same_block("::1", "0.0.0.1").unwrap());
+    }
+
+    /// A reader counting the bytes read through it.
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        read: u64,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_warm_reads_whole_database_and_restores_position() {
+        let mut path = home_dir().unwrap();
+        path.push("ipcap/geo_ip_city_v4.dat");
+        let database = std::fs::read(path).unwrap();
+        let size = database.len() as u64;
+        let mut geo_ip = GeoIpReader::from_reader(CountingReader {
+            inner: Cursor::new(database),
+            read: 0,
+        })
+        .unwrap();
+        let expected = geo_ip.find_record("108.95.4.105").unwrap();
+
+        geo_ip.fp.seek(SeekFrom::Start(42)).unwrap();
+        geo_ip.fp.read = 0;
+        #[allow(deprecated)]
+        geo_ip.warm().unwrap();
+
+        assert_eq!(geo_ip.fp.read, size);
+        assert_eq!(geo_ip.fp.stream_position().unwrap(), 42);
+        assert_eq!(geo_ip.find_record("108.95.4.105").unwrap(), expected);
+    }
+
+    struct FailingReader {
+        inner: Cursor<Vec<u8>>,
+        fail: bool,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.fail {
+                return Err(io::ErrorKind::PermissionDenied.into());
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_io_errors() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.8.8.0/24", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(FailingReader {
+            inner: Cursor::new(builder.build()),
+            fail: false,
+        })
+        .unwrap();
+        geo_ip.fp.fail = true;
+
+        let err = geo_ip.detect_database_type().unwrap_err();
+        assert!(matches!(&err, GeoIpReaderError::Io(source)
+            if source.kind() == io::ErrorKind::PermissionDenied));
+        assert!(std::error::Error::source(&err).is_some());
+        // A failed detection leaves the position and the layout unchanged
+        geo_ip.fp.seek(SeekFrom::Start(3)).unwrap();
+        let layout = (geo_ip.database_type, geo_ip.database_segments);
+        assert!(geo_ip.detect_database_type().is_err());
+        assert_eq!(geo_ip.fp.stream_position().unwrap(), 3);
+        assert_eq!((geo_ip.database_type, geo_ip.database_segments), layout);
+        assert!(matches!(
+            geo_ip.get_country(0x08080808),
+            Err(GeoIpReaderError::Io(_))
+        ));
+        // The reads of records and of the database info keep their cause too
+        let mut buffer = [0; 4];
+        assert!(matches!(
+            geo_ip.read_at(0, &mut buffer),
+            Err(GeoIpReaderError::Io(source)) if source.kind() == io::ErrorKind::PermissionDenied
+        ));
+        assert!(matches!(geo_ip.fingerprint(), Err(GeoIpReaderError::Io(_))));
+
+        // A node past the end of the database is still a corruption
+        geo_ip.fp.fail = false;
+        geo_ip.database_segments = u32::MAX;
+        assert!(matches!(
+            geo_ip.get_country(0x08080808),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_lat_lng_approx_eq() {
+        let point = LatLng {
+            latitude: 32.7977,
+            longitude: -117.2335,
+        };
+        // The same point, off by one unit in the last place
+        let next = |value: f64| f64::from_bits(value.to_bits() + 1);
+        let noisy = LatLng {
+            latitude: next(point.latitude),
+            longitude: next(point.longitude),
+        };
+
+        assert_ne!(point, noisy);
+        assert!(point.approx_eq(&noisy, 1e-9));
+        assert!(!noisy.approx_eq(&point, 0.0));
+        assert!(!point.approx_eq(
+            &LatLng {
+                latitude: 32.7978,
+                ..point
+            },
+            1e-5
+        ));
+        assert!(point.approx_eq(
+            &LatLng {
+                latitude: 32.7978,
+                ..point
+            },
+            1e-3
+        ));
+
+        // Across the antimeridian
+        let east = LatLng {
+            latitude: 0.0,
+            longitude: 179.9999,
+        };
+        let west = LatLng {
+            latitude: 0.0,
+            longitude: -179.9999,
+        };
+        assert!(east.approx_eq(&west, 1e-3));
+        assert!(!east.approx_eq(&west, 1e-5));
+
+        let nan = LatLng {
+            latitude: f64::NAN,
+            longitude: 0.0,
+        };
+        assert!(!nan.approx_eq(&nan, 1.0));
+    }
+
+    #[test]
+    fn test_lat_lng_round() {
+        let noisy = LatLng {
+            latitude: 0.1 + 0.2,
+            longitude: -117.23349999999999,
+        };
+
+        assert_eq!(
+            noisy.round(4),
+            LatLng {
+                latitude: 0.3,
+                longitude: -117.2335
+            }
+        );
+        assert_eq!(
+            noisy.round(0),
+            LatLng {
+                latitude: 0.0,
+                longitude: -117.0
+            }
+        );
+        // Rounding a rounded point changes nothing
+        assert_eq!(noisy.round(4).round(4), noisy.round(4));
+        assert_eq!(noisy.round(400), noisy);
+    }
+
+    #[test]
+    fn test_strict_coordinates() {
+        let mut builder = DatabaseBuilder::new();
+        let spec = |latitude, longitude| {
+            RecordSpec::new(Country::Ghana)
+                .city("Accra")
+                .coordinates(latitude, longitude)
+        };
+        builder
+            // Null island, then zeroed coordinates
+            .insert("1.0.0.0/8", spec(0.0, 0.0))
+            .unwrap()
+            .insert("2.0.0.0/8", RecordSpec::new(Country::Ghana))
+            .unwrap()
+            // Real coordinates, a few meters away from the origin or on the prime meridian
+            .insert("3.0.0.0/8", spec(0.0001, -0.0001))
+            .unwrap()
+            .insert("4.0.0.0/8", spec(5.6037, 0.0))
+            .unwrap();
+        let database = builder.build();
+
+        let mut lenient = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        let mut strict = GeoIpReaderBuilder::new()
+            .strict_coordinates(true)
+            .build(Cursor::new(database))
+            .unwrap();
+        for ip in ["1.2.3.4", "2.2.3.4"] {
+            let record = lenient.get_record(ip).unwrap();
+            assert!(!record.has_real_coordinates(), "{}", ip);
+            // Without real coordinates, nor a coordinate policy to give them some
+            assert_eq!(lenient.get_coordinates(ip).unwrap(), None);
+
+            assert!(matches!(
+                strict.find_record(ip),
+                Err(GeoIpReaderError::MissingCoordinates(input)) if input == ip
+            ));
+            assert!(matches!(
+                strict.get_coordinates(ip),
+                Err(GeoIpReaderError::MissingCoordinates(input)) if input == ip
+            ));
+        }
+        for ip in ["3.2.3.4", "4.2.3.4"] {
+            let record = strict.get_record(ip).unwrap();
+            assert!(record.has_real_coordinates(), "{}", ip);
+            assert_eq!(record, lenient.get_record(ip).unwrap());
+            assert!(strict.get_coordinates(ip).unwrap().unwrap().is_real());
+        }
+        // Addresses that are not in the database are not affected
+        assert_eq!(strict.find_record("10.0.0.1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_map_v4_into_v6() {
+        let mut v4 = GeoIpReader::<File>::new("v4").unwrap();
+        let mut v6 = GeoIpReader::<File>::new("v6").unwrap();
+        let path = GeoIpReader::<File>::database_path("v6").unwrap();
+        let mut mapped = GeoIpReaderBuilder::new()
+            .map_v4_into_v6(true)
+            .open(path)
+            .unwrap();
+
+        for ip in ["108.95.4.105", "185.90.90.120", "8.8.8.8", "1.1.1.1"] {
+            assert!(matches!(
+                v6.find_record(ip),
+                Err(GeoIpReaderError::AddressFamilyMismatch(input)) if input == ip
+            ));
+            assert!(matches!(
+                v6.contains(ip),
+                Err(GeoIpReaderError::AddressFamilyMismatch(_))
+            ));
+
+            // Found in the IPv4-mapped range, in the country of the v4 database
+            let record = mapped.get_record(ip).unwrap();
+            assert_eq!(record.country, v4.get_record(ip).unwrap().country, "{}", ip);
+            assert!(record.netmask > 96, "{}", ip);
+            assert_eq!(record.max_netmask, 128);
+            assert!(mapped.contains(ip).unwrap());
+            assert!(mapped.get_coordinates(ip).unwrap().is_some());
+
+            // The literal IPv4-mapped form is the same address, found without widening
+            let literal = format!("::ffff:{}", ip);
+            assert_eq!(v6.get_record(&literal).unwrap(), record, "{}", literal);
+            assert_eq!(mapped.get_record(&literal).unwrap(), record, "{}", literal);
+        }
+        assert_eq!(
+            mapped.get_record("108.95.4.105").unwrap().country,
+            Country::UnitedStates
+        );
+        assert!(mapped.same_block("108.95.4.105", "108.95.4.105").unwrap());
+
+        // IPv6 addresses and IPv4 databases are not affected
+        assert_eq!(
+            mapped.get_record("2001:4860:4860::8888").unwrap().country,
+            v6.get_record("2001:4860:4860::8888").unwrap().country
+        );
+        let mut v4_mapped = GeoIpReaderBuilder::new()
+            .map_v4_into_v6(true)
+            .open(GeoIpReader::<File>::database_path("v4").unwrap())
+            .unwrap();
+        assert_eq!(
+            v4_mapped.get_record("108.95.4.105").unwrap(),
+            v4.get_record("108.95.4.105").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_map_v4_into_v6_without_mapped_range() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("2001:4860::/32", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let database = builder.build();
+
+        let mut mismatched = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        assert!(matches!(
+            mismatched.get_record("8.8.8.8"),
+            Err(GeoIpReaderError::AddressFamilyMismatch(ip)) if ip == "8.8.8.8"
+        ));
+        let addr: IpAddr = "8.8.8.8".parse().unwrap();
+        assert!(matches!(
+            mismatched.get_records_interned([addr]).next(),
+            Some((_, Err(GeoIpReaderError::AddressFamilyMismatch(_))))
+        ));
+
+        // The widened address is looked up in the empty IPv4-mapped range
+        let mut mapped = GeoIpReaderBuilder::new()
+            .map_v4_into_v6(true)
+            .build(Cursor::new(database))
+            .unwrap();
+        assert_eq!(mapped.find_record("8.8.8.8").unwrap(), None);
+        assert!(!mapped.contains_addr(addr).unwrap());
+        assert_eq!(
+            mapped.get_record("2001:4860::1").unwrap().country,
+            Country::UnitedStates
+        );
+    }
+
+    #[test]
+    fn test_get_records_is_lazy() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.8.8.0/24", RecordSpec::new(Country::UnitedStates))
+            .unwrap()
+            .insert("9.9.9.0/24", RecordSpec::new(Country::Switzerland))
+            .unwrap();
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .octet_index(false)
+            .build(CountingReader {
+                inner: Cursor::new(builder.build()),
+                read: 0,
+            })
+            .unwrap();
+        let ips: Vec<IpAddr> = ["10.0.0.1", "8.8.8.8", "9.9.9.9"]
+            .map(|ip| ip.parse().unwrap())
+            .to_vec();
+
+        geo_ip.fp.read = 0;
+        geo_ip.get_record("10.0.0.1").unwrap_err();
+        let not_found = geo_ip.fp.read;
+
+        // Creating the results performs no lookup, the first error only the ones up to it
+        geo_ip.fp.read = 0;
+        let mut results = geo_ip.get_records(ips.clone());
+        assert!(matches!(
+            results.first_error(),
+            Some(GeoIpReaderError::AddressNotFound(ip)) if ip == "10.0.0.1"
+        ));
+        assert!(results.first_error().is_some());
+        drop(results);
+        assert_eq!(geo_ip.fp.read, not_found);
+
+        // The results are yielded in the order of the input
+        let results: Vec<_> = geo_ip.get_records(ips.clone()).into_iter().collect();
+        let found: Vec<_> = results
+            .iter()
+            .map(|(ip, result)| (*ip, result.as_ref().ok().map(|record| record.country)))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (ips[0], None),
+                (ips[1], Some(Country::UnitedStates)),
+                (ips[2], Some(Country::Switzerland))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_records_interned() {
+        let mut builder = DatabaseBuilder::new();
+        for network in ["8.8.8.0/24", "8.8.4.0/24"] {
+            builder
+                .insert(
+                    network,
+                    RecordSpec::new(Country::UnitedStates)
+                        .region_code("CA")
+                        .city("Mountain View")
+                        .coordinates(37.3845, -122.0881),
+                )
+                .unwrap();
+        }
+        builder
+            .insert("9.9.9.0/24", RecordSpec::new(Country::Switzerland))
+            .unwrap();
+        let database = builder.build();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        let ips: Vec<IpAddr> = ["8.8.8.8", "8.8.4.4", "9.9.9.9", "10.0.0.1"]
+            .map(|ip| ip.parse().unwrap())
+            .to_vec();
+
+        // The same records as `get_records`, the strings of the same city being shared
+        let expected: Vec<_> = geo_ip.get_records(ips.clone()).into_iter().collect();
+        let results: Vec<_> = geo_ip.get_records_interned(ips.clone()).collect();
+        for ((ip, expected), (interned_ip, result)) in expected.iter().zip(&results) {
+            assert_eq!(ip, interned_ip);
+            match (expected, result) {
+                (Ok(expected), Ok(record)) => {
+                    let mut interner = StringInterner::new();
+                    let expected = InternedRecord::from_record(expected.clone(), &mut interner);
+                    assert_eq!(record, &expected);
+                }
+                (Err(expected), Err(error)) => assert_eq!(error.to_string(), expected.to_string()),
+                _ => panic!("{}: {:?} != {:?}", ip, result, expected),
+            }
+        }
+        let city = |index: usize| results[index].1.as_ref().unwrap().city.clone().unwrap();
+        assert!(Arc::ptr_eq(&city(0), &city(1)));
+
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .strict_coordinates(true)
+            .build(Cursor::new(database))
+            .unwrap();
+        let results: Vec<_> = geo_ip.get_records_interned(ips).map(|(_, r)| r).collect();
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            &results[2],
+            Err(GeoIpReaderError::MissingCoordinates(ip)) if ip == "9.9.9.9"
+        ));
+
+        let canada = CANADA_OFFSET + region_offset("ON");
+        let database = region_database(REGION_EDITION_REV1, [0, canada]);
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        let ips = ["200.0.0.1", "10.0.0.1"].map(|ip| ip.parse::<IpAddr>().unwrap());
+        let results: Vec<_> = geo_ip.get_records_interned(ips).map(|(_, r)| r).collect();
+        let record = results[0].as_ref().unwrap();
+        assert_eq!(
+            (record.country, record.region_code(), record.netmask),
+            (Country::Canada, Some("ON"), 1)
+        );
+        assert!(matches!(
+            &results[1],
+            Err(GeoIpReaderError::AddressNotFound(ip)) if ip == "10.0.0.1"
+        ));
+    }
+
+    #[test]
+    fn test_octet_index() {
+        let mut path = home_dir().unwrap();
+        path.push("ipcap/geo_ip_city_v4.dat");
+        let mut in_memory =
+            GeoIpReader::from_reader(Cursor::new(std::fs::read(&path).unwrap())).unwrap();
+        let mut from_file = GeoIpReaderBuilder::new().open(&path).unwrap();
+
+        // Only the reader in memory indexes the first octets, with the same lookups
+        let step = u32::MAX / 5000;
+        for ip_number in (0..5000).map(|i| u128::from(i * step)) {
+            assert_eq!(
+                in_memory.get_country(ip_number).unwrap(),
+                from_file.get_country(ip_number).unwrap(),
+                "{}",
+                ip_number
+            );
+            assert_eq!(in_memory.netmask(), from_file.netmask());
+        }
+        assert!(in_memory.octet_index.is_some());
+        assert!(from_file.octet_index.is_none());
+
+        let mut unindexed = GeoIpReaderBuilder::new()
+            .octet_index(false)
+            .build(Cursor::new(std::fs::read(&path).unwrap()))
+            .unwrap();
+        unindexed.get_country(16777216).unwrap();
+        assert!(unindexed.octet_index.is_none());
+
+        assert_eq!(
+            in_memory.get_record("108.95.4.105").unwrap(),
+            from_file.get_record("108.95.4.105").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_build_with_layout() {
+        let database = std::fs::read(GeoIpReader::<File>::database_path("v4").unwrap()).unwrap();
+        let mut detected = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        let layout = detected.detected_layout();
+
+        // The readers built with the layout share its octet index instead of building their own
+        let mut reader = GeoIpReaderBuilder::new().build_with_layout(&database, &layout);
+        assert!(Arc::ptr_eq(
+            reader.octet_index.as_ref().unwrap(),
+            layout.octet_index.as_ref().unwrap()
+        ));
+        assert_eq!(reader.build_date, detected.build_date);
+        for ip in ["108.95.4.105", "185.90.90.120", "8.8.8.8", "1.1.1.1"] {
+            assert_eq!(
+                reader.get_record(ip).unwrap(),
+                detected.get_record(ip).unwrap()
+            );
+        }
+        assert_eq!(reader.find_record("10.0.0.1").unwrap(), None);
+
+        let unindexed = GeoIpReaderBuilder::new()
+            .octet_index(false)
+            .build_with_layout(&database, &layout);
+        assert!(unindexed.octet_index.is_none());
+    }
+
+    /// A reader over the v4 database, counting the bytes read, taken to be opened from its file.
+    fn counting_reader() -> GeoIpReader<CountingReader> {
+        let mut path = home_dir().unwrap();
+        path.push("ipcap/geo_ip_city_v4.dat");
+        let database = std::fs::read(&path).unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(CountingReader {
+            inner: Cursor::new(database),
+            read: 0,
+        })
+        .unwrap();
+        geo_ip.source = SourceKind::File {
+            path: Arc::from(path),
+        };
+        geo_ip.fp.read = 0;
+        geo_ip
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let mut geo_ip = counting_reader();
+        let size = geo_ip.fp.inner.get_ref().len() as u64;
+        let expected = geo_ip.find_record("108.95.4.105").unwrap();
+
+        geo_ip.fp.seek(SeekFrom::Start(42)).unwrap();
+        geo_ip.fp.read = 0;
+        let report = geo_ip.warm_up(Duration::from_secs(60)).unwrap();
+
+        assert_eq!(report.bytes_touched, size);
+        assert_eq!(report.total_bytes, size);
+        assert!(!report.already_resident);
+        assert!(report.is_complete());
+        assert_eq!(geo_ip.fp.read, size);
+        assert_eq!(geo_ip.fp.stream_position().unwrap(), 42);
+        assert_eq!(geo_ip.find_record("108.95.4.105").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_warm_up_budget() {
+        let mut geo_ip = counting_reader();
+
+        let report = geo_ip.warm_up(Duration::ZERO).unwrap();
+
+        assert_eq!(report.bytes_touched, 0);
+        assert!(report.total_bytes > 0);
+        assert!(!report.is_complete());
+        assert_eq!(geo_ip.fp.read, 0);
+    }
+
+    #[test]
+    fn test_warm_up_index_only() {
+        let mut geo_ip = counting_reader();
+        let tree_size = 2 * geo_ip.record_length as u64 * u64::from(geo_ip.database_segments);
+
+        let report = geo_ip.warm_up_index_only(Duration::from_secs(60)).unwrap();
+
+        assert_eq!(report.total_bytes, tree_size);
+        assert_eq!(report.bytes_touched, tree_size);
+        assert_eq!(geo_ip.fp.read, tree_size);
+        assert!(tree_size < geo_ip.fp.inner.get_ref().len() as u64);
+    }
+
+    #[test]
+    fn test_warm_up_in_memory() {
+        let database = city_database(CITY_EDITION_REV1);
+        let mut geo_ip = GeoIpReaderBuilder::new()
+            .build_in_memory(database.clone())
+            .unwrap();
+
+        let report = geo_ip.warm_up(Duration::from_secs(60)).unwrap();
+
+        assert!(report.already_resident);
+        assert!(report.is_complete());
+        assert_eq!(report.bytes_touched, 0);
+        assert_eq!(report.total_bytes, database.len() as u64);
+
+        // Any other reader is read, whether or not it was opened from a path
+        let mut geo_ip = GeoIpReader::from_reader(CountingReader {
+            inner: Cursor::new(database.clone()),
+            read: 0,
+        })
+        .unwrap();
+        geo_ip.fp.read = 0;
+
+        let report = geo_ip.warm_up(Duration::from_secs(60)).unwrap();
+
+        assert!(!report.already_resident);
+        assert_eq!(report.bytes_touched, database.len() as u64);
+        assert_eq!(geo_ip.fp.read, database.len() as u64);
+    }
 
-        let expected_value = Record {
-            dma: Some(DesignatedMarketArea(825858)),
-            postal_code: Some("92109".into()),
-            country: Country::UnitedStates,
-            region_code: Some("CA".into()),
-            city: Some("San Diego".into()),
-            latitude: 32.79769999999999,
-            longitude: -117.23349999999999,
-            time_zone: "America/Los_Angeles",
-        };
+    #[test]
+    fn test_record_new_matches_lookup() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
 
-        assert_eq!(record, expected_value);
+        let record = Record::new(Country::UnitedStates)
+            .with_dma(DesignatedMarketArea(825858))
+            .with_postal_code("92109")
+            .with_region_code("CA")
+            .with_city("San Diego")
+            .with_coordinates(32.79769999999999, -117.23349999999999)
+            .with_netmask(23, 32);
+
+        assert_eq!(record, geo_ip.get_record("108.95.4.105").unwrap());
+    }
+
+    #[test]
+    fn test_location_confidence() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+        assert_eq!((record.netmask, record.max_netmask), (23, 32));
+        assert_eq!(record.location_confidence(), 23.0 / 32.0);
+
+        let mut geo_ip = GeoIpReader::<File>::new("v6").unwrap();
+        let record = geo_ip.get_record("2001:4860:4860::8888").unwrap();
+        assert_eq!(record.max_netmask, 128);
+        assert_eq!(
+            record.location_confidence(),
+            geo_ip.netmask() as f32 / 128.0
+        );
+
+        let record = Record::new(Country::Germany);
+        assert_eq!(record.location_confidence(), 0.0);
+        assert_eq!(record.with_netmask(32, 32).location_confidence(), 1.0);
+    }
+
+    #[test]
+    fn test_city_without_region() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert(
+                "8.8.8.0/24",
+                RecordSpec::new(Country::Switzerland).city("Bern"),
+            )
+            .unwrap();
+        builder
+            .insert(
+                "9.9.9.0/24",
+                RecordSpec::new(Country::UnitedStates).city("Springfield"),
+            )
+            .unwrap();
+        let mut reader = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+
+        // A country of a single zone resolves it without the region
+        let record = reader.get_record("8.8.8.8").unwrap();
+        assert_eq!((record.city(), record.region_code()), (Some("Bern"), None));
+        assert_eq!(record.time_zone, "Europe/Zurich");
+        assert_eq!(record.accuracy(), Accuracy::City);
+        assert_eq!(record.location_parts(), ["Bern", "Switzerland"]);
+
+        // A country of several zones leaves it empty rather than guessing
+        let record = reader.get_record("9.9.9.9").unwrap();
+        assert_eq!(record.region_code(), None);
+        assert_eq!(record.time_zone, "");
+        assert_eq!(record.accuracy(), Accuracy::City);
+        assert_eq!(record.location_parts(), ["Springfield", "United States"]);
+
+        // The synthetic records agree with the decoded ones
+        let record = Record::new(Country::UnitedStates).with_city("Springfield");
+        assert_eq!((record.time_zone, record.accuracy()), ("", Accuracy::City));
+        let record = Record::new(Country::Switzerland).with_city("Bern");
+        assert_eq!(record.time_zone, "Europe/Zurich");
+    }
+
+    #[test]
+    fn test_region_codes() {
+        let record = Record::new(Country::UnitedStates).with_region_code("TX");
+        assert_eq!(
+            record.region_codes(),
+            RegionCodes {
+                fips: Some("TX".into()),
+                iso: Some("US-TX".into()),
+            }
+        );
+        assert_eq!(record.region_code(), record.region_codes().fips.as_deref());
+
+        // A code without a known conversion only has its database form
+        let record = Record::new(Country::Poland).with_region_code("72");
+        assert_eq!(record.region_codes().fips.as_deref(), Some("72"));
+        assert_eq!(record.region_codes().iso, None);
+
+        assert_eq!(
+            Record::new(Country::UnitedStates).region_codes(),
+            RegionCodes::default()
+        );
+    }
+
+    #[test]
+    fn test_accuracy() {
+        let record = Record::new(Country::UnitedStates);
+        assert_eq!(record.accuracy(), Accuracy::Country);
+        let record = record.with_region_code("CA");
+        assert_eq!(record.accuracy(), Accuracy::Region);
+        let record = record.with_city("San Diego");
+        assert_eq!(record.accuracy(), Accuracy::City);
+
+        // An empty city is no city
+        let record = Record::new(Country::UnitedStates).with_city("");
+        assert_eq!(record.accuracy(), Accuracy::Country);
+        assert!(Accuracy::Country < Accuracy::Region && Accuracy::Region < Accuracy::City);
+    }
+
+    #[test]
+    fn test_location_parts() {
+        let records = [
+            Record::new(Country::UnitedStates),
+            Record::new(Country::UnitedStates).with_region_code("CA"),
+            Record::new(Country::UnitedStates).with_city("San Diego"),
+            Record::new(Country::UnitedStates)
+                .with_city("")
+                .with_region_code(""),
+            Record::new(Country::UnitedStates)
+                .with_region_code("CA")
+                .with_city("San Diego"),
+        ];
+        for record in &records {
+            let location = record.location_parts().join(", ");
+            assert!(!location.contains(", ,"), "{:?}", location);
+            assert!(!location.starts_with(','), "{:?}", location);
+            assert!(!location.ends_with(", "), "{:?}", location);
+        }
+
+        let locations: Vec<String> = records
+            .iter()
+            .map(|record| record.location_parts().join(", "))
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                "United States",
+                "CA, United States",
+                "San Diego, United States",
+                "United States",
+                "San Diego, CA, United States",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_new_defaults() {
+        let record = Record::new(Country::UnitedKingdom);
+
+        assert_eq!(record.dma, None);
+        assert_eq!(record.city(), None);
+        assert_eq!(record.time_zone, "Europe/London");
+        assert_eq!(record.with_time_zone("UTC").time_zone, "UTC");
+    }
+
+    #[test]
+    fn test_record_accessors_without_values() {
+        let mut geo_ip = GeoIpReader::<File>::new("v6").unwrap();
+        let record = geo_ip.get_record("2a08:1450:300f:900::1003").unwrap();
+
+        assert_eq!(record.postal_code(), None);
+        assert_eq!(record.city(), None);
+        assert_eq!(record.region_code(), None);
+    }
+
+    #[test]
+    fn test_fallible_lookups_with_invalid_ip() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+        assert!(matches!(
+            geo_ip.find_record("108.95.4"),
+            Err(GeoIpReaderError::InvalidAddress { input, .. }) if input == "108.95.4"
+        ));
+        assert!(matches!(
+            geo_ip.same_block("108.95.4.105", "-"),
+            Err(GeoIpReaderError::InvalidAddress { input, .. }) if input == "-"
+        ));
     }
 
     #[test]
-    #[should_panic(expected = "Invalid IP address")]
     fn test_get_record_with_invalid_ip() {
         let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-        let _record = geo_ip.get_record("-");
 
-        todo!("Error handling")
+        let err = geo_ip.get_record("-").unwrap_err();
+        assert!(err.to_string().starts_with("Invalid IP address \"-\""));
+    }
+
+    #[test]
+    fn test_lookups_with_empty_ip() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let is_invalid = |result: Result<_, GeoIpReaderError>, ip: &str| matches!(result, Err(GeoIpReaderError::InvalidAddress { input, .. }) if input == ip);
+
+        for ip in ["", " ", " \t\n"] {
+            assert!(is_invalid(geo_ip.get_record(ip).map(|_| ()), ip));
+            assert!(is_invalid(geo_ip.find_record(ip).map(|_| ()), ip));
+            assert!(is_invalid(geo_ip.get_coordinates(ip).map(|_| ()), ip));
+            assert!(is_invalid(
+                geo_ip.get_time_zone_given_ip_addr(ip).map(|_| ()),
+                ip
+            ));
+            assert!(is_invalid(geo_ip.contains(ip).map(|_| ()), ip));
+            assert!(is_invalid(
+                geo_ip.same_block(ip, "108.95.4.105").map(|_| ()),
+                ip
+            ));
+        }
+        assert_eq!(geo_ip.analyze(["", " "]).errored, 2);
+
+        let database = string_database(ORG_EDITION, "Google LLC");
+        let mut org = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert!(is_invalid(org.get_org("").map(|_| ()), ""));
+        let database = string_database(ASNUM_EDITION, "AS15169 Google LLC");
+        let mut asn = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert!(is_invalid(asn.get_asn("").map(|_| ()), ""));
+    }
+
+    #[test]
+    fn test_get_record_out_of_range_country() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.8.8.0/24", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let mut database = builder.build();
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        let position = reader
+            .record_position(try_ip_to_number("8.8.8.8").unwrap())
+            .unwrap()
+            .unwrap();
+
+        for byte in [0, u8::MAX] {
+            database[position as usize] = byte;
+            let mut reader = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+
+            assert!(matches!(
+                reader.get_record("8.8.8.8"),
+                Err(GeoIpReaderError::CorruptDatabase)
+            ));
+            assert!(matches!(
+                reader.find_record("8.8.8.8"),
+                Err(GeoIpReaderError::CorruptDatabase)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_detect_database_type_skips_invalid_headers() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.8.8.0/24", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let database = builder.build();
+        let reader = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+        let segments = reader.database_segments;
+
+        // Delimiters followed by an unknown edition, and by a tree larger than the file
+        for junk in [
+            &[255, 255, 255, 200][..],
+            &[255, 255, 255, 2, 255, 255, 127],
+        ] {
+            let mut database = database.clone();
+            database.extend(junk);
+            let mut reader = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+            assert_eq!(reader.database_type, CITY_EDITION_REV1);
+            assert_eq!(reader.database_segments, segments);
+            assert_eq!(
+                reader.get_record("8.8.8.8").unwrap().country,
+                Country::UnitedStates
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_record_with_invalid_bytes() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert(
+                "8.8.8.0/24",
+                RecordSpec::new(Country::Switzerland)
+                    .city("Z####ürich")
+                    .postal_code("8001"),
+            )
+            .unwrap();
+        let mut database = builder.build();
+
+        // Embed a run of delimiter bytes in the city of the last record
+        let start = database
+            .windows(4)
+            .position(|window| window == b"####")
+            .unwrap();
+        database[start..start + 4].copy_from_slice(&[255; 4]);
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(reader.database_type, CITY_EDITION_REV1);
+
+        let record = reader.get_record("8.8.8.8").unwrap();
+        assert_eq!(record.city(), Some("Zürich"));
+        assert_eq!(record.postal_code(), Some("8001"));
+        assert_eq!(
+            record.warnings,
+            [RecordWarning::InvalidBytes {
+                field: "city",
+                count: 4
+            }]
+        );
+        assert_eq!(
+            record.warnings[0].to_string(),
+            "dropped 4 invalid bytes from the city"
+        );
+
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        assert!(geo_ip
+            .get_record("108.95.4.105")
+            .unwrap()
+            .warnings
+            .is_empty());
+    }
+
+    #[test]
+    fn test_from_reader_at() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert(
+                "8.8.8.0/24",
+                RecordSpec::new(Country::UnitedStates).city("Mountain View"),
+            )
+            .unwrap();
+        let database = builder.build();
+
+        // Surround the database with bytes that would be taken for a region database trailer
+        let mut file = vec![255; 1000];
+        file.extend(&database);
+        file.extend(&region_database(REGION_EDITION_REV1, [0, 0]));
+        let offset = 1000;
+        let len = database.len() as u64;
+
+        let mut reader =
+            GeoIpReader::from_reader_at(Cursor::new(file.clone()), offset, len, "v4").unwrap();
+        assert_eq!(reader.database_type, CITY_EDITION_REV1);
+        assert_eq!(
+            reader.get_record("8.8.8.8").unwrap().city(),
+            Some("Mountain View")
+        );
+        assert_eq!(reader.find_record("10.0.0.1").unwrap(), None);
+
+        assert!(matches!(
+            GeoIpReader::from_reader_at(Cursor::new(file.clone()), offset, len, "v6"),
+            Err(GeoIpReaderError::InvalidDatabaseType)
+        ));
+        assert!(matches!(
+            GeoIpReader::from_reader_at(Cursor::new(file.clone()), offset, len, "v5"),
+            Err(GeoIpReaderError::InvalidDatabaseType)
+        ));
+        assert!(matches!(
+            GeoIpReader::from_reader_at(Cursor::new(file.clone()), offset, file.len() as u64, "v4"),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_database_slice() {
+        let mut slice = DatabaseSlice::new(Cursor::new(b"abcdefgh".to_vec()), 2, 4).unwrap();
+        let mut bytes = Vec::new();
+
+        slice.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, b"cdef");
+        assert_eq!(slice.seek(SeekFrom::End(-1)).unwrap(), 3);
+        assert_eq!(slice.read(&mut [0; 4]).unwrap(), 1);
+        assert_eq!(slice.seek(SeekFrom::Current(-3)).unwrap(), 1);
+        assert_eq!(slice.stream_position().unwrap(), 1);
+        assert!(slice.seek(SeekFrom::Current(-2)).is_err());
+        assert_eq!(slice.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(slice.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(slice.into_inner().into_inner(), b"abcdefgh");
+    }
+
+    #[test]
+    fn test_get_record_not_found() {
+        let mut v4 = GeoIpReader::<File>::new("v4").unwrap();
+        for ip in ["10.0.0.1", "127.0.0.1", "192.168.1.1"] {
+            assert!(matches!(
+                v4.get_record(ip),
+                Err(GeoIpReaderError::AddressNotFound(ref addr)) if addr == ip
+            ));
+        }
+
+        let mut v6 = GeoIpReader::<File>::new("v6").unwrap();
+        assert!(matches!(
+            v6.get_record("fc00::1"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+
+        let database = region_database(REGION_EDITION_REV1, [0, 0]);
+        let mut region = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert!(matches!(
+            region.get_record("10.0.0.1"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+    }
+
+    /// A xorshift generator, enough to draw reproducible addresses without a dependency.
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0.wrapping_shl(13);
+            self.0 ^= self.0.wrapping_shr(7);
+            self.0 ^= self.0.wrapping_shl(17);
+            self.0
+        }
+
+        fn next_u128(&mut self) -> u128 {
+            u128::from(self.next()).wrapping_shl(64) | u128::from(self.next())
+        }
+    }
+
+    /// The reference conversion of `utils::try_ip_to_number`, forcing wrapping arithmetic.
+    fn reference_ip_number(ip: IpAddr) -> u128 {
+        match ip {
+            IpAddr::V4(ip) => u128::from(u32::from(ip)),
+            IpAddr::V6(ip) => ip.segments().iter().fold(0u128, |number, &segment| {
+                number.wrapping_shl(16) | u128::from(segment)
+            }),
+        }
+    }
+
+    /// The reference lookup over the raw bytes of a database built by `DatabaseBuilder`,
+    /// forcing wrapping arithmetic whatever the build profile.
+    fn reference_lookup(database: &[u8], ip_number: u128) -> Option<Record<'static>> {
+        let le = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .enumerate()
+                .fold(Wrapping(0u32), |value, (j, &byte)| {
+                    value + Wrapping(u32::from(byte).wrapping_shl(8u32.wrapping_mul(j as u32)))
+                })
+        };
+        let segments = le(&database[database.len().wrapping_sub(3)..]).0;
+        let record_length = STANDARD_RECORD_LENGTH;
+        let seek_depth = if ip_number.to_string().len() > 10 {
+            127
+        } else {
+            31
+        };
+
+        let mut offset = Wrapping(0usize);
+        for (netmask, depth) in (1..).zip((0..=seek_depth).rev()) {
+            let bit = (ip_number.wrapping_shr(depth) & 1) as usize;
+            let start = (Wrapping(2 * record_length) * offset + Wrapping(bit * record_length)).0;
+            let pointer = le(&database[start..start.wrapping_add(record_length)]).0;
+            if pointer < segments {
+                offset = Wrapping(pointer as usize);
+                continue;
+            }
+            if pointer == segments {
+                return None;
+            }
+
+            let position = (Wrapping(u64::from(pointer))
+                + Wrapping(2 * record_length as u64 - 1) * Wrapping(u64::from(segments)))
+            .0 as usize;
+            let buffer = &database[position..position.wrapping_add(FULL_RECORD_LENGTH)];
+
+            let country = Country::from_buffer(buffer[0]).unwrap();
+            let mut parts = buffer[1..].splitn(4, |&byte| byte == 0);
+            let mut string = || {
+                let part = parts.next().unwrap();
+                (!part.is_empty()).then(|| String::from_utf8_lossy(part).into())
+            };
+            let (region_code, city, postal_code): (Option<Box<str>>, _, _) =
+                (string(), string(), string());
+            let tail = parts.next().unwrap();
+
+            let coordinate = |bytes: &[u8]| f64::from(le(bytes).0) / 10000.0 - 180.0;
+            let dma =
+                (country == Country::UnitedStates).then(|| DesignatedMarketArea(le(&tail[6..9]).0));
+            let time_zone = resolve_time_zone(country.alphabetic_code_2(), region_code.as_deref())
+                .unwrap_or_default();
+
+            return Some(Record {
+                dma,
+                postal_code,
+                country,
+                region_code,
+                city,
+                latitude: coordinate(&tail[..3]),
+                longitude: coordinate(&tail[3..6]),
+                time_zone,
+                netmask,
+                max_netmask: seek_depth as usize + 1,
+                warnings: Vec::new(),
+                gaps: Vec::new(),
+                provenance: None,
+                country_byte: country.to_buffer(),
+            });
+        }
+        None
+    }
+
+    /// Builds a database of random ranges of at most `max_prefix` bits and returns it with the
+    /// networks of these ranges.
+    fn random_database(rng: &mut Xorshift, ipv6: bool, max_prefix: u64) -> (Vec<u8>, Vec<u128>) {
+        let cities = ["San Diego", "Sydney", "Łódź", "Zürich", ""];
+        let mut builder = DatabaseBuilder::new();
+        let mut networks = Vec::new();
+
+        for _ in 0..300 {
+            let network = rng.next_u128();
+            let prefix = rng.next() % max_prefix + 1;
+            let cidr = if ipv6 {
+                format!("{}/{}", std::net::Ipv6Addr::from(network), prefix)
+            } else {
+                format!("{}/{}", std::net::Ipv4Addr::from(network as u32), prefix)
+            };
+
+            let country = Country::from_buffer((rng.next() % 250) as u8 + 1).unwrap();
+            let mut spec = RecordSpec::new(country).coordinates(
+                (rng.next() % 180_000) as f64 / 1000.0 - 90.0,
+                (rng.next() % 360_000) as f64 / 1000.0 - 180.0,
+            );
+            let city = cities[(rng.next() % cities.len() as u64) as usize];
+            if !city.is_empty() {
+                spec = spec.city(city).region_code("CA").postal_code("92109");
+            }
+            if country == Country::UnitedStates {
+                spec = spec.dma(DesignatedMarketArea((rng.next() % 1_000_000) as u32));
+            }
+
+            builder.insert(&cidr, spec).unwrap();
+            networks.push(network);
+        }
+
+        (builder.build(), networks)
+    }
+
+    fn assert_matches_reference(ipv6: bool, max_prefix: u64) {
+        let mut rng = Xorshift(0x9E37_79B9_7F4A_7C15);
+        let (database, networks) = random_database(&mut rng, ipv6, max_prefix);
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database.clone())).unwrap();
+
+        let mut found = 0;
+        for i in 0..4000 {
+            // Draw half of the addresses next to an inserted network to reach its leaves
+            let mut number = rng.next_u128();
+            if i % 2 == 0 {
+                let network = networks[(rng.next() % networks.len() as u64) as usize];
+                number = network ^ (number & u128::from(u16::MAX));
+            }
+            let ip = if ipv6 {
+                IpAddr::V6(number.into())
+            } else {
+                IpAddr::V4((number as u32).into())
+            };
+
+            let ip_number = try_ip_to_number(&ip.to_string()).unwrap();
+            assert_eq!(ip_number, reference_ip_number(ip), "{}", ip);
+
+            let record = reader.find_record(&ip.to_string()).unwrap();
+            assert_eq!(record, reference_lookup(&database, ip_number), "{}", ip);
+            found += record.is_some() as usize;
+        }
+
+        assert!(found > 1000, "only {} addresses found", found);
+    }
+
+    #[test]
+    fn test_v4_lookups_match_wrapping_reference() {
+        assert_matches_reference(false, 32);
+    }
+
+    #[test]
+    fn test_v6_lookups_match_wrapping_reference() {
+        assert_matches_reference(true, 32);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut v4 = GeoIpReader::<File>::new("v4").unwrap();
+        assert!(v4.contains("108.95.4.105").unwrap());
+        for bogon in ["10.0.0.1", "127.0.0.1", "192.168.1.1", "0.0.0.0"] {
+            assert!(!v4.contains(bogon).unwrap(), "{}", bogon);
+        }
+        assert!(matches!(
+            v4.contains("banana"),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+
+        let addrs = ["108.95.4.105", "10.0.0.1"].map(|ip| ip.parse().unwrap());
+        assert_eq!(v4.contains_batch(&addrs).unwrap(), vec![true, false]);
+
+        let mut v6 = GeoIpReader::<File>::new("v6").unwrap();
+        assert!(v6
+            .contains_addr("2a08:1450:300f:900::1003".parse().unwrap())
+            .unwrap());
+        assert!(!v6.contains("fc00::1").unwrap());
+    }
+
+    #[test]
+    fn test_contains_agrees_with_find_record() {
+        for ipv6 in [false, true] {
+            // Long prefixes only, so that a good part of the address space is not covered
+            let mut rng = Xorshift(0x2545_F491_4F6C_DD1D);
+            let mut builder = DatabaseBuilder::new();
+            let mut networks = Vec::new();
+            for _ in 0..100 {
+                let network = rng.next_u128();
+                let cidr = if ipv6 {
+                    format!(
+                        "{}/{}",
+                        std::net::Ipv6Addr::from(network),
+                        16 + rng.next() % 32
+                    )
+                } else {
+                    format!(
+                        "{}/{}",
+                        std::net::Ipv4Addr::from(network as u32),
+                        8 + rng.next() % 16
+                    )
+                };
+                builder
+                    .insert(&cidr, RecordSpec::new(Country::Germany))
+                    .unwrap();
+                networks.push(network);
+            }
+            let mut reader = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+
+            let (mut found, mut missing) = (0, 0);
+            for i in 0..2000 {
+                let mut number = rng.next_u128();
+                if i % 2 == 0 {
+                    let network = networks[(rng.next() % networks.len() as u64) as usize];
+                    number = network ^ (number & u128::from(u16::MAX));
+                }
+                let ip = if ipv6 {
+                    IpAddr::V6(number.into())
+                } else {
+                    IpAddr::V4((number as u32).into())
+                };
+
+                let contains = reader.contains_addr(ip).unwrap();
+                let netmask = reader.netmask();
+                let record = reader.find_record(&ip.to_string()).unwrap();
+                assert_eq!(contains, record.is_some(), "{}", ip);
+                assert_eq!(netmask, reader.netmask(), "{}", ip);
+
+                if contains {
+                    found += 1;
+                } else {
+                    missing += 1;
+                }
+            }
+
+            assert!(
+                found > 100 && missing > 100,
+                "{} found, {} missing",
+                found,
+                missing
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_coordinates() {
+        let mut v4 = GeoIpReader::<File>::new("v4").unwrap();
+        for ip in ["108.95.4.105", "185.90.90.120", "8.8.8.8", "1.0.0.1"] {
+            let record = v4.get_record(ip).unwrap();
+            assert_eq!(
+                v4.get_coordinates(ip).unwrap(),
+                Some(LatLng {
+                    latitude: record.latitude,
+                    longitude: record.longitude
+                }),
+                "{}",
+                ip
+            );
+        }
+        assert_eq!(v4.get_coordinates("10.0.0.1").unwrap(), None);
+        assert!(matches!(
+            v4.get_coordinates("banana"),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+
+        let mut v6 = GeoIpReader::<File>::new("v6").unwrap();
+        assert_eq!(
+            v6.get_coordinates("2a08:1450:300f:900::1003").unwrap(),
+            Some(LatLng {
+                latitude: 54.0,
+                longitude: -2.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_coordinates_matches_random_records() {
+        let mut rng = Xorshift(0xD1B5_4A32_D192_ED03);
+        let (database, networks) = random_database(&mut rng, false, 32);
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+        for network in networks {
+            let ip = std::net::Ipv4Addr::from(network as u32).to_string();
+            let coordinates = reader.get_coordinates(&ip).unwrap();
+            let record = reader.find_record(&ip).unwrap();
+            assert_eq!(
+                coordinates,
+                record.map(|record| LatLng {
+                    latitude: record.latitude,
+                    longitude: record.longitude
+                }),
+                "{}",
+                ip
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_coordinates_without_coordinates() {
+        let database = string_database(ORG_EDITION, "Google LLC");
+        let mut org = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(org.get_coordinates("200.0.0.1").unwrap(), None);
+
+        let database = region_database(REGION_EDITION_REV1, [0, US_OFFSET]);
+        let mut region = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert_eq!(region.get_coordinates("200.0.0.1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_geohash() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+        assert_eq!(record.geohash(12).as_deref(), Some("9muds69hmqqt"));
+        assert_eq!(record.geohash(1).as_deref(), Some("9"));
+        assert_eq!(record.geohash(0).as_deref(), Some(""));
+
+        // The reference example of the geohash format, and points on the prime meridian and
+        // the equator
+        let record = |latitude, longitude| {
+            Record::new(Country::Denmark).with_coordinates(latitude, longitude)
+        };
+        assert_eq!(
+            record(57.64911, 10.40744).geohash(11).as_deref(),
+            Some("u4pruydqqvj")
+        );
+        assert_eq!(record(51.4779, 0.0).geohash(6).as_deref(), Some("u10hb5"));
+        assert_eq!(record(0.0, 6.7273).geohash(3).as_deref(), Some("s0h"));
+
+        assert_eq!(Record::new(Country::Denmark).geohash(5), None);
+        assert_eq!(record(-180.0, -180.0).geohash(5), None);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+
+        // Coordinates equal but for float noise, and the fields left out, keep the hash
+        let mut same_record = record.clone();
+        same_record.latitude = 32.7977;
+        same_record.longitude += 1e-9;
+        same_record.dma = None;
+        same_record.netmask = 8;
+        assert_eq!(same_record.content_hash(), record.content_hash());
+
+        let mut other_record = record.clone();
+        other_record.city = Some("La Jolla".into());
+        assert_ne!(other_record.content_hash(), record.content_hash());
+        other_record = record.clone();
+        other_record.latitude += 0.0001;
+        assert_ne!(other_record.content_hash(), record.content_hash());
+
+        // An empty field differs from a missing one, and a value from the next field
+        let record = Record::new(Country::France);
+        let empty = Record::new(Country::France).with_postal_code("");
+        assert_ne!(empty.content_hash(), record.content_hash());
+        let mut city = Record::new(Country::France);
+        city.city = Some("75001".into());
+        let postal_code = Record::new(Country::France).with_postal_code("75001");
+        assert_ne!(city.content_hash(), postal_code.content_hash());
+
+        // Stable across runs and platforms
+        assert_eq!(record.content_hash(), 2101280475902680055);
+    }
+
+    #[test]
+    fn test_coordinate_policy() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        builder
+            .insert("2.0.0.0/8", RecordSpec::new(Country::AnonymousProxy))
+            .unwrap();
+        builder
+            .insert(
+                "3.0.0.0/8",
+                RecordSpec::new(Country::Germany).coordinates(52.5, 13.25),
+            )
+            .unwrap();
+        let database = builder.build();
+        let coordinates = |latitude, longitude| LatLng {
+            latitude,
+            longitude,
+        };
+        let sentinel = coordinates(-180.0, -180.0);
+
+        // The coordinates of `get_coordinates`, the records keeping the decoded ones without
+        let cases = [
+            (CoordinatePolicy::None, None, None),
+            (
+                CoordinatePolicy::CountryCentroid,
+                Some(coordinates(51.0, 9.0)),
+                None,
+            ),
+            (
+                CoordinatePolicy::LegacySentinel,
+                Some(sentinel),
+                Some(sentinel),
+            ),
+        ];
+        for (policy, germany, proxy) in cases {
+            let mut geo_ip = GeoIpReaderBuilder::new()
+                .coordinate_policy(policy)
+                .build(Cursor::new(database.clone()))
+                .unwrap();
+            assert_eq!(geo_ip.coordinate_policy(), policy);
+            for (ip, expected) in [("1.2.3.4", germany), ("2.2.3.4", proxy)] {
+                let record = geo_ip.get_record(ip).unwrap();
+                let found = coordinates(record.latitude, record.longitude);
+                assert_eq!(found, expected.unwrap_or(sentinel), "{} {}", policy, ip);
+                assert_eq!(
+                    geo_ip.get_coordinates(ip).unwrap(),
+                    expected,
+                    "{} {}",
+                    policy,
+                    ip
+                );
+            }
+            let record = geo_ip.get_record("3.2.3.4").unwrap();
+            assert_eq!((record.latitude, record.longitude), (52.5, 13.25));
+            assert_eq!(
+                geo_ip.get_coordinates("3.2.3.4").unwrap(),
+                Some(coordinates(52.5, 13.25))
+            );
+        }
+
+        // The records of Region editions are at the origin
+        let database = region_database(REGION_EDITION_REV1, [0, US_OFFSET]);
+        let mut region = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        let record = region.get_record("200.0.0.1").unwrap();
+        assert_eq!((record.latitude, record.longitude), (0.0, 0.0));
+        let fields = region.record_fields(&record, None, 0, Locale::En);
+        assert_eq!(fields.get("latitude"), None);
+        region.set_coordinate_policy(CoordinatePolicy::CountryCentroid);
+        let record = region.get_record("200.0.0.1").unwrap();
+        assert_eq!((record.latitude, record.longitude), (38.0, -97.0));
+        let fields = region.record_fields(&record, None, 0, Locale::En);
+        assert_eq!(fields.get("latitude"), Some("38"));
+
+        assert_eq!(
+            "legacy-sentinel".parse::<CoordinatePolicy>(),
+            Ok(CoordinatePolicy::LegacySentinel)
+        );
+        assert!("centroid".parse::<CoordinatePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_post_processors_shared_between_threads() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        builder
+            .insert("2.0.0.0/8", RecordSpec::new(Country::Switzerland))
+            .unwrap();
+        let database = builder.build();
+        let options = GeoIpReaderBuilder::new()
+            .post_processor(crate::post_process::add_is_eu_flag())
+            .post_processor(|fields: &mut RecordFields| {
+                fields.rename("is_eu", "eu");
+            });
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|thread| {
+                    let options = options.clone();
+                    let database = database.clone();
+                    scope.spawn(move || {
+                        let mut geo_ip = options.build(Cursor::new(database)).unwrap();
+                        (0..100)
+                            .map(|i| {
+                                let ip = format!("{}.0.0.{}", (thread + i) % 2 + 1, i);
+                                let record = geo_ip.get_record(&ip).unwrap();
+                                let fields = geo_ip.record_fields(&record, None, 0, Locale::En);
+                                assert!(!fields.contains("is_eu"));
+                                (ip.starts_with("1."), fields.get("eu") == Some("true"))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            for handle in handles {
+                for (germany, eu) in handle.join().unwrap() {
+                    assert_eq!(germany, eu);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_country_in() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let allowed = HashSet::from([Country::UnitedStates, Country::Canada]);
+        assert!(geo_ip.country_in("108.95.4.105", &allowed).unwrap());
+        assert!(!geo_ip.country_in("185.90.90.120", &allowed).unwrap());
+        assert!(!geo_ip.country_in("108.95.4.105", &HashSet::new()).unwrap());
+        assert!(!geo_ip.country_in("10.0.0.1", &allowed).unwrap());
+        assert!(matches!(
+            geo_ip.country_in("10.0.0", &allowed),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let allowed = HashSet::from([Country::Germany]);
+        assert!(geo_ip.country_in("1.2.3.4", &allowed).unwrap());
+        assert!(!geo_ip.country_in("2.2.3.4", &allowed).unwrap());
+
+        let database = region_database(REGION_EDITION_REV1, [0, US_OFFSET]);
+        let mut region = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        let allowed = HashSet::from([Country::UnitedStates]);
+        assert!(region.country_in("200.0.0.1", &allowed).unwrap());
+        assert!(!region.country_in("10.0.0.1", &allowed).unwrap());
+    }
+
+    #[test]
+    fn test_is_geolocatable() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        assert!(geo_ip.is_geolocatable("108.95.4.105").unwrap());
+        assert!(!geo_ip.is_geolocatable("10.0.0.1").unwrap());
+        assert!(!geo_ip.is_geolocatable("::ffff:192.168.0.1").unwrap());
+        assert!(matches!(
+            geo_ip.is_geolocatable("10.0.0"),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        builder
+            .insert("3.0.0.0/8", RecordSpec::new(Country::AnonymousProxy))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        assert!(geo_ip.is_geolocatable("1.2.3.4").unwrap());
+        // A public address outside of the ranges of the database
+        assert!(!geo_ip.is_geolocatable("2.2.3.4").unwrap());
+        assert!(matches!(
+            geo_ip.get_record("2.2.3.4"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+        // An entry that is not a territory
+        assert!(!geo_ip.is_geolocatable("3.2.3.4").unwrap());
+        assert!(geo_ip.get_record("3.2.3.4").is_ok());
+
+        let database = region_database(REGION_EDITION_REV1, [0, US_OFFSET]);
+        let mut region = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+        assert!(region.is_geolocatable("200.0.0.1").unwrap());
+        assert!(!region.is_geolocatable("100.0.0.1").unwrap());
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("1.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let mut snapshot = Vec::new();
+        geo_ip.save_snapshot(&mut snapshot).unwrap();
+        // Saving leaves the reader usable
+        assert_eq!(
+            geo_ip.get_record("1.2.3.4").unwrap().country,
+            Country::Germany
+        );
+
+        let mut restored = GeoIpReader::load_snapshot(&snapshot[..]).unwrap();
+        assert_eq!(restored.edition_name(), geo_ip.edition_name());
+        assert_eq!(restored.build_date, geo_ip.build_date);
+        assert_eq!(
+            restored.get_record("1.2.3.4").unwrap(),
+            geo_ip.get_record("1.2.3.4").unwrap()
+        );
+        assert!(matches!(
+            restored.get_record("2.2.3.4"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+
+        let mut city =
+            GeoIpReader::from_reader(Cursor::new(city_database(CITY_EDITION_REV1))).unwrap();
+        let mut snapshot = Vec::new();
+        city.save_snapshot(&mut snapshot).unwrap();
+        let mut restored = GeoIpReaderBuilder::new()
+            .load_snapshot(&snapshot[..])
+            .unwrap();
+        assert_eq!(
+            restored.get_record("200.0.0.1").unwrap(),
+            city.get_record("200.0.0.1").unwrap()
+        );
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_snapshot_refused() {
+        let mut geo_ip =
+            GeoIpReader::from_reader(Cursor::new(city_database(CITY_EDITION_REV1))).unwrap();
+        let mut snapshot = Vec::new();
+        geo_ip.save_snapshot(&mut snapshot).unwrap();
+
+        let mut corrupted = snapshot.clone();
+        if let Some(byte) = corrupted.last_mut() {
+            *byte ^= 0xff;
+        }
+        assert!(matches!(
+            GeoIpReader::load_snapshot(&corrupted[..]),
+            Err(SnapshotError::HashMismatch { .. })
+        ));
+
+        let mut newer = snapshot.clone();
+        newer[8] = 2;
+        assert!(matches!(
+            GeoIpReader::load_snapshot(&newer[..]),
+            Err(SnapshotError::UnsupportedVersion(2))
+        ));
+
+        let mut edition = snapshot.clone();
+        edition[9] = 0xee;
+        assert!(matches!(
+            GeoIpReader::load_snapshot(&edition[..]),
+            Err(SnapshotError::InvalidLayout)
+        ));
+
+        assert!(matches!(
+            GeoIpReader::load_snapshot(&city_database(CITY_EDITION_REV1)[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        assert!(matches!(
+            GeoIpReader::load_snapshot(&snapshot[..snapshot.len() - 1]),
+            Err(SnapshotError::Io(_))
+        ));
     }
 }
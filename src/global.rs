@@ -0,0 +1,340 @@
+//! A process-wide reader for the one-off lookups of scripts and small applications, e.g.
+//! `ipcap::lookup("8.8.8.8")`, without constructing and threading a reader around.
+//!
+//! Library crates should take an explicit `GeoIpReader` or `IpCap` from their caller instead:
+//! the global reader is configured once for the whole process, by whichever crate comes first.
+
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::{DetectedLayout, GeoIpReader, GeoIpReaderBuilder, Record};
+use once_cell::sync::OnceCell;
+use std::fs::File;
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// `IpCap` holds the v4 and v6 databases in memory and looks addresses of either family up
+/// through a shared reference, so that it can be shared between threads.
+///
+/// Each lookup reads the shared bytes through its own cursor, so no lock is involved. The layout
+/// of each database is detected once, when it is loaded, and the readers of the lookups share
+/// the index of its first octets.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+/// use ipcap::IpCap;
+///
+/// let ipcap = IpCap::open(GeoIpReaderBuilder::new()).unwrap();
+///
+/// assert_eq!(ipcap.lookup("108.95.4.105").unwrap().city, Some("San Diego".into()));
+/// assert_eq!(ipcap.lookup("2a08:1450:300f:900::1003").unwrap().time_zone, "Europe/London");
+/// ```
+#[derive(Debug, Clone)]
+pub struct IpCap {
+    v4: Option<Database>,
+    v6: Option<Database>,
+    options: GeoIpReaderBuilder,
+}
+
+/// The bytes of a database in memory and their layout, detected when they were loaded.
+#[derive(Debug, Clone)]
+struct Database {
+    bytes: Arc<[u8]>,
+    layout: DetectedLayout,
+}
+
+impl Database {
+    /// Detects the layout of the database bytes with the options of the builder.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a database refused by the builder, e.g. a corrupt one.
+    fn new(bytes: Arc<[u8]>, options: &GeoIpReaderBuilder) -> Result<Self, GeoIpReaderError> {
+        let layout = options
+            .clone()
+            .build(Cursor::new(&bytes[..]))?
+            .detected_layout();
+        Ok(Database { bytes, layout })
+    }
+}
+
+impl IpCap {
+    /// Loads the v4 and v6 databases into memory from the paths of `GeoIpReader::new`, the
+    /// records being decoded with the options of the given builder.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::OpenFileError` if neither database is found, or the error of
+    /// a database found but refused by the builder, e.g. a corrupt one.
+    pub fn open(options: GeoIpReaderBuilder) -> Result<Self, GeoIpReaderError> {
+        let v4_path = GeoIpReader::<File>::database_path("v4")?;
+        let v6_path = GeoIpReader::<File>::database_path("v6")?;
+        let v4 = Self::load(&options, &v4_path)?;
+        // With `IPCAP_FILE_PATH`, both families resolve to the same file, loaded once
+        let v6 = if v6_path == v4_path {
+            v4.clone()
+        } else {
+            Self::load(&options, &v6_path)?
+        };
+        if v4.is_none() && v6.is_none() {
+            return Err(GeoIpReaderError::OpenFileError);
+        }
+
+        Ok(IpCap { v4, v6, options })
+    }
+
+    /// Holds databases already in memory, e.g. downloaded, the records being decoded with the
+    /// options of the given builder.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of a database refused by the builder, e.g. a corrupt one.
+    pub fn from_bytes(
+        v4: Option<Vec<u8>>,
+        v6: Option<Vec<u8>>,
+        options: GeoIpReaderBuilder,
+    ) -> Result<Self, GeoIpReaderError> {
+        let load = |database: Option<Vec<u8>>| -> Result<_, GeoIpReaderError> {
+            let Some(database) = database else {
+                return Ok(None);
+            };
+            Ok(Some(Database::new(Arc::from(database), &options)?))
+        };
+        Ok(IpCap {
+            v4: load(v4)?,
+            v6: load(v6)?,
+            options,
+        })
+    }
+
+    /// Loads a database, `None` if its file does not exist.
+    fn load(
+        options: &GeoIpReaderBuilder,
+        path: &Path,
+    ) -> Result<Option<Database>, GeoIpReaderError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        // The trust policy and the sanity checks of the file apply as to a reader of the file
+        options.clone().open(path)?;
+        let database = std::fs::read(path).map_err(|_| GeoIpReaderError::OpenFileError)?;
+        Ok(Some(Database::new(Arc::from(database), options)?))
+    }
+
+    /// Looks up the geographical record of an IP address, v4 or v6.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if the input is not an IP address,
+    /// `GeoIpReaderError::OpenFileError` if the database of its family was not found, and
+    /// `GeoIpReaderError::AddressNotFound` if the address is not in the database.
+    pub fn lookup(&self, ip: &str) -> Result<Record<'static>, GeoIpReaderError> {
+        let addr = ip
+            .parse::<IpAddr>()
+            .map_err(|source| GeoIpReaderError::InvalidAddress {
+                input: ip.to_string(),
+                source,
+            })?;
+        let database = match addr {
+            IpAddr::V4(_) => &self.v4,
+            IpAddr::V6(_) => &self.v6,
+        };
+        let database = database.as_ref().ok_or(GeoIpReaderError::OpenFileError)?;
+
+        self.options
+            .clone()
+            .build_with_layout(Arc::clone(&database.bytes), &database.layout)
+            .get_record(ip)
+    }
+}
+
+/// The options of the global reader, then the reader itself, each set at most once.
+struct Global {
+    options: OnceCell<GeoIpReaderBuilder>,
+    ipcap: OnceCell<IpCap>,
+}
+
+static GLOBAL: Global = Global::new();
+
+impl Global {
+    const fn new() -> Self {
+        Global {
+            options: OnceCell::new(),
+            ipcap: OnceCell::new(),
+        }
+    }
+
+    fn init(&self, options: GeoIpReaderBuilder) -> Result<(), GeoIpReaderError> {
+        self.options
+            .set(options)
+            .map_err(|_| GeoIpReaderError::GlobalAlreadyInitialized)
+    }
+
+    /// Returns the reader, opened by `open` on first use. Opening it freezes the options, the
+    /// defaults unless `init` came first, so that a later `init` fails rather than being
+    /// ignored.
+    fn get(
+        &self,
+        open: impl FnOnce(GeoIpReaderBuilder) -> Result<IpCap, GeoIpReaderError>,
+    ) -> Result<&IpCap, GeoIpReaderError> {
+        self.ipcap
+            .get_or_try_init(|| open(self.options.get_or_init(GeoIpReaderBuilder::new).clone()))
+    }
+}
+
+/// Configures the global reader of `global` and `lookup`, before their first use.
+///
+/// # Errors
+///
+/// Returns `GeoIpReaderError::GlobalAlreadyInitialized` if the global reader was already used
+/// or configured.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::geo_ip_reader::{DmaPolicy, GeoIpReaderBuilder};
+///
+/// ipcap::init_global(GeoIpReaderBuilder::new().dma_policy(DmaPolicy::Never)).unwrap();
+/// assert_eq!(ipcap::lookup("108.95.4.105").unwrap().dma, None);
+///
+/// assert!(ipcap::init_global(GeoIpReaderBuilder::new()).is_err());
+/// ```
+pub fn init_global(options: GeoIpReaderBuilder) -> Result<(), GeoIpReaderError> {
+    GLOBAL.init(options)
+}
+
+/// Returns the global reader, loading the databases as `IpCap::open` on first use, with the
+/// options of `init_global` if it was called before. The reader is never loaded if neither
+/// `global` nor `lookup` is called.
+///
+/// # Errors
+///
+/// Returns the error of `IpCap::open`, the next call trying to load the databases again.
+///
+/// # Examples
+///
+/// ```
+/// let ipcap = ipcap::global().unwrap();
+///
+/// for ip in ["108.95.4.105", "185.90.90.120"] {
+///     assert!(ipcap.lookup(ip).is_ok());
+/// }
+/// ```
+pub fn global() -> Result<&'static IpCap, GeoIpReaderError> {
+    GLOBAL.get(IpCap::open)
+}
+
+/// Looks up the geographical record of an IP address with the global reader, see `global`.
+///
+/// # Errors
+///
+/// Returns the error of `global` or of `IpCap::lookup`.
+///
+/// # Examples
+///
+/// ```
+/// let record = ipcap::lookup("108.95.4.105").unwrap();
+/// assert_eq!(record.city, Some("San Diego".into()));
+/// ```
+pub fn lookup(ip: &str) -> Result<Record<'static>, GeoIpReaderError> {
+    global()?.lookup(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_ip_reader::DmaPolicy;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+
+    #[test]
+    fn test_lazy_initialization() {
+        let global = Global::new();
+        assert!(global.ipcap.get().is_none());
+        assert!(global.options.get().is_none());
+
+        global
+            .init(GeoIpReaderBuilder::new().dma_policy(DmaPolicy::Never))
+            .unwrap();
+        assert!(global.ipcap.get().is_none());
+
+        let ipcap = global.get(IpCap::open).unwrap();
+        assert!(global.ipcap.get().is_some());
+        assert!(std::ptr::eq(ipcap, global.get(IpCap::open).unwrap()));
+        assert_eq!(ipcap.lookup("108.95.4.105").unwrap().dma, None);
+    }
+
+    #[test]
+    fn test_init_after_use() {
+        let global = Global::new();
+        global.get(IpCap::open).unwrap();
+
+        assert!(matches!(
+            global.init(GeoIpReaderBuilder::new()),
+            Err(GeoIpReaderError::GlobalAlreadyInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_failed_initialization_is_retried() {
+        let global = Global::new();
+        let result = global.get(|_| Err(GeoIpReaderError::OpenFileError));
+        assert!(matches!(result, Err(GeoIpReaderError::OpenFileError)));
+        assert!(global.ipcap.get().is_none());
+
+        assert!(global.get(IpCap::open).is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_first_use() {
+        let global = Global::new();
+        let opened = AtomicUsize::new(0);
+        let barrier = Barrier::new(8);
+
+        let ipcaps: Vec<usize> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        let ipcap = global
+                            .get(|options| {
+                                opened.fetch_add(1, Ordering::SeqCst);
+                                IpCap::open(options)
+                            })
+                            .unwrap();
+                        ipcap as *const IpCap as usize
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(opened.load(Ordering::SeqCst), 1);
+        assert!(ipcaps.iter().all(|ipcap| *ipcap == ipcaps[0]));
+    }
+
+    #[test]
+    fn test_lookup_matches_reader() {
+        let ipcap = global().unwrap();
+        for (family, ip) in [("v4", "108.95.4.105"), ("v6", "2a08:1450:300f:900::1003")] {
+            let mut reader = GeoIpReader::<File>::new(family).unwrap();
+            assert_eq!(lookup(ip).unwrap(), reader.get_record(ip).unwrap());
+            assert_eq!(ipcap.lookup(ip).unwrap(), reader.get_record(ip).unwrap());
+        }
+
+        assert!(matches!(
+            lookup("10.0.0.1"),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+        assert!(matches!(
+            lookup("not an ip"),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+    }
+}
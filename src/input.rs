@@ -0,0 +1,296 @@
+//! Normalization of the lines of batch inputs, which often come from spreadsheets and Windows
+//! exports rather than clean address lists.
+//!
+//! `InputNormalizer` reads UTF-8 input, with or without a byte order mark, and UTF-16 input
+//! recognized by its byte order mark. It trims the line endings and surrounding whitespace,
+//! skips the blank and `#` comment lines, and by default keeps the first whitespace-delimited
+//! token of each line, dropping the columns that follow the address.
+
+use std::io::{self, BufRead};
+
+/// The encoding of an input, detected from its byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// UTF-8, the default without a byte order mark. Invalid sequences are replaced with
+    /// U+FFFD.
+    #[default]
+    Utf8,
+    /// UTF-16, little-endian.
+    Utf16Le,
+    /// UTF-16, big-endian.
+    Utf16Be,
+}
+
+/// The counts of the lines read by an `InputNormalizer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputStats {
+    /// The lines read, skipped or not.
+    pub lines: u64,
+    /// The empty or whitespace-only lines skipped.
+    pub blank: u64,
+    /// The lines starting with `#` skipped.
+    pub comments: u64,
+}
+
+impl InputStats {
+    /// The number of lines skipped, blank or comments.
+    pub fn skipped(&self) -> u64 {
+        self.blank + self.comments
+    }
+}
+
+/// An iterator adapter over a reader, yielding the normalized entry of each line that is not
+/// blank or a comment.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::input::InputNormalizer;
+///
+/// let input = b"\xef\xbb\xbf8.8.8.8\r\n# resolvers\r\n\r\n1.1.1.1\tcloudflare\r\n";
+/// let mut normalizer = InputNormalizer::new(&input[..]);
+///
+/// let entries: Vec<String> = normalizer.by_ref().collect::<Result<_, _>>().unwrap();
+/// assert_eq!(entries, ["8.8.8.8", "1.1.1.1"]);
+/// assert_eq!(normalizer.stats().comments, 1);
+/// assert_eq!(normalizer.stats().blank, 1);
+/// ```
+#[derive(Debug)]
+pub struct InputNormalizer<R> {
+    reader: R,
+    encoding: Option<Encoding>,
+    whole_line: bool,
+    stats: InputStats,
+}
+
+impl<R: BufRead> InputNormalizer<R> {
+    /// Wraps a reader, whose encoding is detected on the first read.
+    pub fn new(reader: R) -> Self {
+        InputNormalizer {
+            reader,
+            encoding: None,
+            whole_line: false,
+            stats: InputStats::default(),
+        }
+    }
+
+    /// Sets whether the whole trimmed line is yielded rather than its first
+    /// whitespace-delimited token, `false` by default.
+    pub fn whole_line(mut self, whole_line: bool) -> Self {
+        self.whole_line = whole_line;
+        self
+    }
+
+    /// The counts of the lines read so far.
+    pub fn stats(&self) -> InputStats {
+        self.stats
+    }
+
+    /// The encoding of the input, `None` before the first read.
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    /// Detects the encoding from the byte order mark, which is consumed.
+    fn detect_encoding(&mut self) -> io::Result<Encoding> {
+        let buffer = self.reader.fill_buf()?;
+        let (encoding, bom) = if buffer.starts_with(&[0xef, 0xbb, 0xbf]) {
+            (Encoding::Utf8, 3)
+        } else if buffer.starts_with(&[0xff, 0xfe]) {
+            (Encoding::Utf16Le, 2)
+        } else if buffer.starts_with(&[0xfe, 0xff]) {
+            (Encoding::Utf16Be, 2)
+        } else {
+            (Encoding::Utf8, 0)
+        };
+        self.reader.consume(bom);
+        Ok(encoding)
+    }
+
+    /// Reads the next line, without its `\n`, or `None` at the end of the input.
+    fn read_line(&mut self, encoding: Encoding) -> io::Result<Option<String>> {
+        match encoding {
+            Encoding::Utf8 => {
+                let mut bytes = Vec::new();
+                if self.reader.read_until(b'\n', &mut bytes)? == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut units = Vec::new();
+                let mut unit = [0u8; 2];
+                loop {
+                    match self.reader.read_exact(&mut unit) {
+                        Ok(()) => {}
+                        // A trailing odd byte is dropped
+                        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                            if units.is_empty() {
+                                return Ok(None);
+                            }
+                            break;
+                        }
+                        Err(err) => return Err(err),
+                    }
+                    let unit = match encoding {
+                        Encoding::Utf16Le => u16::from_le_bytes(unit),
+                        _ => u16::from_be_bytes(unit),
+                    };
+                    if unit == u16::from(b'\n') {
+                        break;
+                    }
+                    units.push(unit);
+                }
+                Ok(Some(String::from_utf16_lossy(&units)))
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for InputNormalizer<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => match self.detect_encoding() {
+                Ok(encoding) => *self.encoding.insert(encoding),
+                Err(err) => return Some(Err(err)),
+            },
+        };
+
+        loop {
+            let line = match self.read_line(encoding) {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            self.stats.lines += 1;
+
+            // `trim` also removes the `\r` of CRLF line endings, and a stray byte order mark
+            // left by the concatenation of files
+            let line = line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+            if line.is_empty() {
+                self.stats.blank += 1;
+                continue;
+            }
+            if line.starts_with('#') {
+                self.stats.comments += 1;
+                continue;
+            }
+            let entry = match self.whole_line {
+                true => line,
+                false => line.split_whitespace().next().unwrap_or(line),
+            };
+            return Some(Ok(entry.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLEAN: &str = "8.8.8.8\n1.1.1.1\n2001:4860:4860::8888\n";
+
+    fn normalize(input: &[u8]) -> (Vec<String>, InputStats) {
+        let mut normalizer = InputNormalizer::new(input);
+        let entries = normalizer.by_ref().collect::<io::Result<_>>().unwrap();
+        (entries, normalizer.stats())
+    }
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn test_fixtures_match_clean_input() {
+        let (clean, _) = normalize(CLEAN.as_bytes());
+        assert_eq!(clean, ["8.8.8.8", "1.1.1.1", "2001:4860:4860::8888"]);
+
+        let fixtures: [(&str, Vec<u8>, InputStats); 6] = [
+            (
+                "bom",
+                b"\xef\xbb\xbf8.8.8.8\n1.1.1.1\n2001:4860:4860::8888\n".to_vec(),
+                InputStats {
+                    lines: 3,
+                    ..Default::default()
+                },
+            ),
+            (
+                "crlf without a final line ending",
+                b"8.8.8.8\r\n1.1.1.1\r\n2001:4860:4860::8888".to_vec(),
+                InputStats {
+                    lines: 3,
+                    ..Default::default()
+                },
+            ),
+            (
+                "utf-16le",
+                utf16le("8.8.8.8\r\n1.1.1.1\r\n2001:4860:4860::8888\r\n"),
+                InputStats {
+                    lines: 3,
+                    ..Default::default()
+                },
+            ),
+            (
+                "tab-separated",
+                b"8.8.8.8\tgoogle\t15169\n 1.1.1.1  cloudflare\n2001:4860:4860::8888\t\n".to_vec(),
+                InputStats {
+                    lines: 3,
+                    ..Default::default()
+                },
+            ),
+            (
+                "comments and blanks",
+                b"# resolvers\n8.8.8.8\n\n   \n1.1.1.1\n  # v6\n2001:4860:4860::8888\n\n".to_vec(),
+                InputStats {
+                    lines: 8,
+                    blank: 3,
+                    comments: 2,
+                },
+            ),
+            (
+                "everything",
+                utf16le(
+                    "\u{feff}# export\r\n8.8.8.8\tgoogle\r\n\r\n1.1.1.1\r\n2001:4860:4860::8888",
+                ),
+                InputStats {
+                    lines: 5,
+                    blank: 1,
+                    comments: 1,
+                },
+            ),
+        ];
+        for (name, input, stats) in fixtures {
+            assert_eq!(normalize(&input), (clean.clone(), stats), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_encodings() {
+        let mut normalizer = InputNormalizer::new(&b"\xfe\xff\x001\x00\n"[..]);
+        assert_eq!(normalizer.encoding(), None);
+        assert_eq!(normalizer.next().unwrap().unwrap(), "1");
+        assert_eq!(normalizer.encoding(), Some(Encoding::Utf16Be));
+        assert!(normalizer.next().is_none());
+
+        // Invalid UTF-8 is replaced rather than failing the batch
+        let (entries, _) = normalize(b"8.8.8.8\xff\n");
+        assert_eq!(entries, ["8.8.8.8\u{fffd}"]);
+        assert_eq!(normalize(b""), (vec![], InputStats::default()));
+        assert_eq!(normalize(&[0xff, 0xfe]), (vec![], InputStats::default()));
+    }
+
+    #[test]
+    fn test_whole_line() {
+        let input = b"8.8.8.8\tgoogle\r\n# comment\n";
+        let entries: Vec<String> = InputNormalizer::new(&input[..])
+            .whole_line(true)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(entries, ["8.8.8.8\tgoogle"]);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::countries::Country;
+use crate::designated_market_area::DesignatedMarketArea;
+use crate::geo_ip_reader::{Record, RecordWarning};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A pool of shared strings, handing out one `Arc<str>` per distinct value.
+///
+/// Looking up a value already in the pool does not allocate, so the records of a batch with
+/// geographic locality share their city and region strings instead of each holding a copy.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::interner::StringInterner;
+/// use std::sync::Arc;
+///
+/// let mut interner = StringInterner::new();
+/// let first = interner.intern("San Diego");
+/// let second = interner.intern("San Diego");
+///
+/// assert!(Arc::ptr_eq(&first, &second));
+/// assert_eq!(interner.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StringInterner {
+    strings: HashMap<Box<str>, Arc<str>>,
+}
+
+impl StringInterner {
+    /// Constructs an empty interner.
+    pub fn new() -> Self {
+        StringInterner::default()
+    }
+
+    /// Returns the shared string equal to `value`, adding it to the pool if it is new.
+    pub fn intern(&mut self, value: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(value) {
+            return Arc::clone(shared);
+        }
+        let shared: Arc<str> = Arc::from(value);
+        self.strings.insert(Box::from(value), Arc::clone(&shared));
+        shared
+    }
+
+    /// Returns the number of distinct strings in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns whether the pool holds no string.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// A geographical record whose strings are drawn from a `StringInterner`, as returned by
+/// `GeoIpReader::get_records_interned`.
+///
+/// The fields are those of `Record`, without its provenance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternedRecord<'a> {
+    pub dma: Option<DesignatedMarketArea>,
+    pub postal_code: Option<Arc<str>>,
+    pub country: Country,
+    pub region_code: Option<Arc<str>>,
+    pub city: Option<Arc<str>>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub time_zone: &'a str,
+    /// The length of the prefix the record was matched on.
+    pub netmask: usize,
+    /// The longest prefix the lookup could have matched.
+    pub max_netmask: usize,
+    /// The defects of the record that were worked around while decoding it.
+    pub warnings: Vec<RecordWarning>,
+}
+
+impl<'a> InternedRecord<'a> {
+    /// Converts a record, drawing its strings from `interner`.
+    pub fn from_record(record: Record<'a>, interner: &mut StringInterner) -> Self {
+        let mut intern = |value: Option<Box<str>>| value.map(|value| interner.intern(&value));
+        InternedRecord {
+            dma: record.dma,
+            postal_code: intern(record.postal_code),
+            country: record.country,
+            region_code: intern(record.region_code),
+            city: intern(record.city),
+            latitude: record.latitude,
+            longitude: record.longitude,
+            time_zone: record.time_zone,
+            netmask: record.netmask,
+            max_netmask: record.max_netmask,
+            warnings: record.warnings,
+        }
+    }
+
+    /// Returns the city, if any.
+    pub fn city(&self) -> Option<&str> {
+        self.city.as_deref()
+    }
+
+    /// Returns the region code, if any.
+    pub fn region_code(&self) -> Option<&str> {
+        self.region_code.as_deref()
+    }
+
+    /// Returns the postal code, if any.
+    pub fn postal_code(&self) -> Option<&str> {
+        self.postal_code.as_deref()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = StringInterner::new();
+        assert!(interner.is_empty());
+
+        let zurich = interner.intern("Zürich");
+        let geneva = interner.intern("Genève");
+        assert!(Arc::ptr_eq(&zurich, &interner.intern("Zürich")));
+        assert!(!Arc::ptr_eq(&zurich, &geneva));
+        assert_eq!(&*geneva, "Genève");
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_from_record() {
+        let mut interner = StringInterner::new();
+        let record = Record::new(Country::Switzerland)
+            .with_region_code("ZH")
+            .with_netmask(24, 32);
+        let region_code = interner.intern("ZH");
+
+        let interned = InternedRecord::from_record(record, &mut interner);
+        assert_eq!(interned.country, Country::Switzerland);
+        assert_eq!(interned.region_code(), Some("ZH"));
+        assert!(Arc::ptr_eq(
+            interned.region_code.as_ref().unwrap(),
+            &region_code
+        ));
+        assert_eq!(interned.city(), None);
+        assert_eq!(interned.postal_code(), None);
+        assert_eq!((interned.netmask, interned.max_netmask), (24, 32));
+        assert_eq!(interner.len(), 1);
+    }
+}
@@ -0,0 +1,217 @@
+//! Keyed hashing of IP addresses, to export lookups without the raw addresses, e.g. with
+//! `ipcap batch --hash-ip`.
+//!
+//! An address is replaced by the SipHash-2-4 of its octets under a secret 128-bit key, rendered
+//! as 16 lowercase hex digits. Within a key, the same address always gives the same token, so the
+//! rows of an address can still be joined or counted, but the addresses cannot be recovered, nor
+//! their tokens precomputed, without the key.
+
+use crate::errors::HashKeyError;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// The number of hex digits of a key.
+const KEY_HEX_DIGITS: usize = 32;
+
+/// A secret 128-bit key of the SipHash-2-4 of IP addresses.
+///
+/// The `Debug` output of a key does not show it.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::ip_hash::IpHashKey;
+///
+/// let key: IpHashKey = "000102030405060708090a0b0c0d0e0f".parse().unwrap();
+/// let token = key.hash("10.0.0.1".parse().unwrap());
+///
+/// assert_eq!(token.len(), 16);
+/// assert_eq!(token, key.hash("10.0.0.1".parse().unwrap()));
+/// assert_ne!(token, key.hash("10.0.0.2".parse().unwrap()));
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct IpHashKey {
+    k0: u64,
+    k1: u64,
+}
+
+impl IpHashKey {
+    /// Constructs a key from its 16 bytes, in the order of the SipHash reference implementation.
+    pub fn new(key: [u8; 16]) -> Self {
+        let (k0, k1) = key.split_at(8);
+        IpHashKey {
+            k0: u64::from_le_bytes(k0.try_into().unwrap_or_default()),
+            k1: u64::from_le_bytes(k1.try_into().unwrap_or_default()),
+        }
+    }
+
+    /// Returns the token of an address: the SipHash-2-4 of its 4 or 16 octets, as 16 lowercase
+    /// hex digits in the byte order of the SipHash reference implementation.
+    ///
+    /// An IPv4 address and its IPv4-mapped IPv6 form, e.g. `::ffff:10.0.0.1`, are distinct
+    /// addresses and give distinct tokens.
+    pub fn hash(&self, ip: IpAddr) -> String {
+        let hash = match ip {
+            IpAddr::V4(ip) => siphash24(self.k0, self.k1, &ip.octets()),
+            IpAddr::V6(ip) => siphash24(self.k0, self.k1, &ip.octets()),
+        };
+        hash.to_le_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+impl fmt::Debug for IpHashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IpHashKey(..)")
+    }
+}
+
+impl FromStr for IpHashKey {
+    type Err = HashKeyError;
+
+    /// Parses a key from 32 hex digits, in either case.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(c) = value.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(HashKeyError::NotHex(c));
+        }
+        if value.len() != KEY_HEX_DIGITS {
+            return Err(HashKeyError::Length(value.len()));
+        }
+
+        let digits: Vec<u8> = value
+            .chars()
+            .filter_map(|c| c.to_digit(16))
+            .map(|digit| digit as u8)
+            .collect();
+        let mut key = [0u8; 16];
+        for (byte, pair) in key.iter_mut().zip(digits.chunks(2)) {
+            *byte = pair[0] << 4 | pair[1];
+        }
+        Ok(IpHashKey::new(key))
+    }
+}
+
+/// A SipHash round over the state `v`.
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+/// The SipHash-2-4 of a message under the key `(k0, k1)`, as in the reference implementation.
+fn siphash24(k0: u64, k1: u64, message: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        sip_round(&mut v);
+        sip_round(&mut v);
+        v[0] ^= m;
+    };
+
+    let mut blocks = message.chunks_exact(8);
+    for block in blocks.by_ref() {
+        compress(u64::from_le_bytes(block.try_into().unwrap_or_default()));
+    }
+    // The last block holds the remaining bytes and the length of the message in its top byte
+    let mut last = [0u8; 8];
+    last[..blocks.remainder().len()].copy_from_slice(blocks.remainder());
+    last[7] = message.len() as u8;
+    compress(u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference_key() -> IpHashKey {
+        IpHashKey::new(std::array::from_fn(|i| i as u8))
+    }
+
+    #[test]
+    fn test_siphash24_reference_vectors() {
+        let key = reference_key();
+        let hex = |message: &[u8]| {
+            siphash24(key.k0, key.k1, message)
+                .to_le_bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        };
+        let message: Vec<u8> = (0..64).collect();
+
+        assert_eq!(hex(&message[..0]), "310e0edd47db6f72");
+        assert_eq!(hex(&message[..1]), "fd67dc93c539f874");
+        assert_eq!(hex(&message[..15]), "e545be4961ca29a1");
+        // The octets of 0.1.2.3 are the first 4 bytes of the message
+        assert_eq!(key.hash("0.1.2.3".parse().unwrap()), hex(&message[..4]));
+    }
+
+    #[test]
+    fn test_hash_is_keyed() {
+        let key = reference_key();
+        let other: IpHashKey = "ffffffffffffffffffffffffffffffff".parse().unwrap();
+        let ip = "108.95.4.105".parse().unwrap();
+
+        assert_eq!(key.hash(ip), key.hash(ip));
+        assert_ne!(key.hash(ip), other.hash(ip));
+        assert!(key
+            .hash(ip)
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert_ne!(
+            key.hash("10.0.0.1".parse().unwrap()),
+            key.hash("::ffff:10.0.0.1".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(
+            "000102030405060708090A0B0C0D0E0F".parse::<IpHashKey>(),
+            Ok(reference_key())
+        );
+        assert_eq!(
+            "0001020304".parse::<IpHashKey>(),
+            Err(HashKeyError::Length(10))
+        );
+        assert_eq!("".parse::<IpHashKey>(), Err(HashKeyError::Length(0)));
+        assert_eq!(
+            "000102030405060708090a0b0c0d0e0f00".parse::<IpHashKey>(),
+            Err(HashKeyError::Length(34))
+        );
+        assert_eq!(
+            "000102030405060708090a0b0c0d0e0g".parse::<IpHashKey>(),
+            Err(HashKeyError::NotHex('g'))
+        );
+        assert_eq!(
+            "0x0102030405060708090a0b0c0d0e0f".parse::<IpHashKey>(),
+            Err(HashKeyError::NotHex('x'))
+        );
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        assert_eq!(format!("{:?}", reference_key()), "IpHashKey(..)");
+    }
+}
@@ -23,7 +23,7 @@
 //! use ipcap::designated_market_area::DesignatedMarketArea;
 //!
 //! let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
-//! let mut record = geo_ip.get_record("108.95.4.105");
+//! let mut record = geo_ip.get_record("108.95.4.105").unwrap();
 //!
 //! let mut expected_value = Record {
 //!     dma: Some(DesignatedMarketArea(825858)),
@@ -33,13 +33,19 @@
 //!     city: Some("San Diego".into()),
 //!     latitude: 32.79769999999999,
 //!     longitude: -117.23349999999999,
-//!     time_zone: "America/Los_Angeles"
+//!     time_zone: "America/Los_Angeles",
+//!     netmask: 23,
+//!     max_netmask: 32,
+//!     warnings: Vec::new(),
+//!     gaps: Vec::new(),
+//!     provenance: None,
+//!     country_byte: Country::UnitedStates.to_buffer(),
 //! };
 //!
 //! assert_eq!(record, expected_value);
 //!
 //! geo_ip = GeoIpReader::<File>::new("v6").unwrap();
-//! record = geo_ip.get_record("2a08:1450:300f:900::1003");
+//! record = geo_ip.get_record("2a08:1450:300f:900::1003").unwrap();
 //!
 //! expected_value = Record {
 //!     dma: None,
@@ -49,7 +55,13 @@
 //!     city: None,
 //!     latitude: 54.0,
 //!     longitude: -2.0,
-//!     time_zone: "Europe/London"
+//!     time_zone: "Europe/London",
+//!     netmask: 19,
+//!     max_netmask: 128,
+//!     warnings: Vec::new(),
+//!     gaps: Vec::new(),
+//!     provenance: None,
+//!     country_byte: Country::UnitedKingdom.to_buffer(),
 //! };
 //! assert_eq!(record, expected_value);
 //! ```
@@ -70,13 +82,68 @@
 //! please engage with the project on [GitHub](https://github.com/wiseaidev/ipcap).
 //! Your contributions help improve this crate for the community.
 
+pub mod annotate;
+pub mod asn;
+pub mod audit;
+#[cfg(feature = "serde")]
+pub mod binary;
+pub mod bulk;
+pub mod cache;
+pub mod capabilities;
 #[cfg(feature = "cli")]
 pub mod cli;
+pub mod compat;
 pub mod constants;
 pub mod continents;
 pub mod countries;
+pub mod data_export;
+pub mod db_install;
 pub mod designated_market_area;
+pub mod enricher;
 pub mod errors;
+#[cfg(feature = "embedded-country-data")]
+pub mod fallback;
+#[cfg(test)]
+mod fixtures;
+pub mod format;
+pub mod global;
+// The lookup path and the formatters of its records return a typed error rather than panic, for
+// any input and any database bytes, as enforced by the `no_panic` tests
+#[deny(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
 pub mod geo_ip_reader;
+pub mod input;
+#[deny(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+pub mod interner;
+pub mod ip_hash;
+pub mod locale;
+#[cfg(feature = "maxminddb-compat")]
+pub mod mmdb;
+#[cfg(test)]
+mod no_panic;
+pub mod otel;
+pub mod overrides;
+pub mod post_process;
+#[cfg(feature = "problem")]
+pub mod problem;
+pub mod progress;
+#[deny(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+pub mod report;
+pub mod reverse;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod test_support;
+#[deny(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
 pub mod time_zones;
+#[deny(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+mod tree;
+#[deny(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
 pub mod utils;
+pub mod validate;
+pub mod writer;
+
+pub use capabilities::capabilities;
+pub use global::{global, init_global, lookup, IpCap};
+pub use reverse::reverse_geocode;
+pub use time_zones::time_zone_by_country;
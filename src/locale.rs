@@ -0,0 +1,113 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The languages the names of countries and continents are translated to.
+///
+/// The codes of the records, such as the country and region codes, are the same in every
+/// locale: only the names are translated.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::continents::Continent;
+/// use ipcap::countries::Country;
+/// use ipcap::locale::Locale;
+///
+/// let locale: Locale = "de".parse().unwrap();
+///
+/// assert_eq!(Country::UnitedStates.name_in(locale), "Vereinigte Staaten");
+/// assert_eq!(Continent::NorthAmerica.name_in(locale), "Nordamerika");
+/// assert_eq!(Country::UnitedStates.alphabetic_code_2(), "US");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Locale {
+    /// English, the names displayed by default.
+    #[default]
+    En,
+    /// French.
+    Fr,
+    /// German.
+    De,
+    /// Spanish.
+    Es,
+}
+
+impl Locale {
+    /// The locales, English first.
+    pub const ALL: [Locale; 4] = [Locale::En, Locale::Fr, Locale::De, Locale::Es];
+
+    /// The ISO 639-1 code of the language of the locale.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+            Locale::De => "de",
+            Locale::Es => "es",
+        }
+    }
+
+    /// Returns the locale of a language tag such as `fr` or `de-AT`, falling back to English
+    /// for the languages without translations, e.g. to follow the `LANG` of a user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::locale::Locale;
+    ///
+    /// assert_eq!(Locale::from_tag("fr-CA"), Locale::Fr);
+    /// assert_eq!(Locale::from_tag("de_AT.UTF-8"), Locale::De);
+    /// assert_eq!(Locale::from_tag("it"), Locale::En);
+    /// ```
+    pub fn from_tag(tag: &str) -> Locale {
+        let language = tag.split(['-', '_', '.']).next().unwrap_or_default();
+        language.parse().unwrap_or_default()
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parses the code of a locale, case-insensitively.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.code().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("invalid locale '{}', expected one of en, fr, de, es", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for locale in Locale::ALL {
+            assert_eq!(locale.code().parse::<Locale>(), Ok(locale));
+            assert_eq!(
+                locale.to_string().to_uppercase().parse::<Locale>(),
+                Ok(locale)
+            );
+        }
+        assert_eq!(
+            "it".parse::<Locale>(),
+            Err("invalid locale 'it', expected one of en, fr, de, es".to_string())
+        );
+        assert!("de-AT".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::from_tag("es-MX"), Locale::Es);
+        assert_eq!(Locale::from_tag("DE"), Locale::De);
+        assert_eq!(Locale::from_tag("C.UTF-8"), Locale::En);
+        assert_eq!(Locale::from_tag("pt_BR"), Locale::En);
+        assert_eq!(Locale::from_tag(""), Locale::En);
+    }
+}
@@ -4,7 +4,14 @@
 /// the provided command-line options, and performs an IP lookup using the specified target.
 ///
 /// # Arguments
-/// * `--target` - The IP address to be looked up.
+/// * `--target` - The IP addresses to be looked up.
+/// * `--expect` - Checks a field of the record of each target instead of printing it.
+/// * `annotate` - Annotates the lines read from stdin instead.
+/// * `audit` - Compares the v4 and v6 databases on dual-stack address pairs instead.
+/// * `batch` - Looks up the addresses read from stdin instead.
+/// * `doctor` - Prints the capabilities of the build and the databases found instead.
+/// * `export-data` - Prints a table of the built-in reference data instead.
+/// * `migrate-help` - Prints the guide to migrate from the 0.1 library API instead.
 ///
 /// # Examples
 /// ```
@@ -16,48 +23,1068 @@
 /// # Errors
 /// The function handles errors gracefully and prints out error messages if the IP lookup fails,
 /// if the target is missing, etc.
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     #[cfg(feature = "cli")]
+    if let Err(err) = run() {
+        eprintln!("\u{1b}[1;31merror\u{1b}[0m: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Runs the command described by the command-line arguments.
+#[cfg(feature = "cli")]
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    use clap::Parser;
+    use ipcap::cli::{Cli, Command, Config};
+    // Parse command-line arguments
+    let args = Cli::parse();
+    // The locale of the command line takes precedence over the one of the config file
+    let locale = match args.locale {
+        Some(locale) => locale,
+        None => Config::load()?.locale.unwrap_or_default(),
+    };
+
+    #[cfg(feature = "snapshot")]
+    if args.snapshot.is_some() && !matches!(args.command, None | Some(Command::Range(_))) {
+        return Err("--snapshot only applies to lookups of --target and to range".into());
+    }
+    #[cfg(feature = "snapshot")]
+    if args.snapshot.is_some() && (args.asn_db.is_some() || args.org_db.is_some()) {
+        return Err("--snapshot cannot be combined with --asn-db or --org-db".into());
+    }
+
+    match &args.command {
+        Some(Command::Annotate(annotate_args)) => return annotate(annotate_args, &args, locale),
+        Some(Command::Audit(audit_args)) => return audit(audit_args),
+        Some(Command::Batch(batch_args)) => return batch(batch_args, &args, locale),
+        Some(Command::DbStats(db_stats_args)) => return db_stats(db_stats_args),
+        Some(Command::Doctor) => {
+            doctor(args.coordinate_policy);
+            return Ok(());
+        }
+        Some(Command::ExportData(export_args)) => {
+            let mut stdout = std::io::stdout().lock();
+            ipcap::data_export::export(export_args.table, export_args.format, &mut stdout)?;
+            return Ok(());
+        }
+        Some(Command::MigrateHelp) => {
+            print!("{}", ipcap::compat::MIGRATION);
+            return Ok(());
+        }
+        Some(Command::Range(range_args)) => return range(range_args, &args, locale),
+        #[cfg(feature = "snapshot")]
+        Some(Command::Snapshot(snapshot_args)) => return snapshot(snapshot_args),
+        None => {}
+    }
+
+    if args.target.is_empty() {
+        // Print an error message and exit if the target is missing
+        return Err("Target is required!".into());
+    }
+    if !args.expect.is_empty() {
+        return expect(&args, locale);
+    }
+
+    for target in &args.target {
+        lookup_target(&args, target, locale)?;
+    }
+    Ok(())
+}
+
+/// Looks up a target of `--target` and prints its record.
+#[cfg(feature = "cli")]
+fn lookup_target(
+    args: &ipcap::cli::Cli,
+    target: &str,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::utils::{embedded_ipv4, normalize_ip_text, ParseMode};
+    use std::net::IpAddr;
+
+    // auto detect ip address type
+    let mode = if args.legacy_parse {
+        ParseMode::Legacy
+    } else {
+        ParseMode::Strict
+    };
+    let input = target;
+    let target = match normalize_ip_text(input, mode) {
+        Ok(target) => target,
+        #[cfg(feature = "resolve")]
+        Err(ipcap::errors::IpParseError::Invalid(_)) if args.resolve => {
+            let addresses =
+                ipcap::utils::resolve_host(input).map_err(|err| format!("{}: {}", err, input))?;
+            if args.verbose {
+                let addresses: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+                eprintln!("resolved: {}", addresses.join(", "));
+            }
+            // The addresses are never empty, the IPv4 ones coming first
+            let target = addresses[0];
+            eprintln!("note: {} resolves to {}, looking it up", input, target);
+            target
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // IPv4-mapped and IPv4-compatible addresses are looked up in the v4 database
+    let mut fallbacks = Vec::new();
+    let (type_, target) = match target {
+        IpAddr::V4(ip) => ("v4", IpAddr::V4(ip)),
+        IpAddr::V6(ip) => match embedded_ipv4(&ip) {
+            Some(ipv4) => {
+                if args.verbose {
+                    eprintln!(
+                        "note: {} embeds the IPv4 address {}, using the v4 database",
+                        ip, ipv4
+                    );
+                }
+                fallbacks.push("v4-extraction");
+                ("v4", IpAddr::V4(ipv4))
+            }
+            None => ("v6", IpAddr::V6(ip)),
+        },
+    };
+
+    // A snapshot replaces the database of either family, whose edition must match the target
+    #[cfg(feature = "snapshot")]
+    if let Some(path) = &args.snapshot {
+        let mut geo_ip = open_snapshot(path, args.verbose)?;
+        configure_reader(&mut geo_ip, args);
+        if args.verbose {
+            eprintln!("snapshot: {}", path.display());
+            eprintln!("edition: {}", geo_ip.edition_name());
+            eprintln!("coordinate policy: {}", geo_ip.coordinate_policy());
+        }
+        if geo_ip.is_v6_edition() != (type_ == "v6") {
+            return Err(
+                ipcap::errors::GeoIpReaderError::AddressFamilyMismatch(target.to_string()).into(),
+            );
+        }
+        let overridden = find_override(args, target)?;
+        return city_lookup(args, target, &mut geo_ip, overridden, fallbacks, locale);
+    }
+
+    // Without a v4 database, resolve the country from the embedded dataset
+    #[cfg(feature = "embedded-country-data")]
     {
-        use clap::Parser;
-        use ipcap::cli::Cli;
         use ipcap::geo_ip_reader::GeoIpReader;
-        use ipcap::utils::pretty_print_dict;
         use std::fs::File;
-        use std::net::{Ipv4Addr, Ipv6Addr};
-        // Parse command-line arguments
-        let args = Cli::parse();
 
-        // auto detect ip address type
+        let path = GeoIpReader::<File>::database_path(type_)?;
+        if type_ == "v4" && !path.exists() {
+            eprintln!(
+                "warning: no v4 database at {}, using the embedded country data",
+                path.display()
+            );
+            return fallback_lookup(args, target, fallbacks, locale);
+        }
+    }
+
+    // Perform IP lookup based on the provided target
+    let (path, mut geo_ip) = open_database(type_, args.verbose)?;
+    configure_reader(&mut geo_ip, args);
+    if args.verbose {
+        eprintln!("database: {}", path.display());
+        eprintln!("edition: {}", geo_ip.edition_name());
+        eprintln!("coordinate policy: {}", geo_ip.coordinate_policy());
+    }
+
+    lookup(args, target, geo_ip, fallbacks, locale)?;
+    Ok(())
+}
+
+/// Looks up each target of `--target` and checks the `--expect` at the same position against the
+/// fields of its record, printing whether each one passed, then fails if any did not.
+///
+/// A target that cannot be looked up, e.g. not in the database, fails its expectation, the
+/// other targets being checked nonetheless.
+#[cfg(feature = "cli")]
+fn expect(
+    args: &ipcap::cli::Cli,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::cli::AuditFormat;
+    use ipcap::validate::failure_json;
+    use std::collections::HashMap;
+
+    if args.expect.len() != args.target.len() {
+        return Err(format!(
+            "expected one --expect per --target, each applying to the --target at the same \
+             position, got {} --target and {} --expect",
+            args.target.len(),
+            args.expect.len()
+        )
+        .into());
+    }
+    if args.asn_db.is_some() || args.org_db.is_some() {
+        return Err("--expect cannot be combined with --asn-db or --org-db".into());
+    }
+    #[cfg(feature = "snapshot")]
+    if args.snapshot.is_some() {
+        return Err("--expect cannot be combined with --snapshot".into());
+    }
+
+    let mut readers = HashMap::new();
+    let mut objects = Vec::new();
+    let mut failed = 0;
+    for (target, expectation) in args.target.iter().zip(&args.expect) {
+        let result = check_target(args, target, expectation, &mut readers, locale);
+        if !result.as_ref().is_ok_and(|result| result.passed) {
+            failed += 1;
+        }
+        match (args.format, result) {
+            (AuditFormat::Json, Ok(result)) => objects.push(result.to_json(target)),
+            (AuditFormat::Json, Err(err)) => objects.push(failure_json(target, expectation, &err)),
+            (AuditFormat::Text, Ok(result)) => println!(
+                "{} {} {} (actual: {})",
+                if result.passed { "PASS" } else { "FAIL" },
+                target,
+                expectation,
+                result.actual.as_deref().unwrap_or("none")
+            ),
+            (AuditFormat::Text, Err(err)) => {
+                println!("FAIL {} {} (error: {})", target, expectation, err)
+            }
+        }
+    }
+    if args.format == AuditFormat::Json {
+        println!("[\n{}\n]", objects.join(",\n"));
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} expectations failed", failed, args.expect.len()).into());
+    }
+    Ok(())
+}
+
+/// Looks up a target of `--expect`, in the database of its family opened on first use, or in
+/// `--overrides`, and checks the expectation against the fields of its record.
+#[cfg(feature = "cli")]
+fn check_target(
+    args: &ipcap::cli::Cli,
+    target: &str,
+    expectation: &ipcap::validate::Expectation,
+    readers: &mut std::collections::HashMap<
+        &'static str,
+        ipcap::geo_ip_reader::GeoIpReader<std::fs::File>,
+    >,
+    locale: ipcap::locale::Locale,
+) -> Result<ipcap::validate::ExpectationResult, Box<dyn std::error::Error>> {
+    use ipcap::errors::GeoIpReaderError;
+    use ipcap::time_zones::unix_now;
+    use ipcap::utils::{embedded_ipv4, normalize_ip_text, ParseMode};
+    use std::collections::hash_map::Entry;
+    use std::net::IpAddr;
 
-        // Perform IP lookup based on the provided target
-        if !args.target.is_empty() {
-            match args.target.parse::<Ipv4Addr>() {
-                Ok(_ipv4_addr) => {
-                    let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+    let mode = if args.legacy_parse {
+        ParseMode::Legacy
+    } else {
+        ParseMode::Strict
+    };
+    // IPv4-mapped and IPv4-compatible addresses are looked up in the v4 database
+    let (type_, target) = match normalize_ip_text(target, mode)? {
+        IpAddr::V4(ip) => ("v4", IpAddr::V4(ip)),
+        IpAddr::V6(ip) => match embedded_ipv4(&ip) {
+            Some(ipv4) => ("v4", IpAddr::V4(ipv4)),
+            None => ("v6", IpAddr::V6(ip)),
+        },
+    };
 
-                    let record = geo_ip.get_record(&args.target);
-                    pretty_print_dict(record);
+    let geo_ip = match readers.entry(type_) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let (_, mut geo_ip) = open_database(type_, false)?;
+            configure_reader(&mut geo_ip, args);
+            entry.insert(geo_ip)
+        }
+    };
+    let record = match find_override(args, target)? {
+        Some(record) => record,
+        None => geo_ip
+            .find_record(&target.to_string())?
+            .ok_or_else(|| GeoIpReaderError::AddressNotFound(target.to_string()))?,
+    };
+    let at = args.at.unwrap_or_else(unix_now);
+    let fields = geo_ip.record_fields(&record, Some(target), at, locale);
+    Ok(expectation.check(&fields)?)
+}
+
+/// Prints the capabilities of this build, the coordinate policy of the lookups, then the path
+/// and edition of the v4 and v6 databases.
+#[cfg(feature = "cli")]
+fn doctor(coordinate_policy: ipcap::geo_ip_reader::CoordinatePolicy) {
+    use ipcap::capabilities;
+    use ipcap::errors::GeoIpReaderError;
+    use ipcap::geo_ip_reader::GeoIpReader;
+    use std::fs::File;
+
+    println!("{}", capabilities());
+    println!("coordinate policy: {}", coordinate_policy);
+    println!("databases:");
+    for type_ in ["v4", "v6"] {
+        let path = match GeoIpReader::<File>::database_path(type_) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("  {} {}", type_, err);
+                continue;
+            }
+        };
+        match GeoIpReader::<File>::from_path(&path) {
+            Ok(geo_ip) => println!("  {} {} ({})", type_, path.display(), geo_ip.edition_name()),
+            Err(GeoIpReaderError::OpenFileError) if !path.exists() => {
+                println!("  {} {}: missing", type_, path.display())
+            }
+            Err(err) => println!("  {} {}: {}", type_, path.display(), err),
+        }
+    }
+}
+
+/// Looks up the target in the city database and, when `--asn-db` or `--org-db` is given,
+/// enriches it with the ASN and organization databases before printing it.
+///
+/// When `--overrides` is given and one of its ranges contains the target, the record of the
+/// range replaces the one of the city database, which is not read.
+///
+/// With `--verbose`, the diagnostics of the lookup are printed to stderr, along with the
+/// fallbacks applied: those of the target, such as `v4-extraction`, and those of the record.
+#[cfg(feature = "cli")]
+fn lookup(
+    args: &ipcap::cli::Cli,
+    target: std::net::IpAddr,
+    mut geo_ip: ipcap::geo_ip_reader::GeoIpReader<std::fs::File>,
+    mut fallbacks: Vec<&str>,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::enricher::Enricher;
+    use ipcap::geo_ip_reader::GeoIpReader;
+    use ipcap::time_zones::unix_now;
+    use ipcap::utils::pretty_print_enriched_in;
+    use std::fs::File;
+    use std::time::Instant;
+
+    let ip = target.to_string();
+    let at = args.at.unwrap_or_else(unix_now);
+    let overridden = find_override(args, target)?;
+
+    if args.asn_db.is_none() && args.org_db.is_none() {
+        return city_lookup(args, target, &mut geo_ip, overridden, fallbacks, locale);
+    }
+
+    let mut enricher = Enricher::new().lenient(args.lenient);
+    if overridden.is_none() {
+        enricher = enricher.with_city(geo_ip);
+    }
+    if let Some(path) = &args.asn_db {
+        enricher = enricher.with_asn(GeoIpReader::<File>::from_path(path)?);
+    }
+    if let Some(path) = &args.org_db {
+        enricher = enricher.with_org(GeoIpReader::<File>::from_path(path)?);
+    }
+
+    let start = Instant::now();
+    let mut record = enricher.enrich(&ip)?;
+    if overridden.is_some() {
+        record.geo = overridden;
+    }
+    if args.verbose {
+        eprintln!("lookup: {:?}", start.elapsed());
+        if let Some(geo) = &record.geo {
+            fallbacks.extend(record_fallbacks(geo));
+        }
+        print_fallbacks(&fallbacks);
+    }
+    if args.verbose || args.explain_gaps {
+        print_gaps(record.geo.as_ref().map_or(&[], |geo| geo.gaps()));
+    }
+    pretty_print_enriched_in(record, at, locale);
+    Ok(())
+}
+
+/// Looks up the target in a city database, or resolves it to its override if any.
+#[cfg(feature = "cli")]
+fn city_lookup<R: std::io::Read + std::io::Seek>(
+    args: &ipcap::cli::Cli,
+    target: std::net::IpAddr,
+    geo_ip: &mut ipcap::geo_ip_reader::GeoIpReader<R>,
+    overridden: Option<ipcap::geo_ip_reader::Record>,
+    mut fallbacks: Vec<&str>,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::errors::GeoIpReaderError;
+    use ipcap::time_zones::unix_now;
+    use ipcap::utils::pretty_print_fields;
+    use std::time::Instant;
+
+    let ip = target.to_string();
+    let at = args.at.unwrap_or_else(unix_now);
+    if let Some(record) = overridden {
+        pretty_print_fields(&geo_ip.record_fields(&record, Some(target), at, locale));
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    let record = geo_ip
+        .find_record(&ip)?
+        .ok_or_else(|| GeoIpReaderError::AddressNotFound(ip.clone()))?;
+    if args.verbose {
+        let elapsed = start.elapsed();
+        eprintln!("prefix: {}", network(target, geo_ip.netmask()));
+        if let Some(timings) = geo_ip.last_timings() {
+            eprintln!(
+                "timings: parse {:?}, tree walk {:?}, record parse {:?}",
+                timings.parse, timings.tree_walk, timings.record_parse
+            );
+        }
+        eprintln!("lookup: {:?}", elapsed);
+        fallbacks.extend(record_fallbacks(&record));
+        print_fallbacks(&fallbacks);
+    }
+    if args.verbose || args.explain_gaps {
+        print_gaps(record.gaps());
+    }
+    pretty_print_fields(&geo_ip.record_fields(&record, Some(target), at, locale));
+    Ok(())
+}
+
+/// Returns the record of the range of `--overrides` containing the target, if any.
+#[cfg(feature = "cli")]
+fn find_override(
+    args: &ipcap::cli::Cli,
+    target: std::net::IpAddr,
+) -> Result<Option<ipcap::geo_ip_reader::Record<'static>>, Box<dyn std::error::Error>> {
+    use ipcap::overrides::OverrideTable;
+
+    let overridden = match &args.overrides {
+        Some(path) => OverrideTable::from_path(path)?.lookup(target),
+        None => None,
+    };
+    if args.verbose {
+        if let Some(record) = &overridden {
+            eprintln!("override: {}", network(target, record.netmask));
+        }
+    }
+    Ok(overridden)
+}
+
+/// Looks up the target in the embedded country dataset, the ranges of `--overrides` taking
+/// precedence as with a database. The ASN and organization databases are not read.
+#[cfg(all(feature = "cli", feature = "embedded-country-data"))]
+fn fallback_lookup(
+    args: &ipcap::cli::Cli,
+    target: std::net::IpAddr,
+    mut fallbacks: Vec<&str>,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::errors::GeoIpReaderError;
+    use ipcap::fallback::FallbackResolver;
+    use ipcap::overrides::OverrideTable;
+    use ipcap::time_zones::unix_now;
+    use ipcap::utils::pretty_print_dict_in;
+
+    let overridden = match &args.overrides {
+        Some(path) => OverrideTable::from_path(path)?.lookup(target),
+        None => None,
+    };
+    let record = match overridden {
+        Some(record) => record,
+        None => {
+            fallbacks.push("embedded country data");
+            FallbackResolver::new()
+                .lookup_addr(target)
+                .ok_or_else(|| GeoIpReaderError::AddressNotFound(target.to_string()))?
+        }
+    };
+    if args.verbose {
+        eprintln!("prefix: {}", network(target, record.netmask));
+        fallbacks.extend(record_fallbacks(&record));
+        print_fallbacks(&fallbacks);
+    }
+    if args.verbose || args.explain_gaps {
+        print_gaps(record.gaps());
+    }
+    pretty_print_dict_in(record, args.at.unwrap_or_else(unix_now), locale);
+    Ok(())
+}
+
+/// Returns the fallbacks a city record was resolved with: `country centroid` when it has no
+/// city, its coordinates being those of the country, and `country time zone` when it has no
+/// region but a time zone, which is then the one of the country as a whole.
+#[cfg(feature = "cli")]
+fn record_fallbacks(record: &ipcap::geo_ip_reader::Record) -> Vec<&'static str> {
+    let mut fallbacks = Vec::new();
+    if record.city.is_none() {
+        fallbacks.push("country centroid");
+    }
+    if record.region_code.is_none() && !record.time_zone.is_empty() {
+        fallbacks.push("country time zone");
+    }
+    fallbacks
+}
+
+#[cfg(feature = "cli")]
+fn print_fallbacks(fallbacks: &[&str]) {
+    if fallbacks.is_empty() {
+        eprintln!("fallbacks: none");
+    } else {
+        eprintln!("fallbacks: {}", fallbacks.join(", "));
+    }
+}
+
+/// Prints the fields the lookup left empty, one line each, e.g. `time_zone missing: no zone
+/// mapping for US region 'XX'`.
+#[cfg(feature = "cli")]
+fn print_gaps(gaps: &[ipcap::geo_ip_reader::FieldGap]) {
+    if gaps.is_empty() {
+        eprintln!("gaps: none");
+    }
+    for gap in gaps {
+        eprintln!("gap: {gap}");
+    }
+}
+
+/// Formats the network of the given prefix length containing the address, e.g. `8.8.8.0/24`.
+#[cfg(feature = "cli")]
+fn network(ip: std::net::IpAddr, prefix: usize) -> String {
+    use std::net::IpAddr;
+
+    let network = match ip {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(ip) & mask).into())
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(ip) & mask).into())
+        }
+    };
+    format!("{}/{}", network, prefix)
+}
+
+/// Annotates each line read from stdin with the requested fields of its first IP address and
+/// writes it to stdout.
+#[cfg(feature = "cli")]
+fn annotate(
+    args: &ipcap::cli::AnnotateArgs,
+    cli: &ipcap::cli::Cli,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{self, BufRead, Write};
+
+    let mut annotator = annotator(&args.append, args.pattern, &args.delimiter, cli)?.locale(locale);
+
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        writeln!(stdout, "{}", annotator.annotate(&line?)?)?;
+    }
+    Ok(())
+}
+
+/// Prints each address read from stdin, or each distinct address with `--unique`, followed by
+/// the requested fields.
+#[cfg(feature = "cli")]
+fn batch(
+    args: &ipcap::cli::BatchArgs,
+    cli: &ipcap::cli::Cli,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::annotate::json_error;
+    use ipcap::bulk::unique_lookup;
+    use ipcap::cli::BatchFormat;
+    use ipcap::errors::GeoIpReaderError;
+    use ipcap::input::InputNormalizer;
+    use ipcap::progress::{Progress, StderrProgress};
+    use ipcap::utils::find_first_ip_matching;
+    use std::cell::Cell;
+    use std::io::{self, Write};
+    use std::net::IpAddr;
+
+    // Every format but text reports the lines that are not an address in place
+    let structured = args.format != BatchFormat::Text;
+    let array = args.format == BatchFormat::JsonArray;
+    let verbose = cli.verbose;
+    let mut annotator = annotator(&args.append, args.pattern, &args.delimiter, cli)?
+        .provenance(verbose)
+        .schema(args.schema)
+        .schema_mode(args.schema_mode)
+        .locale(locale);
+    let hash_key = match (&args.hash_ip, &args.hash_ip_env) {
+        (Some(key), _) => Some(key.clone()),
+        (None, Some(var)) => {
+            let key = std::env::var(var)
+                .map_err(|_| format!("environment variable {} of --hash-ip-env is not set", var))?;
+            Some(key.trim().parse::<ipcap::ip_hash::IpHashKey>()?)
+        }
+        (None, None) => None,
+    };
+    if let Some(key) = hash_key {
+        annotator = annotator.hash_ip(key);
+    }
+    let mut stdout = io::stdout().lock();
+    let first = Cell::new(true);
+    if array {
+        writeln!(stdout, "[")?;
+    }
+    #[cfg(feature = "serde")]
+    if args.format == BatchFormat::Binary {
+        let fields: Vec<&str> = args.append.iter().map(String::as_str).collect();
+        ipcap::binary::write_header(&mut stdout, &fields)?;
+    }
+
+    // Stream the addresses, so that `--unique` keeps its memory bound on large inputs. In JSON
+    // and binary, the lines that are not an address are reported in place, in the same stream
+    // as the rows
+    let mut read_error = None;
+    let mut write_error = None;
+    let mut input = InputNormalizer::new(io::stdin().lock()).whole_line(args.whole_line);
+    let ips = input
+        .by_ref()
+        .map_while(|line| line.map_err(|err| read_error = Some(err)).ok())
+        .filter_map(|line| {
+            if let Some((_, ip)) = find_first_ip_matching(&line, args.pattern) {
+                return Some(ip);
+            }
+            let input = line.trim();
+            if !structured || input.is_empty() {
+                return None;
+            }
+            if let Err(source) = input.parse::<IpAddr>() {
+                let err = GeoIpReaderError::InvalidAddress {
+                    input: input.to_string(),
+                    source,
+                };
+                let written = match args.format {
+                    #[cfg(feature = "serde")]
+                    BatchFormat::Binary => io::stdout()
+                        .write_all(&ipcap::binary::BinaryRow::error(input, &err).encode()),
+                    _ => write_object(
+                        &mut io::stdout(),
+                        &json_error(input, &err, args.schema),
+                        array,
+                        &first,
+                    ),
+                };
+                if let Err(err) = written {
+                    write_error.get_or_insert(err);
                 }
-                Err(_) => {
-                    // Not an IPv4 address, try IPv6
-                    match args.target.parse::<Ipv6Addr>() {
-                        Ok(_ipv6_addr) => {
-                            let mut geo_ip = GeoIpReader::<File>::new("v6").unwrap();
-
-                            let record = geo_ip.get_record(&args.target);
-                            pretty_print_dict(record);
-                        }
-                        Err(_) => {
-                            // todo
-                        }
-                    }
+            }
+            None
+        });
+
+    // Count the addresses as they are read, ahead of the deduplication of `--unique`
+    let progress = StderrProgress::new("addresses");
+    let read = Cell::new(0u64);
+    let ips = ips.inspect(|_| {
+        read.set(read.get() + 1);
+        if verbose && read.get().is_multiple_of(args.progress_every) {
+            progress.report(read.get(), None);
+        }
+    });
+
+    if args.unique && structured {
+        // The lookup is deferred to the output, the count of an address being known only then
+        let rows = unique_lookup(ips, Ok::<_, GeoIpReaderError>).exact_limit(args.unique_limit);
+        for row in rows {
+            let row = row?;
+            let count = row.count.filter(|_| args.count);
+            write_row(&mut stdout, &mut annotator, row.ip, count, args, &first)?;
+        }
+    } else if args.unique {
+        let mut rows = unique_lookup(ips, |ip| annotator.annotate(&ip.to_string()))
+            .exact_limit(args.unique_limit);
+        let mut warned = false;
+        while let Some(row) = rows.next() {
+            let row = row?;
+            if !warned && !rows.is_exact() {
+                eprintln!(
+                    "warning: more than {} distinct addresses, deduplicating with a probabilistic \
+                     filter: duplicates may rarely slip through and the count column is omitted",
+                    args.unique_limit
+                );
+                warned = true;
+            }
+            match row.count {
+                Some(count) if args.count => {
+                    writeln!(stdout, "{}{}{}", row.value, args.delimiter, count)?
+                }
+                _ => writeln!(stdout, "{}", row.value)?,
+            }
+        }
+    } else if structured {
+        for ip in ips {
+            write_row(&mut stdout, &mut annotator, ip, None, args, &first)?;
+        }
+    } else {
+        for ip in ips {
+            writeln!(stdout, "{}", annotator.annotate(&ip.to_string())?)?;
+        }
+    }
+
+    if array {
+        if !first.get() {
+            writeln!(stdout)?;
+        }
+        writeln!(stdout, "]")?;
+    }
+    if verbose {
+        progress.report(read.get(), Some(read.get()));
+        let stats = input.stats();
+        if stats.skipped() > 0 {
+            eprintln!(
+                "skipped: {} blank and {} comment lines of {}",
+                stats.blank, stats.comments, stats.lines
+            );
+        }
+    }
+    match read_error.or(write_error) {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+/// Looks up an address and writes its row of `batch` in the JSON or binary format of `args`.
+#[cfg(feature = "cli")]
+fn write_row(
+    out: &mut impl std::io::Write,
+    annotator: &mut ipcap::annotate::Annotator,
+    ip: std::net::IpAddr,
+    count: Option<u64>,
+    args: &ipcap::cli::BatchArgs,
+    first: &std::cell::Cell<bool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::cli::BatchFormat;
+
+    match args.format {
+        #[cfg(feature = "serde")]
+        BatchFormat::Binary => out.write_all(&annotator.to_binary(ip, count)?.encode())?,
+        format => write_object(
+            out,
+            &annotator.to_json(ip, count)?,
+            format == BatchFormat::JsonArray,
+            first,
+        )?,
+    }
+    Ok(())
+}
+
+/// Writes a JSON object of `batch` on its own line, preceded by a comma in an array if it is not
+/// the `first`.
+#[cfg(feature = "cli")]
+fn write_object(
+    out: &mut impl std::io::Write,
+    object: &str,
+    array: bool,
+    first: &std::cell::Cell<bool>,
+) -> std::io::Result<()> {
+    if array && !first.replace(false) {
+        writeln!(out, ",")?;
+    }
+    write!(out, "{}", object)?;
+    if !array {
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Prints the distinct records of a range, one per line: the comma-separated sub-ranges the
+/// record covers, then the requested fields.
+#[cfg(feature = "cli")]
+fn range(
+    args: &ipcap::cli::RangeArgs,
+    cli: &ipcap::cli::Cli,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::utils::parse_ip_range;
+
+    let (start, end) = parse_ip_range(&args.range)?;
+    #[cfg(feature = "snapshot")]
+    if let Some(path) = &cli.snapshot {
+        let mut geo_ip = open_snapshot(path, false)?;
+        if geo_ip.is_v6_edition() != start.is_ipv6() {
+            return Err(
+                ipcap::errors::GeoIpReaderError::AddressFamilyMismatch(start.to_string()).into(),
+            );
+        }
+        configure_reader(&mut geo_ip, cli);
+        return print_range(args, cli, &mut geo_ip, start, end, locale);
+    }
+    let type_ = if start.is_ipv4() { "v4" } else { "v6" };
+    let (_, mut geo_ip) = open_database(type_, false)?;
+    configure_reader(&mut geo_ip, cli);
+    print_range(args, cli, &mut geo_ip, start, end, locale)
+}
+
+/// Prints the records of a range of addresses in a city database, one line per record.
+#[cfg(feature = "cli")]
+fn print_range<R: std::io::Read + std::io::Seek>(
+    args: &ipcap::cli::RangeArgs,
+    cli: &ipcap::cli::Cli,
+    geo_ip: &mut ipcap::geo_ip_reader::GeoIpReader<R>,
+    start: std::net::IpAddr,
+    end: std::net::IpAddr,
+    locale: ipcap::locale::Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::time_zones::unix_now;
+    use ipcap::utils::annotate_line;
+    use std::io::{self, Write};
+
+    let unix_ts = cli.at.unwrap_or_else(unix_now);
+
+    let mut stdout = io::stdout().lock();
+    for found in geo_ip.get_records_in_range(start, end)? {
+        let ranges: Vec<String> = found
+            .ranges
+            .iter()
+            .map(|(first, last)| {
+                if first == last {
+                    first.to_string()
+                } else {
+                    format!("{}-{}", first, last)
                 }
+            })
+            .collect();
+        let fields = geo_ip.record_fields(&found.record, None, unix_ts, locale);
+        let values: Vec<Option<String>> = args
+            .append
+            .iter()
+            .map(|name| fields.get(name).map(str::to_string))
+            .collect();
+        writeln!(
+            stdout,
+            "{}",
+            annotate_line(&ranges.join(","), &values, &args.delimiter)
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints the profile of the database of an address family.
+#[cfg(feature = "cli")]
+fn db_stats(args: &ipcap::cli::DbStatsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::audit::{database_stats, SampleSpec};
+    use ipcap::cli::AuditFormat;
+
+    // A fixed seed, so that two runs over a database print the same estimates
+    let sample = if args.full {
+        SampleSpec::Full
+    } else {
+        SampleSpec::Sample {
+            size: args.sample,
+            seed: 0x1bca_9e0d,
+        }
+    };
+    let (_, mut geo_ip) = open_database(&args.family, false)?;
+    let stats = database_stats(&mut geo_ip, sample)?;
+    match args.format {
+        AuditFormat::Text => print!("{}", stats),
+        AuditFormat::Json => println!("{}", stats.to_json()),
+    }
+    Ok(())
+}
+
+/// Compares the records of the `v4,v6` address pairs of a CSV file in the v4 and v6 databases,
+/// then prints the report.
+#[cfg(feature = "cli")]
+fn audit(args: &ipcap::cli::AuditArgs) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::audit::compare_dual_stack;
+    use ipcap::cli::AuditFormat;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let content = std::fs::read_to_string(&args.pairs)
+        .map_err(|err| format!("cannot read {}: {}", args.pairs, err))?;
+    let mut pairs = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let pair = line.split_once(',').and_then(|(v4, v6)| {
+            Some((
+                v4.trim().parse::<Ipv4Addr>().ok()?,
+                v6.trim().parse::<Ipv6Addr>().ok()?,
+            ))
+        });
+        match pair {
+            Some(pair) => pairs.push(pair),
+            None => {
+                return Err(format!(
+                    "line {}: expected an IPv4 and an IPv6 address separated by a comma, got '{}'",
+                    index + 1,
+                    line
+                )
+                .into())
             }
-        } else {
-            // Print an error message and exit if the target is missing
-            return Err("Target is required!".into());
         }
     }
+
+    let (_, mut v4) = open_database("v4", false)?;
+    let (_, mut v6) = open_database("v6", false)?;
+    let report = compare_dual_stack(&mut v4, &mut v6, &pairs);
+    match args.format {
+        AuditFormat::Text => print!("{}", report),
+        AuditFormat::Json => println!("{}", report.to_json()),
+    }
     Ok(())
 }
+
+/// Opens the city database of the given address family, timing its lookups if requested,
+/// and explaining how to get it when the file is missing.
+#[cfg(feature = "cli")]
+fn open_database(
+    type_: &str,
+    timings: bool,
+) -> Result<
+    (
+        std::path::PathBuf,
+        ipcap::geo_ip_reader::GeoIpReader<std::fs::File>,
+    ),
+    Box<dyn std::error::Error>,
+> {
+    use ipcap::errors::GeoIpReaderError;
+    use ipcap::geo_ip_reader::GeoIpReader;
+    use std::fs::File;
+
+    let path = GeoIpReader::<File>::database_path(type_)?;
+    match reader_builder(timings)?.open(&path) {
+        Ok(geo_ip) => Ok((path, geo_ip)),
+        Err(GeoIpReaderError::OpenFileError) if !path.exists() => {
+            let file_name = format!("geo_ip_city_{}.dat", type_);
+            let location = if std::env::var_os("IPCAP_FILE_PATH").is_some() {
+                "set by IPCAP_FILE_PATH"
+            } else {
+                "the default location, override it with IPCAP_FILE_PATH"
+            };
+            Err(format!(
+                "no {type_} database at {path}\n\n\
+                 The path is {location}. Download the database with:\n\n    \
+                 curl -LS https://raw.githubusercontent.com/wiseaidev/ipcap/main/data/{file_name} \
+                 --create-dirs -o {path}\n\n\
+                 or point IPCAP_FILE_PATH to an existing {file_name} file.",
+                path = path.display(),
+            )
+            .into())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Constructs the builder of the readers of the databases and snapshots, timing their lookups
+/// if requested, with the time zone overrides of the file set by `IPCAP_TZ_OVERRIDES`.
+#[cfg(feature = "cli")]
+fn reader_builder(
+    timings: bool,
+) -> Result<ipcap::geo_ip_reader::GeoIpReaderBuilder, Box<dyn std::error::Error>> {
+    use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+    use ipcap::time_zones::{OverrideSet, TZ_OVERRIDES_ENV};
+
+    let mut builder = GeoIpReaderBuilder::new().timings(timings);
+    let overrides =
+        OverrideSet::from_env().map_err(|err| format!("{}: {}", TZ_OVERRIDES_ENV, err))?;
+    if let Some(overrides) = overrides {
+        builder = builder.time_zone_overrides(overrides);
+    }
+    Ok(builder)
+}
+
+/// Writes a snapshot of the database of an address family to the output path.
+#[cfg(all(feature = "cli", feature = "snapshot"))]
+fn snapshot(args: &ipcap::cli::SnapshotArgs) -> Result<(), Box<dyn std::error::Error>> {
+    use ipcap::cli::SnapshotCommand;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+
+    match &args.command {
+        SnapshotCommand::Build(build_args) => {
+            let (_, mut geo_ip) = open_database(&build_args.family, false)?;
+            let file = File::create(&build_args.output)
+                .map_err(|err| format!("cannot create {}: {}", build_args.output.display(), err))?;
+            let mut out = BufWriter::new(file);
+            geo_ip.save_snapshot(&mut out)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens a snapshot written by `snapshot build`, timing its lookups if requested.
+#[cfg(all(feature = "cli", feature = "snapshot"))]
+fn open_snapshot(
+    path: &std::path::Path,
+    timings: bool,
+) -> Result<ipcap::geo_ip_reader::GeoIpReader<std::io::Cursor<Vec<u8>>>, Box<dyn std::error::Error>>
+{
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let file =
+        File::open(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let geo_ip = reader_builder(timings)?
+        .load_snapshot(BufReader::new(file))
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(geo_ip)
+}
+
+/// Sets the `--coordinate-policy` of a database, then registers the built-in post-processors
+/// of `--post-processor` on it, in order.
+#[cfg(feature = "cli")]
+fn configure_reader<R: std::io::Read + std::io::Seek>(
+    geo_ip: &mut ipcap::geo_ip_reader::GeoIpReader<R>,
+    cli: &ipcap::cli::Cli,
+) {
+    use ipcap::cli::BuiltinProcessor;
+    use ipcap::post_process::{add_is_eu_flag, add_modern_country_code};
+
+    geo_ip.set_coordinate_policy(cli.coordinate_policy);
+    for processor in &cli.post_processors {
+        match processor {
+            BuiltinProcessor::IsEu => geo_ip.add_post_processor(add_is_eu_flag()),
+            BuiltinProcessor::ModernCountry => geo_ip.add_post_processor(add_modern_country_code()),
+        }
+    }
+}
+
+/// Builds an `Annotator` over the warmed-up databases of the given address families, warming
+/// each one for at most `--warm-up` if given, with the post-processors of `--post-processor`.
+#[cfg(feature = "cli")]
+fn annotator(
+    fields: &[String],
+    pattern: ipcap::utils::IpPattern,
+    delimiter: &str,
+    cli: &ipcap::cli::Cli,
+) -> Result<ipcap::annotate::Annotator, Box<dyn std::error::Error>> {
+    use ipcap::annotate::Annotator;
+    use ipcap::utils::IpPattern;
+    use std::time::Duration;
+
+    let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+    let mut annotator = Annotator::new(&fields)
+        .pattern(pattern)
+        .delimiter(delimiter);
+    if let Some(at) = cli.at {
+        annotator = annotator.at(at);
+    }
+    // Warm the databases up front, a long-running annotation would otherwise start with slow
+    // lookups
+    let budget = cli.warm_up.unwrap_or(Duration::MAX);
+    for (family, wanted) in [
+        ("v4", pattern != IpPattern::Ipv6),
+        ("v6", pattern != IpPattern::Ipv4),
+    ] {
+        if !wanted {
+            continue;
+        }
+        let (_, mut geo_ip) = open_database(family, false)?;
+        configure_reader(&mut geo_ip, cli);
+        let report = geo_ip.warm_up(budget)?;
+        if !report.is_complete() {
+            eprintln!(
+                "warning: warm-up budget exhausted after {} of {} bytes of the {} database",
+                report.bytes_touched, report.total_bytes, family
+            );
+        }
+        annotator = match family {
+            "v4" => annotator.with_v4(geo_ip),
+            _ => annotator.with_v6(geo_ip),
+        };
+    }
+    Ok(annotator)
+}
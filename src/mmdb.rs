@@ -0,0 +1,332 @@
+//! Conversions between `Record` and the City model of MMDB databases, as decoded by the
+//! `maxminddb` crate, so that the same downstream code can consume both data sources.
+//!
+//! `Record::from_mmdb_city` converts the fields of a `maxminddb::geoip2::City` a `Record` has a
+//! place for. The other pieces of the model have no equivalent in the legacy format and are
+//! dropped: the accuracy radius, the continent, the registered and represented countries, the
+//! traits, the geoname identifiers, the names in other languages than English and the
+//! subdivisions after the first.
+
+use crate::countries::Country;
+use crate::designated_market_area::DesignatedMarketArea;
+use crate::geo_ip_reader::Record;
+use crate::utils::json_string;
+use maxminddb::geoip2::City;
+use std::net::IpAddr;
+
+impl<'a> Record<'a> {
+    /// Converts the City record of an MMDB database, looked up for `ip`.
+    ///
+    /// The country is read from its ISO code, the region from the ISO code of the first
+    /// subdivision and the city from its English name. The metro code becomes the DMA code of
+    /// the designated market area, without an area code. Without a time zone in the record, the
+    /// one of the country and region is used, as by `Record::with_region_code`.
+    ///
+    /// A record without a country, e.g. of an anycast network, or with one that has no
+    /// equivalent in `Country`, is converted with `Country::Other`.
+    ///
+    /// MMDB lookups return no prefix length with the record, so the netmask is the full length
+    /// of the address: 32 for an IPv4 address, 128 for an IPv6 one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    /// use maxminddb::geoip2::City;
+    ///
+    /// let json = r#"{
+    ///     "city": {"names": {"en": "San Diego", "de": "San Diego"}},
+    ///     "country": {"iso_code": "US"},
+    ///     "location": {"metro_code": 825},
+    ///     "subdivisions": [{"iso_code": "CA"}]
+    /// }"#;
+    /// let city: City = serde_json::from_str(json).unwrap();
+    /// let record = Record::from_mmdb_city(&city, "108.95.4.105".parse().unwrap());
+    ///
+    /// assert_eq!(record.country, Country::UnitedStates);
+    /// assert_eq!(record.region_code(), Some("CA"));
+    /// assert_eq!(record.time_zone, "America/Los_Angeles");
+    /// assert_eq!(record.dma.unwrap().dma_code(), 825);
+    /// ```
+    pub fn from_mmdb_city(city: &City<'a>, ip: IpAddr) -> Record<'a> {
+        let country = city
+            .country
+            .as_ref()
+            .and_then(|country| country.iso_code)
+            .and_then(Country::from_alphabetic_code_2)
+            .unwrap_or(Country::Other);
+        let max_netmask = if ip.is_ipv4() { 32 } else { 128 };
+
+        let mut record = Record::new(country).with_netmask(max_netmask, max_netmask);
+        let region_code = city
+            .subdivisions
+            .as_ref()
+            .and_then(|subdivisions| subdivisions.first())
+            .and_then(|subdivision| subdivision.iso_code);
+        if let Some(region_code) = region_code {
+            record = record.with_region_code(region_code);
+        }
+        let name = city
+            .city
+            .as_ref()
+            .and_then(|city| city.names.as_ref())
+            .and_then(|names| names.get("en"));
+        if let Some(name) = name {
+            record = record.with_city(name);
+        }
+        if let Some(postal_code) = city.postal.as_ref().and_then(|postal| postal.code) {
+            record = record.with_postal_code(postal_code);
+        }
+        if let Some(location) = &city.location {
+            if let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude) {
+                record = record.with_coordinates(latitude, longitude);
+            }
+            if let Some(time_zone) = location.time_zone {
+                record = record.with_time_zone(time_zone);
+            }
+            if let Some(metro_code) = location.metro_code {
+                record = record.with_dma(DesignatedMarketArea(u32::from(metro_code) * 1000));
+            }
+        }
+        record
+    }
+
+    /// Formats the record as a JSON object with the structure of an MMDB City record, e.g.
+    /// `{"city": {"names": {"en": "San Diego"}}, "country": {"iso_code": "US", ...}, ...}`.
+    ///
+    /// Like in MMDB records, the members without a value are omitted: the city, postal and
+    /// subdivisions without a value, the location coordinates when they are not real and the
+    /// metro code outside of the United States. The names are the English ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    ///
+    /// let record = Record::new(Country::Switzerland).with_city("Zürich");
+    ///
+    /// assert_eq!(
+    ///     record.to_mmdb_like_json(),
+    ///     "{\"city\": {\"names\": {\"en\": \"Zürich\"}}, \
+    ///      \"continent\": {\"code\": \"EU\", \"names\": {\"en\": \"Europe\"}}, \
+    ///      \"country\": {\"iso_code\": \"CH\", \"names\": {\"en\": \"Switzerland\"}}, \
+    ///      \"location\": {\"time_zone\": \"Europe/Zurich\"}}"
+    /// );
+    /// ```
+    pub fn to_mmdb_like_json(&self) -> String {
+        let names = |name: &str| format!("{{\"en\": {}}}", json_string(name));
+
+        let mut members = Vec::new();
+        if let Some(city) = self.city() {
+            members.push(format!("\"city\": {{\"names\": {}}}", names(city)));
+        }
+        if let Some(continent) = self.country.continent() {
+            members.push(format!(
+                "\"continent\": {{\"code\": {}, \"names\": {}}}",
+                json_string(continent.alphabetic_code_2()),
+                names(&continent.to_string())
+            ));
+        }
+        members.push(format!(
+            "\"country\": {{\"iso_code\": {}, \"names\": {}}}",
+            json_string(self.country.alphabetic_code_2()),
+            names(self.country.name())
+        ));
+
+        let mut location = Vec::new();
+        if self.has_real_coordinates() {
+            location.push(format!("\"latitude\": {}", self.latitude));
+            location.push(format!("\"longitude\": {}", self.longitude));
+        }
+        if let Some(dma) = self.dma.filter(|dma| !dma.is_empty()) {
+            location.push(format!("\"metro_code\": {}", dma.dma_code()));
+        }
+        if !self.time_zone.is_empty() {
+            location.push(format!("\"time_zone\": {}", json_string(self.time_zone)));
+        }
+        if !location.is_empty() {
+            members.push(format!("\"location\": {{{}}}", location.join(", ")));
+        }
+
+        if let Some(postal_code) = self.postal_code() {
+            members.push(format!(
+                "\"postal\": {{\"code\": {}}}",
+                json_string(postal_code)
+            ));
+        }
+        if let Some(region_code) = self.region_code() {
+            members.push(format!(
+                "\"subdivisions\": [{{\"iso_code\": {}}}]",
+                json_string(region_code)
+            ));
+        }
+        format!("{{{}}}", members.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4() -> IpAddr {
+        "108.95.4.105".parse().unwrap()
+    }
+
+    /// Decodes a City record from JSON, as the `maxminddb` crate decodes it from a database.
+    fn city(json: &str) -> City<'_> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_from_full_mmdb_city() {
+        let city = city(
+            r#"{
+                "city": {"geoname_id": 5391811, "names": {"en": "San Diego", "ja": "サンディエゴ"}},
+                "continent": {"code": "NA", "names": {"en": "North America"}},
+                "country": {"iso_code": "US", "names": {"en": "United States"}},
+                "location": {
+                    "accuracy_radius": 5,
+                    "latitude": 32.7977,
+                    "longitude": -117.2335,
+                    "metro_code": 825,
+                    "time_zone": "America/Los_Angeles"
+                },
+                "postal": {"code": "92109"},
+                "registered_country": {"iso_code": "CA"},
+                "subdivisions": [{"iso_code": "CA", "names": {"en": "California"}}]
+            }"#,
+        );
+
+        let record = Record::from_mmdb_city(&city, ipv4());
+        assert_eq!(
+            record,
+            Record::new(Country::UnitedStates)
+                .with_region_code("CA")
+                .with_city("San Diego")
+                .with_postal_code("92109")
+                .with_coordinates(32.7977, -117.2335)
+                .with_dma(DesignatedMarketArea(825_000))
+                .with_netmask(32, 32)
+        );
+        assert_eq!(record.provenance, None);
+        let ipv6 = "2001:4860:4860::8888".parse().unwrap();
+        assert_eq!(Record::from_mmdb_city(&city, ipv6).max_netmask, 128);
+    }
+
+    #[test]
+    fn test_from_mmdb_city_without_subdivisions() {
+        let city = city(
+            r#"{
+                "city": {"names": {"en": "London"}},
+                "country": {"iso_code": "GB"},
+                "location": {"latitude": 51.5142, "longitude": -0.0931}
+            }"#,
+        );
+
+        let record = Record::from_mmdb_city(&city, ipv4());
+        assert_eq!(record.country, Country::UnitedKingdom);
+        assert_eq!(record.region_code(), None);
+        assert_eq!(record.city(), Some("London"));
+        // The time zone of the country, the record has none
+        assert_eq!(record.time_zone, "Europe/London");
+        assert_eq!(record.dma, None);
+    }
+
+    #[test]
+    fn test_from_mmdb_city_without_city() {
+        let json = r#"{
+            "city": {"names": {"de": "München"}},
+            "country": {"iso_code": "DE"},
+            "location": {"time_zone": "Europe/Berlin"},
+            "subdivisions": [{"iso_code": "BY"}, {"iso_code": "09"}]
+        }"#;
+
+        // Names in other languages only are dropped
+        let record = Record::from_mmdb_city(&city(json), ipv4());
+        assert_eq!(record.country, Country::Germany);
+        assert_eq!(record.region_code(), Some("BY"));
+        assert_eq!(record.city(), None);
+        assert_eq!(record.postal_code(), None);
+        assert!(!record.has_real_coordinates());
+        assert_eq!(record.time_zone, "Europe/Berlin");
+
+        // Without a country, or with an unknown one, the country is `Other`
+        let anycast = city(r#"{"registered_country": {"iso_code": "US"}}"#);
+        let record = Record::from_mmdb_city(&anycast, ipv4());
+        assert_eq!(record, Record::new(Country::Other).with_netmask(32, 32));
+        let unknown = city(r#"{"country": {"iso_code": "XX"}}"#);
+        assert_eq!(
+            Record::from_mmdb_city(&unknown, ipv4()).country,
+            Country::Other
+        );
+    }
+
+    #[test]
+    fn test_to_mmdb_like_json() {
+        let record = Record::new(Country::UnitedStates)
+            .with_region_code("CA")
+            .with_city("San Diego")
+            .with_postal_code("92109")
+            .with_coordinates(32.7977, -117.2335)
+            .with_dma(DesignatedMarketArea(825_858));
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&record.to_mmdb_like_json()).unwrap(),
+            serde_json::json!({
+                "city": {"names": {"en": "San Diego"}},
+                "continent": {"code": "NA", "names": {"en": "North America"}},
+                "country": {"iso_code": "US", "names": {"en": "United States"}},
+                "location": {
+                    "latitude": 32.7977,
+                    "longitude": -117.2335,
+                    "metro_code": 825,
+                    "time_zone": "America/Los_Angeles",
+                },
+                "postal": {"code": "92109"},
+                "subdivisions": [{"iso_code": "CA"}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_mmdb_like_json_omits_missing_members() {
+        let record = Record::new(Country::UnitedStates).with_time_zone("");
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&record.to_mmdb_like_json()).unwrap(),
+            serde_json::json!({
+                "continent": {"code": "NA", "names": {"en": "North America"}},
+                "country": {"iso_code": "US", "names": {"en": "United States"}},
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = r#"{
+            "city": {"names": {"en": "Tokyo"}},
+            "country": {"iso_code": "JP"},
+            "location": {"latitude": 35.6895, "longitude": 139.6917, "time_zone": "Asia/Tokyo"},
+            "postal": {"code": "100-0001"},
+            "subdivisions": [{"iso_code": "13"}]
+        }"#;
+        let record = Record::from_mmdb_city(&city(json), ipv4());
+
+        // The reverse JSON decodes as a City record again
+        let mmdb_like_json = record.to_mmdb_like_json();
+        let city = city(&mmdb_like_json);
+        assert_eq!(Record::from_mmdb_city(&city, ipv4()), record);
+
+        let json: serde_json::Value = serde_json::from_str(&mmdb_like_json).unwrap();
+        assert_eq!(json["country"]["iso_code"], "JP");
+        assert_eq!(json["subdivisions"][0]["iso_code"], "13");
+        assert_eq!(json["city"]["names"]["en"], "Tokyo");
+        assert_eq!(json["postal"]["code"], "100-0001");
+        assert_eq!(json["location"]["latitude"], 35.6895);
+        assert_eq!(json["location"]["longitude"], 139.6917);
+        assert_eq!(json["location"]["time_zone"], "Asia/Tokyo");
+        assert!(json["location"].get("metro_code").is_none());
+    }
+}
@@ -0,0 +1,306 @@
+//! The enforcement of the panic-free lookup path: for any input string and any database bytes,
+//! the public lookup APIs and the formatters of their records return `Ok` or a typed `Err`.
+//!
+//! Each call runs inside `catch_unwind` over adversarial inputs and the mutations of the
+//! fixture databases, and the test fails with the list of the calls that panicked.
+
+use crate::constants::*;
+use crate::fixtures::{city_database, mutations, region_database, string_database};
+use crate::geo_ip_reader::{GeoIpReader, GeoIpReaderBuilder};
+use crate::time_zones::{
+    abbreviation, display_abbreviation, format_utc_offset, parse_timestamp, time_zone_by_country,
+    utc_offset,
+};
+use crate::utils::{
+    annotate_line, find_first_ip, find_first_ip_matching, normalize_ip_text, read_data_checked,
+    record_fields, record_fields_at, try_ip_to_number, IpPattern, ParseMode,
+};
+use std::fmt;
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+/// Strings that are empty, huge, malformed or at the boundaries of the address spaces.
+fn inputs() -> Vec<String> {
+    let mut inputs: Vec<String> = [
+        "",
+        " ",
+        "\0",
+        "banana",
+        "0.0.0.0",
+        "10.0.0.1",
+        "200.0.0.1",
+        "255.255.255.255",
+        "256.0.0.1",
+        "1.2.3",
+        "1.2.3.4.5",
+        "01.02.03.04",
+        "0x7f.1",
+        "4294967295",
+        "340282366920938463463374607431768211455",
+        "340282366920938463463374607431768211456",
+        "-1",
+        "::",
+        "::1",
+        "::ffff:200.0.0.1",
+        "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff",
+        "fe80::1%eth0",
+        "[2001:db8::1]:443",
+        "200.0.0.1:80",
+        "1.2.3.4xyz 5.6.7.8.",
+        "١.٢.٣.٤",
+        "Zürich 200.0.0.1 ∞",
+        "...:::...",
+    ]
+    .map(String::from)
+    .to_vec();
+    inputs.push("1".repeat(100_000));
+    inputs.push("200.0.0.1 ".repeat(10_000));
+    inputs.push(":".repeat(100_000));
+    inputs.push(String::from_utf8_lossy(&mutations(b"\xF0\x9F\x92\xA9", 7).concat()).into());
+    inputs
+}
+
+/// The databases of each supported layout, then their mutations.
+fn databases() -> Vec<Vec<u8>> {
+    let mut databases = vec![
+        city_database(CITY_EDITION_REV0),
+        city_database(CITY_EDITION_REV1),
+        city_database(CITY_EDITION_REV1_V6),
+        region_database(REGION_EDITION_REV0, [0, US_OFFSET]),
+        region_database(
+            REGION_EDITION_REV1,
+            [CANADA_OFFSET, WORLD_OFFSET + FIPS_RANGE],
+        ),
+        region_database(REGION_EDITION_REV1, [u32::MAX - STATE_BEGIN_REV1, 1 << 23]),
+        string_database(ORG_EDITION, "Google LLC"),
+        string_database(ISP_EDITION, ""),
+        string_database(ASNUM_EDITION, "AS15169 Google LLC"),
+        string_database(ASNUM_EDITION_V6, "AS"),
+    ];
+    for (seed, database) in (1..).zip(databases.clone()) {
+        databases.extend(mutations(&database, seed));
+    }
+    databases
+}
+
+/// Runs `f`, recording a panic as a failure named after `call`, which is only formatted then.
+fn check<T>(failures: &mut Vec<String>, call: fmt::Arguments, f: impl FnOnce() -> T) -> Option<T> {
+    let result = catch_unwind(AssertUnwindSafe(f));
+    if result.is_err() {
+        failures.push(call.to_string());
+    }
+    result.ok()
+}
+
+/// Calls every lookup of a reader with every input, and the formatters on the found records.
+fn exercise(failures: &mut Vec<String>, database: &[u8], inputs: &[String]) {
+    let Some(Ok(mut reader)) = check(
+        failures,
+        format_args!("from_reader on database {:?}", database),
+        || GeoIpReader::from_reader(Cursor::new(database.to_vec())),
+    ) else {
+        return;
+    };
+    check(
+        failures,
+        format_args!("from_reader_at on database {:?}", database),
+        || {
+            let len = database.len() as u64;
+            let _ = GeoIpReader::from_reader_at(Cursor::new(database.to_vec()), 1, len, "v4");
+            let _ = GeoIpReader::from_reader_at(Cursor::new(database.to_vec()), u64::MAX, 2, "v6");
+            let _ = GeoIpReaderBuilder::new()
+                .timings(true)
+                .build(Cursor::new(database.to_vec()))
+                .map(|mut reader| reader.find_record("200.0.0.1"));
+        },
+    );
+    check(
+        failures,
+        format_args!("metadata on database {:?}", database),
+        || {
+            let _ = reader.edition_name();
+            let _ = reader.netmask();
+            let _ = reader.fingerprint();
+            let _ = reader.warm_up(Duration::MAX);
+            let _ = reader.detect_database_type();
+        },
+    );
+    for ip_number in [0, 1 << 31, u128::from(u32::MAX), 1 << 127, u128::MAX] {
+        check(
+            failures,
+            format_args!("get_country({}) on database {:?}", ip_number, database),
+            || reader.get_country(ip_number),
+        );
+    }
+
+    for input in inputs {
+        let record = check(
+            failures,
+            format_args!("find_record({:?}) on database {:?}", input, database),
+            || reader.find_record(input),
+        );
+        if let Some(Ok(Some(record))) = record {
+            check(
+                failures,
+                format_args!("record_fields({:?}) on database {:?}", input, database),
+                || {
+                    let _ = record_fields(&record);
+                    for unix_ts in [i64::MIN, -1, 0, i64::MAX] {
+                        let _ = record_fields_at(&record, unix_ts);
+                    }
+                    let _ = record.location_confidence();
+                    let _ = record.accuracy();
+                    let _ = record.location_parts();
+                    let _ = format!("{:?}", record);
+                },
+            );
+        }
+        check(
+            failures,
+            format_args!("get_record({:?}) on database {:?}", input, database),
+            || reader.get_record(input),
+        );
+        check(
+            failures,
+            format_args!("get_coordinates({:?}) on database {:?}", input, database),
+            || reader.get_coordinates(input),
+        );
+        check(
+            failures,
+            format_args!(
+                "get_time_zone_given_ip_addr({:?}) on database {:?}",
+                input, database
+            ),
+            || {
+                let _ = reader.get_time_zone_given_ip_addr(input);
+            },
+        );
+        check(
+            failures,
+            format_args!("get_org({:?}) on database {:?}", input, database),
+            || reader.get_org(input),
+        );
+        check(
+            failures,
+            format_args!("get_asn({:?}) on database {:?}", input, database),
+            || reader.get_asn(input),
+        );
+        check(
+            failures,
+            format_args!("contains({:?}) on database {:?}", input, database),
+            || reader.contains(input),
+        );
+        check(
+            failures,
+            format_args!("same_block({:?}) on database {:?}", input, database),
+            || reader.same_block(input, "200.0.0.1"),
+        );
+    }
+
+    let addrs: Vec<IpAddr> = inputs
+        .iter()
+        .filter_map(|input| input.parse().ok())
+        .collect();
+    check(
+        failures,
+        format_args!("contains_batch on database {:?}", database),
+        || reader.contains_batch(&addrs),
+    );
+    check(
+        failures,
+        format_args!("analyze on database {:?}", database),
+        || reader.analyze(inputs.iter().map(String::as_str)),
+    );
+}
+
+#[test]
+fn test_lookup_path_never_panics() {
+    let inputs = inputs();
+    let mut failures = Vec::new();
+
+    for database in databases() {
+        exercise(&mut failures, &database, &inputs);
+    }
+
+    for input in &inputs {
+        check(
+            &mut failures,
+            format_args!("try_ip_to_number({:?})", input),
+            || try_ip_to_number(input),
+        );
+        check(
+            &mut failures,
+            format_args!("normalize_ip_text({:?})", input),
+            || {
+                let _ = normalize_ip_text(input, ParseMode::Strict);
+                let _ = normalize_ip_text(input, ParseMode::Legacy);
+            },
+        );
+        check(
+            &mut failures,
+            format_args!("find_first_ip({:?})", input),
+            || {
+                let _ = find_first_ip(input);
+                for pattern in [IpPattern::Ipv4, IpPattern::Ipv6, IpPattern::Auto] {
+                    let _ = find_first_ip_matching(input, pattern);
+                }
+            },
+        );
+        check(
+            &mut failures,
+            format_args!("annotate_line({:?})", input),
+            || annotate_line(input, &[None, Some(input.clone())], input),
+        );
+        check(
+            &mut failures,
+            format_args!("read_data_checked({:?})", input),
+            || {
+                for pos in [0, 1, input.len(), usize::MAX] {
+                    let _ = read_data_checked(input.as_bytes(), pos);
+                }
+            },
+        );
+        check(
+            &mut failures,
+            format_args!("time_zones({:?})", input),
+            || {
+                let _ = parse_timestamp(input);
+                let _ = time_zone_by_country(input, Some(input));
+                for unix_ts in [i64::MIN, -1, 0, i64::MAX] {
+                    let _ = utc_offset(input, unix_ts);
+                    let _ = abbreviation(input, unix_ts);
+                    let _ = display_abbreviation(input, unix_ts);
+                }
+            },
+        );
+    }
+
+    for zone in [
+        "Europe/Zurich",
+        "America/New_York",
+        "Australia/Lord_Howe",
+        "Asia/Kathmandu",
+    ] {
+        for unix_ts in [i64::MIN, -1, 0, i64::MAX] {
+            check(
+                &mut failures,
+                format_args!("utc_offset({:?}, {})", zone, unix_ts),
+                || {
+                    let _ = utc_offset(zone, unix_ts).map(format_utc_offset);
+                    let _ = display_abbreviation(zone, unix_ts);
+                },
+            );
+        }
+    }
+    for offset in [i32::MIN, -1, 0, i32::MAX] {
+        check(
+            &mut failures,
+            format_args!("format_utc_offset({})", offset),
+            || format_utc_offset(offset),
+        );
+    }
+
+    assert!(failures.is_empty(), "panicked: {:#?}", failures);
+}
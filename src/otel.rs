@@ -0,0 +1,197 @@
+//! The geo attributes of the OpenTelemetry semantic conventions for a `Record`, e.g. to attach
+//! the location of a client to the spans of its requests.
+//!
+//! `Record::otel_attributes` maps the fields of a record to the attributes below, leaving out
+//! those of the fields the record does not have:
+//!
+//! | Attribute              | Value    | Field                                                     |
+//! |------------------------|----------|-----------------------------------------------------------|
+//! | `geo.continent.code`   | `String` | The code of the continent of the country, e.g. `NA`.      |
+//! | `geo.country.iso_code` | `String` | The ISO 3166-1 alpha-2 code of the country, e.g. `US`, omitted for pseudo-countries such as `A1`. |
+//! | `geo.region.iso_code`  | `String` | The ISO 3166-2 code of the region, e.g. `US-CA`, see `Record::region_codes`. |
+//! | `geo.locality.name`    | `String` | The city.                                                 |
+//! | `geo.postal_code`      | `String` | The postal code.                                          |
+//! | `geo.location.lat`     | `F64`    | The latitude, omitted without real coordinates, see `Record::has_real_coordinates`. |
+//! | `geo.location.lon`     | `F64`    | The longitude, omitted without real coordinates.          |
+//! | `geo.timezone`         | `String` | The IANA time zone, e.g. `America/Los_Angeles`.           |
+//!
+//! The crate does not depend on OpenTelemetry. With the `otel` feature, `AttrValue` converts
+//! into an `opentelemetry::Value`, and `Record::otel_key_values` returns the attributes as
+//! `opentelemetry::KeyValue`s.
+
+use crate::countries::CountryStatus;
+use crate::geo_ip_reader::Record;
+
+/// The value of a geo attribute of `Record::otel_attributes`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    /// A string attribute, e.g. `geo.locality.name`.
+    String(String),
+    /// A floating-point attribute, e.g. `geo.location.lat`.
+    F64(f64),
+}
+
+#[cfg(feature = "otel")]
+impl From<AttrValue> for opentelemetry::Value {
+    fn from(value: AttrValue) -> Self {
+        match value {
+            AttrValue::String(value) => opentelemetry::Value::from(value),
+            AttrValue::F64(value) => opentelemetry::Value::F64(value),
+        }
+    }
+}
+
+impl Record<'_> {
+    /// Returns the geo attributes of the OpenTelemetry semantic conventions of the record, in
+    /// the order of the table of the `otel` module, without those of the fields the record does
+    /// not have.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::geo_ip_reader::Record;
+    /// use ipcap::otel::AttrValue;
+    ///
+    /// let record = Record::new(Country::France).with_city("Paris");
+    ///
+    /// assert_eq!(
+    ///     record.otel_attributes(),
+    ///     [
+    ///         ("geo.continent.code", AttrValue::String("EU".into())),
+    ///         ("geo.country.iso_code", AttrValue::String("FR".into())),
+    ///         ("geo.locality.name", AttrValue::String("Paris".into())),
+    ///         ("geo.timezone", AttrValue::String("Europe/Paris".into())),
+    ///     ]
+    /// );
+    /// ```
+    pub fn otel_attributes(&self) -> Vec<(&'static str, AttrValue)> {
+        let string = |value: &str| AttrValue::String(value.to_string());
+        let mut attributes = Vec::new();
+
+        if let Some(continent) = self.country.continent() {
+            attributes.push(("geo.continent.code", string(continent.alphabetic_code_2())));
+        }
+        if self.country.status() != CountryStatus::Reserved {
+            attributes.push((
+                "geo.country.iso_code",
+                string(self.country.alphabetic_code_2()),
+            ));
+        }
+        if let Some(region) = self.region_codes().iso {
+            attributes.push(("geo.region.iso_code", AttrValue::String(region.into())));
+        }
+        if let Some(city) = self.city() {
+            attributes.push(("geo.locality.name", string(city)));
+        }
+        if let Some(postal_code) = self.postal_code() {
+            attributes.push(("geo.postal_code", string(postal_code)));
+        }
+        if self.has_real_coordinates() {
+            attributes.push(("geo.location.lat", AttrValue::F64(self.latitude)));
+            attributes.push(("geo.location.lon", AttrValue::F64(self.longitude)));
+        }
+        if !self.time_zone.is_empty() {
+            attributes.push(("geo.timezone", string(self.time_zone)));
+        }
+        attributes
+    }
+
+    /// Returns the attributes of `otel_attributes` as OpenTelemetry key-values, e.g. to set them
+    /// on a span with `Span::set_attributes`.
+    #[cfg(feature = "otel")]
+    pub fn otel_key_values(&self) -> Vec<opentelemetry::KeyValue> {
+        self.otel_attributes()
+            .into_iter()
+            .map(|(key, value)| opentelemetry::KeyValue::new(key, value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use crate::geo_ip_reader::GeoIpReader;
+    use std::fs::File;
+
+    #[test]
+    fn test_otel_attributes() {
+        let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+        let record = geo_ip.get_record("108.95.4.105").unwrap();
+        let string = |value: &str| AttrValue::String(value.to_string());
+
+        assert_eq!(
+            record.otel_attributes(),
+            [
+                ("geo.continent.code", string("NA")),
+                ("geo.country.iso_code", string("US")),
+                ("geo.region.iso_code", string("US-CA")),
+                ("geo.locality.name", string("San Diego")),
+                ("geo.postal_code", string("92109")),
+                ("geo.location.lat", AttrValue::F64(32.79769999999999)),
+                ("geo.location.lon", AttrValue::F64(-117.23349999999999)),
+                ("geo.timezone", string("America/Los_Angeles")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_otel_attributes_of_sparse_records() {
+        let string = |value: &str| AttrValue::String(value.to_string());
+
+        // A country spanning several zones, without region, city nor coordinates
+        assert_eq!(
+            Record::new(Country::UnitedStates).otel_attributes(),
+            [
+                ("geo.continent.code", string("NA")),
+                ("geo.country.iso_code", string("US")),
+            ]
+        );
+        // A pseudo-country has neither a continent nor an ISO code
+        assert_eq!(Record::new(Country::AnonymousProxy).otel_attributes(), []);
+        // A region code without an ISO form
+        let record = Record::new(Country::France)
+            .with_region_code("ZZ")
+            .with_coordinates(48.8566, 2.3522);
+        let names: Vec<&str> = record
+            .otel_attributes()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "geo.continent.code",
+                "geo.country.iso_code",
+                "geo.location.lat",
+                "geo.location.lon",
+                "geo.timezone",
+            ]
+        );
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_otel_key_values() {
+        use opentelemetry::{KeyValue, Value};
+
+        let record = Record::new(Country::France)
+            .with_city("Paris")
+            .with_coordinates(48.8566, 2.3522);
+        let key_values = record.otel_key_values();
+
+        assert_eq!(key_values.len(), record.otel_attributes().len());
+        for ((name, value), key_value) in record.otel_attributes().into_iter().zip(&key_values) {
+            assert_eq!(key_value.key.as_str(), name);
+            let round_trip = match &key_value.value {
+                Value::String(value) => AttrValue::String(value.to_string()),
+                Value::F64(value) => AttrValue::F64(*value),
+                other => panic!("unexpected value {:?}", other),
+            };
+            assert_eq!(round_trip, value);
+        }
+        assert!(key_values.contains(&KeyValue::new("geo.locality.name", "Paris")));
+        assert!(key_values.contains(&KeyValue::new("geo.location.lat", 48.8566)));
+    }
+}
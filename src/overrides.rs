@@ -0,0 +1,617 @@
+//! Records of your own ranges that take precedence over the database, e.g. to resolve corporate
+//! networks to office locations.
+
+use crate::countries::Country;
+use crate::errors::{GeoIpReaderError, OverrideError};
+use crate::geo_ip_reader::{GeoIpReader, Provenance, Record, SourceKind};
+use crate::time_zones::known_time_zone;
+use crate::utils::parse_cidr;
+use crate::writer::RecordSpec;
+use std::fs;
+use std::io::{Read, Seek};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// A node of a `PrefixTrie`.
+#[derive(Debug, Clone, Copy, Default)]
+struct TrieNode {
+    /// The indices of the children followed for a 0 and a 1 bit.
+    children: [Option<usize>; 2],
+    /// The value of the prefix ending at this node.
+    value: Option<usize>,
+}
+
+/// A binary trie over the prefixes of one address family, for longest-prefix matches.
+#[derive(Debug, Clone, Default)]
+struct PrefixTrie {
+    /// The nodes, the root first once a prefix was inserted.
+    nodes: Vec<TrieNode>,
+}
+
+impl PrefixTrie {
+    /// Returns the value of the prefix of `prefix` bits of `network`, an address of `bits` bits,
+    /// adding the nodes of the prefix if needed.
+    fn value_mut(&mut self, network: u128, prefix: u8, bits: u8) -> &mut Option<usize> {
+        if self.nodes.is_empty() {
+            self.nodes.push(TrieNode::default());
+        }
+
+        let mut node = 0;
+        for depth in 0..prefix {
+            let bit = ((network >> (bits - 1 - depth)) & 1) as usize;
+            node = match self.nodes[node].children[bit] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node].children[bit] = Some(self.nodes.len() - 1);
+                    self.nodes.len() - 1
+                }
+            };
+        }
+        &mut self.nodes[node].value
+    }
+
+    /// Returns the value of the longest prefix containing `number`, an address of `bits` bits,
+    /// with the length of that prefix.
+    fn longest_match(&self, number: u128, bits: u8) -> Option<(usize, u8)> {
+        let mut node = self.nodes.first()?;
+        let mut longest = node.value.map(|value| (value, 0));
+
+        for depth in 0..bits {
+            let bit = ((number >> (bits - 1 - depth)) & 1) as usize;
+            match node.children[bit] {
+                Some(child) => node = &self.nodes[child],
+                None => break,
+            }
+            if let Some(value) = node.value {
+                longest = Some((value, depth + 1));
+            }
+        }
+        longest
+    }
+}
+
+/// `OverrideTable` maps CIDR ranges of both address families to records, the most specific range
+/// containing an address winning over the others.
+///
+/// A table is built with `insert` or loaded from a CSV file with `from_path`, and consulted
+/// before a database by `LayeredResolver`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::overrides::OverrideTable;
+/// use ipcap::writer::RecordSpec;
+///
+/// let mut overrides = OverrideTable::new();
+/// overrides
+///     .insert("10.0.0.0/8", RecordSpec::new(Country::Germany).city("Berlin"))
+///     .unwrap()
+///     .insert("10.1.0.0/16", RecordSpec::new(Country::France).city("Paris"))
+///     .unwrap();
+///
+/// let record = overrides.lookup("10.1.2.3".parse().unwrap()).unwrap();
+/// assert_eq!(record.city(), Some("Paris"));
+/// assert_eq!(record.netmask, 16);
+/// assert!(overrides.lookup("8.8.8.8".parse().unwrap()).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OverrideTable {
+    /// The IPv4 ranges, mapped to indices of `records`.
+    v4: PrefixTrie,
+    /// The IPv6 ranges, mapped to indices of `records`.
+    v6: PrefixTrie,
+    records: Vec<Record<'static>>,
+}
+
+impl OverrideTable {
+    /// Constructs an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps every address of a CIDR range, e.g. `10.0.0.0/8`, to a record, replacing the record
+    /// of the same range if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OverrideError::InvalidCidr` if the range is not a valid CIDR.
+    pub fn insert(&mut self, cidr: &str, record: RecordSpec) -> Result<&mut Self, OverrideError> {
+        self.insert_record(cidr, record.to_record())
+    }
+
+    fn insert_record(
+        &mut self,
+        cidr: &str,
+        record: Record<'static>,
+    ) -> Result<&mut Self, OverrideError> {
+        let (network, prefix, ipv6) =
+            parse_cidr(cidr).ok_or_else(|| OverrideError::InvalidCidr(cidr.to_string()))?;
+
+        let value = if ipv6 {
+            self.v6.value_mut(network, prefix, 128)
+        } else {
+            self.v4.value_mut(network, prefix, 32)
+        };
+        match *value {
+            Some(index) => self.records[index] = record,
+            None => {
+                *value = Some(self.records.len());
+                self.records.push(record);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Parses a table from CSV text.
+    ///
+    /// Each line holds `cidr,country,region,city,lat,lon,time_zone`, where the country is a 2 or
+    /// 3-letter code and the fields after it may be left empty or omitted. The time zone
+    /// defaults to the one of the country and region. Fields cannot hold commas. Blank lines,
+    /// lines starting with `#` and a first line starting with `cidr` are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OverrideError::InvalidLine` with the number of the first line that cannot be
+    /// parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::overrides::OverrideTable;
+    ///
+    /// let overrides = OverrideTable::parse(
+    ///     "cidr,country,region,city,lat,lon,time_zone\n\
+    ///      10.0.0.0/8,DE,,Berlin,52.52,13.405,Europe/Berlin\n\
+    ///      2001:db8::/32,US,CA,San Diego\n",
+    /// )
+    /// .unwrap();
+    ///
+    /// let record = overrides.lookup("2001:db8::1".parse().unwrap()).unwrap();
+    /// assert_eq!(record.time_zone, "America/Los_Angeles");
+    /// assert!(OverrideTable::parse("10.0.0.0/33,DE").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<Self, OverrideError> {
+        let mut table = OverrideTable::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || (index == 0 && line.to_ascii_lowercase().starts_with("cidr"))
+            {
+                continue;
+            }
+
+            let invalid = |reason: String| OverrideError::InvalidLine {
+                line: index + 1,
+                reason,
+            };
+            let (cidr, record) = parse_line(line).map_err(invalid)?;
+            table
+                .insert_record(cidr, record)
+                .map_err(|err| invalid(err.to_string()))?;
+        }
+        Ok(table)
+    }
+
+    /// Loads a table from a CSV file, in the format of `parse`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OverrideError::Read` if the file cannot be read, or the errors of `parse`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, OverrideError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| OverrideError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text)
+    }
+
+    /// Looks up the record of the most specific range containing an address.
+    ///
+    /// # Returns
+    ///
+    /// (`Option<Record<'static>>`): The record, its netmask being the prefix length of the range
+    /// and its provenance `SourceKind::Override`, or `None` if no range contains the address.
+    pub fn lookup(&self, ip: IpAddr) -> Option<Record<'static>> {
+        let (trie, number, bits) = match ip {
+            IpAddr::V4(v4) => (&self.v4, u128::from(u32::from(v4)), 32),
+            IpAddr::V6(v6) => (&self.v6, u128::from(v6), 128),
+        };
+        let (index, prefix) = trie.longest_match(number, bits)?;
+        Some(
+            self.records[index]
+                .clone()
+                .with_netmask(usize::from(prefix), usize::from(bits))
+                .with_provenance(Provenance {
+                    source: SourceKind::Override,
+                    edition: None,
+                    db_build_date: None,
+                }),
+        )
+    }
+
+    /// Returns the number of ranges of the table.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns whether the table has no record.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// Parses a line of an override file into its range and record.
+fn parse_line(line: &str) -> Result<(&str, Record<'static>), String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() < 2 || fields.len() > 7 {
+        return Err(format!("expected 2 to 7 fields, found {}", fields.len()));
+    }
+    let field = |index: usize| fields.get(index).copied().filter(|field| !field.is_empty());
+
+    let cidr = fields[0];
+    if parse_cidr(cidr).is_none() {
+        return Err(format!("invalid CIDR {:?}", cidr));
+    }
+    let country = Country::from_code(fields[1])
+        .ok_or_else(|| format!("unknown country code {:?}", fields[1]))?;
+
+    let mut spec = RecordSpec::new(country);
+    if let Some(region_code) = field(2) {
+        spec = spec.region_code(region_code);
+    }
+    if let Some(city) = field(3) {
+        spec = spec.city(city);
+    }
+    match (field(4), field(5)) {
+        (Some(latitude), Some(longitude)) => {
+            let latitude = latitude
+                .parse::<f64>()
+                .ok()
+                .filter(|latitude| (-90.0..=90.0).contains(latitude))
+                .ok_or_else(|| format!("invalid latitude {:?}", latitude))?;
+            let longitude = longitude
+                .parse::<f64>()
+                .ok()
+                .filter(|longitude| (-180.0..=180.0).contains(longitude))
+                .ok_or_else(|| format!("invalid longitude {:?}", longitude))?;
+            spec = spec.coordinates(latitude, longitude);
+        }
+        (None, None) => {}
+        _ => return Err("latitude and longitude must be given together".to_string()),
+    }
+
+    let mut record = spec.to_record();
+    if let Some(time_zone) = field(6) {
+        record.time_zone = known_time_zone(time_zone)
+            .ok_or_else(|| format!("unknown time zone {:?}", time_zone))?;
+    }
+    Ok((cidr, record))
+}
+
+/// `LayeredResolver` looks up addresses in an `OverrideTable` first, and in a database for the
+/// addresses that no override covers.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+/// use ipcap::overrides::{LayeredResolver, OverrideTable};
+/// use ipcap::writer::RecordSpec;
+///
+/// let mut overrides = OverrideTable::new();
+/// overrides
+///     .insert("108.95.4.0/24", RecordSpec::new(Country::Germany).city("Berlin"))
+///     .unwrap();
+///
+/// let geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let mut resolver = LayeredResolver::new(overrides, geo_ip);
+///
+/// let record = resolver.lookup("108.95.4.105").unwrap().unwrap();
+/// assert_eq!(record.city(), Some("Berlin"));
+/// let record = resolver.lookup("108.95.5.1").unwrap().unwrap();
+/// assert_eq!(record.country, Country::UnitedStates);
+/// ```
+#[derive(Debug)]
+pub struct LayeredResolver<R>
+where
+    R: Read + Seek,
+{
+    overrides: OverrideTable,
+    reader: GeoIpReader<R>,
+}
+
+impl<R> LayeredResolver<R>
+where
+    R: Read + Seek,
+{
+    /// Constructs a resolver consulting `overrides` before `reader`.
+    pub fn new(overrides: OverrideTable, reader: GeoIpReader<R>) -> Self {
+        LayeredResolver { overrides, reader }
+    }
+
+    /// Looks up the record of an IP address, in the overrides first.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address, v4 or v6.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<Record<'static>>, GeoIpReaderError>`): The record of the most specific
+    /// override containing the address, otherwise the record of the database, or `None` if the
+    /// database does not have the address either.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidAddress` if `ip` is not a valid IP address, or the
+    /// errors of `GeoIpReader::find_record` for the addresses looked up in the database.
+    pub fn lookup(&mut self, ip: &str) -> Result<Option<Record<'static>>, GeoIpReaderError> {
+        let addr = ip
+            .parse::<IpAddr>()
+            .map_err(|source| GeoIpReaderError::InvalidAddress {
+                input: ip.to_string(),
+                source,
+            })?;
+
+        match self.overrides.lookup(addr) {
+            Some(record) => Ok(Some(record)),
+            None => self.reader.find_record(ip),
+        }
+    }
+
+    /// Returns the override table.
+    pub fn overrides(&self) -> &OverrideTable {
+        &self.overrides
+    }
+
+    /// Returns the database reader.
+    pub fn reader(&self) -> &GeoIpReader<R> {
+        &self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::DatabaseBuilder;
+    use std::cell::Cell;
+    use std::io::{self, Cursor, SeekFrom};
+    use std::rc::Rc;
+
+    fn lookup(overrides: &OverrideTable, ip: &str) -> Option<Record<'static>> {
+        overrides.lookup(ip.parse().unwrap())
+    }
+
+    /// A reader counting the reads and seeks made through it.
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.calls.set(self.calls.get() + 1);
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.calls.set(self.calls.get() + 1);
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_longest_prefix_precedence() {
+        let mut overrides = OverrideTable::new();
+        overrides
+            .insert("10.1.2.0/24", RecordSpec::new(Country::Japan))
+            .unwrap()
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap()
+            .insert("10.1.0.0/16", RecordSpec::new(Country::France))
+            .unwrap();
+
+        let record = lookup(&overrides, "10.1.2.3").unwrap();
+        assert_eq!((record.country, record.netmask), (Country::Japan, 24));
+        let record = lookup(&overrides, "10.1.3.4").unwrap();
+        assert_eq!((record.country, record.netmask), (Country::France, 16));
+        let record = lookup(&overrides, "10.200.0.1").unwrap();
+        assert_eq!((record.country, record.netmask), (Country::Germany, 8));
+        assert_eq!(lookup(&overrides, "11.0.0.1"), None);
+
+        // The last record of a range replaces the previous ones
+        overrides
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Austria))
+            .unwrap();
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(
+            lookup(&overrides, "10.200.0.1").unwrap().country,
+            Country::Austria
+        );
+    }
+
+    #[test]
+    fn test_v4_and_v6_overrides() {
+        let mut overrides = OverrideTable::new();
+        overrides
+            .insert("0.0.0.0/0", RecordSpec::new(Country::Germany))
+            .unwrap()
+            .insert("2001:db8::/32", RecordSpec::new(Country::France))
+            .unwrap()
+            .insert("2001:db8:1::1/128", RecordSpec::new(Country::Japan))
+            .unwrap();
+
+        let record = lookup(&overrides, "203.0.113.1").unwrap();
+        assert_eq!((record.country, record.max_netmask), (Country::Germany, 32));
+        assert_eq!(record.netmask, 0);
+        assert_eq!(
+            lookup(&overrides, "2001:db8:2::1").unwrap().country,
+            Country::France
+        );
+        let record = lookup(&overrides, "2001:db8:1::1").unwrap();
+        assert_eq!((record.country, record.netmask), (Country::Japan, 128));
+        assert_eq!(lookup(&overrides, "2001:db9::1"), None);
+        assert!(matches!(
+            overrides.insert("2001:db8::/129", RecordSpec::new(Country::Japan)),
+            Err(OverrideError::InvalidCidr(_))
+        ));
+    }
+
+    #[test]
+    fn test_v6_longest_prefix_precedence() {
+        let mut overrides = OverrideTable::new();
+        overrides
+            .insert("2001:db8:1:2::/64", RecordSpec::new(Country::Japan))
+            .unwrap()
+            .insert("2001:db8:1::/48", RecordSpec::new(Country::France))
+            .unwrap();
+
+        let record = lookup(&overrides, "2001:db8:1:2::5").unwrap();
+        assert_eq!((record.country, record.netmask), (Country::Japan, 64));
+        let record = lookup(&overrides, "2001:db8:1:3::5").unwrap();
+        assert_eq!((record.country, record.netmask), (Country::France, 48));
+        // The segments past the first ones tell the ranges apart
+        assert_eq!(lookup(&overrides, "2001:db8:2::5"), None);
+        assert_eq!(lookup(&overrides, "2001:db8::1"), None);
+        assert_eq!(lookup(&overrides, "2001:db9:1:2::5"), None);
+    }
+
+    #[test]
+    fn test_fall_through() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.0.0.0/8", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let reader = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+        let mut overrides = OverrideTable::new();
+        overrides
+            .insert("8.8.8.0/24", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let mut resolver = LayeredResolver::new(overrides, reader);
+
+        let record = resolver.lookup("8.8.8.8").unwrap().unwrap();
+        assert_eq!(record.country, Country::Germany);
+        assert_eq!(record.provenance.unwrap().source, SourceKind::Override);
+        let record = resolver.lookup("8.8.4.4").unwrap().unwrap();
+        assert_eq!(record.country, Country::UnitedStates);
+        assert_eq!(record.provenance.unwrap(), resolver.reader().provenance());
+        assert_eq!(resolver.lookup("10.0.0.1").unwrap(), None);
+        assert!(matches!(
+            resolver.lookup("8.8.8"),
+            Err(GeoIpReaderError::InvalidAddress { .. })
+        ));
+    }
+
+    #[test]
+    fn test_override_skips_reader() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("8.0.0.0/8", RecordSpec::new(Country::UnitedStates))
+            .unwrap();
+        let calls = Rc::new(Cell::new(0));
+        let reader = GeoIpReader::from_reader(CountingReader {
+            inner: Cursor::new(builder.build()),
+            calls: Rc::clone(&calls),
+        })
+        .unwrap();
+        let mut overrides = OverrideTable::new();
+        overrides
+            .insert("8.8.8.0/24", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let mut resolver = LayeredResolver::new(overrides, reader);
+
+        calls.set(0);
+        resolver.lookup("8.8.8.8").unwrap();
+        assert_eq!(calls.get(), 0);
+        resolver.lookup("8.8.4.4").unwrap();
+        assert!(calls.get() > 0);
+    }
+
+    #[test]
+    fn test_parse() {
+        let overrides = OverrideTable::parse(
+            "CIDR, country, region, city, lat, lon, time_zone\n\
+             \n\
+             # Offices\n\
+             10.0.0.0/8, DEU, , Berlin, 52.52, 13.405, Europe/Berlin\n\
+             198.51.100.0/24,US,NY,New York,40.71,-74.01\n\
+             2001:db8::/32,fr\n",
+        )
+        .unwrap();
+        assert_eq!(overrides.len(), 3);
+
+        let record = lookup(&overrides, "10.1.1.1").unwrap();
+        assert_eq!(record.country, Country::Germany);
+        assert_eq!(
+            (record.region_code(), record.city()),
+            (None, Some("Berlin"))
+        );
+        assert_eq!((record.latitude, record.longitude), (52.52, 13.405));
+        assert_eq!(record.time_zone, "Europe/Berlin");
+        let record = lookup(&overrides, "198.51.100.7").unwrap();
+        assert_eq!(record.time_zone, "America/New_York");
+        let record = lookup(&overrides, "2001:db8::1").unwrap();
+        assert_eq!((record.country, record.city()), (Country::France, None));
+        assert_eq!(record.time_zone, "Europe/Paris");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("10.0.0.0/8", 1, "expected 2 to 7 fields, found 1"),
+            ("10.0.0.0/8,DE,,,,,,", 1, "expected 2 to 7 fields, found 8"),
+            (
+                "# comment\n10.0.0.0/33,DE",
+                2,
+                "invalid CIDR \"10.0.0.0/33\"",
+            ),
+            (
+                "10.0.0.0/8,DE\n\n10.0.0.0,DE",
+                3,
+                "invalid CIDR \"10.0.0.0\"",
+            ),
+            ("10.0.0.0/8,XX", 1, "unknown country code \"XX\""),
+            ("10.0.0.0/8,DE,,,91,0", 1, "invalid latitude \"91\""),
+            ("10.0.0.0/8,DE,,,0,east", 1, "invalid longitude \"east\""),
+            (
+                "10.0.0.0/8,DE,,,52.52",
+                1,
+                "latitude and longitude must be given together",
+            ),
+            (
+                "10.0.0.0/8,DE,,,,,Europe/Atlantis",
+                1,
+                "unknown time zone \"Europe/Atlantis\"",
+            ),
+        ];
+
+        for (text, line, reason) in cases {
+            let err = OverrideTable::parse(text).unwrap_err();
+            assert!(
+                matches!(&err, OverrideError::InvalidLine { line: l, reason: r } if *l == line && r == reason),
+                "{:?}: {}",
+                text,
+                err
+            );
+        }
+        assert_eq!(
+            OverrideTable::parse("cidr\n10.0.0.0/8,XX")
+                .unwrap_err()
+                .to_string(),
+            "Invalid override on line 2: unknown country code \"XX\""
+        );
+        assert!(matches!(
+            OverrideTable::from_path("/nonexistent/overrides.csv"),
+            Err(OverrideError::Read { .. })
+        ));
+    }
+}
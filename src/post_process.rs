@@ -0,0 +1,325 @@
+//! Post-processors of the displayed fields of records, to add computed fields such as an
+//! internal region grouping, rename fields or drop them before any formatter sees them.
+//!
+//! The processors registered on a `GeoIpReader`, with `GeoIpReader::add_post_processor` or
+//! `GeoIpReaderBuilder::post_processor`, run in registration order on the `RecordFields` of each
+//! record formatted through `GeoIpReader::record_fields`: by the `Annotator` of `ipcap annotate`
+//! and `ipcap batch`, by the single lookups and ranges of the CLI, and by
+//! `AsyncIpCap::lookup_fields`. They only see the record itself through a shared reference, so
+//! they cannot alter what the lookups return.
+
+use crate::geo_ip_reader::Record;
+use std::borrow::Cow;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// The alpha-2 codes of the member states of the European Union.
+const EU_MEMBERS: [&str; 27] = [
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU", "IE", "IT",
+    "LT", "LU", "LV", "MT", "NL", "PL", "PT", "RO", "SE", "SI", "SK",
+];
+
+/// The names of the fields added by the built-in processors, which `--append` accepts next to
+/// `utils::RECORD_FIELD_NAMES`.
+pub const BUILTIN_FIELD_NAMES: [&str; 2] = ["country_code_modern", "is_eu"];
+
+/// The names and values of the displayed fields of a record, in order.
+pub type FieldList = Vec<(Cow<'static, str>, Option<String>)>;
+
+/// The displayed fields of a record, as collected by `utils::record_fields_in`, in the mutable
+/// form handed to the post-processors.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::Record;
+/// use ipcap::locale::Locale;
+/// use ipcap::post_process::RecordFields;
+///
+/// let record = Record::new(Country::France);
+/// let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+///
+/// fields.insert("region_group", Some("emea".to_string()));
+/// fields.rename("country_code", "country");
+/// fields.remove("utc_offset");
+///
+/// assert_eq!(fields.get("region_group"), Some("emea"));
+/// assert_eq!(fields.get("country"), Some("FR"));
+/// assert!(!fields.contains("country_code"));
+/// assert!(!fields.contains("utc_offset"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordFields<'r> {
+    record: &'r Record<'r>,
+    ip: Option<IpAddr>,
+    fields: FieldList,
+}
+
+impl<'r> RecordFields<'r> {
+    /// Collects the fields of a record, as `utils::record_fields_in`.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record.
+    /// * `ip` - The address the record was looked up for, if any.
+    /// * `unix_ts` - The instant of the time zone abbreviation and UTC offset.
+    /// * `locale` - The language of the names.
+    pub fn new(
+        record: &'r Record<'r>,
+        ip: Option<IpAddr>,
+        unix_ts: i64,
+        locale: crate::locale::Locale,
+    ) -> Self {
+        let fields = crate::utils::record_fields_in(record, unix_ts, locale)
+            .into_iter()
+            .map(|(name, value)| (Cow::Borrowed(name), value))
+            .collect();
+        RecordFields { record, ip, fields }
+    }
+
+    /// The record the fields were collected from.
+    pub fn record(&self) -> &Record<'r> {
+        self.record
+    }
+
+    /// The address the record was looked up for, if known.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
+
+    /// Returns the value of a field, `None` if the field is missing or has no value.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name)
+            .and_then(|index| self.fields[index].1.as_deref())
+    }
+
+    /// Returns whether the field is present, with or without a value.
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Sets the value of a field, in place if it is present, after the others otherwise.
+    pub fn insert(&mut self, name: impl Into<Cow<'static, str>>, value: Option<String>) {
+        let name = name.into();
+        match self.position(&name) {
+            Some(index) => self.fields[index].1 = value,
+            None => self.fields.push((name, value)),
+        }
+    }
+
+    /// Removes a field, returning whether it was present.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let present = self.contains(name);
+        self.fields.retain(|(key, _)| key != name);
+        present
+    }
+
+    /// Renames a field in place, replacing any field of the new name, and returns whether it
+    /// was present.
+    pub fn rename(&mut self, from: &str, to: impl Into<Cow<'static, str>>) -> bool {
+        if !self.contains(from) {
+            return false;
+        }
+        let to = to.into();
+        if to != from {
+            self.fields.retain(|(key, _)| *key != to);
+        }
+        if let Some(index) = self.position(from) {
+            self.fields[index].0 = to;
+        }
+        true
+    }
+
+    /// Iterates over the names and values of the fields, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_deref()))
+    }
+
+    /// Returns the names and values of the fields, in order.
+    pub fn into_vec(self) -> FieldList {
+        self.fields
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|(key, _)| key == name)
+    }
+}
+
+/// A post-processor, shared between the readers and threads it is registered on.
+pub type PostProcessor = Arc<dyn Fn(&mut RecordFields<'_>) + Send + Sync>;
+
+/// The post-processors registered on a reader, run in registration order.
+#[derive(Clone, Default)]
+pub struct PostProcessors(Vec<PostProcessor>);
+
+impl PostProcessors {
+    /// Constructs an empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a processor after the others.
+    pub fn add<F>(&mut self, processor: F)
+    where
+        F: Fn(&mut RecordFields<'_>) + Send + Sync + 'static,
+    {
+        self.0.push(Arc::new(processor));
+    }
+
+    /// Runs the processors on the fields, in registration order.
+    pub fn apply(&self, fields: &mut RecordFields<'_>) {
+        for processor in &self.0 {
+            processor(fields);
+        }
+    }
+
+    /// Returns the number of processors.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether no processor is registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for PostProcessors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PostProcessors({})", self.0.len())
+    }
+}
+
+/// A built-in processor adding the `is_eu` field: `true` for the records of a member state of
+/// the European Union, `false` otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::geo_ip_reader::GeoIpReaderBuilder;
+/// use ipcap::locale::Locale;
+/// use ipcap::post_process::add_is_eu_flag;
+///
+/// let mut path = dirs::home_dir().unwrap();
+/// path.push("ipcap/geo_ip_city_v4.dat");
+///
+/// let mut geo_ip = GeoIpReaderBuilder::new()
+///     .post_processor(add_is_eu_flag())
+///     .open(path)
+///     .unwrap();
+/// let record = geo_ip.get_record("108.95.4.105").unwrap();
+///
+/// let fields = geo_ip.record_fields(&record, None, 0, Locale::En);
+/// assert_eq!(fields.get("is_eu"), Some("false"));
+/// ```
+pub fn add_is_eu_flag() -> impl Fn(&mut RecordFields<'_>) + Send + Sync + 'static {
+    |fields| {
+        let code = fields.record().country.alphabetic_code_2();
+        let is_eu = EU_MEMBERS.contains(&code);
+        fields.insert("is_eu", Some(is_eu.to_string()));
+    }
+}
+
+/// A built-in processor adding the `country_code_modern` field to the records of a deprecated
+/// country: the alpha-2 code of its current successor, see `Record::modernized_country`. The
+/// records of other countries are left as they are.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::Record;
+/// use ipcap::locale::Locale;
+/// use ipcap::post_process::{add_modern_country_code, RecordFields};
+///
+/// let processor = add_modern_country_code();
+///
+/// let record = Record::new(Country::MetropolitanFrance);
+/// let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+/// processor(&mut fields);
+/// assert_eq!(fields.get("country_code_modern"), Some("FR"));
+///
+/// let record = Record::new(Country::France);
+/// let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+/// processor(&mut fields);
+/// assert!(!fields.contains("country_code_modern"));
+/// ```
+pub fn add_modern_country_code() -> impl Fn(&mut RecordFields<'_>) + Send + Sync + 'static {
+    |fields| {
+        let record = fields.record();
+        let modern = record.modernized_country();
+        if modern != record.country {
+            fields.insert(
+                "country_code_modern",
+                Some(modern.alphabetic_code_2().to_string()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use crate::locale::Locale;
+
+    #[test]
+    fn test_record_fields_edits() {
+        let record = Record::new(Country::France).with_city("Paris");
+        let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+        let names = |fields: &RecordFields| -> Vec<String> {
+            fields.iter().map(|(name, _)| name.to_string()).collect()
+        };
+        let before = names(&fields);
+
+        // In place, or after the others
+        fields.insert("city", Some("Lutetia".to_string()));
+        fields.insert("region_group", None);
+        assert_eq!(fields.get("city"), Some("Lutetia"));
+        assert!(fields.contains("region_group"));
+        assert_eq!(fields.get("region_group"), None);
+        assert_eq!(names(&fields)[..before.len()], before[..]);
+        assert_eq!(names(&fields).last().unwrap(), "region_group");
+
+        // Renaming over another field replaces it
+        assert!(fields.rename("city", "region_group"));
+        assert_eq!(fields.get("region_group"), Some("Lutetia"));
+        assert_eq!(names(&fields).len(), before.len());
+        assert!(!fields.rename("city", "town"));
+
+        assert!(fields.remove("region_group"));
+        assert!(!fields.remove("region_group"));
+        assert_eq!(names(&fields).len(), before.len() - 1);
+    }
+
+    #[test]
+    fn test_processors_run_in_order() {
+        let record = Record::new(Country::Germany);
+        let mut processors = PostProcessors::new();
+        processors.add(add_is_eu_flag());
+        processors.add(|fields: &mut RecordFields| {
+            let flag = fields.get("is_eu").map(str::to_string);
+            fields.insert("region_group", flag.map(|flag| format!("eu={}", flag)));
+        });
+        processors.add(|fields: &mut RecordFields| {
+            fields.rename("region_group", "group");
+        });
+        assert_eq!(processors.len(), 3);
+        assert_eq!(format!("{:?}", processors), "PostProcessors(3)");
+
+        let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+        processors.apply(&mut fields);
+        assert_eq!(fields.get("is_eu"), Some("true"));
+        assert_eq!(fields.get("group"), Some("eu=true"));
+        assert!(!fields.contains("region_group"));
+
+        let record = Record::new(Country::Switzerland);
+        let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+        processors.apply(&mut fields);
+        assert_eq!(fields.get("group"), Some("eu=false"));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::errors::GeoIpReaderError;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// An RFC 7807 problem details object describing a failed lookup, e.g. to be returned as an
+/// `application/problem+json` body.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::errors::GeoIpReaderError;
+/// use ipcap::problem::Problem;
+///
+/// let problem = Problem::from(GeoIpReaderError::AddressNotFound("10.0.0.1".to_string()));
+///
+/// assert_eq!(problem.status, 404);
+/// assert_eq!(problem.title, "Address not found");
+/// assert_eq!(problem.detail, "Address not found in the database: 10.0.0.1");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Problem {
+    /// A URI identifying the problem type.
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub type_uri: &'static str,
+    /// A short summary of the problem type.
+    pub title: &'static str,
+    /// The HTTP status code.
+    pub status: u16,
+    /// The explanation of this occurrence of the problem.
+    pub detail: String,
+}
+
+impl GeoIpReaderError {
+    /// Converts the error into an RFC 7807 problem.
+    ///
+    /// Invalid addresses map to 400, reserved addresses and addresses missing from the database
+    /// to 404, a missing or corrupt database to 503 and every other error to 500.
+    pub fn to_problem(&self) -> Problem {
+        let (type_uri, title, status) = self.problem_type();
+        Problem {
+            type_uri,
+            title,
+            status,
+            detail: self.to_string(),
+        }
+    }
+
+    /// The type, title and status of the problem of each variant.
+    fn problem_type(&self) -> (&'static str, &'static str, u16) {
+        macro_rules! docs {
+            ($variant: literal) => {
+                concat!(
+                    "https://docs.rs/ipcap/latest/ipcap/errors/enum.GeoIpReaderError.html#variant.",
+                    $variant
+                )
+            };
+        }
+
+        // No wildcard, so that a new variant cannot be added without mapping it
+        match self {
+            GeoIpReaderError::InvalidAddress { .. } => {
+                (docs!("InvalidAddress"), "Invalid IP address", 400)
+            }
+            GeoIpReaderError::AddressNotFound(_) => {
+                (docs!("AddressNotFound"), "Address not found", 404)
+            }
+            GeoIpReaderError::ReservedAddress(_) => {
+                (docs!("ReservedAddress"), "Reserved address", 404)
+            }
+            GeoIpReaderError::MissingCoordinates(_) => {
+                (docs!("MissingCoordinates"), "Missing coordinates", 404)
+            }
+            GeoIpReaderError::AddressFamilyMismatch(_) => (
+                docs!("AddressFamilyMismatch"),
+                "Address family mismatch",
+                400,
+            ),
+            GeoIpReaderError::InvalidRange(_) => (docs!("InvalidRange"), "Invalid range", 400),
+            GeoIpReaderError::OpenFileError => {
+                (docs!("OpenFileError"), "Database unavailable", 503)
+            }
+            GeoIpReaderError::UntrustedDatabaseFile { .. } => (
+                docs!("UntrustedDatabaseFile"),
+                "Untrusted database file",
+                503,
+            ),
+            GeoIpReaderError::CorruptDatabase => {
+                (docs!("CorruptDatabase"), "Corrupt database", 503)
+            }
+            GeoIpReaderError::Io(_) => (docs!("Io"), "Database unreadable", 503),
+            GeoIpReaderError::DatabaseTooSmall { .. } => {
+                (docs!("DatabaseTooSmall"), "Database too small", 503)
+            }
+            GeoIpReaderError::InvalidDatabaseType => {
+                (docs!("InvalidDatabaseType"), "Invalid database type", 500)
+            }
+            GeoIpReaderError::GetHostByNameError => {
+                (docs!("GetHostByNameError"), "Host lookup failed", 500)
+            }
+            GeoIpReaderError::GlobalAlreadyInitialized => (
+                docs!("GlobalAlreadyInitialized"),
+                "Global reader already initialized",
+                500,
+            ),
+        }
+    }
+}
+
+impl From<GeoIpReaderError> for Problem {
+    fn from(err: GeoIpReaderError) -> Self {
+        err.to_problem()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors() -> Vec<GeoIpReaderError> {
+        vec![
+            GeoIpReaderError::InvalidAddress {
+                input: "-".to_string(),
+                source: "-".parse::<std::net::IpAddr>().unwrap_err(),
+            },
+            GeoIpReaderError::AddressNotFound("10.0.0.1".to_string()),
+            GeoIpReaderError::ReservedAddress("10.0.0.1".to_string()),
+            GeoIpReaderError::MissingCoordinates("1.2.3.4".to_string()),
+            GeoIpReaderError::AddressFamilyMismatch("1.2.3.4".to_string()),
+            GeoIpReaderError::InvalidRange(crate::errors::RangeError::Invalid("-".to_string())),
+            GeoIpReaderError::OpenFileError,
+            GeoIpReaderError::CorruptDatabase,
+            GeoIpReaderError::Io(std::io::ErrorKind::PermissionDenied.into()),
+            GeoIpReaderError::DatabaseTooSmall {
+                path: "geo_ip_city_v4.dat".into(),
+                size: 0,
+                minimum: 13,
+            },
+            GeoIpReaderError::InvalidDatabaseType,
+            GeoIpReaderError::GetHostByNameError,
+            GeoIpReaderError::GlobalAlreadyInitialized,
+        ]
+    }
+
+    #[test]
+    fn test_problem_for_every_variant() {
+        let problems: Vec<(u16, &str)> = errors()
+            .iter()
+            .map(|err| {
+                let problem = err.to_problem();
+                (problem.status, problem.title)
+            })
+            .collect();
+
+        assert_eq!(
+            problems,
+            vec![
+                (400, "Invalid IP address"),
+                (404, "Address not found"),
+                (404, "Reserved address"),
+                (404, "Missing coordinates"),
+                (400, "Address family mismatch"),
+                (400, "Invalid range"),
+                (503, "Database unavailable"),
+                (503, "Corrupt database"),
+                (503, "Database unreadable"),
+                (503, "Database too small"),
+                (500, "Invalid database type"),
+                (500, "Host lookup failed"),
+                (500, "Global reader already initialized"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_problem_from_error() {
+        let problem: Problem = GeoIpReaderError::CorruptDatabase.into();
+
+        assert!(problem.type_uri.ends_with("#variant.CorruptDatabase"));
+        assert_eq!(problem.detail, "Corrupt database");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_problem_json() {
+        let problem = GeoIpReaderError::AddressNotFound("10.0.0.1".to_string()).to_problem();
+
+        assert_eq!(
+            serde_json::to_value(problem).unwrap(),
+            serde_json::json!({
+                "type": "https://docs.rs/ipcap/latest/ipcap/errors/enum.GeoIpReaderError.html#variant.AddressNotFound",
+                "title": "Address not found",
+                "status": 404,
+                "detail": "Address not found in the database: 10.0.0.1",
+            })
+        );
+    }
+}
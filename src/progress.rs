@@ -0,0 +1,240 @@
+//! Progress reporting for the operations that read a whole database, e.g.
+//! `CityIndex::build_with_progress`.
+//!
+//! The operations call the `Progress` they are given at a bounded rate, every
+//! `REPORT_EVERY_ITEMS` items or `REPORT_INTERVAL`, whichever comes first, and once more when
+//! they are done.
+
+use std::time::{Duration, Instant};
+
+/// The number of items processed between two reports.
+pub const REPORT_EVERY_ITEMS: u64 = 4096;
+
+/// The time elapsed between two reports, when fewer than `REPORT_EVERY_ITEMS` items were
+/// processed meanwhile.
+pub const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A receiver of the progress of a long-running operation.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::fs::File;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+/// use ipcap::progress::Progress;
+/// use ipcap::reverse::CityIndex;
+///
+/// struct Last(Cell<(u64, Option<u64>)>);
+///
+/// impl Progress for Last {
+///     fn report(&self, done: u64, total: Option<u64>) {
+///         self.0.set((done, total));
+///     }
+/// }
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let last = Last(Cell::new((0, None)));
+/// CityIndex::build_with_progress(&mut geo_ip, Some(&last)).unwrap();
+///
+/// let (done, total) = last.0.get();
+/// assert_eq!(Some(done), total);
+/// ```
+pub trait Progress {
+    /// Reports that `done` items out of `total` are processed, `total` being `None` when it is
+    /// unknown. The last report of an operation has `done` equal to `total`.
+    fn report(&self, done: u64, total: Option<u64>);
+}
+
+/// `StderrProgress` prints the progress to stderr, one line per report with the percentage
+/// when the total is known and the throughput.
+///
+/// The lines read `progress: 42% (420/1000 records) in 1.20s (350 records/s)`, or `done: 1000
+/// records in 2.86s (350 records/s)` for the last one.
+#[derive(Debug)]
+pub struct StderrProgress {
+    unit: &'static str,
+    start: Instant,
+}
+
+impl StderrProgress {
+    /// Constructs a `StderrProgress` counting the items as `unit`, e.g. "records", from now on.
+    pub fn new(unit: &'static str) -> Self {
+        StderrProgress {
+            unit,
+            start: Instant::now(),
+        }
+    }
+
+    /// Formats the line of a report.
+    fn line(&self, done: u64, total: Option<u64>, elapsed: Duration) -> String {
+        let rate = done as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        let unit = self.unit;
+        match total {
+            Some(total) if done >= total => format!(
+                "done: {} {} in {:.2?} ({:.0} {}/s)",
+                done, unit, elapsed, rate, unit
+            ),
+            Some(total) => format!(
+                "progress: {}% ({}/{} {}) in {:.2?} ({:.0} {}/s)",
+                done.saturating_mul(100) / total.max(1),
+                done,
+                total,
+                unit,
+                elapsed,
+                rate,
+                unit
+            ),
+            None => format!(
+                "progress: {} {} in {:.2?} ({:.0} {}/s)",
+                done, unit, elapsed, rate, unit
+            ),
+        }
+    }
+}
+
+impl Progress for StderrProgress {
+    fn report(&self, done: u64, total: Option<u64>) {
+        eprintln!("{}", self.line(done, total, self.start.elapsed()));
+    }
+}
+
+/// Calls a `Progress` at a bounded rate, so that reporting cannot slow an operation down.
+/// Without a `Progress`, counting items is a single branch.
+pub(crate) struct Throttle<'a> {
+    progress: Option<&'a dyn Progress>,
+    every: u64,
+    interval: Duration,
+    done: u64,
+    last_done: u64,
+    last_time: Option<Instant>,
+}
+
+impl<'a> Throttle<'a> {
+    pub(crate) fn new(progress: Option<&'a dyn Progress>) -> Self {
+        Throttle {
+            progress,
+            every: REPORT_EVERY_ITEMS,
+            interval: REPORT_INTERVAL,
+            done: 0,
+            last_done: 0,
+            last_time: progress.map(|_| Instant::now()),
+        }
+    }
+
+    /// Counts an item out of `total`, reporting when enough items or time went by since the
+    /// last report.
+    pub(crate) fn tick(&mut self, total: Option<u64>) {
+        let Some(progress) = self.progress else {
+            return;
+        };
+        self.done = self.done.saturating_add(1);
+
+        let now = Instant::now();
+        let elapsed = self
+            .last_time
+            .map_or(Duration::MAX, |last_time| now.duration_since(last_time));
+        if self.done - self.last_done >= self.every || elapsed >= self.interval {
+            progress.report(self.done, total);
+            self.last_done = self.done;
+            self.last_time = Some(now);
+        }
+    }
+
+    /// Reports the end of the operation, all the counted items being processed.
+    pub(crate) fn finish(&mut self) {
+        if let Some(progress) = self.progress {
+            progress.report(self.done, Some(self.done));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<(u64, Option<u64>)>>);
+
+    impl Progress for Recorder {
+        fn report(&self, done: u64, total: Option<u64>) {
+            self.0.borrow_mut().push((done, total));
+        }
+    }
+
+    #[test]
+    fn test_throttle_every_items() {
+        let recorder = Recorder::default();
+        let mut throttle = Throttle::new(Some(&recorder));
+        throttle.every = 10;
+        throttle.interval = Duration::MAX;
+
+        for _ in 0..95 {
+            throttle.tick(Some(95));
+        }
+        throttle.finish();
+
+        let reports = recorder.0.into_inner();
+        let done: Vec<u64> = reports.iter().map(|(done, _)| *done).collect();
+        assert_eq!(done, [10, 20, 30, 40, 50, 60, 70, 80, 90, 95]);
+        assert!(done.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reports.last(), Some(&(95, Some(95))));
+    }
+
+    #[test]
+    fn test_throttle_interval() {
+        let recorder = Recorder::default();
+        let mut throttle = Throttle::new(Some(&recorder));
+        throttle.every = u64::MAX;
+        throttle.interval = Duration::from_millis(20);
+
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(110) {
+            throttle.tick(None);
+        }
+        throttle.finish();
+
+        // At most one report per interval, then the final one
+        let reports = recorder.0.into_inner();
+        assert!(reports.len() <= 110 / 20 + 1 + 1, "{:?}", reports.len());
+        assert!(reports.len() >= 2);
+        assert!(reports[..reports.len() - 1]
+            .iter()
+            .all(|(_, total)| total.is_none()));
+    }
+
+    #[test]
+    fn test_throttle_without_progress() {
+        let mut throttle = Throttle::new(None);
+        assert!(throttle.last_time.is_none());
+
+        // Counting is a single branch, far below the cost of reading a record
+        let start = Instant::now();
+        for _ in 0..1_000_000 {
+            throttle.tick(Some(1_000_000));
+        }
+        throttle.finish();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!((throttle.done, throttle.last_time), (0, None));
+    }
+
+    #[test]
+    fn test_stderr_progress_line() {
+        let progress = StderrProgress::new("records");
+        let second = Duration::from_secs(1);
+
+        assert_eq!(
+            progress.line(420, Some(1000), second),
+            "progress: 42% (420/1000 records) in 1.00s (420 records/s)"
+        );
+        assert_eq!(
+            progress.line(1000, Some(1000), second),
+            "done: 1000 records in 1.00s (1000 records/s)"
+        );
+        assert_eq!(
+            progress.line(3, None, second),
+            "progress: 3 records in 1.00s (3 records/s)"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The summary of the lookups of a batch of IP addresses, as returned by
+/// `GeoIpReader::analyze`.
+///
+/// Each input is counted once in `total` and in exactly one of `successful`, `not_found`,
+/// `reserved` and `errored`.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let report = geo_ip.analyze(["108.95.4.105", "10.0.0.1", "banana"]);
+///
+/// assert_eq!(report.total, 3);
+/// assert_eq!(report.successful, 1);
+/// assert_eq!(report.reserved, 1);
+/// assert_eq!(report.errored, 1);
+/// assert_eq!(report.by_country["US"], 1);
+/// assert_eq!(report.by_continent["NA"], 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct LookupReport {
+    /// The number of inputs.
+    pub total: usize,
+    /// The number of addresses found in the database.
+    pub successful: usize,
+    /// The number of public addresses that are not in the database.
+    pub not_found: usize,
+    /// The number of addresses of special-purpose ranges, see `utils::is_reserved`, which are
+    /// not looked up.
+    pub reserved: usize,
+    /// The number of inputs that are not IP addresses or whose lookup failed.
+    pub errored: usize,
+    /// The number of found addresses per ISO 3166-1 alpha-2 country code.
+    pub by_country: BTreeMap<String, usize>,
+    /// The number of found addresses per two-letter continent code, for the countries that
+    /// belong to a continent.
+    pub by_continent: BTreeMap<String, usize>,
+    /// The error message of each errored input, in input order.
+    pub errors: Vec<String>,
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+mod tests {
+    use crate::countries::Country;
+    use crate::geo_ip_reader::GeoIpReader;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::io::Cursor;
+
+    fn reader() -> GeoIpReader<Cursor<Vec<u8>>> {
+        let mut builder = DatabaseBuilder::new();
+        for (cidr, country) in [
+            ("8.8.8.0/24", Country::UnitedStates),
+            ("1.0.0.0/24", Country::Australia),
+            ("81.0.0.0/8", Country::France),
+            ("82.0.0.0/8", Country::Japan),
+        ] {
+            builder.insert(cidr, RecordSpec::new(country)).unwrap();
+        }
+        GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap()
+    }
+
+    #[test]
+    fn test_analyze_mixed_inputs() {
+        let report = reader().analyze([
+            "8.8.8.8",
+            "8.8.8.4",
+            "1.0.0.1",
+            "81.2.3.4",
+            "82.2.3.4",
+            "9.9.9.9",
+            "10.0.0.1",
+            "127.0.0.1",
+            "::ffff:192.168.0.1",
+            "banana",
+            "",
+        ]);
+
+        assert_eq!(report.total, 11);
+        assert_eq!(report.successful, 5);
+        assert_eq!(report.not_found, 1);
+        assert_eq!(report.reserved, 3);
+        assert_eq!(report.errored, 2);
+        assert_eq!(
+            report.total,
+            report.successful + report.not_found + report.reserved + report.errored
+        );
+
+        let by_country: Vec<_> = report
+            .by_country
+            .iter()
+            .map(|(code, count)| (code.as_str(), *count))
+            .collect();
+        assert_eq!(by_country, vec![("AU", 1), ("FR", 1), ("JP", 1), ("US", 2)]);
+        let by_continent: Vec<_> = report
+            .by_continent
+            .iter()
+            .map(|(code, count)| (code.as_str(), *count))
+            .collect();
+        assert_eq!(
+            by_continent,
+            vec![("AS", 1), ("EU", 1), ("NA", 2), ("OC", 1)]
+        );
+
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.errors[0].starts_with("Invalid IP address \"banana\""));
+    }
+
+    #[test]
+    fn test_analyze_empty() {
+        let report = reader().analyze([]);
+
+        assert_eq!(report, Default::default());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serialize_report() {
+        let report = reader().analyze(["8.8.8.8", "10.0.0.1", "x"]);
+
+        assert_eq!(
+            serde_json::to_value(report).unwrap(),
+            serde_json::json!({
+                "total": 3,
+                "successful": 1,
+                "not_found": 0,
+                "reserved": 1,
+                "errored": 1,
+                "by_country": {"US": 1},
+                "by_continent": {"NA": 1},
+                "errors": ["Invalid IP address \"x\": invalid IP address syntax"],
+            })
+        );
+    }
+}
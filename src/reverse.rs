@@ -0,0 +1,627 @@
+//! Reverse lookup of the city nearest to a coordinate, over the cities of a city database, and
+//! of the country of a coordinate, over the bundled country coordinates.
+//!
+//! Coordinates are indexed as points on the unit sphere in a k-d tree, so that the distances
+//! are not distorted near the poles or the antimeridian.
+
+use crate::countries::Country;
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::GeoIpReader;
+use crate::progress::Progress;
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+
+/// The mean radius of the Earth, in kilometers.
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// A city of a `CityIndex`, as returned by its queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CityMatch {
+    pub city: Box<str>,
+    pub region_code: Option<Box<str>>,
+    pub country: Country,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// The great-circle distance from the queried coordinate, in kilometers.
+    pub distance_km: f64,
+}
+
+/// A city and its position on the unit sphere.
+#[derive(Debug, Clone)]
+struct City {
+    city: Box<str>,
+    region_code: Option<Box<str>>,
+    country: Country,
+    latitude: f64,
+    longitude: f64,
+    point: [f64; 3],
+}
+
+/// `CityIndex` finds the cities of a city database nearest to a coordinate, e.g. to label GPS
+/// coordinates with the cities of IP lookups.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+/// use ipcap::reverse::CityIndex;
+///
+/// let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+/// let index = CityIndex::build(&mut geo_ip).unwrap();
+///
+/// let city = index.nearest(32.7977, -117.2335).unwrap();
+/// assert_eq!(&*city.city, "San Diego");
+/// assert!(city.distance_km < 10.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CityIndex {
+    /// The cities laid out as an implicit k-d tree: the middle city of each range splits it
+    /// along the axis of its depth.
+    cities: Vec<City>,
+}
+
+impl CityIndex {
+    /// Builds the index of the distinct cities of a city database.
+    ///
+    /// The records without a city are left out. Building reads every record of the database,
+    /// which takes a few seconds for a full database: build the index once and reuse it, the
+    /// queries take microseconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader of a city database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::InvalidDatabaseType` if the database is not a city edition,
+    /// `GeoIpReaderError::CorruptDatabase` if the database is corrupted.
+    pub fn build<R: Read + Seek>(reader: &mut GeoIpReader<R>) -> Result<Self, GeoIpReaderError> {
+        Self::build_with_progress(reader, None)
+    }
+
+    /// Builds the index like `build`, reporting the records read to `progress`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader of a city database.
+    /// * `progress` - The receiver of the number of records read out of the number of records
+    ///   of the database, or `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `build`.
+    pub fn build_with_progress<R: Read + Seek>(
+        reader: &mut GeoIpReader<R>,
+        progress: Option<&dyn Progress>,
+    ) -> Result<Self, GeoIpReaderError> {
+        let mut seen = HashSet::new();
+        let mut cities = Vec::new();
+        for record in reader.city_records(progress)? {
+            let Some(city) = record.city else {
+                continue;
+            };
+            let key = (
+                city.clone(),
+                record.region_code.clone(),
+                record.country.to_buffer(),
+                record.latitude.to_bits(),
+                record.longitude.to_bits(),
+            );
+            if seen.insert(key) {
+                cities.push(City {
+                    city,
+                    region_code: record.region_code,
+                    country: record.country,
+                    latitude: record.latitude,
+                    longitude: record.longitude,
+                    point: unit_vector(record.latitude, record.longitude),
+                });
+            }
+        }
+
+        arrange(&mut cities, 0);
+        Ok(CityIndex { cities })
+    }
+
+    /// Returns the number of distinct cities in the index.
+    pub fn len(&self) -> usize {
+        self.cities.len()
+    }
+
+    /// Returns whether the index holds no city.
+    pub fn is_empty(&self) -> bool {
+        self.cities.is_empty()
+    }
+
+    /// Finds the city nearest to a coordinate.
+    ///
+    /// # Arguments
+    ///
+    /// * `lat` - The latitude, in degrees.
+    /// * `lon` - The longitude, in degrees.
+    ///
+    /// # Returns
+    ///
+    /// (`Option<CityMatch>`): The nearest city however far it is, `None` if the index is empty
+    /// or the coordinate is not finite.
+    pub fn nearest(&self, lat: f64, lon: f64) -> Option<CityMatch> {
+        if !lat.is_finite() || !lon.is_finite() {
+            return None;
+        }
+
+        let query = unit_vector(lat, lon);
+        let mut best = None;
+        self.nearest_in(0, self.cities.len(), 0, &query, &mut best);
+        best.map(|(i, chord_squared)| self.city_match(i, chord_squared))
+    }
+
+    /// Finds the cities within a distance of a coordinate.
+    ///
+    /// # Arguments
+    ///
+    /// * `lat` - The latitude, in degrees.
+    /// * `lon` - The longitude, in degrees.
+    /// * `km` - The great-circle distance, in kilometers.
+    ///
+    /// # Returns
+    ///
+    /// (`Vec<CityMatch>`): The cities at most `km` kilometers away, nearest first.
+    pub fn within_radius(&self, lat: f64, lon: f64, km: f64) -> Vec<CityMatch> {
+        if !lat.is_finite() || !lon.is_finite() || km.is_nan() || km < 0.0 {
+            return Vec::new();
+        }
+
+        let query = unit_vector(lat, lon);
+        let radius = chord(km);
+        let mut found = Vec::new();
+        self.within_in(0, self.cities.len(), 0, &query, radius * radius, &mut found);
+
+        let mut matches: Vec<CityMatch> = found
+            .into_iter()
+            .map(|(i, chord_squared)| self.city_match(i, chord_squared))
+            .collect();
+        matches.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+        matches
+    }
+
+    /// Searches the cities of the range `lo..hi` for one nearer than `best`.
+    fn nearest_in(
+        &self,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        query: &[f64; 3],
+        best: &mut Option<(usize, f64)>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let point = &self.cities[mid].point;
+
+        let distance = squared_distance(point, query);
+        if best.is_none_or(|(_, best)| distance < best) {
+            *best = Some((mid, distance));
+        }
+
+        // Search the side of the split holding the query first
+        let axis = depth % 3;
+        let offset = query[axis] - point[axis];
+        let (near, far) = if offset < 0.0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+        self.nearest_in(near.0, near.1, depth + 1, query, best);
+        if best.is_none_or(|(_, best)| offset * offset < best) {
+            self.nearest_in(far.0, far.1, depth + 1, query, best);
+        }
+    }
+
+    /// Collects the cities of the range `lo..hi` within a squared chord of the query.
+    fn within_in(
+        &self,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        query: &[f64; 3],
+        radius_squared: f64,
+        found: &mut Vec<(usize, f64)>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let point = &self.cities[mid].point;
+
+        let distance = squared_distance(point, query);
+        if distance <= radius_squared {
+            found.push((mid, distance));
+        }
+
+        let axis = depth % 3;
+        let offset = query[axis] - point[axis];
+        if offset < 0.0 || offset * offset <= radius_squared {
+            self.within_in(lo, mid, depth + 1, query, radius_squared, found);
+        }
+        if offset >= 0.0 || offset * offset <= radius_squared {
+            self.within_in(mid + 1, hi, depth + 1, query, radius_squared, found);
+        }
+    }
+
+    fn city_match(&self, i: usize, chord_squared: f64) -> CityMatch {
+        let city = &self.cities[i];
+        CityMatch {
+            city: city.city.clone(),
+            region_code: city.region_code.clone(),
+            country: city.country,
+            latitude: city.latitude,
+            longitude: city.longitude,
+            distance_km: great_circle(chord_squared.sqrt()),
+        }
+    }
+}
+
+/// Finds the country of a coordinate, e.g. of GPS coordinates, without any database.
+///
+/// The country is the one with the smallest of the bundled bounding boxes holding the
+/// coordinate, so that enclaves such as Lesotho or the Vatican win over the countries around
+/// them. A coordinate outside of every box, e.g. at sea, gets the country with the nearest
+/// centroid. The boxes are coarse: expect wrong answers within tens of kilometers of a border.
+///
+/// # Arguments
+///
+/// * `lat` - The latitude, in degrees.
+/// * `lon` - The longitude, in degrees.
+///
+/// # Returns
+///
+/// (`Option<Country>`): The country, `None` if the coordinate is not finite or its latitude
+/// is out of `-90.0..=90.0`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+///
+/// assert_eq!(ipcap::reverse_geocode(48.8566, 2.3522), Some(Country::France));
+/// assert_eq!(ipcap::reverse_geocode(41.9029, 12.4534), Some(Country::HolySee));
+/// ```
+pub fn reverse_geocode(lat: f64, lon: f64) -> Option<Country> {
+    if !lat.is_finite() || !lon.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return None;
+    }
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+    let containing = Country::all()
+        .flat_map(|country| {
+            country
+                .bounding_boxes()
+                .iter()
+                .filter(|bounds| box_contains(bounds, lat, lon))
+                .map(move |bounds| (country, box_area(bounds)))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((country, _)) = containing {
+        return Some(country);
+    }
+
+    let query = unit_vector(lat, lon);
+    Country::all()
+        .filter_map(|country| {
+            let (lat, lon) = country.centroid()?;
+            Some((country, squared_distance(&unit_vector(lat, lon), &query)))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(country, _)| country)
+}
+
+/// Whether a `[south, west, north, east]` box holds a coordinate, the box crossing the
+/// antimeridian when its west bound is greater than its east bound.
+fn box_contains(&[south, west, north, east]: &[f64; 4], lat: f64, lon: f64) -> bool {
+    let within_lon = if west <= east {
+        (west..=east).contains(&lon)
+    } else {
+        lon >= west || lon <= east
+    };
+    (south..=north).contains(&lat) && within_lon
+}
+
+/// The area of a `[south, west, north, east]` box, on the unit sphere.
+fn box_area(&[south, west, north, east]: &[f64; 4]) -> f64 {
+    let width = (east - west).rem_euclid(360.0);
+    let width = if width == 0.0 && west != east {
+        360.0
+    } else {
+        width
+    };
+    width.to_radians() * (north.to_radians().sin() - south.to_radians().sin())
+}
+
+/// Lays out the cities as an implicit k-d tree, splitting along the axis of the depth.
+fn arrange(cities: &mut [City], depth: usize) {
+    if cities.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = cities.len() / 2;
+    cities.select_nth_unstable_by(mid, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+
+    let (left, right) = cities.split_at_mut(mid);
+    arrange(left, depth + 1);
+    arrange(&mut right[1..], depth + 1);
+}
+
+/// The point of a coordinate on the unit sphere.
+fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn squared_distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// The length of the chord of the unit sphere spanning a great-circle distance.
+fn chord(km: f64) -> f64 {
+    let angle = (km / EARTH_RADIUS_KM).min(std::f64::consts::PI);
+    2.0 * (angle / 2.0).sin()
+}
+
+/// The great-circle distance spanned by a chord of the unit sphere.
+fn great_circle(chord: f64) -> f64 {
+    2.0 * EARTH_RADIUS_KM * (chord / 2.0).min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::cell::RefCell;
+    use std::io::Cursor;
+
+    const PARIS: (f64, f64) = (48.8566, 2.3522);
+    const BERLIN: (f64, f64) = (52.52, 13.405);
+    const NEW_YORK: (f64, f64) = (40.7128, -74.006);
+    const TOKYO: (f64, f64) = (35.6762, 139.6503);
+    const SUVA: (f64, f64) = (-18.1416, 178.4419);
+
+    fn database() -> Vec<u8> {
+        let mut builder = DatabaseBuilder::new();
+        for (cidr, country, region_code, city, (latitude, longitude)) in [
+            ("1.0.0.0/8", Country::France, "A8", "Paris", PARIS),
+            // The same city on another network is indexed once
+            ("2.0.0.0/8", Country::France, "A8", "Paris", PARIS),
+            ("3.0.0.0/8", Country::Germany, "16", "Berlin", BERLIN),
+            (
+                "4.0.0.0/8",
+                Country::UnitedStates,
+                "NY",
+                "New York",
+                NEW_YORK,
+            ),
+            ("5.0.0.0/8", Country::Japan, "40", "Tokyo", TOKYO),
+            ("6.0.0.0/8", Country::Fiji, "01", "Suva", SUVA),
+        ] {
+            let spec = RecordSpec::new(country)
+                .region_code(region_code)
+                .city(city)
+                .coordinates(latitude, longitude);
+            builder.insert(cidr, spec).unwrap();
+        }
+        // A record without a city is left out
+        builder
+            .insert(
+                "7.0.0.0/8",
+                RecordSpec::new(Country::Spain).coordinates(40.0, -4.0),
+            )
+            .unwrap();
+        builder.build()
+    }
+
+    fn index() -> CityIndex {
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database())).unwrap();
+        CityIndex::build(&mut reader).unwrap()
+    }
+
+    #[test]
+    fn test_build() {
+        let index = index();
+
+        assert_eq!(index.len(), 5);
+        assert!(!index.is_empty());
+    }
+
+    #[test]
+    fn test_build_with_progress() {
+        struct Recorder(RefCell<Vec<(u64, Option<u64>)>>);
+
+        impl Progress for Recorder {
+            fn report(&self, done: u64, total: Option<u64>) {
+                self.0.borrow_mut().push((done, total));
+            }
+        }
+
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database())).unwrap();
+        let recorder = Recorder(RefCell::new(Vec::new()));
+        let index = CityIndex::build_with_progress(&mut reader, Some(&recorder)).unwrap();
+        assert_eq!(index.len(), 5);
+
+        // Every record is read, the last report being complete
+        let reports = recorder.0.into_inner();
+        let &(done, total) = reports.last().unwrap();
+        assert_eq!(total, Some(done));
+        assert!(done >= 6);
+        assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn test_nearest_on_city() {
+        let index = index();
+
+        for (name, (latitude, longitude)) in [
+            ("Paris", PARIS),
+            ("Berlin", BERLIN),
+            ("New York", NEW_YORK),
+            ("Tokyo", TOKYO),
+        ] {
+            let city = index.nearest(latitude, longitude).unwrap();
+            assert_eq!(&*city.city, name);
+            assert!(city.distance_km < 0.1, "{}: {}", name, city.distance_km);
+        }
+
+        let paris = index.nearest(PARIS.0, PARIS.1).unwrap();
+        assert_eq!(paris.country, Country::France);
+        assert_eq!(paris.region_code.as_deref(), Some("A8"));
+    }
+
+    #[test]
+    fn test_nearest_near_city() {
+        let index = index();
+
+        // Versailles, about 17 km from Paris
+        let city = index.nearest(48.8049, 2.1204).unwrap();
+        assert_eq!(&*city.city, "Paris");
+        assert!(
+            (city.distance_km - 17.8).abs() < 1.0,
+            "{}",
+            city.distance_km
+        );
+
+        // Across the antimeridian from Suva
+        let city = index.nearest(-18.0, -179.5).unwrap();
+        assert_eq!(&*city.city, "Suva");
+        assert!(city.distance_km < 250.0, "{}", city.distance_km);
+    }
+
+    #[test]
+    fn test_nearest_in_the_ocean() {
+        let index = index();
+
+        // The middle of the South Pacific still gets its nearest city, far away
+        let city = index.nearest(-40.0, -130.0).unwrap();
+        assert!(city.distance_km > 5000.0, "{}", city.distance_km);
+        assert!(index.within_radius(-40.0, -130.0, 1000.0).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_invalid_coordinate() {
+        let index = index();
+
+        assert_eq!(index.nearest(f64::NAN, 0.0), None);
+        assert_eq!(CityIndex::default().nearest(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_within_radius() {
+        let index = index();
+
+        // Paris and Berlin are about 880 km apart
+        let cities: Vec<_> = index
+            .within_radius(PARIS.0, PARIS.1, 1000.0)
+            .into_iter()
+            .map(|city| city.city)
+            .collect();
+        assert_eq!(cities, vec!["Paris".into(), "Berlin".into()]);
+
+        let cities = index.within_radius(PARIS.0, PARIS.1, 500.0);
+        assert_eq!(cities.len(), 1);
+        assert_eq!(index.within_radius(PARIS.0, PARIS.1, 25_000.0).len(), 5);
+        assert!(index.within_radius(PARIS.0, PARIS.1, -1.0).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let index = index();
+        let cities = index.within_radius(0.0, 0.0, 25_000.0);
+
+        for lat in (-80..=80).step_by(20) {
+            for lon in (-180..180).step_by(30) {
+                let (lat, lon) = (lat as f64, lon as f64);
+                let nearest = index.nearest(lat, lon).unwrap();
+                let expected = cities
+                    .iter()
+                    .map(|city| {
+                        great_circle(
+                            squared_distance(
+                                &unit_vector(lat, lon),
+                                &unit_vector(city.latitude, city.longitude),
+                            )
+                            .sqrt(),
+                        )
+                    })
+                    .fold(f64::INFINITY, f64::min);
+                assert!(
+                    (nearest.distance_km - expected).abs() < 1e-6,
+                    "{} {}",
+                    lat,
+                    lon
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_geocode() {
+        for ((lat, lon), country) in [
+            (PARIS, Country::France),
+            (BERLIN, Country::Germany),
+            (NEW_YORK, Country::UnitedStates),
+            (TOKYO, Country::Japan),
+            (SUVA, Country::Fiji),
+            ((-33.8688, 151.2093), Country::Australia),
+            ((-34.6037, -58.3816), Country::Argentina),
+            ((-1.2921, 36.8219), Country::Kenya),
+            ((55.7558, 37.6173), Country::RussianFederation),
+            ((43.6532, -79.3832), Country::Canada),
+            ((64.1466, -21.9426), Country::Iceland),
+            ((61.2181, -149.9003), Country::UnitedStates),
+        ] {
+            assert_eq!(reverse_geocode(lat, lon), Some(country), "{} {}", lat, lon);
+        }
+    }
+
+    #[test]
+    fn test_reverse_geocode_enclaves() {
+        // The smallest box wins over the country around it
+        assert_eq!(reverse_geocode(41.9029, 12.4534), Some(Country::HolySee));
+        assert_eq!(reverse_geocode(43.9424, 12.4578), Some(Country::SanMarino));
+        assert_eq!(reverse_geocode(-29.3151, 27.4869), Some(Country::Lesotho));
+        assert_eq!(reverse_geocode(41.9028, 12.4964), Some(Country::Italy));
+    }
+
+    #[test]
+    fn test_reverse_geocode_across_the_antimeridian() {
+        // Fiji spans the antimeridian, and longitudes are wrapped
+        assert_eq!(reverse_geocode(-16.5, -179.9), Some(Country::Fiji));
+        assert_eq!(reverse_geocode(SUVA.0, SUVA.1 - 360.0), Some(Country::Fiji));
+        assert_eq!(
+            reverse_geocode(65.0, 178.0),
+            Some(Country::RussianFederation)
+        );
+    }
+
+    #[test]
+    fn test_reverse_geocode_at_sea() {
+        // Off the coast of Portugal, the nearest centroid is that of Portugal
+        assert_eq!(reverse_geocode(39.5, -12.0), Some(Country::Portugal));
+    }
+
+    #[test]
+    fn test_reverse_geocode_invalid_coordinate() {
+        assert_eq!(reverse_geocode(f64::NAN, 0.0), None);
+        assert_eq!(reverse_geocode(0.0, f64::INFINITY), None);
+        assert_eq!(reverse_geocode(91.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_build_requires_city_edition() {
+        let database = crate::fixtures::string_database(crate::constants::ORG_EDITION, "Google");
+        let mut reader = GeoIpReader::from_reader(Cursor::new(database)).unwrap();
+
+        assert!(matches!(
+            CityIndex::build(&mut reader),
+            Err(GeoIpReaderError::InvalidDatabaseType)
+        ));
+    }
+}
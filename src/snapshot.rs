@@ -0,0 +1,108 @@
+//! Snapshots of parsed databases, to start a reader without detecting the layout of its
+//! database, e.g. on the cold starts of a serverless function.
+//!
+//! A snapshot, written by `GeoIpReader::save_snapshot` and read by `GeoIpReader::load_snapshot`,
+//! holds the database bytes together with the layout detected when the database was opened.
+//! All the integers are little-endian:
+//!
+//! | Bytes | Content                                                     |
+//! |-------|-------------------------------------------------------------|
+//! | 8     | The magic `IPCAPSNP`                                        |
+//! | 1     | The version of the format, `SNAPSHOT_VERSION`               |
+//! | 1     | The edition of the database, one of the `*_EDITION*` constants |
+//! | 1     | The record length                                           |
+//! | 4     | The segment count                                           |
+//! | 8     | The 64-bit FNV-1a hash of the database bytes                |
+//! | 8     | The length of the database, then its bytes                  |
+//!
+//! The hash is checked on load, so a snapshot corrupted in storage is refused rather than
+//! answering lookups with garbage.
+
+use crate::capabilities::SUPPORTED_EDITIONS;
+use crate::constants::{ORG_RECORD_LENGTH, STANDARD_RECORD_LENGTH};
+use crate::errors::SnapshotError;
+use crate::utils::fnv1a;
+use std::io::{Read, Write};
+
+/// The magic bytes starting a snapshot.
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"IPCAPSNP";
+
+/// The version of the snapshot format written by this build, the only one it reads.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// The layout of a database, as detected by `GeoIpReader::detect_database_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub(crate) edition: u8,
+    pub(crate) record_length: usize,
+    pub(crate) segments: u32,
+}
+
+/// Writes a snapshot of the database bytes and their layout.
+pub(crate) fn write_snapshot(
+    out: &mut impl Write,
+    layout: Layout,
+    database: &[u8],
+) -> Result<(), SnapshotError> {
+    let record_length =
+        u8::try_from(layout.record_length).map_err(|_| SnapshotError::InvalidLayout)?;
+    let mut header = SNAPSHOT_MAGIC.to_vec();
+    header.push(SNAPSHOT_VERSION);
+    header.push(layout.edition);
+    header.push(record_length);
+    header.extend(layout.segments.to_le_bytes());
+    header.extend(fnv1a(database).to_le_bytes());
+    header.extend((database.len() as u64).to_le_bytes());
+    out.write_all(&header)?;
+    out.write_all(database)?;
+    Ok(())
+}
+
+/// Reads a snapshot, checking its version, its layout and the hash of its database bytes.
+pub(crate) fn read_snapshot(input: &mut impl Read) -> Result<(Layout, Vec<u8>), SnapshotError> {
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if magic != SNAPSHOT_MAGIC {
+        return Err(SnapshotError::NotASnapshot);
+    }
+    let [version] = read_array(input)?;
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let [edition, record_length] = read_array(input)?;
+    let segments = u32::from_le_bytes(read_array(input)?);
+    let expected = u64::from_le_bytes(read_array(input)?);
+    let length = u64::from_le_bytes(read_array(input)?);
+
+    // Read up to the announced length, rather than allocating it up front
+    let mut database = Vec::new();
+    input.take(length).read_to_end(&mut database)?;
+    if database.len() as u64 != length {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    let actual = fnv1a(&database);
+    if actual != expected {
+        return Err(SnapshotError::HashMismatch { expected, actual });
+    }
+
+    let layout = Layout {
+        edition,
+        record_length: record_length as usize,
+        segments,
+    };
+    // The lookups trust the layout, which must describe a tree that fits in the database
+    let tree_size = u64::from(segments) * 2 * u64::from(record_length);
+    if !SUPPORTED_EDITIONS.contains(&edition)
+        || ![STANDARD_RECORD_LENGTH, ORG_RECORD_LENGTH].contains(&layout.record_length)
+        || tree_size > length
+    {
+        return Err(SnapshotError::InvalidLayout);
+    }
+    Ok((layout, database))
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N], SnapshotError> {
+    let mut bytes = [0u8; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
@@ -0,0 +1,342 @@
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::{GeoIpReaderBuilder, Record};
+use crate::locale::Locale;
+use crate::post_process::FieldList;
+use futures::{Stream, StreamExt};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+/// `AsyncIpCap` is a cheaply cloneable handle over an in-memory database, suited for async
+/// pipelines where lookups are shared between tasks.
+///
+/// Each lookup reads the shared bytes through its own cursor, so no lock is involved.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::stream::AsyncIpCap;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let mut path = dirs::home_dir().unwrap();
+/// path.push("ipcap/geo_ip_city_v4.dat");
+///
+/// let ipcap = AsyncIpCap::open(path).await.unwrap();
+/// let record = ipcap.lookup("108.95.4.105").unwrap();
+/// assert_eq!(record.city, Some("San Diego".into()));
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncIpCap {
+    database: Arc<[u8]>,
+    options: GeoIpReaderBuilder,
+}
+
+impl AsyncIpCap {
+    /// Loads the database file at the given path into memory.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, GeoIpReaderError> {
+        let database = tokio::fs::read(path)
+            .await
+            .map_err(|_| GeoIpReaderError::OpenFileError)?;
+
+        Self::from_bytes(database, GeoIpReaderBuilder::new())
+    }
+
+    /// Wraps the database bytes, decoded with the options of the given builder.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid database.
+    pub fn from_bytes(
+        database: Vec<u8>,
+        options: GeoIpReaderBuilder,
+    ) -> Result<Self, GeoIpReaderError> {
+        let ipcap = AsyncIpCap {
+            database: database.into(),
+            options,
+        };
+        // Fail early rather than on every lookup
+        ipcap
+            .options
+            .clone()
+            .build(Cursor::new(ipcap.database.clone()))?;
+
+        Ok(ipcap)
+    }
+
+    /// Looks up the geographical record of an IP address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::AddressNotFound` if the address is not in the database.
+    pub fn lookup(&self, ip: &str) -> Result<Record<'static>, GeoIpReaderError> {
+        let mut reader = self
+            .options
+            .clone()
+            .build(Cursor::new(self.database.clone()))?;
+
+        reader
+            .find_record(ip)?
+            .ok_or_else(|| GeoIpReaderError::AddressNotFound(ip.to_string()))
+    }
+
+    /// Looks up the displayed fields of the record of an IP address, as edited by the
+    /// post-processors of the builder, see `GeoIpReader::record_fields`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address.
+    /// * `unix_ts` - The instant of the time zone abbreviation and UTC offset.
+    /// * `locale` - The language of the names.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::AddressNotFound` if the address is not in the database.
+    pub fn lookup_fields(
+        &self,
+        ip: &str,
+        unix_ts: i64,
+        locale: Locale,
+    ) -> Result<FieldList, GeoIpReaderError> {
+        let mut reader = self
+            .options
+            .clone()
+            .build(Cursor::new(self.database.clone()))?;
+
+        let record = reader
+            .find_record(ip)?
+            .ok_or_else(|| GeoIpReaderError::AddressNotFound(ip.to_string()))?;
+        Ok(reader
+            .record_fields(&record, ip.parse().ok(), unix_ts, locale)
+            .into_vec())
+    }
+}
+
+/// An extension trait geolocating the IP addresses of a `Stream`, e.g. of `String`s or
+/// `IpAddr`s, into `(input, Result<Record, GeoIpReaderError>)` items. The items of a stream of
+/// `IpAddr`s collect into a `LookupResults`, like the batches of `GeoIpReader::get_records`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use ipcap::stream::{AsyncIpCap, GeoIpStreamExt};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let mut path = dirs::home_dir().unwrap();
+/// path.push("ipcap/geo_ip_city_v4.dat");
+/// let ipcap = AsyncIpCap::open(path).await.unwrap();
+///
+/// let ips = futures::stream::iter(vec!["108.95.4.105", "185.90.90.120"]);
+/// let results: Vec<_> = ips.geolocate_concurrent(ipcap, 2).collect().await;
+///
+/// assert_eq!(results[0].0, "108.95.4.105");
+/// assert_eq!(results[1].1.as_ref().unwrap().time_zone, "Asia/Riyadh");
+/// # });
+/// ```
+pub trait GeoIpStreamExt: Stream + Sized
+where
+    Self::Item: ToString,
+{
+    /// Geolocates each item inline when it is polled, without spawning any task.
+    fn geolocate(
+        self,
+        ipcap: AsyncIpCap,
+    ) -> impl Stream<Item = (Self::Item, Result<Record<'static>, GeoIpReaderError>)> {
+        self.map(move |item| {
+            let record = ipcap.lookup(&item.to_string());
+            (item, record)
+        })
+    }
+
+    /// Geolocates up to `n` items at once on the blocking thread pool, yielding the results in
+    /// the order of the input.
+    ///
+    /// At most `n` lookups are in flight, so a slow consumer applies back-pressure to the input.
+    fn geolocate_concurrent(
+        self,
+        ipcap: AsyncIpCap,
+        n: usize,
+    ) -> impl Stream<Item = (Self::Item, Result<Record<'static>, GeoIpReaderError>)> {
+        self.map(move |item| {
+            let ipcap = ipcap.clone();
+            let ip = item.to_string();
+            async move {
+                let record = tokio::task::spawn_blocking(move || ipcap.lookup(&ip))
+                    .await
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+                (item, record)
+            }
+        })
+        .buffered(n)
+    }
+}
+
+impl<S> GeoIpStreamExt for S
+where
+    S: Stream,
+    S::Item: ToString,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulk::LookupResults;
+    use crate::countries::Country;
+    use crate::geo_ip_reader::GeoIpReader;
+    use crate::writer::{DatabaseBuilder, RecordSpec};
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    fn ipcap() -> AsyncIpCap {
+        let mut builder = DatabaseBuilder::new();
+        for i in 0..=255 {
+            let country = Country::from_buffer(i % 200 + 3).unwrap();
+            builder
+                .insert(&format!("{}.0.0.0/8", i), RecordSpec::new(country))
+                .unwrap();
+        }
+        AsyncIpCap::from_bytes(builder.build(), GeoIpReaderBuilder::new()).unwrap()
+    }
+
+    fn ips() -> Vec<String> {
+        (0..100)
+            .map(|i| format!("{}.1.2.3", (i * 37) % 256))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_geolocate_matches_sync_reader() {
+        let ipcap = ipcap();
+        let mut reader = GeoIpReader::from_reader(Cursor::new(ipcap.database.to_vec())).unwrap();
+
+        let results: Vec<_> = futures::stream::iter(ips())
+            .geolocate(ipcap)
+            .collect()
+            .await;
+
+        assert_eq!(results.len(), 100);
+        for (ip, record) in results {
+            assert_eq!(record.unwrap(), reader.get_record(&ip).unwrap());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_geolocate_concurrent_preserves_order() {
+        let ipcap = ipcap();
+
+        let serial: Vec<_> = futures::stream::iter(ips())
+            .geolocate(ipcap.clone())
+            .collect()
+            .await;
+        let concurrent: Vec<_> = futures::stream::iter(ips())
+            .geolocate_concurrent(ipcap, 8)
+            .collect()
+            .await;
+
+        let inputs: Vec<String> = concurrent.iter().map(|(ip, _)| ip.clone()).collect();
+        assert_eq!(inputs, ips());
+        for ((_, a), (_, b)) in serial.iter().zip(concurrent.iter()) {
+            assert_eq!(a.as_ref().unwrap(), b.as_ref().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_geolocate_ip_addr_items() {
+        let ips: Vec<IpAddr> = vec!["8.8.8.8".parse().unwrap()];
+
+        let results: Vec<_> = futures::stream::iter(ips)
+            .geolocate(ipcap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            results[0].1.as_ref().unwrap().country,
+            Country::from_buffer(11).unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_geolocate_collects_into_lookup_results() {
+        let ips: Vec<IpAddr> = ips().iter().map(|ip| ip.parse().unwrap()).collect();
+
+        let results: LookupResults<_> = futures::stream::iter(ips.clone())
+            .geolocate_concurrent(ipcap(), 8)
+            .collect()
+            .await;
+
+        let found: Vec<IpAddr> = results.ok_only().map(|(ip, _)| ip).collect();
+        assert_eq!(found, ips);
+    }
+
+    #[test]
+    fn test_lookup_fields_runs_post_processors() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let options = GeoIpReaderBuilder::new()
+            .post_processor(crate::post_process::add_is_eu_flag())
+            .post_processor(|fields: &mut crate::post_process::RecordFields| {
+                fields.remove("country_name");
+            });
+        let ipcap = AsyncIpCap::from_bytes(builder.build(), options).unwrap();
+
+        let fields = ipcap.lookup_fields("10.1.2.3", 0, Locale::En).unwrap();
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(value("country_code"), Some(Some("DE".to_string())));
+        assert_eq!(value("country_name"), None);
+        assert_eq!(fields.last().unwrap().0, "is_eu");
+        assert_eq!(value("is_eu"), Some(Some("true".to_string())));
+        assert!(matches!(
+            ipcap.lookup_fields("11.0.0.1", 0, Locale::En),
+            Err(GeoIpReaderError::AddressNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_geolocate_not_found() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        let ipcap = AsyncIpCap::from_bytes(builder.build(), GeoIpReaderBuilder::new()).unwrap();
+
+        let results: Vec<_> = futures::stream::iter(vec!["11.0.0.1"])
+            .geolocate(ipcap)
+            .collect()
+            .await;
+
+        assert!(matches!(
+            &results[0].1,
+            Err(GeoIpReaderError::AddressNotFound(ip)) if ip == "11.0.0.1"
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dropping_concurrent_stream_releases_tasks() {
+        let ipcap = ipcap();
+
+        let results: Vec<_> = futures::stream::iter(ips())
+            .geolocate_concurrent(ipcap.clone(), 8)
+            .take(3)
+            .collect()
+            .await;
+        assert_eq!(results.len(), 3);
+
+        // The lookups still in flight finish on their own and drop their handle
+        for _ in 0..100 {
+            if Arc::strong_count(&ipcap.database) == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("lookup tasks are still holding the database");
+    }
+}
@@ -0,0 +1,299 @@
+//! Support for fuzzing the lookup path: the cases of the corpus replayed by
+//! `tests/corpus_replay.rs`, and the minimization of the crashers a fuzz target finds before
+//! they join the corpus, so that they can never regress.
+//!
+//! A case is stored as the magic `IPCAPFZ1`, the length of the looked up input as a
+//! little-endian `u32`, the input in UTF-8, then the database bytes up to the end of the file.
+
+use crate::errors::GeoIpReaderError;
+use crate::geo_ip_reader::GeoIpReader;
+use crate::utils::{fnv1a, record_fields};
+use std::fmt;
+use std::io::{self, Cursor};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// The first bytes of an encoded case, the last one being the version of the envelope.
+pub const MAGIC: &[u8; 8] = b"IPCAPFZ1";
+
+/// A database and an input looked up in it.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::test_support::CorpusCase;
+///
+/// let case = CorpusCase::new("200.0.0.1", vec![0xFF; 7]);
+///
+/// assert_eq!(CorpusCase::decode(&case.encode()), Some(case.clone()));
+/// assert!(case.replay().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusCase {
+    /// The input looked up, not necessarily an IP address.
+    pub input: String,
+    /// The bytes read as a database.
+    pub database: Vec<u8>,
+}
+
+/// How the replay of a case failed.
+#[derive(Debug)]
+pub enum ReplayFailure {
+    /// A call panicked, with the message of the panic.
+    Panic { call: &'static str, message: String },
+    /// A call returned an error that no database bytes nor input should give, e.g.
+    /// `GeoIpReaderError::OpenFileError` for a database in memory.
+    UnexpectedError {
+        call: &'static str,
+        error: GeoIpReaderError,
+    },
+}
+
+impl fmt::Display for ReplayFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayFailure::Panic { call, message } => write!(f, "{} panicked: {}", call, message),
+            ReplayFailure::UnexpectedError { call, error } => {
+                write!(f, "{} returned an unexpected error: {:?}", call, error)
+            }
+        }
+    }
+}
+
+impl CorpusCase {
+    /// Constructs a case looking `input` up in `database`.
+    pub fn new(input: &str, database: Vec<u8>) -> Self {
+        CorpusCase {
+            input: input.to_string(),
+            database,
+        }
+    }
+
+    /// Encodes the case in the envelope of the corpus files.
+    pub fn encode(&self) -> Vec<u8> {
+        let input = self.input.as_bytes();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((input.len() as u32).to_le_bytes());
+        bytes.extend(input);
+        bytes.extend(&self.database);
+        bytes
+    }
+
+    /// Decodes a case from the envelope of the corpus files, `None` if the bytes are not a
+    /// case, e.g. truncated or with an input that is not UTF-8.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.strip_prefix(MAGIC)?;
+        let (length, bytes) = bytes.split_first_chunk::<4>()?;
+        let length = usize::try_from(u32::from_le_bytes(*length)).ok()?;
+        let input = bytes.get(..length)?;
+        Some(CorpusCase {
+            input: String::from_utf8(input.to_vec()).ok()?,
+            database: bytes.get(length..)?.to_vec(),
+        })
+    }
+
+    /// Reads the database with `GeoIpReader::from_reader`, then looks the input up with
+    /// `find_record`, `get_org` and `get_asn` and formats the found record, each call inside
+    /// `catch_unwind`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first call that panicked, or that returned an error other than the typed
+    /// errors of a corrupt database or of an input that cannot be looked up.
+    pub fn replay(&self) -> Result<(), ReplayFailure> {
+        let reader = guard("from_reader", || {
+            GeoIpReader::from_reader(Cursor::new(self.database.as_slice()))
+        })?;
+        let Some(mut reader) = reader else {
+            return Ok(());
+        };
+
+        let record = guard("find_record", || reader.find_record(&self.input))?;
+        if let Some(Some(record)) = record {
+            guard("record_fields", || {
+                let _ = record_fields(&record);
+                let _ = format!("{:?}", record);
+                Ok(())
+            })?;
+        }
+        guard("get_org", || reader.get_org(&self.input))?;
+        guard("get_asn", || reader.get_asn(&self.input))?;
+        Ok(())
+    }
+}
+
+/// Runs a call of the replay, an expected error giving `None`.
+fn guard<T>(
+    call: &'static str,
+    f: impl FnOnce() -> Result<T, GeoIpReaderError>,
+) -> Result<Option<T>, ReplayFailure> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(Some(value)),
+        Ok(Err(error)) if is_expected(&error) => Ok(None),
+        Ok(Err(error)) => Err(ReplayFailure::UnexpectedError { call, error }),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Err(ReplayFailure::Panic { call, message })
+        }
+    }
+}
+
+/// Whether the error is one that corrupt database bytes or an arbitrary input may give.
+fn is_expected(error: &GeoIpReaderError) -> bool {
+    matches!(
+        error,
+        GeoIpReaderError::InvalidDatabaseType
+            | GeoIpReaderError::CorruptDatabase
+            | GeoIpReaderError::AddressNotFound(_)
+            | GeoIpReaderError::ReservedAddress(_)
+            | GeoIpReaderError::InvalidAddress { .. }
+            | GeoIpReaderError::MissingCoordinates(_)
+            | GeoIpReaderError::AddressFamilyMismatch(_)
+            | GeoIpReaderError::Io(_)
+    )
+}
+
+/// Shrinks a case that fails to replay, then saves it in the envelope of the corpus, e.g. from
+/// a fuzz target that found a crasher. A case that replays fine is saved as it is.
+///
+/// The shrinking removes chunks of the database, then of the input, halving the chunks down to
+/// single bytes, as long as the replay still fails. The file is named after the hash of its
+/// content, so saving the same case twice leaves a single file.
+///
+/// # Arguments
+///
+/// * `input` - The case to save.
+/// * `dir` - The directory of the corpus, created if needed.
+///
+/// # Returns
+///
+/// (`io::Result<PathBuf>`): The path of the saved case.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::test_support::{minimize_and_save, CorpusCase};
+///
+/// let dir = std::env::temp_dir().join(format!("ipcap-corpus-{}", std::process::id()));
+/// let case = CorpusCase::new("200.0.0.1", vec![0xFF; 7]);
+///
+/// let path = minimize_and_save(&case, &dir).unwrap();
+/// let saved = CorpusCase::decode(&std::fs::read(&path).unwrap()).unwrap();
+/// assert_eq!(saved, case);
+/// # std::fs::remove_dir_all(dir).unwrap();
+/// ```
+pub fn minimize_and_save(input: &CorpusCase, dir: &Path) -> io::Result<PathBuf> {
+    let mut case = input.clone();
+    if case.replay().is_err() {
+        case.database = shrink(case.database, |database| {
+            CorpusCase::new(&case.input, database.to_vec())
+                .replay()
+                .is_err()
+        });
+        let input = shrink(case.input.clone().into_bytes(), |input| {
+            std::str::from_utf8(input).is_ok_and(|input| {
+                CorpusCase::new(input, case.database.clone())
+                    .replay()
+                    .is_err()
+            })
+        });
+        case.input = String::from_utf8(input).unwrap_or(case.input);
+    }
+
+    let bytes = case.encode();
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{:016x}.case", fnv1a(&bytes)));
+    std::fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+/// Removes the chunks of the bytes whose removal keeps `fails` true, from chunks of half the
+/// bytes down to single bytes.
+fn shrink(mut bytes: Vec<u8>, fails: impl Fn(&[u8]) -> bool) -> Vec<u8> {
+    let mut chunk = bytes.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < bytes.len() {
+            let end = (start + chunk).min(bytes.len());
+            let mut candidate = bytes[..start].to_vec();
+            candidate.extend(&bytes[end..]);
+            if fails(&candidate) {
+                bytes = candidate;
+            } else {
+                start = end;
+            }
+        }
+        chunk /= 2;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CITY_EDITION_REV1;
+    use crate::fixtures::city_database;
+
+    #[test]
+    fn test_envelope() {
+        let case = CorpusCase::new("Zürich", vec![0, 1, 2]);
+        let bytes = case.encode();
+        assert_eq!(&bytes[..8], b"IPCAPFZ1");
+        assert_eq!(&bytes[8..12], &[7, 0, 0, 0]);
+        assert_eq!(CorpusCase::decode(&bytes), Some(case));
+
+        assert_eq!(
+            CorpusCase::decode(&CorpusCase::new("", Vec::new()).encode()),
+            Some(CorpusCase::new("", Vec::new()))
+        );
+        assert_eq!(CorpusCase::decode(b"IPCAPFZ0\0\0\0\0"), None);
+        assert_eq!(CorpusCase::decode(b"IPCAPFZ1\x05\0\0\0abc"), None);
+        assert_eq!(CorpusCase::decode(b"IPCAPFZ1\x01\0\0\0\xFF"), None);
+    }
+
+    #[test]
+    fn test_replay() {
+        let database = city_database(CITY_EDITION_REV1);
+        assert!(CorpusCase::new("200.0.0.1", database.clone())
+            .replay()
+            .is_ok());
+        assert!(CorpusCase::new("banana", database).replay().is_ok());
+        assert!(CorpusCase::new("200.0.0.1", Vec::new()).replay().is_ok());
+
+        assert!(matches!(
+            guard("find_record", || -> Result<(), GeoIpReaderError> {
+                panic!("boom")
+            }),
+            Err(ReplayFailure::Panic {
+                call: "find_record",
+                ref message
+            }) if message == "boom"
+        ));
+        assert!(matches!(
+            guard("get_org", || -> Result<(), GeoIpReaderError> {
+                Err(GeoIpReaderError::OpenFileError)
+            }),
+            Err(ReplayFailure::UnexpectedError {
+                call: "get_org",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_shrink() {
+        // Keeps the bytes the failure depends on, here a 7 and a 9 in this order
+        let fails = |bytes: &[u8]| {
+            let seven = bytes.iter().position(|byte| *byte == 7);
+            let nine = bytes.iter().rposition(|byte| *byte == 9);
+            matches!((seven, nine), (Some(seven), Some(nine)) if seven < nine)
+        };
+        let bytes: Vec<u8> = (0..64).map(|i| [1, 7, 3, 9][i % 4]).collect();
+        assert_eq!(shrink(bytes, fails), [7, 9]);
+        assert_eq!(shrink(vec![1, 2, 3], |_| false), [1, 2, 3]);
+    }
+}
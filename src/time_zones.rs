@@ -1,6 +1,14 @@
+use crate::countries::Country;
+use crate::errors::OverrideError;
 use crate::utils::{multi_level, single_level};
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub static COUNTRY_DICT: Lazy<HashMap<&'static str, HashMap<&'static str, &'static str>>> =
     Lazy::new(|| {
@@ -783,85 +791,1482 @@ pub static COUNTRY_DICT: Lazy<HashMap<&'static str, HashMap<&'static str, &'stat
         .collect()
     });
 
-/// Given a country code, returns the associated time zone (its country and region).
+/// The region key of the time zone of a country as a whole in `COUNTRY_DICT`, as inserted by
+/// `utils::single_level`. A country has either this key or the keys of its regions.
+pub const DEFAULT_REGION_KEY: &str = "default";
+
+/// Given a country code and optionally a region code, returns the associated IANA time zone.
+///
+/// This is the zone table of the records, usable on its own when the country and region come
+/// from another source than a lookup. The time zone of the region comes first. Without a region,
+/// or if the region has no zone of its own, the time zone of the country as a whole is returned,
+/// the one under `DEFAULT_REGION_KEY`. The codes are matched case-insensitively.
 ///
-/// If the value for the given key is a HashMap and the second key is provided,
-/// it retrieves the inner map value of the second key.
+/// The lookups resolve the time zones of their records with `resolve_time_zone`, which also
+/// falls back to the single zone of a country.
 ///
 /// # Arguments
 ///
-/// * `country_code` - A two-letter country code.
-/// * `key1` - The first key to retrieve the value from the outer map.
-/// * `key2` - The second key to retrieve the inner map value if provided.
+/// * `country_code` - The ISO 3166-1 alpha-2 code of the country, e.g. `US`.
+/// * `region_code` - The region code as found in the records, if any: the state code in the
+///   United States and Canada, e.g. `CA`, and the FIPS 10-4 subdivision code elsewhere, e.g.
+///   `02` for New South Wales in Australia.
 ///
 /// # Returns
 ///
-/// (`Option<&'static str>`): The time zone, or None if the country code or keys are not found.
+/// (`Option<&'static str>`): The time zone, or None if the country code is not found, or if the
+/// country has no time zone as a whole and the region has none either.
 ///
 /// # Examples
 ///
 /// ```
 /// use ipcap::time_zones::time_zone_by_country;
 ///
-/// let time_zone = time_zone_by_country("AD", "default", None);
+/// let time_zone = time_zone_by_country("AD", None);
 /// assert_eq!(time_zone, Some("Europe/Andorra"));
 ///
-/// let multi_level_zone = time_zone_by_country("AD", "multi_level", None);
-/// assert_eq!(multi_level_zone, None);
+/// let region_zone = time_zone_by_country("AU", Some("02"));
+/// assert_eq!(region_zone, Some("Australia/NSW"));
 ///
-/// let inner_value = time_zone_by_country("AD", "multi_level", Some("inner_key"));
-/// assert_eq!(inner_value, None);
+/// // Andorra is a single zone, whatever the region
+/// assert_eq!(time_zone_by_country("AD", Some("07")), Some("Europe/Andorra"));
+/// // Australia has no time zone as a whole
+/// assert_eq!(time_zone_by_country("AU", None), None);
+/// // Codes from other sources may be in lower case
+/// assert_eq!(time_zone_by_country("us", Some("ny")), Some("America/New_York"));
 /// ```
-pub fn time_zone_by_country<'a>(
-    country_code: &str,
-    key1: &str,
-    key2: Option<&str>,
-) -> Option<&'a str> {
-    COUNTRY_DICT
+pub fn time_zone_by_country(country_code: &str, region_code: Option<&str>) -> Option<&'static str> {
+    let regions = get_ignore_case(&COUNTRY_DICT, country_code)?;
+    region_code
+        .and_then(|region_code| get_ignore_case(regions, region_code))
+        .or_else(|| regions.get(DEFAULT_REGION_KEY))
+        .copied()
+}
+
+/// Gets the value of a code in a map keyed by upper-case codes, retrying in upper case only when
+/// the code has lower-case letters, so that the lookups of records do not allocate.
+fn get_ignore_case<'m, K, V>(map: &'m HashMap<K, V>, code: &str) -> Option<&'m V>
+where
+    K: Borrow<str> + Eq + Hash,
+{
+    match map.get(code) {
+        None if code.bytes().any(|byte| byte.is_ascii_lowercase()) => {
+            map.get(code.to_ascii_uppercase().as_str())
+        }
+        value => value,
+    }
+}
+
+/// Returns the distinct time zones of a country, sorted, e.g. to tell whether the country has a
+/// single one.
+///
+/// # Arguments
+///
+/// * `country_code` - The ISO 3166-1 alpha-2 code of the country.
+///
+/// # Returns
+///
+/// (`Vec<&'static str>`): The time zones, empty if the country code is not found.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::zones_for_country;
+///
+/// assert_eq!(zones_for_country("CH"), ["Europe/Zurich"]);
+/// assert_eq!(zones_for_country("AU").len(), 8);
+/// assert!(zones_for_country("XX").is_empty());
+/// ```
+pub fn zones_for_country(country_code: &str) -> Vec<&'static str> {
+    let mut zones: Vec<&'static str> = COUNTRY_DICT
         .get(country_code)
-        .and_then(|inner_map| {
-            let value = inner_map.get(key1);
-            if let Some(inner_key) = key2 {
-                // If the value is a HashMap and the second key is provided, retrieve the inner map value
-                inner_map.get(inner_key)
+        .map(|regions| regions.values().copied().collect())
+        .unwrap_or_default();
+    zones.sort_unstable();
+    zones.dedup();
+    zones
+}
+
+/// Resolves the time zone of a record like `time_zone_by_country`, trying the zone of its
+/// region, then the zone of its country as a whole, then the zone of its country when the
+/// country has a single one.
+///
+/// # Arguments
+///
+/// * `country_code` - The ISO 3166-1 alpha-2 code of the country.
+/// * `region_code` - The region code of the record, if it has one.
+///
+/// # Returns
+///
+/// (`Option<&'static str>`): The time zone, or None if the country spans several zones and the
+/// region does not tell which one, e.g. a city of the United States without a region.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::resolve_time_zone;
+///
+/// assert_eq!(resolve_time_zone("US", Some("CA")), Some("America/Los_Angeles"));
+/// assert_eq!(resolve_time_zone("CH", None), Some("Europe/Zurich"));
+/// assert_eq!(resolve_time_zone("US", None), None);
+/// ```
+pub fn resolve_time_zone(country_code: &str, region_code: Option<&str>) -> Option<&'static str> {
+    time_zone_by_country(country_code, region_code).or_else(|| {
+        match zones_for_country(country_code)[..] {
+            [zone] => Some(zone),
+            _ => None,
+        }
+    })
+}
+
+/// Returns every entry of `COUNTRY_DICT` as `(country code, region code, time zone)`, sorted by
+/// country and region code. The time zone of a country as a whole has the `DEFAULT_REGION_KEY`
+/// region code.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::time_zone_mappings;
+///
+/// let mappings = time_zone_mappings();
+/// assert!(mappings.contains(&("AD", "default", "Europe/Andorra")));
+/// assert!(mappings.contains(&("AU", "02", "Australia/NSW")));
+/// ```
+pub fn time_zone_mappings() -> Vec<(&'static str, &'static str, &'static str)> {
+    let mut mappings: Vec<_> = COUNTRY_DICT
+        .iter()
+        .flat_map(|(country, regions)| {
+            regions
+                .iter()
+                .map(|(region, zone)| (*country, *region, *zone))
+        })
+        .collect();
+    mappings.sort_unstable();
+    mappings
+}
+
+/// The time zones of `COUNTRY_DICT`, sorted and deduplicated.
+static ALL_ZONES: Lazy<Vec<&'static str>> = Lazy::new(|| {
+    let mut zones: Vec<&'static str> = COUNTRY_DICT
+        .values()
+        .flat_map(|regions| regions.values().copied())
+        .collect();
+    zones.sort_unstable();
+    zones.dedup();
+    zones
+});
+
+/// Returns every time zone `time_zone_by_country` can return, sorted and deduplicated, e.g. to
+/// validate a zone chosen by a user or to fill a dropdown.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::all_zones;
+///
+/// let zones = all_zones();
+/// assert!(zones.binary_search(&"Europe/London").is_ok());
+/// assert!(zones.binary_search(&"Europe/Atlantis").is_err());
+/// ```
+pub fn all_zones() -> &'static [&'static str] {
+    &ALL_ZONES
+}
+
+/// The environment variable holding the path of the time zone overrides of the CLI, see
+/// `OverrideSet::from_env`.
+pub const TZ_OVERRIDES_ENV: &str = "IPCAP_TZ_OVERRIDES";
+
+/// The zones of an `OverrideSet` that are not in the built-in tables, each allocated once for
+/// the lifetime of the process so that the records can keep borrowing `&'static str` zones.
+static OVERRIDE_ZONES: Lazy<Mutex<HashSet<&'static str>>> = Lazy::new(Mutex::default);
+
+/// The overrides of a country in an `OverrideSet`.
+#[derive(Debug, Clone, Default)]
+struct CountryOverrides {
+    /// The zone of the country as a whole, under `DEFAULT_REGION_KEY`.
+    default: Option<&'static str>,
+    /// The zones of the regions, keyed by upper-case region code.
+    regions: HashMap<Box<str>, &'static str>,
+}
+
+/// `OverrideSet` holds time zones of countries and regions taking precedence over the built-in
+/// tables, e.g. to fix an outdated mapping without waiting for a release.
+///
+/// The lookups of a reader built with `GeoIpReaderBuilder::time_zone_overrides` resolve the
+/// time zone of a record from, in order: the override of its region, the override of its
+/// country as a whole, then the built-in tables as `resolve_time_zone` does.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::OverrideSet;
+///
+/// let overrides = OverrideSet::parse(
+///     "IN.default = \"Asia/Kolkata\"\n\
+///      US.CA = \"America/Los_Angeles\"\n",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(overrides.time_zone("IN", Some("07")), Some("Asia/Kolkata"));
+/// assert_eq!(overrides.time_zone("US", Some("NY")), None);
+/// assert_eq!(overrides.time_zone("FR", None), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OverrideSet {
+    /// The overrides, keyed by the alpha-2 code of their country.
+    countries: HashMap<&'static str, CountryOverrides>,
+}
+
+impl OverrideSet {
+    /// Constructs an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a set from a TOML document of `country.region = "Area/Location"` entries, the
+    /// zone of a country as a whole being under the `default` region, e.g.
+    /// `IN.default = "Asia/Kolkata"`. The entries of a country can also be grouped under a
+    /// `[country]` table. Countries are 2 or 3-letter codes, codes are matched
+    /// case-insensitively, and `#` starts a comment.
+    ///
+    /// Only that subset of TOML is accepted. The zones do not have to be in the built-in
+    /// tables, but must look like IANA names: at least two `/`-separated parts of ASCII
+    /// letters, digits, `_`, `-` and `+`, starting with a letter.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OverrideError::InvalidLine` with the number of the first line that cannot be
+    /// parsed, or that has an unknown country code, an invalid zone or a duplicate key.
+    pub fn parse(text: &str) -> Result<Self, OverrideError> {
+        let mut set = OverrideSet::new();
+        let mut table: Option<Country> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let invalid = |reason: String| OverrideError::InvalidLine {
+                line: index + 1,
+                reason,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let (header, rest) = header
+                    .split_once(']')
+                    .ok_or_else(|| invalid("unclosed table header".to_string()))?;
+                if !is_blank_or_comment(rest) {
+                    return Err(invalid(format!("unexpected {:?} after table header", rest)));
+                }
+                let key = parse_key(header).map_err(invalid)?;
+                let [country] = key[..] else {
+                    return Err(invalid(format!(
+                        "expected a country table, found {:?}",
+                        header.trim()
+                    )));
+                };
+                table = Some(parse_country(country).map_err(invalid)?);
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `country.region = \"Area/Location\"`".into()))?;
+            let key = parse_key(key).map_err(invalid)?;
+            let (country, region) = match (table, &key[..]) {
+                (Some(country), [region]) => (country, *region),
+                (None, [country, region]) => (parse_country(country).map_err(invalid)?, *region),
+                _ => {
+                    return Err(invalid(format!(
+                        "expected a `country.region` key, found {:?}",
+                        key.join(".")
+                    )))
+                }
+            };
+            let zone = parse_zone(value).map_err(invalid)?;
+
+            let overrides = set
+                .countries
+                .entry(country.alphabetic_code_2())
+                .or_default();
+            let duplicate = if region.eq_ignore_ascii_case(DEFAULT_REGION_KEY) {
+                overrides.default.replace(zone).is_some()
+            } else {
+                let region = region.to_ascii_uppercase().into_boxed_str();
+                overrides.regions.insert(region, zone).is_some()
+            };
+            if duplicate {
+                return Err(invalid(format!(
+                    "duplicate key \"{}.{}\"",
+                    country.alphabetic_code_2(),
+                    region
+                )));
+            }
+        }
+        Ok(set)
+    }
+
+    /// Loads a set from a TOML file, in the format of `parse`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OverrideError::Read` if the file cannot be read, or the errors of `parse`.
+    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self, OverrideError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| OverrideError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text)
+    }
+
+    /// Loads the set of the file at the path of the `IPCAP_TZ_OVERRIDES` environment variable,
+    /// as the CLI does.
+    ///
+    /// # Returns
+    ///
+    /// (`Result<Option<OverrideSet>, OverrideError>`): The set, or `None` if the variable is
+    /// not set or empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `from_toml`.
+    pub fn from_env() -> Result<Option<Self>, OverrideError> {
+        match std::env::var_os(TZ_OVERRIDES_ENV) {
+            Some(path) if !path.is_empty() => Self::from_toml(path).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the overriding time zone of a country and region: the one of the region if the
+    /// set has it, otherwise the one of the country as a whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `country_code` - The ISO 3166-1 alpha-2 code of the country.
+    /// * `region_code` - The region code of the record, if it has one.
+    ///
+    /// # Returns
+    ///
+    /// (`Option<&'static str>`): The time zone, or None if the set does not override the zone
+    /// of the country and region, which then resolves from the built-in tables.
+    pub fn time_zone(&self, country_code: &str, region_code: Option<&str>) -> Option<&'static str> {
+        let overrides = get_ignore_case(&self.countries, country_code)?;
+        region_code
+            .and_then(|region_code| get_ignore_case(&overrides.regions, region_code))
+            .copied()
+            .or(overrides.default)
+    }
+
+    /// Resolves the time zone of a record like `resolve_time_zone`, the overrides of this set
+    /// taking precedence over the built-in tables.
+    pub fn resolve(&self, country_code: &str, region_code: Option<&str>) -> Option<&'static str> {
+        self.time_zone(country_code, region_code)
+            .or_else(|| resolve_time_zone(country_code, region_code))
+    }
+
+    /// Returns the number of overridden zones, regions and countries as a whole.
+    pub fn len(&self) -> usize {
+        self.countries
+            .values()
+            .map(|overrides| overrides.regions.len() + usize::from(overrides.default.is_some()))
+            .sum()
+    }
+
+    /// Returns whether the set overrides no zone.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns whether the rest of a line is blank or a comment.
+fn is_blank_or_comment(rest: &str) -> bool {
+    let rest = rest.trim();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Splits a dotted TOML key into its parts, unquoting the quoted ones.
+fn parse_key(key: &str) -> Result<Vec<&str>, String> {
+    key.split('.')
+        .map(|part| {
+            let part = part.trim();
+            let part = part
+                .strip_prefix('"')
+                .and_then(|part| part.strip_suffix('"'))
+                .unwrap_or(part);
+            if part.is_empty()
+                || !part
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-')
+            {
+                Err(format!("invalid key {:?}", key.trim()))
             } else {
-                value
+                Ok(part)
             }
         })
-        .copied()
+        .collect()
+}
+
+/// Parses the country code of a key.
+fn parse_country(code: &str) -> Result<Country, String> {
+    Country::from_code(code).ok_or_else(|| format!("unknown country code {:?}", code))
+}
+
+/// Parses the quoted zone of an entry, followed by an optional comment.
+fn parse_zone(value: &str) -> Result<&'static str, String> {
+    let value = value.trim();
+    let (zone, rest) = value
+        .strip_prefix('"')
+        .and_then(|value| value.split_once('"'))
+        .ok_or_else(|| format!("expected a quoted time zone, found {:?}", value))?;
+    if !is_blank_or_comment(rest) {
+        return Err(format!("unexpected {:?} after the time zone", rest.trim()));
+    }
+    if !is_iana_name(zone) {
+        return Err(format!(
+            "invalid time zone {:?}, expected an IANA Area/Location name",
+            zone
+        ));
+    }
+    Ok(static_zone(zone))
+}
+
+/// Returns whether a zone looks like an IANA name, e.g. `America/Argentina/Buenos_Aires` or
+/// `Etc/GMT+5`, without checking that it exists.
+fn is_iana_name(zone: &str) -> bool {
+    let mut parts = 0;
+    let valid = zone.split('/').all(|part| {
+        parts += 1;
+        part.bytes()
+            .next()
+            .is_some_and(|byte| byte.is_ascii_alphabetic())
+            && part
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || b"_-+".contains(&byte))
+    });
+    valid && parts >= 2
+}
+
+/// Returns a zone as a `&'static str`: the one of the built-in tables if they have it,
+/// otherwise the one of `OVERRIDE_ZONES`, allocated on its first use.
+fn static_zone(zone: &str) -> &'static str {
+    if let Some(zone) = known_time_zone(zone) {
+        return zone;
+    }
+    let mut zones = OVERRIDE_ZONES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match zones.get(zone) {
+        Some(zone) => zone,
+        None => {
+            let zone: &'static str = Box::leak(Box::from(zone));
+            zones.insert(zone);
+            zone
+        }
+    }
+}
+
+/// A transition between standard and daylight saving time, happening on the `week`th
+/// (`5` for the last) `weekday` (`0` for Sunday) of `month`, `time` seconds after the UTC
+/// midnight of that date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transition {
+    month: u32,
+    week: u32,
+    weekday: u32,
+    time: i64,
+}
+
+/// The daylight saving time of a zone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Dst {
+    offset: i32,
+    abbreviation: Option<&'static str>,
+    start: Transition,
+    end: Transition,
+}
+
+/// The current rule of a zone: its standard UTC offset in seconds and abbreviation, and its
+/// daylight saving time if it observes one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ZoneRule {
+    offset: i32,
+    abbreviation: Option<&'static str>,
+    dst: Option<Dst>,
+}
+
+/// The rules of the zones of `COUNTRY_DICT`, sorted by zone, as in effect since 2025.
+///
+/// Past rules are not kept, and rules tied to religious calendars (e.g. in Morocco during
+/// Ramadan) are approximated by the closest yearly rule.
+#[rustfmt::skip]
+static ZONE_RULES: &[(&str, ZoneRule)] = &[
+    ("Africa/Abidjan", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Accra", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Addis_Ababa", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/Algiers", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: None }),
+    ("Africa/Asmera", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/Bamako", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Bangui", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Banjul", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Bissau", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Blantyre", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Brazzaville", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Bujumbura", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Cairo", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 4, week: 5, weekday: 5, time: -7200 }, end: Transition { month: 10, week: 5, weekday: 4, time: 75600 } }) }),
+    ("Africa/Casablanca", ZoneRule { offset: 3600, abbreviation: None, dst: None }),
+    ("Africa/Ceuta", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Africa/Conakry", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Dakar", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Dar_es_Salaam", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/Djibouti", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/El_Aaiun", ZoneRule { offset: 3600, abbreviation: None, dst: None }),
+    ("Africa/Freetown", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Gaborone", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Harare", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Johannesburg", ZoneRule { offset: 7200, abbreviation: Some("SAST"), dst: None }),
+    ("Africa/Juba", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Kampala", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/Khartoum", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Kigali", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Kinshasa", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Lagos", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Libreville", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Lome", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Luanda", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Lubumbashi", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Lusaka", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Malabo", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Maputo", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("Africa/Maseru", ZoneRule { offset: 7200, abbreviation: Some("SAST"), dst: None }),
+    ("Africa/Mbabane", ZoneRule { offset: 7200, abbreviation: Some("SAST"), dst: None }),
+    ("Africa/Mogadishu", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/Monrovia", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Nairobi", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Africa/Ndjamena", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Niamey", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Nouakchott", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Ouagadougou", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Porto-Novo", ZoneRule { offset: 3600, abbreviation: Some("WAT"), dst: None }),
+    ("Africa/Sao_Tome", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Africa/Tripoli", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: None }),
+    ("Africa/Tunis", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: None }),
+    ("Africa/Windhoek", ZoneRule { offset: 7200, abbreviation: Some("CAT"), dst: None }),
+    ("America/Anchorage", ZoneRule { offset: -32400, abbreviation: Some("AKST"), dst: Some(Dst { offset: -28800, abbreviation: Some("AKDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 39600 }, end: Transition { month: 11, week: 1, weekday: 0, time: 36000 } }) }),
+    ("America/Anguilla", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Antigua", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Araguaina", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Buenos_Aires", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Catamarca", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Cordoba", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Jujuy", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/La_Rioja", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Mendoza", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Rio_Gallegos", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Salta", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/San_Juan", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/San_Luis", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Tucuman", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Argentina/Ushuaia", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Aruba", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Asuncion", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Bahia", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Barbados", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Belem", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Belize", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Boa_Vista", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Bogota", ZoneRule { offset: -18000, abbreviation: None, dst: None }),
+    ("America/Campo_Grande", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Cancun", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: None }),
+    ("America/Caracas", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Cayenne", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Cayman", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: None }),
+    ("America/Chicago", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: Some(Dst { offset: -18000, abbreviation: Some("CDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 28800 }, end: Transition { month: 11, week: 1, weekday: 0, time: 25200 } }) }),
+    ("America/Chihuahua", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Costa_Rica", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Cuiaba", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Curacao", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Denver", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: Some(Dst { offset: -21600, abbreviation: Some("MDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 32400 }, end: Transition { month: 11, week: 1, weekday: 0, time: 28800 } }) }),
+    ("America/Detroit", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Dominica", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Edmonton", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: Some(Dst { offset: -21600, abbreviation: Some("MDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 32400 }, end: Transition { month: 11, week: 1, weekday: 0, time: 28800 } }) }),
+    ("America/El_Salvador", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Fortaleza", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Godthab", ZoneRule { offset: -7200, abbreviation: None, dst: Some(Dst { offset: -3600, abbreviation: None, start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("America/Grand_Turk", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Grenada", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Guadeloupe", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Guatemala", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Guayaquil", ZoneRule { offset: -18000, abbreviation: None, dst: None }),
+    ("America/Guyana", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Halifax", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: Some(Dst { offset: -10800, abbreviation: Some("ADT"), start: Transition { month: 3, week: 2, weekday: 0, time: 21600 }, end: Transition { month: 11, week: 1, weekday: 0, time: 18000 } }) }),
+    ("America/Havana", ZoneRule { offset: -18000, abbreviation: Some("CST"), dst: Some(Dst { offset: -14400, abbreviation: Some("CDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 18000 }, end: Transition { month: 11, week: 1, weekday: 0, time: 18000 } }) }),
+    ("America/Hermosillo", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: None }),
+    ("America/Indianapolis", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Jamaica", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: None }),
+    ("America/La_Paz", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Lima", ZoneRule { offset: -18000, abbreviation: None, dst: None }),
+    ("America/Los_Angeles", ZoneRule { offset: -28800, abbreviation: Some("PST"), dst: Some(Dst { offset: -25200, abbreviation: Some("PDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 36000 }, end: Transition { month: 11, week: 1, weekday: 0, time: 32400 } }) }),
+    ("America/Lower_Princes", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Maceio", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Managua", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Manaus", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Marigot", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Martinique", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Mazatlan", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: None }),
+    ("America/Merida", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Mexico_City", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Miquelon", ZoneRule { offset: -10800, abbreviation: None, dst: Some(Dst { offset: -7200, abbreviation: None, start: Transition { month: 3, week: 2, weekday: 0, time: 18000 }, end: Transition { month: 11, week: 1, weekday: 0, time: 14400 } }) }),
+    ("America/Monterrey", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Montevideo", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Montreal", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Montserrat", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Nassau", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/New_York", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Panama", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: None }),
+    ("America/Paramaribo", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Phoenix", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: None }),
+    ("America/Port-au-Prince", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Port_of_Spain", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Porto_Velho", ZoneRule { offset: -14400, abbreviation: None, dst: None }),
+    ("America/Puerto_Rico", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Rankin_Inlet", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: Some(Dst { offset: -18000, abbreviation: Some("CDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 28800 }, end: Transition { month: 11, week: 1, weekday: 0, time: 25200 } }) }),
+    ("America/Recife", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/Regina", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Rio_Branco", ZoneRule { offset: -18000, abbreviation: None, dst: None }),
+    ("America/Santo_Domingo", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Sao_Paulo", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("America/St_Barthelemy", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/St_Johns", ZoneRule { offset: -12600, abbreviation: Some("NST"), dst: Some(Dst { offset: -9000, abbreviation: Some("NDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 19800 }, end: Transition { month: 11, week: 1, weekday: 0, time: 16200 } }) }),
+    ("America/St_Kitts", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/St_Lucia", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/St_Thomas", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/St_Vincent", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Tegucigalpa", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: None }),
+    ("America/Thule", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: Some(Dst { offset: -10800, abbreviation: Some("ADT"), start: Transition { month: 3, week: 2, weekday: 0, time: 21600 }, end: Transition { month: 11, week: 1, weekday: 0, time: 18000 } }) }),
+    ("America/Tijuana", ZoneRule { offset: -28800, abbreviation: Some("PST"), dst: Some(Dst { offset: -25200, abbreviation: Some("PDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 36000 }, end: Transition { month: 11, week: 1, weekday: 0, time: 32400 } }) }),
+    ("America/Toronto", ZoneRule { offset: -18000, abbreviation: Some("EST"), dst: Some(Dst { offset: -14400, abbreviation: Some("EDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 25200 }, end: Transition { month: 11, week: 1, weekday: 0, time: 21600 } }) }),
+    ("America/Tortola", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: None }),
+    ("America/Vancouver", ZoneRule { offset: -28800, abbreviation: Some("PST"), dst: Some(Dst { offset: -25200, abbreviation: Some("PDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 36000 }, end: Transition { month: 11, week: 1, weekday: 0, time: 32400 } }) }),
+    ("America/Whitehorse", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: None }),
+    ("America/Winnipeg", ZoneRule { offset: -21600, abbreviation: Some("CST"), dst: Some(Dst { offset: -18000, abbreviation: Some("CDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 28800 }, end: Transition { month: 11, week: 1, weekday: 0, time: 25200 } }) }),
+    ("America/Yellowknife", ZoneRule { offset: -25200, abbreviation: Some("MST"), dst: Some(Dst { offset: -21600, abbreviation: Some("MDT"), start: Transition { month: 3, week: 2, weekday: 0, time: 32400 }, end: Transition { month: 11, week: 1, weekday: 0, time: 28800 } }) }),
+    ("Arctic/Longyearbyen", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Asia/Aden", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Almaty", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Amman", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Anadyr", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("Asia/Aqtau", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Aqtobe", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Ashgabat", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Baghdad", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Bahrain", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Baku", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Asia/Bangkok", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Beirut", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: -7200 }, end: Transition { month: 10, week: 5, weekday: 0, time: -10800 } }) }),
+    ("Asia/Bishkek", ZoneRule { offset: 21600, abbreviation: None, dst: None }),
+    ("Asia/Brunei", ZoneRule { offset: 28800, abbreviation: None, dst: None }),
+    ("Asia/Bukhara", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Calcutta", ZoneRule { offset: 19800, abbreviation: Some("IST"), dst: None }),
+    ("Asia/Choibalsan", ZoneRule { offset: 28800, abbreviation: None, dst: None }),
+    ("Asia/Chongqing", ZoneRule { offset: 28800, abbreviation: Some("CST"), dst: None }),
+    ("Asia/Colombo", ZoneRule { offset: 19800, abbreviation: None, dst: None }),
+    ("Asia/Damascus", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Dhaka", ZoneRule { offset: 21600, abbreviation: None, dst: None }),
+    ("Asia/Dili", ZoneRule { offset: 32400, abbreviation: None, dst: None }),
+    ("Asia/Dubai", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Asia/Dushanbe", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Gaza", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 4, week: 2, weekday: 6, time: 0 }, end: Transition { month: 10, week: 4, weekday: 6, time: -3600 } }) }),
+    ("Asia/Harbin", ZoneRule { offset: 28800, abbreviation: Some("CST"), dst: None }),
+    ("Asia/Ho_Chi_Minh", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Hong_Kong", ZoneRule { offset: 28800, abbreviation: Some("HKT"), dst: None }),
+    ("Asia/Irkutsk", ZoneRule { offset: 28800, abbreviation: None, dst: None }),
+    ("Asia/Jakarta", ZoneRule { offset: 25200, abbreviation: Some("WIB"), dst: None }),
+    ("Asia/Jayapura", ZoneRule { offset: 32400, abbreviation: Some("WIT"), dst: None }),
+    ("Asia/Jerusalem", ZoneRule { offset: 7200, abbreviation: Some("IST"), dst: Some(Dst { offset: 10800, abbreviation: Some("IDT"), start: Transition { month: 3, week: 4, weekday: 5, time: 0 }, end: Transition { month: 10, week: 5, weekday: 0, time: -3600 } }) }),
+    ("Asia/Kabul", ZoneRule { offset: 16200, abbreviation: None, dst: None }),
+    ("Asia/Kamchatka", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("Asia/Karachi", ZoneRule { offset: 18000, abbreviation: Some("PKT"), dst: None }),
+    ("Asia/Katmandu", ZoneRule { offset: 20700, abbreviation: None, dst: None }),
+    ("Asia/Krasnoyarsk", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Kuala_Lumpur", ZoneRule { offset: 28800, abbreviation: None, dst: None }),
+    ("Asia/Kuching", ZoneRule { offset: 28800, abbreviation: None, dst: None }),
+    ("Asia/Kuwait", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Macao", ZoneRule { offset: 28800, abbreviation: Some("CST"), dst: None }),
+    ("Asia/Magadan", ZoneRule { offset: 39600, abbreviation: None, dst: None }),
+    ("Asia/Makassar", ZoneRule { offset: 28800, abbreviation: Some("WITA"), dst: None }),
+    ("Asia/Manila", ZoneRule { offset: 28800, abbreviation: Some("PST"), dst: None }),
+    ("Asia/Muscat", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Asia/Nicosia", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Asia/Novokuznetsk", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Novosibirsk", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Omsk", ZoneRule { offset: 21600, abbreviation: None, dst: None }),
+    ("Asia/Oral", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Phnom_Penh", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Pontianak", ZoneRule { offset: 25200, abbreviation: Some("WIB"), dst: None }),
+    ("Asia/Pyongyang", ZoneRule { offset: 32400, abbreviation: Some("KST"), dst: None }),
+    ("Asia/Qatar", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Qyzylorda", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Rangoon", ZoneRule { offset: 23400, abbreviation: None, dst: None }),
+    ("Asia/Riyadh", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Asia/Sakhalin", ZoneRule { offset: 39600, abbreviation: None, dst: None }),
+    ("Asia/Samarkand", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Seoul", ZoneRule { offset: 32400, abbreviation: Some("KST"), dst: None }),
+    ("Asia/Shanghai", ZoneRule { offset: 28800, abbreviation: Some("CST"), dst: None }),
+    ("Asia/Singapore", ZoneRule { offset: 28800, abbreviation: None, dst: None }),
+    ("Asia/Taipei", ZoneRule { offset: 28800, abbreviation: Some("CST"), dst: None }),
+    ("Asia/Tashkent", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Tbilisi", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Asia/Tehran", ZoneRule { offset: 12600, abbreviation: None, dst: None }),
+    ("Asia/Thimphu", ZoneRule { offset: 21600, abbreviation: None, dst: None }),
+    ("Asia/Tokyo", ZoneRule { offset: 32400, abbreviation: Some("JST"), dst: None }),
+    ("Asia/Urumqi", ZoneRule { offset: 21600, abbreviation: None, dst: None }),
+    ("Asia/Vientiane", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Asia/Vladivostok", ZoneRule { offset: 36000, abbreviation: None, dst: None }),
+    ("Asia/Yakutsk", ZoneRule { offset: 32400, abbreviation: None, dst: None }),
+    ("Asia/Yekaterinburg", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Asia/Yerevan", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Atlantic/Bermuda", ZoneRule { offset: -14400, abbreviation: Some("AST"), dst: Some(Dst { offset: -10800, abbreviation: Some("ADT"), start: Transition { month: 3, week: 2, weekday: 0, time: 21600 }, end: Transition { month: 11, week: 1, weekday: 0, time: 18000 } }) }),
+    ("Atlantic/Canary", ZoneRule { offset: 0, abbreviation: Some("WET"), dst: Some(Dst { offset: 3600, abbreviation: Some("WEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Atlantic/Cape_Verde", ZoneRule { offset: -3600, abbreviation: None, dst: None }),
+    ("Atlantic/Faeroe", ZoneRule { offset: 0, abbreviation: Some("WET"), dst: Some(Dst { offset: 3600, abbreviation: Some("WEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Atlantic/Madeira", ZoneRule { offset: 0, abbreviation: Some("WET"), dst: Some(Dst { offset: 3600, abbreviation: Some("WEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Atlantic/Reykjavik", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Atlantic/South_Georgia", ZoneRule { offset: -7200, abbreviation: None, dst: None }),
+    ("Atlantic/St_Helena", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: None }),
+    ("Atlantic/Stanley", ZoneRule { offset: -10800, abbreviation: None, dst: None }),
+    ("Australia/Canberra", ZoneRule { offset: 36000, abbreviation: Some("AEST"), dst: Some(Dst { offset: 39600, abbreviation: Some("AEDT"), start: Transition { month: 10, week: 1, weekday: 0, time: -28800 }, end: Transition { month: 4, week: 1, weekday: 0, time: -28800 } }) }),
+    ("Australia/NSW", ZoneRule { offset: 36000, abbreviation: Some("AEST"), dst: Some(Dst { offset: 39600, abbreviation: Some("AEDT"), start: Transition { month: 10, week: 1, weekday: 0, time: -28800 }, end: Transition { month: 4, week: 1, weekday: 0, time: -28800 } }) }),
+    ("Australia/North", ZoneRule { offset: 34200, abbreviation: Some("ACST"), dst: None }),
+    ("Australia/Queensland", ZoneRule { offset: 36000, abbreviation: Some("AEST"), dst: None }),
+    ("Australia/South", ZoneRule { offset: 34200, abbreviation: Some("ACST"), dst: Some(Dst { offset: 37800, abbreviation: Some("ACDT"), start: Transition { month: 10, week: 1, weekday: 0, time: -27000 }, end: Transition { month: 4, week: 1, weekday: 0, time: -27000 } }) }),
+    ("Australia/Tasmania", ZoneRule { offset: 36000, abbreviation: Some("AEST"), dst: Some(Dst { offset: 39600, abbreviation: Some("AEDT"), start: Transition { month: 10, week: 1, weekday: 0, time: -28800 }, end: Transition { month: 4, week: 1, weekday: 0, time: -28800 } }) }),
+    ("Australia/Victoria", ZoneRule { offset: 36000, abbreviation: Some("AEST"), dst: Some(Dst { offset: 39600, abbreviation: Some("AEDT"), start: Transition { month: 10, week: 1, weekday: 0, time: -28800 }, end: Transition { month: 4, week: 1, weekday: 0, time: -28800 } }) }),
+    ("Australia/West", ZoneRule { offset: 28800, abbreviation: Some("AWST"), dst: None }),
+    ("Chile/Continental", ZoneRule { offset: -14400, abbreviation: None, dst: Some(Dst { offset: -10800, abbreviation: None, start: Transition { month: 9, week: 1, weekday: 0, time: 14400 }, end: Transition { month: 4, week: 1, weekday: 0, time: 10800 } }) }),
+    ("Europe/Amsterdam", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Andorra", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Athens", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Belgrade", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Berlin", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Bratislava", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Brussels", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Bucharest", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Budapest", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Chisinau", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 0 }, end: Transition { month: 10, week: 5, weekday: 0, time: 0 } }) }),
+    ("Europe/Copenhagen", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Dnipro", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Dublin", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: Some(Dst { offset: 3600, abbreviation: Some("IST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Gibraltar", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Guernsey", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: Some(Dst { offset: 3600, abbreviation: Some("BST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Helsinki", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Isle_of_Man", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: Some(Dst { offset: 3600, abbreviation: Some("BST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Istanbul", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Europe/Jersey", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: Some(Dst { offset: 3600, abbreviation: Some("BST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Kaliningrad", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: None }),
+    ("Europe/Kiev", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Lisbon", ZoneRule { offset: 0, abbreviation: Some("WET"), dst: Some(Dst { offset: 3600, abbreviation: Some("WEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Ljubljana", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/London", ZoneRule { offset: 0, abbreviation: Some("GMT"), dst: Some(Dst { offset: 3600, abbreviation: Some("BST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Luxembourg", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Madrid", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Malta", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Mariehamn", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Minsk", ZoneRule { offset: 10800, abbreviation: None, dst: None }),
+    ("Europe/Monaco", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Moscow", ZoneRule { offset: 10800, abbreviation: Some("MSK"), dst: None }),
+    ("Europe/Oslo", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Paris", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Podgorica", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Prague", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Riga", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Rome", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Samara", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Europe/San_Marino", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Sarajevo", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Simferopol", ZoneRule { offset: 10800, abbreviation: Some("MSK"), dst: None }),
+    ("Europe/Skopje", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Sofia", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Stockholm", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Tallinn", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Tirane", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Uzhgorod", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Vaduz", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Vatican", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Vienna", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Vilnius", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Volgograd", ZoneRule { offset: 10800, abbreviation: Some("MSK"), dst: None }),
+    ("Europe/Warsaw", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Zagreb", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Zaporozhye", ZoneRule { offset: 7200, abbreviation: Some("EET"), dst: Some(Dst { offset: 10800, abbreviation: Some("EEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Europe/Zurich", ZoneRule { offset: 3600, abbreviation: Some("CET"), dst: Some(Dst { offset: 7200, abbreviation: Some("CEST"), start: Transition { month: 3, week: 5, weekday: 0, time: 3600 }, end: Transition { month: 10, week: 5, weekday: 0, time: 3600 } }) }),
+    ("Indian/Antananarivo", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Indian/Chagos", ZoneRule { offset: 21600, abbreviation: None, dst: None }),
+    ("Indian/Christmas", ZoneRule { offset: 25200, abbreviation: None, dst: None }),
+    ("Indian/Cocos", ZoneRule { offset: 23400, abbreviation: None, dst: None }),
+    ("Indian/Comoro", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Indian/Kerguelen", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Indian/Mahe", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Indian/Maldives", ZoneRule { offset: 18000, abbreviation: None, dst: None }),
+    ("Indian/Mauritius", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Indian/Mayotte", ZoneRule { offset: 10800, abbreviation: Some("EAT"), dst: None }),
+    ("Indian/Reunion", ZoneRule { offset: 14400, abbreviation: None, dst: None }),
+    ("Pacific/Apia", ZoneRule { offset: 46800, abbreviation: None, dst: None }),
+    ("Pacific/Auckland", ZoneRule { offset: 43200, abbreviation: Some("NZST"), dst: Some(Dst { offset: 46800, abbreviation: Some("NZDT"), start: Transition { month: 9, week: 5, weekday: 0, time: -36000 }, end: Transition { month: 4, week: 1, weekday: 0, time: -36000 } }) }),
+    ("Pacific/Chatham", ZoneRule { offset: 45900, abbreviation: None, dst: Some(Dst { offset: 49500, abbreviation: None, start: Transition { month: 9, week: 5, weekday: 0, time: -36000 }, end: Transition { month: 4, week: 1, weekday: 0, time: -36000 } }) }),
+    ("Pacific/Efate", ZoneRule { offset: 39600, abbreviation: None, dst: None }),
+    ("Pacific/Fakaofo", ZoneRule { offset: 46800, abbreviation: None, dst: None }),
+    ("Pacific/Fiji", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("Pacific/Funafuti", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("Pacific/Galapagos", ZoneRule { offset: -21600, abbreviation: None, dst: None }),
+    ("Pacific/Guadalcanal", ZoneRule { offset: 39600, abbreviation: None, dst: None }),
+    ("Pacific/Guam", ZoneRule { offset: 36000, abbreviation: Some("ChST"), dst: None }),
+    ("Pacific/Honolulu", ZoneRule { offset: -36000, abbreviation: Some("HST"), dst: None }),
+    ("Pacific/Johnston", ZoneRule { offset: -36000, abbreviation: Some("HST"), dst: None }),
+    ("Pacific/Marquesas", ZoneRule { offset: -34200, abbreviation: None, dst: None }),
+    ("Pacific/Midway", ZoneRule { offset: -39600, abbreviation: Some("SST"), dst: None }),
+    ("Pacific/Nauru", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("Pacific/Niue", ZoneRule { offset: -39600, abbreviation: None, dst: None }),
+    ("Pacific/Norfolk", ZoneRule { offset: 39600, abbreviation: None, dst: Some(Dst { offset: 43200, abbreviation: None, start: Transition { month: 10, week: 1, weekday: 0, time: -32400 }, end: Transition { month: 4, week: 1, weekday: 0, time: -32400 } }) }),
+    ("Pacific/Noumea", ZoneRule { offset: 39600, abbreviation: None, dst: None }),
+    ("Pacific/Pago_Pago", ZoneRule { offset: -39600, abbreviation: Some("SST"), dst: None }),
+    ("Pacific/Palau", ZoneRule { offset: 32400, abbreviation: None, dst: None }),
+    ("Pacific/Pitcairn", ZoneRule { offset: -28800, abbreviation: None, dst: None }),
+    ("Pacific/Port_Moresby", ZoneRule { offset: 36000, abbreviation: None, dst: None }),
+    ("Pacific/Rarotonga", ZoneRule { offset: -36000, abbreviation: None, dst: None }),
+    ("Pacific/Saipan", ZoneRule { offset: 36000, abbreviation: Some("ChST"), dst: None }),
+    ("Pacific/Tarawa", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("Pacific/Tongatapu", ZoneRule { offset: 46800, abbreviation: None, dst: None }),
+    ("Pacific/Wallis", ZoneRule { offset: 43200, abbreviation: None, dst: None }),
+    ("US/Samoa", ZoneRule { offset: -39600, abbreviation: Some("SST"), dst: None }),
+];
+
+/// Returns the number of days since 1970-01-01 of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Returns the year of a number of days since 1970-01-01.
+fn year_from_days(days: i64) -> i64 {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    year_of_era + era * 400 + i64::from(month_index >= 10)
+}
+
+/// Returns the number of days of a month.
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Transition {
+    /// Returns the unix timestamp of the transition in the given year.
+    fn at(&self, year: i64) -> i64 {
+        let first = days_from_civil(year, self.month, 1);
+        // 1970-01-01 was a Thursday
+        let first_weekday = (first + 4).rem_euclid(7) as u32;
+        let mut day = 1 + (self.weekday + 7 - first_weekday) % 7;
+        if self.week == 5 {
+            while day + 7 <= days_in_month(year, self.month) {
+                day += 7;
+            }
+        } else {
+            day += 7 * (self.week - 1);
+        }
+        (first + day as i64 - 1) * 86400 + self.time
+    }
+}
+
+impl ZoneRule {
+    /// Returns the daylight saving time in effect at the given instant, if any.
+    fn dst_at(&self, unix_ts: i64) -> Option<&Dst> {
+        let dst = self.dst.as_ref()?;
+        let year = year_from_days(unix_ts.div_euclid(86400));
+        let (start, end) = (dst.start.at(year), dst.end.at(year));
+
+        // In the southern hemisphere, the daylight saving time spans the new year
+        let in_dst = if start < end {
+            start <= unix_ts && unix_ts < end
+        } else {
+            unix_ts >= start || unix_ts < end
+        };
+        in_dst.then_some(dst)
+    }
+}
+
+fn zone_rule(zone: &str) -> Option<&'static ZoneRule> {
+    ZONE_RULES
+        .binary_search_by(|(name, _)| (*name).cmp(zone))
+        .ok()
+        .and_then(|index| ZONE_RULES.get(index))
+        .map(|(_, rule)| rule)
+}
+
+/// Looks up a time zone by its IANA name.
+///
+/// # Arguments
+///
+/// * `zone` - The name of the time zone, e.g. `Europe/Berlin`.
+///
+/// # Returns
+///
+/// (`Option<&'static str>`): The name, with a static lifetime to be stored in a `Record`, or
+/// None if the zone is unknown.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::known_time_zone;
+///
+/// assert_eq!(known_time_zone("Europe/Berlin"), Some("Europe/Berlin"));
+/// assert_eq!(known_time_zone("Europe/Atlantis"), None);
+/// ```
+pub fn known_time_zone(zone: &str) -> Option<&'static str> {
+    ZONE_RULES
+        .binary_search_by(|(name, _)| (*name).cmp(zone))
+        .ok()
+        .and_then(|index| ZONE_RULES.get(index))
+        .map(|(name, _)| *name)
+}
+
+/// Returns the UTC offset of a time zone at a given instant, accounting for daylight saving time.
+///
+/// # Arguments
+///
+/// * `zone` - An IANA time zone name, as returned by `time_zone_by_country`.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+///
+/// # Returns
+///
+/// (`Option<i32>`): The offset in seconds, or None if the zone is unknown.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::utc_offset;
+///
+/// // 2024-01-15 and 2024-07-15
+/// assert_eq!(utc_offset("America/Los_Angeles", 1705276800), Some(-8 * 3600));
+/// assert_eq!(utc_offset("America/Los_Angeles", 1721001600), Some(-7 * 3600));
+/// assert_eq!(utc_offset("Asia/Calcutta", 1705276800), Some(19800));
+/// ```
+pub fn utc_offset(zone: &str, unix_ts: i64) -> Option<i32> {
+    let rule = zone_rule(zone)?;
+    Some(rule.dst_at(unix_ts).map_or(rule.offset, |dst| dst.offset))
+}
+
+/// Returns the abbreviation of a time zone at a given instant, e.g. "PST" or "PDT".
+///
+/// # Arguments
+///
+/// * `zone` - An IANA time zone name, as returned by `time_zone_by_country`.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+///
+/// # Returns
+///
+/// (`Option<&'static str>`): The abbreviation, or None if the zone is unknown or has no
+/// alphabetic abbreviation (see `display_abbreviation` for a fallback).
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::abbreviation;
+///
+/// assert_eq!(abbreviation("America/Los_Angeles", 1705276800), Some("PST"));
+/// assert_eq!(abbreviation("America/Los_Angeles", 1721001600), Some("PDT"));
+/// assert_eq!(abbreviation("Asia/Riyadh", 1705276800), None);
+/// ```
+pub fn abbreviation(zone: &str, unix_ts: i64) -> Option<&'static str> {
+    let rule = zone_rule(zone)?;
+    match rule.dst_at(unix_ts) {
+        Some(dst) => dst.abbreviation,
+        None => rule.abbreviation,
+    }
+}
+
+/// Returns the abbreviation of a time zone at a given instant, falling back to the offset
+/// form, e.g. "UTC+3", for zones without an alphabetic abbreviation.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::display_abbreviation;
+///
+/// assert_eq!(display_abbreviation("Europe/London", 1705276800), Some("GMT".to_string()));
+/// assert_eq!(display_abbreviation("Asia/Riyadh", 1705276800), Some("UTC+3".to_string()));
+/// assert_eq!(display_abbreviation("Asia/Katmandu", 1705276800), Some("UTC+5:45".to_string()));
+/// assert_eq!(display_abbreviation("Mars/Olympus_Mons", 1705276800), None);
+/// ```
+pub fn display_abbreviation(zone: &str, unix_ts: i64) -> Option<String> {
+    if let Some(abbreviation) = abbreviation(zone, unix_ts) {
+        return Some(abbreviation.to_string());
+    }
+
+    let offset = utc_offset(zone, unix_ts)?;
+    let (hours, minutes) = (
+        offset.unsigned_abs() / 3600,
+        offset.unsigned_abs() % 3600 / 60,
+    );
+    let sign = if offset < 0 { '-' } else { '+' };
+    Some(match (hours, minutes) {
+        (0, 0) => "UTC".to_string(),
+        (_, 0) => format!("UTC{}{}", sign, hours),
+        _ => format!("UTC{}{}:{:02}", sign, hours, minutes),
+    })
+}
+
+/// Formats a UTC offset in seconds as in RFC 3339, e.g. "-08:00".
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::format_utc_offset;
+///
+/// assert_eq!(format_utc_offset(-8 * 3600), "-08:00");
+/// assert_eq!(format_utc_offset(19800), "+05:30");
+/// assert_eq!(format_utc_offset(0), "+00:00");
+/// ```
+pub fn format_utc_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.unsigned_abs();
+    format!("{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+}
+
+/// Returns the current time, in seconds since the Unix epoch.
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Parses an instant given either as seconds since the Unix epoch or as an RFC 3339 date-time
+/// of the form `YYYY-MM-DDTHH:MM:SS[.fraction](Z|+HH:MM|-HH:MM)`.
+///
+/// # Arguments
+///
+/// * `value` - The instant to parse.
+///
+/// # Returns
+///
+/// (`Option<i64>`): The instant in seconds since the Unix epoch, the fraction of a second being
+/// truncated, or None if the value is of neither form.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::time_zones::parse_timestamp;
+///
+/// assert_eq!(parse_timestamp("1705276800"), Some(1705276800));
+/// assert_eq!(parse_timestamp("2024-01-15T00:00:00Z"), Some(1705276800));
+/// assert_eq!(parse_timestamp("2024-01-14T16:00:00.5-08:00"), Some(1705276800));
+/// assert_eq!(parse_timestamp("2024-01-15"), None);
+/// ```
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return value.parse().ok();
+    }
+
+    let bytes = value.as_bytes();
+    let number = |range: std::ops::Range<usize>| -> Option<u32> {
+        let field = bytes.get(range)?;
+        if !field.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        std::str::from_utf8(field).ok()?.parse().ok()
+    };
+    let separators = [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')];
+    if separators.iter().any(|&(i, c)| bytes.get(i) != Some(&c)) {
+        return None;
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year as i64, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    // An optional fraction of a second
+    let mut rest = &value[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        rest = &fraction[len..];
+    }
+
+    let offset = match rest.as_bytes() {
+        [b'Z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let hours = number_of(&[*h1, *h2])?;
+            let minutes = number_of(&[*m1, *m2])?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = (hours * 3600 + minutes * 60) as i64;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year as i64, month, day);
+    Some(days * 86400 + (hour * 3600 + minute * 60 + second) as i64 - offset)
+}
+
+/// Parses two ASCII digits.
+fn number_of(digits: &[u8; 2]) -> Option<u32> {
+    digits.iter().try_fold(0, |value, digit| match digit {
+        b'0'..=b'9' => Some(value * 10 + u32::from(digit - b'0')),
+        _ => None,
+    })
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_all_zones() {
+        let zones = all_zones();
+
+        for zone in ["America/Los_Angeles", "Europe/London", "Australia/NSW"] {
+            assert!(zones.contains(&zone), "{}", zone);
+        }
+        assert!(zones.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(zones
+            .iter()
+            .all(|zone| time_zone_mappings().iter().any(|(_, _, z)| z == zone)));
+        assert!(zones.iter().all(|zone| known_time_zone(zone).is_some()));
+    }
+
+    #[test]
+    fn test_override_set() {
+        let overrides = OverrideSet::parse(
+            "# Renamed zones\n\
+             \n\
+             IN.default = \"Asia/Kolkata\"  # was Asia/Calcutta\n\
+             usa.\"ca\" = \"America/Phoenix\"\n\
+             [AU]\n\
+             default = \"Australia/Sydney\"\n\
+             03 = \"Australia/Darwin\"\n",
+        )
+        .unwrap();
+        assert_eq!(overrides.len(), 4);
+
+        // The override of the region, then of the country as a whole
+        assert_eq!(
+            overrides.time_zone("AU", Some("03")),
+            Some("Australia/Darwin")
+        );
+        assert_eq!(
+            overrides.time_zone("au", Some("02")),
+            Some("Australia/Sydney")
+        );
+        assert_eq!(overrides.time_zone("AU", None), Some("Australia/Sydney"));
+        assert_eq!(
+            overrides.time_zone("US", Some("CA")),
+            Some("America/Phoenix")
+        );
+        assert_eq!(overrides.time_zone("US", Some("NY")), None);
+        assert_eq!(overrides.time_zone("IN", Some("07")), Some("Asia/Kolkata"));
+        assert_eq!(overrides.time_zone("FR", None), None);
+
+        // Then the built-in region, then the built-in country as a whole
+        assert_eq!(overrides.resolve("IN", None), Some("Asia/Kolkata"));
+        assert_eq!(
+            overrides.resolve("US", Some("NY")),
+            Some("America/New_York")
+        );
+        assert_eq!(overrides.resolve("FR", None), Some("Europe/Paris"));
+        assert_eq!(overrides.resolve("US", None), None);
+
+        // A zone outside the built-in tables is allocated once
+        let again = OverrideSet::parse("IN.default = \"Asia/Kolkata\"").unwrap();
+        assert!(std::ptr::eq(
+            overrides.time_zone("IN", None).unwrap(),
+            again.time_zone("IN", None).unwrap()
+        ));
+        assert!(OverrideSet::parse("# nothing\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_override_set_errors() {
+        let cases = [
+            (
+                "XX.default = \"Europe/Paris\"",
+                1,
+                "unknown country code \"XX\"",
+            ),
+            (
+                "# comment\n[Atlantis]\n",
+                2,
+                "unknown country code \"Atlantis\"",
+            ),
+            (
+                "FR.default = \"Paris\"",
+                1,
+                "invalid time zone \"Paris\", expected an IANA Area/Location name",
+            ),
+            (
+                "FR.default = \"Europe/Paris City\"",
+                1,
+                "invalid time zone \"Europe/Paris City\", expected an IANA Area/Location name",
+            ),
+            (
+                "FR.default = \"Europe//Paris\"",
+                1,
+                "invalid time zone \"Europe//Paris\", expected an IANA Area/Location name",
+            ),
+            (
+                "FR.default = Europe/Paris",
+                1,
+                "expected a quoted time zone, found \"Europe/Paris\"",
+            ),
+            (
+                "FR.default = \"Europe/Paris\" extra",
+                1,
+                "unexpected \"extra\" after the time zone",
+            ),
+            (
+                "FR = \"Europe/Paris\"",
+                1,
+                "expected a `country.region` key, found \"FR\"",
+            ),
+            (
+                "[FR]\nFR.default = \"Europe/Paris\"",
+                2,
+                "expected a `country.region` key, found \"FR.default\"",
+            ),
+            ("[FR.01]", 1, "expected a country table, found \"FR.01\""),
+            ("[FR", 1, "unclosed table header"),
+            (
+                "FR.default \"Europe/Paris\"",
+                1,
+                "expected `country.region = \"Area/Location\"`",
+            ),
+            ("FR. = \"Europe/Paris\"", 1, "invalid key \"FR.\""),
+            (
+                "US.CA = \"America/Phoenix\"\n\n[US]\nca = \"America/Denver\"",
+                4,
+                "duplicate key \"US.ca\"",
+            ),
+            (
+                "FRA.default = \"Europe/Paris\"\nFR.default = \"Europe/Paris\"",
+                2,
+                "duplicate key \"FR.default\"",
+            ),
+        ];
+
+        for (text, line, reason) in cases {
+            let err = OverrideSet::parse(text).unwrap_err();
+            assert!(
+                matches!(&err, OverrideError::InvalidLine { line: l, reason: r } if *l == line && r == reason),
+                "{:?}: {}",
+                text,
+                err
+            );
+        }
+        assert!(matches!(
+            OverrideSet::from_toml("/nonexistent/time_zones.toml"),
+            Err(OverrideError::Read { .. })
+        ));
+    }
+
     #[test]
     fn test_time_zone_by_country() {
-        // Test with a default key and no inner key
+        // A country as a whole
+        assert_eq!(time_zone_by_country("AD", None), Some("Europe/Andorra"));
         assert_eq!(
-            time_zone_by_country("AD", "default", None),
+            time_zone_by_country("AD", Some(DEFAULT_REGION_KEY)),
             Some("Europe/Andorra")
         );
 
-        // Test with a default key and an inner key
+        // A region with its own zone
         assert_eq!(
-            time_zone_by_country("AR", "default", Some("01")),
+            time_zone_by_country("AR", Some("01")),
             Some("America/Argentina/Buenos_Aires")
         );
 
+        // A region without a zone falls back to the country as a whole, if it has one
+        assert_eq!(
+            time_zone_by_country("AD", Some("non_existent")),
+            Some("Europe/Andorra")
+        );
+        assert_eq!(time_zone_by_country("AR", Some("non_existent")), None);
+        assert_eq!(time_zone_by_country("AR", None), None);
+
         // Test with a non-existent country code
-        assert_eq!(time_zone_by_country("XX", "default", None), None);
+        assert_eq!(time_zone_by_country("XX", None), None);
+        assert_eq!(time_zone_by_country("XX", Some("01")), None);
+    }
 
-        // Test with a non-existent inner key
+    #[test]
+    fn test_time_zone_by_country_ignores_case() {
         assert_eq!(
-            time_zone_by_country("AD", "non_existent", Some("inner_key")),
-            None
+            time_zone_by_country("us", Some("ca")),
+            Some("America/Los_Angeles")
+        );
+        assert_eq!(
+            time_zone_by_country("Ca", Some("On")),
+            Some("America/Toronto")
+        );
+        assert_eq!(time_zone_by_country("ad", None), Some("Europe/Andorra"));
+        assert_eq!(
+            time_zone_by_country("ad", Some(DEFAULT_REGION_KEY)),
+            Some("Europe/Andorra")
         );
+        assert_eq!(time_zone_by_country("xx", None), None);
+    }
+
+    #[test]
+    fn test_resolve_time_zone() {
+        // The zone of the region comes first
+        assert_eq!(
+            resolve_time_zone("US", Some("NY")),
+            Some("America/New_York")
+        );
+        // Then the default zone of the country, also for an unknown region
+        assert_eq!(resolve_time_zone("CH", None), Some("Europe/Zurich"));
+        assert_eq!(resolve_time_zone("CH", Some("ZZ")), Some("Europe/Zurich"));
+        // Several zones and no region to choose between them
+        assert_eq!(resolve_time_zone("US", None), None);
+        assert_eq!(resolve_time_zone("US", Some("ZZ")), None);
+        assert_eq!(resolve_time_zone("XX", None), None);
+
+        // Every country of a single zone resolves to it without a region
+        for (country, _, _) in time_zone_mappings() {
+            let zones = zones_for_country(country);
+            assert!(!zones.is_empty());
+            if let [zone] = zones[..] {
+                assert_eq!(resolve_time_zone(country, None), Some(zone), "{}", country);
+            }
+        }
+    }
+
+    // 2024-03-10 10:00:00 UTC, when Los Angeles switches to daylight saving time
+    const US_DST_START: i64 = 1710064800;
+    // 2024-11-03 09:00:00 UTC, when Los Angeles switches back to standard time
+    const US_DST_END: i64 = 1730624400;
+
+    #[test]
+    fn test_abbreviation_across_dst_boundaries() {
+        let zone = "America/Los_Angeles";
+
+        assert_eq!(abbreviation(zone, US_DST_START - 1), Some("PST"));
+        assert_eq!(abbreviation(zone, US_DST_START), Some("PDT"));
+        assert_eq!(utc_offset(zone, US_DST_START), Some(-7 * 3600));
+        assert_eq!(abbreviation(zone, US_DST_END - 1), Some("PDT"));
+        assert_eq!(abbreviation(zone, US_DST_END), Some("PST"));
+        assert_eq!(utc_offset(zone, US_DST_END), Some(-8 * 3600));
+    }
+
+    #[test]
+    fn test_southern_hemisphere_dst() {
+        // January is summer in Sydney
+        assert_eq!(abbreviation("Australia/NSW", 1705276800), Some("AEDT"));
+        assert_eq!(abbreviation("Australia/NSW", 1721001600), Some("AEST"));
+        assert_eq!(utc_offset("Australia/NSW", 1705276800), Some(11 * 3600));
+    }
+
+    #[test]
+    fn test_zone_without_dst() {
+        for unix_ts in [1705276800, 1721001600] {
+            assert_eq!(abbreviation("Asia/Tokyo", unix_ts), Some("JST"));
+            assert_eq!(utc_offset("Asia/Tokyo", unix_ts), Some(9 * 3600));
+        }
+    }
+
+    #[test]
+    fn test_display_abbreviation_fallback() {
+        assert_eq!(
+            display_abbreviation("Asia/Riyadh", US_DST_START),
+            Some("UTC+3".to_string())
+        );
+        assert_eq!(
+            display_abbreviation("America/Sao_Paulo", US_DST_START),
+            Some("UTC-3".to_string())
+        );
+        assert_eq!(display_abbreviation("Unknown/Zone", US_DST_START), None);
+    }
+
+    #[test]
+    fn test_zone_rules_cover_country_dict() {
+        let mut names: Vec<&str> = ZONE_RULES.iter().map(|(name, _)| *name).collect();
+        names.dedup();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        for zones in COUNTRY_DICT.values() {
+            for zone in zones.values() {
+                assert!(zone_rule(zone).is_some(), "{} has no rule", zone);
+            }
+        }
+    }
 
-        // Test with a non-existent inner key and a non-existent country code
+    #[test]
+    fn test_parse_timestamp_unix() {
+        assert_eq!(parse_timestamp("0"), Some(0));
+        assert_eq!(parse_timestamp("-86400"), Some(-86400));
+        assert_eq!(parse_timestamp("1710064800"), Some(US_DST_START));
+    }
+
+    #[test]
+    fn test_parse_timestamp_rfc3339() {
+        assert_eq!(parse_timestamp("2024-03-10T10:00:00Z"), Some(US_DST_START));
         assert_eq!(
-            time_zone_by_country("XX", "non_existent", Some("inner_key")),
-            None
+            parse_timestamp("2024-03-10T03:00:00-07:00"),
+            Some(US_DST_START)
         );
+        assert_eq!(
+            parse_timestamp("2024-03-10T15:30:00.123+05:30"),
+            Some(US_DST_START)
+        );
+        assert_eq!(parse_timestamp("2024-02-29T00:00:00Z"), Some(1709164800));
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59Z"), Some(-1));
+    }
+
+    #[test]
+    fn test_parse_timestamp_invalid() {
+        for value in [
+            "",
+            "-",
+            "12a",
+            "2024-03-10",
+            "2024-03-10T10:00:00",
+            "2024-03-10 10:00:00Z",
+            "2024-13-10T10:00:00Z",
+            "2023-02-29T10:00:00Z",
+            "2024-03-10T24:00:00Z",
+            "2024-03-10T10:00:00.Z",
+            "2024-03-10T10:00:00+0700",
+            "2024-03-10T10:00:00+07:00 ",
+            "2024-03-1OT10:00:00Z",
+        ] {
+            assert_eq!(parse_timestamp(value), None, "{:?}", value);
+        }
     }
 }
@@ -0,0 +1,865 @@
+//! Decoding and walking of the binary tree that maps IP addresses to database records.
+//!
+//! The tree is an array of nodes at the start of the database. Each node holds two pointers of
+//! `record_length` bytes, little-endian unless the database is read with another `ByteOrder`,
+//! followed for a 0 and a 1 bit of the address respectively. A pointer below the segment count
+//! is the index of the next node, any other pointer is a leaf: the segment count itself for
+//! addresses that are not in the database, or the offset of their record.
+
+use crate::errors::GeoIpReaderError;
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// A node of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Node {
+    /// The pointer followed for a 0 bit.
+    pub left: u32,
+    /// The pointer followed for a 1 bit.
+    pub right: u32,
+}
+
+/// The leaf a walk ended at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leaf {
+    /// The leaf pointer, at least the segment count.
+    pub pointer: u32,
+    /// The number of address bits walked to reach the leaf.
+    pub netmask: usize,
+}
+
+/// Where the walk of the IPv4 addresses with a given first octet stands after its first 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OctetEntry {
+    /// The walk ended at a leaf within the first 8 bits.
+    Leaf(Leaf),
+    /// The walk continues from the node at this index.
+    Node(u32),
+}
+
+/// The index of the first octets of IPv4 addresses, sparing each walk the reads of the top 8
+/// levels of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OctetIndex {
+    entries: Vec<Option<OctetEntry>>,
+}
+
+impl OctetIndex {
+    /// Returns where the walk of the addresses with the given first octet stands, `None` if
+    /// their first 8 bits could not be walked.
+    pub fn get(&self, octet: u8) -> Option<OctetEntry> {
+        self.entries.get(usize::from(octet)).copied().flatten()
+    }
+}
+
+/// The order of the bytes of the integers of a database: the pointers of the tree, the segment
+/// count of its structure info, and the coordinates and DMA codes of its city records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    /// The order of the standard MaxMind databases.
+    #[default]
+    LittleEndian,
+    /// The order of non-standard dumps, e.g. written by big-endian tools.
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Decodes an unsigned integer of up to 4 bytes, ignoring any further byte.
+    pub fn read(self, bytes: &[u8]) -> u32 {
+        match self {
+            ByteOrder::LittleEndian => read_le(bytes),
+            ByteOrder::BigEndian => bytes
+                .iter()
+                .take(4)
+                .fold(0, |value, byte| value << 8 | u32::from(*byte)),
+        }
+    }
+
+    /// Encodes the `length` low bytes of an integer, 4 at most.
+    pub fn write(self, value: u32, length: usize) -> Vec<u8> {
+        match self {
+            ByteOrder::LittleEndian => value.to_le_bytes().into_iter().take(length).collect(),
+            ByteOrder::BigEndian => {
+                let bytes = value.to_be_bytes();
+                bytes
+                    .get(4usize.saturating_sub(length)..)
+                    .unwrap_or_default()
+                    .to_vec()
+            }
+        }
+    }
+}
+
+/// Decodes a little-endian unsigned integer of up to 4 bytes, ignoring any further byte.
+pub fn read_le(bytes: &[u8]) -> u32 {
+    let mut value = [0; 4];
+    for (byte, input) in value.iter_mut().zip(bytes) {
+        *byte = *input;
+    }
+    u32::from_le_bytes(value)
+}
+
+/// Returns the depth of the first bit of the address the walk tests: 31 for a number that fits
+/// the 10 digits of an IPv4 address, 127 otherwise.
+pub fn seek_depth(ip_number: u128) -> u32 {
+    // Compared numerically rather than by formatting the number, which would allocate
+    if ip_number > 9_999_999_999 {
+        127
+    } else {
+        31
+    }
+}
+
+/// Decodes a node from its bytes.
+///
+/// # Arguments
+///
+/// * `buf` - The bytes of the node, any byte past the two pointers being ignored.
+/// * `record_length` - The length of a pointer, 3 or 4 bytes.
+/// * `byte_order` - The order of the bytes of the pointers.
+///
+/// # Errors
+///
+/// Returns `GeoIpReaderError::CorruptDatabase` if the record length is not between 1 and 4
+/// bytes or the buffer is shorter than two pointers.
+pub fn decode_node(
+    buf: &[u8],
+    record_length: usize,
+    byte_order: ByteOrder,
+) -> Result<Node, GeoIpReaderError> {
+    if !(1..=4).contains(&record_length) {
+        return Err(GeoIpReaderError::CorruptDatabase);
+    }
+    let left = buf.get(..record_length);
+    let right = buf.get(record_length..record_length.saturating_mul(2));
+
+    match (left, right) {
+        (Some(left), Some(right)) => Ok(Node {
+            left: byte_order.read(left),
+            right: byte_order.read(right),
+        }),
+        _ => Err(GeoIpReaderError::CorruptDatabase),
+    }
+}
+
+/// Whether a pointer is a leaf rather than the index of another node.
+pub fn is_leaf(value: u32, segments: u32) -> bool {
+    value >= segments
+}
+
+/// `TreeWalker` walks the tree of a database down to the leaf of an address.
+#[derive(Debug)]
+pub struct TreeWalker<'r, R> {
+    reader: &'r mut R,
+    record_length: usize,
+    segments: u32,
+    byte_order: ByteOrder,
+}
+
+impl<'r, R> TreeWalker<'r, R>
+where
+    R: Read + Seek,
+{
+    /// Constructs a walker over a database whose tree has `segments` nodes with pointers of
+    /// `record_length` bytes.
+    pub fn new(reader: &'r mut R, record_length: usize, segments: u32) -> Self {
+        TreeWalker {
+            reader,
+            record_length,
+            segments,
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    /// Sets the order of the bytes of the pointers, little-endian by default.
+    pub fn byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Reads the node at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the node is truncated, or
+    /// `GeoIpReaderError::Io` if it cannot be read.
+    pub fn read_node(&mut self, index: u32) -> Result<Node, GeoIpReaderError> {
+        // A node holds two pointers of `record_length` (3 or 4) bytes each
+        let read_length = self.record_length.saturating_mul(2);
+        let mut buf = [0u8; 8];
+        let buf = buf
+            .get_mut(..read_length)
+            .ok_or(GeoIpReaderError::CorruptDatabase)?;
+
+        // Indices are below the 32-bit segment count, so the start index fits in a u64 for any
+        // record length
+        let start_index = u64::from(index)
+            .checked_mul(read_length as u64)
+            .ok_or(GeoIpReaderError::CorruptDatabase)?;
+        self.reader.seek(SeekFrom::Start(start_index))?;
+        self.reader
+            .read_exact(buf)
+            .map_err(|err| match err.kind() {
+                // The node is past the end of the database
+                ErrorKind::UnexpectedEof => GeoIpReaderError::CorruptDatabase,
+                _ => GeoIpReaderError::Io(err),
+            })?;
+        decode_node(buf, self.record_length, self.byte_order)
+    }
+
+    /// Walks the tree along the bits of an IP number, from the most significant one.
+    ///
+    /// IP numbers with more than 10 decimal digits are walked over 128 bits, the others over
+    /// 32 bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if a node is truncated or the walk does not
+    /// reach a leaf.
+    pub fn walk(&mut self, ip_number: u128) -> Result<Leaf, GeoIpReaderError> {
+        self.walk_from(ip_number, 0, seek_depth(ip_number), 1)
+    }
+
+    /// Walks the tree like `walk`, starting the walk of an IPv4 number where the index of its
+    /// first octet stands.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `walk`.
+    pub fn walk_indexed(
+        &mut self,
+        ip_number: u128,
+        octet_index: &OctetIndex,
+    ) -> Result<Leaf, GeoIpReaderError> {
+        if seek_depth(ip_number) != 31 {
+            return self.walk(ip_number);
+        }
+        // Bits 31 to 24, the first 8 bits walked
+        let octet = (ip_number >> 24) as u8;
+        match octet_index.get(octet) {
+            Some(OctetEntry::Leaf(leaf)) => Ok(leaf),
+            Some(OctetEntry::Node(index)) => self.walk_from(ip_number, index, 23, 9),
+            None => self.walk(ip_number),
+        }
+    }
+
+    /// Walks the first 8 bits of every IPv4 number, to build the index of `walk_indexed`. The
+    /// octets whose walk fails, e.g. on a truncated node, are left out of the index, their
+    /// walks reporting the error instead.
+    pub fn octet_index(&mut self) -> OctetIndex {
+        let entries = (0..=u8::MAX)
+            .map(|octet| {
+                let ip_number = u128::from(octet) << 24;
+                let mut index = 0;
+                for (netmask, depth) in (1..=8).zip((24..=31).rev()) {
+                    let node = self.read_node(index)?;
+                    let pointer = if (ip_number >> depth) & 1 != 0 {
+                        node.right
+                    } else {
+                        node.left
+                    };
+                    if is_leaf(pointer, self.segments) {
+                        return Ok(OctetEntry::Leaf(Leaf { pointer, netmask }));
+                    }
+                    index = pointer;
+                }
+                Ok(OctetEntry::Node(index))
+            })
+            .map(Result::<_, GeoIpReaderError>::ok)
+            .collect();
+        OctetIndex { entries }
+    }
+
+    /// Walks the tree along the bits of an IP number from the bit at `seek_depth`, starting at
+    /// the node at `index` with `netmask` bits walked once that bit is.
+    #[deny(clippy::arithmetic_side_effects)]
+    fn walk_from(
+        &mut self,
+        ip_number: u128,
+        mut index: u32,
+        seek_depth: u32,
+        netmask: usize,
+    ) -> Result<Leaf, GeoIpReaderError> {
+        // Iterate through the seek depth in reverse order, `netmask` counting the walked bits
+        for (netmask, depth) in (netmask..).zip((0..=seek_depth).rev()) {
+            let node = self.read_node(index)?;
+
+            // Check if the bit at the current depth is set in the IP number
+            let pointer = if ip_number.checked_shr(depth).unwrap_or(0) & 1 != 0 {
+                node.right
+            } else {
+                node.left
+            };
+
+            if is_leaf(pointer, self.segments) {
+                return Ok(Leaf { pointer, netmask });
+            }
+            index = pointer;
+        }
+
+        Err(GeoIpReaderError::CorruptDatabase)
+    }
+
+    /// Collects the networks within a prefix with their leaves, in the order of their
+    /// addresses, as `(network, prefix length, leaf)`. A network larger than the prefix is cut
+    /// down to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - The address of the prefix, as all of its `bits` bits.
+    /// * `prefix` - The length of the prefix, at most `bits`.
+    /// * `bits` - The length of the addresses of the tree: 32 or 128.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if a node cannot be read, or if the tree is
+    /// deeper than the addresses.
+    pub fn prefix_leaves(
+        &mut self,
+        network: u128,
+        prefix: u32,
+        bits: u32,
+    ) -> Result<Vec<(u128, u32, u32)>, GeoIpReaderError> {
+        // The mask of the bit tested at a depth, from the most significant one
+        let bit = |depth: u32| 1u128 << (bits - 1 - depth);
+
+        let mut index = 0;
+        for depth in 0..prefix {
+            let node = self.read_node(index)?;
+            let pointer = if network & bit(depth) == 0 {
+                node.left
+            } else {
+                node.right
+            };
+            if is_leaf(pointer, self.segments) {
+                return Ok(vec![(network, prefix, pointer)]);
+            }
+            index = pointer;
+        }
+
+        // Depth-first, the left subtree first, so that the networks come in ascending order
+        let mut networks = Vec::new();
+        let mut pending = vec![(index, network, prefix)];
+        while let Some((pointer, network, depth)) = pending.pop() {
+            if depth > prefix && is_leaf(pointer, self.segments) {
+                networks.push((network, depth, pointer));
+                continue;
+            }
+            if depth >= bits {
+                return Err(GeoIpReaderError::CorruptDatabase);
+            }
+            let node = self.read_node(pointer)?;
+            pending.push((node.right, network | bit(depth), depth + 1));
+            pending.push((node.left, network, depth + 1));
+        }
+        Ok(networks)
+    }
+
+    /// Collects the distinct leaves of the tree, except the one of the addresses that are not
+    /// in the database, by reading the whole node array.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the node array is truncated or cannot be
+    /// read.
+    pub fn leaves(&mut self) -> Result<BTreeSet<u32>, GeoIpReaderError> {
+        let mut leaves = BTreeSet::new();
+        self.visit_leaves(|pointer| {
+            leaves.insert(pointer);
+        })?;
+        Ok(leaves)
+    }
+
+    /// Calls `visit` with each pointer of the node array to a leaf, except the one of the
+    /// addresses that are not in the database, in the order of the array. A leaf is visited
+    /// once per network of its record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if the node array is truncated or cannot be
+    /// read.
+    pub fn visit_leaves(&mut self, mut visit: impl FnMut(u32)) -> Result<(), GeoIpReaderError> {
+        // Read the nodes by chunks rather than seeking to each of them
+        const CHUNK_NODES: usize = 4096;
+
+        let node_length = self.record_length.saturating_mul(2);
+        let mut chunk = vec![0; node_length.saturating_mul(CHUNK_NODES)];
+        let mut remaining = self.segments as usize;
+
+        self.reader
+            .seek(SeekFrom::Start(0))
+            .map_err(|_| GeoIpReaderError::CorruptDatabase)?;
+        while remaining > 0 {
+            let nodes = remaining.min(CHUNK_NODES);
+            let bytes = chunk
+                .get_mut(..nodes.saturating_mul(node_length))
+                .ok_or(GeoIpReaderError::CorruptDatabase)?;
+            self.reader
+                .read_exact(bytes)
+                .map_err(|_| GeoIpReaderError::CorruptDatabase)?;
+
+            for buf in bytes.chunks_exact(node_length) {
+                let node = decode_node(buf, self.record_length, self.byte_order)?;
+                for pointer in [node.left, node.right] {
+                    if pointer > self.segments {
+                        visit(pointer);
+                    }
+                }
+            }
+            remaining -= nodes;
+        }
+
+        Ok(())
+    }
+
+    /// Collects the distinct leaves of the tree like `leaves`, by following the pointers from
+    /// the root rather than reading a node array of `segments` nodes.
+    ///
+    /// Region editions store the first state offset in place of their node count, so their
+    /// node array can only be found this way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GeoIpReaderError::CorruptDatabase` if a node is truncated or cannot be read.
+    pub fn reachable_leaves(&mut self) -> Result<BTreeSet<u32>, GeoIpReaderError> {
+        let mut leaves = BTreeSet::new();
+        let mut visited = BTreeSet::from([0]);
+        let mut pending = vec![0];
+
+        while let Some(index) = pending.pop() {
+            let node = self.read_node(index)?;
+            for pointer in [node.left, node.right] {
+                if pointer > self.segments {
+                    leaves.insert(pointer);
+                } else if pointer < self.segments && visited.insert(pointer) {
+                    pending.push(pointer);
+                }
+            }
+        }
+
+        Ok(leaves)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_le() {
+        assert_eq!(read_le(&[]), 0);
+        assert_eq!(read_le(&[0x01]), 0x01);
+        assert_eq!(read_le(&[0x01, 0x02, 0x03]), 0x030201);
+        assert_eq!(read_le(&[0x01, 0x02, 0x03, 0x04]), 0x04030201);
+        assert_eq!(read_le(&[0x01, 0x02, 0x03, 0x04, 0x05]), 0x04030201);
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let (le, be) = (ByteOrder::LittleEndian, ByteOrder::BigEndian);
+        assert_eq!(be.read(&[]), 0);
+        assert_eq!(be.read(&[0x01, 0x02, 0x03]), 0x010203);
+        assert_eq!(be.read(&[0x01, 0x02, 0x03, 0x04, 0x05]), 0x01020304);
+        assert_eq!(le.read(&[0x01, 0x02, 0x03]), 0x030201);
+
+        assert_eq!(be.write(0x010203, 3), [0x01, 0x02, 0x03]);
+        assert_eq!(le.write(0x010203, 3), [0x03, 0x02, 0x01]);
+        for order in [le, be] {
+            for value in [0, 1, 0x0A0B0C, 0xFFFFFF] {
+                assert_eq!(order.read(&order.write(value, 3)), value);
+            }
+        }
+
+        // The same node under each order
+        let node = Node {
+            left: 0x010203,
+            right: 0x040506,
+        };
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        assert_eq!(decode_node(&bytes, 3, be).unwrap(), node);
+        assert_ne!(decode_node(&bytes, 3, le).unwrap(), node);
+    }
+
+    #[test]
+    fn test_decode_node_3_bytes() {
+        let cases: [([u8; 6], Node); 5] = [
+            ([0; 6], Node { left: 0, right: 0 }),
+            (
+                [0xff; 6],
+                Node {
+                    left: 0xffffff,
+                    right: 0xffffff,
+                },
+            ),
+            (
+                [0x01, 0x00, 0x00, 0x00, 0x00, 0x01],
+                Node {
+                    left: 1,
+                    right: 0x010000,
+                },
+            ),
+            (
+                [0x56, 0x34, 0x12, 0xcc, 0xbb, 0xaa],
+                Node {
+                    left: 0x123456,
+                    right: 0xaabbcc,
+                },
+            ),
+            (
+                [0x00, 0xff, 0x00, 0xff, 0x00, 0xff],
+                Node {
+                    left: 0x00ff00,
+                    right: 0xff00ff,
+                },
+            ),
+        ];
+
+        for (bytes, node) in cases {
+            assert_eq!(
+                decode_node(&bytes, 3, ByteOrder::LittleEndian).unwrap(),
+                node,
+                "{:?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_node_4_bytes() {
+        let cases: [([u8; 8], Node); 4] = [
+            ([0; 8], Node { left: 0, right: 0 }),
+            (
+                [0xff; 8],
+                Node {
+                    left: u32::MAX,
+                    right: u32::MAX,
+                },
+            ),
+            (
+                [0x78, 0x56, 0x34, 0x12, 0xdd, 0xcc, 0xbb, 0xaa],
+                Node {
+                    left: 0x12345678,
+                    right: 0xaabbccdd,
+                },
+            ),
+            (
+                [0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00],
+                Node {
+                    left: 0x01000000,
+                    right: 1,
+                },
+            ),
+        ];
+
+        for (bytes, node) in cases {
+            assert_eq!(
+                decode_node(&bytes, 4, ByteOrder::LittleEndian).unwrap(),
+                node,
+                "{:?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_node_ignores_trailing_bytes() {
+        assert_eq!(
+            decode_node(&[1, 0, 0, 2, 0, 0, 0xff, 0xff], 3, ByteOrder::LittleEndian).unwrap(),
+            Node { left: 1, right: 2 }
+        );
+    }
+
+    #[test]
+    fn test_decode_node_short_buffer() {
+        for record_length in [3, 4] {
+            for length in 0..2 * record_length {
+                let buf = vec![0xab; length];
+                assert!(
+                    matches!(
+                        decode_node(&buf, record_length, ByteOrder::LittleEndian),
+                        Err(GeoIpReaderError::CorruptDatabase)
+                    ),
+                    "{} bytes with record length {}",
+                    length,
+                    record_length
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_node_invalid_record_length() {
+        for record_length in [0, 5, usize::MAX] {
+            assert!(matches!(
+                decode_node(&[0; 16], record_length, ByteOrder::LittleEndian),
+                Err(GeoIpReaderError::CorruptDatabase)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_is_leaf() {
+        let segments = 16776960;
+
+        assert!(!is_leaf(0, segments));
+        assert!(!is_leaf(segments - 1, segments));
+        assert!(is_leaf(segments, segments));
+        assert!(is_leaf(segments + 1, segments));
+        assert!(is_leaf(u32::MAX, segments));
+        assert!(is_leaf(0, 0));
+    }
+
+    /// Builds a tree of 3-byte pointers from its nodes.
+    fn tree(nodes: &[(u32, u32)]) -> Cursor<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for (left, right) in nodes {
+            bytes.extend(&left.to_le_bytes()[..3]);
+            bytes.extend(&right.to_le_bytes()[..3]);
+        }
+        Cursor::new(bytes)
+    }
+
+    #[test]
+    fn test_walk() {
+        // 0.0.0.0/1 is not in the database, 128.0.0.0/2 resolves to 12 and 192.0.0.0/2 to 11
+        let mut database = tree(&[(2, 1), (12, 11)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 2);
+
+        assert_eq!(
+            walker.walk(0x0a000001).unwrap(),
+            Leaf {
+                pointer: 2,
+                netmask: 1
+            }
+        );
+        assert_eq!(
+            walker.walk(0x80000001).unwrap(),
+            Leaf {
+                pointer: 12,
+                netmask: 2
+            }
+        );
+        assert_eq!(
+            walker.walk(0xc0000001).unwrap(),
+            Leaf {
+                pointer: 11,
+                netmask: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_walk_boundary_values() {
+        // A pointer equal to the segment count is a leaf, one below it is a node
+        let segments = 3;
+        let mut database = tree(&[(segments - 1, segments), (segments, segments), (3, 4)]);
+        let mut walker = TreeWalker::new(&mut database, 3, segments);
+
+        assert_eq!(walker.walk(0x80000000).unwrap().pointer, segments);
+        assert_eq!(walker.walk(0).unwrap().netmask, 2);
+        assert_eq!(walker.walk(0x40000000).unwrap().pointer, 4);
+    }
+
+    #[test]
+    fn test_walk_ipv6_depth() {
+        // The root points to itself for a 0 bit, so a v6 number walks until its first 1 bit
+        let mut database = tree(&[(0, 5)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 1);
+
+        assert_eq!(walker.walk(1 << 100).unwrap().netmask, 28);
+        assert_eq!(walker.walk(1).unwrap().netmask, 32);
+        assert!(matches!(
+            walker.walk(0),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_walk_truncated_node() {
+        let mut database = Cursor::new(vec![5, 0, 0, 1, 0]);
+        let mut walker = TreeWalker::new(&mut database, 3, 1);
+
+        assert!(matches!(
+            walker.walk(0),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+
+        let mut database = Cursor::new(Vec::new());
+        let mut walker = TreeWalker::new(&mut database, 4, 1);
+        assert!(matches!(
+            walker.walk(0),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_walk_indexed() {
+        // A chain of nodes followed for 0 bits, each 1 bit ending at its own leaf: addresses
+        // end within the first octet or well past it
+        let segments = 12;
+        let mut nodes: Vec<(u32, u32)> = (0..segments - 1).map(|i| (i + 1, segments + i)).collect();
+        nodes.push((segments + 100, segments + 101));
+        let mut database = tree(&nodes);
+        let mut walker = TreeWalker::new(&mut database, 3, segments);
+        let octet_index = walker.octet_index();
+
+        assert_eq!(
+            octet_index.get(0x80),
+            Some(OctetEntry::Leaf(Leaf {
+                pointer: segments,
+                netmask: 1
+            }))
+        );
+        assert_eq!(octet_index.get(0), Some(OctetEntry::Node(8)));
+
+        let mut ip_numbers: Vec<u128> = (0..32).map(|bit| 1 << bit).collect();
+        ip_numbers.extend([
+            0,
+            0x00ff_ffff,
+            0x0123_4567,
+            u128::from(u32::MAX),
+            9_999_999_999,
+        ]);
+        for ip_number in ip_numbers {
+            assert_eq!(
+                walker.walk_indexed(ip_number, &octet_index).unwrap(),
+                walker.walk(ip_number).unwrap(),
+                "{:#x}",
+                ip_number
+            );
+        }
+        // IPv6 numbers are walked from the root
+        assert_eq!(
+            walker.walk_indexed(1 << 100, &octet_index).unwrap(),
+            walker.walk(1 << 100).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_walk_indexed_truncated_node() {
+        // The root leads to a missing node for a 0 bit
+        let mut database = tree(&[(1, 7)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 2);
+        let octet_index = walker.octet_index();
+
+        assert_eq!(octet_index.get(0), None);
+        assert!(matches!(
+            walker.walk_indexed(0, &octet_index),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+        assert_eq!(
+            walker
+                .walk_indexed(0x80000000, &octet_index)
+                .unwrap()
+                .pointer,
+            7
+        );
+    }
+
+    #[test]
+    fn test_walk_4_bytes() {
+        let mut database = Cursor::new(vec![0x00, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00]);
+        let mut walker = TreeWalker::new(&mut database, 4, 1);
+
+        assert_eq!(
+            walker.walk(0).unwrap(),
+            Leaf {
+                pointer: 0x01000000,
+                netmask: 1
+            }
+        );
+        assert_eq!(walker.walk(0x80000000).unwrap().pointer, 2);
+    }
+
+    #[test]
+    fn test_leaves() {
+        // The "not found" leaf 3 is left out, and leaf 13 is collected once
+        let mut database = tree(&[(3, 1), (2, 13), (13, 12)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 3);
+
+        assert_eq!(
+            walker.leaves().unwrap().into_iter().collect::<Vec<_>>(),
+            vec![12, 13]
+        );
+    }
+
+    #[test]
+    fn test_prefix_leaves() {
+        // Over 3-bit addresses: 0xx is node 1, 1xx leaf 10, 00x leaf 11, 01x node 2
+        let mut database = tree(&[(1, 10), (11, 2), (12, 13)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 3);
+
+        assert_eq!(
+            walker.prefix_leaves(0, 0, 3).unwrap(),
+            [
+                (0b000, 2, 11),
+                (0b010, 3, 12),
+                (0b011, 3, 13),
+                (0b100, 1, 10)
+            ]
+        );
+        assert_eq!(
+            walker.prefix_leaves(0b010, 2, 3).unwrap(),
+            [(0b010, 3, 12), (0b011, 3, 13)]
+        );
+        // A network larger than the prefix is cut down to it
+        assert_eq!(walker.prefix_leaves(0b110, 2, 3).unwrap(), [(0b110, 2, 10)]);
+        assert_eq!(walker.prefix_leaves(0b011, 3, 3).unwrap(), [(0b011, 3, 13)]);
+
+        // A tree deeper than the addresses
+        let mut database = tree(&[(1, 10), (1, 10)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 2);
+        assert!(matches!(
+            walker.prefix_leaves(0, 0, 2),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_reachable_leaves() {
+        // Node 2 is never reached, and node 1 is reached twice
+        let mut database = tree(&[(1, 1), (4, 13), (13, 12)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 4);
+
+        assert_eq!(
+            walker
+                .reachable_leaves()
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![13]
+        );
+
+        // A segment count past the node array, as in region editions
+        let mut database = tree(&[(1_000_000, 1_000_005)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 1_000_000);
+        assert_eq!(
+            walker
+                .reachable_leaves()
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![1_000_005]
+        );
+
+        let mut database = tree(&[(1, 7)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 2);
+        assert!(matches!(
+            walker.reachable_leaves(),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_leaves_truncated_tree() {
+        let mut database = tree(&[(2, 3)]);
+        let mut walker = TreeWalker::new(&mut database, 3, 2);
+
+        assert!(matches!(
+            walker.leaves(),
+            Err(GeoIpReaderError::CorruptDatabase)
+        ));
+    }
+}
@@ -1,10 +1,78 @@
+use crate::designated_market_area::DesignatedMarketArea;
+use crate::enricher::EnrichedRecord;
+use crate::errors::{GeoIpReaderError, IpParseError, RangeError};
 use crate::geo_ip_reader::Record;
+use crate::locale::Locale;
+use crate::post_process::RecordFields;
+use crate::time_zones::{
+    display_abbreviation, format_utc_offset, unix_now, utc_offset, DEFAULT_REGION_KEY,
+};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// The keys of the fields returned by `record_fields`, sorted alphabetically.
+pub const RECORD_FIELD_NAMES: [&str; 15] = [
+    "area_code",
+    "city",
+    "continent",
+    "country_code",
+    "country_code3",
+    "country_name",
+    "dma_code",
+    "latitude",
+    "longitude",
+    "metro_code",
+    "postal_code",
+    "region_code",
+    "time_zone",
+    "tz_abbreviation",
+    "utc_offset",
+];
+
+/// The address families `find_first_ip_matching` scans a line for.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum IpPattern {
+    Ipv4,
+    Ipv6,
+    /// Both IPv4 and IPv6 addresses.
+    #[default]
+    Auto,
+}
+
+impl IpPattern {
+    fn matches(&self, ip: &IpAddr) -> bool {
+        matches!(
+            (self, ip),
+            (IpPattern::Auto, _)
+                | (IpPattern::Ipv4, IpAddr::V4(_))
+                | (IpPattern::Ipv6, IpAddr::V6(_))
+        )
+    }
+}
+
+impl FromStr for IpPattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "ipv4" => Ok(IpPattern::Ipv4),
+            "ipv6" => Ok(IpPattern::Ipv6),
+            "auto" => Ok(IpPattern::Auto),
+            _ => Err(format!(
+                "invalid pattern '{}', expected one of ipv4, ipv6, auto",
+                value
+            )),
+        }
+    }
+}
 
 /// Helper function to create a single-level hashmap.
 ///
-/// This function takes a single value and wraps it in a hashmap with a default key "default".
+/// This function takes a single value and wraps it in a hashmap under the key
+/// `time_zones::DEFAULT_REGION_KEY`, the time zone of a country as a whole.
 ///
 /// # Arguments
 ///
@@ -26,7 +94,7 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 /// ```
 pub fn single_level(value: &'static str) -> HashMap<&'static str, &'static str> {
     let mut map = HashMap::new();
-    map.insert("default", value);
+    map.insert(DEFAULT_REGION_KEY, value);
     map
 }
 
@@ -64,11 +132,11 @@ pub fn multi_level(
     entries.into_iter().collect()
 }
 
-/// Converts an IP address in string format to a 128-bit unsigned integer representation.
-///
-/// This function takes a string representing an IP address and converts it into a 128-bit
-/// unsigned integer. It supports both IPv4 and IPv6 addresses. The result is the numeric
-/// representation of the IP address.
+#[allow(deprecated)]
+pub use crate::compat::ip_to_number;
+
+/// Converts an IP address in string format to a 128-bit unsigned integer representation,
+/// reporting invalid addresses instead of panicking.
 ///
 /// # Arguments
 ///
@@ -76,51 +144,613 @@ pub fn multi_level(
 ///
 /// # Returns
 ///
-/// (`u128`): A 128-bit unsigned integer representation of the IP address.
+/// (`Result<u128, GeoIpReaderError>`): The numeric representation of the IP address, as
+/// returned by `ip_to_number`.
 ///
-/// # Panics
+/// # Errors
 ///
-/// This function will panic if the input string does not represent a valid IPv4 or IPv6 address.
+/// Returns `GeoIpReaderError::InvalidAddress`, holding the input and the parse error, if the
+/// input is not a valid IPv4 or IPv6 address, e.g. an empty or whitespace-only one.
 ///
 /// # Examples
 ///
 /// ```
-/// use ipcap::utils::ip_to_number;
+/// use ipcap::errors::GeoIpReaderError;
+/// use ipcap::utils::try_ip_to_number;
 ///
-/// let ipv4_address = "1.32.0.0";
-/// let ipv6_address = "2001:0db8:85a3:0000:0000:8a2e:0370:7334";
+/// assert_eq!(try_ip_to_number("1.32.0.0").unwrap(), 18874368);
 ///
-/// let ipv4_numeric = ip_to_number(ipv4_address);
-/// let ipv6_numeric = ip_to_number(ipv6_address);
+/// let err = try_ip_to_number("1.32.0.256").unwrap_err();
+/// assert!(matches!(err, GeoIpReaderError::InvalidAddress { ref input, .. } if input == "1.32.0.256"));
+/// assert_eq!(err.to_string(), "Invalid IP address \"1.32.0.256\": invalid IP address syntax");
+/// ```
+#[deny(clippy::arithmetic_side_effects)]
+pub fn try_ip_to_number(ip: &str) -> Result<u128, GeoIpReaderError> {
+    let addr = ip
+        .parse::<IpAddr>()
+        .map_err(|source| GeoIpReaderError::InvalidAddress {
+            input: ip.to_string(),
+            source,
+        })?;
+
+    Ok(addr_to_number(addr))
+}
+
+/// Parses a CIDR range, e.g. `10.0.0.0/8`, keeping only the network bits of its address.
+///
+/// # Returns
 ///
-/// assert_eq!(ipv4_numeric, 18874368);
-/// assert_eq!(ipv6_numeric, 42540766411283223938465490629124161536);
+/// (`Option<(u128, u8, bool)>`): The network as a number, the prefix length and whether the
+/// range is an IPv6 one, or `None` if the input is not a valid CIDR.
+pub(crate) fn parse_cidr(cidr: &str) -> Option<(u128, u8, bool)> {
+    let (address, prefix) = cidr.split_once('/')?;
+    let address: IpAddr = address.parse().ok()?;
+    let prefix: u8 = prefix.parse().ok()?;
+
+    let (number, bits) = match address {
+        IpAddr::V4(v4) => (u128::from(u32::from(v4)), 32),
+        IpAddr::V6(v6) => (u128::from(v6), 128),
+    };
+    if prefix > bits {
+        return None;
+    }
+    let network = match prefix {
+        0 => 0,
+        _ => (number >> (bits - prefix)) << (bits - prefix),
+    };
+    Some((network, prefix, bits == 128))
+}
+
+/// Returns the address as an unsigned integer of its bits, as `addr_to_number` does, and its
+/// number of bits: 32 for an IPv4 address, 128 for an IPv6 one.
+pub(crate) fn addr_bits(addr: IpAddr) -> (u128, u32) {
+    let bits = if addr.is_ipv4() { 32 } else { 128 };
+    (addr_to_number(addr), bits)
+}
+
+/// Returns the address of the given bits, the reverse of `addr_bits`.
+pub(crate) fn bits_addr(number: u128, bits: u32) -> IpAddr {
+    if bits == 32 {
+        IpAddr::V4(Ipv4Addr::from(number as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(number))
+    }
+}
+
+/// Returns the last address number of the block of `host_bits` trailing bits starting at
+/// `network`.
+#[deny(clippy::arithmetic_side_effects)]
+pub(crate) fn block_last(network: u128, host_bits: u32) -> u128 {
+    let mask = 1u128
+        .checked_shl(host_bits)
+        .map_or(u128::MAX, |size| size.wrapping_sub(1));
+    network | mask
+}
+
+/// Parses a range of addresses given as `start-end`, e.g. `8.8.8.0-8.8.8.255` as found in
+/// WHOIS output, as a CIDR, e.g. `8.8.8.0/24`, or as a single address.
+///
+/// # Returns
+///
+/// (`Result<(IpAddr, IpAddr), RangeError>`): The first and last addresses of the range. The
+/// address of a CIDR is not required to be the first one of its network.
+///
+/// # Errors
+///
+/// Returns `RangeError::Invalid` if the input is none of the above, `RangeError::MixedFamilies`
+/// or `RangeError::Reversed` if the bounds of a `start-end` range are of different families or
+/// in reverse order.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::errors::RangeError;
+/// use ipcap::utils::parse_ip_range;
+///
+/// let (start, end) = parse_ip_range("8.8.8.0 - 8.8.8.255").unwrap();
+/// assert_eq!((start.to_string(), end.to_string()), ("8.8.8.0".into(), "8.8.8.255".into()));
+/// assert_eq!(parse_ip_range("8.8.8.7/24").unwrap(), (start, end));
+///
+/// assert!(matches!(parse_ip_range("8.8.8.9-8.8.8.0"), Err(RangeError::Reversed { .. })));
 /// ```
-pub fn ip_to_number(ip: &str) -> u128 {
-    match ip.parse::<Ipv4Addr>() {
-        Ok(ipv4_addr) => {
-            // IPv4 case
-            let ipv4_u32: u32 = u32::from(ipv4_addr);
-            u128::from(ipv4_u32)
+#[deny(clippy::arithmetic_side_effects)]
+pub fn parse_ip_range(text: &str) -> Result<(IpAddr, IpAddr), RangeError> {
+    let invalid = || RangeError::Invalid(text.to_string());
+    let input = text.trim();
+
+    if let Some((address, prefix)) = input.split_once('/') {
+        let address: IpAddr = address.trim().parse().map_err(|_| invalid())?;
+        let prefix: u32 = prefix.trim().parse().map_err(|_| invalid())?;
+        let (number, bits) = addr_bits(address);
+        let host_bits = bits.checked_sub(prefix).ok_or_else(invalid)?;
+        let last = block_last(number, host_bits);
+        let network = last ^ block_last(0, host_bits);
+        return Ok((bits_addr(network, bits), bits_addr(last, bits)));
+    }
+
+    let (start, end) = match input.split_once('-') {
+        Some((start, end)) => (start.trim(), end.trim()),
+        None => (input, input),
+    };
+    let start: IpAddr = start.parse().map_err(|_| invalid())?;
+    let end: IpAddr = end.parse().map_err(|_| invalid())?;
+    match (start, end) {
+        (IpAddr::V4(_), IpAddr::V6(_)) | (IpAddr::V6(_), IpAddr::V4(_)) => {
+            Err(RangeError::MixedFamilies { start, end })
         }
-        Err(_) => {
-            // Not an IPv4 address, try IPv6
-            match ip.parse::<Ipv6Addr>() {
-                Ok(ipv6_addr) => {
-                    // IPv6 case
-                    let segments = ipv6_addr.segments();
-                    (u128::from(segments[0]) << 112)
-                        | (u128::from(segments[1]) << 96)
-                        | (u128::from(segments[2]) << 64)
-                        | u128::from(segments[3])
-                }
-                Err(_) => {
-                    // Invalid IP address
-                    panic!("Invalid IP address: {}", ip);
-                }
+        _ if start > end => Err(RangeError::Reversed { start, end }),
+        _ => Ok((start, end)),
+    }
+}
+
+/// Decomposes a range of addresses into the minimal list of aligned CIDR blocks covering it
+/// exactly, e.g. to look up a range whose bounds are not on prefix boundaries.
+///
+/// # Arguments
+///
+/// * `start` - The first address of the range.
+/// * `end` - The last address of the range, of the same family.
+///
+/// # Returns
+///
+/// (`Result<Vec<(IpAddr, u8)>, RangeError>`): The network addresses and prefix lengths of the
+/// blocks, in ascending order.
+///
+/// # Errors
+///
+/// Returns `RangeError::MixedFamilies` if the bounds are of different families, or
+/// `RangeError::Reversed` if `start` is after `end`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::range_to_cidrs;
+///
+/// let cidrs = range_to_cidrs("10.0.0.3".parse().unwrap(), "10.0.0.9".parse().unwrap()).unwrap();
+/// let cidrs: Vec<String> = cidrs
+///     .iter()
+///     .map(|(network, prefix)| format!("{}/{}", network, prefix))
+///     .collect();
+///
+/// assert_eq!(cidrs, ["10.0.0.3/32", "10.0.0.4/30", "10.0.0.8/31"]);
+/// ```
+#[deny(clippy::arithmetic_side_effects)]
+pub fn range_to_cidrs(start: IpAddr, end: IpAddr) -> Result<Vec<(IpAddr, u8)>, RangeError> {
+    let ((first, bits), (last, end_bits)) = (addr_bits(start), addr_bits(end));
+    if bits != end_bits {
+        return Err(RangeError::MixedFamilies { start, end });
+    }
+    if first > last {
+        return Err(RangeError::Reversed { start, end });
+    }
+
+    let mut cidrs = Vec::new();
+    let mut network = first;
+    loop {
+        // The largest block aligned on the network that does not go past the end
+        let mut host_bits = network.trailing_zeros().min(bits);
+        while host_bits > 0 && block_last(network, host_bits) > last {
+            host_bits = host_bits.saturating_sub(1);
+        }
+        let prefix = bits.saturating_sub(host_bits) as u8;
+        cidrs.push((bits_addr(network, bits), prefix));
+
+        match block_last(network, host_bits).checked_add(1) {
+            Some(next) if next <= last => network = next,
+            _ => return Ok(cidrs),
+        }
+    }
+}
+
+/// How `normalize_ip_text` treats IP addresses written in non-canonical forms.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ParseMode {
+    /// Accepts exactly what `std::net::IpAddr::from_str` accepts.
+    #[default]
+    Strict,
+    /// Additionally accepts IPv4 octets with leading zeros, as found in exports of tools based on
+    /// `inet_aton`.
+    Legacy,
+}
+
+/// Parses an IP address written in text, with the leniency of the given mode.
+///
+/// Both modes accept the forms of `std::net`: dotted-decimal IPv4 addresses and IPv6 addresses
+/// in upper or lower case, with groups of up to four hexadecimal digits (so `0900` and `0000`
+/// are fine), `::` compression and a trailing dotted IPv4 address.
+///
+/// `ParseMode::Legacy` also accepts IPv4 octets of up to three decimal digits with leading
+/// zeros, also in the trailing IPv4 address of an IPv6 address. They are always read as
+/// decimal: `010.0.0.1` is `10.0.0.1`, never the octal `8.0.0.1`, and `0300.0.0.1` is rejected
+/// rather than read as `192.0.0.1`.
+///
+/// Neither mode accepts surrounding whitespace, fewer than four IPv4 octets (`127.1`), octal or
+/// hexadecimal notations (`0x7f.0.0.1`), zone indices (`fe80::1%eth0`), nor IPv6 groups of more
+/// than four digits.
+///
+/// # Arguments
+///
+/// * `input` - The IP address text.
+/// * `mode` - The accepted forms.
+///
+/// # Returns
+///
+/// (`Result<IpAddr, IpParseError>`): The parsed address.
+///
+/// # Errors
+///
+/// Returns `IpParseError::LeadingZeros` if the input has IPv4 octets with leading zeros in
+/// strict mode, or `IpParseError::Invalid` if it is not an IP address.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::errors::IpParseError;
+/// use ipcap::utils::{normalize_ip_text, ParseMode};
+///
+/// assert_eq!(
+///     normalize_ip_text("008.008.008.008", ParseMode::Legacy).unwrap(),
+///     "8.8.8.8".parse::<std::net::IpAddr>().unwrap()
+/// );
+/// assert!(matches!(
+///     normalize_ip_text("008.008.008.008", ParseMode::Strict),
+///     Err(IpParseError::LeadingZeros(_))
+/// ));
+/// ```
+pub fn normalize_ip_text(input: &str, mode: ParseMode) -> Result<IpAddr, IpParseError> {
+    if let Ok(addr) = input.parse::<IpAddr>() {
+        return Ok(addr);
+    }
+
+    match (parse_legacy(input), mode) {
+        (Some(addr), ParseMode::Legacy) => Ok(addr),
+        (Some(_), ParseMode::Strict) => Err(IpParseError::LeadingZeros(input.to_string())),
+        (None, _) => Err(IpParseError::Invalid(input.to_string())),
+    }
+}
+
+/// Resolves a host name to its IP addresses with the system resolver, e.g. for DNS lookups
+/// before geolocation. This is the only function of the crate that may use the network.
+///
+/// # Arguments
+///
+/// * `host` - The host name, e.g. `example.com`.
+///
+/// # Returns
+///
+/// (`Result<Vec<IpAddr>, GeoIpReaderError>`): The distinct addresses of the host, the IPv4 ones
+/// first, each family in the order of the resolver. The first one is the address to look up,
+/// the IPv4 databases being the more complete.
+///
+/// # Errors
+///
+/// Returns `GeoIpReaderError::GetHostByNameError` if the host name cannot be resolved.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::resolve_host;
+///
+/// let addresses = resolve_host("localhost").unwrap();
+/// assert!(addresses[0].is_loopback());
+/// ```
+#[cfg(feature = "resolve")]
+pub fn resolve_host(host: &str) -> Result<Vec<IpAddr>, GeoIpReaderError> {
+    use std::net::ToSocketAddrs;
+
+    let resolved = (host, 0)
+        .to_socket_addrs()
+        .map_err(|_| GeoIpReaderError::GetHostByNameError)?;
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    for address in resolved {
+        if !addresses.contains(&address.ip()) {
+            addresses.push(address.ip());
+        }
+    }
+    if addresses.is_empty() {
+        return Err(GeoIpReaderError::GetHostByNameError);
+    }
+    // A stable sort, keeping the order of the resolver within each family
+    addresses.sort_by_key(|address| address.is_ipv6());
+    Ok(addresses)
+}
+
+/// Parses the IP addresses that only `ParseMode::Legacy` accepts, see `normalize_ip_text`.
+fn parse_legacy(input: &str) -> Option<IpAddr> {
+    // An IPv6 address may end with an IPv4 address, which is then the only one with dots
+    match input.rfind(':') {
+        Some(colon) if input[colon..].contains('.') => {
+            let ipv4 = parse_legacy_ipv4(&input[colon + 1..])?;
+            format!("{}{}", &input[..=colon], ipv4).parse().ok()
+        }
+        Some(_) => None,
+        None => parse_legacy_ipv4(input).map(IpAddr::V4),
+    }
+}
+
+/// Parses four dotted decimal octets of one to three digits each, leading zeros included.
+fn parse_legacy_ipv4(input: &str) -> Option<Ipv4Addr> {
+    let mut octets = [0u8; 4];
+    let mut parts = input.split('.');
+    for octet in octets.iter_mut() {
+        let part = parts.next()?;
+        if part.is_empty() || part.len() > 3 || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        *octet = part.parse().ok()?;
+    }
+    match parts.next() {
+        Some(_) => None,
+        None => Some(Ipv4Addr::from(octets)),
+    }
+}
+
+/// Checks whether an IP address belongs to a special-purpose range, which is never in a
+/// geolocation database: unspecified, loopback, private, shared (`100.64.0.0/10`),
+/// link-local, documentation, benchmarking (`198.18.0.0/15`), IETF protocol assignments
+/// (`192.0.0.0/24`), multicast, future use (`240.0.0.0/4`) and broadcast IPv4 addresses, and
+/// unspecified, loopback, unique local, link-local, documentation and multicast IPv6
+/// addresses. IPv4-mapped addresses are checked as IPv4 addresses.
+///
+/// # Arguments
+///
+/// * `addr` - The IP address.
+///
+/// # Returns
+///
+/// (`bool`): `true` if the address is reserved.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::is_reserved;
+///
+/// assert!(is_reserved(&"192.168.1.1".parse().unwrap()));
+/// assert!(is_reserved(&"::ffff:127.0.0.1".parse().unwrap()));
+/// assert!(!is_reserved(&"8.8.8.8".parse().unwrap()));
+/// ```
+pub fn is_reserved(addr: &IpAddr) -> bool {
+    fn is_reserved_v4(ip: &Ipv4Addr) -> bool {
+        let [a, b, c, _] = ip.octets();
+        ip.is_unspecified()
+            || ip.is_loopback()
+            || ip.is_private()
+            || ip.is_link_local()
+            || ip.is_documentation()
+            || ip.is_multicast()
+            || ip.is_broadcast()
+            || a == 0
+            || a >= 240
+            || (a == 100 && (64..128).contains(&b))
+            || (a == 198 && (18..20).contains(&b))
+            || (a == 192 && b == 0 && c == 0)
+    }
+
+    match addr {
+        IpAddr::V4(ip) => is_reserved_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_reserved_v4(&ip),
+            None => {
+                let [a, b, ..] = ip.segments();
+                ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || ip.is_multicast()
+                    || (a == 0x2001 && b == 0xdb8)
             }
+        },
+    }
+}
+
+/// Converts a parsed IP address to the 128-bit unsigned integer representation returned by
+/// `try_ip_to_number`: the 32 bits of an IPv4 address, or the 128 bits of an IPv6 one, in
+/// order.
+///
+/// # Arguments
+///
+/// * `addr` - The IP address.
+///
+/// # Returns
+///
+/// (`u128`): The numeric representation of the IP address.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::{addr_to_number, try_ip_to_number};
+/// use std::net::IpAddr;
+///
+/// let addr: IpAddr = "2a08:1450:300f:900::1003".parse().unwrap();
+/// assert_eq!(addr_to_number(addr), try_ip_to_number("2a08:1450:300f:900::1003").unwrap());
+/// assert_eq!(addr_to_number(addr), 0x2a08_1450_300f_0900_0000_0000_0000_1003);
+/// ```
+pub fn addr_to_number(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(ipv4_addr) => u128::from(u32::from(ipv4_addr)),
+        IpAddr::V6(ipv6_addr) => u128::from(ipv6_addr),
+    }
+}
+
+/// Extracts the IPv4 address embedded in an IPv4-mapped (`::ffff:a.b.c.d`) or IPv4-compatible
+/// (`::a.b.c.d`) IPv6 address.
+///
+/// # Arguments
+///
+/// * `ip` - The IPv6 address.
+///
+/// # Returns
+///
+/// (`Option<Ipv4Addr>`): The embedded IPv4 address, or `None` for other addresses, including
+/// the unspecified (`::`) and loopback (`::1`) addresses.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::embedded_ipv4;
+/// use std::net::{Ipv4Addr, Ipv6Addr};
+///
+/// let mapped: Ipv6Addr = "::ffff:8.8.8.8".parse().unwrap();
+/// let compatible: Ipv6Addr = "::8.8.8.8".parse().unwrap();
+///
+/// assert_eq!(embedded_ipv4(&mapped), Some(Ipv4Addr::new(8, 8, 8, 8)));
+/// assert_eq!(embedded_ipv4(&compatible), Some(Ipv4Addr::new(8, 8, 8, 8)));
+/// assert_eq!(embedded_ipv4(&Ipv6Addr::LOCALHOST), None);
+/// assert_eq!(embedded_ipv4(&"2001:db8::1".parse().unwrap()), None);
+/// ```
+pub fn embedded_ipv4(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+    if ip.is_loopback() || ip.is_unspecified() {
+        return None;
+    }
+    ip.to_ipv4()
+}
+
+/// Finds the first IPv4 or IPv6 address in a line of text.
+///
+/// This is `find_first_ip_matching` with `IpPattern::Auto`.
+///
+/// # Arguments
+///
+/// * `line` - The text to scan.
+///
+/// # Returns
+///
+/// (`Option<(Range<usize>, IpAddr)>`): The byte range of the address within the line and the
+/// parsed address, or `None` if the line contains no address.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::find_first_ip;
+///
+/// let line = r#"108.95.4.105 - - [10/Oct/2023:13:55:36 +0000] "GET / HTTP/1.1" 200"#;
+/// let (range, ip) = find_first_ip(line).unwrap();
+///
+/// assert_eq!(range, 0..12);
+/// assert_eq!(ip.to_string(), "108.95.4.105");
+/// assert_eq!(find_first_ip("Apache/2.4.41 (Ubuntu)"), None);
+/// ```
+pub fn find_first_ip(line: &str) -> Option<(Range<usize>, IpAddr)> {
+    find_first_ip_matching(line, IpPattern::Auto)
+}
+
+/// Finds the first address of the given families in a line of text.
+///
+/// The line is split into maximal runs of hexadecimal digits, dots and colons which are not
+/// glued to other alphanumeric characters, so that version strings such as "2.4.41", times
+/// such as "13:55:36" or words such as "std::net" are not mistaken for addresses. An IPv4
+/// address followed by a port ("1.2.3.4:80") and a bracketed IPv6 address ("[::1]:443") are
+/// both recognized.
+///
+/// # Arguments
+///
+/// * `line` - The text to scan.
+/// * `pattern` - The address families to look for.
+///
+/// # Returns
+///
+/// (`Option<(Range<usize>, IpAddr)>`): The byte range of the address within the line and the
+/// parsed address, or `None` if the line contains no matching address.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::{find_first_ip_matching, IpPattern};
+///
+/// let line = "GET http://[2001:db8::1]:8080/ from 10.0.0.1";
+///
+/// let (_, ip) = find_first_ip_matching(line, IpPattern::Ipv6).unwrap();
+/// assert_eq!(ip.to_string(), "2001:db8::1");
+///
+/// let (range, ip) = find_first_ip_matching(line, IpPattern::Ipv4).unwrap();
+/// assert_eq!(&line[range], "10.0.0.1");
+/// assert_eq!(ip.to_string(), "10.0.0.1");
+/// ```
+pub fn find_first_ip_matching(line: &str, pattern: IpPattern) -> Option<(Range<usize>, IpAddr)> {
+    let bytes = line.as_bytes();
+    let is_run = |b: u8| b.is_ascii_hexdigit() || b == b'.' || b == b':';
+    let is_glued = |b: u8| is_run(b) || b.is_ascii_alphanumeric() || b == b'_';
+    let byte = |i: usize| bytes.get(i).copied();
+    let mut i = 0;
+
+    while let Some(b) = byte(i) {
+        let glued_before = i.checked_sub(1).and_then(byte).is_some_and(is_glued);
+        if !is_run(b) || glued_before {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while byte(i).is_some_and(is_run) {
+            i += 1;
+        }
+        // The run must not be glued to a word, e.g. "1.2.3.4xyz"
+        if byte(i).is_some_and(is_glued) {
+            continue;
+        }
+
+        if let Some((range, ip)) = parse_run(line, start, i) {
+            if pattern.matches(&ip) {
+                return Some((range, ip));
+            }
+        }
+    }
+
+    None
+}
+
+/// Parses the address held by `line[start..end]`, a run of hexadecimal digits, dots and colons.
+fn parse_run(line: &str, start: usize, end: usize) -> Option<(Range<usize>, IpAddr)> {
+    // A sentence may end right after an address
+    let run = line[start..end].trim_end_matches('.');
+    let end = start + run.len();
+
+    if run.contains(':') {
+        if let Ok(ip) = run.parse::<Ipv6Addr>() {
+            return Some((start..end, IpAddr::V6(ip)));
         }
+        // An IPv4 address followed by a port
+        let (host, _) = run.split_once(':')?;
+        return host
+            .parse::<Ipv4Addr>()
+            .ok()
+            .map(|ip| (start..start + host.len(), IpAddr::V4(ip)));
+    }
+
+    run.parse::<Ipv4Addr>()
+        .ok()
+        .map(|ip| (start..end, IpAddr::V4(ip)))
+}
+
+/// Appends the given values to a line, each preceded by the delimiter.
+///
+/// # Arguments
+///
+/// * `line` - The original line.
+/// * `values` - The values to append, `None` standing for a missing value.
+/// * `delimiter` - The separator written before each value.
+///
+/// # Returns
+///
+/// (`String`): The annotated line. Missing values are appended as empty strings, so every
+/// annotated line has the same number of columns.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::utils::annotate_line;
+///
+/// let values = [Some("US".to_string()), None];
+///
+/// assert_eq!(annotate_line("GET /", &values, "\t"), "GET /\tUS\t");
+/// ```
+pub fn annotate_line(line: &str, values: &[Option<String>], delimiter: &str) -> String {
+    let mut annotated = line.to_string();
+    for value in values {
+        annotated.push_str(delimiter);
+        annotated.push_str(value.as_deref().unwrap_or_default());
     }
+    annotated
 }
 
 /// Reads null-terminated string data from the given buffer starting at the specified position.
@@ -148,22 +778,73 @@ pub fn ip_to_number(ip: &str) -> u128 {
 /// assert_eq!(data, Some("Hello".into()));
 /// ```
 pub fn read_data(buffer: &[u8], pos: usize) -> (usize, Option<Box<str>>) {
-    let mut cur = pos;
-    while buffer[cur] != 0 {
-        cur += 1;
-    }
-    let data = if cur > pos {
-        Some(
-            String::from_utf8_lossy(&buffer[pos..cur])
-                .to_string()
-                .into_boxed_str(),
-        )
-    } else {
-        None
-    };
+    let (cur, data, _) = read_data_checked(buffer, pos);
     (cur, data)
 }
 
+/// Reads null-terminated string data like `read_data`, counting the bytes that are not valid
+/// UTF-8.
+///
+/// The strings of the database are UTF-8. Invalid bytes, such as any byte from 0xF5 to 0xFF or
+/// a 0xF0 to 0xF4 byte that does not start a 4-byte sequence, are dropped instead of being
+/// decoded into replacement characters.
+///
+/// # Arguments
+///
+/// * `buffer` - The buffer containing the string data.
+/// * `pos` - The starting position to read the string from.
+///
+/// # Returns
+///
+/// A tuple containing:
+/// - The updated position after reading the string, the end of the buffer if the string is not
+///   terminated.
+/// - An optional string representing the data read. `None` if no valid string is found.
+/// - The number of invalid bytes dropped from the string.
+///
+/// # Examples
+///
+/// ```rust
+/// use ipcap::utils::read_data_checked;
+///
+/// let buffer = b"Z\xFF\xFF\xC3\xBCrich\0";
+/// let (new_pos, data, invalid) = read_data_checked(buffer, 0);
+/// assert_eq!(new_pos, 9);
+/// assert_eq!(data, Some("Zürich".into()));
+/// assert_eq!(invalid, 2);
+/// ```
+pub fn read_data_checked(buffer: &[u8], pos: usize) -> (usize, Option<Box<str>>, usize) {
+    let (cur, data, invalid) = read_str_checked(buffer, pos);
+    // Valid strings, by far the most common, are boxed with a single allocation of their length
+    (cur, data.map(Box::from), invalid)
+}
+
+/// Reads null-terminated string data like `read_data_checked`, borrowing it from the buffer
+/// unless invalid bytes had to be dropped.
+pub(crate) fn read_str_checked(buffer: &[u8], pos: usize) -> (usize, Option<Cow<'_, str>>, usize) {
+    // A string missing its terminator ends with the buffer
+    let tail = buffer.get(pos..).unwrap_or_default();
+    let length = tail
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(tail.len());
+    let cur = pos.saturating_add(length);
+
+    let bytes = tail.get(..length).unwrap_or_default();
+    if let Ok(data) = std::str::from_utf8(bytes) {
+        return (cur, (!data.is_empty()).then_some(Cow::Borrowed(data)), 0);
+    }
+
+    let mut data = String::new();
+    let mut invalid = 0;
+    for chunk in bytes.utf8_chunks() {
+        data.push_str(chunk.valid());
+        invalid += chunk.invalid().len();
+    }
+    let data = (!data.is_empty()).then_some(Cow::Owned(data));
+    (cur, data, invalid)
+}
+
 /// Pretty prints the fields of a Record struct by sorting them alphabetically and formatting the output.
 ///
 /// # Arguments
@@ -179,7 +860,7 @@ pub fn read_data(buffer: &[u8], pos: usize) -> (usize, Option<Box<str>>) {
 /// use ipcap::designated_market_area::DesignatedMarketArea;
 ///
 /// let record = Record {
-///     dma: Some(DesignatedMarketArea(80700)),
+///     dma: Some(DesignatedMarketArea(807650)),
 ///     postal_code: Some("94040".into()),
 ///     country: Country::UnitedStates,
 ///     region_code: Some("CA".into()),
@@ -187,6 +868,12 @@ pub fn read_data(buffer: &[u8], pos: usize) -> (usize, Option<Box<str>>) {
 ///     latitude: 37.3845,
 ///     longitude: -122.0881,
 ///     time_zone: "America/Los_Angeles",
+///     netmask: 0,
+///     max_netmask: 0,
+///     warnings: Vec::new(),
+///     gaps: Vec::new(),
+///     provenance: None,
+///     country_byte: Country::UnitedStates.to_buffer(),
 /// };
 ///
 /// pretty_print_dict(record);
@@ -209,17 +896,218 @@ pub fn read_data(buffer: &[u8], pos: usize) -> (usize, Option<Box<str>>) {
 ///     "postal_code": "94040",
 ///     "region_code": "CA",
 ///     "time_zone": "America/Los_Angeles",
+///     "tz_abbreviation": "PST",
+///     "utc_offset": "-08:00",
 /// }
 /// ```
 pub fn pretty_print_dict(record: Record) {
-    let data: Vec<(&str, Option<String>)> = vec![
-        ("dma_code", record.dma.map(|d| d.dma_code().to_string())),
-        ("area_code", record.dma.map(|d| d.area_code().to_string())),
-        ("metro_code", record.dma.map(|c| c.to_string())),
+    pretty_print_dict_at(record, unix_now());
+}
+
+/// Pretty prints the fields of a Record struct like `pretty_print_dict`, the time zone
+/// abbreviation and UTC offset being those at the given instant.
+///
+/// # Arguments
+///
+/// * `record` - A Record struct.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+pub fn pretty_print_dict_at(record: Record, unix_ts: i64) {
+    pretty_print_dict_in(record, unix_ts, Locale::En);
+}
+
+/// Pretty prints the fields of a Record struct like `pretty_print_dict_at`, the country and
+/// continent names being those of the given locale.
+///
+/// # Arguments
+///
+/// * `record` - A Record struct.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+/// * `locale` - The language of the names.
+pub fn pretty_print_dict_in(record: Record, unix_ts: i64, locale: Locale) {
+    println!("{{");
+    print_fields(&record_fields_in(&record, unix_ts, locale), 1);
+    println!("}}");
+}
+
+/// Pretty prints the fields of a record as edited by post-processors, e.g. those returned by
+/// `GeoIpReader::record_fields`, like `pretty_print_dict_in`.
+///
+/// # Arguments
+///
+/// * `fields` - The processed fields of a record.
+pub fn pretty_print_fields(fields: &RecordFields) {
+    let fields: Vec<(&str, Option<String>)> = fields
+        .iter()
+        .map(|(name, value)| (name, value.map(str::to_string)))
+        .collect();
+    println!("{{");
+    print_fields(&fields, 1);
+    println!("}}");
+}
+
+/// Pretty prints an `EnrichedRecord`, nesting the fields of each lookup under its own key.
+///
+/// # Arguments
+///
+/// * `record` - The combined record returned by `Enricher::enrich`.
+///
+/// Output:
+///
+/// ```sh
+/// {
+///     "asn": {
+///         "name": "Google LLC",
+///         "number": "15169",
+///     },
+///     "geo": {
+///         "area_code": "650",
+///         ...
+///     },
+///     "org": null,
+/// }
+/// ```
+pub fn pretty_print_enriched(record: EnrichedRecord) {
+    pretty_print_enriched_at(record, unix_now());
+}
+
+/// Pretty prints an `EnrichedRecord` like `pretty_print_enriched`, the time zone abbreviation and
+/// UTC offset being those at the given instant.
+///
+/// # Arguments
+///
+/// * `record` - The combined record returned by `Enricher::enrich`.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+pub fn pretty_print_enriched_at(record: EnrichedRecord, unix_ts: i64) {
+    pretty_print_enriched_in(record, unix_ts, Locale::En);
+}
+
+/// Pretty prints an `EnrichedRecord` like `pretty_print_enriched_at`, the country and continent
+/// names being those of the given locale.
+///
+/// # Arguments
+///
+/// * `record` - The combined record returned by `Enricher::enrich`.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+/// * `locale` - The language of the names.
+pub fn pretty_print_enriched_in(record: EnrichedRecord, unix_ts: i64, locale: Locale) {
+    println!("{{");
+
+    let asn = record.asn.map(|asn| {
+        vec![
+            ("name", asn.name.map(|d| d.to_string())),
+            ("number", Some(asn.number.to_string())),
+        ]
+    });
+    print_nested("asn", asn.as_deref(), 1);
+    let geo = record
+        .geo
+        .as_ref()
+        .map(|geo| record_fields_in(geo, unix_ts, locale));
+    print_nested("geo", geo.as_deref(), 1);
+    print_fields(&[("org", record.org.map(|d| d.to_string()))], 1);
+
+    for (source, err) in record.errors {
+        eprintln!(
+            "\u{1b}[1;33mwarning\u{1b}[0m: {} lookup failed: {}",
+            source, err
+        );
+    }
+
+    println!("}}");
+}
+
+/// Collects the displayable fields of a Record, sorted alphabetically by key.
+///
+/// The time zone abbreviation and UTC offset are those of the current time, see
+/// `record_fields_at`.
+///
+/// # Arguments
+///
+/// * `record` - A reference to a Record struct.
+///
+/// # Returns
+///
+/// (`Vec<(&'static str, Option<String>)>`): The key and formatted value of each field, `None`
+/// standing for a missing value.
+pub fn record_fields(record: &Record) -> Vec<(&'static str, Option<String>)> {
+    record_fields_at(record, unix_now())
+}
+
+/// Collects the displayable fields of a Record, sorted alphabetically by key, the time zone
+/// abbreviation and UTC offset being those at the given instant.
+///
+/// # Arguments
+///
+/// * `record` - A reference to a Record struct.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+///
+/// # Returns
+///
+/// (`Vec<(&'static str, Option<String>)>`): The key and formatted value of each field, `None`
+/// standing for a missing value.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::Record;
+/// use ipcap::utils::record_fields_at;
+///
+/// let record = Record::new(Country::UnitedStates).with_region_code("CA");
+/// let fields = record_fields_at(&record, 1705276800);
+///
+/// assert!(fields.contains(&("tz_abbreviation", Some("PST".to_string()))));
+/// assert!(fields.contains(&("utc_offset", Some("-08:00".to_string()))));
+/// ```
+pub fn record_fields_at(record: &Record, unix_ts: i64) -> Vec<(&'static str, Option<String>)> {
+    record_fields_in(record, unix_ts, Locale::En)
+}
+
+/// Collects the displayable fields of a Record like `record_fields_at`, the `country_name` and
+/// `continent` fields being in the given locale. The codes are the same in every locale.
+///
+/// # Arguments
+///
+/// * `record` - A reference to a Record struct.
+/// * `unix_ts` - The instant, in seconds since the Unix epoch.
+/// * `locale` - The language of the names.
+///
+/// # Returns
+///
+/// (`Vec<(&'static str, Option<String>)>`): The key and formatted value of each field, `None`
+/// standing for a missing value.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::Record;
+/// use ipcap::locale::Locale;
+/// use ipcap::utils::record_fields_in;
+///
+/// let record = Record::new(Country::UnitedStates).with_region_code("CA");
+/// let fields = record_fields_in(&record, 1705276800, Locale::Es);
+///
+/// assert!(fields.contains(&("country_name", Some("Estados Unidos".to_string()))));
+/// assert!(fields.contains(&("continent", Some("América del Norte".to_string()))));
+/// assert!(fields.contains(&("country_code", Some("US".to_string()))));
+/// ```
+pub fn record_fields_in(
+    record: &Record,
+    unix_ts: i64,
+    locale: Locale,
+) -> Vec<(&'static str, Option<String>)> {
+    // A packed value of 0 is no DMA at all, and a code without a name has no metro
+    let dma = record.dma.filter(|dma| !dma.is_empty());
+    let mut data: Vec<(&str, Option<String>)> = vec![
+        ("dma_code", dma.map(|d| d.dma_code().to_string())),
+        ("area_code", dma.map(|d| d.area_code().to_string())),
         (
-            "postal_code",
-            record.postal_code.as_ref().map(|d| d.to_string()),
+            "metro_code",
+            dma.filter(DesignatedMarketArea::is_known)
+                .map(|d| d.to_string()),
         ),
+        ("postal_code", record.postal_code().map(str::to_string)),
         (
             "country_code",
             Some(record.country.alphabetic_code_2().to_string()),
@@ -228,44 +1116,123 @@ pub fn pretty_print_dict(record: Record) {
             "country_code3",
             Some(record.country.alphabetic_code_3().to_string()),
         ),
-        ("country_name", Some(record.country.to_string())),
+        (
+            "country_name",
+            Some(record.country.name_in(locale).to_string()),
+        ),
         (
             "continent",
-            record.country.continent().map(|c| c.to_string()),
+            record
+                .country
+                .continent()
+                .map(|c| c.name_in(locale).to_string()),
         ),
-        ("region_code", record.region_code.map(|d| d.to_string())),
-        ("city", record.city.map(|d| d.to_string())),
+        ("region_code", record.region_code().map(str::to_string)),
+        ("city", record.city().map(str::to_string)),
         ("latitude", Some(record.latitude.to_string())),
         ("longitude", Some(record.longitude.to_string())),
         ("time_zone", Some(record.time_zone.to_string())),
+        (
+            "tz_abbreviation",
+            display_abbreviation(record.time_zone, unix_ts),
+        ),
+        (
+            "utc_offset",
+            utc_offset(record.time_zone, unix_ts).map(format_utc_offset),
+        ),
     ];
 
-    let mut sorted_data = data.clone();
-    sorted_data.sort_by(|a, b| a.0.cmp(b.0));
-
-    println!("{{");
+    data.sort_by(|a, b| a.0.cmp(b.0));
+    data
+}
 
-    for (key, value) in sorted_data {
-        print!("    \"\u{1b}[1;32m{}\": ", key); // Green color for keys
+/// Prints each field on its own line at the given indentation level.
+fn print_fields(fields: &[(&str, Option<String>)], level: usize) {
+    let indent = "    ".repeat(level);
+    for (key, value) in fields {
+        print!("{}\"\u{1b}[1;32m{}\": ", indent, key); // Green color for keys
         match value {
             Some(v) => print!("\u{1b}[1;37m\"{}\"\u{1b}[0m,", v), // Silver color for values
             None => print!("\u{1b}[1;30mnull\u{1b}[0m,"),         // Gray color for null values
         }
         println!();
     }
+}
 
-    println!("}}");
+/// Prints a nested object of fields under the given key, or null if there are none.
+fn print_nested(key: &str, fields: Option<&[(&str, Option<String>)]>, level: usize) {
+    let indent = "    ".repeat(level);
+    match fields {
+        Some(fields) => {
+            println!("{}\"\u{1b}[1;32m{}\": {{", indent, key);
+            print_fields(fields, level + 1);
+            println!("{}}},", indent);
+        }
+        None => println!(
+            "{}\"\u{1b}[1;32m{}\": \u{1b}[1;30mnull\u{1b}[0m,",
+            indent, key
+        ),
+    }
+}
+
+/// The 64-bit FNV-1a hash of the bytes, stable across runs and platforms.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Formats a JSON string literal, escaping the quotes, backslashes and control characters.
+pub(crate) fn json_string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            char if char.is_control() => literal.push_str(&format!("\\u{:04x}", char as u32)),
+            char => literal.push(char),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[macro_export]
+macro_rules! codegen {
+    ($name: expr) => {
+        include!(concat!(env!("OUT_DIR"), "/", $name))
+    };
+
+    (statement; $name: expr) => {
+        include!(concat!(env!("OUT_DIR"), "/", $name));
+    };
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
 mod tests {
     use super::*;
+    use crate::countries::Country;
     use std::collections::HashMap;
 
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
     #[test]
     fn test_single_level() {
         let map: HashMap<&'static str, &'static str> = single_level("example_value");
-        assert_eq!(map.get("default"), Some(&"example_value"));
+        assert_eq!(map.get(DEFAULT_REGION_KEY), Some(&"example_value"));
         assert_eq!(map.len(), 1);
     }
 
@@ -279,6 +1246,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_ip_to_number_ipv4() {
         let ipv4_address = "192.168.1.1";
         let result = ip_to_number(ipv4_address);
@@ -286,20 +1254,541 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_ip_to_number_ipv6() {
         // Test with a valid IPv6 address
         let ipv6_address = "2001:0db8:85a3:0000:0000:8a2e:0370:7334";
         let result = ip_to_number(ipv6_address);
-        assert_eq!(result, 42540766411283223938465490629124161536);
+        assert_eq!(result, 42540766452641154071740215577757643572);
+    }
+
+    #[test]
+    fn test_addr_to_number_keeps_every_segment() {
+        let number = |ip: &str| addr_to_number(ip.parse().unwrap());
+
+        assert_eq!(number("::1"), 1);
+        assert_eq!(number("1::"), 1 << 112);
+        assert_eq!(
+            number("2001:4860:4860::8888"),
+            0x2001_4860_4860_0000_0000_0000_0000_8888
+        );
+        assert_ne!(
+            number("2001:4860:ffff::8888"),
+            number("2001:4860:4860::8888")
+        );
+        assert_ne!(number("2001:db8::1:0:0:1"), number("2001:db8::1"));
+        assert_eq!(number("10.0.0.1"), 0x0a00_0001);
+    }
+
+    #[test]
+    fn test_try_ip_to_number_invalid() {
+        let err = try_ip_to_number("10.0.0.1x").unwrap_err();
+
+        match &err {
+            GeoIpReaderError::InvalidAddress { input, .. } => assert_eq!(input, "10.0.0.1x"),
+            err => panic!("unexpected error: {:?}", err),
+        }
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(err.to_string().contains("10.0.0.1x"));
+    }
+
+    /// The expected outcome of an input in strict and legacy modes: the address, or `Err` with
+    /// `true` for `IpParseError::LeadingZeros` and `false` for `IpParseError::Invalid`.
+    type Outcome = Result<&'static str, bool>;
+
+    const NON_CANONICAL_FORMS: &[(&str, Outcome, Outcome)] = &[
+        // Canonical forms, accepted by both modes
+        ("8.8.8.8", Ok("8.8.8.8"), Ok("8.8.8.8")),
+        ("0.0.0.0", Ok("0.0.0.0"), Ok("0.0.0.0")),
+        (
+            "255.255.255.255",
+            Ok("255.255.255.255"),
+            Ok("255.255.255.255"),
+        ),
+        // Leading zeros, read as decimal in legacy mode
+        ("008.008.008.008", Err(true), Ok("8.8.8.8")),
+        ("1.2.3.04", Err(true), Ok("1.2.3.4")),
+        ("00.0.0.0", Err(true), Ok("0.0.0.0")),
+        ("000.000.000.001", Err(true), Ok("0.0.0.1")),
+        ("09.09.09.09", Err(true), Ok("9.9.9.9")),
+        // Octal lookalikes, which `inet_aton` reads as octal, keep their decimal value
+        ("010.0.0.1", Err(true), Ok("10.0.0.1")),
+        ("017.0.0.01", Err(true), Ok("17.0.0.1")),
+        ("012.034.056.077", Err(true), Ok("12.34.56.77")),
+        // ... or are rejected when they are longer than three digits or their decimal value is
+        // not an octet
+        ("0177.0.0.1", Err(false), Err(false)),
+        ("0300.0250.0.1", Err(false), Err(false)),
+        ("0377.0377.0377.0377", Err(false), Err(false)),
+        ("256.0.0.1", Err(false), Err(false)),
+        ("0256.0.0.1", Err(false), Err(false)),
+        // Other inet_aton forms are rejected
+        ("0x7f.0.0.1", Err(false), Err(false)),
+        ("0x7f000001", Err(false), Err(false)),
+        ("127.1", Err(false), Err(false)),
+        ("2130706433", Err(false), Err(false)),
+        ("1.2.3", Err(false), Err(false)),
+        ("1.2.3.4.5", Err(false), Err(false)),
+        ("1.2.3.", Err(false), Err(false)),
+        ("1..2.3", Err(false), Err(false)),
+        ("+1.2.3.4", Err(false), Err(false)),
+        (" 1.2.3.4", Err(false), Err(false)),
+        ("1.2.3.4 ", Err(false), Err(false)),
+        ("", Err(false), Err(false)),
+        // IPv6 in upper case, expanded or with zero-padded groups
+        (
+            "2A08:1450:300F:0900:0000:0000:0000:1003",
+            Ok("2a08:1450:300f:900::1003"),
+            Ok("2a08:1450:300f:900::1003"),
+        ),
+        (
+            "2a08:1450:300f:900::1003",
+            Ok("2a08:1450:300f:900::1003"),
+            Ok("2a08:1450:300f:900::1003"),
+        ),
+        (
+            "0000:0000:0000:0000:0000:0000:0000:0001",
+            Ok("::1"),
+            Ok("::1"),
+        ),
+        ("::FFFF:8.8.8.8", Ok("::ffff:8.8.8.8"), Ok("::ffff:8.8.8.8")),
+        // IPv6 with a trailing IPv4 address with leading zeros
+        ("::ffff:008.008.008.008", Err(true), Ok("::ffff:8.8.8.8")),
+        ("::ffff:010.0.0.1", Err(true), Ok("::ffff:10.0.0.1")),
+        ("::ffff:0300.0.0.1", Err(false), Err(false)),
+        // Invalid IPv6 forms
+        ("2a08:01450::1", Err(false), Err(false)),
+        ("fe80::1%eth0", Err(false), Err(false)),
+        ("[::1]", Err(false), Err(false)),
+        ("1:2:3:4:5:6:7:8:9", Err(false), Err(false)),
+        ("010.0.0.1:80", Err(false), Err(false)),
+    ];
+
+    fn cidrs(start: &str, end: &str) -> Vec<String> {
+        range_to_cidrs(start.parse().unwrap(), end.parse().unwrap())
+            .unwrap()
+            .iter()
+            .map(|(network, prefix)| format!("{}/{}", network, prefix))
+            .collect()
+    }
+
+    #[test]
+    fn test_range_to_cidrs() {
+        assert_eq!(
+            cidrs("10.0.0.3", "10.0.0.9"),
+            ["10.0.0.3/32", "10.0.0.4/30", "10.0.0.8/31"]
+        );
+        assert_eq!(cidrs("8.8.8.0", "8.8.8.255"), ["8.8.8.0/24"]);
+        assert_eq!(
+            cidrs("192.168.0.1", "192.168.0.6"),
+            [
+                "192.168.0.1/32",
+                "192.168.0.2/31",
+                "192.168.0.4/31",
+                "192.168.0.6/32"
+            ]
+        );
+        assert_eq!(
+            cidrs("10.255.255.255", "11.0.0.0"),
+            ["10.255.255.255/32", "11.0.0.0/32"]
+        );
+        assert_eq!(cidrs("1.2.3.4", "1.2.3.4"), ["1.2.3.4/32"]);
+
+        // The bounds of the address spaces, without overflowing
+        assert_eq!(cidrs("0.0.0.0", "255.255.255.255"), ["0.0.0.0/0"]);
+        assert_eq!(
+            cidrs("255.255.255.254", "255.255.255.255"),
+            ["255.255.255.254/31"]
+        );
+        assert_eq!(
+            cidrs("::", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"),
+            ["::/0"]
+        );
+        assert_eq!(
+            cidrs("2001:db8::1", "2001:db8::4"),
+            ["2001:db8::1/128", "2001:db8::2/127", "2001:db8::4/128"]
+        );
+        assert_eq!(
+            cidrs("::ffff:0.0.0.0", "::ffff:255.255.255.255"),
+            ["::ffff:0.0.0.0/96"]
+        );
+    }
+
+    #[test]
+    fn test_range_to_cidrs_covers_the_range() {
+        // The blocks are aligned and adjacent, and cover the range exactly, whatever the
+        // alignment of the bounds
+        for (start, end) in [
+            (0u32, 0u32),
+            (3, 9),
+            (1, 1000),
+            (4095, 65537),
+            (77, 77 + 1031),
+        ] {
+            let blocks =
+                range_to_cidrs(Ipv4Addr::from(start).into(), Ipv4Addr::from(end).into()).unwrap();
+
+            let mut next = u64::from(start);
+            for (network, prefix) in &blocks {
+                let IpAddr::V4(network) = network else {
+                    panic!("{}", network)
+                };
+                let network = u64::from(u32::from(*network));
+                let size = 1u64 << (32 - prefix);
+                assert_eq!(network, next);
+                assert_eq!(network % size, 0, "{}/{}", network, prefix);
+                next = network + size;
+            }
+            assert_eq!(next, u64::from(end) + 1);
+        }
+    }
+
+    #[test]
+    fn test_range_errors() {
+        let (v4, v6): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "::1".parse().unwrap());
+        assert_eq!(
+            range_to_cidrs(v4, v6),
+            Err(RangeError::MixedFamilies { start: v4, end: v6 })
+        );
+        let before: IpAddr = "10.0.0.0".parse().unwrap();
+        assert_eq!(
+            range_to_cidrs(v4, before),
+            Err(RangeError::Reversed {
+                start: v4,
+                end: before
+            })
+        );
+        assert_eq!(
+            RangeError::Reversed {
+                start: v4,
+                end: before
+            }
+            .to_string(),
+            "Invalid IP range 10.0.0.1-10.0.0.0: the start is after the end"
+        );
+
+        assert!(matches!(
+            parse_ip_range("10.0.0.1-::1"),
+            Err(RangeError::MixedFamilies { .. })
+        ));
+        assert!(matches!(
+            parse_ip_range("10.0.0.9 - 10.0.0.3"),
+            Err(RangeError::Reversed { .. })
+        ));
+        for input in [
+            "",
+            "10.0.0.1-",
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0.0/x",
+            "a-b",
+        ] {
+            assert_eq!(
+                parse_ip_range(input),
+                Err(RangeError::Invalid(input.to_string())),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_ip_range() {
+        let range = |start: &str, end: &str| (start.parse().unwrap(), end.parse().unwrap());
+
+        assert_eq!(
+            parse_ip_range("10.0.0.3-10.0.0.9"),
+            Ok(range("10.0.0.3", "10.0.0.9"))
+        );
+        assert_eq!(
+            parse_ip_range(" 8.8.8.0 - 8.8.8.255 "),
+            Ok(range("8.8.8.0", "8.8.8.255"))
+        );
+        assert_eq!(
+            parse_ip_range("8.8.8.77/24"),
+            Ok(range("8.8.8.0", "8.8.8.255"))
+        );
+        assert_eq!(
+            parse_ip_range("0.0.0.0/0"),
+            Ok(range("0.0.0.0", "255.255.255.255"))
+        );
+        assert_eq!(parse_ip_range("1.2.3.4"), Ok(range("1.2.3.4", "1.2.3.4")));
+        assert_eq!(
+            parse_ip_range("2001:db8::/126"),
+            Ok(range("2001:db8::", "2001:db8::3"))
+        );
+        assert_eq!(
+            parse_ip_range("2001:db8::1-2001:db8::4"),
+            Ok(range("2001:db8::1", "2001:db8::4"))
+        );
+    }
+
+    #[test]
+    fn test_normalize_ip_text() {
+        for (input, strict, legacy) in NON_CANONICAL_FORMS {
+            for (mode, expected) in [(ParseMode::Strict, strict), (ParseMode::Legacy, legacy)] {
+                let actual = normalize_ip_text(input, mode);
+                match expected {
+                    Ok(addr) => assert_eq!(
+                        actual,
+                        Ok(addr.parse().unwrap()),
+                        "{:?} in {:?} mode",
+                        input,
+                        mode
+                    ),
+                    Err(true) => assert_eq!(
+                        actual,
+                        Err(IpParseError::LeadingZeros(input.to_string())),
+                        "{:?} in {:?} mode",
+                        input,
+                        mode
+                    ),
+                    Err(false) => assert_eq!(
+                        actual,
+                        Err(IpParseError::Invalid(input.to_string())),
+                        "{:?} in {:?} mode",
+                        input,
+                        mode
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_ip_text_default_is_strict() {
+        assert_eq!(ParseMode::default(), ParseMode::Strict);
+        assert_eq!(
+            normalize_ip_text("010.0.0.1", ParseMode::default())
+                .unwrap_err()
+                .to_string(),
+            "Invalid IP address \"010.0.0.1\": leading zeros in an IPv4 octet are only \
+             accepted by legacy parsing"
+        );
+    }
+
+    #[cfg(feature = "resolve")]
+    #[test]
+    fn test_resolve_host() {
+        // Resolved from the hosts file, without any network
+        let addresses = resolve_host("localhost").unwrap();
+        assert_eq!(addresses[0], IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert!(addresses.iter().all(IpAddr::is_loopback));
+
+        // The reserved top-level domain never resolves
+        assert!(matches!(
+            resolve_host("no-such-host.invalid"),
+            Err(GeoIpReaderError::GetHostByNameError)
+        ));
     }
 
     #[test]
     #[should_panic(expected = "Invalid IP address")]
+    #[allow(deprecated)]
     fn test_ip_to_number_invalid() {
         let invalid_address = "invalid_ip";
         ip_to_number(invalid_address);
     }
 
+    #[test]
+    fn test_record_fields_are_sorted() {
+        let record = Record {
+            dma: None,
+            postal_code: None,
+            country: Country::UnitedKingdom,
+            region_code: None,
+            city: None,
+            latitude: 54.0,
+            longitude: -2.0,
+            time_zone: "Europe/London",
+            netmask: 0,
+            max_netmask: 0,
+            warnings: Vec::new(),
+            gaps: Vec::new(),
+            provenance: None,
+            country_byte: Country::UnitedKingdom.to_buffer(),
+        };
+
+        let fields = record_fields(&record);
+        let keys: Vec<&str> = fields.iter().map(|(key, _)| *key).collect();
+        let mut sorted_keys = keys.clone();
+        sorted_keys.sort();
+
+        assert_eq!(keys, sorted_keys);
+        assert!(fields.contains(&("country_code", Some("GB".to_string()))));
+        assert!(fields.contains(&("city", None)));
+    }
+
+    #[test]
+    fn test_dma_fields() {
+        let fields = |dma| {
+            let record = Record::new(Country::UnitedStates).with_dma(DesignatedMarketArea(dma));
+            let fields = record_fields(&record);
+            ["dma_code", "area_code", "metro_code"].map(|name| {
+                fields
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .and_then(|(_, value)| value.clone())
+            })
+        };
+        let some = |value: &str| Some(value.to_string());
+
+        // No DMA, an unknown one, then a known one
+        assert_eq!(fields(0), [None, None, None]);
+        assert_eq!(fields(999_650), [some("999"), some("650"), None]);
+        assert_eq!(
+            fields(807_650),
+            [some("807"), some("650"), some("San Francisco, CA")]
+        );
+    }
+
+    #[test]
+    fn test_record_fields_in_locale() {
+        let record = Record::new(Country::Germany)
+            .with_region_code("02")
+            .with_dma(DesignatedMarketArea(807_650));
+        let english = record_fields_in(&record, 0, Locale::En);
+        let value = |fields: &[(&str, Option<String>)], name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| *key == name)
+                .and_then(|(_, value)| value.clone())
+        };
+
+        for (locale, country_name, continent) in [
+            (Locale::En, "Germany", "Europe"),
+            (Locale::Fr, "Allemagne", "Europe"),
+            (Locale::De, "Deutschland", "Europa"),
+            (Locale::Es, "Alemania", "Europa"),
+        ] {
+            let fields = record_fields_in(&record, 0, locale);
+            assert_eq!(value(&fields, "country_name").unwrap(), country_name);
+            assert_eq!(value(&fields, "continent").unwrap(), continent);
+            // Every other field, the codes included, is the same in every locale
+            for ((key, english), (other_key, other)) in english.iter().zip(&fields) {
+                assert_eq!(key, other_key);
+                if !["country_name", "continent"].contains(key) {
+                    assert_eq!(english, other, "{} in {}", key, locale);
+                }
+            }
+        }
+        assert_eq!(english, record_fields_at(&record, 0));
+    }
+
+    #[test]
+    fn test_record_field_names() {
+        let record = Record {
+            dma: None,
+            postal_code: None,
+            country: Country::UnitedKingdom,
+            region_code: None,
+            city: None,
+            latitude: 54.0,
+            longitude: -2.0,
+            time_zone: "Europe/London",
+            netmask: 0,
+            max_netmask: 0,
+            warnings: Vec::new(),
+            gaps: Vec::new(),
+            provenance: None,
+            country_byte: Country::UnitedKingdom.to_buffer(),
+        };
+
+        let keys: Vec<&str> = record_fields(&record).iter().map(|(key, _)| *key).collect();
+
+        assert_eq!(keys, RECORD_FIELD_NAMES);
+    }
+
+    fn first_ip(line: &str) -> Option<&str> {
+        find_first_ip(line).map(|(range, _)| &line[range])
+    }
+
+    #[test]
+    fn test_find_first_ip_ipv4() {
+        assert_eq!(first_ip("1.2.3.4"), Some("1.2.3.4"));
+        assert_eq!(first_ip("client 10.0.0.1 connected"), Some("10.0.0.1"));
+        assert_eq!(first_ip("ip=192.168.1.1&port=80"), Some("192.168.1.1"));
+        assert_eq!(first_ip("connected from 8.8.8.8."), Some("8.8.8.8"));
+        assert_eq!(first_ip("upstream 10.0.0.2:8080 failed"), Some("10.0.0.2"));
+        assert_eq!(first_ip("(172.16.0.1)"), Some("172.16.0.1"));
+    }
+
+    #[test]
+    fn test_find_first_ip_skips_lookalikes() {
+        assert_eq!(first_ip("Apache/2.4.41 (Ubuntu)"), None);
+        assert_eq!(first_ip("version 1.2.3.4.5"), None);
+        assert_eq!(first_ip("[10/Oct/2023:13:55:36 +0000]"), None);
+        assert_eq!(first_ip("took 13:55:36.123"), None);
+        assert_eq!(first_ip("use std::net::IpAddr;"), None);
+        assert_eq!(first_ip("999.1.1.1"), None);
+        assert_eq!(first_ip("v1.2.3.4 build1.2.3.4"), None);
+        assert_eq!(first_ip("deadbeef cafe"), None);
+        assert_eq!(first_ip(""), None);
+    }
+
+    #[test]
+    fn test_find_first_ip_skips_lookalikes_before_address() {
+        assert_eq!(
+            first_ip("Apache/2.4.41 at 13:55:36 served 1.2.3.4"),
+            Some("1.2.3.4")
+        );
+    }
+
+    #[test]
+    fn test_find_first_ip_ipv6() {
+        assert_eq!(first_ip("::1"), Some("::1"));
+        assert_eq!(
+            first_ip("GET http://[2001:db8::1]:8080/index.html"),
+            Some("2001:db8::1")
+        );
+        assert_eq!(first_ip("from fe80::1%eth0"), Some("fe80::1"));
+        assert_eq!(
+            first_ip("mapped ::ffff:192.0.2.1 here"),
+            Some("::ffff:192.0.2.1")
+        );
+    }
+
+    #[test]
+    fn test_find_first_ip_matching_pattern() {
+        let line = "2001:db8::1 via 10.0.0.1";
+
+        let (_, ip) = find_first_ip_matching(line, IpPattern::Ipv4).unwrap();
+        assert_eq!(ip, "10.0.0.1".parse::<IpAddr>().unwrap());
+        let (range, _) = find_first_ip_matching(line, IpPattern::Ipv6).unwrap();
+        assert_eq!(range, 0..11);
+        assert_eq!(find_first_ip_matching("10.0.0.1", IpPattern::Ipv6), None);
+    }
+
+    #[test]
+    fn test_ip_pattern_from_str() {
+        assert_eq!("ipv4".parse(), Ok(IpPattern::Ipv4));
+        assert_eq!("auto".parse(), Ok(IpPattern::Auto));
+        assert!("ipv5".parse::<IpPattern>().is_err());
+    }
+
+    #[test]
+    fn test_embedded_ipv4() {
+        let ip = |value: &str| value.parse::<Ipv6Addr>().unwrap();
+
+        assert_eq!(
+            embedded_ipv4(&ip("::ffff:108.95.4.105")),
+            Some(Ipv4Addr::new(108, 95, 4, 105))
+        );
+        assert_eq!(embedded_ipv4(&ip("::")), None);
+        assert_eq!(embedded_ipv4(&ip("2a08:1450:300f:900::1003")), None);
+    }
+
+    #[test]
+    fn test_annotate_line() {
+        let values = [Some("US".to_string()), None, Some("San Diego".to_string())];
+
+        assert_eq!(
+            annotate_line("a b", &values, " | "),
+            "a b | US |  | San Diego"
+        );
+        assert_eq!(annotate_line("a b", &[], ","), "a b");
+    }
+
     #[test]
     fn test_read_data_with_valid_string() {
         let buffer = b"Hello\0World";
@@ -319,23 +1808,23 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "index out of bounds: the len is 10 but the index is 10")]
     fn test_read_data_with_no_null_terminator() {
         let buffer = b"HelloWorld";
         let pos = 0;
         let (new_pos, data) = read_data(buffer, pos);
         assert_eq!(new_pos, buffer.len());
+        assert_eq!(data, Some("HelloWorld".into()));
+
+        let (new_pos, data) = read_data(buffer, 42);
+        assert_eq!(new_pos, 42);
         assert_eq!(data, None);
     }
-}
-
-#[macro_export]
-macro_rules! codegen {
-    ($name: expr) => {
-        include!(concat!(env!("OUT_DIR"), "/", $name))
-    };
 
-    (statement; $name: expr) => {
-        include!(concat!(env!("OUT_DIR"), "/", $name));
-    };
+    #[test]
+    fn test_json_string() {
+        assert_eq!(
+            json_string("a \"b\" \\ c\n\u{1}"),
+            "\"a \\\"b\\\" \\\\ c\\n\\u0001\""
+        );
+    }
 }
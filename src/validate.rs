@@ -0,0 +1,292 @@
+//! Assertions on the displayed fields of looked up records, e.g. for the smoke tests of a
+//! deployment checking that known addresses still resolve where they should, as
+//! `ipcap -t 8.8.8.8 --expect country_code=US`.
+//!
+//! The expectations are checked against the `RecordFields` of a record, after the coordinate
+//! policy and the post-processors of its reader, so that any field of the output can be
+//! asserted, e.g. `is_eu=true`.
+
+use crate::errors::ExpectationError;
+use crate::post_process::{FieldList, RecordFields};
+use crate::utils::json_string;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the value of a field is compared to the expected one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `field=value`, the value of the field being exactly the expected one.
+    Equals,
+    /// `field~=substring`, the value of the field containing the expected one.
+    Contains,
+}
+
+impl Operator {
+    /// The operator as written in an expression.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Equals => "=",
+            Operator::Contains => "~=",
+        }
+    }
+}
+
+/// An assertion on a field of a record, parsed from `field=value` or `field~=substring`.
+///
+/// A field without a value, e.g. the `postal_code` of a record without one, is only expected by
+/// `field=`, with an empty value.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::validate::{Expectation, Operator};
+///
+/// let expectation: Expectation = "city~=San".parse().unwrap();
+///
+/// assert_eq!(expectation.field, "city");
+/// assert_eq!(expectation.operator, Operator::Contains);
+/// assert_eq!(expectation.value, "San");
+/// assert_eq!(expectation.to_string(), "city~=San");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    /// The name of the field, e.g. `country_code`.
+    pub field: String,
+    pub operator: Operator,
+    /// The expected value, or substring of the value.
+    pub value: String,
+}
+
+impl FromStr for Expectation {
+    type Err = ExpectationError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let invalid = || ExpectationError::Invalid(expr.to_string());
+        let (field, value) = expr.split_once('=').ok_or_else(invalid)?;
+        let (field, operator) = match field.strip_suffix('~') {
+            Some(field) => (field, Operator::Contains),
+            None => (field, Operator::Equals),
+        };
+        let is_name = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+        if field.is_empty() || !field.chars().all(is_name) {
+            return Err(invalid());
+        }
+
+        Ok(Expectation {
+            field: field.to_string(),
+            operator,
+            value: value.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.field, self.operator.as_str(), self.value)
+    }
+}
+
+impl Expectation {
+    /// Checks the expectation against the fields of a record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectationError::UnknownField` if the record has no such field, e.g. `is_eu`
+    /// without the post-processor adding it.
+    pub fn check(&self, fields: &RecordFields<'_>) -> Result<ExpectationResult, ExpectationError> {
+        if !fields.contains(&self.field) {
+            return Err(ExpectationError::UnknownField(self.field.clone()));
+        }
+
+        let actual = fields.get(&self.field);
+        let passed = match (self.operator, actual) {
+            (Operator::Equals, actual) => actual.unwrap_or_default() == self.value,
+            (Operator::Contains, Some(actual)) => actual.contains(&self.value),
+            (Operator::Contains, None) => false,
+        };
+        Ok(ExpectationResult {
+            expectation: self.clone(),
+            actual: actual.map(str::to_string),
+            passed,
+            record: fields.clone().into_vec(),
+        })
+    }
+}
+
+/// The outcome of checking an `Expectation` against a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectationResult {
+    pub expectation: Expectation,
+    /// The value of the field, `None` if the field has no value.
+    pub actual: Option<String>,
+    pub passed: bool,
+    /// The fields of the record the expectation was checked against.
+    pub record: FieldList,
+}
+
+impl ExpectationResult {
+    /// Serializes the outcome for a target as a JSON object of its `target`, `passed`,
+    /// `expected` expression, `actual` value and `record` fields.
+    pub fn to_json(&self, target: &str) -> String {
+        let optional =
+            |value: Option<&String>| value.map_or("null".to_string(), |value| json_string(value));
+        let record: Vec<String> = self
+            .record
+            .iter()
+            .map(|(name, value)| format!("{}: {}", json_string(name), optional(value.as_ref())))
+            .collect();
+        format!(
+            "{{\"target\": {}, \"passed\": {}, \"expected\": {}, \"actual\": {}, \
+             \"record\": {{{}}}}}",
+            json_string(target),
+            self.passed,
+            json_string(&self.expectation.to_string()),
+            optional(self.actual.as_ref()),
+            record.join(", ")
+        )
+    }
+}
+
+/// Serializes the outcome of an expectation on a target that could not be looked up, e.g. not
+/// in the database, as the JSON object of `ExpectationResult::to_json` without an `actual`
+/// value nor a `record`, and with the `error` of the lookup.
+pub fn failure_json(target: &str, expectation: &Expectation, error: &dyn fmt::Display) -> String {
+    format!(
+        "{{\"target\": {}, \"passed\": false, \"expected\": {}, \"actual\": null, \
+         \"record\": null, \"error\": {}}}",
+        json_string(target),
+        json_string(&expectation.to_string()),
+        json_string(&error.to_string())
+    )
+}
+
+/// Parses an expression, `field=value` or `field~=substring`, and checks it against the fields
+/// of a record.
+///
+/// # Errors
+///
+/// Returns `ExpectationError::Invalid` if the expression cannot be parsed, and
+/// `ExpectationError::UnknownField` if the record has no such field.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::Record;
+/// use ipcap::locale::Locale;
+/// use ipcap::post_process::RecordFields;
+/// use ipcap::validate::check_expectation;
+///
+/// let record = Record::new(Country::France);
+/// let fields = RecordFields::new(&record, None, 0, Locale::En);
+///
+/// assert!(check_expectation(&fields, "country_code=FR").unwrap().passed);
+/// assert!(!check_expectation(&fields, "country_name~=Germ").unwrap().passed);
+/// assert!(check_expectation(&fields, "planet=Earth").is_err());
+/// ```
+pub fn check_expectation(
+    fields: &RecordFields<'_>,
+    expr: &str,
+) -> Result<ExpectationResult, ExpectationError> {
+    expr.parse::<Expectation>()?.check(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::Country;
+    use crate::geo_ip_reader::Record;
+    use crate::locale::Locale;
+    use crate::post_process::add_is_eu_flag;
+
+    #[test]
+    fn test_parse_expectation() {
+        let parse = |expr: &str| expr.parse::<Expectation>();
+        assert_eq!(
+            parse("country_code=US").unwrap(),
+            Expectation {
+                field: "country_code".to_string(),
+                operator: Operator::Equals,
+                value: "US".to_string(),
+            }
+        );
+        let contains = parse("city~=San Die").unwrap();
+        assert_eq!(contains.operator, Operator::Contains);
+        assert_eq!(contains.value, "San Die");
+        // The value is everything after the first `=`
+        assert_eq!(parse("time_zone=a=b").unwrap().value, "a=b");
+        assert_eq!(parse("postal_code=").unwrap().value, "");
+
+        for expr in [
+            "",
+            "country_code",
+            "=US",
+            "~=US",
+            "Country=US",
+            "city ~= San",
+        ] {
+            assert_eq!(
+                parse(expr),
+                Err(ExpectationError::Invalid(expr.to_string())),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_expectation() {
+        let record = Record::new(Country::Germany).with_city("Berlin");
+        let mut fields = RecordFields::new(&record, None, 0, Locale::En);
+
+        let result = check_expectation(&fields, "country_code=DE").unwrap();
+        assert!(result.passed);
+        assert_eq!(result.actual.as_deref(), Some("DE"));
+        assert_eq!(result.record, fields.clone().into_vec());
+
+        let result = check_expectation(&fields, "country_code=GB").unwrap();
+        assert!(!result.passed);
+        assert_eq!(result.actual.as_deref(), Some("DE"));
+
+        assert!(check_expectation(&fields, "city~=erl").unwrap().passed);
+        assert!(!check_expectation(&fields, "city~=erlin!").unwrap().passed);
+        assert!(!check_expectation(&fields, "city=Berl").unwrap().passed);
+
+        // A field without a value is only the empty value
+        let result = check_expectation(&fields, "postal_code=").unwrap();
+        assert!(result.passed);
+        assert_eq!(result.actual, None);
+        assert!(!check_expectation(&fields, "postal_code~=").unwrap().passed);
+
+        // The fields added by the post-processors are known once added
+        assert_eq!(
+            check_expectation(&fields, "is_eu=true"),
+            Err(ExpectationError::UnknownField("is_eu".to_string()))
+        );
+        add_is_eu_flag()(&mut fields);
+        assert!(check_expectation(&fields, "is_eu=true").unwrap().passed);
+    }
+
+    #[test]
+    fn test_expectation_json() {
+        let record = Record::new(Country::Germany);
+        let fields = RecordFields::new(&record, None, 0, Locale::En);
+        let result = check_expectation(&fields, "country_code=GB").unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&result.to_json("192.0.2.1")).unwrap();
+        assert_eq!(json["target"], "192.0.2.1");
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["expected"], "country_code=GB");
+        assert_eq!(json["actual"], "DE");
+        assert_eq!(json["record"]["country_name"], "Germany");
+        assert_eq!(json["record"]["city"], serde_json::Value::Null);
+
+        let expectation = "country_code=US".parse().unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&failure_json("10.0.0.1", &expectation, &"not found")).unwrap();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["actual"], serde_json::Value::Null);
+        assert_eq!(json["record"], serde_json::Value::Null);
+        assert_eq!(json["error"], "not found");
+    }
+}
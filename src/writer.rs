@@ -0,0 +1,535 @@
+//! City records built from their fields, and with the `writer` feature, city databases built
+//! from CIDR ranges.
+
+#[cfg(any(test, feature = "writer"))]
+use crate::constants::*;
+use crate::countries::Country;
+use crate::designated_market_area::DesignatedMarketArea;
+#[cfg(any(test, feature = "writer"))]
+use crate::errors::DatabaseWriterError;
+#[cfg(any(test, feature = "writer"))]
+use crate::geo_ip_reader::ByteOrder;
+use crate::geo_ip_reader::Record;
+#[cfg(any(test, feature = "writer"))]
+use crate::utils::parse_cidr;
+#[cfg(any(test, feature = "writer"))]
+use std::fs;
+#[cfg(any(test, feature = "writer"))]
+use std::io;
+#[cfg(any(test, feature = "writer"))]
+use std::path::Path;
+
+/// The content of a city record to be written by `DatabaseBuilder` or to override the database
+/// with `overrides::OverrideTable`.
+///
+/// # Examples
+///
+/// ```
+/// use ipcap::countries::Country;
+/// use ipcap::writer::RecordSpec;
+///
+/// let spec = RecordSpec::new(Country::UnitedStates)
+///     .region_code("CA")
+///     .city("San Diego")
+///     .postal_code("92109")
+///     .coordinates(32.7977, -117.2335);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordSpec {
+    pub country: Country,
+    pub region_code: Option<String>,
+    pub city: Option<String>,
+    pub postal_code: Option<String>,
+    /// Latitude and longitude, written as `-180.0, -180.0` when missing.
+    pub coordinates: Option<(f64, f64)>,
+    pub dma: Option<DesignatedMarketArea>,
+}
+
+impl RecordSpec {
+    /// Constructs a record holding only a country.
+    pub fn new(country: Country) -> Self {
+        RecordSpec {
+            country,
+            region_code: None,
+            city: None,
+            postal_code: None,
+            coordinates: None,
+            dma: None,
+        }
+    }
+
+    /// Sets the region code.
+    pub fn region_code(mut self, region_code: &str) -> Self {
+        self.region_code = Some(region_code.to_string());
+        self
+    }
+
+    /// Sets the city.
+    pub fn city(mut self, city: &str) -> Self {
+        self.city = Some(city.to_string());
+        self
+    }
+
+    /// Sets the postal code.
+    pub fn postal_code(mut self, postal_code: &str) -> Self {
+        self.postal_code = Some(postal_code.to_string());
+        self
+    }
+
+    /// Sets the latitude and longitude.
+    pub fn coordinates(mut self, latitude: f64, longitude: f64) -> Self {
+        self.coordinates = Some((latitude, longitude));
+        self
+    }
+
+    /// Sets the designated market area.
+    pub fn dma(mut self, dma: DesignatedMarketArea) -> Self {
+        self.dma = Some(dma);
+        self
+    }
+
+    /// Converts the spec into the record a lookup returns, its time zone being the one of its
+    /// country and region, and its coordinates `0.0` when missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipcap::countries::Country;
+    /// use ipcap::writer::RecordSpec;
+    ///
+    /// let record = RecordSpec::new(Country::UnitedStates)
+    ///     .region_code("CA")
+    ///     .city("San Diego")
+    ///     .to_record();
+    ///
+    /// assert_eq!(record.city(), Some("San Diego"));
+    /// assert_eq!(record.time_zone, "America/Los_Angeles");
+    /// ```
+    pub fn to_record(&self) -> Record<'static> {
+        let mut record = Record::new(self.country);
+        if let Some(region_code) = &self.region_code {
+            record = record.with_region_code(region_code);
+        }
+        if let Some(city) = &self.city {
+            record = record.with_city(city);
+        }
+        if let Some(postal_code) = &self.postal_code {
+            record = record.with_postal_code(postal_code);
+        }
+        if let Some((latitude, longitude)) = self.coordinates {
+            record = record.with_coordinates(latitude, longitude);
+        }
+        if let Some(dma) = self.dma {
+            record = record.with_dma(dma);
+        }
+        record
+    }
+
+    /// Encodes the record the way `GeoIpReader::get_record` decodes it, with the integers in
+    /// the given byte order.
+    #[cfg(any(test, feature = "writer"))]
+    fn encode(&self, byte_order: ByteOrder) -> Vec<u8> {
+        let mut buf = vec![self.country.to_buffer()];
+        for value in [&self.region_code, &self.city, &self.postal_code] {
+            if let Some(value) = value {
+                buf.extend(value.as_bytes());
+            }
+            buf.push(0);
+        }
+
+        let (latitude, longitude) = self.coordinates.unwrap_or((-180.0, -180.0));
+        for coordinate in [latitude, longitude] {
+            let value = ((coordinate + 180.0) * 10000.0).round() as u32;
+            buf.extend(byte_order.write(value, 3));
+        }
+
+        // The standard databases always store the DMA of United States records
+        let dma = match self.dma {
+            Some(dma) => Some(dma.0),
+            None if self.country == Country::UnitedStates => Some(0),
+            None => None,
+        };
+        if let Some(dma) = dma {
+            buf.extend(byte_order.write(dma, 3));
+        }
+
+        buf
+    }
+}
+
+/// A pointer stored in a tree node.
+#[cfg(any(test, feature = "writer"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pointer {
+    /// The address is not in the database.
+    Empty,
+    /// The index of the next node.
+    Node(usize),
+    /// The index of a record.
+    Leaf(usize),
+}
+
+/// `DatabaseBuilder` creates city databases, readable by `GeoIpReader`, from CIDR ranges.
+///
+/// The databases are `CITY_EDITION_REV1` for IPv4 ranges and `CITY_EDITION_REV1_V6` for IPv6
+/// ranges. When ranges overlap, the most specific prefix wins.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use ipcap::countries::Country;
+/// use ipcap::geo_ip_reader::GeoIpReader;
+/// use ipcap::writer::{DatabaseBuilder, RecordSpec};
+///
+/// let mut builder = DatabaseBuilder::new();
+/// builder.insert("10.0.0.0/8", RecordSpec::new(Country::Germany)).unwrap();
+/// builder.insert("10.1.0.0/16", RecordSpec::new(Country::France).city("Paris")).unwrap();
+///
+/// let mut geo_ip = GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap();
+///
+/// assert_eq!(geo_ip.get_record("10.2.3.4").unwrap().country, Country::Germany);
+/// assert_eq!(geo_ip.get_record("10.1.2.3").unwrap().city, Some("Paris".into()));
+/// ```
+#[cfg(any(test, feature = "writer"))]
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseBuilder {
+    /// The inserted ranges as (network, prefix length, record spec).
+    ranges: Vec<(u128, u8, RecordSpec)>,
+    /// Whether the inserted ranges are IPv6 ones.
+    ipv6: Option<bool>,
+    /// The order of the bytes of the integers of the database.
+    byte_order: ByteOrder,
+}
+
+#[cfg(any(test, feature = "writer"))]
+impl DatabaseBuilder {
+    /// Constructs an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the order of the bytes of the integers of the database, little-endian by default as
+    /// in the standard databases, see `GeoIpReaderBuilder::byte_order`.
+    pub fn byte_order(&mut self, byte_order: ByteOrder) -> &mut Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Maps every address of a CIDR range, e.g. `10.0.0.0/8`, to a record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is not a valid CIDR, is not of the same address family as
+    /// the previously inserted ranges, or if the record does not fit in a full record.
+    pub fn insert(
+        &mut self,
+        cidr: &str,
+        record: RecordSpec,
+    ) -> Result<&mut Self, DatabaseWriterError> {
+        let (network, prefix, ipv6) =
+            parse_cidr(cidr).ok_or_else(|| DatabaseWriterError::InvalidCidr(cidr.to_string()))?;
+        if *self.ipv6.get_or_insert(ipv6) != ipv6 {
+            return Err(DatabaseWriterError::MixedAddressFamilies(cidr.to_string()));
+        }
+        if record.encode(self.byte_order).len() > FULL_RECORD_LENGTH {
+            return Err(DatabaseWriterError::RecordTooLong);
+        }
+
+        self.ranges.push((network, prefix, record));
+        Ok(self)
+    }
+
+    /// Serializes the inserted ranges into a database.
+    ///
+    /// The database holds the binary search tree over the prefixes, followed by the records
+    /// and the structure info trailer.
+    pub fn build(&self) -> Vec<u8> {
+        let bits: u8 = if self.ipv6 == Some(true) { 128 } else { 32 };
+        let edition = if bits == 128 {
+            CITY_EDITION_REV1_V6
+        } else {
+            CITY_EDITION_REV1
+        };
+
+        // Insert the least specific prefixes first so the more specific ones split them
+        let mut ranges: Vec<(usize, &(u128, u8, RecordSpec))> =
+            self.ranges.iter().enumerate().collect();
+        ranges.sort_by_key(|(i, (_, prefix, _))| (*prefix, *i));
+
+        let mut nodes: Vec<[Pointer; 2]> = vec![[Pointer::Empty; 2]];
+        for (record, (network, prefix, _)) in ranges {
+            if *prefix == 0 {
+                nodes[0] = [Pointer::Leaf(record); 2];
+                continue;
+            }
+
+            let mut node = 0;
+            for depth in 0..*prefix {
+                let bit = ((network >> (bits - 1 - depth)) & 1) as usize;
+                if depth == prefix - 1 {
+                    nodes[node][bit] = Pointer::Leaf(record);
+                    break;
+                }
+
+                node = match nodes[node][bit] {
+                    Pointer::Node(next) => next,
+                    pointer => {
+                        // Split an empty or less specific pointer into a new node
+                        nodes.push([pointer; 2]);
+                        nodes[node][bit] = Pointer::Node(nodes.len() - 1);
+                        nodes.len() - 1
+                    }
+                };
+            }
+        }
+
+        // Records are addressed relatively to the end of the tree, plus one padding byte
+        let segments = nodes.len() as u32;
+        let mut records = vec![0u8];
+        let mut record_offsets = Vec::with_capacity(self.ranges.len());
+        for (_, _, spec) in &self.ranges {
+            record_offsets.push(records.len() as u32);
+            records.extend(spec.encode(self.byte_order));
+        }
+
+        let mut database = Vec::new();
+        for node in &nodes {
+            for pointer in node {
+                let value = match *pointer {
+                    Pointer::Empty => segments,
+                    Pointer::Node(next) => next as u32,
+                    Pointer::Leaf(record) => segments + record_offsets[record],
+                };
+                database.extend(self.byte_order.write(value, STANDARD_RECORD_LENGTH));
+            }
+        }
+        database.extend(records);
+        // Leave room for a full record read on the last record
+        database.extend(vec![0u8; FULL_RECORD_LENGTH]);
+
+        database.extend([255, 255, 255, edition]);
+        database.extend(self.byte_order.write(segments, SEGMENT_RECORD_LENGTH));
+        database
+    }
+
+    /// Serializes the inserted ranges into a database file.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_ip_reader::GeoIpReader;
+    use std::io::Cursor;
+
+    fn ranges() -> Vec<(&'static str, RecordSpec)> {
+        vec![
+            (
+                "1.0.0.0/8",
+                RecordSpec::new(Country::Australia).coordinates(-27.0, 133.0),
+            ),
+            (
+                "1.1.0.0/16",
+                RecordSpec::new(Country::Australia)
+                    .region_code("02")
+                    .city("Sydney"),
+            ),
+            (
+                "1.1.1.0/24",
+                RecordSpec::new(Country::Australia)
+                    .region_code("07")
+                    .city("Melbourne"),
+            ),
+            ("8.8.8.0/24", RecordSpec::new(Country::UnitedStates)),
+            (
+                "8.8.4.4/32",
+                RecordSpec::new(Country::UnitedStates)
+                    .region_code("CA")
+                    .city("Mountain View")
+                    .postal_code("94043")
+                    .coordinates(37.4192, -122.0574)
+                    .dma(DesignatedMarketArea(807650)),
+            ),
+            (
+                "81.2.69.0/24",
+                RecordSpec::new(Country::UnitedKingdom)
+                    .city("London")
+                    .coordinates(51.5142, -0.0931),
+            ),
+            (
+                "89.160.20.0/22",
+                RecordSpec::new(Country::Sweden).city("Linköping"),
+            ),
+            ("128.101.101.0/24", RecordSpec::new(Country::UnitedStates)),
+            (
+                "175.16.199.0/24",
+                RecordSpec::new(Country::China)
+                    .region_code("05")
+                    .city("Changchun"),
+            ),
+            ("185.90.90.0/23", RecordSpec::new(Country::SaudiArabia)),
+            (
+                "202.196.224.0/20",
+                RecordSpec::new(Country::Philippines).coordinates(13.0, 122.0),
+            ),
+            (
+                "216.160.83.56/29",
+                RecordSpec::new(Country::UnitedStates).city("Milton"),
+            ),
+        ]
+    }
+
+    fn reader(builder: &DatabaseBuilder) -> GeoIpReader<Cursor<Vec<u8>>> {
+        GeoIpReader::from_reader(Cursor::new(builder.build())).unwrap()
+    }
+
+    #[test]
+    fn test_build_and_lookup_every_range() {
+        let mut builder = DatabaseBuilder::new();
+        for (cidr, spec) in ranges() {
+            builder.insert(cidr, spec).unwrap();
+        }
+        let mut geo_ip = reader(&builder);
+
+        let lookups = [
+            ("1.0.0.1", 0),
+            ("1.255.255.255", 0),
+            ("1.1.0.1", 1),
+            ("1.1.1.1", 2),
+            ("8.8.8.8", 3),
+            ("8.8.4.4", 4),
+            ("81.2.69.160", 5),
+            ("89.160.23.255", 6),
+            ("128.101.101.101", 7),
+            ("175.16.199.0", 8),
+            ("185.90.91.255", 9),
+            ("202.196.239.1", 10),
+            ("216.160.83.63", 11),
+        ];
+        for (ip, index) in lookups {
+            let spec = &ranges()[index].1;
+            let record = geo_ip.find_record(ip).unwrap().unwrap();
+
+            assert_eq!(record.country, spec.country, "{}", ip);
+            assert_eq!(record.region_code.as_deref(), spec.region_code.as_deref());
+            assert_eq!(record.city.as_deref(), spec.city.as_deref());
+            assert_eq!(record.postal_code.as_deref(), spec.postal_code.as_deref());
+            let (latitude, longitude) = spec.coordinates.unwrap_or((-180.0, -180.0));
+            assert!((record.latitude - latitude).abs() < 1e-6);
+            assert!((record.longitude - longitude).abs() < 1e-6);
+        }
+
+        let record = geo_ip.find_record("8.8.4.4").unwrap().unwrap();
+        assert_eq!(record.dma, Some(DesignatedMarketArea(807650)));
+    }
+
+    #[test]
+    fn test_uncovered_addresses() {
+        let mut builder = DatabaseBuilder::new();
+        for (cidr, spec) in ranges() {
+            builder.insert(cidr, spec).unwrap();
+        }
+        let mut geo_ip = reader(&builder);
+
+        for ip in [
+            "0.0.0.0",
+            "2.0.0.0",
+            "8.8.4.5",
+            "89.160.24.0",
+            "255.255.255.255",
+        ] {
+            assert!(geo_ip.find_record(ip).unwrap().is_none(), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_most_specific_prefix_wins_regardless_of_order() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("10.1.0.0/16", RecordSpec::new(Country::France))
+            .unwrap()
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap()
+            .insert("0.0.0.0/0", RecordSpec::new(Country::Europe))
+            .unwrap();
+        let mut geo_ip = reader(&builder);
+
+        assert_eq!(
+            geo_ip.get_record("10.1.0.1").unwrap().country,
+            Country::France
+        );
+        assert_eq!(
+            geo_ip.get_record("10.2.0.1").unwrap().country,
+            Country::Germany
+        );
+        assert_eq!(
+            geo_ip.get_record("192.0.2.1").unwrap().country,
+            Country::Europe
+        );
+    }
+
+    #[test]
+    fn test_ipv6_database() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert(
+                "2a08:1450::/32",
+                RecordSpec::new(Country::UnitedKingdom).coordinates(54.0, -2.0),
+            )
+            .unwrap();
+        let mut geo_ip = reader(&builder);
+
+        let record = geo_ip.get_record("2a08:1450:300f:900::1003").unwrap();
+        assert_eq!(record.country, Country::UnitedKingdom);
+        assert_eq!(record.time_zone, "Europe/London");
+    }
+
+    #[test]
+    fn test_insert_errors() {
+        let mut builder = DatabaseBuilder::new();
+
+        assert!(matches!(
+            builder.insert("10.0.0.0", RecordSpec::new(Country::Germany)),
+            Err(DatabaseWriterError::InvalidCidr(_))
+        ));
+        assert!(matches!(
+            builder.insert("10.0.0.0/33", RecordSpec::new(Country::Germany)),
+            Err(DatabaseWriterError::InvalidCidr(_))
+        ));
+        builder
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+        assert!(matches!(
+            builder.insert("2a08::/16", RecordSpec::new(Country::Germany)),
+            Err(DatabaseWriterError::MixedAddressFamilies(_))
+        ));
+        assert!(matches!(
+            builder.insert(
+                "11.0.0.0/8",
+                RecordSpec::new(Country::Germany).city(&"x".repeat(FULL_RECORD_LENGTH))
+            ),
+            Err(DatabaseWriterError::RecordTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut builder = DatabaseBuilder::new();
+        builder
+            .insert("10.0.0.0/8", RecordSpec::new(Country::Germany))
+            .unwrap();
+
+        let path = std::env::temp_dir().join("ipcap_test_write_to.dat");
+        builder.write_to(&path).unwrap();
+        let mut geo_ip = GeoIpReader::<std::fs::File>::from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            geo_ip.get_record("10.0.0.1").unwrap().country,
+            Country::Germany
+        );
+    }
+}
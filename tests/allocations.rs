@@ -0,0 +1,127 @@
+//! The heap allocations of the lookups over a database in memory, counted by a global allocator
+//! so that a regression of the fast paths fails the tests rather than only the benchmarks.
+//!
+//! The allocations are counted per thread, so the tests of this binary can run in parallel.
+
+use ipcap::geo_ip_reader::GeoIpReader;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::Cursor;
+
+/// The system allocator, counting the allocations of each thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// SAFETY: every call is forwarded to the system allocator, only counting is added
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // The thread-local may be gone while the thread exits
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the value of `f` and the number of allocations it performed on this thread.
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let value = f();
+    (value, ALLOCATIONS.with(Cell::get) - before)
+}
+
+/// A reader over the database of the given address family in memory, after a first lookup
+/// so that the allocations done once per reader, e.g. the octet index, are not counted.
+fn reader(type_: &str, warm_up: &str) -> GeoIpReader<Cursor<Vec<u8>>> {
+    let path = GeoIpReader::<File>::database_path(type_).unwrap();
+    let mut geo_ip = GeoIpReader::from_reader(Cursor::new(fs::read(path).unwrap())).unwrap();
+    geo_ip.find_record(warm_up).unwrap();
+    geo_ip
+}
+
+#[test]
+fn test_lookup_allocations() {
+    let mut geo_ip = reader("v4", "8.8.8.8");
+
+    // The region code, city and postal code strings only, a record without gaps allocating
+    // no list of them
+    let (record, count) = allocations(|| geo_ip.get_record("108.95.4.105").unwrap());
+    assert_eq!(record.city(), Some("San Diego"));
+    assert!(record.gaps().is_empty());
+    assert!(count <= 3, "{} allocations", count);
+
+    // An address that is not in the database has no record to allocate
+    let (_, count) = allocations(|| geo_ip.find_record("10.0.0.1").unwrap());
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_v6_lookup_allocations() {
+    let mut geo_ip = reader("v6", "2001:4860:4860::8888");
+
+    let (record, count) = allocations(|| geo_ip.get_record("2a08:1450:300f:900::1003").unwrap());
+    assert_eq!(record.country.alphabetic_code_2(), "GB");
+    assert!(count <= 3, "{} allocations", count);
+}
+
+#[test]
+fn test_contains_allocations() {
+    let mut v4 = reader("v4", "8.8.8.8");
+    let mut v6 = reader("v6", "2001:4860:4860::8888");
+
+    let (found, count) = allocations(|| {
+        (
+            v4.contains("108.95.4.105").unwrap(),
+            v4.contains("10.0.0.1").unwrap(),
+            v6.contains("2a08:1450:300f:900::1003").unwrap(),
+        )
+    });
+    assert_eq!(found, (true, false, true));
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_interned_batch_allocations() {
+    let mut geo_ip = reader("v4", "8.8.8.8");
+    // A batch with geographic locality, every address being in San Diego
+    let ips = vec!["108.95.4.105".parse().unwrap(); 1000];
+
+    let (records, boxed) = allocations(|| {
+        geo_ip
+            .get_records(ips.clone())
+            .into_iter()
+            .map(|(_, record)| record.unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(records.len(), 1000);
+
+    // The distinct strings once, plus the interner and the collected vector
+    let (records, interned) = allocations(|| {
+        geo_ip
+            .get_records_interned(ips.clone())
+            .map(|(_, record)| record.unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(records.len(), 1000);
+    assert!(boxed >= 3000, "{} allocations", boxed);
+    assert!(interned <= 20, "{} allocations", interned);
+}
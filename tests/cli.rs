@@ -0,0 +1,1101 @@
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn ipcap(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ipcap"))
+        .args(args)
+        .env_remove("IPCAP_FILE_PATH")
+        .env_remove("IPCAP_TZ_OVERRIDES")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_garbage_target() {
+    let output = ipcap(&["-t", "banana"]);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid IP address \"banana\""));
+}
+
+#[cfg(feature = "resolve")]
+#[test]
+fn test_resolve_target() {
+    // Resolved from the hosts file, without any network
+    let output = ipcap(&["-v", "-t", "localhost", "--resolve"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("resolved: 127.0.0.1"));
+    assert!(stderr.contains("note: localhost resolves to 127.0.0.1, looking it up\n"));
+    assert!(stderr.contains("Address not found in the database: 127.0.0.1"));
+
+    let output = ipcap(&["-t", "localhost"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid IP address \"localhost\""));
+
+    let output = ipcap(&["-t", "no-such-host.invalid", "--resolve"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error getting host by name: no-such-host.invalid"));
+}
+
+#[test]
+fn test_leading_zeros_target() {
+    let strict = ipcap(&["-t", "108.095.004.105"]);
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("only accepted by legacy parsing"));
+
+    let legacy = ipcap(&["-t", "108.095.004.105", "--legacy-parse"]);
+    assert!(legacy.status.success());
+    assert_eq!(legacy.stdout, ipcap(&["-t", "108.95.4.105"]).stdout);
+}
+
+#[test]
+fn test_ipv4_mapped_target_uses_v4_database() {
+    let output = ipcap(&["-v", "-t", "::ffff:108.95.4.105"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("San Diego"));
+    assert!(stderr.contains("embeds the IPv4 address 108.95.4.105"));
+    assert!(stderr.contains("geo_ip_city_v4.dat"));
+    assert!(stderr.contains("fallbacks: v4-extraction\n"));
+}
+
+#[test]
+fn test_verbose_output() {
+    let verbose = ipcap(&["--verbose", "-t", "108.95.4.105"]);
+    let quiet = ipcap(&["-t", "108.95.4.105"]);
+
+    let verbose_stderr = String::from_utf8_lossy(&verbose.stderr);
+    assert!(verbose_stderr.contains("geo_ip_city_v4.dat"));
+    assert!(verbose_stderr.contains("edition: City Edition, Rev 1"));
+    assert!(verbose_stderr.contains("prefix: 108.95.4.0/"));
+    assert!(verbose_stderr.contains("lookup: "));
+    assert!(verbose_stderr.contains("fallbacks: none\n"));
+    // The durations vary, only their presence and order are checked
+    let timings = verbose_stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("timings: "))
+        .unwrap();
+    let phases: Vec<_> = timings
+        .split(", ")
+        .map(|phase| phase.rsplit_once(' ').unwrap())
+        .collect();
+    assert_eq!(
+        phases.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        vec!["parse", "tree walk", "record parse"]
+    );
+    assert!(phases.iter().all(|(_, duration)| duration.ends_with('s')
+        && duration.starts_with(|c: char| c.is_ascii_digit())));
+
+    assert!(quiet.status.success());
+    assert!(!String::from_utf8_lossy(&quiet.stdout).contains("geo_ip_city_v4.dat"));
+    assert!(!String::from_utf8_lossy(&quiet.stderr).contains("geo_ip_city_v4.dat"));
+    assert_eq!(verbose.stdout, quiet.stdout);
+}
+
+#[test]
+fn test_explain_gaps() {
+    let output = ipcap(&["--explain-gaps", "-t", "185.90.90.120"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr,
+        "gap: region_code missing: not in the record of the database\n\
+         gap: city missing: not in the record of the database\n\
+         gap: postal_code missing: not in the record of the database\n"
+    );
+    assert_eq!(output.stdout, ipcap(&["-t", "185.90.90.120"]).stdout);
+
+    let output = ipcap(&["--explain-gaps", "-t", "108.95.4.105"]);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "gaps: none\n");
+    let verbose = ipcap(&["--verbose", "-t", "108.95.4.105"]);
+    assert!(String::from_utf8_lossy(&verbose.stderr).contains("gaps: none\n"));
+}
+
+fn ipcap_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+        .args(args)
+        .env_remove("IPCAP_FILE_PATH")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // A process failing on its arguments may exit before reading its input
+    match child.stdin.take().unwrap().write_all(stdin.as_bytes()) {
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+        result => result.unwrap(),
+    }
+    child.wait_with_output().unwrap()
+}
+
+const ACCESS_LOG: &str = "108.95.4.105 GET /\n10.0.0.1 GET /health\n108.95.4.105 GET /about\n";
+
+#[test]
+fn test_batch_unique_count() {
+    let output = ipcap_with_stdin(&["batch", "--unique", "--count"], ACCESS_LOG);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.105\tUS\t2\n10.0.0.1\t\t1\n"
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_batch_unique_past_limit() {
+    let output = ipcap_with_stdin(
+        &["batch", "--unique", "--count", "--unique-limit", "1"],
+        ACCESS_LOG,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.105\tUS\n10.0.0.1\t\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("duplicates may rarely slip through"));
+}
+
+#[test]
+fn test_batch_without_unique() {
+    let output = ipcap_with_stdin(&["batch"], ACCESS_LOG);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 3);
+}
+
+#[test]
+fn test_batch_json() {
+    let input = format!("{}not an address\n\n", ACCESS_LOG);
+    let output = ipcap_with_stdin(
+        &["batch", "--format", "json", "--append", "country_code,city"],
+        &input,
+    );
+
+    assert!(output.status.success());
+    let rows: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 4);
+    assert!(rows.iter().all(|row| row["schema_version"] == "1"));
+    assert_eq!(rows[0]["ip"], "108.95.4.105");
+    assert_eq!(rows[0]["country_code"], "US");
+    assert_eq!(rows[0]["city"], "San Diego");
+    assert_eq!(rows[1]["ip"], "10.0.0.1");
+    assert_eq!(rows[1]["error"], "reserved_address");
+    // Only the first token of a line is read as its address
+    assert_eq!(rows[3]["ip"], "not");
+    assert_eq!(rows[3]["error"], "invalid_address");
+    assert!(rows[3]["message"].is_string());
+    assert!(rows[0].get("provenance").is_none());
+}
+
+#[test]
+fn test_batch_windows_export() {
+    fn ipcap_with_bytes(args: &[&str], stdin: &[u8]) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+            .args(args)
+            .env_remove("IPCAP_FILE_PATH")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(stdin).unwrap();
+        child.wait_with_output().unwrap()
+    }
+    let args = ["--verbose", "batch", "--append", "country_code,city"];
+    let clean = ipcap_with_bytes(
+        &args,
+        b"108.95.4.105
+2a08:1450:300f:900::1003
+",
+    );
+    assert!(clean.status.success());
+
+    let utf8 =
+        b"\xef\xbb\xbf108.95.4.105\tSan Diego office\r\n# v6\r\n\r\n2a08:1450:300f:900::1003\r\n";
+    let utf16: Vec<u8> = [0xff, 0xfe]
+        .into_iter()
+        .chain(
+            "108.95.4.105\tSan Diego office\r\n# v6\r\n\r\n2a08:1450:300f:900::1003\r\n"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        )
+        .collect();
+    for input in [&utf8[..], &utf16] {
+        let output = ipcap_with_bytes(&args, input);
+        assert!(output.status.success());
+        assert_eq!(output.stdout, clean.stdout);
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("skipped: 1 blank and 1 comment lines of 4"));
+    }
+
+    // The whole line may hold the address after other columns
+    let output = ipcap_with_bytes(&["batch", "--whole-line"], b"office\t108.95.4.105\r\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.105\tUS\n"
+    );
+    let output = ipcap_with_bytes(&["batch"], b"office\t108.95.4.105\r\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_batch_binary() {
+    use ipcap::binary::{BinaryReader, BinaryRow};
+
+    let input = format!("{}not an address\n", ACCESS_LOG);
+    let args = [
+        "batch",
+        "--append",
+        "country_code,city",
+        "--unique",
+        "--count",
+    ];
+    let output = ipcap_with_stdin(&[&args[..], &["--format", "binary"]].concat(), &input);
+    assert!(output.status.success());
+
+    let mut reader = BinaryReader::new(&output.stdout[..]).unwrap();
+    assert_eq!(reader.fields(), ["country_code", "city"]);
+    let rows: Vec<BinaryRow> = reader.by_ref().map(Result::unwrap).collect();
+    // The invalid line is reported as it is read, ahead of the counted rows
+    assert!(matches!(
+        &rows[0],
+        BinaryRow::Error { ip, code, .. } if ip == "not" && code == "invalid_address"
+    ));
+    assert_eq!(
+        rows[1],
+        BinaryRow::Record {
+            ip: "108.95.4.105".to_string(),
+            values: vec![Some("US".to_string()), Some("San Diego".to_string())],
+            count: Some(2),
+        }
+    );
+    assert!(matches!(
+        &rows[2],
+        BinaryRow::Error { ip, code, .. } if ip == "10.0.0.1" && code == "reserved_address"
+    ));
+    assert_eq!(rows.len(), 3);
+
+    // The same rows as in JSON
+    let output = ipcap_with_stdin(&[&args[..], &["--format", "json"]].concat(), &input);
+    let objects: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(objects.len(), rows.len());
+    for (object, row) in objects.iter().zip(&rows) {
+        match row {
+            BinaryRow::Record { ip, values, count } => {
+                assert_eq!(object["ip"], ip.as_str());
+                assert_eq!(object["city"].as_str(), values[1].as_deref());
+                assert_eq!(object["count"].as_u64(), *count);
+            }
+            BinaryRow::Error { ip, code, message } => {
+                assert_eq!(object["ip"], ip.as_str());
+                assert_eq!(object["error"], code.as_str());
+                assert_eq!(object["message"], message.as_str());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_batch_json_verbose_provenance() {
+    let output = ipcap_with_stdin(
+        &["--verbose", "batch", "--format", "json", "--schema", "2"],
+        ACCESS_LOG,
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let row: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    assert_eq!(row["schema_version"], "2");
+    assert_eq!(row["provenance"]["source"], "file");
+    assert!(row["provenance"]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("geo_ip_city_v4.dat"));
+    assert_eq!(row["provenance"]["edition"], "City Edition, Rev 1");
+    assert_eq!(row["provenance"]["build_date"], "2016-04-05");
+}
+
+#[test]
+fn test_batch_json_unique_count() {
+    let output = ipcap_with_stdin(
+        &["batch", "--format", "json", "--unique", "--count"],
+        ACCESS_LOG,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).lines().next(),
+        Some(r#"{"schema_version": "1", "ip": "108.95.4.105", "country_code": "US", "count": 2}"#)
+    );
+}
+
+#[test]
+fn test_batch_json_schema_v1_has_no_provenance() {
+    let output = ipcap_with_stdin(&["--verbose", "batch", "--format", "json"], ACCESS_LOG);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let row: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    assert_eq!(row["schema_version"], "1");
+    assert!(row.get("provenance").is_none());
+}
+
+#[test]
+fn test_batch_unsupported_schema() {
+    let output = ipcap_with_stdin(&["batch", "--format", "json", "--schema", "3"], ACCESS_LOG);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("unsupported schema version '3', expected one of 1, 2"));
+}
+
+#[test]
+fn test_batch_json_array_agrees_with_json() {
+    let input = format!(
+        "{}not an address
+",
+        ACCESS_LOG
+    );
+    for schema in ["1", "2"] {
+        let args = [
+            "--verbose",
+            "batch",
+            "--append",
+            "country_code,city",
+            "--schema",
+            schema,
+            "--format",
+        ];
+        let lines = ipcap_with_stdin(&[&args[..], &["json"]].concat(), &input);
+        let array = ipcap_with_stdin(&[&args[..], &["json-array"]].concat(), &input);
+        assert!(lines.status.success() && array.status.success());
+
+        let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&lines.stdout)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let array: serde_json::Value = serde_json::from_slice(&array.stdout).unwrap();
+        assert_eq!(array.as_array().unwrap(), &lines);
+    }
+
+    let empty = ipcap_with_stdin(&["batch", "--format", "json-array"], "");
+    let empty: serde_json::Value = serde_json::from_slice(&empty.stdout).unwrap();
+    assert_eq!(empty, serde_json::json!([]));
+}
+
+#[test]
+fn test_doctor() {
+    let output = ipcap(&["doctor"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(&format!("ipcap {}\n", env!("CARGO_PKG_VERSION"))));
+    assert!(stdout.contains("cli"));
+    assert!(stdout.contains("City Edition, Rev 1"));
+    assert!(stdout.contains("geo_ip_city_v4.dat (City Edition, Rev 1)"));
+    assert!(stdout.contains("coordinate policy: none\n"));
+
+    let output = ipcap(&["doctor", "--coordinate-policy", "legacy-sentinel"]);
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("coordinate policy: legacy-sentinel\n")
+    );
+}
+
+#[test]
+fn test_doctor_broken_databases() {
+    let doctor = |path: &std::path::Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+            .arg("doctor")
+            .env("IPCAP_FILE_PATH", path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let path = std::env::temp_dir().join(format!("ipcap-empty-{}.dat", std::process::id()));
+
+    assert!(doctor(&path).contains(&format!("  v4 {}: missing\n", path.display())));
+    std::fs::write(&path, "").unwrap();
+    let stdout = doctor(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(stdout.contains("too small: 0 bytes, expected at least 13"));
+    assert!(!stdout.contains("missing"));
+}
+
+#[test]
+fn test_migrate_help() {
+    let output = ipcap(&["migrate-help"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        ipcap::compat::MIGRATION
+    );
+}
+
+#[cfg(not(feature = "embedded-country-data"))]
+#[test]
+fn test_missing_database() {
+    let path = std::env::temp_dir().join("ipcap_test_missing/geo_ip_city_v4.dat");
+    let output = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+        .args(["-t", "8.8.8.8"])
+        .env("IPCAP_FILE_PATH", &path)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("no v4 database at {}", path.display())));
+    assert!(stderr.contains("set by IPCAP_FILE_PATH"));
+    assert!(stderr.contains("data/geo_ip_city_v4.dat --create-dirs -o"));
+}
+
+#[cfg(feature = "embedded-country-data")]
+#[test]
+fn test_missing_database_uses_embedded_data() {
+    let path = std::env::temp_dir().join("ipcap_test_missing/geo_ip_city_v4.dat");
+    let output = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+        .args(["-v", "-t", "8.8.8.8"])
+        .env("IPCAP_FILE_PATH", &path)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("United States"));
+    assert!(stderr.contains(&format!(
+        "warning: no v4 database at {}, using the embedded country data",
+        path.display()
+    )));
+    assert!(stderr.contains("prefix: 8.0.0.0/8\n"));
+    assert!(stderr.contains("fallbacks: embedded country data, country centroid\n"));
+
+    // The v6 addresses are not in the dataset
+    let output = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+        .args(["-t", "2001:4860:4860::8888"])
+        .env("IPCAP_FILE_PATH", &path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no v6 database at"));
+}
+
+#[cfg(feature = "embedded-country-data")]
+#[test]
+fn test_installed_database_is_preferred_over_embedded_data() {
+    let output = ipcap(&["-v", "-t", "108.95.4.105"]);
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("San Diego"));
+    assert!(!stderr.contains("embedded country data"));
+    assert!(stderr.contains("fallbacks: none\n"));
+}
+
+#[test]
+fn test_verbose_country_level_record() {
+    // The v6 database only holds the country of this address
+    let output = ipcap(&["-v", "-t", "2a08:1450:300f:900::1003"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("fallbacks: country centroid, country time zone\n"));
+}
+
+#[test]
+fn test_batch_hash_ip() {
+    use ipcap::ip_hash::IpHashKey;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+    let input = format!("{}2a08:1450:300f:900::1003 GET /\n", ACCESS_LOG);
+    let ips = ["108.95.4.105", "10.0.0.1", "2a08:1450:300f:900::1003"];
+    let key: IpHashKey = KEY.parse().unwrap();
+    let tokens = ips.map(|ip| key.hash(ip.parse().unwrap()));
+
+    let formats: [&[&str]; 5] = [
+        &[],
+        &["--unique", "--count"],
+        &["--format", "json"],
+        &["--format", "json", "--unique", "--count", "--schema", "2"],
+        &["--format", "json-array"],
+    ];
+    for format in formats {
+        let args = [&["--verbose", "batch", "--hash-ip", KEY][..], format].concat();
+        let output = ipcap_with_stdin(&args, &input);
+        assert!(output.status.success(), "{:?}", args);
+
+        // No raw address anywhere in the output, only their tokens
+        for stream in [&output.stdout, &output.stderr] {
+            for ip in ips {
+                assert!(
+                    !stream
+                        .windows(ip.len())
+                        .any(|window| window == ip.as_bytes()),
+                    "{:?} leaks {}",
+                    args,
+                    ip
+                );
+            }
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for token in &tokens {
+            assert!(stdout.contains(token.as_str()), "{:?}", args);
+        }
+        // The record fields are unchanged
+        assert!(stdout.contains("US") && stdout.contains("GB"));
+    }
+
+    let output = ipcap_with_stdin(&["batch", "--hash-ip", KEY], &input);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).lines().next(),
+        Some(format!("{}\tUS", tokens[0]).as_str())
+    );
+
+    // The same tokens with the key read from the environment, other tokens with another key
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ipcap"))
+        .args(["batch", "--hash-ip-env", "IPCAP_TEST_HASH_KEY"])
+        .env("IPCAP_TEST_HASH_KEY", KEY)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    assert_eq!(child.wait_with_output().unwrap().stdout, output.stdout);
+    let other = ipcap_with_stdin(
+        &["batch", "--hash-ip", "ffffffffffffffffffffffffffffffff"],
+        &input,
+    );
+    assert!(other.status.success());
+    assert_ne!(other.stdout, output.stdout);
+}
+
+#[test]
+fn test_batch_hash_ip_invalid_key() {
+    // No input, the key is rejected before stdin is read
+    let output = ipcap_with_stdin(&["batch", "--hash-ip", "0123"], "");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("expected 32 hex digits (128 bits), got 4"));
+
+    let output = ipcap_with_stdin(
+        &["batch", "--hash-ip", "000102030405060708090a0b0c0d0e0z"],
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'z' is not a hex digit"));
+
+    let output = ipcap_with_stdin(&["batch", "--hash-ip-env", "IPCAP_TEST_UNSET_HASH_KEY"], "");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("environment variable IPCAP_TEST_UNSET_HASH_KEY of --hash-ip-env is not set"));
+}
+
+#[test]
+fn test_batch_warm_up_budget() {
+    let output = ipcap_with_stdin(
+        &["batch", "--pattern", "ipv4", "--warm-up", "0"],
+        ACCESS_LOG,
+    );
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("warning: warm-up budget exhausted after 0 of "));
+    assert_eq!(
+        output.stdout,
+        ipcap_with_stdin(&["batch", "--pattern", "ipv4"], ACCESS_LOG).stdout
+    );
+
+    let invalid = ipcap_with_stdin(&["batch", "--warm-up", "-1"], ACCESS_LOG);
+    assert!(!invalid.status.success());
+}
+
+#[test]
+fn test_batch_verbose_progress() {
+    let output = ipcap_with_stdin(
+        &["-v", "batch", "--unique", "--progress-every", "2"],
+        ACCESS_LOG,
+    );
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<_> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stderr);
+    assert!(lines[0].starts_with("progress: 2 addresses in "));
+    assert!(lines[1].starts_with("done: 3 addresses in "));
+    assert!(lines.iter().all(|line| line.ends_with(" addresses/s)")));
+
+    let quiet = ipcap_with_stdin(&["batch", "--unique"], ACCESS_LOG);
+    assert!(quiet.stderr.is_empty());
+    assert_eq!(quiet.stdout, output.stdout);
+}
+
+#[test]
+fn test_overrides() {
+    let path = std::env::temp_dir().join(format!("ipcap-overrides-{}.csv", std::process::id()));
+    std::fs::write(
+        &path,
+        "cidr,country,region,city,lat,lon,time_zone\n108.95.4.0/24,DE,,Berlin,52.52,13.405\n",
+    )
+    .unwrap();
+    let overrides = path.to_str().unwrap();
+
+    let output = ipcap(&["-v", "-t", "108.95.4.105", "--overrides", overrides]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("Berlin"));
+    assert!(!stdout.contains("San Diego"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("override: 108.95.4.0/24\n"));
+
+    let output = ipcap(&["-t", "108.95.5.1", "--overrides", overrides]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, ipcap(&["-t", "108.95.5.1"]).stdout);
+
+    std::fs::write(&path, "108.95.4.0/24,DE\n108.95.5.0/24,Germany\n").unwrap();
+    let output = ipcap(&["-t", "108.95.4.105", "--overrides", overrides]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Invalid override on line 2: unknown country code \"Germany\""));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_time_zone_overrides() {
+    let path = std::env::temp_dir().join(format!("ipcap-tz-overrides-{}.toml", std::process::id()));
+    let ipcap_with_overrides = |target: &str| {
+        Command::new(env!("CARGO_BIN_EXE_ipcap"))
+            .args(["-t", target])
+            .env_remove("IPCAP_FILE_PATH")
+            .env("IPCAP_TZ_OVERRIDES", &path)
+            .output()
+            .unwrap()
+    };
+
+    std::fs::write(&path, "[US]\nCA = \"America/Phoenix\"\n").unwrap();
+    let output = ipcap_with_overrides("108.95.4.105");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("America/Phoenix"));
+    assert!(!stdout.contains("America/Los_Angeles"));
+    let target = "2a08:1450:300f:900::1003";
+    assert_eq!(
+        ipcap_with_overrides(target).stdout,
+        ipcap(&["-t", target]).stdout
+    );
+
+    std::fs::write(
+        &path,
+        "US.CA = \"America/Phoenix\"\nGB.default = \"London\"\n",
+    )
+    .unwrap();
+    let output = ipcap_with_overrides("108.95.4.105");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("IPCAP_TZ_OVERRIDES: Invalid override on line 2: invalid time zone \"London\""));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_audit() {
+    let path = std::env::temp_dir().join(format!("ipcap-pairs-{}.csv", std::process::id()));
+    std::fs::write(
+        &path,
+        "8.8.8.8,2001:4860:4860::8888\n\n10.0.0.1, 2001:4860:4860::8888\n",
+    )
+    .unwrap();
+    let pairs = path.to_str().unwrap();
+
+    let output = ipcap(&["audit", "--pairs", pairs]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("10.0.0.1 2001:4860:4860::8888 v4_not_found\n"));
+    assert!(stdout.contains("pairs: 2\n"));
+    assert!(stdout.contains("same country: 100.0%\n"));
+
+    let output = ipcap(&["audit", "--pairs", pairs, "--format", "json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.starts_with("{\"total\": 2, \"comparable\": 1, \"country_agreement\": 100"));
+
+    std::fs::write(
+        &path,
+        "8.8.8.8,2001:4860:4860::8888\n2001:4860:4860::8888,8.8.8.8\n",
+    )
+    .unwrap();
+    let output = ipcap(&["audit", "--pairs", pairs]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2: expected an IPv4"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_db_stats() {
+    let output = ipcap(&["db-stats", "--sample", "500"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\nsampled: 500 of "));
+    assert!(stdout.contains("\ncontinents:\n  AF "));
+
+    let output = ipcap(&["db-stats", "--full", "--format", "json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["sampled"], serde_json::Value::Null);
+    assert!(json["networks"].as_u64().unwrap() > json["records"].as_u64().unwrap());
+    let continents: u64 = json["continents"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|count| count.as_u64().unwrap())
+        .sum();
+    assert!(continents <= json["records"].as_u64().unwrap());
+
+    let output = ipcap(&["db-stats", "--full", "--sample", "10"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_export_data() {
+    let output = ipcap(&["export-data", "countries"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.starts_with("index,alpha2,alpha3,name,continent\n"));
+    assert!(stdout.contains("\n225,US,USA,United States,NA\n"));
+    assert_eq!(stdout.lines().count(), 255);
+    assert_eq!(output.stdout, ipcap(&["export-data", "countries"]).stdout);
+
+    let output = ipcap(&["export-data", "time-zones", "--format", "json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json.as_array().unwrap().contains(
+        &serde_json::json!({"country": "AD", "region": "default", "zone": "Europe/Andorra"})
+    ));
+
+    let output = ipcap(&["export-data", "currencies"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid table 'currencies'"));
+}
+
+#[test]
+fn test_range() {
+    let output = ipcap(&["range", "108.95.4.0-108.95.5.10"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.0-108.95.5.10\tUS\tCA\tSan Diego\n"
+    );
+
+    let output = ipcap(&["range", "10.0.0.1-10.0.0.0"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the start is after the end"));
+}
+
+#[test]
+fn test_post_processor() {
+    let output = ipcap(&["-t", "108.95.4.105", "--post-processor", "is-eu"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is_eu\": \u{1b}[1;37m\"false\""));
+
+    let output = ipcap(&[
+        "range",
+        "108.95.4.0-108.95.4.9",
+        "--append",
+        "country_code,is_eu",
+    ]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.0-108.95.4.9\tUS\t\n"
+    );
+    let output = ipcap(&[
+        "range",
+        "108.95.4.0-108.95.4.9",
+        "--append",
+        "country_code,is_eu",
+        "--post-processor",
+        "is-eu",
+    ]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.0-108.95.4.9\tUS\tfalse\n"
+    );
+
+    let batch = |args: &[&str]| {
+        let args = [
+            &["--post-processor", "is-eu", "batch", "--unique"][..],
+            args,
+        ]
+        .concat();
+        let output = ipcap_with_stdin(&args, ACCESS_LOG);
+        assert!(output.status.success(), "{:?}", args);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert_eq!(
+        batch(&["--append", "country_code,is_eu", "--delimiter", ","]),
+        "108.95.4.105,US,false\n10.0.0.1,,\n"
+    );
+    assert!(!batch(&["--format", "json"]).contains("is_eu"));
+    assert!(batch(&["--format", "json", "--schema-mode", "permissive"])
+        .contains(r#""country_code": "US", "is_eu": "false"}"#));
+
+    let output = ipcap(&["-t", "108.95.4.105", "--post-processor", "is-us"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("expected one of is-eu, modern-country")
+    );
+}
+
+#[test]
+fn test_locale() {
+    let output = ipcap(&["-t", "108.95.4.105", "--locale", "de"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"Vereinigte Staaten\""));
+    assert!(stdout.contains("\"Nordamerika\""));
+    // The codes are not translated
+    assert!(stdout.contains("\"US\""));
+    assert!(stdout.contains("\"USA\""));
+    assert!(stdout.contains("\"CA\""));
+
+    let output = ipcap_with_stdin(
+        &[
+            "--locale",
+            "fr",
+            "batch",
+            "--append",
+            "country_code,country_name,continent",
+            "--format",
+            "json",
+        ],
+        "108.95.4.105\n",
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["country_code"], "US");
+    assert_eq!(json["country_name"], "États-Unis");
+    assert_eq!(json["continent"], "Amérique du Nord");
+
+    let output = ipcap_with_stdin(
+        &["annotate", "--append", "country_name", "--locale", "es"],
+        "108.95.4.105 GET /\n",
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "108.95.4.105 GET /\tEstados Unidos\n"
+    );
+
+    let output = ipcap(&["-t", "108.95.4.105", "--locale", "it"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid locale 'it'"));
+}
+
+#[test]
+fn test_locale_from_config() {
+    let path = std::env::temp_dir().join(format!("ipcap-config-{}", std::process::id()));
+    let ipcap = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_ipcap"))
+            .args(args)
+            .env_remove("IPCAP_FILE_PATH")
+            .env("IPCAP_CONFIG", &path)
+            .output()
+            .unwrap()
+    };
+
+    // A missing config file gives the English names
+    let output = ipcap(&["-t", "108.95.4.105"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"United States\""));
+
+    std::fs::write(&path, "# Defaults of ipcap\nlocale = es\n").unwrap();
+    let output = ipcap(&["-t", "108.95.4.105"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"Estados Unidos\""));
+
+    // The command line takes precedence
+    let output = ipcap(&["-t", "108.95.4.105", "--locale", "de"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"Vereinigte Staaten\""));
+
+    std::fs::write(&path, "locale = es\ncolor = always\n").unwrap();
+    let output = ipcap(&["-t", "108.95.4.105"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2: unknown key 'color'"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "snapshot")]
+#[test]
+fn test_snapshot() {
+    let path = std::env::temp_dir().join(format!("ipcap-{}.snap", std::process::id()));
+    let snapshot = path.to_str().unwrap();
+
+    let output = ipcap(&["snapshot", "build", "-o", snapshot]);
+    assert!(output.status.success());
+
+    let output = ipcap(&["-t", "108.95.4.105", "--snapshot", snapshot]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, ipcap(&["-t", "108.95.4.105"]).stdout);
+
+    let output = ipcap(&["range", "108.95.4.0-108.95.5.10", "--snapshot", snapshot]);
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        ipcap(&["range", "108.95.4.0-108.95.5.10"]).stdout
+    );
+
+    // The snapshot of the v4 database does not answer IPv6 lookups
+    let output = ipcap(&["-t", "2001:4860:4860::8888", "--snapshot", snapshot]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Address of another family"));
+
+    let output = ipcap_with_stdin(&["batch", "--snapshot", snapshot], ACCESS_LOG);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--snapshot only applies"));
+
+    std::fs::write(&path, "not a snapshot").unwrap();
+    let output = ipcap(&["-t", "108.95.4.105", "--snapshot", snapshot]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(snapshot));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_coordinate_policy() {
+    let output = ipcap(&[
+        "-v",
+        "-t",
+        "108.95.4.105",
+        "--coordinate-policy",
+        "country-centroid",
+    ]);
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("coordinate policy: country-centroid\n")
+    );
+    // The record has real coordinates, which the policy keeps
+    assert_eq!(output.stdout, ipcap(&["-t", "108.95.4.105"]).stdout);
+
+    let output = ipcap(&["-t", "108.95.4.105", "--coordinate-policy", "centroid"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unsupported coordinate policy"));
+}
+
+#[test]
+fn test_expect() {
+    // Each expectation applies to the target at the same position
+    let output = ipcap(&[
+        "-t",
+        "108.95.4.105",
+        "-t",
+        "2a08:1450:300f:900::1003",
+        "--expect",
+        "country_code=US",
+        "--expect",
+        "country_name~=Kingdom",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "PASS 108.95.4.105 country_code=US (actual: US)\n\
+         PASS 2a08:1450:300f:900::1003 country_name~=Kingdom (actual: United Kingdom)\n"
+    );
+
+    // A failing expectation fails the command, the other targets being checked nonetheless
+    let output = ipcap(&[
+        "-t",
+        "108.95.4.105",
+        "-t",
+        "2a08:1450:300f:900::1003",
+        "-t",
+        "10.0.0.1",
+        "--expect",
+        "city~=Diego",
+        "--expect",
+        "country_code=US",
+        "--expect",
+        "country_code=US",
+        "--format",
+        "json",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 of 3 expectations failed"));
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["passed"], true);
+    assert_eq!(results[0]["actual"], "San Diego");
+    assert_eq!(results[1]["target"], "2a08:1450:300f:900::1003");
+    assert_eq!(results[1]["passed"], false);
+    assert_eq!(results[1]["expected"], "country_code=US");
+    assert_eq!(results[1]["actual"], "GB");
+    assert_eq!(results[1]["record"]["time_zone"], "Europe/London");
+    assert_eq!(results[2]["passed"], false);
+    assert_eq!(results[2]["record"], serde_json::Value::Null);
+    assert!(results[2]["error"]
+        .as_str()
+        .unwrap()
+        .contains("Address not found"));
+
+    // The fields of the post-processors can be asserted
+    let output = ipcap(&[
+        "-t",
+        "2a08:1450:300f:900::1003",
+        "--expect",
+        "is_eu=false",
+        "--post-processor",
+        "is-eu",
+    ]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_expect_usage_errors() {
+    let output = ipcap(&[
+        "-t",
+        "108.95.4.105",
+        "--expect",
+        "country_code=US",
+        "--expect",
+        "city=X",
+    ]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("expected one --expect per --target, each applying to the --target at the same position, got 1 --target and 2 --expect"));
+
+    let output = ipcap(&["-t", "108.95.4.105", "--expect", "contry_code=US"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown field 'contry_code'"));
+
+    let output = ipcap(&["-t", "108.95.4.105", "--expect", "country_code"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("expected field=value or field~=substring"));
+
+    let output = ipcap(&["-t", "108.95.4.105", "--format", "json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--expect"));
+}
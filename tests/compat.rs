@@ -0,0 +1,78 @@
+//! Every deprecated entry point of `ipcap::compat` keeps working until 0.3.
+#![allow(deprecated)]
+
+use ipcap::compat;
+use ipcap::geo_ip_reader::GeoIpReader;
+use ipcap::utils;
+use std::fs::File;
+
+#[test]
+fn test_get_record() {
+    let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+    let record = compat::get_record(&mut geo_ip, "108.95.4.105");
+
+    assert_eq!(record, geo_ip.get_record("108.95.4.105").unwrap());
+}
+
+#[test]
+#[should_panic(expected = "use `GeoIpReader::get_record`")]
+fn test_get_record_not_found() {
+    let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+    compat::get_record(&mut geo_ip, "10.0.0.1");
+}
+
+#[test]
+#[should_panic(expected = "Invalid IP address \"banana\"")]
+fn test_get_record_invalid() {
+    let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+    compat::get_record(&mut geo_ip, "banana");
+}
+
+#[test]
+fn test_get_time_zone_given_ip_addr() {
+    let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+    assert_eq!(
+        compat::get_time_zone_given_ip_addr(&mut geo_ip, "108.95.4.105"),
+        "America/Los_Angeles"
+    );
+}
+
+#[test]
+#[should_panic(expected = "use `GeoIpReader::get_time_zone_given_ip_addr`")]
+fn test_get_time_zone_given_ip_addr_not_found() {
+    let mut geo_ip = GeoIpReader::<File>::new("v4").unwrap();
+
+    compat::get_time_zone_given_ip_addr(&mut geo_ip, "10.0.0.1");
+}
+
+#[test]
+fn test_ip_to_number() {
+    assert_eq!(compat::ip_to_number("1.32.0.0"), 18874368);
+    assert_eq!(utils::ip_to_number("1.32.0.0"), 18874368);
+    assert_eq!(
+        utils::ip_to_number("2001:0db8:85a3:0000:0000:8a2e:0370:7334"),
+        utils::try_ip_to_number("2001:0db8:85a3:0000:0000:8a2e:0370:7334").unwrap()
+    );
+}
+
+#[test]
+#[should_panic(expected = "use `utils::try_ip_to_number`")]
+fn test_ip_to_number_invalid() {
+    utils::ip_to_number("banana");
+}
+
+#[test]
+fn test_migration_names_every_entry_point() {
+    for name in [
+        "compat::get_record",
+        "compat::get_time_zone_given_ip_addr",
+        "utils::ip_to_number",
+        "utils::try_ip_to_number",
+    ] {
+        assert!(compat::MIGRATION.contains(name), "{}", name);
+    }
+}
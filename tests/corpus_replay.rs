@@ -0,0 +1,56 @@
+//! Replays every case of `tests/corpus`, the databases and inputs that once crashed the lookup
+//! path or exercise its bounds checks, so that a panic reintroduced in the parsing fails
+//! `cargo test` rather than only the fuzz targets.
+//!
+//! New crashers are added with `ipcap::test_support::minimize_and_save`.
+
+use ipcap::test_support::{minimize_and_save, CorpusCase};
+use std::fs;
+use std::path::Path;
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+
+#[test]
+fn test_replay_corpus() {
+    let mut paths: Vec<_> = fs::read_dir(CORPUS)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    assert!(paths.len() >= 10, "the corpus has {} cases", paths.len());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            let case = CorpusCase::decode(&fs::read(path).unwrap())
+                .unwrap_or_else(|| panic!("{} is not a corpus case", path.display()));
+            case.replay()
+                .err()
+                .map(|failure| format!("{}: {}", path.display(), failure))
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_minimize_and_save() {
+    let dir = std::env::temp_dir().join(format!("ipcap-corpus-replay-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    for path in fs::read_dir(CORPUS).unwrap().take(3) {
+        let case = CorpusCase::decode(&fs::read(path.unwrap().path()).unwrap()).unwrap();
+        let saved = minimize_and_save(&case, &dir).unwrap();
+        assert_eq!(saved.parent(), Some(dir.as_path()));
+        assert_eq!(
+            CorpusCase::decode(&fs::read(&saved).unwrap()),
+            Some(case.clone())
+        );
+        // Saving the same case again leaves a single file
+        assert_eq!(minimize_and_save(&case, &dir).unwrap(), saved);
+    }
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(!Path::new(&dir).exists());
+}